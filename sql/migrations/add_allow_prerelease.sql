@@ -0,0 +1,8 @@
+-- Migration: Pre-release opt-in for version resolution
+-- Population resolves a configuration's version_spec ('latest', an exact version such as
+-- '0.12.0-rc.1', or a requirement such as '^0.12') against the crate's versions on
+-- crates.io, skipping yanked releases, and crawls the resolved version's docs. Pre-releases
+-- only resolve for configurations with allow_prerelease, or when pinned exactly. The
+-- resolved version is recorded in current_version.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS allow_prerelease BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,6 @@
+-- Migration: Establish expected_docs from the first population
+-- When auto_expected_docs is set, the first successful population replaces the placeholder
+-- expected_docs with the number of documents actually stored, so coverage checks compare
+-- later populations against a real baseline.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS auto_expected_docs BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,19 @@
+-- Migration: Record docs_backup runs
+-- One row per run of the docs_backup binary. The HTTP server reports the last completed
+-- run in get_server_stats when BACKUP_INTERVAL_HOURS says backups are scheduled.
+
+CREATE TABLE IF NOT EXISTS backup_runs (
+    id SERIAL PRIMARY KEY,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMPTZ,
+    status TEXT NOT NULL CHECK (status IN ('running', 'completed', 'failed')),
+    -- File path or object storage URL the archive was written to
+    location TEXT NOT NULL,
+    crates INTEGER,
+    documents BIGINT,
+    bytes BIGINT,
+    error_message TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_backup_runs_completed ON backup_runs (completed_at DESC)
+    WHERE status = 'completed';
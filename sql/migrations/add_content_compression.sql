@@ -0,0 +1,18 @@
+-- Migration: Optional zstd compression of stored content
+-- With MCPDOCS_COMPRESS_CONTENT=true, new rows store an empty `content` and the
+-- zstd-compressed text in `content_compressed`. Existing rows are left as-is.
+-- Requires add_content_tsv.sql: inserts now set content_tsv directly.
+
+ALTER TABLE doc_embeddings ADD COLUMN IF NOT EXISTS content_compressed BYTEA;
+ALTER TABLE doc_embeddings ADD COLUMN IF NOT EXISTS content_size INTEGER; -- Uncompressed length in bytes
+
+-- Compressed rows have an empty content column; their tsvector is set by the insert
+CREATE OR REPLACE FUNCTION update_content_tsv_column()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.content_compressed IS NULL THEN
+        NEW.content_tsv = to_tsvector('english', NEW.content);
+    END IF;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
@@ -0,0 +1,5 @@
+-- Migration: Add per-crate content selector override
+-- Crates with custom rustdoc themes can specify the CSS selector used to extract content.
+-- NULL means the default rustdoc docblock selector is used.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS content_selector TEXT;
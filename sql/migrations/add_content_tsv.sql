@@ -0,0 +1,22 @@
+-- Migration: Add full-text search column for hybrid/text search
+-- New and updated rows get their tsvector from a trigger; rows that existed before this
+-- migration stay NULL until backfilled (populate_db --reindex-text).
+
+ALTER TABLE doc_embeddings ADD COLUMN IF NOT EXISTS content_tsv tsvector;
+
+CREATE INDEX IF NOT EXISTS idx_doc_embeddings_content_tsv
+ON doc_embeddings
+USING gin (content_tsv);
+
+-- Keep content_tsv in sync with content
+CREATE OR REPLACE FUNCTION update_content_tsv_column()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.content_tsv = to_tsvector('english', NEW.content);
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+DROP TRIGGER IF EXISTS update_doc_embeddings_content_tsv ON doc_embeddings;
+CREATE TRIGGER update_doc_embeddings_content_tsv BEFORE INSERT OR UPDATE OF content
+    ON doc_embeddings FOR EACH ROW EXECUTE FUNCTION update_content_tsv_column();
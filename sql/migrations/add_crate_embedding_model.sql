@@ -0,0 +1,6 @@
+-- Migration: Record the embedding model behind each crate's vectors
+-- Query embeddings only match stored vectors from the same model, so a fallback embedding
+-- provider may serve a crate only when its model equals the one recorded here. NULL for
+-- crates populated before this migration; those are queried with the primary provider only.
+
+ALTER TABLE crates ADD COLUMN IF NOT EXISTS embedding_model TEXT;
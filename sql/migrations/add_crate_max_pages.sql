@@ -0,0 +1,5 @@
+-- Migration: Per-crate crawl page limit
+-- max_pages caps the docs.rs pages crawled for one configuration, e.g. the per-crate
+-- `max_pages` of a migrated proxy-config.json. NULL keeps the populating tool's default.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS max_pages INTEGER;
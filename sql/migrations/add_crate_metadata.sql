@@ -0,0 +1,14 @@
+-- Migration: Cached crates.io package facts for the crate_info tool
+-- One row per crate, refreshed from the crates.io API when older than a day. Rows are kept
+-- when crates.io is unreachable so crate_info can still answer with a staleness note.
+
+CREATE TABLE IF NOT EXISTS crate_metadata (
+    crate_name TEXT PRIMARY KEY,
+    description TEXT,
+    repository TEXT,
+    homepage TEXT,
+    license TEXT,
+    latest_version TEXT,
+    downloads BIGINT NOT NULL DEFAULT 0,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,46 @@
+-- Migration: Per-target documentation
+-- A crate config may pick a docs.rs build target (e.g. x86_64-pc-windows-msvc); NULL means
+-- docs.rs's default target. Several targets of one crate are separate config rows, so the
+-- (name, version_spec) uniqueness becomes (name, version_spec, target).
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS target TEXT;
+
+ALTER TABLE crate_configs DROP CONSTRAINT IF EXISTS crate_configs_name_version_spec_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_crate_configs_name_version_target
+ON crate_configs (name, version_spec, (COALESCE(target, '')));
+
+-- Target of the most recent population, next to its version
+ALTER TABLE crates ADD COLUMN IF NOT EXISTS target TEXT;
+
+-- Each target of a crate has its own row, documents and generations, so the name alone
+-- no longer identifies a row
+ALTER TABLE crates DROP CONSTRAINT IF EXISTS crates_name_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_crates_name_target
+ON crates (name, (COALESCE(target, '')));
+
+-- Targets share paths (the README fallback, re-exported pages) and generation numbers, so
+-- a document is unique per crate row rather than per crate name. crate_name stays in the
+-- key for partitioned tables, which need the partition key in every unique constraint.
+DO $$
+DECLARE
+    unique_path record;
+BEGIN
+    FOR unique_path IN
+        SELECT conname FROM pg_constraint
+        WHERE conrelid = 'doc_embeddings'::regclass
+          AND contype = 'u'
+          AND pg_get_constraintdef(oid) = 'UNIQUE (crate_name, doc_path, generation)'
+    LOOP
+        EXECUTE format('ALTER TABLE doc_embeddings DROP CONSTRAINT %I', unique_path.conname);
+    END LOOP;
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_constraint
+        WHERE conrelid = 'doc_embeddings'::regclass
+          AND contype = 'u'
+          AND pg_get_constraintdef(oid) = 'UNIQUE (crate_name, crate_id, doc_path, generation)'
+    ) THEN
+        ALTER TABLE doc_embeddings
+            ADD CONSTRAINT doc_embeddings_crate_name_crate_id_doc_path_generation_key
+            UNIQUE (crate_name, crate_id, doc_path, generation);
+    END IF;
+END $$;
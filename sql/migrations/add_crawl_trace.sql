@@ -0,0 +1,7 @@
+-- Migration: Crawl traces of population jobs
+-- Every URL a docs.rs crawl considered, with what became of it (fetched, empty,
+-- skipped_filter, skipped_robots, not_found, error), in crawl order. Read with the
+-- crawl_trace tool to see why a crate came out with fewer documents than expected.
+-- At most 10,000 URLs are kept per job; `dropped` counts the rest.
+
+ALTER TABLE population_jobs ADD COLUMN IF NOT EXISTS crawl_trace JSONB;
@@ -0,0 +1,12 @@
+-- Migration: Per-crate document language filtering
+-- A configuration listing `languages` (ISO 639-3 codes, e.g. {eng}) has the language of
+-- each document detected at population time. With language_action 'drop' (or NULL),
+-- documents in other languages are not embedded; with 'tag' they are stored with their
+-- language in doc_embeddings.language and query_rust_docs leaves them out.
+-- An empty list (the default) keeps every document and detects nothing.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS languages TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS language_action TEXT;
+
+-- NULL = not detected (crate without `languages`, or not reliably detectable)
+ALTER TABLE doc_embeddings ADD COLUMN IF NOT EXISTS language TEXT;
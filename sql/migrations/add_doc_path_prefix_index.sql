@@ -0,0 +1,5 @@
+-- Migration: Index for doc_path prefix lookups (related item suggestions)
+-- text_pattern_ops lets `doc_path LIKE 'prefix%'` use the index regardless of collation.
+
+CREATE INDEX IF NOT EXISTS idx_doc_embeddings_crate_doc_path_prefix
+ON doc_embeddings (crate_name, doc_path text_pattern_ops);
@@ -0,0 +1,15 @@
+-- Migration: Docblock section metadata
+-- Configurations with section_metadata tag each chunk that starts at a heading inside a
+-- docblock with that heading (Examples, Panics, Safety, Errors, ...) in
+-- doc_embeddings.section, so query_rust_docs can search e.g. only the Panics sections.
+-- Extraction already splits docblocks at their headings; the flag only adds the tags.
+-- Off by default; the crate needs repopulating after turning it on.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS section_metadata BOOLEAN NOT NULL DEFAULT false;
+
+-- NULL = not tagged (crate without section_metadata, or content outside a docblock section)
+ALTER TABLE doc_embeddings ADD COLUMN IF NOT EXISTS section TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_doc_embeddings_crate_section
+ON doc_embeddings (crate_name, lower(section))
+WHERE section IS NOT NULL;
@@ -0,0 +1,15 @@
+-- Migration: Per-document freshness timestamps
+-- created_at is reset by every upsert, so it can't tell when a document first appeared.
+-- first_seen_at is set once on insert; last_updated_at moves on every re-index.
+
+ALTER TABLE doc_embeddings ADD COLUMN IF NOT EXISTS first_seen_at TIMESTAMPTZ;
+ALTER TABLE doc_embeddings ADD COLUMN IF NOT EXISTS last_updated_at TIMESTAMPTZ;
+
+-- Existing rows: the last upsert time is the best available estimate for both
+UPDATE doc_embeddings
+SET first_seen_at = COALESCE(first_seen_at, created_at),
+    last_updated_at = COALESCE(last_updated_at, created_at)
+WHERE first_seen_at IS NULL OR last_updated_at IS NULL;
+
+ALTER TABLE doc_embeddings ALTER COLUMN first_seen_at SET DEFAULT CURRENT_TIMESTAMP;
+ALTER TABLE doc_embeddings ALTER COLUMN last_updated_at SET DEFAULT CURRENT_TIMESTAMP;
@@ -0,0 +1,34 @@
+-- Migration: Staged repopulation
+-- A repopulation of a crate that already has documents writes into a new generation
+-- while queries keep reading crates.live_generation. Once the population completes, a
+-- single UPDATE of the crate's live_generation swaps the new documents in, and the old
+-- generation is deleted afterwards. A failed population leaves the live generation
+-- untouched; its staged rows are discarded by the next population.
+
+ALTER TABLE crates ADD COLUMN IF NOT EXISTS live_generation INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE doc_embeddings ADD COLUMN IF NOT EXISTS generation INTEGER NOT NULL DEFAULT 0;
+
+-- A staged document shares its path with the live one, so paths are unique per generation
+DO $$
+DECLARE
+    unique_path record;
+BEGIN
+    FOR unique_path IN
+        SELECT conname FROM pg_constraint
+        WHERE conrelid = 'doc_embeddings'::regclass
+          AND contype = 'u'
+          AND pg_get_constraintdef(oid) = 'UNIQUE (crate_name, doc_path)'
+    LOOP
+        EXECUTE format('ALTER TABLE doc_embeddings DROP CONSTRAINT %I', unique_path.conname);
+    END LOOP;
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_constraint
+        WHERE conrelid = 'doc_embeddings'::regclass
+          AND contype = 'u'
+          AND pg_get_constraintdef(oid) = 'UNIQUE (crate_name, doc_path, generation)'
+    ) THEN
+        ALTER TABLE doc_embeddings
+            ADD CONSTRAINT doc_embeddings_crate_name_doc_path_generation_key
+            UNIQUE (crate_name, doc_path, generation);
+    END IF;
+END $$;
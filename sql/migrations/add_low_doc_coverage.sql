@@ -0,0 +1,7 @@
+-- Migration: Low documentation coverage flag
+-- Macro-only and proc-macro crates often have no docblocks for the content selector to
+-- match. When a crawl extracts almost nothing, population falls back to the crate README
+-- and the root page's full text and sets low_doc_coverage, so status reports and query
+-- responses can say the answers come from the README rather than API documentation.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS low_doc_coverage BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,7 @@
+-- Migration: Per-crate embedding limit
+-- max_embeddings caps the chunks one configuration stores; population stops embedding
+-- once it is reached and sets truncated, so status reports can tell a partial index by
+-- design from a broken one. NULL leaves the crate unlimited.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS max_embeddings INTEGER;
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS truncated BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,12 @@
+-- Migration: Time-boxed populations
+-- max_duration_secs caps how long one population of a configuration runs. When the
+-- deadline hits, what was embedded so far stays stored, the job is marked 'partial' and
+-- partial_at is set, so the next population resumes after the stored chunks instead of
+-- starting over. A completed population clears partial_at. NULL durations are unlimited.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS max_duration_secs INTEGER;
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS partial_at TIMESTAMPTZ;
+
+ALTER TABLE population_jobs DROP CONSTRAINT IF EXISTS population_jobs_status_check;
+ALTER TABLE population_jobs ADD CONSTRAINT population_jobs_status_check
+    CHECK (status IN ('pending', 'running', 'completed', 'partial', 'failed'));
@@ -0,0 +1,5 @@
+-- Migration: Store crawl diagnostics on population jobs
+-- JSON rendering of the loader's CrawlDiagnostics (pages fetched/skipped/failed, 429s, bytes, ...)
+-- so a job that yielded few documents can be explained after the fact.
+
+ALTER TABLE population_jobs ADD COLUMN IF NOT EXISTS diagnostics JSONB;
@@ -0,0 +1,6 @@
+-- Migration: Record the retrievability self-test on population jobs
+-- JSON rendering of pipeline::SelfTest: at the end of each population one stored
+-- document's own vector is searched under the crate's configured name, and the job fails
+-- when nothing comes back (e.g. documents stored under a hyphen/underscore variant).
+
+ALTER TABLE population_jobs ADD COLUMN IF NOT EXISTS self_test JSONB;
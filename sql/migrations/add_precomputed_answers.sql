@@ -0,0 +1,20 @@
+-- Migration: Precomputed answers to orientation questions
+-- When a population completes, a handful of built-in orientation questions ("how do I
+-- get started", "what are the main types", ...) are embedded and searched, and the hits
+-- stored here. query_rust_docs serves a question whose embedding is close enough to one
+-- of these from the stored hits instead of searching. Rows are deleted whenever the
+-- crate's chunks change and rewritten when the next population completes.
+
+CREATE TABLE IF NOT EXISTS precomputed_answers (
+    crate_name TEXT NOT NULL,
+    -- Which built-in question, e.g. 'getting_started'
+    question_key TEXT NOT NULL,
+    question TEXT NOT NULL,
+    -- Model that embedded the question; queries embedded with another never match
+    model TEXT NOT NULL,
+    question_embedding vector NOT NULL,
+    -- [[doc_path, distance], ...] in rank order
+    results JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (crate_name, question_key)
+);
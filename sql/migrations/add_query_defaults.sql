@@ -0,0 +1,9 @@
+-- Migration: Per-crate retrieval hints and default search parameters
+-- query_hint is prepended to questions before embedding (e.g. "async Rust SQL toolkit" for
+-- sqlx) to steer them away from generic content. The default_* columns apply to
+-- query_rust_docs whenever the caller leaves the matching argument unset.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS query_hint TEXT;
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS default_limit INTEGER;
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS default_min_similarity REAL;
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS default_path_prefix TEXT;
@@ -0,0 +1,29 @@
+-- Migration: Query log
+-- One row per query_rust_docs call when QUERY_LOG_POLICY is set. What is kept of the
+-- question depends on the policy the row was written under (see `policy`):
+--   store_full   the question as asked, in `question`
+--   store_hashed HMAC-SHA256 of the normalized question (keyed with QUERY_LOG_HMAC_KEY)
+--                in `question_hash`, so repeats can be counted but not read back
+--   store_none   neither; only the crate, result summary and timing
+-- Both question columns are nullable, so changing the policy needs no schema change.
+-- Rows older than QUERY_LOG_RETENTION_DAYS are deleted by the HTTP server every hour.
+
+CREATE TABLE IF NOT EXISTS query_log (
+    id BIGSERIAL PRIMARY KEY,
+    -- The "Query ID" printed under the answer, as used by submit_feedback
+    query_id TEXT NOT NULL,
+    crate_name TEXT NOT NULL,
+    policy TEXT NOT NULL,
+    question TEXT,
+    question_hash TEXT,
+    -- Results shown; NULL when the answer came from the query cache or a repeat
+    result_count INTEGER,
+    top_similarity REAL,
+    cached BOOLEAN NOT NULL DEFAULT FALSE,
+    latency_ms BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_query_log_created_at ON query_log (created_at);
+CREATE INDEX IF NOT EXISTS idx_query_log_question_hash ON query_log (question_hash)
+    WHERE question_hash IS NOT NULL;
@@ -0,0 +1,17 @@
+-- Migration: Optional raw HTML storage for re-extraction without re-crawling
+-- Configurations with store_raw_html keep each crawled page's HTML, zstd-compressed, in
+-- doc_pages so `db_maintenance reextract-crate` can re-run content extraction (e.g. after
+-- selector changes) and re-embed without fetching docs.rs again. Pages are stored once
+-- per page rather than per document: one page yields several section documents.
+-- Off by default since it roughly doubles a crate's storage.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS store_raw_html BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE IF NOT EXISTS doc_pages (
+    crate_name TEXT NOT NULL,
+    page_path TEXT NOT NULL, -- Relative to the docs origin, like doc_embeddings.doc_path without the #anchor
+    raw_html BYTEA NOT NULL, -- zstd-compressed
+    html_size INTEGER NOT NULL, -- Uncompressed length in bytes
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (crate_name, page_path)
+);
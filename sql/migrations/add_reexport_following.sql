@@ -0,0 +1,9 @@
+-- Migration: Add opt-in re-export following for facade crates
+-- When enabled, the crawler follows docs.rs links from the crate's own pages into
+-- the crates it re-exports, bounded by a total and a per-crate page budget.
+-- Fetched pages are stored under the facade crate name with their original doc paths.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS follow_reexports BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS reexport_crates TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS reexport_page_budget INTEGER;
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS reexport_pages_per_crate INTEGER;
@@ -0,0 +1,19 @@
+-- Migration: Store retrieval feedback from submit_feedback
+-- One row per (query, document): whether a result returned by query_rust_docs helped.
+-- Resubmitting for the same query and document replaces the earlier signal, so one
+-- query can't vote a document up or down more than once. The HTTP server folds these
+-- into a per-document prior that can nudge rankings (FEEDBACK_WEIGHT).
+
+CREATE TABLE IF NOT EXISTS retrieval_feedback (
+    id SERIAL PRIMARY KEY,
+    -- The "Query ID" printed under query_rust_docs results
+    query_id TEXT NOT NULL,
+    crate_name TEXT NOT NULL,
+    -- Document path without the " [chunk i/n]" suffix, as in "Source:" lines
+    doc_path TEXT NOT NULL,
+    useful BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (query_id, crate_name, doc_path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_retrieval_feedback_doc ON retrieval_feedback (crate_name, doc_path);
@@ -0,0 +1,4 @@
+-- Migration: Allow populating a crate from a published rustdoc JSON file
+-- When set, population downloads this (optionally gzipped) rustdoc JSON instead of crawling docs.rs.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS rustdoc_json_url TEXT;
@@ -0,0 +1,10 @@
+-- Migration: Trigram indexes for the find_symbol tool's substring search
+-- Optional: the HTTP server also creates these on startup when it has the rights to;
+-- without pg_trgm, find_symbol falls back to unindexed ILIKE.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_doc_embeddings_content_trgm
+    ON doc_embeddings USING gin (content gin_trgm_ops);
+
+CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_doc_embeddings_doc_path_trgm
+    ON doc_embeddings USING gin (doc_path gin_trgm_ops);
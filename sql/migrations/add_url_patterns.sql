@@ -0,0 +1,5 @@
+-- Migration: Add per-crate include/exclude URL regex filters
+-- Pages whose URL matches an exclude pattern (or misses every include pattern) are never fetched.
+
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS include_url_patterns TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE crate_configs ADD COLUMN IF NOT EXISTS exclude_url_patterns TEXT[] NOT NULL DEFAULT '{}';
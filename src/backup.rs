@@ -0,0 +1,325 @@
+//! Snapshot archives of the whole documentation database
+//!
+//! An archive is zstd-compressed JSON lines: a [`Record::Header`], then for each crate a
+//! [`Record::Crate`] (its `crates` row, configurations and crates.io metadata), its
+//! [`Record::Doc`]s and a [`Record::CrateEnd`] with the document count and content hash,
+//! and finally a [`Record::Manifest`] listing every crate's summary. Writing and reading
+//! both stream, so neither side holds a crate in memory; [`Verifier`] checks the counts
+//! and hashes as records go by, and a missing manifest means the archive was cut short.
+
+use crate::{
+    database::{CrateConfig, CrateMetadata, CrateRecord, StoredDocument},
+    error::ServerError,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read, Write},
+};
+
+/// `format` of every archive's header
+pub const FORMAT: &str = "mcpdocs-backup";
+
+/// Archive layout version; readers refuse newer ones
+///
+/// Version 2 stores a `crates` row per docs.rs target and tags documents with theirs.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Everything about a crate except its documents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateSnapshot {
+    pub name: String,
+    /// The `crates` row of every docs.rs target populated; empty for a crate that was
+    /// configured but never populated
+    #[serde(default)]
+    pub records: Vec<CrateRecord>,
+    /// The one `crates` row of a version 1 archive, see [`CrateSnapshot::records`]
+    #[serde(default, skip_serializing)]
+    pub record: Option<CrateRecord>,
+    pub configs: Vec<CrateConfig>,
+    pub metadata: Option<CrateMetadata>,
+}
+
+impl CrateSnapshot {
+    /// Every `crates` row, including the single one version 1 archives stored
+    pub fn records(&self) -> impl Iterator<Item = &CrateRecord> {
+        self.records.iter().chain(&self.record)
+    }
+}
+
+/// A crate's document count and content hash, at its end and in the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateSummary {
+    pub name: String,
+    pub docs: u64,
+    /// Hex SHA-256 over the crate's documents in archive order (see [`CrateDigest`])
+    pub content_hash: String,
+}
+
+/// One line of an archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    Header {
+        format: String,
+        version: u32,
+        created_at: DateTime<Utc>,
+    },
+    Crate(CrateSnapshot),
+    Doc(StoredDocument),
+    CrateEnd(CrateSummary),
+    Manifest {
+        crates: Vec<CrateSummary>,
+    },
+}
+
+/// Running document count and hash of one crate
+///
+/// Every field of every document goes in, length-prefixed so that moving bytes between
+/// fields changes the hash.
+#[derive(Default)]
+pub struct CrateDigest {
+    docs: u64,
+    hasher: Sha256,
+}
+
+impl CrateDigest {
+    pub fn add(&mut self, doc: &StoredDocument) {
+        self.docs += 1;
+        for field in [doc.doc_path.as_bytes(), doc.content.as_bytes()] {
+            self.hasher.update((field.len() as u64).to_le_bytes());
+            self.hasher.update(field);
+        }
+        self.hasher.update(doc.token_count.to_le_bytes());
+        let language = doc.language.as_deref().unwrap_or_default().as_bytes();
+        self.hasher.update((language.len() as u64).to_le_bytes());
+        self.hasher.update(language);
+        // Only tagged sections and targets go in, so archives written before either
+        // existed verify
+        if let Some(section) = &doc.section {
+            self.hasher.update((section.len() as u64).to_le_bytes());
+            self.hasher.update(section.as_bytes());
+        }
+        // Marked, so a target can't hash like a section
+        if let Some(target) = &doc.target {
+            self.hasher.update(b"target");
+            self.hasher.update((target.len() as u64).to_le_bytes());
+            self.hasher.update(target.as_bytes());
+        }
+        self.hasher
+            .update((doc.embedding.len() as u64).to_le_bytes());
+        for value in &doc.embedding {
+            self.hasher.update(value.to_le_bytes());
+        }
+    }
+
+    pub fn finish(self, name: &str) -> CrateSummary {
+        let content_hash = self
+            .hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        CrateSummary {
+            name: name.to_string(),
+            docs: self.docs,
+            content_hash,
+        }
+    }
+}
+
+/// Writes an archive record by record
+pub struct ArchiveWriter<W: Write> {
+    encoder: zstd::Encoder<'static, W>,
+    current: Option<(String, CrateDigest)>,
+    manifest: Vec<CrateSummary>,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    /// Start an archive compressed at zstd `level`, writing its header
+    pub fn new(writer: W, level: i32) -> Result<Self, ServerError> {
+        let mut archive = Self {
+            encoder: zstd::Encoder::new(writer, level)?,
+            current: None,
+            manifest: Vec::new(),
+        };
+        archive.write(&Record::Header {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            created_at: Utc::now(),
+        })?;
+        Ok(archive)
+    }
+
+    fn write(&mut self, record: &Record) -> Result<(), ServerError> {
+        serde_json::to_writer(&mut self.encoder, record)?;
+        self.encoder.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn begin_crate(&mut self, snapshot: CrateSnapshot) -> Result<(), ServerError> {
+        if let Some((name, _)) = &self.current {
+            return Err(ServerError::Internal(format!(
+                "Crate {name} is still open in the archive"
+            )));
+        }
+        self.current = Some((snapshot.name.clone(), CrateDigest::default()));
+        self.write(&Record::Crate(snapshot))
+    }
+
+    pub fn doc(&mut self, doc: StoredDocument) -> Result<(), ServerError> {
+        let Some((_, digest)) = &mut self.current else {
+            return Err(ServerError::Internal(
+                "Document written outside a crate".to_string(),
+            ));
+        };
+        digest.add(&doc);
+        self.write(&Record::Doc(doc))
+    }
+
+    /// Close the open crate, returning its summary
+    pub fn end_crate(&mut self) -> Result<CrateSummary, ServerError> {
+        let Some((name, digest)) = self.current.take() else {
+            return Err(ServerError::Internal("No crate is open".to_string()));
+        };
+        let summary = digest.finish(&name);
+        self.write(&Record::CrateEnd(summary.clone()))?;
+        self.manifest.push(summary.clone());
+        Ok(summary)
+    }
+
+    /// Write the manifest and flush the compressor, returning the manifest and the writer
+    pub fn finish(mut self) -> Result<(Vec<CrateSummary>, W), ServerError> {
+        if let Some((name, _)) = &self.current {
+            return Err(ServerError::Internal(format!(
+                "Crate {name} was never closed"
+            )));
+        }
+        let manifest = std::mem::take(&mut self.manifest);
+        self.write(&Record::Manifest {
+            crates: manifest.clone(),
+        })?;
+        let mut writer = self.encoder.finish()?;
+        writer.flush()?;
+        Ok((manifest, writer))
+    }
+}
+
+/// Read an archive's records in order, checking the header first
+pub fn read_archive<R: Read>(
+    reader: R,
+) -> Result<impl Iterator<Item = Result<Record, ServerError>>, ServerError> {
+    let mut lines = BufReader::new(zstd::Decoder::new(reader)?).lines();
+    let header = lines
+        .next()
+        .ok_or_else(|| ServerError::Parsing("Archive is empty".to_string()))??;
+    match serde_json::from_str(&header) {
+        Ok(Record::Header {
+            format, version, ..
+        }) if format == FORMAT && version <= FORMAT_VERSION => {}
+        Ok(Record::Header {
+            format, version, ..
+        }) => {
+            return Err(ServerError::Parsing(format!(
+                "Unsupported archive: {format} version {version} (this build reads {FORMAT} up to version {FORMAT_VERSION})"
+            )))
+        }
+        _ => {
+            return Err(ServerError::Parsing(format!(
+                "Not a {FORMAT} archive: the first record is not a header"
+            )))
+        }
+    }
+    Ok(lines
+        .filter(|line| !matches!(line, Ok(line) if line.is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?)))
+}
+
+/// Checks an archive's counts and hashes as its records are read
+///
+/// [`Verifier::check`] fails on the first crate whose documents don't match its
+/// [`Record::CrateEnd`], and on a manifest that disagrees with the crates seen;
+/// [`Verifier::finish`] fails if the manifest never came.
+#[derive(Default)]
+pub struct Verifier {
+    current: Option<(String, CrateDigest)>,
+    seen: Vec<CrateSummary>,
+    manifest_checked: bool,
+}
+
+impl Verifier {
+    pub fn check(&mut self, record: &Record) -> Result<(), ServerError> {
+        if self.manifest_checked {
+            return Err(corrupt("records follow the manifest"));
+        }
+        match record {
+            Record::Header { .. } => return Err(corrupt("a second header")),
+            Record::Crate(snapshot) => {
+                if let Some((name, _)) = &self.current {
+                    return Err(corrupt(&format!("crate {name} has no end record")));
+                }
+                self.current = Some((snapshot.name.clone(), CrateDigest::default()));
+            }
+            Record::Doc(doc) => match &mut self.current {
+                Some((_, digest)) => digest.add(doc),
+                None => return Err(corrupt("a document outside any crate")),
+            },
+            Record::CrateEnd(expected) => {
+                let Some((name, digest)) = self.current.take() else {
+                    return Err(corrupt("an end record outside any crate"));
+                };
+                let actual = digest.finish(&name);
+                if &actual != expected {
+                    return Err(corrupt(&format!(
+                        "crate {name} has {} documents hashing to {}, its end record says {} documents hashing to {}",
+                        actual.docs, actual.content_hash, expected.docs, expected.content_hash
+                    )));
+                }
+                self.seen.push(actual);
+            }
+            Record::Manifest { crates } => {
+                if let Some((name, _)) = &self.current {
+                    return Err(corrupt(&format!("crate {name} has no end record")));
+                }
+                let by_name = |list: &[CrateSummary]| -> BTreeMap<String, CrateSummary> {
+                    list.iter().map(|c| (c.name.clone(), c.clone())).collect()
+                };
+                if by_name(crates) != by_name(&self.seen) {
+                    return Err(corrupt(&format!(
+                        "the manifest lists {} crates, the archive holds {}",
+                        crates.len(),
+                        self.seen.len()
+                    )));
+                }
+                self.manifest_checked = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// The verified crate summaries, once the whole archive was read
+    pub fn finish(self) -> Result<Vec<CrateSummary>, ServerError> {
+        if !self.manifest_checked {
+            return Err(corrupt("no manifest; the archive was cut short"));
+        }
+        Ok(self.seen)
+    }
+}
+
+fn corrupt(reason: &str) -> ServerError {
+    ServerError::Parsing(format!("Corrupt backup archive: {reason}"))
+}
+
+/// What a restore does with a crate the target database already has
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConflictPolicy {
+    /// Refuse to restore anything if any crate already exists
+    #[default]
+    Fail,
+    /// Keep the existing crate and leave the archived one out
+    Skip,
+    /// Delete the existing crate's documents and restore the archived ones
+    Replace,
+}
@@ -28,13 +28,31 @@ async fn main() -> Result<(), ServerError> {
         );
 
         // Load just the first page to extract version
-        match doc_loader::load_documents_from_docs_rs(&crate_stat.name, "*", None, Some(1)).await {
+        match doc_loader::load_documents_from_docs_rs(
+            &crate_stat.name,
+            "*",
+            None,
+            Some(1),
+            &doc_loader::CrawlOptions {
+                target: crate_stat.target.clone(),
+                ..doc_loader::CrawlOptions::default()
+            },
+        )
+        .await
+        {
             Ok(load_result) => {
                 if let Some(version) = load_result.version {
                     println!("  ✅ Detected version: {version}");
 
                     // Update the crate with version
-                    match db.upsert_crate(&crate_stat.name, Some(&version)).await {
+                    match db
+                        .upsert_crate(
+                            &crate_stat.name,
+                            Some(&version),
+                            crate_stat.target.as_deref(),
+                        )
+                        .await
+                    {
                         Ok(_) => {
                             println!("  ✅ Updated database");
                             updated += 1;
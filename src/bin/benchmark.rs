@@ -0,0 +1,586 @@
+use async_openai::{config::OpenAIConfig, Client as OpenAIClient};
+use clap::{Parser, ValueEnum};
+use ndarray::Array1;
+use rustdocs_mcp_server::{
+    database::Database,
+    embeddings::{initialize_embedding_provider, EmbeddingConfig, EmbeddingProvider},
+    error::ServerError,
+    search,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Benchmark search latency and recall@k", long_about = None)]
+struct Cli {
+    /// Crate to search (golden entries may name their own crate)
+    #[arg(short, long)]
+    crate_name: Option<String>,
+
+    /// Query to run (repeatable)
+    #[arg(short, long)]
+    query: Vec<String>,
+
+    /// File with one query per line
+    #[arg(long)]
+    queries: Option<PathBuf>,
+
+    /// JSON Lines golden file: {"query": "...", "expected": ["tokio/latest/..."], "crate": "tokio"}
+    #[arg(long)]
+    golden: Option<PathBuf>,
+
+    /// Results per query when no --config is given
+    #[arg(long, default_value_t = 10)]
+    top_k: i32,
+
+    /// HNSW ef_search when no --config is given (default: server setting)
+    #[arg(long)]
+    ef_search: Option<u32>,
+
+    /// Configuration as key=value pairs: name, top_k, ef_search, provider, model, database_url.
+    /// Pass twice to compare, e.g. --config ef_search=40 --config ef_search=200
+    #[arg(long = "config")]
+    configs: Vec<String>,
+
+    /// Times each query is searched per configuration; latency percentiles cover every run
+    #[arg(long, default_value_t = 3)]
+    iterations: usize,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// Write the report here instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+/// Queries sent to the embedding provider per request
+const EMBED_BATCH_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Deserialize)]
+struct BenchQuery {
+    query: String,
+    #[serde(default)]
+    expected: Vec<String>,
+    #[serde(default, rename = "crate")]
+    crate_name: Option<String>,
+}
+
+/// One side of a comparison
+#[derive(Debug, Clone, Serialize)]
+struct BenchConfig {
+    name: String,
+    top_k: i32,
+    ef_search: Option<u32>,
+    provider: String,
+    model: String,
+    #[serde(skip)]
+    database_url: Option<String>,
+}
+
+impl BenchConfig {
+    fn parse(spec: &str, index: usize, defaults: &BenchConfig) -> Result<Self, ServerError> {
+        let mut config = BenchConfig {
+            name: format!("config{}", index + 1),
+            ..defaults.clone()
+        };
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').ok_or_else(|| {
+                ServerError::Config(format!("Invalid config entry '{pair}': expected key=value"))
+            })?;
+            let value = value.trim();
+            match key.trim() {
+                "name" => config.name = value.to_string(),
+                "top_k" => {
+                    config.top_k = value.parse().map_err(|_| {
+                        ServerError::Config(format!("Invalid top_k '{value}'"))
+                    })?
+                }
+                "ef_search" => {
+                    config.ef_search = Some(value.parse().map_err(|_| {
+                        ServerError::Config(format!("Invalid ef_search '{value}'"))
+                    })?)
+                }
+                "provider" => config.provider = value.to_lowercase(),
+                "model" => config.model = value.to_string(),
+                "database_url" => config.database_url = Some(value.to_string()),
+                other => {
+                    return Err(ServerError::Config(format!(
+                        "Unknown config key '{other}' (use name, top_k, ef_search, provider, model, database_url)"
+                    )))
+                }
+            }
+        }
+        if config.top_k <= 0 {
+            return Err(ServerError::Config(format!(
+                "top_k must be positive for {}",
+                config.name
+            )));
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct QueryResult {
+    crate_name: String,
+    query: String,
+    p50_ms: f64,
+    p95_ms: f64,
+    p99_ms: f64,
+    /// Fraction of expected paths found in the top k (None without expectations)
+    recall: Option<f64>,
+    top_path: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ConfigReport {
+    config: BenchConfig,
+    runs: usize,
+    p50_ms: f64,
+    p95_ms: f64,
+    p99_ms: f64,
+    /// Mean recall@k over queries with expected paths
+    mean_recall: Option<f64>,
+    queries: Vec<QueryResult>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ServerError> {
+    dotenvy::dotenv().ok();
+
+    let cli = Cli::parse();
+    let queries = load_queries(&cli)?;
+    if queries.is_empty() {
+        return Err(ServerError::Config(
+            "No queries given: use --query, --queries or --golden".to_string(),
+        ));
+    }
+    if cli.iterations == 0 {
+        return Err(ServerError::Config(
+            "--iterations must be at least 1".to_string(),
+        ));
+    }
+
+    let provider = env::var("EMBEDDING_PROVIDER").unwrap_or_else(|_| "openai".to_string());
+    let defaults = BenchConfig {
+        name: "default".to_string(),
+        top_k: cli.top_k,
+        ef_search: cli.ef_search,
+        model: env::var("EMBEDDING_MODEL").unwrap_or_else(|_| default_model(&provider)),
+        provider: provider.to_lowercase(),
+        database_url: None,
+    };
+    let configs = if cli.configs.is_empty() {
+        vec![defaults.clone()]
+    } else {
+        cli.configs
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| BenchConfig::parse(spec, i, &defaults))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    // Configurations sharing a provider and model reuse the same query embeddings,
+    // so only search time differs between them
+    let mut embeddings: HashMap<(String, String), Vec<Array1<f32>>> = HashMap::new();
+    let mut reports = Vec::new();
+    for config in &configs {
+        let key = (config.provider.clone(), config.model.clone());
+        if !embeddings.contains_key(&key) {
+            eprintln!(
+                "🧠 Embedding {} queries with {}/{}...",
+                queries.len(),
+                config.provider,
+                config.model
+            );
+            let provider = embedding_provider(&config.provider, &config.model)?;
+            embeddings.insert(
+                key.clone(),
+                embed_queries(provider.as_ref(), &queries).await?,
+            );
+        }
+
+        let db = match &config.database_url {
+            Some(url) => Database::connect(url).await?,
+            None => Database::new().await?,
+        };
+        eprintln!(
+            "⏱️  Running {} ({} queries x {} iterations, top_k={}, ef_search={})",
+            config.name,
+            queries.len(),
+            cli.iterations,
+            config.top_k,
+            config
+                .ef_search
+                .map_or_else(|| "default".to_string(), |ef| ef.to_string())
+        );
+        let report = run_config(
+            &db,
+            config,
+            &queries,
+            &embeddings[&key],
+            cli.crate_name.as_deref(),
+            cli.iterations,
+        )
+        .await?;
+        reports.push(report);
+    }
+
+    let rendered = match cli.format {
+        OutputFormat::Table => render_table(&reports),
+        OutputFormat::Csv => render_csv(&reports),
+        OutputFormat::Json => serde_json::to_string_pretty(&serde_json::json!({
+            "configs": reports,
+            "comparison": comparison(&reports),
+        }))
+        .map_err(|e| ServerError::Internal(format!("Failed to serialize report: {e}")))?,
+    };
+    match &cli.output {
+        Some(path) => {
+            std::fs::write(path, rendered)?;
+            eprintln!("📄 Wrote report to {}", path.display());
+        }
+        None => println!("{rendered}"),
+    }
+    Ok(())
+}
+
+fn default_model(provider: &str) -> String {
+    match provider.to_lowercase().as_str() {
+        "voyage" => "voyage-3.5".to_string(),
+        _ => "text-embedding-3-large".to_string(),
+    }
+}
+
+fn embedding_provider(
+    provider: &str,
+    model: &str,
+) -> Result<Arc<dyn EmbeddingProvider + Send + Sync>, ServerError> {
+    let config = match provider {
+        "openai" => {
+            let client = if let Ok(api_base) = env::var("OPENAI_API_BASE") {
+                OpenAIClient::with_config(OpenAIConfig::new().with_api_base(api_base))
+            } else {
+                OpenAIClient::new()
+            };
+            EmbeddingConfig::OpenAI {
+                client,
+                model: model.to_string(),
+            }
+        }
+        "voyage" => {
+            let api_key = env::var("VOYAGE_API_KEY")
+                .map_err(|_| ServerError::MissingEnvVar("VOYAGE_API_KEY".to_string()))?;
+            EmbeddingConfig::VoyageAI {
+                api_key,
+                model: model.to_string(),
+            }
+        }
+        _ => {
+            return Err(ServerError::Config(format!(
+                "Unsupported embedding provider: {provider}. Use 'openai' or 'voyage'"
+            )));
+        }
+    };
+    Ok(initialize_embedding_provider(config))
+}
+
+/// Queries from --query, --queries and --golden, in that order
+fn load_queries(cli: &Cli) -> Result<Vec<BenchQuery>, ServerError> {
+    let mut queries: Vec<BenchQuery> = cli
+        .query
+        .iter()
+        .map(|q| BenchQuery {
+            query: q.clone(),
+            expected: Vec::new(),
+            crate_name: None,
+        })
+        .collect();
+
+    if let Some(path) = &cli.queries {
+        let text = std::fs::read_to_string(path)?;
+        queries.extend(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| BenchQuery {
+                    query: line.to_string(),
+                    expected: Vec::new(),
+                    crate_name: None,
+                }),
+        );
+    }
+
+    if let Some(path) = &cli.golden {
+        let text = std::fs::read_to_string(path)?;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry: BenchQuery = serde_json::from_str(line)
+                .map_err(|e| ServerError::Config(format!("{}:{}: {e}", path.display(), i + 1)))?;
+            queries.push(entry);
+        }
+    }
+
+    for query in &queries {
+        if query.crate_name.is_none() && cli.crate_name.is_none() {
+            return Err(ServerError::Config(format!(
+                "No crate for query '{}': pass --crate-name or set \"crate\" in the golden file",
+                query.query
+            )));
+        }
+    }
+    Ok(queries)
+}
+
+async fn embed_queries(
+    provider: &(dyn EmbeddingProvider + Send + Sync),
+    queries: &[BenchQuery],
+) -> Result<Vec<Array1<f32>>, ServerError> {
+    let texts: Vec<String> = queries.iter().map(|q| q.query.clone()).collect();
+    let mut embeddings = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(EMBED_BATCH_SIZE) {
+        let (vectors, _tokens) = provider.generate_embeddings(batch).await?;
+        embeddings.extend(vectors.into_iter().map(Array1::from_vec));
+    }
+    if embeddings.len() != texts.len() {
+        return Err(ServerError::Internal(format!(
+            "Expected {} query embeddings, got {}",
+            texts.len(),
+            embeddings.len()
+        )));
+    }
+    Ok(embeddings)
+}
+
+async fn run_config(
+    db: &Database,
+    config: &BenchConfig,
+    queries: &[BenchQuery],
+    embeddings: &[Array1<f32>],
+    default_crate: Option<&str>,
+    iterations: usize,
+) -> Result<ConfigReport, ServerError> {
+    let mut all_latencies = Vec::new();
+    let mut results = Vec::new();
+
+    for (query, embedding) in queries.iter().zip(embeddings) {
+        let crate_name = query
+            .crate_name
+            .as_deref()
+            .or(default_crate)
+            .unwrap_or_default();
+        let mut latencies = Vec::with_capacity(iterations);
+        let mut paths = Vec::new();
+        for _ in 0..iterations {
+            let start = Instant::now();
+            let docs = db
+                .search_similar_docs_tuned(
+                    crate_name,
+                    embedding,
+                    config.top_k,
+                    config.ef_search,
+                    None,
+                    None,
+                )
+                .await?;
+            latencies.push(start.elapsed());
+            paths = docs.into_iter().map(|doc| doc.doc_path).collect();
+        }
+
+        let recall = recall_at_k(&query.expected, &paths);
+        latencies.sort();
+        results.push(QueryResult {
+            crate_name: crate_name.to_string(),
+            query: query.query.clone(),
+            p50_ms: percentile_ms(&latencies, 50.0),
+            p95_ms: percentile_ms(&latencies, 95.0),
+            p99_ms: percentile_ms(&latencies, 99.0),
+            recall,
+            top_path: paths.first().cloned(),
+        });
+        all_latencies.extend(latencies);
+    }
+
+    all_latencies.sort();
+    let recalls: Vec<f64> = results.iter().filter_map(|r| r.recall).collect();
+    Ok(ConfigReport {
+        config: config.clone(),
+        runs: all_latencies.len(),
+        p50_ms: percentile_ms(&all_latencies, 50.0),
+        p95_ms: percentile_ms(&all_latencies, 95.0),
+        p99_ms: percentile_ms(&all_latencies, 99.0),
+        mean_recall: (!recalls.is_empty())
+            .then(|| recalls.iter().sum::<f64>() / recalls.len() as f64),
+        queries: results,
+    })
+}
+
+/// Fraction of expected paths present in the results
+///
+/// Chunk suffixes are ignored, and an expected page also matches sections on that page.
+fn recall_at_k(expected: &[String], results: &[String]) -> Option<f64> {
+    if expected.is_empty() {
+        return None;
+    }
+    let found = expected
+        .iter()
+        .filter(|expected| {
+            results.iter().any(|path| {
+                let path = search::base_doc_path(path);
+                path == expected.as_str()
+                    || path
+                        .strip_prefix(expected.as_str())
+                        .is_some_and(|rest| rest.starts_with('#'))
+            })
+        })
+        .count();
+    Some(found as f64 / expected.len() as f64)
+}
+
+/// Nearest-rank percentile of sorted latencies, in milliseconds
+fn percentile_ms(sorted: &[Duration], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    let index = rank.clamp(1, sorted.len()) - 1;
+    (sorted[index].as_secs_f64() * 1000.0 * 100.0).round() / 100.0
+}
+
+/// Differences of every configuration against the first one
+fn comparison(reports: &[ConfigReport]) -> Vec<serde_json::Value> {
+    let Some((baseline, others)) = reports.split_first() else {
+        return Vec::new();
+    };
+    others
+        .iter()
+        .map(|report| {
+            serde_json::json!({
+                "baseline": baseline.config.name,
+                "config": report.config.name,
+                "p50_ms_delta": report.p50_ms - baseline.p50_ms,
+                "p95_ms_delta": report.p95_ms - baseline.p95_ms,
+                "p99_ms_delta": report.p99_ms - baseline.p99_ms,
+                "recall_delta": match (report.mean_recall, baseline.mean_recall) {
+                    (Some(r), Some(b)) => Some(r - b),
+                    _ => None,
+                },
+            })
+        })
+        .collect()
+}
+
+fn format_recall(recall: Option<f64>) -> String {
+    recall.map_or_else(|| "-".to_string(), |r| format!("{r:.3}"))
+}
+
+fn render_table(reports: &[ConfigReport]) -> String {
+    let mut out = format!(
+        "{:<16} {:>6} {:>10} {:>6} {:>10} {:>10} {:>10} {:>10}\n{:-<86}\n",
+        "Config", "top_k", "ef_search", "Runs", "p50 (ms)", "p95 (ms)", "p99 (ms)", "Recall", ""
+    );
+    for report in reports {
+        out.push_str(&format!(
+            "{:<16} {:>6} {:>10} {:>6} {:>10.2} {:>10.2} {:>10.2} {:>10}\n",
+            report.config.name,
+            report.config.top_k,
+            report
+                .config
+                .ef_search
+                .map_or_else(|| "default".to_string(), |ef| ef.to_string()),
+            report.runs,
+            report.p50_ms,
+            report.p95_ms,
+            report.p99_ms,
+            format_recall(report.mean_recall)
+        ));
+    }
+
+    for delta in comparison(reports) {
+        out.push_str(&format!(
+            "\n{} vs {}: p50 {:+.2} ms, p95 {:+.2} ms, p99 {:+.2} ms, recall {}",
+            delta["config"].as_str().unwrap_or_default(),
+            delta["baseline"].as_str().unwrap_or_default(),
+            delta["p50_ms_delta"].as_f64().unwrap_or_default(),
+            delta["p95_ms_delta"].as_f64().unwrap_or_default(),
+            delta["p99_ms_delta"].as_f64().unwrap_or_default(),
+            delta["recall_delta"]
+                .as_f64()
+                .map_or_else(|| "-".to_string(), |d| format!("{d:+.3}"))
+        ));
+    }
+
+    // Queries that missed expected paths in any configuration
+    for report in reports {
+        for result in report
+            .queries
+            .iter()
+            .filter(|r| r.recall.is_some_and(|r| r < 1.0))
+        {
+            out.push_str(&format!(
+                "\n⚠️  [{}] recall {} for '{}' (top: {})",
+                report.config.name,
+                format_recall(result.recall),
+                result.query,
+                result.top_path.as_deref().unwrap_or("none")
+            ));
+        }
+    }
+    out
+}
+
+fn render_csv(reports: &[ConfigReport]) -> String {
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    let mut out = String::from(
+        "config,top_k,ef_search,provider,model,crate,query,p50_ms,p95_ms,p99_ms,recall,top_path\n",
+    );
+    for report in reports {
+        let config = &report.config;
+        for result in &report.queries {
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{},{:.2},{:.2},{:.2},{},{}\n",
+                field(&config.name),
+                config.top_k,
+                config
+                    .ef_search
+                    .map(|ef| ef.to_string())
+                    .unwrap_or_default(),
+                field(&config.provider),
+                field(&config.model),
+                field(&result.crate_name),
+                field(&result.query),
+                result.p50_ms,
+                result.p95_ms,
+                result.p99_ms,
+                result.recall.map(|r| format!("{r:.3}")).unwrap_or_default(),
+                field(result.top_path.as_deref().unwrap_or_default())
+            ));
+        }
+    }
+    out
+}
@@ -0,0 +1,622 @@
+use async_openai::{config::OpenAIConfig, Client as OpenAIClient};
+use clap::{Parser, Subcommand};
+use futures::StreamExt;
+use ndarray::Array1;
+use rustdocs_mcp_server::{
+    database::{AuditSeverity, Database, UNPARTITIONED_TABLE},
+    doc_loader,
+    embeddings::{
+        self, embed_chunk, initialize_embedding_provider, truncate_for_embedding, vector_problem,
+        EmbeddingConfig, TruncationStrategy, EMBEDDING_CLIENT, MODEL_TOKEN_LIMIT,
+    },
+    error::ServerError,
+    language::LanguageFilter,
+    pipeline::{self, ChunkSink, DatabaseSink, EmbeddedChunk, PageChunk, PipelineOptions},
+};
+use std::{collections::HashSet, env};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "One-off maintenance operations on stored documentation", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Re-clean stored content with the current extractor's boilerplate rules
+    Reclean {
+        /// Only process this crate (default: all crates with embeddings)
+        #[arg(short, long)]
+        crate_name: Option<String>,
+
+        /// Regenerate embeddings for documents whose content changed
+        #[arg(long)]
+        re_embed: bool,
+
+        /// Report what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Find stored embeddings that are all zeros, non-finite or the wrong dimension
+    FindBadVectors {
+        /// Only scan this crate (default: all crates with embeddings)
+        #[arg(short, long)]
+        crate_name: Option<String>,
+
+        /// Delete the offending documents
+        #[arg(long, conflicts_with = "re_embed")]
+        delete: bool,
+
+        /// Regenerate embeddings for the offending documents from their stored content
+        #[arg(long)]
+        re_embed: bool,
+    },
+    /// Cross-check crates, doc_embeddings and crate_configs for drift
+    Audit {
+        /// Apply the fixes marked safe (e.g. recomputing crate stats)
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Re-run content extraction over a crate's stored page HTML and re-embed the result,
+    /// without fetching anything from docs.rs (needs a store_raw_html population)
+    ReextractCrate {
+        /// The crate to re-extract
+        #[arg(short, long)]
+        crate_name: String,
+
+        /// CSS selector to extract with instead of the crate config's content_selector
+        #[arg(long)]
+        content_selector: Option<String>,
+
+        /// Report how many documents the stored pages yield without embedding or writing
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Merge stored chunks below the minimum size (MIN_DOC_CHARS, MIN_DOC_TOKENS,
+    /// MIN_CHUNK_TOKENS) into a neighbouring chunk of their page, re-embedding only the
+    /// merged chunks, and delete the ones no neighbour can take
+    MergeSmallChunks {
+        /// Only process this crate (default: all crates with embeddings)
+        #[arg(short, long)]
+        crate_name: Option<String>,
+
+        /// Report what would change without embedding or writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Convert doc_embeddings into a table partitioned by crate, so deleting or
+    /// repopulating a crate drops a partition instead of deleting rows
+    PartitionEmbeddings {
+        /// Rows copied per statement while the old table keeps serving
+        #[arg(long, default_value_t = 5000)]
+        batch_rows: i64,
+    },
+}
+
+/// Documents sent to the embedding provider per request when re-embedding
+const RE_EMBED_BATCH_SIZE: usize = 16;
+
+#[tokio::main]
+async fn main() -> Result<(), ServerError> {
+    dotenvy::dotenv().ok();
+
+    let cli = Cli::parse();
+    let db = Database::new().await?;
+
+    match cli.command {
+        Command::Reclean {
+            crate_name,
+            re_embed,
+            dry_run,
+        } => reclean(&db, crate_name, re_embed, dry_run).await,
+        Command::FindBadVectors {
+            crate_name,
+            delete,
+            re_embed,
+        } => find_bad_vectors(&db, crate_name, delete, re_embed).await,
+        Command::Audit { fix } => audit(&db, fix).await,
+        Command::ReextractCrate {
+            crate_name,
+            content_selector,
+            dry_run,
+        } => reextract_crate(&db, &crate_name, content_selector, dry_run).await,
+        Command::MergeSmallChunks {
+            crate_name,
+            dry_run,
+        } => merge_small_chunks(&db, crate_name, dry_run).await,
+        Command::PartitionEmbeddings { batch_rows } => partition_embeddings(&db, batch_rows).await,
+    }
+}
+
+async fn reclean(
+    db: &Database,
+    crate_name: Option<String>,
+    re_embed: bool,
+    dry_run: bool,
+) -> Result<(), ServerError> {
+    let crates = match crate_name {
+        Some(name) => vec![name],
+        None => db.get_all_crates_with_embeddings().await?,
+    };
+
+    if re_embed && !dry_run {
+        init_embedding_provider()?;
+    }
+
+    let bpe = tiktoken_rs::cl100k_base().map_err(|e| ServerError::Tiktoken(e.to_string()))?;
+    let truncation = TruncationStrategy::from_env()?;
+
+    let mut total_changed = 0;
+    let mut total_docs = 0;
+    for crate_name in &crates {
+        println!("🧹 Re-cleaning {crate_name}...");
+
+        // Stream rows so only changed documents are held in memory
+        let mut changed = Vec::new();
+        let mut scanned = 0;
+        let mut bytes_before = 0;
+        let mut bytes_after = 0;
+        let dimension = db.crate_embedding_dimension(crate_name).await?;
+        let mut rows = Box::pin(db.get_crate_documents_stream(crate_name, dimension));
+        while let Some(row) = rows.next().await {
+            let (doc_path, content, _embedding) = row?;
+            scanned += 1;
+            let cleaned = doc_loader::clean_content(&content);
+            if cleaned != content {
+                bytes_before += content.len();
+                bytes_after += cleaned.len();
+                changed.push((doc_path, cleaned));
+            }
+        }
+        drop(rows);
+
+        println!(
+            "  {} of {scanned} documents changed ({bytes_before} -> {bytes_after} bytes)",
+            changed.len()
+        );
+        total_docs += scanned;
+        total_changed += changed.len();
+
+        if dry_run || changed.is_empty() {
+            continue;
+        }
+
+        for batch in changed.chunks(RE_EMBED_BATCH_SIZE) {
+            let embeddings = if re_embed {
+                let provider = EMBEDDING_CLIENT.get().ok_or_else(|| {
+                    ServerError::Internal("Embedding provider not initialized".to_string())
+                })?;
+                let inputs: Vec<String> = batch
+                    .iter()
+                    .map(|(doc_path, c)| {
+                        truncate_for_embedding(c, &bpe, MODEL_TOKEN_LIMIT, truncation)
+                            .inspect(|_| {
+                                println!("  ✂️  Truncated {doc_path} (strategy: {truncation})")
+                            })
+                            .unwrap_or_else(|| c.clone())
+                    })
+                    .collect();
+                let (vectors, _tokens) = provider.generate_embeddings(&inputs).await?;
+                Some(vectors.into_iter().map(Array1::from).collect::<Vec<_>>())
+            } else {
+                None
+            };
+
+            for (i, (doc_path, content)) in batch.iter().enumerate() {
+                let token_count = bpe.encode_with_special_tokens(content).len() as i32;
+                let embedding = embeddings.as_ref().and_then(|e| e.get(i));
+                db.update_document_content(crate_name, doc_path, content, token_count, embedding)
+                    .await?;
+            }
+        }
+
+        db.refresh_crate_stats(crate_name).await?;
+        println!("  ✅ Updated {crate_name}");
+    }
+
+    let action = if dry_run { "Would update" } else { "Updated" };
+    println!(
+        "\n📊 {action} {total_changed} of {total_docs} documents across {} crates",
+        crates.len()
+    );
+    Ok(())
+}
+
+async fn find_bad_vectors(
+    db: &Database,
+    crate_name: Option<String>,
+    delete: bool,
+    re_embed: bool,
+) -> Result<(), ServerError> {
+    let crates = match crate_name {
+        Some(name) => vec![name],
+        None => db.get_all_crates_with_embeddings().await?,
+    };
+
+    if re_embed {
+        init_embedding_provider()?;
+    }
+
+    let bpe = tiktoken_rs::cl100k_base().map_err(|e| ServerError::Tiktoken(e.to_string()))?;
+    let truncation = TruncationStrategy::from_env()?;
+
+    let mut total_bad = 0;
+    for crate_name in &crates {
+        println!("🔍 Scanning {crate_name}...");
+
+        let mut bad = Vec::new();
+        let dimension = db.crate_embedding_dimension(crate_name).await?;
+        let mut rows = Box::pin(db.get_crate_documents_stream(crate_name, dimension));
+        while let Some(row) = rows.next().await {
+            let (doc_path, content, embedding) = row?;
+            let vector = embedding.as_slice().unwrap_or(&[]);
+            if let Some(problem) = vector_problem(vector, Some(dimension)) {
+                println!("  ❌ {doc_path}: embedding {problem}");
+                bad.push((doc_path, content));
+            }
+        }
+        drop(rows);
+        total_bad += bad.len();
+
+        if bad.is_empty() {
+            continue;
+        }
+
+        if delete {
+            let paths: Vec<String> = bad.into_iter().map(|(doc_path, _)| doc_path).collect();
+            let deleted = db.delete_documents(crate_name, &paths).await?;
+            db.refresh_crate_stats(crate_name).await?;
+            println!("  🗑️  Deleted {deleted} documents from {crate_name}");
+        } else if re_embed {
+            let provider = EMBEDDING_CLIENT.get().ok_or_else(|| {
+                ServerError::Internal("Embedding provider not initialized".to_string())
+            })?;
+            for (doc_path, content) in &bad {
+                let embedding = embed_chunk(
+                    provider.as_ref(),
+                    &bpe,
+                    doc_path,
+                    content.clone(),
+                    truncation,
+                )
+                .await?;
+                let token_count = bpe.encode_with_special_tokens(content).len() as i32;
+                db.update_document_content(
+                    crate_name,
+                    doc_path,
+                    content,
+                    token_count,
+                    Some(&embedding),
+                )
+                .await?;
+            }
+            println!("  ✅ Re-embedded {} documents in {crate_name}", bad.len());
+        }
+    }
+
+    println!(
+        "\n📊 Found {total_bad} bad embeddings across {} crates",
+        crates.len()
+    );
+    if total_bad > 0 && !delete && !re_embed {
+        println!("Re-run with --delete or --re-embed to fix them");
+    }
+    Ok(())
+}
+
+/// Passes batches on to a [`DatabaseSink`], remembering which chunk paths were stored
+struct RecordingSink<'a> {
+    inner: DatabaseSink<'a>,
+    paths: HashSet<String>,
+}
+
+#[async_trait::async_trait]
+impl ChunkSink for RecordingSink<'_> {
+    async fn insert_batch(&mut self, batch: Vec<EmbeddedChunk>) -> Result<(), ServerError> {
+        let paths: Vec<String> = batch.iter().map(|chunk| chunk.path.clone()).collect();
+        self.inner.insert_batch(batch).await?;
+        self.paths.extend(paths);
+        Ok(())
+    }
+}
+
+async fn reextract_crate(
+    db: &Database,
+    crate_name: &str,
+    content_selector: Option<String>,
+    dry_run: bool,
+) -> Result<(), ServerError> {
+    let pages = db.count_raw_pages(crate_name).await?;
+    if pages == 0 {
+        return Err(ServerError::Config(format!(
+            "No stored HTML for {crate_name}; enable store_raw_html in its configuration and populate it once"
+        )));
+    }
+    let config = db
+        .get_crate_configs_by_name(crate_name)
+        .await?
+        .into_iter()
+        .next();
+    let content_selector =
+        content_selector.or_else(|| config.as_ref().and_then(|c| c.content_selector.clone()));
+    let source = pipeline::DocumentSource::StoredHtml {
+        db,
+        crate_name,
+        content_selector: content_selector.as_deref(),
+    };
+    println!(
+        "🔁 Re-extracting {pages} stored pages of {crate_name} (selector: {})",
+        content_selector
+            .as_deref()
+            .unwrap_or(doc_loader::DEFAULT_CONTENT_SELECTOR)
+    );
+    let stored_docs = db.count_crate_documents(crate_name).await?;
+
+    if dry_run {
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<doc_loader::Document>(64);
+        let count = async {
+            let (mut documents, mut bytes) = (0, 0);
+            while let Some(doc) = rx.recv().await {
+                documents += 1;
+                bytes += doc.content.len();
+            }
+            (documents, bytes)
+        };
+        let (summary, (documents, bytes)) = tokio::join!(source.crawl(tx), count);
+        let summary = summary?;
+        println!(
+            "  {documents} documents ({:.1} KB) from {} pages, {} without content; {stored_docs} chunks are stored now",
+            bytes as f64 / 1024.0,
+            summary.diagnostics.pages_fetched,
+            summary.diagnostics.empty_pages
+        );
+        println!("\n📊 Would re-embed {documents} documents of {crate_name}");
+        return Ok(());
+    }
+
+    init_embedding_provider()?;
+    let provider = embeddings::embedding_provider()?;
+    let target = config.as_ref().and_then(|c| c.target.clone());
+    let mut sink = RecordingSink {
+        inner: DatabaseSink::new(
+            db,
+            crate_name,
+            target.as_deref(),
+            provider.get_model_name(),
+            None,
+        ),
+        paths: HashSet::new(),
+    };
+    let mut options = pipeline::PipelineOptions::from_env()?;
+    options.languages = match &config {
+        Some(config) => LanguageFilter::for_crate(config)?,
+        None => None,
+    };
+    options.section_metadata = config.as_ref().is_some_and(|c| c.section_metadata);
+    // Rows are upserted by path, so the old documents keep serving until replaced
+    let stats = pipeline::run(|tx| source.crawl(tx), provider.clone(), &mut sink, &options).await?;
+    if stats.stored_chunks == 0 {
+        return Err(ServerError::Config(format!(
+            "Re-extraction of {crate_name} produced no documents ({}); stored documents were kept",
+            stats.crawl.diagnostics
+        )));
+    }
+
+    // Documents the new extraction no longer produces would otherwise linger
+    let stale: Vec<String> = pipeline::stored_paths(db, crate_name)
+        .await?
+        .into_iter()
+        .filter(|path| !sink.paths.contains(path))
+        .collect();
+    let deleted = db.delete_documents(crate_name, &stale).await?;
+    db.refresh_crate_stats(crate_name).await?;
+
+    println!(
+        "\n📊 Re-extracted {crate_name}: {} documents from {} pages, {} chunks embedded ({} tokens), {deleted} stale chunks removed",
+        stats.documents,
+        stats.crawl.diagnostics.pages_fetched,
+        stats.stored_chunks,
+        stats.total_tokens
+    );
+    Ok(())
+}
+
+async fn partition_embeddings(db: &Database, batch_rows: i64) -> Result<(), ServerError> {
+    println!("🧩 Partitioning doc_embeddings by crate ({batch_rows} rows per batch)...");
+    let report = db.partition_embeddings(batch_rows).await?;
+    println!(
+        "✅ Partitioned {} rows into {} crate partitions ({} written during the copy caught up at the swap)",
+        report.rows, report.crates, report.caught_up
+    );
+    println!("   The old table is kept as {UNPARTITIONED_TABLE}; drop it once searches check out:");
+    println!("   psql \"$MCPDOCS_DATABASE_URL\" -c 'DROP TABLE {UNPARTITIONED_TABLE}'");
+    Ok(())
+}
+
+async fn audit(db: &Database, fix: bool) -> Result<(), ServerError> {
+    println!("🔍 Auditing crates, doc_embeddings and crate_configs...");
+    let findings = db.audit_consistency().await?;
+
+    let mut fixed = 0;
+    for finding in &findings {
+        let icon = match finding.severity {
+            AuditSeverity::Error => "❌",
+            AuditSeverity::Warning => "⚠️ ",
+            AuditSeverity::Info => "ℹ️ ",
+        };
+        println!(
+            "{icon} [{}] {}: {}",
+            finding.check, finding.crate_name, finding.detail
+        );
+        if fix && db.apply_audit_fix(finding).await? {
+            fixed += 1;
+            println!("    ✅ Fixed automatically");
+        } else {
+            let note = if finding.auto_fix.is_some() {
+                " (safe; rerun with --fix)"
+            } else {
+                ""
+            };
+            println!("    Fix{note}: {}", finding.fix_sql);
+        }
+    }
+
+    let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+    println!(
+        "\n📊 {} findings: {} errors, {} warnings, {} info; {fixed} fixed",
+        findings.len(),
+        count(AuditSeverity::Error),
+        count(AuditSeverity::Warning),
+        count(AuditSeverity::Info)
+    );
+    Ok(())
+}
+
+async fn merge_small_chunks(
+    db: &Database,
+    crate_name: Option<String>,
+    dry_run: bool,
+) -> Result<(), ServerError> {
+    let crates = match crate_name {
+        Some(name) => vec![name],
+        None => db.get_all_crates_with_embeddings().await?,
+    };
+
+    if !dry_run {
+        init_embedding_provider()?;
+    }
+
+    let options = PipelineOptions::from_env()?;
+    let bpe = tiktoken_rs::cl100k_base().map_err(|e| ServerError::Tiktoken(e.to_string()))?;
+    let truncation = TruncationStrategy::from_env()?;
+
+    let (mut total_merged, mut total_dropped) = (0, 0);
+    for crate_name in &crates {
+        println!("🧩 Merging small chunks of {crate_name}...");
+
+        // Rows arrive ordered by path, so a page's chunks are consecutive and each page
+        // is merged on its own as the next one starts
+        let mut merged = Vec::new();
+        let mut removed = Vec::new();
+        let mut dropped = 0;
+        let mut page: Vec<PageChunk> = Vec::new();
+        let mut flush = |page: &mut Vec<PageChunk>| {
+            let merge = pipeline::merge_small_chunks(page, embeddings::TOKEN_LIMIT);
+            for chunk in merge.chunks.into_iter().filter(|c| !c.absorbed.is_empty()) {
+                removed.extend(chunk.absorbed);
+                merged.push((chunk.path, chunk.content));
+            }
+            dropped += merge.dropped.len();
+            removed.extend(merge.dropped);
+            page.clear();
+        };
+        let dimension = db.crate_embedding_dimension(crate_name).await?;
+        let mut rows = Box::pin(db.get_crate_documents_stream(crate_name, dimension));
+        while let Some(row) = rows.next().await {
+            let (doc_path, content, _embedding) = row?;
+            if page
+                .first()
+                .is_some_and(|first| pipeline::page_of(&first.path) != pipeline::page_of(&doc_path))
+            {
+                flush(&mut page);
+            }
+            let tokens = bpe.encode_with_special_tokens(&content).len();
+            page.push(PageChunk {
+                small: options.is_small_chunk(&content, tokens),
+                path: doc_path,
+                content,
+                tokens,
+            });
+        }
+        flush(&mut page);
+        drop(rows);
+
+        let absorbed = removed.len() - dropped;
+        println!(
+            "  {absorbed} small chunks merged into {} chunks, {dropped} dropped",
+            merged.len()
+        );
+        total_merged += absorbed;
+        total_dropped += dropped;
+
+        if dry_run || removed.is_empty() {
+            continue;
+        }
+
+        let provider = EMBEDDING_CLIENT.get().ok_or_else(|| {
+            ServerError::Internal("Embedding provider not initialized".to_string())
+        })?;
+        for (doc_path, content) in &merged {
+            let embedding = embed_chunk(
+                provider.as_ref(),
+                &bpe,
+                doc_path,
+                content.clone(),
+                truncation,
+            )
+            .await?;
+            let token_count = bpe.encode_with_special_tokens(content).len() as i32;
+            db.update_document_content(
+                crate_name,
+                doc_path,
+                content,
+                token_count,
+                Some(&embedding),
+            )
+            .await?;
+        }
+        let deleted = db.delete_documents(crate_name, &removed).await?;
+        db.refresh_crate_stats(crate_name).await?;
+        println!(
+            "  ✅ Re-embedded {} chunks and deleted {deleted} from {crate_name}",
+            merged.len()
+        );
+    }
+
+    let action = if dry_run { "Would merge" } else { "Merged" };
+    println!(
+        "\n📊 {action} {total_merged} small chunks ({total_dropped} with no neighbour to merge into) across {} crates",
+        crates.len()
+    );
+    Ok(())
+}
+
+fn init_embedding_provider() -> Result<(), ServerError> {
+    let provider_type = env::var("EMBEDDING_PROVIDER").unwrap_or_else(|_| "openai".to_string());
+    let embedding_config = match provider_type.to_lowercase().as_str() {
+        "openai" => {
+            let model = env::var("EMBEDDING_MODEL")
+                .unwrap_or_else(|_| "text-embedding-3-large".to_string());
+            let openai_client = if let Ok(api_base) = env::var("OPENAI_API_BASE") {
+                let config = OpenAIConfig::new().with_api_base(api_base);
+                OpenAIClient::with_config(config)
+            } else {
+                OpenAIClient::new()
+            };
+            EmbeddingConfig::OpenAI {
+                client: openai_client,
+                model,
+            }
+        }
+        "voyage" => {
+            let api_key = env::var("VOYAGE_API_KEY")
+                .map_err(|_| ServerError::MissingEnvVar("VOYAGE_API_KEY".to_string()))?;
+            let model = env::var("EMBEDDING_MODEL").unwrap_or_else(|_| "voyage-3.5".to_string());
+            EmbeddingConfig::VoyageAI { api_key, model }
+        }
+        _ => {
+            return Err(ServerError::Config(format!(
+                "Unsupported embedding provider: {provider_type}. Use 'openai' or 'voyage'"
+            )));
+        }
+    };
+
+    let provider = initialize_embedding_provider(embedding_config);
+    if EMBEDDING_CLIENT.set(provider).is_err() {
+        return Err(ServerError::Internal(
+            "Failed to set embedding provider".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use clap::Parser;
+use futures::TryStreamExt;
+use rustdocs_mcp_server::{
+    backup::{ArchiveWriter, CrateSnapshot},
+    database::Database,
+    error::ServerError,
+    object_store::{self, Credentials, ObjectLocation},
+};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Snapshot every crate's documentation, embeddings, configurations and metadata into one archive", long_about = None)]
+struct Cli {
+    /// Archive file to write (default: docs-backup-<timestamp>.jsonl.zst, or a temporary
+    /// file removed after upload when --s3-url is given)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Upload the archive to object storage, as s3://bucket/key or an http(s) object URL.
+    /// Credentials come from AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY and AWS_SESSION_TOKEN;
+    /// AWS_REGION and AWS_ENDPOINT_URL select the service
+    #[arg(long, env = "BACKUP_S3_URL")]
+    s3_url: Option<String>,
+
+    /// zstd compression level (1-22)
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(i32).range(1..=22))]
+    compression_level: i32,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ServerError> {
+    dotenvy::dotenv().ok();
+
+    let cli = Cli::parse();
+    let db = Database::new().await?;
+
+    // Fail on bad object storage settings before spending time on the snapshot
+    let upload = match &cli.s3_url {
+        Some(url) => Some((ObjectLocation::parse(url)?, Credentials::from_env()?)),
+        None => None,
+    };
+    let (path, temporary) = match (&cli.output, &upload) {
+        (Some(path), _) => (path.clone(), false),
+        (None, Some(_)) => (
+            std::env::temp_dir().join(format!("docs-backup-{}.jsonl.zst", std::process::id())),
+            true,
+        ),
+        (None, None) => (
+            PathBuf::from(format!(
+                "docs-backup-{}.jsonl.zst",
+                chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+            )),
+            false,
+        ),
+    };
+    let location = cli
+        .s3_url
+        .clone()
+        .unwrap_or_else(|| path.display().to_string());
+
+    let run_id = db.start_backup_run(&location).await?;
+    let result = run(&db, &path, cli.compression_level, upload.as_ref()).await;
+    if temporary {
+        let _ = std::fs::remove_file(&path);
+    }
+
+    match result {
+        Ok((crates, documents, bytes)) => {
+            db.finish_backup_run(run_id, Ok((crates, documents, bytes)))
+                .await?;
+            println!(
+                "✅ Backed up {crates} crates ({documents} documents, {bytes} bytes) to {location}"
+            );
+            Ok(())
+        }
+        Err(e) => {
+            db.finish_backup_run(run_id, Err(&e.to_string())).await?;
+            Err(e)
+        }
+    }
+}
+
+/// Write the archive to `path` and upload it, returning (crates, documents, bytes)
+async fn run(
+    db: &Database,
+    path: &Path,
+    compression_level: i32,
+    upload: Option<&(ObjectLocation, Credentials)>,
+) -> Result<(i32, i64, i64), ServerError> {
+    let mut archive = ArchiveWriter::new(BufWriter::new(File::create(path)?), compression_level)?;
+    let mut documents = 0i64;
+
+    for name in db.get_known_crate_names().await? {
+        let snapshot = CrateSnapshot {
+            records: db.get_crate_records(&name).await?,
+            record: None,
+            configs: db.get_crate_configs_by_name(&name).await?,
+            metadata: db.get_crate_metadata(&name).await?,
+            name: name.clone(),
+        };
+        archive.begin_crate(snapshot)?;
+
+        let dimension = db.crate_embedding_dimension(&name).await?;
+        let mut docs = Box::pin(db.get_stored_documents_stream(&name, dimension));
+        while let Some(doc) = docs.try_next().await? {
+            archive.doc(doc)?;
+        }
+
+        let summary = archive.end_crate()?;
+        documents += summary.docs as i64;
+        println!("  📦 {name}: {} documents", summary.docs);
+    }
+
+    let (manifest, writer) = archive.finish()?;
+    writer
+        .into_inner()
+        .map_err(|e| ServerError::Io(e.into_error()))?
+        .sync_all()?;
+    let bytes = std::fs::metadata(path)?.len() as i64;
+
+    if let Some((location, credentials)) = upload {
+        println!("☁️  Uploading {bytes} bytes to {}...", location.url);
+        object_store::upload(&reqwest::Client::new(), credentials, location, path).await?;
+    }
+
+    Ok((manifest.len() as i32, documents, bytes))
+}
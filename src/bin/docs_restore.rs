@@ -0,0 +1,302 @@
+use clap::Parser;
+use ndarray::Array1;
+use rustdocs_mcp_server::{
+    backup::{read_archive, ConflictPolicy, CrateSnapshot, Record, Verifier},
+    database::{Database, StoredDocument},
+    error::ServerError,
+    object_store::{self, Credentials, ObjectLocation},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+/// Documents inserted per batch
+const RESTORE_BATCH_SIZE: usize = 500;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Restore a docs_backup archive, verifying its manifest first", long_about = None)]
+struct Cli {
+    /// Archive file to restore
+    #[arg(
+        short,
+        long,
+        required_unless_present = "s3_url",
+        conflicts_with = "s3_url"
+    )]
+    input: Option<PathBuf>,
+
+    /// Download the archive from object storage (s3://bucket/key or an http(s) object URL),
+    /// with the same credentials and settings as docs_backup
+    #[arg(long)]
+    s3_url: Option<String>,
+
+    /// What to do with crates the database already has
+    #[arg(long, value_enum, default_value_t = ConflictPolicy::Fail)]
+    on_conflict: ConflictPolicy,
+
+    /// Refuse to restore into a database that has any crates at all
+    #[arg(long)]
+    require_empty: bool,
+
+    /// Check the archive's counts and hashes without writing anything
+    #[arg(long)]
+    verify_only: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ServerError> {
+    dotenvy::dotenv().ok();
+
+    let cli = Cli::parse();
+
+    let (path, temporary) = match (&cli.input, &cli.s3_url) {
+        (Some(path), _) => (path.clone(), false),
+        (None, Some(url)) => {
+            let location = ObjectLocation::parse(url)?;
+            let credentials = Credentials::from_env()?;
+            let path =
+                std::env::temp_dir().join(format!("docs-restore-{}.jsonl.zst", std::process::id()));
+            println!("☁️  Downloading {}...", location.url);
+            let bytes =
+                object_store::download(&reqwest::Client::new(), &credentials, &location, &path)
+                    .await?;
+            println!("  Received {bytes} bytes");
+            (path, true)
+        }
+        (None, None) => unreachable!("clap requires --input or --s3-url"),
+    };
+
+    let result = restore(&cli, &path).await;
+    if temporary {
+        let _ = std::fs::remove_file(&path);
+    }
+    result
+}
+
+async fn restore(cli: &Cli, path: &Path) -> Result<(), ServerError> {
+    // Pass 1: verify the whole archive before touching the database
+    let mut verifier = Verifier::default();
+    for record in read_archive(File::open(path)?)? {
+        verifier.check(&record?)?;
+    }
+    let manifest = verifier.finish()?;
+    let documents: u64 = manifest.iter().map(|c| c.docs).sum();
+    println!(
+        "✅ Archive verified: {} crates, {documents} documents",
+        manifest.len()
+    );
+    if cli.verify_only {
+        return Ok(());
+    }
+
+    let db = Database::new().await?;
+    let existing: HashSet<String> = db.get_known_crate_names().await?.into_iter().collect();
+    if cli.require_empty && !existing.is_empty() {
+        return Err(ServerError::Config(format!(
+            "--require-empty: the database already has {} crates",
+            existing.len()
+        )));
+    }
+    let conflicts: Vec<&str> = manifest
+        .iter()
+        .map(|c| c.name.as_str())
+        .filter(|name| existing.contains(*name))
+        .collect();
+    if !conflicts.is_empty() && cli.on_conflict == ConflictPolicy::Fail {
+        return Err(ServerError::Config(format!(
+            "{} crates already exist ({}); pass --on-conflict skip or --on-conflict replace",
+            conflicts.len(),
+            conflicts.join(", ")
+        )));
+    }
+
+    // Pass 2: write, re-checking each crate against its end record as it goes by
+    let mut verifier = Verifier::default();
+    let mut current: Option<RestoringCrate> = None;
+    let (mut restored, mut skipped) = (0usize, 0usize);
+    for record in read_archive(File::open(path)?)? {
+        let record = record?;
+        verifier.check(&record)?;
+        match record {
+            Record::Crate(snapshot) => {
+                if existing.contains(&snapshot.name) && cli.on_conflict == ConflictPolicy::Skip {
+                    println!("  ⏭️  {}: already present, skipped", snapshot.name);
+                    skipped += 1;
+                    current = Some(RestoringCrate::skipped(snapshot.name));
+                    continue;
+                }
+                if existing.contains(&snapshot.name) {
+                    db.delete_crate_embeddings(&snapshot.name).await?;
+                }
+                current = Some(RestoringCrate::begin(&db, snapshot).await?);
+            }
+            Record::Doc(doc) => {
+                if let Some(restoring) = current.as_mut() {
+                    restoring.push(&db, doc).await?;
+                }
+            }
+            Record::CrateEnd(summary) => {
+                let Some(mut restoring) = current.take() else {
+                    continue;
+                };
+                if restoring.skip {
+                    continue;
+                }
+                restoring.flush(&db).await?;
+                let stored = db.count_crate_documents(&summary.name).await?;
+                if stored as u64 != summary.docs {
+                    db.delete_crate_embeddings(&summary.name).await?;
+                    return Err(ServerError::Internal(format!(
+                        "Restoring {} stored {stored} documents, the archive holds {}; its documents were removed",
+                        summary.name, summary.docs
+                    )));
+                }
+                println!("  📥 {}: {stored} documents", summary.name);
+                restored += 1;
+            }
+            Record::Header { .. } | Record::Manifest { .. } => {}
+        }
+    }
+    verifier.finish()?;
+
+    println!("✅ Restored {restored} crates, skipped {skipped}");
+    Ok(())
+}
+
+/// A crate being written, with its pending batch of documents
+struct RestoringCrate {
+    name: String,
+    skip: bool,
+    /// `crates` row of each docs.rs target written so far
+    crate_ids: HashMap<Option<String>, i32>,
+    batch: Vec<StoredDocument>,
+}
+
+impl RestoringCrate {
+    fn skipped(name: String) -> Self {
+        Self {
+            name,
+            skip: true,
+            crate_ids: HashMap::new(),
+            batch: Vec::new(),
+        }
+    }
+
+    /// Write the crate's rows, configurations and metadata
+    async fn begin(db: &Database, snapshot: CrateSnapshot) -> Result<Self, ServerError> {
+        let mut crate_ids = HashMap::new();
+        for record in snapshot.records() {
+            let id = db
+                .upsert_crate(
+                    &snapshot.name,
+                    record.version.as_deref(),
+                    record.target.as_deref(),
+                )
+                .await?;
+            if let Some(model) = &record.embedding_model {
+                db.set_crate_embedding_model(id, model).await?;
+            }
+            crate_ids.insert(record.target.clone(), id);
+        }
+        for config in &snapshot.configs {
+            let stored = db.upsert_crate_config(config).await?;
+            db.set_crate_config_timestamps(stored.id, config.last_populated, config.last_checked)
+                .await?;
+            if config.truncated {
+                db.set_crate_truncated(stored.id, true).await?;
+            }
+            if config.low_doc_coverage {
+                db.set_crate_low_doc_coverage(stored.id, true).await?;
+            }
+        }
+        if let Some(metadata) = &snapshot.metadata {
+            db.upsert_crate_metadata(metadata).await?;
+        }
+        Ok(Self {
+            name: snapshot.name,
+            skip: false,
+            crate_ids,
+            batch: Vec::new(),
+        })
+    }
+
+    async fn push(&mut self, db: &Database, doc: StoredDocument) -> Result<(), ServerError> {
+        if self.skip {
+            return Ok(());
+        }
+        self.batch.push(doc);
+        if self.batch.len() >= RESTORE_BATCH_SIZE {
+            self.flush(db).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self, db: &Database) -> Result<(), ServerError> {
+        // Each target's documents go under that target's row
+        let mut by_target: Vec<(Option<String>, Vec<StoredDocument>)> = Vec::new();
+        for doc in std::mem::take(&mut self.batch) {
+            match by_target
+                .iter_mut()
+                .find(|(target, _)| *target == doc.target)
+            {
+                Some((_, docs)) => docs.push(doc),
+                None => by_target.push((doc.target.clone(), vec![doc])),
+            }
+        }
+        for (target, batch) in by_target {
+            self.store(db, target, batch).await?;
+        }
+        Ok(())
+    }
+
+    async fn store(
+        &mut self,
+        db: &Database,
+        target: Option<String>,
+        batch: Vec<StoredDocument>,
+    ) -> Result<(), ServerError> {
+        let crate_id = match self.crate_ids.get(&target) {
+            Some(id) => *id,
+            None => {
+                let id = db.upsert_crate(&self.name, None, target.as_deref()).await?;
+                self.crate_ids.insert(target, id);
+                id
+            }
+        };
+        let languages: Vec<(String, String)> = batch
+            .iter()
+            .filter_map(|doc| Some((doc.doc_path.clone(), doc.language.clone()?)))
+            .collect();
+        let sections: Vec<(String, String)> = batch
+            .iter()
+            .filter_map(|doc| Some((doc.doc_path.clone(), doc.section.clone()?)))
+            .collect();
+        let rows: Vec<(String, String, Array1<f32>, i32)> = batch
+            .into_iter()
+            .map(|doc| {
+                (
+                    doc.doc_path,
+                    doc.content,
+                    Array1::from_vec(doc.embedding),
+                    doc.token_count,
+                )
+            })
+            .collect();
+        db.insert_embeddings_batch(crate_id, &self.name, &rows)
+            .await?;
+        if !languages.is_empty() {
+            let languages: Vec<(String, &str)> = languages
+                .iter()
+                .map(|(path, code)| (path.clone(), code.as_str()))
+                .collect();
+            db.set_document_languages(&self.name, &languages).await?;
+        }
+        if !sections.is_empty() {
+            db.set_document_sections(&self.name, None, &sections)
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -5,33 +5,65 @@ use hyper_util::rt::{TokioExecutor, TokioIo};
 use hyper_util::server::conn::auto::Builder;
 use ndarray::Array1;
 use rmcp::{
+    handler::server::tool::ToolCallContext,
     model::{
-        AnnotateAble, CallToolResult, Content, GetPromptRequestParam, GetPromptResult,
-        Implementation, ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult,
-        PaginatedRequestParam, ProtocolVersion, RawResource, ReadResourceRequestParam,
-        ReadResourceResult, Resource, ServerCapabilities, ServerInfo,
+        AnnotateAble, CallToolRequestParam, CallToolResult, Content, GetPromptRequestParam,
+        GetPromptResult, Implementation, ListPromptsResult, ListResourceTemplatesResult,
+        ListResourcesResult, ListToolsResult, PaginatedRequestParam, ProtocolVersion, RawResource,
+        ReadResourceRequestParam, ReadResourceResult, Resource, ServerCapabilities, ServerInfo,
     },
     service::{RequestContext, RoleServer, ServiceExt},
-    tool,
-    transport::sse_server::{SseServer, SseServerConfig},
-    Error as McpError, ServerHandler,
+    tool, Error as McpError, ServerHandler,
 };
 use rustdocs_mcp_server::{
-    database::Database,
+    crate_metadata::{self, CratesIo},
+    crate_status::CrateStatus,
+    database::{
+        AuditFinding, AuditSeverity, CrateConfig, CrateMetadata, CrateStorage, Database,
+        SchemaFinding, ScoredDoc, EMBEDDING_DIMENSION,
+    },
+    db_breaker::{self, DbBreaker},
     doc_loader,
     embeddings::{
-        generate_embeddings, initialize_embedding_provider, EmbeddingConfig, EMBEDDING_CLIENT,
+        self, initialize_embedding_provider, EmbeddingConfig, EmbeddingProvider, EMBEDDING_CLIENT,
     },
     error::ServerError,
+    estimate::{self, EstimateSettings},
+    freshness::{self, LatestVersions, DEFAULT_STALE_AFTER_DAYS},
+    hyde::HydeClient,
+    language::{LanguageAction, LanguageFilter},
+    orientation::{self, OrientationStats},
+    pipeline,
+    profiles::{self, Profile},
+    provenance,
+    query_cache::{self, QueryCache, QueryCacheKey, RecentQuestions},
+    query_defaults::QueryDefaults,
+    query_log::{self, QueryLogEntry, QueryLogSettings},
+    rate_limit::{self, RateLimiter},
+    refresh::{self, Decision, RefreshStatus},
+    request_context,
+    response::{self, DEFAULT_MAX_CONTENT_ITEM_BYTES},
+    search,
+    sse::{SseServer, SseServerConfig, SseServerTransport},
+    threshold,
+    tool_args::{
+        self, AddCrateArgs, AddCratesArgs, CheckCrateStatusArgs, CompareCratesArgs, CrateInfoArgs,
+        CrawlTraceArgs, EstimatePopulationArgs, FindSymbolArgs, GcOrphansArgs, GetDocArgs,
+        IndexHealthArgs, JobStatusArgs, ListCratesArgs, PopulatePendingArgs, QueryRustDocsArgs,
+        RawQuery, RawSearchArgs, RemoveCrateArgs, RenameCrateArgs, SetCrateEnabledArgs,
+        SmartQueryArgs, StorageReportArgs, SubmitFeedbackArgs, SuggestThresholdArgs,
+    },
+    validation::Validate,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
 use std::{
+    collections::{BTreeMap, HashMap},
     convert::Infallible,
     env,
     net::SocketAddr,
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 use tokio_util::sync::CancellationToken;
@@ -71,22 +103,83 @@ struct ReadinessState {
     database_connected: Arc<AtomicBool>,
     embedding_initialized: Arc<AtomicBool>,
     auto_population_complete: Arc<AtomicBool>,
+    /// Set once `--prewarm` finishes (immediately when it is off)
+    warmup_complete: Arc<AtomicBool>,
+    audit: Arc<AuditMetrics>,
+    /// Open while the database is unreachable from background tasks
+    db_breaker: Arc<DbBreaker>,
+    /// Enabled crates per [`CrateStatus`] state, refreshed every [`CRATE_STATUS_REFRESH_INTERVAL`]
+    crate_states: Arc<Mutex<BTreeMap<&'static str, usize>>>,
+}
+
+/// Results of the latest scheduled consistency audit, served on `/metrics`
+#[derive(Default)]
+struct AuditMetrics {
+    errors: AtomicU64,
+    warnings: AtomicU64,
+    infos: AtomicU64,
+    /// Unix time of the last completed audit (0 = never ran)
+    last_run: AtomicI64,
+}
+
+impl AuditMetrics {
+    fn record(&self, findings: &[AuditFinding]) {
+        let count = |severity| findings.iter().filter(|f| f.severity == severity).count() as u64;
+        self.errors
+            .store(count(AuditSeverity::Error), Ordering::Relaxed);
+        self.warnings
+            .store(count(AuditSeverity::Warning), Ordering::Relaxed);
+        self.infos
+            .store(count(AuditSeverity::Info), Ordering::Relaxed);
+        self.last_run
+            .store(chrono::Utc::now().timestamp(), Ordering::Relaxed);
+    }
+
+    /// Prometheus text exposition of the findings gauge
+    fn render(&self) -> String {
+        format!(
+            "# HELP mcpdocs_audit_findings Findings of the last consistency audit by severity\n\
+             # TYPE mcpdocs_audit_findings gauge\n\
+             mcpdocs_audit_findings{{severity=\"error\"}} {}\n\
+             mcpdocs_audit_findings{{severity=\"warning\"}} {}\n\
+             mcpdocs_audit_findings{{severity=\"info\"}} {}\n\
+             # HELP mcpdocs_audit_last_run_timestamp_seconds Unix time of the last consistency audit\n\
+             # TYPE mcpdocs_audit_last_run_timestamp_seconds gauge\n\
+             mcpdocs_audit_last_run_timestamp_seconds {}\n",
+            self.errors.load(Ordering::Relaxed),
+            self.warnings.load(Ordering::Relaxed),
+            self.infos.load(Ordering::Relaxed),
+            self.last_run.load(Ordering::Relaxed)
+        )
+    }
 }
 
 impl ReadinessState {
-    fn new() -> Self {
+    fn new(db_breaker: Arc<DbBreaker>) -> Self {
         Self {
             database_connected: Arc::new(AtomicBool::new(false)),
             embedding_initialized: Arc::new(AtomicBool::new(false)),
             auto_population_complete: Arc::new(AtomicBool::new(false)),
+            warmup_complete: Arc::new(AtomicBool::new(false)),
+            audit: Arc::new(AuditMetrics::default()),
+            db_breaker,
+            crate_states: Arc::new(Mutex::new(BTreeMap::new())),
         }
     }
 
+    /// JSON object of enabled crates per state, e.g. `{"failed":1,"populated":12}`
+    fn crate_states_json(&self) -> String {
+        let states = self.crate_states.lock().unwrap_or_else(|e| e.into_inner());
+        serde_json::to_string(&*states).unwrap_or_else(|_| "{}".to_string())
+    }
+
     fn is_ready(&self) -> bool {
-        // Server is ready as soon as database and embeddings are initialized
+        // Server is ready once database, embeddings and the optional warmup are done
         // Auto-population can run in background without blocking readiness
         self.database_connected.load(Ordering::Relaxed)
             && self.embedding_initialized.load(Ordering::Relaxed)
+            && self.warmup_complete.load(Ordering::Relaxed)
+            && !self.db_breaker.is_degraded()
     }
 }
 
@@ -116,81 +209,425 @@ struct Cli {
     /// Embedding model to use
     #[arg(long, env = "EMBEDDING_MODEL")]
     embedding_model: Option<String>,
+
+    /// Embed query_rust_docs questions with this model while population keeps using
+    /// EMBEDDING_MODEL; it must return vectors in the same space (checked at startup)
+    #[arg(long, env = "QUERY_EMBEDDING_MODEL")]
+    query_embedding_model: Option<String>,
+
+    /// Ordered fallback providers for query embeddings, e.g. `voyage:voyage-3.5,openai`.
+    /// A fallback is only used for crates populated with the same model.
+    #[arg(long, env = "EMBEDDING_FALLBACK_PROVIDERS")]
+    embedding_fallback_providers: Option<String>,
+
+    /// Interval in seconds between SSE keep-alive comments (0 disables them)
+    #[arg(long, default_value_t = 15, env = "SSE_KEEP_ALIVE_SECS")]
+    sse_keep_alive_secs: u64,
+
+    /// Close SSE sessions with no client activity for this many seconds (0 disables)
+    #[arg(long, default_value_t = 1800, env = "SSE_IDLE_TIMEOUT_SECS")]
+    idle_timeout_secs: u64,
+
+    /// Maximum concurrent SSE connections; extra clients get HTTP 503 (default: unlimited)
+    #[arg(long, env = "MAX_CONNECTIONS")]
+    max_connections: Option<usize>,
+
+    /// Maximum query_rust_docs calls per minute per connection (0 disables)
+    #[arg(long, default_value_t = rate_limit::DEFAULT_QUERY_LIMIT_PER_MINUTE, env = "QUERY_RATE_LIMIT_PER_MIN")]
+    query_rate_limit: u32,
+
+    /// Maximum add_crate/add_crates calls per minute per connection (0 disables)
+    #[arg(
+        long,
+        default_value_t = rate_limit::DEFAULT_ADD_CRATE_LIMIT_PER_MINUTE,
+        env = "ADD_CRATE_RATE_LIMIT_PER_MIN"
+    )]
+    add_crate_rate_limit: u32,
+
+    /// Comma-separated features stored for crates whose add_crate/add_crates call leaves
+    /// `features` unset, e.g. `full,serde`
+    #[arg(long, env = "DEFAULT_CRATE_FEATURES")]
+    default_crate_features: Option<String>,
+
+    /// Crawl pages even where the docs host's robots.txt disallows them
+    #[arg(long, env = "IGNORE_ROBOTS")]
+    ignore_robots: bool,
+
+    /// Store crawled pages as decoded, without repairing UTF-8 read as Windows-1252
+    #[arg(long, env = "KEEP_MOJIBAKE")]
+    keep_mojibake: bool,
+
+    /// Hours between scheduled docs_backup runs; when set, get_server_stats reports the
+    /// last successful backup and whether one is overdue (0 = no schedule)
+    #[arg(long, default_value_t = 0, env = "BACKUP_INTERVAL_HOURS")]
+    backup_interval_hours: u32,
+
+    /// Maximum cached query_rust_docs results (0 disables the cache)
+    #[arg(long, default_value_t = 1000, env = "QUERY_CACHE_SIZE")]
+    query_cache_size: usize,
+
+    /// Seconds a cached query result stays valid
+    #[arg(long, default_value_t = 3600, env = "QUERY_CACHE_TTL_SECS")]
+    query_cache_ttl_secs: u64,
+
+    /// Seconds within which a connection asking the same query_rust_docs question again
+    /// gets its earlier answer back, marked as a repeat (0 disables)
+    #[arg(long, default_value_t = 300, env = "REPEAT_QUESTION_WINDOW_SECS")]
+    repeat_question_window_secs: u64,
+
+    /// Warn in query results when a crate was populated more than this many days ago (0 disables)
+    #[arg(long, default_value_t = DEFAULT_STALE_AFTER_DAYS, env = "STALE_AFTER_DAYS")]
+    stale_after_days: u32,
+
+    /// Serve queries only: reject tools that change crate configurations and skip
+    /// auto-population, so the database URL may point at a read-only replica
+    #[arg(long, env = "MCPDOCS_READ_ONLY")]
+    read_only: bool,
+
+    /// Enable admin tools (raw_search) for every client; they return internal data such
+    /// as embedding vectors and are meant for trusted deployments only
+    #[arg(long, env = "MCPDOCS_ADMIN_TOOLS")]
+    admin_tools: bool,
+
+    /// Chat model that drafts hypothetical answers for `hyde` queries (default: LLM_MODEL,
+    /// else gpt-4o-mini); called through OPENAI_API_KEY/OPENAI_API_BASE
+    #[arg(long, env = "HYDE_MODEL")]
+    hyde_model: Option<String>,
+
+    /// Split query_rust_docs and get_doc responses larger than this many bytes into
+    /// several content items, for clients that truncate a single large item (0 disables)
+    #[arg(long, default_value_t = DEFAULT_MAX_CONTENT_ITEM_BYTES, env = "MAX_CONTENT_ITEM_BYTES")]
+    max_content_item_bytes: usize,
+
+    /// Demote query_rust_docs results that resemble the crate's average page (trait impl
+    /// lists, re-export stubs): results are ranked by similarity minus this weight times
+    /// their similarity to the crate centroid (0 disables; 0.2-0.5 is a useful range)
+    #[arg(long, default_value_t = 0.0, env = "GENERIC_PENALTY")]
+    generic_penalty: f32,
+
+    /// Promote query_rust_docs results that submit_feedback marked useful (and demote
+    /// noise): results are ranked by similarity plus this weight times the document's
+    /// feedback prior in (-1, 1). Capped at 0.1; 0 disables
+    #[arg(long, default_value_t = 0.0, env = "FEEDBACK_WEIGHT")]
+    feedback_weight: f32,
+
+    /// Serve query_rust_docs questions whose embedding is at least this similar to one of
+    /// the orientation questions answered at population time ("how do I get started",
+    /// "what are the main types", ...) from the precomputed answer (above 1 disables)
+    #[arg(long, default_value_t = orientation::DEFAULT_MATCH_THRESHOLD, env = "ORIENTATION_MATCH_THRESHOLD")]
+    orientation_match_threshold: f32,
+
+    /// Run the cross-table consistency audit every this many hours, logging findings and
+    /// exporting counts on /metrics (24 = daily; 0 disables). Never changes data.
+    #[arg(long, default_value_t = 0, env = "AUDIT_INTERVAL_HOURS")]
+    audit_interval_hours: u64,
+
+    /// Open pool connections and run one vector search per crate before reporting ready,
+    /// so the first queries after a deploy aren't slow (`--prewarm false` to skip locally)
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set, env = "MCPDOCS_PREWARM")]
+    prewarm: bool,
+
+    /// Crates searched during prewarm, largest first (0 = every available crate)
+    #[arg(long, default_value_t = 20, env = "PREWARM_CRATES")]
+    prewarm_crates: usize,
+
+    /// Pool connections opened during prewarm
+    #[arg(long, default_value_t = 4, env = "PREWARM_CONNECTIONS")]
+    prewarm_connections: u32,
+
+    /// Crates crawled and embedded at the same time, across add_crate, add_crates,
+    /// populate_pending and startup auto-population; the rest wait for a slot
+    #[arg(long, default_value_t = DEFAULT_MAX_CONCURRENT_POPULATIONS, env = "MAX_CONCURRENT_POPULATIONS")]
+    max_concurrent_populations: usize,
+
+    /// Consecutive background database failures after which the server reports degraded
+    /// and background tasks back off instead of retrying at full speed
+    #[arg(long, default_value_t = db_breaker::DEFAULT_FAILURE_THRESHOLD, env = "DB_FAILURE_THRESHOLD")]
+    db_failure_threshold: u32,
+
+    /// Longest wait between background database attempts while it is unavailable
+    #[arg(long, default_value_t = db_breaker::DEFAULT_MAX_BACKOFF.as_secs(), env = "DB_MAX_BACKOFF_SECS")]
+    db_max_backoff_secs: u64,
+
+    /// Every this many hours, look up the docs.rs version of each populated `latest` crate
+    /// and re-populate only those whose version changed (0 disables; off in read-only mode)
+    #[arg(long, default_value_t = 0, env = "VERSION_REFRESH_INTERVAL_HOURS")]
+    version_refresh_interval_hours: u64,
+}
+
+/// Per-connection call limits for tools that spend embedding or population budget
+#[derive(Clone, Copy, Debug)]
+struct ToolRateLimits {
+    query_per_minute: u32,
+    add_crate_per_minute: u32,
+}
+
+impl Default for ToolRateLimits {
+    fn default() -> Self {
+        Self {
+            query_per_minute: rate_limit::DEFAULT_QUERY_LIMIT_PER_MINUTE,
+            add_crate_per_minute: rate_limit::DEFAULT_ADD_CRATE_LIMIT_PER_MINUTE,
+        }
+    }
+}
+
+/// Populations allowed to run at once unless `--max-concurrent-populations` says otherwise
+const DEFAULT_MAX_CONCURRENT_POPULATIONS: usize = 2;
+
+/// How often the database is pinged to detect outages and recoveries
+const DB_PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the readiness endpoint's per-state crate counts are recomputed
+const CRATE_STATUS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Pause between docs.rs version lookups of a refresh run
+const VERSION_CHECK_DELAY: Duration = Duration::from_millis(500);
+
+/// Crate populations of this process: at most `limit` run at once, the rest wait
+struct Populations {
+    slots: Arc<tokio::sync::Semaphore>,
+    limit: usize,
+    /// Configuration ids with a population waiting or running
+    active: std::sync::Mutex<std::collections::HashSet<i32>>,
+    /// Live progress of the waiting or running populations, by job id
+    progress: std::sync::Mutex<HashMap<i32, Arc<pipeline::Progress>>>,
+}
+
+impl Populations {
+    fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            slots: Arc::new(tokio::sync::Semaphore::new(limit)),
+            limit,
+            active: Default::default(),
+            progress: Default::default(),
+        }
+    }
+
+    /// Populations of another profile, drawing on the same slots so the limit holds
+    /// across the whole process; configuration and job ids of profiles can overlap, so
+    /// they are tracked apart
+    fn sharing_slots(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            limit: self.limit,
+            active: Default::default(),
+            progress: Default::default(),
+        }
+    }
+
+    fn is_active(&self, config_id: i32) -> bool {
+        self.active.lock().unwrap().contains(&config_id)
+    }
+
+    /// Progress of job `job_id`, while this process is running it
+    fn progress(&self, job_id: i32) -> Option<pipeline::ProgressSnapshot> {
+        self.progress
+            .lock()
+            .unwrap()
+            .get(&job_id)
+            .map(|progress| progress.snapshot())
+    }
+}
+
+/// Marks a configuration as populating, and publishes its job's progress, until dropped
+struct ActivePopulation {
+    populations: Arc<Populations>,
+    config_id: i32,
+    job_id: Option<i32>,
+    progress: Arc<pipeline::Progress>,
+}
+
+impl ActivePopulation {
+    fn start(populations: &Arc<Populations>, config_id: i32, job_id: Option<i32>) -> Self {
+        let progress = Arc::new(pipeline::Progress::default());
+        populations.active.lock().unwrap().insert(config_id);
+        if let Some(job_id) = job_id {
+            populations
+                .progress
+                .lock()
+                .unwrap()
+                .insert(job_id, progress.clone());
+        }
+        Self {
+            populations: populations.clone(),
+            config_id,
+            job_id,
+            progress,
+        }
+    }
+}
+
+impl Drop for ActivePopulation {
+    fn drop(&mut self) {
+        self.populations
+            .active
+            .lock()
+            .unwrap()
+            .remove(&self.config_id);
+        if let Some(job_id) = self.job_id {
+            self.populations.progress.lock().unwrap().remove(&job_id);
+        }
+    }
 }
 
 #[derive(Clone)]
-#[allow(dead_code)] // Fields are used in async trait implementations
 struct McpHandler {
     database: Database,
     available_crates: Arc<tokio::sync::RwLock<std::collections::HashSet<String>>>,
     startup_message: String,
+    /// The profile this handler serves; `None` for the default corpus
+    profile: Option<Arc<str>>,
+    /// Embeds the profile's documents and questions; `None` uses the server's provider
+    document_provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    /// Identifies the SSE connection this handler clone serves
+    connection_id: Arc<str>,
+    rate_limiter: Arc<RateLimiter>,
+    rate_limits: ToolRateLimits,
+    /// Features for crates added without `features` (`DEFAULT_CRATE_FEATURES`)
+    default_crate_features: Arc<Vec<String>>,
+    /// Passed to the crawler when populating crates
+    ignore_robots: bool,
+    keep_mojibake: bool,
+    /// Expected hours between backups, reported by get_server_stats (0 = no schedule)
+    backup_interval_hours: u32,
+    /// Formatted query_rust_docs results, shared by all connections
+    query_cache: Arc<QueryCache>,
+    /// Each connection's recent questions, for answering repeats
+    recent_questions: Arc<RecentQuestions>,
+    /// Environment defaults for what requests and crate configurations leave unset
+    query_defaults: Arc<QueryDefaults>,
+    /// Age after which query results note that docs may be stale (0 disables)
+    stale_after_days: u32,
+    /// What `query_log` keeps of each question, and for how long
+    query_log: Arc<QueryLogSettings>,
+    /// docs.rs latest versions for `latest`-tracked crates, shared by all connections
+    latest_versions: Arc<LatestVersions>,
+    /// crates.io API for `crate_info`; results are cached in `crate_metadata`
+    crates_io: Arc<CratesIo>,
+    /// Reject tools that write to the database (`--read-only`)
+    read_only: bool,
+    /// Allow admin tools such as raw_search (`--admin-tools`)
+    admin_tools: bool,
+    /// Embeds questions when `QUERY_EMBEDDING_MODEL` differs from the document model
+    query_provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    /// Tried in order when the primary provider fails to embed a query
+    fallback_providers: Arc<Vec<Arc<dyn EmbeddingProvider + Send + Sync>>>,
+    /// Drafts hypothetical answers for `hyde` queries; without it they search plainly
+    hyde: Option<Arc<HydeClient>>,
+    /// Responses above this size are sent as several content items (0 disables)
+    max_content_item_bytes: usize,
+    /// Shared by every handler so the population limit holds across tools and startup
+    populations: Arc<Populations>,
+    /// Weight of the generic-boilerplate penalty in query ranking (0 disables)
+    generic_penalty: f32,
+    /// Mean embedding per (crate, dimension) for the generic penalty, dropped on repopulation
+    centroids: Arc<Mutex<CentroidCache>>,
+    /// Weight of the retrieval feedback prior in query ranking (0 disables)
+    feedback_weight: f32,
+    /// Feedback priors per crate with when they were computed, recomputed after
+    /// [`FEEDBACK_PRIOR_TTL`]
+    feedback_priors: Arc<Mutex<FeedbackPriorCache>>,
+    /// Similarity to an orientation question that serves its precomputed answer (above 1 disables)
+    orientation_match_threshold: f32,
+    /// Precomputed-answer hits and misses, shared by all connections
+    orientation_stats: Arc<OrientationStats>,
+    /// Background database health, shared with the readiness endpoint
+    db_breaker: Arc<DbBreaker>,
+    /// Decisions of the last scheduled version refresh, for `refresh_status`
+    refresh_status: Arc<std::sync::Mutex<RefreshStatus>>,
+    /// Cancelled when this connection's client drops the SSE stream
+    disconnected: Option<CancellationToken>,
 }
 
+/// Mean embedding of each crate, keyed by `(crate_name, dimension)`
+type CentroidCache = HashMap<(String, usize), Arc<Vec<f32>>>;
+
+type FeedbackPriorCache = HashMap<String, (Instant, Arc<HashMap<String, f32>>)>;
+
+/// How long a crate's feedback priors are reused before they are recomputed
+const FEEDBACK_PRIOR_TTL: Duration = Duration::from_secs(600);
+
+/// Recent questions remembered per connection for repeat detection
+const REPEAT_QUESTIONS_PER_CONNECTION: usize = 16;
+
+/// Tools that run to completion after a client disconnects, so a write is never left half applied
+const WRITE_TOOLS: &[&str] = &[
+    "submit_feedback",
+    "add_crate",
+    "add_crates",
+    "remove_crate",
+    "rename_crate",
+    "set_crate_enabled",
+    "populate_pending",
+    "gc_orphans",
+];
+
 /// Enhanced MCP connection handler with timeout management and better error handling
 async fn handle_mcp_connection_with_resilience(
     handler: McpHandler,
-    transport: rmcp::transport::sse_server::SseServerTransport,
+    transport: SseServerTransport,
     config: McpConnectionConfig,
     connection_id: String,
 ) -> Result<(), ServerError> {
     let start_time = Instant::now();
+    let session = transport.handle();
 
     if config.verbose_logging {
-        info!("🔄 Starting MCP connection initialization (ID: {connection_id})");
+        info!(
+            "🔄 Starting MCP connection initialization (ID: {connection_id}, session: {})",
+            session.session_id()
+        );
     }
 
-    // Try to establish the connection with extended timeout
-    let connection_id_clone = connection_id.clone();
-    let connection_result = tokio::time::timeout(config.initialize_timeout, async move {
-        match handler.serve(transport).await {
-            Ok(service) => {
-                if config.verbose_logging {
-                    info!("✅ MCP service initialized successfully (ID: {connection_id_clone})");
-                }
-
-                // Run the service with enhanced error handling
-                info!("🎯 MCP service started successfully (ID: {connection_id_clone})");
-
-                if let Err(e) = service.waiting().await {
-                    error!("❌ MCP service runtime error (ID: {connection_id_clone}): {e}");
-                    return Err(ServerError::Internal(format!(
-                        "MCP service runtime error: {e}"
-                    )));
-                }
-
-                Ok(())
-            }
-            Err(e) => {
-                if config.verbose_logging {
-                    warn!("⚠️  MCP service initialization failed (ID: {connection_id_clone}): {e}");
-                }
-                Err(ServerError::Internal(format!(
-                    "MCP service initialization failed: {e}"
-                )))
+    // Only the initialization handshake is bounded by the timeout; an established
+    // session lives until the client leaves or the idle timeout reaps it
+    let service = match tokio::time::timeout(config.initialize_timeout, handler.serve(transport))
+        .await
+    {
+        Ok(Ok(service)) => {
+            if config.verbose_logging {
+                info!("✅ MCP service initialized successfully (ID: {connection_id})");
             }
-        }
-    })
-    .await;
-
-    match connection_result {
-        Ok(Ok(())) => {
-            // Success!
-            let total_time = start_time.elapsed();
-            info!("🎉 MCP connection established successfully (ID: {connection_id}, total time: {total_time:?})");
-            Ok(())
+            service
         }
         Ok(Err(e)) => {
-            // Service initialization or runtime error
             let total_time = start_time.elapsed();
-            error!("❌ MCP connection failed after {total_time:?} (ID: {connection_id}): {e}");
-            Err(e)
+            if config.verbose_logging {
+                warn!("⚠️  MCP service initialization failed after {total_time:?} (ID: {connection_id}): {e}");
+            }
+            return Err(ServerError::Internal(format!(
+                "MCP service initialization failed: {e}"
+            )));
         }
         Err(_) => {
-            // Timeout
             let total_time = start_time.elapsed();
             error!("⏱️  MCP connection timed out after {total_time:?} (ID: {connection_id})");
-            Err(ServerError::Internal(format!(
+            return Err(ServerError::Internal(format!(
                 "MCP connection timeout after {total_time:?}"
+            )));
+        }
+    };
+
+    info!("🎯 MCP service started successfully (ID: {connection_id})");
+
+    match service.waiting().await {
+        Ok(quit_reason) => {
+            let total_time = start_time.elapsed();
+            match session.close_reason() {
+                Some(reason) => info!(
+                    "🔌 MCP session closed by server (ID: {connection_id}, reason: {reason}, total time: {total_time:?})"
+                ),
+                None => info!(
+                    "🎉 MCP session ended (ID: {connection_id}, reason: {quit_reason:?}, total time: {total_time:?})"
+                ),
+            }
+            Ok(())
+        }
+        Err(e) => {
+            error!("❌ MCP service runtime error (ID: {connection_id}): {e}");
+            Err(ServerError::Internal(format!(
+                "MCP service runtime error: {e}"
             )))
         }
     }
@@ -203,266 +640,999 @@ impl McpHandler {
             database,
             available_crates: Arc::new(tokio::sync::RwLock::new(crates_set)),
             startup_message,
+            profile: None,
+            document_provider: None,
+            connection_id: Arc::from("internal"),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            rate_limits: ToolRateLimits::default(),
+            default_crate_features: Arc::new(Vec::new()),
+            ignore_robots: false,
+            keep_mojibake: false,
+            backup_interval_hours: 0,
+            query_cache: Arc::new(QueryCache::new(0, Duration::ZERO)),
+            recent_questions: Arc::new(RecentQuestions::new(Duration::ZERO, 0)),
+            query_defaults: Arc::new(QueryDefaults::default()),
+            stale_after_days: DEFAULT_STALE_AFTER_DAYS,
+            query_log: Arc::new(QueryLogSettings::default()),
+            latest_versions: Arc::new(LatestVersions::new(doc_loader::DOCS_RS_ORIGIN)),
+            crates_io: Arc::new(CratesIo::new(crate_metadata::CRATES_IO_ORIGIN)),
+            read_only: false,
+            admin_tools: false,
+            query_provider: None,
+            fallback_providers: Arc::new(Vec::new()),
+            hyde: None,
+            max_content_item_bytes: DEFAULT_MAX_CONTENT_ITEM_BYTES,
+            populations: Arc::new(Populations::new(DEFAULT_MAX_CONCURRENT_POPULATIONS)),
+            generic_penalty: 0.0,
+            centroids: Arc::new(Mutex::new(HashMap::new())),
+            feedback_weight: 0.0,
+            feedback_priors: Arc::new(Mutex::new(HashMap::new())),
+            orientation_match_threshold: orientation::DEFAULT_MATCH_THRESHOLD,
+            orientation_stats: Arc::new(OrientationStats::default()),
+            db_breaker: Arc::new(DbBreaker::default()),
+            refresh_status: Default::default(),
+            disconnected: None,
         }
     }
 
-    /// Refresh the available crates cache from the database
-    async fn refresh_available_crates(&self) -> Result<(), ServerError> {
-        let all_crates = self.database.get_all_crates_with_embeddings().await?;
-        let mut crates = self.available_crates.write().await;
-        crates.clear();
-        crates.extend(all_crates);
-        Ok(())
+    fn with_rate_limits(mut self, rate_limits: ToolRateLimits) -> Self {
+        self.rate_limits = rate_limits;
+        self
     }
 
-    /// Add a crate to the available crates cache
-    async fn add_crate_to_available(&self, crate_name: &str) {
-        let mut crates = self.available_crates.write().await;
-        crates.insert(crate_name.to_string());
+    fn with_default_crate_features(mut self, features: Vec<String>) -> Self {
+        self.default_crate_features = Arc::new(features);
+        self
     }
 
-    /// Check if a crate is available (fast in-memory lookup)
-    async fn is_crate_available(&self, crate_name: &str) -> bool {
-        let crates = self.available_crates.read().await;
-        crates.contains(crate_name)
+    fn with_ignore_robots(mut self, ignore_robots: bool) -> Self {
+        self.ignore_robots = ignore_robots;
+        self
     }
 
-    /// Remove a crate from the available crates cache
-    async fn remove_crate_from_available(&self, crate_name: &str) {
-        let mut crates = self.available_crates.write().await;
-        crates.remove(crate_name);
+    fn with_keep_mojibake(mut self, keep_mojibake: bool) -> Self {
+        self.keep_mojibake = keep_mojibake;
+        self
     }
 
-    fn _create_resource_text(&self, uri: &str, name: &str) -> Resource {
-        RawResource::new(uri, name.to_string()).no_annotation()
+    fn with_backup_interval_hours(mut self, backup_interval_hours: u32) -> Self {
+        self.backup_interval_hours = backup_interval_hours;
+        self
     }
 
-    async fn populate_crate(
-        &self,
-        crate_name: &str,
-        features: &[String],
-    ) -> Result<serde_json::Value, ServerError> {
-        use serde_json::json;
+    fn with_query_cache(mut self, query_cache: QueryCache) -> Self {
+        self.query_cache = Arc::new(query_cache);
+        self
+    }
 
-        info!("🚀 Starting automatic population for crate: {}", crate_name);
-        let crate_name = crate_name.to_string();
-        let features = features.to_vec();
-        let database = self.database.clone();
+    fn with_repeat_question_window(mut self, window: Duration) -> Self {
+        self.recent_questions = Arc::new(RecentQuestions::new(
+            window,
+            REPEAT_QUESTIONS_PER_CONNECTION,
+        ));
+        self
+    }
 
-        // Run population in a blocking task to handle non-Send scraper types
-        // Use a dedicated thread pool to avoid blocking the main runtime
-        let result = tokio::task::spawn_blocking(move || {
-            tokio::runtime::Handle::current().block_on(async {
-                let total_start = std::time::Instant::now();
+    fn with_query_defaults(mut self, query_defaults: QueryDefaults) -> Self {
+        self.query_defaults = Arc::new(query_defaults);
+        self
+    }
 
-                // Load documents
-                info!(
-                    "📥 Loading documentation for crate: {} with features: {:?}",
-                    crate_name, features
-                );
-                let doc_start = std::time::Instant::now();
-                let features_opt = if features.is_empty() {
-                    None
-                } else {
-                    Some(features.clone())
-                };
-                let load_result = doc_loader::load_documents_from_docs_rs(
-                    &crate_name,
-                    "*",
-                    features_opt.as_ref(),
-                    Some(10000),
-                )
-                .await?;
-                let documents = load_result.documents;
-                let crate_version = load_result.version;
-                let doc_time = doc_start.elapsed();
+    fn with_query_log(mut self, query_log: QueryLogSettings) -> Self {
+        self.query_log = Arc::new(query_log);
+        self
+    }
 
-                let total_content_size: usize = documents.iter().map(|doc| doc.content.len()).sum();
-                info!(
-                    "✅ Loaded {} documents in {:.2}s ({:.1} KB total)",
-                    documents.len(),
-                    doc_time.as_secs_f64(),
-                    total_content_size as f64 / 1024.0
-                );
+    fn with_stale_after_days(mut self, stale_after_days: u32) -> Self {
+        self.stale_after_days = stale_after_days;
+        self
+    }
 
-                if documents.is_empty() {
-                    return Err(ServerError::Config(format!(
-                        "No documents found for crate: {crate_name}"
-                    )));
-                }
+    fn with_query_provider(
+        mut self,
+        query_provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    ) -> Self {
+        self.query_provider = query_provider;
+        self
+    }
 
-                // Generate embeddings
-                info!(
-                    "🧠 Generating embeddings for {} documents...",
-                    documents.len()
-                );
+    fn with_fallback_providers(
+        mut self,
+        fallback_providers: Vec<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    ) -> Self {
+        self.fallback_providers = Arc::new(fallback_providers);
+        self
+    }
 
-                // Yield before heavy embedding operation
-                tokio::task::yield_now().await;
+    fn with_hyde(mut self, hyde: HydeClient) -> Self {
+        self.hyde = Some(Arc::new(hyde));
+        self
+    }
 
-                let embedding_start = std::time::Instant::now();
-                let (embeddings, total_tokens) = generate_embeddings(&documents).await?;
-                let embedding_time = embedding_start.elapsed();
+    fn with_max_content_item_bytes(mut self, max_content_item_bytes: usize) -> Self {
+        self.max_content_item_bytes = max_content_item_bytes;
+        self
+    }
 
-                info!(
-                    "✅ Generated {} embeddings using {} tokens in {:.2}s",
-                    embeddings.len(),
-                    total_tokens,
-                    embedding_time.as_secs_f64()
-                );
+    fn with_populations(mut self, populations: Arc<Populations>) -> Self {
+        self.populations = populations;
+        self
+    }
 
-                // Store in database
-                info!("💾 Storing embeddings in database...");
-                let db_start = std::time::Instant::now();
-                let crate_id = database
-                    .upsert_crate(&crate_name, crate_version.as_deref())
-                    .await?;
-
-                // Initialize tokenizer for accurate token counting
-                let bpe =
-                    tiktoken_rs::cl100k_base().map_err(|e| ServerError::Tiktoken(e.to_string()))?;
-
-                // Prepare batch data
-                let mut batch_data = Vec::new();
-                for (path, content, embedding) in embeddings.iter() {
-                    let token_count = bpe.encode_with_special_tokens(content).len() as i32;
-                    batch_data.push((
-                        path.clone(),
-                        content.clone(),
-                        embedding.clone(),
-                        token_count,
-                    ));
-                }
+    fn with_generic_penalty(mut self, generic_penalty: f32) -> Self {
+        self.generic_penalty = generic_penalty;
+        self
+    }
 
-                database
-                    .insert_embeddings_batch(crate_id, &crate_name, &batch_data)
-                    .await?;
-                let db_time = db_start.elapsed();
-                let total_time = total_start.elapsed();
+    fn with_feedback_weight(mut self, feedback_weight: f32) -> Self {
+        self.feedback_weight = feedback_weight.clamp(0.0, search::MAX_FEEDBACK_WEIGHT);
+        self
+    }
 
-                info!(
-                    "🎉 Successfully populated crate {} with {} embeddings in {:.2}s total",
-                    crate_name,
-                    embeddings.len(),
-                    total_time.as_secs_f64()
-                );
+    fn with_orientation_match_threshold(mut self, threshold: f32) -> Self {
+        self.orientation_match_threshold = threshold;
+        self
+    }
 
-                Ok(json!({
-                    "documents_loaded": documents.len(),
-                    "embeddings_generated": embeddings.len(),
-                    "total_tokens": total_tokens,
-                    "content_size_kb": (total_content_size as f64 / 1024.0).round(),
-                    "version": crate_version,
-                    "timing": {
-                        "doc_loading_secs": doc_time.as_secs_f64(),
-                        "embedding_generation_secs": embedding_time.as_secs_f64(),
-                        "database_storage_secs": db_time.as_secs_f64(),
-                        "total_secs": total_time.as_secs_f64()
-                    }
-                }))
-            })
-        })
-        .await
-        .map_err(|e| ServerError::Internal(format!("Task join error: {e}")))?;
+    /// A text response as one content item, or several ordered parts if it is large
+    fn text_result(&self, text: &str) -> CallToolResult {
+        CallToolResult::success(
+            response::split_response(text, self.max_content_item_bytes)
+                .into_iter()
+                .map(Content::text)
+                .collect(),
+        )
+    }
 
-        result
+    fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
     }
-}
 
-#[derive(Deserialize, Serialize, JsonSchema)]
-struct QueryRustDocsArgs {
-    /// The crate to search in (e.g., "axum", "tokio", "serde")
-    crate_name: String,
-    /// The specific question about the crate's API or usage.
-    question: String,
-}
+    fn with_db_breaker(mut self, db_breaker: Arc<DbBreaker>) -> Self {
+        self.db_breaker = db_breaker;
+        self
+    }
 
-#[derive(Deserialize, Serialize, JsonSchema)]
-struct AddCrateArgs {
-    /// The crate name (e.g., 'tokio', 'serde')
-    crate_name: String,
-    /// Version specification: 'latest' or specific version (e.g., '1.35.0')
-    version_spec: String,
-    /// Optional features to enable (e.g., ['full', 'macros'])
-    #[serde(skip_serializing_if = "Option::is_none")]
-    features: Option<Vec<String>>,
-    /// Whether the crate is enabled (default: true)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    enabled: Option<bool>,
-    /// Expected number of documents (will be auto-detected if not provided)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    expected_docs: Option<i32>,
-}
+    fn with_admin_tools(mut self, admin_tools: bool) -> Self {
+        self.admin_tools = admin_tools;
+        self
+    }
 
-#[derive(Deserialize, Serialize, JsonSchema)]
-struct ListCratesArgs {
-    /// Only show enabled crates (default: false)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    enabled_only: Option<bool>,
-}
+    /// Refuse an admin tool unless the server was started with `--admin-tools`
+    fn ensure_admin(&self, tool: &str) -> Result<(), McpError> {
+        if self.admin_tools {
+            return Ok(());
+        }
+        warn!(
+            "🔒 Rejected admin tool {tool} (connection: {})",
+            self.connection_id
+        );
+        Err(McpError::invalid_request(
+            format!(
+                "{tool} is an admin tool and is disabled on this server. Start it with --admin-tools (MCPDOCS_ADMIN_TOOLS=true) to enable it."
+            ),
+            None,
+        ))
+    }
 
-#[derive(Deserialize, Serialize, JsonSchema)]
-struct CheckCrateStatusArgs {
-    /// The crate name to check status for
-    crate_name: String,
-}
+    /// Refuse a mutating tool when this server is a read-only replica
+    fn ensure_writable(&self, tool: &str) -> Result<(), McpError> {
+        if !self.read_only {
+            return Ok(());
+        }
+        warn!(
+            "🔒 Rejected {tool} on read-only replica (connection: {})",
+            self.connection_id
+        );
+        Err(McpError::invalid_request(
+            format!(
+                "{tool} is unavailable: this server is a read-only replica. Manage crates on the primary server."
+            ),
+            None,
+        ))
+    }
 
-#[derive(Deserialize, Serialize, JsonSchema)]
-struct RemoveCrateArgs {
-    /// The crate name to remove
-    crate_name: String,
-    /// Version specification (default: 'latest')
-    #[serde(skip_serializing_if = "Option::is_none")]
-    version_spec: Option<String>,
-}
+    /// Warning for query results when the crate's docs are old or behind its latest release
+    /// Record a `query_rust_docs` call in `query_log` under the configured policy; `returned`
+    /// is the result count and top similarity, `None` for cached answers and repeats
+    fn log_query(
+        &self,
+        crate_name: &str,
+        question: &str,
+        query_id: &str,
+        returned: Option<(usize, Option<f32>)>,
+        started: Instant,
+    ) {
+        let Some(policy) = self.query_log.policy else {
+            return;
+        };
+        if self.read_only {
+            return;
+        }
+        let stored = self.query_log.store(question);
+        let entry = QueryLogEntry {
+            query_id: query_id.to_string(),
+            crate_name: crate_name.to_string(),
+            policy: policy.as_str().to_string(),
+            question: stored.question,
+            question_hash: stored.question_hash,
+            result_count: returned.map(|(count, _)| count as i32),
+            top_similarity: returned.and_then(|(_, similarity)| similarity),
+            cached: returned.is_none(),
+            latency_ms: started.elapsed().as_millis() as i64,
+            created_at: chrono::Utc::now(),
+        };
+        let database = self.database.clone();
+        tokio::spawn(async move {
+            if let Err(e) = database.record_query_log(&entry).await {
+                warn!("⚠️  Failed to record query log entry: {e}");
+            }
+        });
+    }
 
-#[derive(Deserialize, Serialize, JsonSchema)]
-struct CrateSpec {
-    /// The crate name (e.g., 'tokio', 'serde')
-    crate_name: String,
-    /// Version specification: 'latest' or specific version (e.g., '1.35.0')
-    #[serde(default = "default_version_spec")]
-    version_spec: String,
-    /// Optional features to enable (e.g., ['full', 'macros'])
-    #[serde(skip_serializing_if = "Option::is_none")]
-    features: Option<Vec<String>>,
-    /// Whether the crate is enabled (default: true)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    enabled: Option<bool>,
-    /// Expected number of documents (will be auto-detected if not provided)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    expected_docs: Option<i32>,
-}
+    async fn staleness_note(&self, crate_name: &str) -> Option<String> {
+        if self.stale_after_days == 0 {
+            return None;
+        }
+        let configs = match self.database.get_crate_configs_by_name(crate_name).await {
+            Ok(configs) => configs,
+            Err(e) => {
+                warn!("⚠️  Failed to check freshness of {crate_name}: {e}");
+                return None;
+            }
+        };
+        let config = configs.first()?;
+        let populated_at = config.last_populated?;
 
-fn default_version_spec() -> String {
-    "latest".to_string()
-}
+        let latest_version = if config.version_spec == "latest" {
+            self.latest_versions.latest_version(crate_name).await
+        } else {
+            None
+        };
+        freshness::staleness_note(
+            populated_at,
+            chrono::Utc::now(),
+            self.stale_after_days,
+            config.current_version.as_deref(),
+            latest_version.as_deref(),
+        )
+    }
 
-#[derive(Deserialize, Serialize, JsonSchema)]
-struct AddCratesArgs {
-    /// List of crates to add/configure
-    crates: Vec<CrateSpec>,
-    /// Whether to fail fast on first error (default: false - best effort)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    fail_fast: Option<bool>,
-}
+    /// Drop cached query results for a crate whose documents changed
+    fn invalidate_query_cache(&self, crate_name: &str) {
+        let removed = self.query_cache.invalidate_crate(crate_name);
+        if removed > 0 {
+            info!("🗑️  Invalidated {removed} cached query results for {crate_name}");
+        }
+        if let Ok(mut centroids) = self.centroids.lock() {
+            centroids.retain(|(name, _), _| name != crate_name);
+        }
+    }
 
-#[derive(Deserialize, Serialize, JsonSchema)]
-struct CrateResult {
-    /// The crate name
-    crate_name: String,
-    /// Whether the crate was successfully configured
-    success: bool,
-    /// Error message if configuration failed
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
-    /// Status message
-    message: String,
-}
+    /// The crate's mean `dimension`-sized embedding, computed once and cached
+    async fn crate_centroid(
+        &self,
+        crate_name: &str,
+        dimension: usize,
+    ) -> Result<Option<Arc<Vec<f32>>>, ServerError> {
+        let key = (crate_name.to_string(), dimension);
+        if let Some(centroid) = self
+            .centroids
+            .lock()
+            .ok()
+            .and_then(|c| c.get(&key).cloned())
+        {
+            return Ok(Some(centroid));
+        }
+        let started = Instant::now();
+        let Some(centroid) = self
+            .database
+            .get_crate_centroid(crate_name, dimension)
+            .await?
+        else {
+            return Ok(None);
+        };
+        info!(
+            "📐 Computed {dimension}-dimension centroid of {crate_name} in {:?}",
+            started.elapsed()
+        );
+        let centroid = Arc::new(centroid);
+        if let Ok(mut centroids) = self.centroids.lock() {
+            centroids.insert(key, centroid.clone());
+        }
+        Ok(Some(centroid))
+    }
 
-#[derive(Deserialize, Serialize, JsonSchema)]
-struct AddCratesResponse {
-    /// Results for each crate
+    /// Centroids of `crate_names` at `dimension`, computing the uncached ones in one query
+    async fn crate_centroids(
+        &self,
+        crate_names: &[String],
+        dimension: usize,
+    ) -> Result<HashMap<String, Arc<Vec<f32>>>, ServerError> {
+        let mut centroids = HashMap::new();
+        if let Ok(cache) = self.centroids.lock() {
+            for crate_name in crate_names {
+                if let Some(centroid) = cache.get(&(crate_name.clone(), dimension)) {
+                    centroids.insert(crate_name.clone(), centroid.clone());
+                }
+            }
+        }
+        let missing: Vec<String> = crate_names
+            .iter()
+            .filter(|name| !centroids.contains_key(*name))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return Ok(centroids);
+        }
+        let started = Instant::now();
+        let computed = self
+            .database
+            .get_crate_centroids(&missing, dimension)
+            .await?;
+        info!(
+            "📐 Computed {dimension}-dimension centroids of {} crates in {:?}",
+            computed.len(),
+            started.elapsed()
+        );
+        let mut cache = self.centroids.lock().ok();
+        for (crate_name, centroid) in computed {
+            let centroid = Arc::new(centroid);
+            if let Some(cache) = cache.as_mut() {
+                cache.insert((crate_name.clone(), dimension), centroid.clone());
+            }
+            centroids.insert(crate_name, centroid);
+        }
+        Ok(centroids)
+    }
+
+    /// Rerank results with the generic-boilerplate penalty, if it is enabled
+    ///
+    /// Failing to score against the centroid only loses the reranking, never the query.
+    async fn penalize_generic(
+        &self,
+        crate_name: &str,
+        dimension: usize,
+        results: Vec<ScoredDoc>,
+    ) -> Vec<ScoredDoc> {
+        if self.generic_penalty <= 0.0 || results.is_empty() {
+            return results;
+        }
+        let doc_paths: Vec<String> = results.iter().map(|d| d.doc_path.clone()).collect();
+        let commonness = match self.crate_centroid(crate_name, dimension).await {
+            Ok(Some(centroid)) => {
+                self.database
+                    .centroid_similarities(crate_name, &centroid, &doc_paths)
+                    .await
+            }
+            Ok(None) => return results,
+            Err(e) => Err(e),
+        };
+        match commonness {
+            Ok(commonness) => search::penalize_generic(results, &commonness, self.generic_penalty),
+            Err(e) => {
+                warn!("⚠️  Failed to apply generic penalty for {crate_name}: {e}");
+                results
+            }
+        }
+    }
+
+    /// The stored hits of the orientation question closest to `embedding`, if it is close
+    /// enough and was embedded with `model`
+    ///
+    /// Every lookup counts towards the hit rate in `get_server_stats`; failing to load
+    /// answers only costs the shortcut.
+    async fn precomputed_answer(
+        &self,
+        crate_name: &str,
+        model: &str,
+        embedding: &[f32],
+    ) -> Option<Vec<ScoredDoc>> {
+        if self.orientation_match_threshold > 1.0 {
+            return None;
+        }
+        let answers = match self
+            .database
+            .get_precomputed_answers(crate_name, model)
+            .await
+        {
+            Ok(answers) => answers,
+            Err(e) => {
+                warn!("⚠️  Failed to load precomputed answers for {crate_name}: {e}");
+                return None;
+            }
+        };
+        let results = match orientation::best_match(
+            embedding,
+            &answers,
+            self.orientation_match_threshold,
+        ) {
+            Some((answer, similarity)) => {
+                match self
+                    .database
+                    .get_precomputed_results(crate_name, answer)
+                    .await
+                {
+                    Ok(results) if !results.is_empty() => {
+                        info!(
+                            "🧭 Served {crate_name} question from the precomputed '{}' answer (similarity {similarity:.3})",
+                            answer.question_key
+                        );
+                        Some(results)
+                    }
+                    Ok(_) => None,
+                    Err(e) => {
+                        warn!("⚠️  Failed to load precomputed results for {crate_name}: {e}");
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        self.orientation_stats.record(results.is_some());
+        results
+    }
+
+    /// Rerank results by their retrieval feedback prior, if feedback weighting is enabled
+    ///
+    /// Priors are cached per crate for [`FEEDBACK_PRIOR_TTL`]; failing to load them only
+    /// loses the reranking, never the query.
+    async fn apply_feedback(&self, crate_name: &str, results: Vec<ScoredDoc>) -> Vec<ScoredDoc> {
+        if self.feedback_weight <= 0.0 || results.is_empty() {
+            return results;
+        }
+        let cached = self.feedback_priors.lock().ok().and_then(|cache| {
+            cache
+                .get(crate_name)
+                .filter(|(computed, _)| computed.elapsed() < FEEDBACK_PRIOR_TTL)
+                .map(|(_, priors)| priors.clone())
+        });
+        let priors = match cached {
+            Some(priors) => priors,
+            None => match self.database.get_feedback_counts(crate_name).await {
+                Ok(counts) => {
+                    let priors: HashMap<String, f32> = counts
+                        .into_iter()
+                        .map(|(doc_path, (useful, not_useful))| {
+                            (doc_path, search::feedback_prior(useful, not_useful))
+                        })
+                        .collect();
+                    let priors = Arc::new(priors);
+                    if let Ok(mut cache) = self.feedback_priors.lock() {
+                        cache.insert(crate_name.to_string(), (Instant::now(), priors.clone()));
+                    }
+                    priors
+                }
+                Err(e) => {
+                    warn!("⚠️  Failed to load retrieval feedback for {crate_name}: {e}");
+                    return results;
+                }
+            },
+        };
+        search::apply_feedback(results, &priors, self.feedback_weight)
+    }
+
+    /// Leave out results tagged with a language the crate doesn't keep
+    ///
+    /// Only crates with `language_action` `tag` store languages; failing to look them up
+    /// keeps every result rather than failing the query.
+    async fn filter_languages(
+        &self,
+        crate_name: &str,
+        languages: Option<&LanguageFilter>,
+        results: Vec<ScoredDoc>,
+    ) -> Vec<ScoredDoc> {
+        let Some(filter) = languages.filter(|f| f.action() == LanguageAction::Tag) else {
+            return results;
+        };
+        if results.is_empty() {
+            return results;
+        }
+        let doc_paths: Vec<String> = results.iter().map(|d| d.doc_path.clone()).collect();
+        match self
+            .database
+            .get_document_languages(crate_name, &doc_paths)
+            .await
+        {
+            Ok(tagged) => {
+                let (results, dropped) =
+                    search::filter_languages(results, &tagged, &filter.codes());
+                if dropped > 0 {
+                    info!("🌐 Left out {dropped} results for {crate_name} in languages other than {:?}", filter.codes());
+                }
+                results
+            }
+            Err(e) => {
+                warn!("⚠️  Failed to filter result languages for {crate_name}: {e}");
+                results
+            }
+        }
+    }
+
+    /// Handler serving `profile` from `database`: its own crates and caches, and its own
+    /// embedding provider if given, sharing only rate limits and population slots
+    fn for_profile(
+        &self,
+        profile: &Profile,
+        database: Database,
+        document_provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+        query_cache: QueryCache,
+        repeat_question_window: Duration,
+    ) -> Self {
+        let mut handler = self.clone();
+        handler.database = database;
+        handler.available_crates = Default::default();
+        handler.startup_message = format!(
+            "HTTP SSE MCP server for the '{}' documentation profile; list_crates shows its crates. {}",
+            profile.name,
+            self.query_defaults.describe(search::DEFAULT_RESULT_LIMIT)
+        );
+        handler.profile = Some(Arc::from(profile.name.as_str()));
+        // A QUERY_EMBEDDING_MODEL only pairs with the server's own document model
+        if document_provider.is_some() {
+            handler.query_provider = None;
+        }
+        handler.document_provider = document_provider;
+        handler.populations = Arc::new(self.populations.sharing_slots());
+        handler.centroids = Default::default();
+        handler.feedback_priors = Default::default();
+        handler.orientation_stats = Default::default();
+        handler.refresh_status = Default::default();
+        handler
+            .with_query_cache(query_cache)
+            .with_repeat_question_window(repeat_question_window)
+    }
+
+    /// The provider this handler's documents are embedded with
+    fn document_provider(&self) -> Result<Arc<dyn EmbeddingProvider + Send + Sync>, ServerError> {
+        match &self.document_provider {
+            Some(provider) => Ok(provider.clone()),
+            None => embeddings::embedding_provider(),
+        }
+    }
+
+    /// Clone of this handler bound to a specific connection (shares all caches)
+    fn for_connection(&self, connection_id: &str, disconnected: CancellationToken) -> Self {
+        let mut handler = self.clone();
+        handler.connection_id = Arc::from(connection_id);
+        handler.disconnected = Some(disconnected);
+        handler
+    }
+
+    /// Enforce the per-connection rate limit for a tool bucket
+    fn check_rate_limit(&self, bucket: &'static str, limit: u32) -> Result<(), McpError> {
+        self.rate_limiter
+            .check(&self.connection_id, bucket, limit)
+            .map_err(|retry_after| {
+                let retry_after_secs = retry_after.as_secs().max(1);
+                warn!(
+                    "🚦 Rate limit exceeded for {bucket} (connection: {}, limit: {limit}/min)",
+                    self.connection_id
+                );
+                McpError::invalid_request(
+                    format!(
+                        "Rate limit exceeded for {bucket}: at most {limit} calls per minute. Retry in {retry_after_secs}s."
+                    ),
+                    Some(serde_json::json!({
+                        "tool": bucket,
+                        "limit_per_minute": limit,
+                        "retry_after_secs": retry_after_secs
+                    })),
+                )
+            })
+    }
+
+    /// Refresh the available crates cache from the database, leaving out disabled crates
+    async fn refresh_available_crates(&self) -> Result<(), ServerError> {
+        let all_crates = self.database.get_all_crates_with_embeddings().await?;
+        let disabled: std::collections::HashSet<String> = self
+            .database
+            .get_disabled_crate_names()
+            .await?
+            .into_iter()
+            .collect();
+        let mut crates = self.available_crates.write().await;
+        crates.clear();
+        crates.extend(
+            all_crates
+                .into_iter()
+                .filter(|name| !disabled.contains(name)),
+        );
+        Ok(())
+    }
+
+    /// Add a crate to the available crates cache
+    async fn add_crate_to_available(&self, crate_name: &str) {
+        let mut crates = self.available_crates.write().await;
+        crates.insert(crate_name.to_string());
+    }
+
+    /// Check if a crate is available (fast in-memory lookup)
+    async fn is_crate_available(&self, crate_name: &str) -> bool {
+        let crates = self.available_crates.read().await;
+        crates.contains(crate_name)
+    }
+
+    /// `invalid_params` error for a crate that can't be queried, saying whether it is
+    /// queued, populating, failed or unknown, and what to do about it
+    async fn crate_unavailable(&self, crate_name: &str) -> McpError {
+        let status = match self
+            .database
+            .get_crate_status(crate_name, self.stale_after_days)
+            .await
+        {
+            Ok(status) => status,
+            Err(e) => {
+                warn!("⚠️  Failed to get status of {crate_name}: {e}");
+                return McpError::invalid_params(
+                    format!("Crate '{crate_name}' not available"),
+                    Some(serde_json::json!({ "crate_name": crate_name })),
+                );
+            }
+        };
+        let message = match &status {
+            // Stored docs this server doesn't serve, e.g. a disabled crate
+            CrateStatus::Populated { .. }
+            | CrateStatus::Partial { .. }
+            | CrateStatus::Stale { .. } => format!(
+                "Crate '{crate_name}' has {} documents but is not enabled on this server; enable it with set_crate_enabled",
+                status.docs()
+            ),
+            _ => status.describe(crate_name),
+        };
+        McpError::invalid_params(
+            message,
+            Some(serde_json::json!({ "crate_name": crate_name, "status": status })),
+        )
+    }
+
+    /// Remove a crate from the available crates cache
+    async fn remove_crate_from_available(&self, crate_name: &str) {
+        let mut crates = self.available_crates.write().await;
+        crates.remove(crate_name);
+    }
+
+    fn _create_resource_text(&self, uri: &str, name: &str) -> Resource {
+        RawResource::new(uri, name.to_string()).no_annotation()
+    }
+
+    /// The `version_spec` of a crate to crawl resolved to a version (`None` = docs.rs's
+    /// `latest`), as a population would
+    async fn resolve_version(
+        &self,
+        crate_name: &str,
+        version_spec: &str,
+        allow_prerelease: bool,
+    ) -> Result<Option<String>, McpError> {
+        pipeline::resolve_version_spec(crate_name, version_spec, allow_prerelease, &self.crates_io)
+            .await
+            .map_err(|e| match e {
+                ServerError::Config(message) => McpError::invalid_params(message, None),
+                e => McpError::internal_error(e.to_string(), None),
+            })
+    }
+
+    /// Estimate populating `crate_name` as `crawl_options` would crawl it, for
+    /// `estimate_population` and `add_crate`'s `estimate_only`
+    async fn estimate_response(
+        &self,
+        crate_name: &str,
+        features: &[String],
+        crawl_options: doc_loader::CrawlOptions,
+        max_pages: usize,
+        sample_pages: Option<usize>,
+    ) -> Result<CallToolResult, McpError> {
+        let provider = self
+            .document_provider()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let pipeline_options = pipeline::PipelineOptions::from_env()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let settings = EstimateSettings {
+            sample_pages: sample_pages.unwrap_or(estimate::DEFAULT_SAMPLE_PAGES),
+            max_pages,
+            model: provider.get_model_name().to_string(),
+            embed_concurrency: pipeline_options.embed_concurrency,
+            page_delay: doc_loader::PAGE_DELAY,
+        };
+        let crawl_options = doc_loader::CrawlOptions {
+            ignore_robots: self.ignore_robots,
+            ..crawl_options
+        };
+        let estimate = estimate::estimate_population(crate_name, &crawl_options, &settings)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(
+                    format!("Failed to estimate the population of {crate_name}: {e}"),
+                    Some(serde_json::json!({ "crate_name": crate_name })),
+                )
+            })?;
+        let response = serde_json::json!({
+            "crate_name": crate_name,
+            "features": features,
+            "target": crawl_options.target,
+            "provider": provider.get_provider_name(),
+            "population_slots_free": self.populations.slots.available_permits(),
+            "estimate": estimate,
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    /// Populate `config` in the background, making the crate queryable once it succeeds
+    ///
+    /// Returns whether a population slot was free; otherwise the population is queued
+    /// until one is.
+    fn spawn_population(&self, config: CrateConfig, job_id: Option<i32>) -> bool {
+        let handler = self.clone();
+        // Reserved now, so callers spawning many populations in a row see the real count
+        let slot = self.populations.slots.clone().try_acquire_owned().ok();
+        let started = slot.is_some();
+        tokio::spawn(async move {
+            let crate_name = config.name.clone();
+            match handler.populate_crate(&config, job_id, slot).await {
+                Ok(_) => {
+                    // Add the crate to the in-memory cache after successful population
+                    handler.add_crate_to_available(&crate_name).await;
+                    eprintln!("✅ Background population completed for crate: {crate_name}");
+                }
+                Err(e) => {
+                    eprintln!("⚠️  Background population failed for crate {crate_name}: {e}");
+                }
+            }
+        });
+        started
+    }
+
+    /// Crawl, embed and store a crate, tracking progress on `job_id` when given
+    ///
+    /// Without a reserved `slot`, waits for one of the population slots first.
+    async fn populate_crate(
+        &self,
+        config: &CrateConfig,
+        job_id: Option<i32>,
+        slot: Option<tokio::sync::OwnedSemaphorePermit>,
+    ) -> Result<serde_json::Value, ServerError> {
+        use serde_json::json;
+
+        info!(
+            "🚀 Starting automatic population for crate: {}",
+            config.name
+        );
+        let crate_name = config.name.clone();
+        let crawl_options = doc_loader::CrawlOptions {
+            ignore_robots: self.ignore_robots,
+            keep_mojibake: self.keep_mojibake,
+            ..config.crawl_options()
+        };
+        let database = self.database.clone();
+
+        // The job stays pending while all population slots are busy
+        let active = ActivePopulation::start(&self.populations, config.id, job_id);
+        let progress = active.progress.clone();
+        let _slot = match slot {
+            Some(slot) => slot,
+            None => {
+                if self.populations.slots.available_permits() == 0 {
+                    info!(
+                        "⏳ {crate_name} is waiting for one of {} population slots",
+                        self.populations.limit
+                    );
+                }
+                self.populations
+                    .slots
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .map_err(|e| {
+                        ServerError::Internal(format!("Population slots were closed: {e}"))
+                    })?
+            }
+        };
+
+        if let Some(job_id) = job_id {
+            if let Err(e) = database
+                .update_population_job(job_id, "running", None, None)
+                .await
+            {
+                warn!("⚠️  Failed to mark population job {job_id} running: {e}");
+            }
+        }
+
+        // Run population in a blocking task to handle non-Send scraper types
+        // Use a dedicated thread pool to avoid blocking the main runtime
+        let populated = config.clone();
+        let provider = self.document_provider();
+        let result = tokio::task::spawn_blocking(move || {
+            tokio::runtime::Handle::current().block_on(async {
+                let provider = provider?;
+                let report = pipeline::populate_config(
+                    &database,
+                    provider,
+                    &populated,
+                    &crawl_options,
+                    job_id,
+                    Some(progress),
+                )
+                .await?;
+                let stats = &report.stats;
+                Ok::<_, ServerError>(json!({
+                    "partial": stats.timed_out,
+                    "documents_loaded": stats.documents,
+                    "embeddings_generated": stats.stored_chunks,
+                    "chunks_already_stored": stats.skipped_chunks,
+                    "documents_too_short": stats.short_documents,
+                    "total_tokens": stats.total_tokens,
+                    "content_size_kb": (stats.content_bytes as f64 / 1024.0).round(),
+                    "version": report.version,
+                    "reexport_pages": stats.crawl.reexport_pages,
+                    "diagnostics": stats.crawl.diagnostics,
+                    "timing": {
+                        "doc_loading_secs": stats.crawl_time.as_secs_f64(),
+                        "total_secs": report.total_time.as_secs_f64()
+                    }
+                }))
+            })
+        })
+        .await
+        .map_err(|e| ServerError::Internal(format!("Task join error: {e}")))?;
+
+        if result.is_ok() {
+            self.invalidate_query_cache(&crate_name);
+        }
+        if let Some(job_id) = job_id {
+            let update = match &result {
+                Ok(stats) => {
+                    let docs = stats["embeddings_generated"].as_i64().unwrap_or(0) as i32;
+                    let status = if stats["partial"] == true {
+                        "partial"
+                    } else {
+                        "completed"
+                    };
+                    self.database
+                        .update_population_job(job_id, status, None, Some(docs))
+                        .await
+                }
+                Err(e) => {
+                    self.database
+                        .update_population_job(job_id, "failed", Some(&e.to_string()), None)
+                        .await
+                }
+            };
+            if let Err(e) = update {
+                warn!("⚠️  Failed to update population job {job_id}: {e}");
+            }
+        }
+        result
+    }
+}
+
+impl McpHandler {
+    /// Look up the docs.rs version of every configuration and queue populations for the
+    /// `latest` crates whose version changed; slots and the population limit still apply
+    async fn refresh_crate_versions(&self) -> Result<Vec<refresh::CrateDecision>, ServerError> {
+        let configs = self
+            .db_breaker
+            .call(self.database.get_crate_configs(false))
+            .await?;
+        let mut decisions = Vec::with_capacity(configs.len());
+        for config in configs {
+            let decision = match refresh::skip_reason(&config) {
+                Some(reason) => Decision::Skipped {
+                    reason: reason.to_string(),
+                },
+                None if self.populations.is_active(config.id) => Decision::Skipped {
+                    reason: "population in progress".to_string(),
+                },
+                None => {
+                    let options = doc_loader::CrawlOptions {
+                        ignore_robots: self.ignore_robots,
+                        ..config.crawl_options()
+                    };
+                    let latest = doc_loader::fetch_latest_version(&config.name, &options).await;
+                    tokio::time::sleep(VERSION_CHECK_DELAY).await;
+                    match latest {
+                        Ok(version) => refresh::decide(&config, version.as_deref()),
+                        Err(e) => {
+                            warn!(
+                                "⚠️  Failed to look up the docs.rs version of {}: {e}",
+                                config.name
+                            );
+                            Decision::Skipped {
+                                reason: format!("docs.rs lookup failed: {e}"),
+                            }
+                        }
+                    }
+                }
+            };
+
+            let mut job_id = None;
+            match &decision {
+                Decision::Updated { from, to } => {
+                    info!(
+                        "🔁 {} moved from {} to {to} on docs.rs; re-populating",
+                        config.name,
+                        from.as_deref().unwrap_or("an unknown version")
+                    );
+                    job_id = self.database.create_population_job(config.id).await.ok();
+                }
+                Decision::Unchanged { version } => {
+                    info!("✅ {} is still at {version} on docs.rs", config.name);
+                }
+                Decision::Skipped { .. } => {}
+            }
+            if matches!(
+                decision,
+                Decision::Updated { .. } | Decision::Unchanged { .. }
+            ) {
+                if let Err(e) = self.database.mark_crate_checked(config.id).await {
+                    warn!("⚠️  Failed to record version check of {}: {e}", config.name);
+                }
+            }
+            decisions.push(refresh::CrateDecision {
+                crate_name: config.name.clone(),
+                target: config.target.clone(),
+                decision: decision.clone(),
+                job_id,
+            });
+            if matches!(decision, Decision::Updated { .. }) {
+                self.spawn_population(config, job_id);
+            }
+        }
+        Ok(decisions)
+    }
+
+    /// One scheduled version refresh, recorded for `refresh_status`
+    async fn run_version_refresh(&self, period: Duration) {
+        let started_at = chrono::Utc::now();
+        let decisions = match self.refresh_crate_versions().await {
+            Ok(decisions) => decisions,
+            Err(e) => {
+                warn!("⚠️  Version refresh failed: {e}");
+                return;
+            }
+        };
+        let next_run_at = chrono::Duration::from_std(period)
+            .ok()
+            .map(|period| started_at + period);
+        let mut status = self.refresh_status.lock().unwrap();
+        status.record_run(started_at, decisions, next_run_at);
+        info!(
+            "🔁 Version refresh complete: {} checked, {} updated, {} skipped",
+            status.checked, status.updated, status.skipped
+        );
+    }
+}
+
+/// Navigation hints returned when a query sets `suggest_related`
+const MAX_RELATED_ITEMS: usize = 10;
+
+/// Documents `find_symbol` returns unless the caller asks for another number
+const DEFAULT_SYMBOL_LIMIT: usize = 10;
+
+/// Lines shown before and after the line containing the identifier
+const SYMBOL_CONTEXT_LINES: usize = 2;
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct CrateResult {
+    /// The crate name
+    crate_name: String,
+    /// Whether the crate was successfully configured
+    success: bool,
+    /// Error message if configuration failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Population job to follow with job_status, if one was created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job_id: Option<i32>,
+    /// Status message
+    message: String,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct AddCratesResponse {
+    /// Results for each crate
     results: Vec<CrateResult>,
     /// Summary statistics
     summary: AddCratesSummary,
@@ -478,12 +1648,15 @@ struct AddCratesSummary {
     successful: usize,
     /// Number of failed configurations
     failed: usize,
-    /// Number of background ingestion tasks started
+    /// Number of background ingestion tasks that took a free population slot
     ingestion_started: usize,
+    /// Number of background ingestion tasks waiting for a population slot
+    ingestion_queued: usize,
+    /// Populations that run at once, across all tools (`MAX_CONCURRENT_POPULATIONS`)
+    max_concurrent_populations: usize,
 }
 
 // Implement ServerHandler trait with correct signatures
-#[tool(tool_box)]
 impl ServerHandler for McpHandler {
     fn get_info(&self) -> ServerInfo {
         let capabilities = ServerCapabilities::builder()
@@ -502,6 +1675,37 @@ impl ServerHandler for McpHandler {
         }
     }
 
+    async fn list_tools(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult {
+            next_cursor: None,
+            tools: Self::tool_box().list(),
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let tool_name = request.name.clone();
+        let disconnected = self
+            .disconnected
+            .as_ref()
+            .filter(|_| !WRITE_TOOLS.contains(&tool_name.as_ref()));
+        let context = ToolCallContext::new(self, request, context);
+        request_context::dispatch(
+            &self.connection_id,
+            &tool_name,
+            disconnected,
+            Self::tool_box().call(context),
+        )
+        .await
+    }
+
     async fn list_resources(
         &self,
         _request: PaginatedRequestParam,
@@ -547,330 +1751,2253 @@ impl ServerHandler for McpHandler {
         ))
     }
 
-    async fn list_resource_templates(
-        &self,
-        _request: PaginatedRequestParam,
-        _context: RequestContext<RoleServer>,
-    ) -> Result<ListResourceTemplatesResult, McpError> {
-        Ok(ListResourceTemplatesResult {
-            resource_templates: vec![],
-            next_cursor: None,
-        })
+    async fn list_resource_templates(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        Ok(ListResourceTemplatesResult {
+            resource_templates: vec![],
+            next_cursor: None,
+        })
+    }
+}
+
+// Tool implementation
+#[tool(tool_box)]
+impl McpHandler {
+    #[tool(
+        description = "Query documentation for a specific Rust crate using semantic search and LLM summarization."
+    )]
+    async fn query_rust_docs(
+        &self,
+        #[tool(aggr)] args: QueryRustDocsArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        self.check_rate_limit("query_rust_docs", self.rate_limits.query_per_minute)?;
+        let started = Instant::now();
+        let include_provenance = args.include_provenance.unwrap_or(false);
+        let best_only = args.best_only.unwrap_or(false);
+        let query_id = search::new_query_id();
+
+        // Check if crate is available (fast in-memory lookup)
+        if !self.is_crate_available(&args.crate_name).await {
+            let mut error = self.crate_unavailable(&args.crate_name).await;
+            let crates = self.available_crates.read().await;
+            let available_list: Vec<String> = crates.iter().cloned().collect();
+            error.message = format!(
+                "{}. Available crates: {}",
+                error.message,
+                available_list.join(", ")
+            )
+            .into();
+            return Err(error);
+        }
+
+        // Generate embedding for the question
+        let document_client = self
+            .document_provider()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let embedding_client = self.query_provider.as_ref().unwrap_or(&document_client);
+
+        // The crate's configured hint and defaults fill in whatever the caller left unset
+        let configs = self
+            .database
+            .get_crate_configs_by_name(&args.crate_name)
+            .await
+            .unwrap_or_else(|e| {
+                warn!(
+                    "⚠️  Failed to get query defaults for {}: {e}",
+                    args.crate_name
+                );
+                Vec::new()
+            });
+        let query_hint = configs.iter().find_map(|c| c.query_hint.clone());
+        let low_doc_coverage = configs.iter().any(|c| c.low_doc_coverage);
+        let languages = configs
+            .iter()
+            .find_map(|c| LanguageFilter::for_crate(c).ok().flatten());
+        let defaults = configs
+            .iter()
+            .fold(search::SearchParams::default(), |defaults, config| {
+                defaults.or(config.search_defaults())
+            });
+        let params = search::SearchParams {
+            limit: args.limit.map(|n| n as usize),
+            min_similarity: args.min_similarity,
+            path_prefix: args.path_prefix.clone(),
+        }
+        .or(defaults)
+        .or(self.query_defaults.search_params());
+        let result_limit = if best_only { 1 } else { params.result_limit() };
+        let max_response_tokens = self
+            .query_defaults
+            .max_response_tokens(args.max_response_tokens);
+        let min_tokens = args.min_tokens.unwrap_or(0);
+        let dedup = args.dedup.unwrap_or(true);
+        // The generic penalty reorders results, so give specific matches room to move up;
+        // the same room backfills results left out for their language
+        let filters_languages = languages
+            .as_ref()
+            .is_some_and(|f| f.action() == LanguageAction::Tag);
+        let fetch_limit = params.fetch_limit(
+            dedup,
+            self.generic_penalty > 0.0
+                || self.feedback_weight > 0.0
+                || filters_languages
+                || min_tokens > 0,
+        );
+
+        // Identical questions with the same output options skip embedding and search
+        let cache_key = QueryCacheKey::new(
+            &args.crate_name,
+            &args.question,
+            embedding_client.get_model_name(),
+            &format!(
+                "debug={};related={};expand={};hyde={};dedup={dedup};best_only={best_only};limit={result_limit};min={:?};min_tokens={min_tokens};budget={max_response_tokens:?};prefix={:?};section={:?};hint={:?}",
+                args.debug.unwrap_or(false),
+                args.suggest_related.unwrap_or(false),
+                args.expand_query.unwrap_or(false),
+                args.hyde.unwrap_or(false),
+                params.min_similarity,
+                params.path_prefix(),
+                args.section,
+                query_hint
+            ),
+        );
+        // A cached answer has no record of the search behind it
+        if !include_provenance {
+            // An agent re-asking in a loop gets told so instead of silently identical results
+            if let Some((response, age)) = self
+                .recent_questions
+                .repeat_of(&self.connection_id, &cache_key)
+            {
+                info!(
+                    "🔁 Repeat question for {} on {} (first asked {age:?} ago)",
+                    args.crate_name, self.connection_id
+                );
+                let response = format!("{response}\n\n{}", query_cache::repeat_note(age));
+                self.log_query(&args.crate_name, &args.question, &query_id, None, started);
+                return Ok(self.text_result(&search::with_query_id(&response, &query_id)));
+            }
+            if let Some(response) = self.query_cache.get(&cache_key) {
+                info!("⚡ Query cache hit for {}", args.crate_name);
+                self.recent_questions.record(
+                    &self.connection_id,
+                    cache_key.clone(),
+                    response.clone(),
+                );
+                self.log_query(&args.crate_name, &args.question, &query_id, None, started);
+                return Ok(self.text_result(&search::with_query_id(&response, &query_id)));
+            }
+        }
+        let mut timings = provenance::StageTimings::default();
+        let stage_started = Instant::now();
+
+        // Compound questions are searched part by part so each half finds its own docs
+        let queries = if args.expand_query.unwrap_or(false) {
+            search::split_question(&args.question)
+        } else {
+            vec![args.question.clone()]
+        };
+        if args.expand_query.unwrap_or(false) {
+            info!(
+                "🔀 Query for {} expanded into {} sub-queries",
+                args.crate_name,
+                queries.len()
+            );
+        }
+        // HyDE drafts one hypothetical answer to the whole question and searches with it too
+        let queries = match (&self.hyde, args.hyde.unwrap_or(false)) {
+            (Some(hyde), true) => {
+                let started = Instant::now();
+                let expanded = hyde.expand(&args.crate_name, &args.question, queries).await;
+                info!(
+                    "💭 HyDE for {} with {} took {:?}",
+                    args.crate_name,
+                    hyde.model(),
+                    started.elapsed()
+                );
+                expanded
+            }
+            (None, true) => {
+                warn!("⚠️  HyDE requested but no LLM client is configured; searching plainly");
+                queries
+            }
+            (_, false) => queries,
+        };
+        let queries: Vec<String> = queries
+            .iter()
+            .map(|query| search::apply_query_hint(query, query_hint.as_deref()))
+            .collect();
+        timings.query_expansion = provenance::millis(stage_started.elapsed());
+
+        // Fallback providers only apply to crates stored with their model
+        let crate_model = if self.fallback_providers.is_empty() {
+            None
+        } else {
+            self.database
+                .get_crate_embedding_model(&args.crate_name)
+                .await
+                .unwrap_or_else(|e| {
+                    warn!(
+                        "⚠️  Failed to look up embedding model of {}: {e}",
+                        args.crate_name
+                    );
+                    None
+                })
+        };
+        // The query model stands in for the document model it was configured alongside
+        let crate_model = match crate_model {
+            Some(model) if model == document_client.get_model_name() => {
+                Some(embedding_client.get_model_name().to_string())
+            }
+            model => model,
+        };
+        let stage_started = Instant::now();
+        let (question_embeddings, model) = embeddings::embed_with_fallback(
+            embedding_client,
+            &self.fallback_providers,
+            crate_model.as_deref(),
+            &queries,
+        )
+        .await
+        .map_err(|e| {
+            McpError::internal_error(format!("Failed to generate embedding: {e}"), None)
+        })?;
+        let used_fallback = model != embedding_client.get_model_name();
+        timings.embedding = provenance::millis(stage_started.elapsed());
+
+        // Perform semantic search using the embedding(s)
+        let crate_name = &args.crate_name;
+        let path_prefix = params.path_prefix();
+        let section = args.section.as_deref();
+        let dimension = question_embeddings.first().map_or(0, Vec::len);
+        // A plain orientation question ("how do I get started?") reuses the search run for
+        // it at population time; a provenance record describes a real search
+        let precomputed = match question_embeddings.as_slice() {
+            [embedding]
+                if path_prefix.is_none()
+                    && section.is_none()
+                    && !include_provenance
+                    && fetch_limit <= orientation::PRECOMPUTED_RESULTS =>
+            {
+                self.precomputed_answer(crate_name, &model, embedding).await
+            }
+            _ => None,
+        };
+        let searches = question_embeddings.into_iter().map(|embedding| {
+            let embedding = Array1::from_vec(embedding);
+            async move {
+                self.database
+                    .search_similar_docs_tuned(
+                        crate_name,
+                        &embedding,
+                        fetch_limit as i32,
+                        None,
+                        path_prefix,
+                        section,
+                    )
+                    .await
+            }
+        });
+        let stage_started = Instant::now();
+        let mut trail = include_provenance.then(provenance::CandidateTrail::default);
+        let result_sets = match precomputed {
+            Some(results) => Ok(vec![results]),
+            None => futures::future::try_join_all(searches).await,
+        };
+        let results = match result_sets {
+            Ok(result_sets) => {
+                timings.search = provenance::millis(stage_started.elapsed());
+                let stage_started = Instant::now();
+                trail
+                    .iter_mut()
+                    .for_each(|t| t.consider(result_sets.iter().flatten()));
+                let merged = search::merge_results(result_sets, fetch_limit);
+                trail
+                    .iter_mut()
+                    .for_each(|t| t.left_out(provenance::Outcome::NotInTop, &merged));
+                let results = params.filter(merged);
+                trail
+                    .iter_mut()
+                    .for_each(|t| t.left_out(provenance::Outcome::BelowMinSimilarity, &results));
+                let results = search::drop_short(results, min_tokens);
+                trail
+                    .iter_mut()
+                    .for_each(|t| t.left_out(provenance::Outcome::BelowMinTokens, &results));
+                let results = self
+                    .filter_languages(crate_name, languages.as_ref(), results)
+                    .await;
+                trail
+                    .iter_mut()
+                    .for_each(|t| t.left_out(provenance::Outcome::OtherLanguage, &results));
+                let results = self.penalize_generic(crate_name, dimension, results).await;
+                let mut results = self.apply_feedback(crate_name, results).await;
+                if dedup {
+                    let dropped;
+                    (results, dropped) = search::dedup_by_content(results);
+                    if dropped > 0 {
+                        info!("🧬 Dropped {dropped} duplicate results for {crate_name}");
+                    }
+                    trail
+                        .iter_mut()
+                        .for_each(|t| t.left_out(provenance::Outcome::Duplicate, &results));
+                }
+                timings.ranking = provenance::millis(stage_started.elapsed());
+                Ok(results)
+            }
+            Err(e) => Err(e),
+        };
+        // Everything needed for the record, taken before the results are formatted away
+        let provenance = trail.map(|trail| {
+            let returned = results.as_ref().map_or(&[][..], |results| {
+                &results[..result_limit.min(results.len())]
+            });
+            let (candidates, crate_versions) = trail.finish(returned);
+            provenance::Provenance {
+                schema_version: provenance::SCHEMA_VERSION,
+                crate_name: crate_name.clone(),
+                crate_versions,
+                embedding: provenance::EmbeddingProvenance {
+                    model: model.clone(),
+                    dimension,
+                    fallback: used_fallback,
+                    queries: queries.clone(),
+                },
+                strategy: provenance::StrategyProvenance {
+                    retrieval: "vector",
+                    expand_query: args.expand_query.unwrap_or(false),
+                    hyde: args.hyde.unwrap_or(false) && self.hyde.is_some(),
+                    reranking: [
+                        (self.generic_penalty > 0.0, "generic_penalty"),
+                        (self.feedback_weight > 0.0, "feedback"),
+                    ]
+                    .into_iter()
+                    .filter_map(|(enabled, name)| enabled.then_some(name))
+                    .collect(),
+                    dedup,
+                    languages: languages
+                        .as_ref()
+                        .filter(|f| f.action() == LanguageAction::Tag)
+                        .map(LanguageFilter::codes),
+                },
+                parameters: provenance::ParameterProvenance {
+                    limit: result_limit,
+                    fetch_limit,
+                    min_similarity: params.min_similarity,
+                    min_tokens,
+                    path_prefix: path_prefix.map(str::to_string),
+                    section: section.map(str::to_string),
+                    ef_search: None,
+                    query_hint: query_hint.clone(),
+                    generic_penalty: self.generic_penalty,
+                },
+                candidates,
+                timings_ms: timings,
+            }
+        });
+        let returned = results.as_ref().ok().map(|results| {
+            let shown = if best_only { 1 } else { result_limit };
+            (
+                shown.min(results.len()),
+                results.first().map(|doc| doc.similarity),
+            )
+        });
+        let response = match results {
+            Ok(results) if best_only => match results.first() {
+                Some(best) => {
+                    let mut response = search::format_best_result(best);
+                    if args.debug.unwrap_or(false) {
+                        response.push('\n');
+                        response.push_str(&search::format_debug_diagnostics(&args.question, best));
+                    }
+                    if let Some(footer) =
+                        freshness::format_freshness_footer(&args.crate_name, &results[..1])
+                    {
+                        response.push_str("\n\n");
+                        response.push_str(&footer);
+                    }
+                    response
+                }
+                None => search::no_best_result(&args.crate_name, params.min_similarity),
+            },
+            Ok(results) => {
+                if results.is_empty() {
+                    format!(
+                        "No relevant documentation found for '{}' in crate '{}'",
+                        args.question, args.crate_name
+                    )
+                } else {
+                    let crate_name = &args.crate_name;
+                    let debug = args.debug.unwrap_or(false);
+                    let related = if args.suggest_related.unwrap_or(false) {
+                        match search::suggest_related(
+                            &self.database,
+                            crate_name,
+                            &results[0].doc_path,
+                            MAX_RELATED_ITEMS,
+                        )
+                        .await
+                        {
+                            Ok(items) => items,
+                            Err(e) => {
+                                warn!("⚠️  Failed to find related items: {e}");
+                                Vec::new()
+                            }
+                        }
+                    } else {
+                        Vec::new()
+                    };
+                    let top = result_limit.min(results.len());
+                    let shown = match max_response_tokens {
+                        Some(budget) => search::within_token_budget(&results[..top], budget).len(),
+                        None => top,
+                    };
+                    let footer = freshness::format_freshness_footer(crate_name, &results[..shown]);
+                    let mut response =
+                        format!("From {crate_name} docs (via vector database search): ");
+
+                    // Take top results and format them
+                    let formatted_results: Vec<String> = results
+                        .into_iter()
+                        .take(shown)
+                        .enumerate()
+                        .map(|(i, doc)| {
+                            let idx = i + 1;
+                            let content_trimmed = doc.content.trim();
+                            let similarity = doc.similarity;
+                            let source_url = search::source_url(&doc.doc_path);
+                            let mut entry = format!(
+                                "{idx}. {content_trimmed} (similarity: {similarity:.3})\nSource: {source_url}"
+                            );
+                            if debug {
+                                entry.push('\n');
+                                entry.push_str(&search::format_debug_diagnostics(
+                                    &args.question,
+                                    &doc,
+                                ));
+                            }
+                            entry
+                        })
+                        .collect();
+
+                    response.push_str(&formatted_results.join("\n\n"));
+                    if shown < top {
+                        response.push_str(&format!(
+                            "\n\n({} more results left out to stay within the {}-token response budget)",
+                            top - shown,
+                            max_response_tokens.unwrap_or_default()
+                        ));
+                    }
+                    if !related.is_empty() {
+                        response.push_str("\n\n");
+                        response.push_str(&search::format_related(&related));
+                    }
+                    if let Some(footer) = footer {
+                        response.push_str("\n\n");
+                        response.push_str(&footer);
+                    }
+                    response
+                }
+            }
+            Err(e) => {
+                return Err(McpError::internal_error(
+                    format!("Database search error: {e}"),
+                    None,
+                ))
+            }
+        };
+
+        let mut response = response;
+        if let Some(note) = self.staleness_note(&args.crate_name).await {
+            response.push_str("\n\n");
+            response.push_str(&note);
+        }
+        if low_doc_coverage {
+            response.push_str("\n\n");
+            response.push_str(doc_loader::LOW_DOC_COVERAGE_NOTE);
+        }
+
+        // Answers from a fallback aren't cached, so the primary serves the question once it recovers;
+        // every answer, cached or not, gets its own query ID
+        let mut result = self.text_result(&search::with_query_id(&response, &query_id));
+        self.log_query(
+            &args.crate_name,
+            &args.question,
+            &query_id,
+            returned,
+            started,
+        );
+        self.recent_questions
+            .record(&self.connection_id, cache_key.clone(), response.clone());
+        if !used_fallback {
+            self.query_cache.insert(cache_key, response);
+        }
+        if let Some(mut provenance) = provenance {
+            provenance.timings_ms.total = provenance::millis(started.elapsed());
+            let record = serde_json::json!({ "provenance": provenance });
+            result.content.push(Content::text(record.to_string()));
+        }
+        Ok(result)
+    }
+
+    #[tool(
+        description = "Search documentation when you don't know which crate holds the API: ranks every available crate by how close its docs are to the question, then searches the closest few and merges their results."
+    )]
+    async fn smart_query(
+        &self,
+        #[tool(aggr)] args: SmartQueryArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        self.check_rate_limit("query_rust_docs", self.rate_limits.query_per_minute)?;
+        let crate_names: Vec<String> = self.available_crates.read().await.iter().cloned().collect();
+        if crate_names.is_empty() {
+            return Err(McpError::invalid_request(
+                "No crates are available to search; add one with add_crate".to_string(),
+                None,
+            ));
+        }
+
+        // Embedded once: the same vector ranks the crates and searches them
+        let document_client = self
+            .document_provider()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let embedding_client = self.query_provider.as_ref().unwrap_or(&document_client);
+        let (embeddings, _tokens) = embedding_client
+            .generate_embeddings(std::slice::from_ref(&args.question))
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to generate embedding: {e}"), None)
+            })?;
+        let Some(embedding) = embeddings.into_iter().next() else {
+            return Err(McpError::internal_error(
+                "Embedding provider returned no embedding".to_string(),
+                None,
+            ));
+        };
+
+        let started = Instant::now();
+        let centroids = self
+            .crate_centroids(&crate_names, embedding.len())
+            .await
+            .map_err(|e| McpError::internal_error(format!("Failed to rank crates: {e}"), None))?;
+        let top = args
+            .crates
+            .map_or(search::DEFAULT_SMART_QUERY_CRATES, |n| n as usize);
+        let chosen = search::rank_crates(&embedding, &centroids, top);
+        if chosen.is_empty() {
+            return Ok(self.text_result(&format!(
+                "No available crate has documents embedded with {}; query_rust_docs searches crates stored with another model",
+                embedding_client.get_model_name()
+            )));
+        }
+        info!(
+            "🧭 Ranked {} crates for smart_query in {:?}; searching {:?}",
+            centroids.len(),
+            started.elapsed(),
+            chosen.iter().map(|c| &c.crate_name).collect::<Vec<_>>()
+        );
+
+        let params = search::SearchParams {
+            limit: args.limit.map(|n| n as usize),
+            min_similarity: args.min_similarity,
+            path_prefix: None,
+        }
+        .or(self.query_defaults.search_params());
+        let result_limit = params.result_limit();
+        let embedding = Array1::from_vec(embedding);
+        let searches = chosen.iter().map(|candidate| {
+            let (embedding, params) = (&embedding, &params);
+            async move {
+                self.database
+                    .search_similar_docs_tuned(
+                        &candidate.crate_name,
+                        embedding,
+                        result_limit as i32,
+                        None,
+                        None,
+                        None,
+                    )
+                    .await
+                    .map(|docs| (candidate.crate_name.clone(), params.filter(docs)))
+            }
+        });
+        let result_sets = futures::future::try_join_all(searches)
+            .await
+            .map_err(|e| McpError::internal_error(format!("Database search error: {e}"), None))?;
+        let results = search::merge_crate_results(
+            result_sets,
+            result_limit,
+            args.max_per_crate.map(|n| n as usize),
+        );
+
+        let chosen_list: Vec<String> = chosen
+            .iter()
+            .map(|c| format!("{} ({:.3})", c.crate_name, c.similarity))
+            .collect();
+        let mut response = format!(
+            "Searched the crates closest to the question: {}",
+            chosen_list.join(", ")
+        );
+        if results.is_empty() {
+            response.push_str(&format!(
+                "\n\nNo relevant documentation found for '{}' in those crates",
+                args.question
+            ));
+        } else {
+            let formatted: Vec<String> = results
+                .iter()
+                .enumerate()
+                .map(|(i, (crate_name, doc))| {
+                    format!(
+                        "{}. [{crate_name}] {} (similarity: {:.3})\nSource: {}",
+                        i + 1,
+                        doc.content.trim(),
+                        doc.similarity,
+                        search::source_url(&doc.doc_path)
+                    )
+                })
+                .collect();
+            response.push_str("\n\n");
+            response.push_str(&formatted.join("\n\n"));
+        }
+        Ok(self.text_result(&response))
+    }
+
+    #[tool(
+        description = "Compare alternative crates on one concept (e.g. reqwest vs ureq for async requests): searches each crate for the question and returns its top results side by side, grouped per crate. Use when choosing between libraries."
+    )]
+    async fn compare_crates(
+        &self,
+        #[tool(aggr)] args: CompareCratesArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        self.check_rate_limit("query_rust_docs", self.rate_limits.query_per_minute)?;
+        let crate_names: Vec<&str> = args.crate_names.iter().map(|c| c.trim()).collect();
+        for crate_name in &crate_names {
+            if !self.is_crate_available(crate_name).await {
+                return Err(self.crate_unavailable(crate_name).await);
+            }
+        }
+
+        // Embedded once and searched in every crate, so the similarities are comparable
+        let document_client = self
+            .document_provider()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let embedding_client = self.query_provider.as_ref().unwrap_or(&document_client);
+        let (embeddings, _tokens) = embedding_client
+            .generate_embeddings(std::slice::from_ref(&args.question))
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to generate embedding: {e}"), None)
+            })?;
+        let Some(embedding) = embeddings.into_iter().next() else {
+            return Err(McpError::internal_error(
+                "Embedding provider returned no embedding".to_string(),
+                None,
+            ));
+        };
+        let embedding = Array1::from_vec(embedding);
+
+        let params = search::SearchParams {
+            limit: Some(
+                args.limit
+                    .map_or(search::DEFAULT_COMPARE_RESULTS, |n| n as usize),
+            ),
+            min_similarity: args.min_similarity,
+            path_prefix: None,
+        }
+        .or(self.query_defaults.search_params());
+        let result_limit = params.result_limit();
+        let searches = crate_names.iter().map(|crate_name| {
+            let (embedding, params) = (&embedding, &params);
+            async move {
+                self.database
+                    .search_similar_docs_scored(crate_name, embedding, result_limit as i32)
+                    .await
+                    .map(|docs| (crate_name.to_string(), params.filter(docs)))
+            }
+        });
+        let result_sets = futures::future::try_join_all(searches)
+            .await
+            .map_err(|e| McpError::internal_error(format!("Database search error: {e}"), None))?;
+
+        Ok(self.text_result(&search::format_comparison(&args.question, &result_sets)))
+    }
+
+    #[tool(
+        description = "Report whether a query_rust_docs result was useful (e.g. cited in your answer) or noise, using the Query ID shown under the results. Feedback can gently rerank future results when the server enables it."
+    )]
+    async fn submit_feedback(
+        &self,
+        #[tool(aggr)] args: SubmitFeedbackArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        self.ensure_writable("submit_feedback")?;
+        self.check_rate_limit("query_rust_docs", self.rate_limits.query_per_minute)?;
+        let doc_path = args.doc_path.trim();
+        let doc_path = search::base_doc_path(
+            doc_path
+                .strip_prefix("https://docs.rs/")
+                .unwrap_or(doc_path),
+        );
+        let query_id = args.query_id.trim();
+
+        match self
+            .database
+            .get_document_by_path(&args.crate_name, doc_path)
+            .await
+        {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "No document '{doc_path}' found in crate '{}'",
+                        args.crate_name
+                    ),
+                    None,
+                ))
+            }
+            Err(e) => {
+                return Err(McpError::internal_error(
+                    format!("Failed to look up document: {e}"),
+                    None,
+                ))
+            }
+        }
+
+        self.database
+            .record_retrieval_feedback(query_id, &args.crate_name, doc_path, args.useful)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to record feedback: {e}"), None)
+            })?;
+        info!(
+            "👍 Feedback for {query_id}: {doc_path} in {} was {}",
+            args.crate_name,
+            if args.useful { "useful" } else { "not useful" }
+        );
+
+        let response = serde_json::json!({
+            "success": true,
+            "query_id": query_id,
+            "crate_name": args.crate_name,
+            "doc_path": doc_path,
+            "useful": args.useful,
+            "ranking_applied": self.feedback_weight > 0.0,
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Get the full stored text of one documentation page")]
+    async fn get_doc(&self, #[tool(aggr)] args: GetDocArgs) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        let doc_path = args.doc_path.trim();
+        let doc_path = doc_path
+            .strip_prefix("https://docs.rs/")
+            .unwrap_or(doc_path);
+
+        match self
+            .database
+            .get_document_by_path(&args.crate_name, doc_path)
+            .await
+        {
+            Ok(Some(content)) => Ok(self.text_result(&format!(
+                "{}\n\nSource: {}",
+                content.trim(),
+                search::source_url(doc_path)
+            ))),
+            Ok(None) => Err(McpError::invalid_params(
+                format!(
+                    "No document '{doc_path}' found in crate '{}'",
+                    args.crate_name
+                ),
+                None,
+            )),
+            Err(e) => Err(McpError::internal_error(
+                format!("Failed to get document: {e}"),
+                None,
+            )),
+        }
+    }
+
+    #[tool(
+        description = "Find documents whose content or path contains an identifier (substring match, ranked by trigram similarity); use for exact symbol names that semantic search misses"
+    )]
+    async fn find_symbol(
+        &self,
+        #[tool(aggr)] args: FindSymbolArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        let identifier = args.identifier.trim();
+        let crate_name = args.crate_name.as_deref().filter(|c| !c.trim().is_empty());
+        if let Some(crate_name) = crate_name {
+            if !self.is_crate_available(crate_name).await {
+                return Err(self.crate_unavailable(crate_name).await);
+            }
+        }
+        let case_sensitive = args.case_sensitive.unwrap_or(false);
+        let limit = args
+            .limit
+            .unwrap_or(DEFAULT_SYMBOL_LIMIT)
+            .clamp(1, search::MAX_RESULT_LIMIT);
+
+        let matches = self
+            .database
+            .find_symbol(crate_name, identifier, case_sensitive, limit as i64)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to search for symbol: {e}"), None)
+            })?;
+
+        if matches.is_empty() {
+            let scope = crate_name.map_or_else(|| "any crate".to_string(), |c| format!("'{c}'"));
+            return Ok(self.text_result(&format!("No documents in {scope} contain '{identifier}'")));
+        }
+
+        let sections: Vec<String> = matches
+            .iter()
+            .map(|m| {
+                let context = search::symbol_context(
+                    &m.content,
+                    identifier,
+                    case_sensitive,
+                    SYMBOL_CONTEXT_LINES,
+                )
+                .unwrap_or_else(|| "(matched the document path)".to_string());
+                format!(
+                    "{} (score: {:.2})\n{context}\nSource: {}",
+                    m.crate_name,
+                    m.score,
+                    search::source_url(&m.doc_path)
+                )
+            })
+            .collect();
+        Ok(self.text_result(&format!(
+            "{} documents containing '{identifier}':\n\n{}",
+            matches.len(),
+            sections.join("\n\n---\n\n")
+        )))
+    }
+
+    #[tool(
+        description = "Get a crate's crates.io facts: description, repository, homepage, license, latest version and downloads. Cached for a day; works for crates that aren't indexed"
+    )]
+    async fn crate_info(
+        &self,
+        #[tool(aggr)] args: CrateInfoArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        let crate_name = args.crate_name.trim();
+
+        let cached = self
+            .database
+            .get_crate_metadata(crate_name)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("⚠️  Failed to read cached metadata for {crate_name}: {e}");
+                None
+            });
+        if let Some(metadata) = cached
+            .as_ref()
+            .filter(|m| !crate_metadata::is_stale(m, chrono::Utc::now()))
+        {
+            return Ok(crate_info_result(metadata, None));
+        }
+
+        match self.crates_io.fetch(crate_name).await {
+            Ok(Some(metadata)) => {
+                // A read-only replica can't store the refresh; it just fetches every time
+                if !self.read_only {
+                    if let Err(e) = self.database.upsert_crate_metadata(&metadata).await {
+                        warn!("⚠️  Failed to cache metadata for {crate_name}: {e}");
+                    }
+                }
+                Ok(crate_info_result(&metadata, None))
+            }
+            Ok(None) => Err(McpError::invalid_params(
+                format!("Crate '{crate_name}' not found on crates.io"),
+                None,
+            )),
+            Err(e) => match cached {
+                Some(metadata) => {
+                    warn!("⚠️  crates.io lookup for {crate_name} failed, serving cached metadata: {e}");
+                    let note = format!(
+                        "crates.io is unreachable ({e}); showing metadata fetched {}",
+                        metadata.fetched_at.format("%Y-%m-%d %H:%M UTC")
+                    );
+                    Ok(crate_info_result(&metadata, Some(note)))
+                }
+                None => Err(McpError::internal_error(
+                    format!("Failed to fetch {crate_name} from crates.io: {e}"),
+                    None,
+                )),
+            },
+        }
+    }
+
+    #[tool(description = "Add or update a crate configuration")]
+    async fn add_crate(
+        &self,
+        #[tool(aggr)] args: AddCrateArgs,
+    ) -> Result<CallToolResult, McpError> {
+        info!(
+            "🔧 add_crate called for: {} ({})",
+            args.crate_name, args.version_spec
+        );
+        args.validate()?;
+        let estimate_only = args.estimate_only.unwrap_or(false);
+        if estimate_only && args.rustdoc_json_url.is_some() {
+            return Err(McpError::invalid_params(
+                "estimate_only estimates docs.rs crawls and cannot be combined with rustdoc_json_url",
+                None,
+            ));
+        }
+        if !estimate_only {
+            self.ensure_writable("add_crate")?;
+        }
+        self.check_rate_limit("add_crate", self.rate_limits.add_crate_per_minute)?;
+
+        // Without an explicit expected_docs, the first population establishes the baseline
+        let expected_docs = args.expected_docs.unwrap_or(1000);
+        let auto_expected_docs = args
+            .auto_expected_docs
+            .unwrap_or(args.expected_docs.is_none());
+
+        // Create config
+        let config = CrateConfig {
+            id: 0, // Will be set by database
+            name: args.crate_name.clone(),
+            version_spec: args.version_spec.clone(),
+            current_version: None, // Will be set during population
+            features: args
+                .features
+                .unwrap_or_else(|| self.default_crate_features.to_vec()),
+            expected_docs,
+            enabled: args.enabled.unwrap_or(true),
+            last_checked: None,
+            last_populated: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            content_selector: args.content_selector.filter(|s| !s.trim().is_empty()),
+            include_url_patterns: args.include_url_patterns.unwrap_or_default(),
+            exclude_url_patterns: args.exclude_url_patterns.unwrap_or_default(),
+            follow_reexports: args.follow_reexports.unwrap_or(false),
+            reexport_crates: args.reexport_crates.unwrap_or_default(),
+            reexport_page_budget: args.reexport_page_budget,
+            reexport_pages_per_crate: args.reexport_pages_per_crate,
+            rustdoc_json_url: args.rustdoc_json_url.filter(|url| !url.trim().is_empty()),
+            target: args.target.filter(|t| !t.trim().is_empty()),
+            auto_expected_docs,
+            query_hint: args.query_hint.filter(|h| !h.trim().is_empty()),
+            default_limit: args.default_limit,
+            default_min_similarity: args.default_min_similarity,
+            default_path_prefix: args.default_path_prefix.filter(|p| !p.trim().is_empty()),
+            max_pages: None,
+            store_raw_html: args.store_raw_html.unwrap_or(false),
+            languages: args.languages.clone().unwrap_or_default(),
+            language_action: args
+                .language_action
+                .clone()
+                .filter(|a| !a.trim().is_empty()),
+            max_embeddings: args.max_embeddings,
+            truncated: false,
+            max_duration_secs: args.max_duration_secs,
+            partial_at: None,
+            low_doc_coverage: false,
+            section_metadata: args.section_metadata.unwrap_or(false),
+            allow_prerelease: args.allow_prerelease.unwrap_or(false),
+        };
+
+        if estimate_only {
+            let version = self
+                .resolve_version(&config.name, &config.version_spec, config.allow_prerelease)
+                .await?;
+            let crawl_options = doc_loader::CrawlOptions {
+                version,
+                ..config.crawl_options()
+            };
+            return self
+                .estimate_response(
+                    &config.name,
+                    &config.features,
+                    crawl_options,
+                    config.max_pages_or(10000),
+                    None,
+                )
+                .await;
+        }
+
+        // Save to database
+        match self.database.upsert_crate_config(&config).await {
+            Ok(saved_config) => {
+                // Cached answers were retrieved with the previous hint and defaults
+                self.invalidate_query_cache(&args.crate_name);
+
+                // Create a population job
+                let job_id = self
+                    .database
+                    .create_population_job(saved_config.id)
+                    .await
+                    .ok();
+
+                // Return response immediately
+                let response = match job_id {
+                    Some(job_id) => {
+                        format!("Ingestion has started (job {job_id}; follow it with job_status)")
+                    }
+                    None => "Ingestion has started".to_string(),
+                };
+                let result = Ok(CallToolResult::success(vec![Content::text(response)]));
+
+                // Spawn background population task after returning response
+                self.spawn_population(saved_config, job_id);
+
+                result
+            }
+            Err(e) => Err(McpError::internal_error(
+                format!("Failed to save crate configuration: {e}"),
+                None,
+            )),
+        }
+    }
+
+    #[tool(
+        description = "Estimate what adding a crate would cost before adding it: pages, tokens, embedding cost and duration, projected from docs.rs's all.html index and a few sampled pages, with the confidence of the estimate. Writes nothing"
+    )]
+    async fn estimate_population(
+        &self,
+        #[tool(aggr)] args: EstimatePopulationArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        self.check_rate_limit("add_crate", self.rate_limits.add_crate_per_minute)?;
+        let crate_name = args.crate_name.trim();
+        let version_spec = args.version_spec.as_deref().unwrap_or("latest");
+        let version = self
+            .resolve_version(
+                crate_name,
+                version_spec,
+                args.allow_prerelease.unwrap_or(false),
+            )
+            .await?;
+        let crawl_options = doc_loader::CrawlOptions {
+            content_selector: args.content_selector.filter(|s| !s.trim().is_empty()),
+            include_url_patterns: args.include_url_patterns.unwrap_or_default(),
+            exclude_url_patterns: args.exclude_url_patterns.unwrap_or_default(),
+            target: args.target.filter(|t| !t.trim().is_empty()),
+            version,
+            ..doc_loader::CrawlOptions::default()
+        };
+        self.estimate_response(
+            crate_name,
+            &args
+                .features
+                .unwrap_or_else(|| self.default_crate_features.to_vec()),
+            crawl_options,
+            10000,
+            args.sample_pages,
+        )
+        .await
+    }
+
+    #[tool(description = "List all configured crates")]
+    async fn list_crates(
+        &self,
+        #[tool(aggr)] args: ListCratesArgs,
+    ) -> Result<CallToolResult, McpError> {
+        let enabled_only =
+            args.enabled_only.unwrap_or(false) || !args.include_disabled.unwrap_or(false);
+        match self.database.get_crate_configs(enabled_only).await {
+            Ok(configs) => {
+                let names: Vec<String> = configs.iter().map(|c| c.name.clone()).collect();
+                let statuses = self
+                    .database
+                    .get_crate_statuses(&names, self.stale_after_days)
+                    .await
+                    .map_err(|e| {
+                        McpError::internal_error(format!("Failed to get crate status: {e}"), None)
+                    })?;
+                let crate_list: Vec<serde_json::Value> = configs.iter().map(|config| {
+                    let status = statuses.get(&config.name).cloned().unwrap_or(CrateStatus::Queued { job_id: None });
+                    serde_json::json!({
+                        "name": config.name,
+                        "version_spec": config.version_spec,
+                        "target": config.target,
+                        "current_version": config.current_version,
+                        "features": config.features,
+                        "enabled": config.enabled,
+                        "expected_docs": config.expected_docs,
+                        "content_selector": config.content_selector,
+                        "follow_reexports": config.follow_reexports,
+                        "max_pages": config.max_pages,
+                        "max_embeddings": config.max_embeddings,
+                        "truncated": config.truncated,
+                        "low_doc_coverage": config.low_doc_coverage,
+                        "source": if config.rustdoc_json_url.is_some() { "rustdoc_json" } else { "docs.rs" },
+                        "last_populated": config.last_populated,
+                        "status": status.name(),
+                        "status_detail": status
+                    })
+                }).collect();
+
+                let response = serde_json::json!({
+                    "crates": crate_list,
+                    "total": configs.len()
+                });
+
+                Ok(CallToolResult::success(vec![Content::text(
+                    response.to_string(),
+                )]))
+            }
+            Err(e) => Err(McpError::internal_error(
+                format!("Failed to list crates: {e}"),
+                None,
+            )),
+        }
+    }
+
+    #[tool(description = "Check the status of crate population jobs")]
+    async fn check_crate_status(
+        &self,
+        #[tool(aggr)] args: CheckCrateStatusArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        // Get crate configs
+        let configs = self.database.get_crate_configs(false).await.map_err(|e| {
+            McpError::internal_error(format!("Failed to get crate configs: {e}"), None)
+        })?;
+
+        // Find the requested crate, and the requested target's configuration among its rows
+        let target = args.target.as_deref().filter(|t| !t.trim().is_empty());
+        let config = configs
+            .iter()
+            .find(|c| c.name == args.crate_name && c.target.as_deref() == target)
+            .or_else(|| {
+                target
+                    .is_none()
+                    .then(|| configs.iter().find(|c| c.name == args.crate_name))
+                    .flatten()
+            })
+            .ok_or_else(|| match target {
+                Some(target) => McpError::invalid_params(
+                    format!(
+                        "Crate '{}' has no configuration for target '{target}'",
+                        args.crate_name
+                    ),
+                    None,
+                ),
+                None => {
+                    McpError::invalid_params(format!("Crate '{}' not found", args.crate_name), None)
+                }
+            })?;
+        let other_targets: Vec<&str> = configs
+            .iter()
+            .filter(|c| c.name == config.name && c.id != config.id)
+            .map(|c| c.target.as_deref().unwrap_or("default"))
+            .collect();
+
+        // Population state and stored document count
+        let crate_status = self
+            .database
+            .get_crate_status(&args.crate_name, self.stale_after_days)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to get crate status: {e}"), None)
+            })?;
+        let total_docs = crate_status.docs() as i32;
+        let has_embeddings = total_docs > 0;
+        let under_populated = has_embeddings && config.is_under_populated(total_docs as usize);
+
+        // Latest population job, with the crawl diagnostics it recorded
+        let latest_job = self
+            .database
+            .get_latest_population_job(config.id)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("⚠️  Failed to get population job for {}: {e}", config.name);
+                None
+            });
+
+        let status = serde_json::json!({
+            "crate_name": config.name,
+            "version_spec": config.version_spec,
+            "target": config.target,
+            "other_targets": other_targets,
+            "current_version": config.current_version,
+            "enabled": config.enabled,
+            "last_populated": config.last_populated,
+            "has_embeddings": has_embeddings,
+            "total_docs": total_docs,
+            "features": config.features,
+            "expected_docs": config.expected_docs,
+            "auto_expected_docs": config.auto_expected_docs,
+            "coverage": config.coverage(total_docs as usize),
+            "content_selector": config.content_selector,
+            "include_url_patterns": config.include_url_patterns,
+            "exclude_url_patterns": config.exclude_url_patterns,
+            "follow_reexports": config.follow_reexports,
+            "max_pages": config.max_pages,
+            "max_embeddings": config.max_embeddings,
+            "truncated": config.truncated,
+            "low_doc_coverage": config.low_doc_coverage,
+            "max_duration_secs": config.max_duration_secs,
+            "partial_at": config.partial_at,
+            "store_raw_html": config.store_raw_html,
+            "section_metadata": config.section_metadata,
+            "languages": config.languages,
+            "language_action": config.language_action,
+            "reexport_crates": config.reexport_crates,
+            "reexport_page_budget": config.reexport_page_budget,
+            "reexport_pages_per_crate": config.reexport_pages_per_crate,
+            "rustdoc_json_url": config.rustdoc_json_url,
+            "query_hint": config.query_hint,
+            "default_limit": config.default_limit,
+            "default_min_similarity": config.default_min_similarity,
+            "default_path_prefix": config.default_path_prefix,
+            "latest_job": latest_job,
+            "status": crate_status.name(),
+            "status_detail": crate_status,
+            "under_populated": under_populated,
+            "note": if !matches!(crate_status, CrateStatus::Populated { .. } | CrateStatus::Stale { .. }) {
+                crate_status.describe(&config.name)
+            } else if config.truncated {
+                format!("Population stopped at max_embeddings ({}); the index is partial by design and covers the crate's earliest pages",
+                    config.max_embeddings.unwrap_or_default())
+            } else if config.low_doc_coverage {
+                format!("{} {total_docs} documents are stored, from the README and crate root page", doc_loader::LOW_DOC_COVERAGE_NOTE)
+            } else if under_populated {
+                format!("Only {total_docs} of the {} expected documents are stored; the last population may have hit a broken docs.rs build or crawl limits (see latest_job diagnostics)",
+                    config.expected_docs)
+            } else {
+                "Crate is populated and ready for queries".to_string()
+            }
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            status.to_string(),
+        )]))
+    }
+
+    #[tool(
+        description = "Live progress of one population job: stage, documents loaded and embeddings stored so far, elapsed time and estimated completion"
+    )]
+    async fn job_status(
+        &self,
+        #[tool(aggr)] args: JobStatusArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        let job = self
+            .database
+            .get_population_job(args.job_id)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to get population job: {e}"), None)
+            })?
+            .ok_or_else(|| {
+                McpError::invalid_params(format!("Population job {} not found", args.job_id), None)
+            })?;
+        let config = self
+            .database
+            .get_crate_configs(false)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to get crate configs: {e}"), None)
+            })?
+            .into_iter()
+            .find(|c| c.id == job.crate_config_id);
+
+        // Counters only exist while this process runs the job; afterwards the job row has
+        // the outcome
+        let live = self.populations.progress(job.id);
+        let expected_docs = config.as_ref().and_then(|c| c.established_expected_docs());
+        let estimated_remaining = live
+            .as_ref()
+            .zip(expected_docs)
+            .and_then(|(progress, expected)| progress.estimated_remaining(expected));
+        let elapsed_secs = match &live {
+            Some(progress) => progress.elapsed_secs,
+            None => job.started_at.map(|started| {
+                let end = job.completed_at.unwrap_or_else(chrono::Utc::now);
+                (end - started).num_milliseconds().max(0) as f64 / 1000.0
+            }),
+        };
+
+        let response = serde_json::json!({
+            "job_id": job.id,
+            "crate_name": config.as_ref().map(|c| c.name.as_str()),
+            "target": config.as_ref().and_then(|c| c.target.as_deref()),
+            "status": job.status,
+            "stage": live.as_ref().map(|progress| progress.stage),
+            "live": live.is_some(),
+            "documents_loaded": live.as_ref().map(|progress| progress.documents_loaded),
+            "chunks_embedded": live.as_ref().map(|progress| progress.chunks_embedded),
+            "embeddings_generated": live
+                .as_ref()
+                .map_or(job.docs_populated.map(|n| n as usize), |progress| Some(progress.chunks_stored)),
+            "chunks_already_stored": live.as_ref().map(|progress| progress.chunks_skipped),
+            "expected_docs": expected_docs,
+            "elapsed_secs": elapsed_secs,
+            "estimated_remaining_secs": estimated_remaining.map(|wait| wait.as_secs_f64().round()),
+            "estimated_completion": estimated_remaining
+                .and_then(|wait| chrono::Duration::from_std(wait).ok())
+                .map(|wait| chrono::Utc::now() + wait),
+            "started_at": job.started_at,
+            "completed_at": job.completed_at,
+            "error_message": job.error_message,
+            "note": match (live.is_some(), job.status.as_str()) {
+                (true, _) => "Estimates assume the crate stores about expected_docs chunks at the rate seen so far",
+                (false, "pending" | "running") => "This server isn't running the job; it may belong to another process or to a server that stopped before it finished",
+                (false, _) => "The job has finished; see check_crate_status for its crawl diagnostics and crawl_trace for the URLs it crawled",
+            },
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(
+        description = "What the crawler did during one population job: every URL it considered with its outcome (fetched, empty, skipped_filter, skipped_robots, not_found, error), to diagnose a crate that came out with fewer docs than expected"
+    )]
+    async fn crawl_trace(
+        &self,
+        #[tool(aggr)] args: CrawlTraceArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        let job = self
+            .database
+            .get_population_job(args.job_id)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to get population job: {e}"), None)
+            })?
+            .ok_or_else(|| {
+                McpError::invalid_params(format!("Population job {} not found", args.job_id), None)
+            })?;
+        let trace = self
+            .database
+            .get_population_job_crawl_trace(job.id)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to get crawl trace: {e}"), None)
+            })?;
+        let Some(trace) = trace else {
+            let note = match job.status.as_str() {
+                "pending" | "running" => "The job hasn't finished crawling yet; its trace is stored once the crawl ends",
+                _ => "No crawl trace was stored for this job: it populated from rustdoc JSON, failed before the crawl finished, or ran before traces were recorded",
+            };
+            let response = serde_json::json!({
+                "job_id": job.id,
+                "status": job.status,
+                "note": note,
+            });
+            return Ok(CallToolResult::success(vec![Content::text(
+                response.to_string(),
+            )]));
+        };
+
+        let counts: BTreeMap<&str, usize> = trace
+            .counts()
+            .into_iter()
+            .map(|(outcome, count)| (outcome.as_str(), count))
+            .collect();
+        let outcome = args
+            .outcome
+            .as_deref()
+            .and_then(doc_loader::TraceOutcome::parse);
+        let limit = args.limit.unwrap_or(tool_args::DEFAULT_TRACE_LIMIT) as usize;
+        let matching: Vec<_> = trace
+            .entries
+            .iter()
+            .filter(|entry| outcome.is_none_or(|outcome| entry.outcome == outcome))
+            .collect();
+        let response = serde_json::json!({
+            "job_id": job.id,
+            "status": job.status,
+            "docs_populated": job.docs_populated,
+            "counts": counts,
+            "urls_not_traced": trace.dropped,
+            "matching": matching.len(),
+            "entries": &matching[..limit.min(matching.len())],
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(
+        description = "Suggest a min_similarity for a crate from the top-result similarities of sample questions compared with off-topic probes; advisory only"
+    )]
+    async fn suggest_threshold(
+        &self,
+        #[tool(aggr)] args: SuggestThresholdArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        self.check_rate_limit("suggest_threshold", self.rate_limits.query_per_minute)?;
+        if !self.is_crate_available(&args.crate_name).await {
+            return Err(self.crate_unavailable(&args.crate_name).await);
+        }
+
+        // Measured with the model queries use, since that is what the threshold applies to
+        let document_client = self
+            .document_provider()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let embedding_client = self.query_provider.as_ref().unwrap_or(&document_client);
+        let texts: Vec<String> = args
+            .questions
+            .iter()
+            .cloned()
+            .chain(threshold::OFF_TOPIC_PROBES.iter().map(|p| p.to_string()))
+            .collect();
+        let (embeddings, _tokens) =
+            embedding_client
+                .generate_embeddings(&texts)
+                .await
+                .map_err(|e| {
+                    McpError::internal_error(format!("Failed to generate embeddings: {e}"), None)
+                })?;
+        if embeddings.len() != texts.len() {
+            return Err(McpError::internal_error(
+                format!(
+                    "Embedding provider returned {} embeddings for {} texts",
+                    embeddings.len(),
+                    texts.len()
+                ),
+                None,
+            ));
+        }
+
+        let crate_name = &args.crate_name;
+        let searches = embeddings.into_iter().map(|embedding| {
+            let embedding = Array1::from_vec(embedding);
+            async move {
+                self.database
+                    .search_similar_docs(crate_name, &embedding, 1)
+                    .await
+            }
+        });
+        let best: Vec<Option<(String, f32)>> = futures::future::try_join_all(searches)
+            .await
+            .map_err(|e| McpError::internal_error(format!("Database search error: {e}"), None))?
+            .into_iter()
+            .map(|top| {
+                top.into_iter()
+                    .next()
+                    .map(|(path, _, similarity)| (path, similarity))
+            })
+            .collect();
+        let (question_tops, probe_tops) = best.split_at(args.questions.len());
+        let scores = |tops: &[Option<(String, f32)>]| -> Vec<f32> {
+            tops.iter()
+                .flatten()
+                .map(|(_, similarity)| *similarity)
+                .collect()
+        };
+        let percentile = args.percentile.unwrap_or(threshold::DEFAULT_PERCENTILE);
+        let Some(suggestion) =
+            threshold::suggest(&scores(question_tops), &scores(probe_tops), percentile)
+        else {
+            return Ok(self.text_result(&format!(
+                "No sample question matched a document of {crate_name}; is it populated?"
+            )));
+        };
+        info!(
+            "🎚️  Suggested min_similarity {} for {crate_name} with {} ({:?})",
+            suggestion.threshold,
+            embedding_client.get_model_name(),
+            suggestion.method
+        );
+
+        let per_question: Vec<serde_json::Value> = args
+            .questions
+            .iter()
+            .zip(question_tops)
+            .map(|(question, top)| {
+                serde_json::json!({
+                    "question": question,
+                    "top_doc_path": top.as_ref().map(|(path, _)| path),
+                    "top_similarity": top.as_ref().map(|(_, similarity)| similarity),
+                })
+            })
+            .collect();
+        let response = serde_json::json!({
+            "crate_name": crate_name,
+            "model": embedding_client.get_model_name(),
+            "suggested_min_similarity": suggestion.threshold,
+            "method": suggestion.method,
+            "questions_kept": suggestion.questions_kept,
+            "off_topic_kept": suggestion.off_topic_kept,
+            "distribution": {
+                "questions": suggestion.questions,
+                "off_topic": suggestion.off_topic,
+            },
+            "per_question": per_question,
+            "note": "Advisory only; nothing was changed. Thresholds differ between embedding models, so re-run after switching models. Apply with add_crate's default_min_similarity or per query with min_similarity",
+        });
+        Ok(self.text_result(&response.to_string()))
+    }
+
+    #[tool(
+        description = "Report text-search (tsvector) coverage per crate and whether the GIN and vector indexes exist and are valid"
+    )]
+    async fn index_health(
+        &self,
+        #[tool(aggr)] args: IndexHealthArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        let health = self
+            .database
+            .get_index_health(args.crate_name.as_deref())
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to get index health: {e}"), None)
+            })?;
+
+        let mut warnings = Vec::new();
+        if !health.has_tsv_column {
+            warnings.push(
+                "content_tsv column missing - run sql/migrations/add_content_tsv.sql".to_string(),
+            );
+        }
+        match &health.text_index {
+            None => warnings.push("GIN index on content_tsv is missing".to_string()),
+            Some(index) if !index.valid => {
+                warnings.push(format!("Text index {} is invalid", index.name))
+            }
+            _ => {}
+        }
+        match &health.vector_index {
+            None => {
+                warnings.push("No vector index - similarity search uses a full scan".to_string())
+            }
+            Some(index) if !index.valid => {
+                warnings.push(format!("Vector index {} is invalid", index.name))
+            }
+            _ => {}
+        }
+
+        let crates: Vec<serde_json::Value> = health
+            .crates
+            .iter()
+            .map(|c| {
+                if c.is_partially_indexed() {
+                    warnings.push(format!(
+                        "{}: {} of {} documents lack a tsvector - run populate_db --reindex-text",
+                        c.crate_name,
+                        c.total_docs - c.docs_with_tsv,
+                        c.total_docs
+                    ));
+                }
+                serde_json::json!({
+                    "crate_name": c.crate_name,
+                    "total_docs": c.total_docs,
+                    "docs_with_tsv": c.docs_with_tsv,
+                    "partially_indexed": c.is_partially_indexed(),
+                })
+            })
+            .collect();
+
+        let response = serde_json::json!({
+            "has_tsv_column": health.has_tsv_column,
+            "text_index": health.text_index,
+            "vector_index": health.vector_index,
+            "crates": crates,
+            "healthy": warnings.is_empty(),
+            "warnings": warnings,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(
+        description = "Report stored content size per crate and how much zstd compression saves"
+    )]
+    async fn storage_report(
+        &self,
+        #[tool(aggr)] args: StorageReportArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        let report = self.database.get_storage_report().await.map_err(|e| {
+            McpError::internal_error(format!("Failed to get storage report: {e}"), None)
+        })?;
+
+        fn reduction_pct(stored: i64, uncompressed: i64) -> f64 {
+            if uncompressed == 0 {
+                0.0
+            } else {
+                (100.0 * (1.0 - stored as f64 / uncompressed as f64) * 10.0).round() / 10.0
+            }
+        }
+
+        let crates: Vec<&CrateStorage> = report
+            .crates
+            .iter()
+            .filter(|c| {
+                args.crate_name
+                    .as_ref()
+                    .is_none_or(|name| &c.crate_name == name)
+            })
+            .collect();
+        let stored_bytes: i64 = crates.iter().map(|c| c.stored_bytes).sum();
+        let uncompressed_bytes: i64 = crates.iter().map(|c| c.uncompressed_bytes).sum();
+
+        let crate_list: Vec<serde_json::Value> = crates
+            .iter()
+            .map(|c| {
+                serde_json::json!({
+                    "crate_name": c.crate_name,
+                    "total_docs": c.total_docs,
+                    "compressed_docs": c.compressed_docs,
+                    "stored_kb": (c.stored_bytes as f64 / 1024.0).round(),
+                    "uncompressed_kb": (c.uncompressed_bytes as f64 / 1024.0).round(),
+                    "reduction_pct": reduction_pct(c.stored_bytes, c.uncompressed_bytes),
+                })
+            })
+            .collect();
+
+        let response = serde_json::json!({
+            "compression_enabled": report.compression_enabled,
+            "table_size_mb": (report.table_bytes as f64 / (1024.0 * 1024.0) * 10.0).round() / 10.0,
+            "content_stored_kb": (stored_bytes as f64 / 1024.0).round(),
+            "content_uncompressed_kb": (uncompressed_bytes as f64 / 1024.0).round(),
+            "reduction_pct": reduction_pct(stored_bytes, uncompressed_bytes),
+            "crates": crate_list,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(
+        description = "Preflight check of the database against what the server expects: pgvector installed and new enough for HNSW indexes, every required table and column with the right type, and the embedding column's dimension against the active provider's vectors. Lists each mismatch with its severity and the command that fixes it."
+    )]
+    async fn schema_check(&self) -> Result<CallToolResult, McpError> {
+        let provider = self
+            .document_provider()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let probe = embeddings::probe_provider(provider.as_ref()).await;
+        let mut findings = self
+            .database
+            .schema_check(probe.as_ref().ok().map(|probe| probe.dimension))
+            .await
+            .map_err(|e| McpError::internal_error(format!("Schema check failed: {e}"), None))?;
+        if let Err(e) = &probe {
+            findings.push(SchemaFinding {
+                check: "provider_dimension",
+                severity: AuditSeverity::Warning,
+                detail: format!(
+                    "could not embed with {} to compare its dimension: {e}",
+                    provider.get_model_name()
+                ),
+                fix: None,
+            });
+        }
+        let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+        let (errors, warnings) = (count(AuditSeverity::Error), count(AuditSeverity::Warning));
+        info!("🩺 Schema check: {errors} errors, {warnings} warnings");
+        let response = serde_json::json!({
+            "ok": errors == 0,
+            "errors": errors,
+            "warnings": warnings,
+            "provider": provider.get_provider_name(),
+            "model": provider.get_model_name(),
+            "provider_dimension": probe.as_ref().ok().map(|probe| probe.dimension),
+            "findings": findings,
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(
+        description = "Embed a probe string with the active embedding provider and report its name, model, vector dimension and latency, to check connectivity and size the vector column"
+    )]
+    async fn provider_info(&self) -> Result<CallToolResult, McpError> {
+        let provider = self
+            .document_provider()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let column_dimension = self
+            .database
+            .embedding_column_dimension()
+            .await
+            .unwrap_or_else(|e| {
+                warn!("⚠️  Failed to read the embedding column dimension: {e}");
+                None
+            });
+
+        let probe = match embeddings::probe_provider(provider.as_ref()).await {
+            Ok(probe) => probe,
+            Err(e) => {
+                warn!(
+                    "❌ Embedding provider probe failed ({}, {}): {e}",
+                    provider.get_provider_name(),
+                    provider.get_model_name()
+                );
+                let response = serde_json::json!({
+                    "success": false,
+                    "provider": provider.get_provider_name(),
+                    "model": provider.get_model_name(),
+                    "error": e.to_string(),
+                    "column_dimension": column_dimension,
+                });
+                return Ok(CallToolResult::error(vec![Content::text(
+                    response.to_string(),
+                )]));
+            }
+        };
+
+        let matches_column =
+            column_dimension.map(|dimension| dimension as usize == probe.dimension);
+        let response = serde_json::json!({
+            "success": true,
+            "provider": probe.provider,
+            "model": probe.model,
+            "dimension": probe.dimension,
+            "latency_ms": probe.latency_ms,
+            "column_dimension": column_dimension,
+            "matches_column": matches_column,
+            "note": match (column_dimension, matches_column) {
+                (Some(column), Some(false)) => format!(
+                    "{} returns {}-dimensional vectors but doc_embeddings.embedding is vector({column}); crates populated with it are stored in doc_embeddings_{}",
+                    probe.model, probe.dimension, probe.dimension
+                ),
+                (None, _) => format!(
+                    "doc_embeddings.embedding was not found; create it as vector({})",
+                    probe.dimension
+                ),
+                _ => "Provider is reachable and matches the vector column".to_string(),
+            },
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Report query result cache size and hit rate")]
+    async fn query_cache_stats(&self) -> Result<CallToolResult, McpError> {
+        let response = serde_json::to_value(self.query_cache.stats()).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize cache stats: {e}"), None)
+        })?;
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(
+        description = "Report the last scheduled version refresh: which latest-tracked crates were checked against docs.rs, which changed and were re-populated, and which were skipped and why"
+    )]
+    async fn refresh_status(&self) -> Result<CallToolResult, McpError> {
+        let status = self.refresh_status.lock().unwrap().clone();
+        let mut response = serde_json::to_value(&status).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize refresh status: {e}"), None)
+        })?;
+        if status.interval_secs.is_none() {
+            response["note"] = serde_json::json!(
+                "Version refresh is off; start the server with --version-refresh-interval-hours to enable it"
+            );
+        }
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
     }
-}
 
-// Tool implementation
-#[tool(tool_box)]
-impl McpHandler {
     #[tool(
-        description = "Query documentation for a specific Rust crate using semantic search and LLM summarization."
+        description = "Report server health: whether the database is degraded (background tasks backing off), query cache and population counts, and the last backup when backups are scheduled"
     )]
-    async fn query_rust_docs(
-        &self,
-        #[tool(aggr)] args: QueryRustDocsArgs,
-    ) -> Result<CallToolResult, McpError> {
-        // Check if crate is available (fast in-memory lookup)
-        if !self.is_crate_available(&args.crate_name).await {
-            let crates = self.available_crates.read().await;
-            let available_list: Vec<String> = crates.iter().cloned().collect();
-            return Err(McpError::invalid_params(
-                format!(
-                    "Crate '{}' not available. Available crates: {}",
-                    args.crate_name,
-                    available_list.join(", ")
-                ),
-                None,
-            ));
+    async fn get_server_stats(&self) -> Result<CallToolResult, McpError> {
+        let active_populations = self.populations.active.lock().unwrap().len();
+        let mut response = serde_json::json!({
+            "database": self.db_breaker.status(),
+            "profile": self.profile.as_deref(),
+            "available_crates": self.available_crates.read().await.len(),
+            "active_populations": active_populations,
+            "max_concurrent_populations": self.populations.limit,
+            "query_cache": self.query_cache.stats(),
+            "orientation_answers": self.orientation_stats.snapshot(),
+            "query_defaults": self.query_defaults.effective(search::DEFAULT_RESULT_LIMIT),
+        });
+        response["query_log"] = match self.database.query_log_summary().await {
+            Ok((rows, oldest)) => serde_json::json!({
+                "settings": self.query_log.status(),
+                "rows": rows,
+                "oldest_at": oldest,
+            }),
+            Err(e) => serde_json::json!({
+                "settings": self.query_log.status(),
+                "error": e.to_string(),
+            }),
+        };
+        if self.backup_interval_hours > 0 {
+            response["backup"] = match self.database.get_last_successful_backup().await {
+                Ok(last) => {
+                    let due = chrono::Duration::hours(i64::from(self.backup_interval_hours));
+                    let overdue = last
+                        .as_ref()
+                        .and_then(|run| run.completed_at)
+                        .is_none_or(|at| chrono::Utc::now() - at > due);
+                    serde_json::json!({
+                        "interval_hours": self.backup_interval_hours,
+                        "last_success_at": last.as_ref().and_then(|run| run.completed_at),
+                        "location": last.as_ref().map(|run| &run.location),
+                        "documents": last.as_ref().and_then(|run| run.documents),
+                        "overdue": overdue,
+                    })
+                }
+                Err(e) => serde_json::json!({
+                    "interval_hours": self.backup_interval_hours,
+                    "error": e.to_string(),
+                }),
+            };
         }
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
 
-        // Generate embedding for the question
-        let embedding_client = EMBEDDING_CLIENT.get().ok_or_else(|| {
-            McpError::internal_error("Embedding client not initialized".to_string(), None)
-        })?;
-
-        let (question_embeddings, _) = embedding_client
-            .generate_embeddings(&[args.question.clone()])
-            .await
-            .map_err(|e| {
-                McpError::internal_error(format!("Failed to generate embedding: {e}"), None)
-            })?;
-
-        let question_embedding = Array1::from_vec(
-            question_embeddings
-                .first()
-                .ok_or_else(|| {
-                    McpError::internal_error("No embedding generated".to_string(), None)
-                })?
-                .clone(),
-        );
+    #[tool(description = "Remove a crate configuration")]
+    async fn remove_crate(
+        &self,
+        #[tool(aggr)] args: RemoveCrateArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        self.ensure_writable("remove_crate")?;
+        let version_spec = args.version_spec.unwrap_or_else(|| "latest".to_string());
+        let target = args.target.filter(|t| !t.trim().is_empty());
+        let label = match &target {
+            Some(target) => format!("{version_spec}, {target}"),
+            None => version_spec.clone(),
+        };
 
-        // Perform semantic search using the embedding
         match self
             .database
-            .search_similar_docs(&args.crate_name, &question_embedding, 10)
+            .delete_crate_config(&args.crate_name, &version_spec, target.as_deref())
             .await
         {
-            Ok(results) => {
-                if results.is_empty() {
-                    Ok(CallToolResult::success(vec![Content::text(format!(
-                        "No relevant documentation found for '{}' in crate '{}'",
-                        args.question, args.crate_name
-                    ))]))
-                } else {
-                    // Format search results - results are tuples (id, content, similarity)
-                    let crate_name = &args.crate_name;
-                    let mut response =
-                        format!("From {crate_name} docs (via vector database search): ");
-
-                    // Take top results and format them
-                    let formatted_results: Vec<String> = results
-                        .into_iter()
-                        .take(5) // Limit to top 5 results
-                        .enumerate()
-                        .map(|(i, (_, content, similarity))| {
-                            let idx = i + 1;
-                            let content_trimmed = content.trim();
-                            format!("{idx}. {content_trimmed} (similarity: {similarity:.3})")
-                        })
-                        .collect();
+            Ok(deleted) => {
+                if deleted {
+                    let remaining = self
+                        .database
+                        .get_crate_configs_by_name(&args.crate_name)
+                        .await
+                        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                    // Another version spec of the same target still reads its documents
+                    let documents_removed = if remaining.iter().any(|c| c.target == target) {
+                        0
+                    } else {
+                        self.database
+                            .delete_crate_target(&args.crate_name, target.as_deref())
+                            .await
+                            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+                    };
+                    // Other versions or targets of the crate stay queryable
+                    if remaining.is_empty() {
+                        self.remove_crate_from_available(&args.crate_name).await;
+                    }
+                    self.invalidate_query_cache(&args.crate_name);
 
-                    response.push_str(&formatted_results.join("\n\n"));
-                    Ok(CallToolResult::success(vec![Content::text(response)]))
+                    let response = serde_json::json!({
+                        "success": true,
+                        "message": format!("Removed crate configuration for {} ({label})", args.crate_name),
+                        "documents_removed": documents_removed,
+                    });
+                    Ok(CallToolResult::success(vec![Content::text(
+                        response.to_string(),
+                    )]))
+                } else {
+                    Err(McpError::invalid_params(
+                        format!("No configuration found for {} ({label})", args.crate_name),
+                        None,
+                    ))
                 }
             }
             Err(e) => Err(McpError::internal_error(
-                format!("Database search error: {e}"),
+                format!("Failed to remove crate: {e}"),
                 None,
             )),
         }
     }
 
-    #[tool(description = "Add or update a crate configuration")]
-    async fn add_crate(
+    #[tool(
+        description = "Rename a crate in place, keeping its stored docs and embeddings (no re-crawl or re-embedding)"
+    )]
+    async fn rename_crate(
         &self,
-        #[tool(aggr)] args: AddCrateArgs,
+        #[tool(aggr)] args: RenameCrateArgs,
     ) -> Result<CallToolResult, McpError> {
-        use rustdocs_mcp_server::database::CrateConfig;
+        args.validate()?;
+        self.ensure_writable("rename_crate")?;
+        let old_name = args.old_name.trim();
+        let new_name = args.new_name.trim();
+
+        let report = self
+            .database
+            .rename_crate(old_name, new_name)
+            .await
+            .map_err(|e| match e {
+                ServerError::Config(message) => McpError::invalid_params(message, None),
+                e => McpError::internal_error(format!("Failed to rename crate: {e}"), None),
+            })?;
 
+        let was_available = self.is_crate_available(old_name).await;
+        self.remove_crate_from_available(old_name).await;
+        self.invalidate_query_cache(old_name);
+        if was_available {
+            self.add_crate_to_available(new_name).await;
+        }
         info!(
-            "🔧 add_crate called for: {} ({})",
-            args.crate_name, args.version_spec
+            "✏️  Renamed {old_name} to {new_name} ({} embeddings, {} configurations)",
+            report.embeddings, report.configs
         );
 
-        // Validate inputs
-        if args.crate_name.is_empty() {
-            return Err(McpError::invalid_params("Crate name cannot be empty", None));
-        }
+        let response = serde_json::json!({
+            "success": true,
+            "old_name": old_name,
+            "new_name": new_name,
+            "embeddings_renamed": report.embeddings,
+            "crates_renamed": report.crates,
+            "configurations_renamed": report.configs,
+            "message": format!("Renamed {old_name} to {new_name}; stored embeddings were kept")
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(
+        description = "Enable or disable a crate for queries without touching its stored docs; re-enabling does not repopulate"
+    )]
+    async fn set_crate_enabled(
+        &self,
+        #[tool(aggr)] args: SetCrateEnabledArgs,
+    ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+        self.ensure_writable("set_crate_enabled")?;
+        let crate_name = &args.crate_name;
 
-        if args.version_spec != "latest" && !args.version_spec.chars().any(|c| c.is_numeric()) {
+        let configs = self
+            .database
+            .get_crate_configs_by_name(crate_name)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to get crate configs: {e}"), None)
+            })?;
+        if configs.is_empty() {
             return Err(McpError::invalid_params(
-                "Version spec must be 'latest' or a valid version number",
+                format!("No configuration found for {crate_name}"),
                 None,
             ));
         }
 
-        // If expected_docs not provided, try to scan for it
-        let expected_docs = args.expected_docs.unwrap_or(1000); // Default for now
-
-        // Create config
-        let config = CrateConfig {
-            id: 0, // Will be set by database
-            name: args.crate_name.clone(),
-            version_spec: args.version_spec.clone(),
-            current_version: None, // Will be set during population
-            features: args.features.unwrap_or_default(),
-            expected_docs,
-            enabled: args.enabled.unwrap_or(true),
-            last_checked: None,
-            last_populated: None,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-        };
-
-        // Save to database
-        match self.database.upsert_crate_config(&config).await {
-            Ok(saved_config) => {
-                // Create a population job
-                let _ = self.database.create_population_job(saved_config.id).await;
-
-                // Return response immediately
-                let response = "Ingestion has started".to_string();
-                let result = Ok(CallToolResult::success(vec![Content::text(response)]));
-
-                // Spawn background population task after returning response
-                let crate_name = args.crate_name.clone();
-                let features = saved_config.features.clone();
-                let handler_clone = self.clone();
-                tokio::spawn(async move {
-                    match handler_clone.populate_crate(&crate_name, &features).await {
-                        Ok(_) => {
-                            // Add the crate to the in-memory cache after successful population
-                            handler_clone.add_crate_to_available(&crate_name).await;
-                            eprintln!("✅ Background population completed for crate: {crate_name}");
-                        }
-                        Err(e) => {
-                            eprintln!(
-                                "⚠️  Background population failed for crate {crate_name}: {e}"
-                            );
-                        }
-                    }
-                });
+        let changed = self
+            .database
+            .set_crate_enabled(crate_name, args.enabled)
+            .await
+            .map_err(|e| McpError::internal_error(format!("Failed to update crate: {e}"), None))?;
 
-                result
+        if args.enabled {
+            let has_embeddings = self
+                .database
+                .has_embeddings(crate_name)
+                .await
+                .map_err(|e| {
+                    McpError::internal_error(format!("Failed to check embeddings: {e}"), None)
+                })?;
+            if has_embeddings {
+                self.add_crate_to_available(crate_name).await;
             }
-            Err(e) => Err(McpError::internal_error(
-                format!("Failed to save crate configuration: {e}"),
-                None,
-            )),
+            info!("👁️  Enabled {crate_name} ({changed} configurations changed)");
+        } else {
+            self.remove_crate_from_available(crate_name).await;
+            self.invalidate_query_cache(crate_name);
+            info!("🙈 Disabled {crate_name} ({changed} configurations changed)");
         }
+
+        let state = if args.enabled { "enabled" } else { "disabled" };
+        let response = serde_json::json!({
+            "success": true,
+            "crate_name": crate_name,
+            "enabled": args.enabled,
+            "configurations_changed": changed,
+            "message": format!("{crate_name} is {state}; stored documentation was not changed")
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
     }
 
-    #[tool(description = "List all configured crates")]
-    async fn list_crates(
+    #[tool(
+        description = "Start populating every enabled crate configuration that has no stored docs yet, e.g. after migrate_config; returns the crates started. Runs at most --max-concurrent-populations at once, the rest queue"
+    )]
+    async fn populate_pending(
         &self,
-        #[tool(aggr)] args: ListCratesArgs,
+        #[tool(aggr)] args: PopulatePendingArgs,
     ) -> Result<CallToolResult, McpError> {
-        match self
+        let dry_run = args.dry_run.unwrap_or(false);
+        if !dry_run {
+            self.ensure_writable("populate_pending")?;
+            // Shares the add_crate budget: each call can start many populations
+            self.check_rate_limit("add_crate", self.rate_limits.add_crate_per_minute)?;
+        }
+
+        let configs = self
             .database
-            .get_crate_configs(args.enabled_only.unwrap_or(false))
+            .get_unpopulated_crate_configs()
             .await
-        {
-            Ok(configs) => {
-                let crate_list: Vec<serde_json::Value> = configs.iter().map(|config| {
-                    serde_json::json!({
-                        "name": config.name,
-                        "version_spec": config.version_spec,
-                        "current_version": config.current_version,
-                        "features": config.features,
-                        "enabled": config.enabled,
-                        "expected_docs": config.expected_docs,
-                        "last_populated": config.last_populated,
-                        "status": if config.last_populated.is_some() { "populated" } else { "pending" }
-                    })
-                }).collect();
-
-                let response = serde_json::json!({
-                    "crates": crate_list,
-                    "total": configs.len()
-                });
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to find pending crates: {e}"), None)
+            })?;
 
-                Ok(CallToolResult::success(vec![Content::text(
-                    response.to_string(),
-                )]))
+        let mut listed = Vec::new();
+        let mut already_running = Vec::new();
+        for config in configs {
+            let mut entry = serde_json::json!({
+                "crate_name": config.name,
+                "version_spec": config.version_spec,
+                "target": config.target,
+            });
+            if self.populations.is_active(config.id) {
+                already_running.push(entry);
+                continue;
             }
-            Err(e) => Err(McpError::internal_error(
-                format!("Failed to list crates: {e}"),
-                None,
-            )),
+            if !dry_run {
+                let job_id = self.database.create_population_job(config.id).await.ok();
+                entry["job_id"] = serde_json::json!(job_id);
+                self.spawn_population(config, job_id);
+            }
+            listed.push(entry);
         }
+        info!(
+            "📦 populate_pending {} {} crate configurations ({} already populating)",
+            if dry_run { "found" } else { "started" },
+            listed.len(),
+            already_running.len()
+        );
+
+        let message = match (dry_run, listed.len()) {
+            (_, 0) => "No pending crate configurations".to_string(),
+            (true, n) => format!("{n} crate configurations are waiting to be populated"),
+            (false, n) => format!(
+                "Started population of {n} crate configurations, at most {} at a time; follow progress with check_crate_status",
+                self.populations.limit
+            ),
+        };
+        let response = serde_json::json!({
+            if dry_run { "pending" } else { "started" }: listed,
+            "already_running": already_running,
+            "dry_run": dry_run,
+            "max_concurrent_populations": self.populations.limit,
+            "message": message,
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
     }
 
-    #[tool(description = "Check the status of crate population jobs")]
-    async fn check_crate_status(
+    #[tool(
+        description = "Admin: vector search without formatting. Searches one or more crates with a question or a raw embedding and returns JSON rows with distances, optionally with content, metadata and stored vectors. Requires --admin-tools"
+    )]
+    async fn raw_search(
         &self,
-        #[tool(aggr)] args: CheckCrateStatusArgs,
+        #[tool(aggr)] args: RawSearchArgs,
     ) -> Result<CallToolResult, McpError> {
-        // Get crate configs
-        let configs = self.database.get_crate_configs(false).await.map_err(|e| {
-            McpError::internal_error(format!("Failed to get crate configs: {e}"), None)
-        })?;
-
-        // Find the requested crate
-        let config = configs
-            .iter()
-            .find(|c| c.name == args.crate_name)
-            .ok_or_else(|| {
-                McpError::invalid_params(format!("Crate '{}' not found", args.crate_name), None)
-            })?;
-
-        // Check if crate has embeddings (has been populated)
-        let has_embeddings = self
-            .database
-            .has_embeddings(&args.crate_name)
-            .await
-            .map_err(|e| {
-                McpError::internal_error(format!("Failed to check embeddings: {e}"), None)
-            })?;
+        args.validate()?;
+        self.ensure_admin("raw_search")?;
+        // Shares the query budget: each call embeds at most one question
+        self.check_rate_limit("query_rust_docs", self.rate_limits.query_per_minute)?;
+
+        for crate_name in &args.crate_names {
+            if !self.is_crate_available(crate_name).await {
+                return Err(self.crate_unavailable(crate_name).await);
+            }
+        }
 
-        // Get document count
-        let total_docs = if has_embeddings {
-            self.database
-                .count_crate_documents(&args.crate_name)
-                .await
-                .unwrap_or(0) as i32
-        } else {
-            0
+        let (embedding, model) = match args.query() {
+            Some(RawQuery::Embedding(embedding)) => (embedding.to_vec(), None),
+            Some(RawQuery::Question(question)) => {
+                let document_client = self
+                    .document_provider()
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                let embedding_client = self.query_provider.as_ref().unwrap_or(&document_client);
+                let (mut embeddings, _) = embedding_client
+                    .generate_embeddings(&[question.to_string()])
+                    .await
+                    .map_err(|e| {
+                        McpError::internal_error(format!("Failed to generate embedding: {e}"), None)
+                    })?;
+                let embedding = embeddings.pop().ok_or_else(|| {
+                    McpError::internal_error("Embedding provider returned no vector", None)
+                })?;
+                (
+                    embedding,
+                    Some(embedding_client.get_model_name().to_string()),
+                )
+            }
+            // Unreachable after validation
+            None => {
+                return Err(McpError::invalid_params(
+                    "question or embedding is required",
+                    None,
+                ))
+            }
         };
+        let dimension = embedding.len();
+        for crate_name in &args.crate_names {
+            let stored = self
+                .database
+                .crate_embedding_dimension(crate_name)
+                .await
+                .map_err(|e| {
+                    McpError::internal_error(
+                        format!("Failed to look up embedding dimension: {e}"),
+                        None,
+                    )
+                })?;
+            args.check_dimension(crate_name, stored, dimension)?;
+        }
 
-        let status = serde_json::json!({
-            "crate_name": config.name,
-            "version_spec": config.version_spec,
-            "current_version": config.current_version,
-            "enabled": config.enabled,
-            "last_populated": config.last_populated,
-            "has_embeddings": has_embeddings,
-            "total_docs": total_docs,
-            "features": config.features,
-            "expected_docs": config.expected_docs,
-            "status": if has_embeddings && total_docs > 0 {
-                "populated"
-            } else if has_embeddings {
-                "empty"
-            } else {
-                "not_populated"
-            },
-            "note": if !has_embeddings || total_docs == 0 {
-                format!("Run on server: cargo run --bin populate_db -- --crate-name {} --features {}",
-                    config.name, config.features.join(" "))
-            } else {
-                "Crate is populated and ready for queries".to_string()
+        let limit = args.limit.unwrap_or(20) as usize;
+        let query = Array1::from_vec(embedding);
+        let searches = args.crate_names.iter().map(|crate_name| {
+            let query = &query;
+            async move {
+                self.database
+                    .search_similar_docs_scored(crate_name, query, limit as i32)
+                    .await
+                    .map(|docs| (crate_name, docs))
             }
         });
+        let result_sets = futures::future::try_join_all(searches)
+            .await
+            .map_err(|e| McpError::internal_error(format!("Search failed: {e}"), None))?;
+
+        let mut hits: Vec<(&String, ScoredDoc)> = result_sets
+            .into_iter()
+            .flat_map(|(crate_name, docs)| docs.into_iter().map(move |doc| (crate_name, doc)))
+            .collect();
+        hits.sort_by(|(a_crate, a), (b_crate, b)| {
+            a.distance
+                .total_cmp(&b.distance)
+                .then_with(|| a_crate.cmp(b_crate))
+                .then_with(|| a.doc_path.cmp(&b.doc_path))
+        });
+        hits.truncate(limit);
+
+        let mut vectors: HashMap<&String, HashMap<String, Vec<f32>>> = HashMap::new();
+        if args.include_vectors.unwrap_or(false) {
+            for crate_name in &args.crate_names {
+                let doc_paths: Vec<String> = hits
+                    .iter()
+                    .filter(|(name, _)| *name == crate_name)
+                    .map(|(_, doc)| doc.doc_path.clone())
+                    .collect();
+                if doc_paths.is_empty() {
+                    continue;
+                }
+                let stored = self
+                    .database
+                    .get_doc_embeddings(crate_name, dimension, &doc_paths)
+                    .await
+                    .map_err(|e| {
+                        McpError::internal_error(format!("Failed to load embeddings: {e}"), None)
+                    })?;
+                vectors.insert(crate_name, stored);
+            }
+        }
+
+        let rows: Vec<serde_json::Value> = hits
+            .into_iter()
+            .map(|(crate_name, doc)| {
+                let mut row = serde_json::json!({
+                    "crate_name": crate_name,
+                    "doc_path": doc.doc_path,
+                    "distance": doc.distance,
+                    "similarity": doc.similarity,
+                });
+                if args.include_content.unwrap_or(false) {
+                    row["content"] = serde_json::json!(doc.content);
+                }
+                if args.include_metadata.unwrap_or(false) {
+                    row["last_updated_at"] = serde_json::json!(doc.last_updated_at);
+                    row["crate_version"] = serde_json::json!(doc.crate_version);
+                }
+                if let Some(stored) = vectors.get(crate_name) {
+                    row["embedding"] = serde_json::json!(stored.get(&doc.doc_path));
+                }
+                row
+            })
+            .collect();
+        info!(
+            "🔬 raw_search over {} crates returned {} rows (connection: {})",
+            args.crate_names.len(),
+            rows.len(),
+            self.connection_id
+        );
 
+        let response = serde_json::json!({
+            "model": model,
+            "dimension": dimension,
+            "rows": rows,
+        });
         Ok(CallToolResult::success(vec![Content::text(
-            status.to_string(),
+            response.to_string(),
         )]))
     }
 
-    #[tool(description = "Remove a crate configuration")]
-    async fn remove_crate(
+    #[tool(
+        description = "Find stored docs for crates that no longer have a configuration; dry run by default, pass dry_run=false to delete them"
+    )]
+    async fn gc_orphans(
         &self,
-        #[tool(aggr)] args: RemoveCrateArgs,
+        #[tool(aggr)] args: GcOrphansArgs,
     ) -> Result<CallToolResult, McpError> {
-        let version_spec = args.version_spec.unwrap_or_else(|| "latest".to_string());
+        let dry_run = args.dry_run.unwrap_or(true);
+        if !dry_run {
+            self.ensure_writable("gc_orphans")?;
+        }
 
-        match self
+        let report = self
             .database
-            .delete_crate_config(&args.crate_name, &version_spec)
+            .delete_orphaned_embeddings(dry_run)
             .await
-        {
-            Ok(deleted) => {
-                if deleted {
-                    // Remove from in-memory cache
-                    self.remove_crate_from_available(&args.crate_name).await;
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to collect orphans: {e}"), None)
+            })?;
 
-                    let response = serde_json::json!({
-                        "success": true,
-                        "message": format!("Removed crate configuration for {} ({})", args.crate_name, version_spec)
-                    });
-                    Ok(CallToolResult::success(vec![Content::text(
-                        response.to_string(),
-                    )]))
-                } else {
-                    Err(McpError::invalid_params(
-                        format!(
-                            "No configuration found for {} ({})",
-                            args.crate_name, version_spec
-                        ),
-                        None,
-                    ))
-                }
+        if !dry_run {
+            for crate_name in &report.crate_names {
+                self.remove_crate_from_available(crate_name).await;
+                self.invalidate_query_cache(crate_name);
             }
-            Err(e) => Err(McpError::internal_error(
-                format!("Failed to remove crate: {e}"),
-                None,
-            )),
+            info!(
+                "🧹 Deleted {} orphaned embeddings and {} crate rows ({} crates)",
+                report.embeddings,
+                report.crates,
+                report.crate_names.len()
+            );
         }
+
+        let action = if dry_run { "Would delete" } else { "Deleted" };
+        let response = serde_json::json!({
+            "success": true,
+            "dry_run": dry_run,
+            "crate_names": report.crate_names,
+            "embeddings": report.embeddings,
+            "crates": report.crates,
+            "message": format!(
+                "{action} {} embeddings and {} crate rows for {} unconfigured crates",
+                report.embeddings,
+                report.crates,
+                report.crate_names.len()
+            )
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
     }
 
     #[tool(description = "Add or update multiple crate configurations")]
@@ -878,26 +4005,25 @@ impl McpHandler {
         &self,
         #[tool(aggr)] args: AddCratesArgs,
     ) -> Result<CallToolResult, McpError> {
-        use rustdocs_mcp_server::database::CrateConfig;
-
         info!("🔧 add_crates called for {} crates", args.crates.len());
-
-        if args.crates.is_empty() {
-            return Err(McpError::invalid_params("No crates provided", None));
-        }
+        args.validate()?;
+        self.ensure_writable("add_crates")?;
+        // Shares the add_crate budget so alternating between the two doesn't double it
+        self.check_rate_limit("add_crate", self.rate_limits.add_crate_per_minute)?;
 
         let fail_fast = args.fail_fast.unwrap_or(false);
         let mut results = Vec::new();
         let mut successful_count = 0;
         let mut failed_count = 0;
         let mut ingestion_started_count = 0;
+        let mut ingestion_queued_count = 0;
 
         // Process each crate
         for crate_spec in args.crates {
             info!("Processing crate: {}", crate_spec.crate_name);
 
-            // Validate inputs
-            let validation_result = self.validate_crate_spec(&crate_spec).await;
+            // Entries are checked one by one so a bad entry only fails itself
+            let validation_result = crate_spec.validate().map_err(|e| e.to_string());
 
             match validation_result {
                 Ok(_) => {
@@ -907,50 +4033,96 @@ impl McpHandler {
                         name: crate_spec.crate_name.clone(),
                         version_spec: crate_spec.version_spec.clone(),
                         current_version: None, // Will be set during population
-                        features: crate_spec.features.unwrap_or_default(),
+                        features: crate_spec
+                            .features
+                            .unwrap_or_else(|| self.default_crate_features.to_vec()),
                         expected_docs: crate_spec.expected_docs.unwrap_or(1000),
                         enabled: crate_spec.enabled.unwrap_or(true),
                         last_checked: None,
                         last_populated: None,
                         created_at: chrono::Utc::now(),
                         updated_at: chrono::Utc::now(),
+                        content_selector: crate_spec
+                            .content_selector
+                            .clone()
+                            .filter(|s| !s.trim().is_empty()),
+                        include_url_patterns: crate_spec
+                            .include_url_patterns
+                            .clone()
+                            .unwrap_or_default(),
+                        exclude_url_patterns: crate_spec
+                            .exclude_url_patterns
+                            .clone()
+                            .unwrap_or_default(),
+                        follow_reexports: crate_spec.follow_reexports.unwrap_or(false),
+                        reexport_crates: crate_spec.reexport_crates.clone().unwrap_or_default(),
+                        reexport_page_budget: crate_spec.reexport_page_budget,
+                        reexport_pages_per_crate: crate_spec.reexport_pages_per_crate,
+                        rustdoc_json_url: crate_spec
+                            .rustdoc_json_url
+                            .clone()
+                            .filter(|url| !url.trim().is_empty()),
+                        target: crate_spec.target.clone().filter(|t| !t.trim().is_empty()),
+                        auto_expected_docs: crate_spec
+                            .auto_expected_docs
+                            .unwrap_or(crate_spec.expected_docs.is_none()),
+                        query_hint: crate_spec
+                            .query_hint
+                            .clone()
+                            .filter(|h| !h.trim().is_empty()),
+                        default_limit: crate_spec.default_limit,
+                        default_min_similarity: crate_spec.default_min_similarity,
+                        default_path_prefix: crate_spec
+                            .default_path_prefix
+                            .clone()
+                            .filter(|p| !p.trim().is_empty()),
+                        max_pages: None,
+                        store_raw_html: crate_spec.store_raw_html.unwrap_or(false),
+                        languages: crate_spec.languages.clone().unwrap_or_default(),
+                        language_action: crate_spec
+                            .language_action
+                            .clone()
+                            .filter(|a| !a.trim().is_empty()),
+                        max_embeddings: crate_spec.max_embeddings,
+                        truncated: false,
+                        max_duration_secs: crate_spec.max_duration_secs,
+                        partial_at: None,
+                        low_doc_coverage: false,
+                        section_metadata: crate_spec.section_metadata.unwrap_or(false),
+                        allow_prerelease: crate_spec.allow_prerelease.unwrap_or(false),
                     };
 
                     // Save to database
                     match self.database.upsert_crate_config(&config).await {
                         Ok(saved_config) => {
+                            self.invalidate_query_cache(&saved_config.name);
+
                             // Create a population job
-                            let _ = self.database.create_population_job(saved_config.id).await;
+                            let job_id = self
+                                .database
+                                .create_population_job(saved_config.id)
+                                .await
+                                .ok();
 
                             successful_count += 1;
-                            ingestion_started_count += 1;
+
+                            // Spawn background population task; it waits if all slots are busy
+                            let message = if self.spawn_population(saved_config, job_id) {
+                                ingestion_started_count += 1;
+                                "Configuration saved, ingestion started"
+                            } else {
+                                ingestion_queued_count += 1;
+                                "Configuration saved, ingestion queued until a population slot frees up"
+                            };
 
                             let result = CrateResult {
                                 crate_name: crate_spec.crate_name.clone(),
                                 success: true,
                                 error: None,
-                                message: "Configuration saved, ingestion queued".to_string(),
+                                job_id,
+                                message: message.to_string(),
                             };
                             results.push(result);
-
-                            // Spawn background population task
-                            let crate_name = crate_spec.crate_name.clone();
-                            let features = saved_config.features.clone();
-                            let handler_clone = self.clone();
-                            tokio::spawn(async move {
-                                match handler_clone.populate_crate(&crate_name, &features).await {
-                                    Ok(_) => {
-                                        // Add the crate to the in-memory cache after successful population
-                                        handler_clone.add_crate_to_available(&crate_name).await;
-                                        eprintln!("✅ Background population completed for crate: {crate_name}");
-                                    }
-                                    Err(e) => {
-                                        eprintln!(
-                                            "⚠️  Background population failed for crate {crate_name}: {e}"
-                                        );
-                                    }
-                                }
-                            });
                         }
                         Err(e) => {
                             failed_count += 1;
@@ -958,6 +4130,7 @@ impl McpHandler {
                                 crate_name: crate_spec.crate_name.clone(),
                                 success: false,
                                 error: Some(e.to_string()),
+                                job_id: None,
                                 message: "Failed to save configuration".to_string(),
                             };
                             results.push(result);
@@ -974,6 +4147,7 @@ impl McpHandler {
                         crate_name: crate_spec.crate_name.clone(),
                         success: false,
                         error: Some(validation_error),
+                        job_id: None,
                         message: "Validation failed".to_string(),
                     };
                     results.push(result);
@@ -991,14 +4165,20 @@ impl McpHandler {
             successful: successful_count,
             failed: failed_count,
             ingestion_started: ingestion_started_count,
+            ingestion_queued: ingestion_queued_count,
+            max_concurrent_populations: self.populations.limit,
         };
 
+        let ingestion = format!(
+            "{ingestion_started_count} ingesting now, {ingestion_queued_count} queued (at most {} at once)",
+            self.populations.limit
+        );
         let message = if failed_count == 0 {
-            format!("Successfully configured {successful_count} crates, ingestion started")
+            format!("Successfully configured {successful_count} crates; {ingestion}")
         } else if successful_count == 0 {
             format!("Failed to configure any crates ({failed_count} errors)")
         } else {
-            format!("Configured {successful_count} crates successfully, {failed_count} failed")
+            format!("Configured {successful_count} crates successfully, {failed_count} failed; {ingestion}")
         };
 
         let response = AddCratesResponse {
@@ -1013,25 +4193,46 @@ impl McpHandler {
             })?,
         )]))
     }
+}
 
-    // Helper method to validate crate specifications
-    async fn validate_crate_spec(&self, crate_spec: &CrateSpec) -> Result<(), String> {
-        if crate_spec.crate_name.is_empty() {
-            return Err("Crate name cannot be empty".to_string());
-        }
+// Health check handler with liveness and readiness endpoints
+/// `crate_info` payload; `stale_note` is set when crates.io couldn't refresh old data
+fn crate_info_result(metadata: &CrateMetadata, stale_note: Option<String>) -> CallToolResult {
+    let mut response = serde_json::json!(metadata);
+    response["stale"] = serde_json::json!(stale_note.is_some());
+    if let Some(note) = stale_note {
+        response["note"] = serde_json::json!(note);
+    }
+    CallToolResult::success(vec![Content::text(response.to_string())])
+}
 
-        if crate_spec.version_spec != "latest"
-            && !crate_spec.version_spec.chars().any(|c| c.is_numeric())
-        {
-            return Err("Version spec must be 'latest' or a valid version number".to_string());
+/// One scheduled consistency audit: log every finding and update the `/metrics` gauge
+async fn run_scheduled_audit(db: &Database, db_breaker: &DbBreaker, metrics: &AuditMetrics) {
+    let findings = match db_breaker.call(db.audit_consistency()).await {
+        Ok(findings) => findings,
+        Err(e) => {
+            warn!("⚠️  Consistency audit failed: {e}");
+            return;
+        }
+    };
+    for finding in &findings {
+        let message = format!(
+            "🩺 Audit [{}] {}: {} (fix: {})",
+            finding.check, finding.crate_name, finding.detail, finding.fix_sql
+        );
+        match finding.severity {
+            AuditSeverity::Error => error!("{message}"),
+            AuditSeverity::Warning => warn!("{message}"),
+            AuditSeverity::Info => info!("{message}"),
         }
-
-        // Additional validation can be added here
-        Ok(())
     }
+    metrics.record(&findings);
+    info!(
+        "🩺 Consistency audit complete: {} findings (run `db_maintenance audit --fix` to apply safe fixes)",
+        findings.len()
+    );
 }
 
-// Health check handler with liveness and readiness endpoints
 fn create_health_handler(
     readiness_state: ReadinessState,
 ) -> impl Fn(Request<hyper::body::Incoming>) -> Result<Response<String>, Infallible> + Clone {
@@ -1052,11 +4253,12 @@ fn create_health_handler(
                     let auto_population_complete = readiness_state
                         .auto_population_complete
                         .load(Ordering::Relaxed);
+                    let crates = readiness_state.crate_states_json();
                     let response = Response::builder()
                         .status(StatusCode::OK)
                         .header("Content-Type", "application/json")
                         .body(format!(
-                            r#"{{"status":"ready","service":"rustdocs-mcp-server","auto_population_complete":{auto_population_complete}}}"#
+                            r#"{{"status":"ready","service":"rustdocs-mcp-server","degraded":false,"auto_population_complete":{auto_population_complete},"crates":{crates}}}"#
                         ))
                         .unwrap();
                     Ok(response)
@@ -1065,15 +4267,26 @@ fn create_health_handler(
                         .status(StatusCode::SERVICE_UNAVAILABLE)
                         .header("Content-Type", "application/json")
                         .body(format!(
-                            r#"{{"status":"not_ready","service":"rustdocs-mcp-server","database_connected":{},"embedding_initialized":{},"auto_population_complete":{}}}"#,
+                            r#"{{"status":"not_ready","service":"rustdocs-mcp-server","database_connected":{},"degraded":{},"embedding_initialized":{},"warmup_complete":{},"auto_population_complete":{},"crates":{}}}"#,
                             readiness_state.database_connected.load(Ordering::Relaxed),
+                            readiness_state.db_breaker.is_degraded(),
                             readiness_state.embedding_initialized.load(Ordering::Relaxed),
-                            readiness_state.auto_population_complete.load(Ordering::Relaxed)
+                            readiness_state.warmup_complete.load(Ordering::Relaxed),
+                            readiness_state.auto_population_complete.load(Ordering::Relaxed),
+                            readiness_state.crate_states_json()
                         ))
                         .unwrap();
                     Ok(response)
                 }
             }
+            (&Method::GET, "/metrics") => {
+                let response = Response::builder()
+                    .status(StatusCode::OK)
+                    .header("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+                    .body(readiness_state.audit.render())
+                    .unwrap();
+                Ok(response)
+            }
             (&Method::GET, "/health") => {
                 // Legacy endpoint - redirect to liveness
                 let response = Response::builder()
@@ -1094,13 +4307,45 @@ fn create_health_handler(
     }
 }
 
+/// Provider settings for `openai` or `voyage`, using the provider's default model when none is given
+fn embedding_config(
+    provider_name: &str,
+    model: Option<String>,
+) -> Result<EmbeddingConfig, ServerError> {
+    match provider_name {
+        "openai" => {
+            let model = model.unwrap_or_else(|| "text-embedding-3-large".to_string());
+            let openai_client = if let Ok(api_base) = env::var("OPENAI_API_BASE") {
+                let config = OpenAIConfig::new().with_api_base(api_base);
+                OpenAIClient::with_config(config)
+            } else {
+                OpenAIClient::new()
+            };
+            Ok(EmbeddingConfig::OpenAI {
+                client: openai_client,
+                model,
+            })
+        }
+        "voyage" => {
+            let api_key = env::var("VOYAGE_API_KEY")
+                .map_err(|_| ServerError::MissingEnvVar("VOYAGE_API_KEY".to_string()))?;
+            let model = model.unwrap_or_else(|| "voyage-3.5".to_string());
+            Ok(EmbeddingConfig::VoyageAI { api_key, model })
+        }
+        _ => Err(ServerError::Config(format!(
+            "Unsupported embedding provider: {provider_name}. Use 'openai' or 'voyage'"
+        ))),
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), ServerError> {
     // Initialize tracing
     tracing_subscriber::registry()
         .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "rustdocs_mcp_server_http=info,rmcp=info".into()),
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+                "rustdocs_mcp_server_http=info,rustdocs_mcp_server=info,rmcp=info".into()
+            }),
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
@@ -1116,7 +4361,11 @@ async fn main() -> Result<(), ServerError> {
     info!("🚀 Starting Rust Docs MCP HTTP SSE Server on {host}:{port}");
 
     // Create readiness state for health checks
-    let readiness_state = ReadinessState::new();
+    let db_breaker = Arc::new(DbBreaker::new(
+        cli.db_failure_threshold,
+        Duration::from_secs(cli.db_max_backoff_secs),
+    ));
+    let readiness_state = ReadinessState::new(db_breaker.clone());
 
     // Start health check server early (before auto-population)
     let health_addr: SocketAddr = format!("{host}:8080")
@@ -1185,16 +4434,31 @@ async fn main() -> Result<(), ServerError> {
     info!("Target crates: {:?}", crate_names);
 
     // Check if all crates exist in database
+    // Crates embedded with a model of another dimension keep their vectors in side tables
+    let extra_dimensions = db.embedding_dimensions().await?;
+    if extra_dimensions.is_empty() {
+        info!("📐 Single embedding dimension ({EMBEDDING_DIMENSION})");
+    } else {
+        info!(
+            "📐 Embedding dimensions: {EMBEDDING_DIMENSION} (doc_embeddings), plus {extra_dimensions:?} in per-dimension tables"
+        );
+    }
+
     info!("🔍 Checking if crates exist in database...");
+    // One query for every crate: per-crate round trips add up on a remote database
+    let presence = db.get_embeddings_presence(&crate_names).await?;
     let mut available_crates = Vec::new();
     let mut missing_crates = Vec::new();
     for crate_name in &crate_names {
-        if !db.has_embeddings(crate_name).await? {
-            missing_crates.push(crate_name.clone());
-            warn!("❌ Missing: {crate_name}");
-        } else {
-            available_crates.push(crate_name.clone());
-            info!("✅ Found: {crate_name}");
+        match presence.get(crate_name).copied().unwrap_or(0) {
+            0 => {
+                missing_crates.push(crate_name.clone());
+                warn!("❌ Missing: {crate_name}");
+            }
+            docs => {
+                available_crates.push(crate_name.clone());
+                info!("✅ Found: {crate_name} ({docs} docs)");
+            }
         }
     }
 
@@ -1202,38 +4466,10 @@ async fn main() -> Result<(), ServerError> {
     let provider_name = cli.embedding_provider.to_lowercase();
     info!("🤖 Initializing {provider_name} embedding provider...");
 
-    let embedding_config = match provider_name.as_str() {
-        "openai" => {
-            let model = cli
-                .embedding_model
-                .unwrap_or_else(|| "text-embedding-3-large".to_string());
-            let openai_client = if let Ok(api_base) = env::var("OPENAI_API_BASE") {
-                let config = OpenAIConfig::new().with_api_base(api_base);
-                OpenAIClient::with_config(config)
-            } else {
-                OpenAIClient::new()
-            };
-            EmbeddingConfig::OpenAI {
-                client: openai_client,
-                model,
-            }
-        }
-        "voyage" => {
-            let api_key = env::var("VOYAGE_API_KEY")
-                .map_err(|_| ServerError::MissingEnvVar("VOYAGE_API_KEY".to_string()))?;
-            let model = cli
-                .embedding_model
-                .unwrap_or_else(|| "voyage-3.5".to_string());
-            EmbeddingConfig::VoyageAI { api_key, model }
-        }
-        _ => {
-            return Err(ServerError::Config(format!(
-                "Unsupported embedding provider: {provider_name}. Use 'openai' or 'voyage'"
-            )));
-        }
-    };
-
-    let provider = initialize_embedding_provider(embedding_config);
+    let provider = initialize_embedding_provider(embedding_config(
+        &provider_name,
+        cli.embedding_model.clone(),
+    )?);
     if EMBEDDING_CLIENT.set(provider).is_err() {
         return Err(ServerError::Internal(
             "Failed to set embedding provider".to_string(),
@@ -1244,6 +4480,49 @@ async fn main() -> Result<(), ServerError> {
         .store(true, Ordering::Relaxed);
     info!("✅ {provider_name} embedding provider initialized");
 
+    let query_provider = match cli
+        .query_embedding_model
+        .clone()
+        .filter(|model| Some(model.as_str()) != EMBEDDING_CLIENT.get().map(|p| p.get_model_name()))
+    {
+        Some(query_model) => {
+            let query_provider =
+                initialize_embedding_provider(embedding_config(&provider_name, Some(query_model))?);
+            let document_provider = embeddings::embedding_provider()?;
+            info!(
+                "🔎 Query embedding model: {} (documents: {})",
+                query_provider.get_model_name(),
+                document_provider.get_model_name()
+            );
+            match embeddings::compare_models(document_provider.as_ref(), query_provider.as_ref())
+                .await
+            {
+                Ok(comparison) => match comparison.problem() {
+                    Some(problem) => error!("🚨 QUERY_EMBEDDING_MODEL mismatch: {problem}"),
+                    None => info!(
+                        "✅ Query and document models agree (probe similarity {:.2})",
+                        comparison.similarity.unwrap_or_default()
+                    ),
+                },
+                Err(e) => warn!("⚠️  Could not compare query and document models: {e}"),
+            }
+            Some(query_provider)
+        }
+        None => None,
+    };
+
+    let mut fallback_providers = Vec::new();
+    if let Some(spec) = &cli.embedding_fallback_providers {
+        for (name, model) in embeddings::parse_provider_list(spec)? {
+            let fallback = initialize_embedding_provider(embedding_config(&name, model)?);
+            info!(
+                "🔁 Fallback embedding provider: {name} ({}), used only for crates embedded with that model",
+                fallback.get_model_name()
+            );
+            fallback_providers.push(fallback);
+        }
+    }
+
     // Note: Auto-population will run after SSE server starts to avoid blocking connections
 
     // Mark auto-population as complete (whether successful or not)
@@ -1252,17 +4531,8 @@ async fn main() -> Result<(), ServerError> {
         .store(true, Ordering::Relaxed);
     info!("✅ Auto-population phase complete - service ready");
 
-    // Get crate statistics for startup message (only for available crates)
-    let stats = db.get_crate_stats().await?;
-    let mut crate_stats = std::collections::HashMap::new();
-
-    for crate_name in &available_crates {
-        if let Some(stat) = stats.iter().find(|s| &s.name == crate_name) {
-            crate_stats.insert(crate_name.clone(), stat.total_docs);
-        }
-    }
-
-    let total_docs: i64 = crate_stats.values().map(|&v| v as i64).sum();
+    // Document counts for the startup message come from the presence check above
+    let total_docs: i64 = presence.values().sum();
 
     // Create startup message
     let startup_message = if available_crates.is_empty() {
@@ -1276,7 +4546,7 @@ async fn main() -> Result<(), ServerError> {
             )
         }
     } else if available_crates.len() == 1 {
-        let doc_count = crate_stats.get(&available_crates[0]).unwrap_or(&0);
+        let doc_count = presence.get(&available_crates[0]).unwrap_or(&0);
         let missing_note = if !missing_crates.is_empty() {
             format!(
                 " (Note: {} crates pending population: {})",
@@ -1291,9 +4561,9 @@ async fn main() -> Result<(), ServerError> {
             available_crates[0], doc_count, missing_note
         )
     } else {
-        let crate_summary: Vec<String> = crate_stats
+        let crate_summary: Vec<String> = available_crates
             .iter()
-            .map(|(name, count)| format!("{name} ({count})"))
+            .map(|name| format!("{name} ({})", presence.get(name).unwrap_or(&0)))
             .collect();
         let missing_note = if !missing_crates.is_empty() {
             format!(
@@ -1313,16 +4583,184 @@ async fn main() -> Result<(), ServerError> {
         )
     };
 
+    let startup_message = if cli.read_only {
+        format!(
+            "{startup_message} Read-only replica: add_crate, add_crates, remove_crate and set_crate_enabled are disabled and crates are not populated here."
+        )
+    } else {
+        startup_message
+    };
+    let query_defaults = QueryDefaults::from_env()?;
+    let startup_message = format!(
+        "{startup_message} {}",
+        query_defaults.describe(search::DEFAULT_RESULT_LIMIT)
+    );
+
     info!("✅ {startup_message}");
 
+    if cli.prewarm {
+        // Largest crates first: they have the most index pages to pull into cache
+        let mut targets = available_crates.clone();
+        targets.sort_by_key(|name| std::cmp::Reverse(presence.get(name).copied().unwrap_or(0)));
+        if cli.prewarm_crates > 0 {
+            targets.truncate(cli.prewarm_crates);
+        }
+        let db_clone = db.clone();
+        let warmup_complete = readiness_state.warmup_complete.clone();
+        let connections = cli.prewarm_connections;
+        info!(
+            "🔥 Prewarming {connections} connections and {} crates before reporting ready",
+            targets.len()
+        );
+        tokio::spawn(async move {
+            match db_clone.prewarm(&targets, connections).await {
+                Ok(report) => {
+                    info!(
+                        "🔥 Prewarm done: {} connections in {}ms, {} crate searches in {}ms",
+                        report.connections, report.connect_ms, report.crates, report.search_ms
+                    );
+                    if !report.failed.is_empty() {
+                        warn!("⚠️  Prewarm search failed for: {:?}", report.failed);
+                    }
+                }
+                // A failed warmup only costs speed; don't keep the pod out of rotation
+                Err(e) => warn!("⚠️  Prewarm failed, reporting ready anyway: {e}"),
+            }
+            warmup_complete.store(true, Ordering::Relaxed);
+        });
+    } else {
+        readiness_state
+            .warmup_complete
+            .store(true, Ordering::Relaxed);
+        info!("⏭️  Prewarm disabled");
+    }
+
     // Create the MCP handler with database access (use available crates for queries)
-    let handler = McpHandler::new(db.clone(), available_crates, startup_message);
+    let rate_limits = ToolRateLimits {
+        query_per_minute: cli.query_rate_limit,
+        add_crate_per_minute: cli.add_crate_rate_limit,
+    };
+    info!("🚦 Per-connection rate limits: {rate_limits:?} (0 = unlimited)");
+    let default_crate_features = match &cli.default_crate_features {
+        Some(list) => tool_args::parse_default_features(list)
+            .map_err(|e| ServerError::Config(e.to_string()))?,
+        None => Vec::new(),
+    };
+    if !default_crate_features.is_empty() {
+        info!("🧩 Crates added without features get: {default_crate_features:?}");
+    }
+    let query_log_settings = QueryLogSettings::from_env()?;
+    match query_log_settings.policy {
+        Some(policy) => info!(
+            "📒 Query log: {policy}, {} days retention (0 = forever)",
+            query_log_settings.retention_days
+        ),
+        None => info!("📒 Query log is off (QUERY_LOG_POLICY)"),
+    }
+    if cli.query_cache_size > 0 {
+        info!(
+            "⚡ Query result cache: {} entries, {}s TTL",
+            cli.query_cache_size, cli.query_cache_ttl_secs
+        );
+    }
+    if cli.ignore_robots {
+        warn!("🤖 Ignoring robots.txt when crawling docs (--ignore-robots)");
+    }
+    if cli.read_only {
+        info!("🔒 Read-only mode: mutating tools and auto-population are disabled");
+    }
+    if cli.admin_tools {
+        warn!("🔑 Admin tools (raw_search) are enabled for every client (--admin-tools)");
+    }
+    if cli.feedback_weight > search::MAX_FEEDBACK_WEIGHT {
+        warn!(
+            "⚠️  FEEDBACK_WEIGHT {} capped at {}",
+            cli.feedback_weight,
+            search::MAX_FEEDBACK_WEIGHT
+        );
+    }
+    if cli.generic_penalty > 0.0 {
+        info!(
+            "📐 Demoting generic results by {} x similarity to the crate centroid",
+            cli.generic_penalty
+        );
+    }
+    let populations = Arc::new(Populations::new(cli.max_concurrent_populations));
+    let handler = McpHandler::new(db.clone(), available_crates, startup_message)
+        .with_rate_limits(rate_limits)
+        .with_default_crate_features(default_crate_features)
+        .with_ignore_robots(cli.ignore_robots)
+        .with_keep_mojibake(cli.keep_mojibake)
+        .with_backup_interval_hours(cli.backup_interval_hours)
+        .with_query_cache(QueryCache::new(
+            cli.query_cache_size,
+            Duration::from_secs(cli.query_cache_ttl_secs),
+        ))
+        .with_repeat_question_window(Duration::from_secs(cli.repeat_question_window_secs))
+        .with_query_defaults(query_defaults)
+        .with_stale_after_days(cli.stale_after_days)
+        .with_query_log(query_log_settings.clone())
+        .with_read_only(cli.read_only)
+        .with_admin_tools(cli.admin_tools)
+        .with_query_provider(query_provider)
+        .with_fallback_providers(fallback_providers)
+        .with_hyde(HydeClient::from_env(cli.hyde_model.clone()))
+        .with_max_content_item_bytes(cli.max_content_item_bytes)
+        .with_populations(populations.clone())
+        .with_generic_penalty(cli.generic_penalty)
+        .with_feedback_weight(cli.feedback_weight)
+        .with_orientation_match_threshold(cli.orientation_match_threshold)
+        .with_db_breaker(db_breaker.clone());
 
     // Refresh the available crates cache from the database to include any recently added crates
     info!("🔄 Refreshing available crates cache from database...");
     handler.refresh_available_crates().await?;
     info!("✅ Available crates cache refreshed");
 
+    // Each profile serves its own schema with its own crates, caches and, if configured,
+    // embedding provider
+    let profiles = profiles::load_profiles()?;
+    let mut profile_handlers = HashMap::new();
+    for profile in &profiles {
+        let database = Database::in_namespace(&profile.namespace).await?;
+        let document_provider = if profile.has_own_embedding() {
+            let name = profile
+                .embedding_provider
+                .as_deref()
+                .unwrap_or(&provider_name)
+                .to_lowercase();
+            let model = match &profile.embedding_model {
+                Some(model) => Some(model.clone()),
+                None if name == provider_name => cli.embedding_model.clone(),
+                None => None,
+            };
+            Some(initialize_embedding_provider(embedding_config(
+                &name, model,
+            )?))
+        } else {
+            None
+        };
+        let profile_handler = handler.for_profile(
+            profile,
+            database,
+            document_provider,
+            QueryCache::new(
+                cli.query_cache_size,
+                Duration::from_secs(cli.query_cache_ttl_secs),
+            ),
+            Duration::from_secs(cli.repeat_question_window_secs),
+        );
+        profile_handler.refresh_available_crates().await?;
+        info!(
+            "🗂️  Profile '{}': schema {}, {} crates, embedding model {}",
+            profile.name,
+            profile.namespace,
+            profile_handler.available_crates.read().await.len(),
+            profile_handler.document_provider()?.get_model_name()
+        );
+        profile_handlers.insert(profile.name.clone(), profile_handler);
+    }
+
     // Create SSE server config
     let host = &cli.host;
     let port = cli.port;
@@ -1335,12 +4773,36 @@ async fn main() -> Result<(), ServerError> {
         sse_path: "/sse".to_string(),
         post_path: "/message".to_string(),
         ct: CancellationToken::new(),
+        keep_alive: (cli.sse_keep_alive_secs > 0)
+            .then(|| Duration::from_secs(cli.sse_keep_alive_secs)),
+        idle_timeout: (cli.idle_timeout_secs > 0)
+            .then(|| Duration::from_secs(cli.idle_timeout_secs)),
+        max_connections: cli.max_connections,
+        profiles: profiles
+            .iter()
+            .map(|profile| profile.name.clone())
+            .collect(),
     };
 
     info!("🌐 Starting MCP server on {bind_addr}");
     info!("📡 SSE endpoint: http://{bind_addr}/sse");
+    for profile in &profiles {
+        info!(
+            "📡 SSE endpoint for profile '{}': http://{bind_addr}{}",
+            profile.name,
+            profile.sse_path(&config.sse_path)
+        );
+    }
     info!("📤 POST endpoint: http://{bind_addr}/message");
     info!("🏥 Health endpoints: /health/live (liveness), /health/ready (readiness)");
+    info!(
+        "💓 SSE keep-alive: {:?}, idle timeout: {:?}, max connections: {}",
+        config.keep_alive,
+        config.idle_timeout,
+        config
+            .max_connections
+            .map_or_else(|| "unlimited".to_string(), |max| max.to_string())
+    );
 
     // Create and serve SSE server
     let mut sse_server = SseServer::serve_with_config(config)
@@ -1351,9 +4813,17 @@ async fn main() -> Result<(), ServerError> {
     info!("🎯 MCP server waiting for connections...");
 
     // Start auto-population in background AFTER server is ready for connections
-    if !missing_crates.is_empty() {
+    if cli.read_only && !missing_crates.is_empty() {
+        warn!(
+            "🔒 Read-only mode: not populating {} missing crates; populate them on the primary: {:?}",
+            missing_crates.len(),
+            missing_crates
+        );
+    } else if !missing_crates.is_empty() {
         let db_clone = db.clone();
         let missing_crates_clone = missing_crates.clone();
+        let populations = populations.clone();
+        let db_breaker = db_breaker.clone();
         tokio::spawn(async move {
             info!(
                 "🚀 Starting background auto-population for {} missing crates: {:?}",
@@ -1362,10 +4832,12 @@ async fn main() -> Result<(), ServerError> {
             );
 
             // Get crate configurations for missing crates
-            match db_clone.get_crate_configs(true).await {
+            match db_breaker.call(db_clone.get_crate_configs(true)).await {
                 Ok(all_configs) => {
                     for crate_name in &missing_crates_clone {
                         if let Some(config) = all_configs.iter().find(|c| &c.name == crate_name) {
+                            // Failing every remaining crate during an outage only floods the log
+                            db_breaker.wait_for_recovery().await;
                             info!(
                                 "📦 Auto-populating crate: {} with features: {:?}",
                                 config.name, config.features
@@ -1373,12 +4845,11 @@ async fn main() -> Result<(), ServerError> {
 
                             // Create a temporary handler to use the populate function
                             let temp_handler =
-                                McpHandler::new(db_clone.clone(), vec![], String::new());
+                                McpHandler::new(db_clone.clone(), vec![], String::new())
+                                    .with_populations(populations.clone());
 
-                            match temp_handler
-                                .populate_crate(&config.name, &config.features)
-                                .await
-                            {
+                            let job_id = db_clone.create_population_job(config.id).await.ok();
+                            match temp_handler.populate_crate(config, job_id, None).await {
                                 Ok(stats) => {
                                     info!("✅ Successfully auto-populated crate: {}", config.name);
                                     info!(
@@ -1409,6 +4880,148 @@ async fn main() -> Result<(), ServerError> {
         info!("✅ No missing crates - auto-population not needed");
     }
 
+    // find_symbol works without it, but substring scans over all content are slow
+    if !cli.read_only {
+        let db_clone = db.clone();
+        tokio::spawn(async move {
+            if db_clone.ensure_trigram_index().await {
+                info!("🔤 Trigram index ready for find_symbol");
+            }
+        });
+    }
+
+    if cli.audit_interval_hours > 0 {
+        let db_clone = db.clone();
+        let metrics = readiness_state.audit.clone();
+        let period = Duration::from_secs(cli.audit_interval_hours * 3600);
+        info!(
+            "🩺 Consistency audit every {} hours",
+            cli.audit_interval_hours
+        );
+        let db_breaker = db_breaker.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                if db_breaker.is_degraded() {
+                    continue;
+                }
+                run_scheduled_audit(&db_clone, &db_breaker, &metrics).await;
+            }
+        });
+    }
+
+    // Retention applies whatever the current policy, so rows written under an earlier one
+    // still expire
+    if query_log_settings.retention_days > 0 && !cli.read_only {
+        let databases: Vec<Database> = std::iter::once(db.clone())
+            .chain(profile_handlers.values().map(|h| h.database.clone()))
+            .collect();
+        let db_breaker = db_breaker.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(query_log::CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                if db_breaker.is_degraded() {
+                    continue;
+                }
+                let Some(cutoff) = query_log_settings.retention_cutoff(chrono::Utc::now()) else {
+                    continue;
+                };
+                for database in &databases {
+                    match database.delete_query_log_before(cutoff).await {
+                        Ok(0) => {}
+                        Ok(deleted) => {
+                            info!("📒 Deleted {deleted} query log rows older than {cutoff}")
+                        }
+                        Err(e) => warn!("⚠️  Query log cleanup failed: {e}"),
+                    }
+                }
+            }
+        });
+    }
+
+    if cli.version_refresh_interval_hours > 0 && cli.read_only {
+        info!("🔒 Read-only mode: scheduled version refresh is disabled");
+    } else if cli.version_refresh_interval_hours > 0 {
+        let period = Duration::from_secs(cli.version_refresh_interval_hours * 3600);
+        {
+            let mut status = handler.refresh_status.lock().unwrap();
+            status.interval_secs = Some(period.as_secs());
+            status.next_run_at = chrono::Duration::from_std(period)
+                .ok()
+                .map(|period| chrono::Utc::now() + period);
+        }
+        info!(
+            "🔁 Checking latest-tracked crates for new docs.rs versions every {} hours",
+            cli.version_refresh_interval_hours
+        );
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            // The first tick is immediate; startup auto-population already covers new crates
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if handler.db_breaker.is_degraded() {
+                    continue;
+                }
+                handler.run_version_refresh(period).await;
+            }
+        });
+    }
+
+    // Per-state crate counts for /health/ready; a failed or stuck crate shows up there
+    // without anyone having to call check_crate_status
+    {
+        let db_clone = db.clone();
+        let db_breaker = db_breaker.clone();
+        let crate_states = readiness_state.crate_states.clone();
+        let stale_after_days = cli.stale_after_days;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CRATE_STATUS_REFRESH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if db_breaker.is_degraded() {
+                    continue;
+                }
+                let statuses = async {
+                    let names: Vec<String> = db_clone
+                        .get_crate_configs(true)
+                        .await?
+                        .into_iter()
+                        .map(|config| config.name)
+                        .collect();
+                    db_clone.get_crate_statuses(&names, stale_after_days).await
+                };
+                match db_breaker.call(statuses).await {
+                    Ok(statuses) => {
+                        let mut counts = BTreeMap::new();
+                        for status in statuses.values() {
+                            *counts.entry(status.name()).or_insert(0) += 1;
+                        }
+                        *crate_states.lock().unwrap_or_else(|e| e.into_inner()) = counts;
+                    }
+                    Err(e) => warn!("⚠️  Failed to refresh crate statuses: {e}"),
+                }
+            }
+        });
+    }
+
+    // Detects outages for readiness and ends them: failures back off and only state
+    // changes are logged (see db_breaker)
+    {
+        let db_clone = db.clone();
+        let db_breaker = db_breaker.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(DB_PROBE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let _ = db_breaker.call(db_clone.ping()).await;
+            }
+        });
+    }
+
     // Initialize connection configuration with enhanced resilience
     let connection_config = McpConnectionConfig::default();
     info!(
@@ -1419,35 +5032,55 @@ async fn main() -> Result<(), ServerError> {
     );
 
     // Handle incoming transports with enhanced resilience
+    let connection_stats = sse_server.stats();
     let mut connection_counter = 0;
     while let Some(transport) = sse_server.next_transport().await {
         connection_counter += 1;
         let connection_id = format!("conn-{connection_counter}");
+        let session = transport.handle();
+        let peer_addr = session.peer_addr();
 
-        info!("🔗 New MCP connection received (ID: {connection_id})");
-        info!("📊 Total active connections: {connection_counter}");
+        info!(
+            "🔗 New MCP connection received (ID: {connection_id}, peer: {peer_addr}, profile: {})",
+            session.profile().unwrap_or("default")
+        );
+        info!(
+            "📊 Total active connections: {} (rejected so far: {})",
+            connection_stats.active(),
+            connection_stats.rejected()
+        );
 
-        let handler_clone = handler.clone();
+        // Profile routes only exist for configured profiles
+        let serving = session
+            .profile()
+            .and_then(|profile| profile_handlers.get(profile))
+            .unwrap_or(&handler);
+        let handler_clone = serving.for_connection(&connection_id, session.disconnected());
+        let rate_limiter = serving.rate_limiter.clone();
+        let recent_questions = serving.recent_questions.clone();
         let config_clone = connection_config.clone();
         let conn_id_clone = connection_id.clone();
+        let connection_stats = connection_stats.clone();
 
         tokio::spawn(async move {
             let start_time = std::time::Instant::now();
-            match handle_mcp_connection_with_resilience(
+            let result = handle_mcp_connection_with_resilience(
                 handler_clone,
                 transport,
                 config_clone,
                 conn_id_clone.clone(),
             )
-            .await
-            {
+            .await;
+            let duration = start_time.elapsed();
+            let remaining = connection_stats.active();
+            rate_limiter.forget_connection(&conn_id_clone);
+            recent_questions.forget_connection(&conn_id_clone);
+            match result {
                 Ok(()) => {
-                    let duration = start_time.elapsed();
-                    info!("✅ MCP connection closed gracefully (ID: {conn_id_clone}, duration: {duration:?})");
+                    info!("✅ MCP connection closed gracefully (ID: {conn_id_clone}, duration: {duration:?}, active: {remaining})");
                 }
                 Err(e) => {
-                    let duration = start_time.elapsed();
-                    error!("🚨 MCP connection failed (ID: {conn_id_clone}, duration: {duration:?}): {e}");
+                    error!("🚨 MCP connection failed (ID: {conn_id_clone}, duration: {duration:?}, active: {remaining}): {e}");
                 }
             }
         });
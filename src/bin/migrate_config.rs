@@ -1,105 +1,135 @@
-use rustdocs_mcp_server::{
-    database::{CrateConfig, Database},
-    error::ServerError,
-};
-use serde::{Deserialize, Serialize};
+use clap::Parser;
+use rustdocs_mcp_server::{database::Database, error::ServerError, proxy_config::ProxyConfig};
 use std::fs;
-use std::path::Path;
-
-#[derive(Debug, Deserialize, Serialize)]
-struct ProxyConfig {
-    rustdocs_binary_path: String,
-    crates: Vec<OldCrateConfig>,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-struct OldCrateConfig {
-    name: String,
-    features: Option<Vec<String>>,
-    enabled: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    expected_docs: Option<usize>,
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Import crate configurations from a legacy proxy-config.json", long_about = None)]
+struct Cli {
+    /// The legacy configuration file
+    #[arg(long, default_value = "proxy-config.json")]
+    config: PathBuf,
+
+    /// Update configurations migrated before instead of skipping them; only the fields
+    /// the file sets (features, enabled, expected_docs, max_pages) are replaced
+    #[arg(long)]
+    update_existing: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), ServerError> {
     dotenvy::dotenv().ok();
+    let cli = Cli::parse();
+    let path = cli.config.display();
 
-    // Check if proxy-config.json exists
-    if !Path::new("proxy-config.json").exists() {
-        println!("No proxy-config.json found. Nothing to migrate.");
+    // Check if the config exists
+    if !cli.config.exists() {
+        println!("No {path} found. Nothing to migrate.");
         return Ok(());
     }
 
-    // Read proxy-config.json
-    println!("📋 Reading proxy-config.json...");
-    let config_content = fs::read_to_string("proxy-config.json")
-        .map_err(|e| ServerError::Config(format!("Failed to read proxy-config.json: {e}")))?;
+    // Read the config
+    println!("📋 Reading {path}...");
+    let config_content = fs::read_to_string(&cli.config)
+        .map_err(|e| ServerError::Config(format!("Failed to read {path}: {e}")))?;
 
-    let config: ProxyConfig = serde_json::from_str(&config_content)
-        .map_err(|e| ServerError::Config(format!("Failed to parse proxy-config.json: {e}")))?;
+    let config = ProxyConfig::parse(&config_content)?;
 
-    println!("Found {} crates in proxy-config.json", config.crates.len());
+    println!("Found {} crates in {path}", config.crates.len());
+    let unknown_fields = config.unknown_fields();
+    for field in &unknown_fields {
+        eprintln!("⚠️  Unknown field '{field}' in {path} is NOT migrated");
+    }
 
     // Initialize database
     let db = Database::new().await?;
 
     // Migrate each crate
     let mut migrated = 0;
+    let mut updated = 0;
     let mut skipped = 0;
+    let mut failed = 0;
 
-    for old_config in config.crates {
+    for old_config in &config.crates {
+        let version_spec = old_config.version_spec();
         println!(
-            "\nMigrating: {} (enabled: {})",
+            "\nMigrating: {} {version_spec} (enabled: {})",
             old_config.name, old_config.enabled
         );
 
         // Check if already exists
-        if let Some(existing) = db.get_crate_config(&old_config.name, "latest").await? {
-            println!(
-                "  ⚠️  Already exists in database (id: {}), skipping",
-                existing.id
-            );
-            skipped += 1;
-            continue;
+        let existing = db
+            .get_crate_config(&old_config.name, version_spec, None)
+            .await?;
+        if let Some(existing) = &existing {
+            if !cli.update_existing {
+                println!(
+                    "  ⚠️  Already exists in database (id: {}), skipping (--update-existing updates it)",
+                    existing.id
+                );
+                skipped += 1;
+                continue;
+            }
+        } else if version_spec != "latest" {
+            // Earlier migrations ignored pins and stored every crate as "latest"
+            if let Some(unpinned) = db
+                .get_crate_config(&old_config.name, "latest", None)
+                .await?
+            {
+                println!(
+                    "  ⚠️  An unpinned 'latest' configuration (id: {}) also exists; remove it with remove_crate if the pin replaces it",
+                    unpinned.id
+                );
+            }
         }
+        let is_update = existing.is_some();
 
-        // Create new config
-        let new_config = CrateConfig {
-            id: 0, // Will be set by database
-            name: old_config.name.clone(),
-            version_spec: "latest".to_string(),
-            current_version: None,
-            features: old_config.features.unwrap_or_default(),
-            expected_docs: old_config.expected_docs.unwrap_or(1000) as i32,
-            enabled: old_config.enabled,
-            last_checked: None,
-            last_populated: None,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
+        let new_config = match old_config.to_crate_config(existing) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("  ❌ Failed to migrate: {e}");
+                failed += 1;
+                continue;
+            }
         };
 
         match db.upsert_crate_config(&new_config).await {
+            Ok(saved) if is_update => {
+                println!("  🔄 Updated (id: {})", saved.id);
+                updated += 1;
+            }
             Ok(saved) => {
                 println!("  ✅ Migrated successfully (id: {})", saved.id);
                 migrated += 1;
             }
             Err(e) => {
                 println!("  ❌ Failed to migrate: {e}");
+                failed += 1;
             }
         }
     }
 
     println!("\n📊 Migration Summary:");
     println!("  ✅ Migrated: {migrated} crates");
+    println!("  🔄 Updated: {updated} crates");
     println!("  ⚠️  Skipped: {skipped} crates (already existed)");
+    if failed > 0 {
+        println!("  ❌ Failed: {failed} crates");
+    }
+    if !unknown_fields.is_empty() {
+        println!(
+            "  ⚠️  Ignored {} unknown fields: {}",
+            unknown_fields.len(),
+            unknown_fields.join(", ")
+        );
+    }
 
     // Offer to rename the old config
-    if migrated > 0 {
+    if migrated > 0 || updated > 0 {
         println!("\n💡 Migration complete! You can now:");
-        println!("  1. Rename proxy-config.json to proxy-config.json.bak");
+        println!("  1. Rename {path} to {path}.bak");
         println!("  2. Use the 'add_crate' and 'list_crates' MCP tools to manage crates");
-        println!("  3. Run 'populate_all' to populate any missing documentation");
+        println!("  3. Run 'populate_all' (or the populate_pending MCP tool) to populate any missing documentation");
     }
 
     Ok(())
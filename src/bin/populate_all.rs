@@ -1,19 +1,52 @@
 use async_openai::{config::OpenAIConfig, Client as OpenAIClient};
+use clap::Parser;
 use futures::future::try_join_all;
 use rustdocs_mcp_server::{
+    crate_metadata::{CratesIo, CRATES_IO_ORIGIN},
     database::Database,
     doc_loader,
-    embeddings::{
-        generate_embeddings, initialize_embedding_provider, EmbeddingConfig, EMBEDDING_CLIENT,
-    },
+    embeddings::{self, initialize_embedding_provider, EmbeddingConfig, EMBEDDING_CLIENT},
     error::ServerError,
+    language::LanguageFilter,
+    pipeline,
 };
 use std::env;
 
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Populate every enabled crate that needs updating", long_about = None)]
+struct Cli {
+    /// Crawl pages even where the docs host's robots.txt disallows them
+    #[arg(long)]
+    ignore_robots: bool,
+
+    /// Store crawled pages as decoded, without repairing UTF-8 read as Windows-1252
+    #[arg(long)]
+    keep_mojibake: bool,
+
+    /// Most new links queued from any one page (default: all qualifying links)
+    #[arg(long)]
+    max_links_per_page: Option<usize>,
+
+    /// Order in which queued pages are crawled
+    #[arg(long, value_enum, default_value_t = doc_loader::CrawlStrategy::BreadthFirst)]
+    crawl_strategy: doc_loader::CrawlStrategy,
+
+    /// Share of the page limit whose links are followed, 0.0-1.0 (default: 0.75)
+    #[arg(long)]
+    follow_links_fraction: Option<f64>,
+
+    /// Longest each crate's population may run, in seconds, for crates without their own
+    /// max_duration_secs; a crate that reaches it is marked partial and resumes next run
+    #[arg(long, value_name = "SECS", env = "POPULATE_MAX_DURATION_SECS")]
+    max_duration: Option<u64>,
+}
+
 #[tokio::main]
 async fn main() -> Result<(), ServerError> {
     dotenvy::dotenv().ok();
 
+    let cli = Cli::parse();
+
     // Initialize database
     println!("📋 Loading crate configurations from database...");
     let db = Database::new().await?;
@@ -83,60 +116,127 @@ async fn main() -> Result<(), ServerError> {
     );
     let start_time = std::time::Instant::now();
 
+    let crates_io = CratesIo::new(CRATES_IO_ORIGIN);
+
     // Create tasks for parallel processing
     let tasks: Vec<_> = crates_to_populate
         .into_iter()
         .enumerate()
         .map(|(i, crate_config)| {
             let db = &db;
+            let crates_io = &crates_io;
             let crate_name = crate_config.name.clone();
             let features = crate_config.features.clone();
+            let mut crawl_options = doc_loader::CrawlOptions {
+                ignore_robots: cli.ignore_robots,
+                keep_mojibake: cli.keep_mojibake,
+                max_links_per_page: cli.max_links_per_page,
+                strategy: cli.crawl_strategy,
+                follow_links_fraction: cli.follow_links_fraction,
+                ..crate_config.crawl_options()
+            };
+            let rustdoc_json_url = crate_config.rustdoc_json_url.clone();
+            let target = crate_config.target.clone();
+            let max_pages = crate_config.max_pages_or(50);
+            let store_raw_html = crate_config.store_raw_html;
+            let languages = LanguageFilter::for_crate(&crate_config);
+            let section_metadata = crate_config.section_metadata;
             let config_id = crate_config.id;
+            let max_duration = crate_config
+                .max_duration()
+                .or(cli.max_duration.map(std::time::Duration::from_secs));
 
             async move {
+                crawl_options.deadline =
+                    max_duration.map(|limit| std::time::Instant::now() + limit);
                 println!(
                     "\n📥 [{}/{}] Loading documentation for: {}",
                     i + 1,
                     i + 1,
                     crate_name
                 );
+                if rustdoc_json_url.is_none() {
+                    crawl_options.version =
+                        pipeline::resolve_crawl_version(&crate_config, crates_io).await?;
+                }
 
                 // Create population job
                 let job_id = db.create_population_job(config_id).await?;
                 db.update_population_job(job_id, "running", None, None)
                     .await?;
 
-                let doc_start = std::time::Instant::now();
+                let source = match &rustdoc_json_url {
+                    Some(url) => pipeline::DocumentSource::RustdocJson {
+                        crate_name: &crate_name,
+                        url,
+                    },
+                    None => pipeline::DocumentSource::DocsRs {
+                        crate_name: &crate_name,
+                        features: Some(&features),
+                        // Use smaller page limit for batch processing unless the crate sets one
+                        max_pages: Some(max_pages),
+                        options: &crawl_options,
+                        raw_html: store_raw_html.then_some(db),
+                    },
+                };
+                let mut options = pipeline::PipelineOptions::from_env()?;
+                options.languages = languages?;
+                options.section_metadata = section_metadata;
+                options.max_chunks = crate_config.embedding_limit();
+                options.deadline = crawl_options.deadline;
+                // Crates populated before are repopulated out of sight and swapped in at the end
+                let staging = pipeline::Staging::plan(db, &crate_config).await?;
+                // Never finished, or stopped at its time limit: keep what was already stored
+                if crate_config.resumes_population() {
+                    options.skip_paths = staging.stored_paths(db, &crate_name).await?;
+                }
 
-                let result = match doc_loader::load_documents_from_docs_rs(
+                // Crawl, embed and store concurrently; each batch is committed as it lands
+                let provider = embeddings::embedding_provider()?;
+                let mut sink = pipeline::DatabaseSink::new(
+                    db,
                     &crate_name,
-                    "*",
-                    Some(&features),
-                    Some(50), // Use smaller page limit for batch processing
+                    target.as_deref(),
+                    provider.get_model_name(),
+                    Some(job_id),
+                )
+                .with_staging(&staging);
+                let stats = match pipeline::run(
+                    |tx| source.crawl(tx),
+                    provider.clone(),
+                    &mut sink,
+                    &options,
                 )
                 .await
                 {
-                    Ok(result) => result,
+                    Ok(stats) => stats,
                     Err(e) => {
                         println!("❌ Failed to populate {crate_name}: {e}");
                         let error_msg = e.to_string();
                         db.update_population_job(job_id, "failed", Some(&error_msg), None)
                             .await?;
-                        return Err(ServerError::DocLoader(e));
+                        return Err(e);
                     }
                 };
 
-                let documents = result.documents;
-                let crate_version = result.version;
+                let crate_version = stats.crawl.version.clone();
+                let mut reexport_pages: Vec<_> = stats.crawl.reexport_pages.iter().collect();
+                reexport_pages.sort();
+                db.set_population_job_diagnostics(job_id, &stats.crawl.diagnostics)
+                    .await?;
+                if !stats.crawl.diagnostics.trace.entries.is_empty() {
+                    db.set_population_job_crawl_trace(job_id, &stats.crawl.diagnostics.trace)
+                        .await?;
+                }
 
-                let doc_time = doc_start.elapsed();
                 println!(
-                    "✅ [{}/{}] Loaded {} documents for {} in {:.2}s",
+                    "✅ [{}/{}] Loaded {} documents for {} in {:.2}s ({} too short to embed)",
                     i + 1,
                     i + 1,
-                    documents.len(),
+                    stats.documents,
                     crate_name,
-                    doc_time.as_secs_f64()
+                    stats.crawl_time.as_secs_f64(),
+                    stats.short_documents
                 );
 
                 if let Some(ref version) = crate_version {
@@ -147,77 +247,115 @@ async fn main() -> Result<(), ServerError> {
                         crate_name,
                         version
                     );
+                } else if stats.crawl.diagnostics.version_missing {
+                    eprintln!(
+                        "❌ [{}/{}] Could not determine the version of {}; it will be stored without one",
+                        i + 1,
+                        i + 1,
+                        crate_name
+                    );
+                }
+                println!(
+                    "🩺 [{}/{}] Crawl diagnostics for {}: {}",
+                    i + 1,
+                    i + 1,
+                    crate_name,
+                    stats.crawl.diagnostics
+                );
+                for (dep_crate, pages) in &reexport_pages {
+                    println!(
+                        "🔗 [{}/{}] Followed re-exports from {} into {}: {} pages",
+                        i + 1,
+                        i + 1,
+                        crate_name,
+                        dep_crate,
+                        pages
+                    );
                 }
 
-                if documents.is_empty() {
+                if stats.documents == 0 {
                     println!("⚠️  No documents found for {crate_name}");
                     db.update_population_job(job_id, "completed", None, Some(0))
                         .await?;
                     return Ok::<_, ServerError>((crate_name, 0, 0.0));
                 }
 
-                // Generate embeddings
-                println!(
-                    "🧠 [{}/{}] Generating embeddings for {}...",
-                    i + 1,
-                    i + 1,
-                    crate_name
-                );
-                let embed_start = std::time::Instant::now();
-                let (embeddings, total_tokens) = generate_embeddings(&documents).await?;
-                let embed_time = embed_start.elapsed();
-
                 let cost_per_million = 0.02;
-                let estimated_cost = (total_tokens as f64 / 1_000_000.0) * cost_per_million;
+                let estimated_cost = (stats.total_tokens as f64 / 1_000_000.0) * cost_per_million;
                 println!(
-                    "✅ [{}/{}] Generated {} embeddings for {} in {:.2}s (${:.6})",
+                    "✅ [{}/{}] Stored {} embeddings for {} (${:.6})",
                     i + 1,
                     i + 1,
-                    embeddings.len(),
+                    stats.stored_chunks,
                     crate_name,
-                    embed_time.as_secs_f64(),
                     estimated_cost
                 );
 
-                // Store in database
-                let crate_id = db
-                    .upsert_crate(&crate_name, crate_version.as_deref())
-                    .await?;
-
-                // Initialize tokenizer for accurate token counting
-                let bpe =
-                    tiktoken_rs::cl100k_base().map_err(|e| ServerError::Tiktoken(e.to_string()))?;
-
-                let mut batch_data = Vec::new();
-                for (path, content, embedding) in embeddings.iter() {
-                    // Calculate actual token count for this chunk
-                    let token_count = bpe.encode_with_special_tokens(content).len() as i32;
-                    batch_data.push((
-                        path.clone(),
-                        content.clone(),
-                        embedding.clone(),
-                        token_count,
-                    ));
+                // The version is only known once the crawl has seen the crate root; a staged
+                // generation records it when it is promoted
+                if !staging.is_staged() && stats.stored_chunks > 0 {
+                    db.upsert_crate(&crate_name, crate_version.as_deref(), target.as_deref())
+                        .await?;
                 }
 
-                db.insert_embeddings_batch(crate_id, &crate_name, &batch_data)
+                // Stopped at the time limit: keep what was stored and resume next run
+                if stats.timed_out {
+                    println!(
+                        "⏱️  [{}/{}] {} reached its time limit; marked partial, the next run resumes it",
+                        i + 1,
+                        i + 1,
+                        crate_name
+                    );
+                    db.record_partial_population(config_id).await?;
+                    db.update_population_job(
+                        job_id,
+                        "partial",
+                        None,
+                        Some(stats.stored_chunks as i32),
+                    )
                     .await?;
+                    return Ok((crate_name, stats.stored_chunks, estimated_cost));
+                }
+                if staging.is_staged() {
+                    staging
+                        .promote(db, &crate_name, crate_version.as_deref())
+                        .await?;
+                    staging.collect_garbage(db, &crate_name).await;
+                }
 
-                // Update crate config with current version and last populated time
-                let mut updated_config = crate_config.clone();
-                updated_config.current_version = crate_version;
-                updated_config.last_populated = Some(chrono::Utc::now());
-                updated_config.last_checked = Some(chrono::Utc::now());
-                db.upsert_crate_config(&updated_config).await?;
+                // Record the current version and population time, and on the first
+                // population the expected_docs baseline
+                let stored_docs = db.count_crate_documents(&crate_name).await?;
+                let expected_docs = crate_config.expected_docs_baseline(stored_docs);
+                if let Some(expected) = expected_docs {
+                    println!("📏 Set expected_docs for {crate_name} to {expected}");
+                }
+                db.record_crate_population(config_id, crate_version.as_deref(), expected_docs)
+                    .await?;
+                if stats.truncated {
+                    println!("✂️  {crate_name} stopped at its max_embeddings; the index is partial");
+                }
+                db.set_crate_truncated(config_id, stats.truncated).await?;
+                if stats.crawl.diagnostics.low_doc_coverage {
+                    println!("📄 {crate_name} has minimal API documentation; its README was stored instead");
+                }
+                db.set_crate_low_doc_coverage(config_id, stats.crawl.diagnostics.low_doc_coverage)
+                    .await?;
+                pipeline::warm_orientation(db, provider.as_ref(), &crate_name).await;
 
                 // Mark job as completed
-                db.update_population_job(job_id, "completed", None, Some(embeddings.len() as i32))
-                    .await?;
+                db.update_population_job(
+                    job_id,
+                    "completed",
+                    None,
+                    Some(stats.stored_chunks as i32),
+                )
+                .await?;
 
                 // Add delay between crates to be respectful to docs.rs
                 tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
 
-                Ok((crate_name, embeddings.len(), estimated_cost))
+                Ok((crate_name, stats.stored_chunks, estimated_cost))
             }
         })
         .collect();
@@ -1,12 +1,13 @@
 use async_openai::{config::OpenAIConfig, Client as OpenAIClient};
 use clap::Parser;
 use rustdocs_mcp_server::{
+    crate_metadata::{CratesIo, CRATES_IO_ORIGIN},
     database::Database,
     doc_loader,
-    embeddings::{
-        generate_embeddings, initialize_embedding_provider, EmbeddingConfig, EMBEDDING_CLIENT,
-    },
+    embeddings::{self, initialize_embedding_provider, EmbeddingConfig, EMBEDDING_CLIENT},
     error::ServerError,
+    language::LanguageFilter,
+    pipeline, rustdoc_json,
 };
 use std::env;
 
@@ -40,6 +41,48 @@ struct Cli {
     /// Maximum number of pages to crawl (default: 10000)
     #[arg(long, default_value_t = 10000)]
     max_pages: usize,
+
+    /// Backfill missing full-text search vectors (for --crate-name, or all crates)
+    #[arg(long)]
+    reindex_text: bool,
+
+    /// Crawl pages even where the docs host's robots.txt disallows them
+    #[arg(long)]
+    ignore_robots: bool,
+
+    /// Store crawled pages as decoded, without repairing UTF-8 read as Windows-1252
+    #[arg(long)]
+    keep_mojibake: bool,
+
+    /// Finish an interrupted population: keep stored chunks and only embed the rest
+    #[arg(long, conflicts_with = "force")]
+    resume: bool,
+
+    /// docs.rs build target to document (e.g. "x86_64-pc-windows-msvc"; default: docs.rs default target)
+    #[arg(long)]
+    target: Option<String>,
+
+    /// Most new links queued from any one page (default: all qualifying links)
+    #[arg(long)]
+    max_links_per_page: Option<usize>,
+
+    /// Order in which queued pages are crawled
+    #[arg(long, value_enum, default_value_t = doc_loader::CrawlStrategy::BreadthFirst)]
+    crawl_strategy: doc_loader::CrawlStrategy,
+
+    /// Share of the page limit whose links are followed, 0.0-1.0 (default: 0.75)
+    #[arg(long)]
+    follow_links_fraction: Option<f64>,
+
+    /// Keep each crawled page's HTML for `db_maintenance reextract-crate` (default: the
+    /// crate config's store_raw_html, else off); roughly doubles the crate's storage
+    #[arg(long)]
+    store_raw_html: bool,
+
+    /// Stop after this many seconds, keeping what was stored; finish later with --resume
+    /// (default: the crate config's max_duration_secs, else no limit)
+    #[arg(long, value_name = "SECS")]
+    max_duration: Option<u64>,
 }
 
 #[tokio::main]
@@ -58,37 +101,79 @@ async fn main() -> Result<(), ServerError> {
             println!("No crates in database.");
         } else {
             println!(
-                "{:<20} {:<15} {:<10} {:<10} {:<20}",
-                "Crate", "Version", "Docs", "Tokens", "Last Updated"
+                "{:<20} {:<15} {:<26} {:<10} {:<10} {:<18} {:<12} {:<12}",
+                "Crate",
+                "Version",
+                "Target",
+                "Docs",
+                "Tokens",
+                "Last Updated",
+                "Oldest Doc",
+                "Newest Doc"
             );
-            println!("{:-<80}", "");
+            println!("{:-<132}", "");
+            let date = |d: Option<chrono::DateTime<chrono::Utc>>| {
+                d.map_or_else(|| "N/A".to_string(), |d| d.format("%Y-%m-%d").to_string())
+            };
             for stat in stats {
                 println!(
-                    "{:<20} {:<15} {:<10} {:<10} {:<20}",
+                    "{:<20} {:<15} {:<26} {:<10} {:<10} {:<18} {:<12} {:<12}",
                     stat.name,
                     stat.version.unwrap_or_else(|| "N/A".to_string()),
+                    stat.target.unwrap_or_else(|| "default".to_string()),
                     stat.total_docs,
                     stat.total_tokens,
-                    stat.last_updated.format("%Y-%m-%d %H:%M")
+                    stat.last_updated.format("%Y-%m-%d %H:%M"),
+                    date(stat.oldest_doc),
+                    date(stat.newest_doc)
                 );
             }
         }
         return Ok(());
     }
 
+    // Handle text index backfill
+    if cli.reindex_text {
+        let scope = cli.crate_name.as_deref().unwrap_or("all crates");
+        println!("Backfilling text search vectors for {scope}...");
+        let updated = db.reindex_text(cli.crate_name.as_deref()).await?;
+        println!("Updated {updated} documents");
+        return Ok(());
+    }
+
     // Handle delete command
     if let Some(crate_to_delete) = cli.delete {
         println!("Deleting embeddings for crate: {crate_to_delete}");
         db.delete_crate_embeddings(&crate_to_delete).await?;
-        println!("Successfully deleted embeddings for {crate_to_delete}");
+        let pages = db.delete_raw_pages(&crate_to_delete).await?;
+        println!(
+            "Successfully deleted embeddings (and {pages} stored pages) for {crate_to_delete}"
+        );
         return Ok(());
     }
 
     // Handle populate command
     if let Some(crate_name) = cli.crate_name {
-        // Check if embeddings already exist
-        if !cli.force && db.has_embeddings(&crate_name).await? {
-            println!("Embeddings already exist for {crate_name}. Use --force to regenerate.");
+        if let Some(target) = &cli.target {
+            doc_loader::validate_target(target)?;
+        }
+
+        // Check if embeddings already exist (for the requested target, which has its own doc paths)
+        let exists = match &cli.target {
+            Some(target) => !db
+                .get_doc_paths_with_prefix(
+                    &crate_name,
+                    &format!("{crate_name}/latest/{target}/"),
+                    1,
+                )
+                .await?
+                .is_empty(),
+            None => db.has_embeddings(&crate_name).await?,
+        };
+        if !cli.force && !cli.resume && exists {
+            println!(
+                "Embeddings already exist for {crate_name}. Use --force to regenerate or --resume to finish an interrupted run."
+            );
             return Ok(());
         }
 
@@ -130,44 +215,82 @@ async fn main() -> Result<(), ServerError> {
             ));
         }
 
-        // Initialize tokenizer for accurate token counting
-        let bpe = tiktoken_rs::cl100k_base().map_err(|e| ServerError::Tiktoken(e.to_string()))?;
-
         println!(
             "📥 Loading documentation for crate: {crate_name} (max {} pages)",
             cli.max_pages
         );
-        let doc_start = std::time::Instant::now();
-        let load_result = doc_loader::load_documents_from_docs_rs(
-            &crate_name,
-            "*",
-            cli.features.as_ref(),
-            Some(cli.max_pages),
-        )
-        .await?;
-        let documents = load_result.documents;
-        let crate_version = load_result.version;
-        let doc_time = doc_start.elapsed();
-
-        let total_content_size: usize = documents.iter().map(|doc| doc.content.len()).sum();
-        println!(
-            "✅ Loaded {} documents in {:.2}s ({:.1} KB total)",
-            documents.len(),
-            doc_time.as_secs_f64(),
-            total_content_size as f64 / 1024.0
-        );
-
-        if let Some(ref version) = crate_version {
-            println!("📦 Detected version: {version}");
-        }
-
-        if documents.is_empty() {
-            println!("No documents found for crate: {crate_name}");
-            return Ok(());
+        // Honor crawl settings (e.g. content selector) from the crate config, if one exists
+        let crate_config = db
+            .get_crate_configs(false)
+            .await?
+            .into_iter()
+            .find(|c| c.name == crate_name && c.target == cli.target);
+        let max_duration = cli
+            .max_duration
+            .map(std::time::Duration::from_secs)
+            .or_else(|| crate_config.as_ref().and_then(|c| c.max_duration()));
+        let mut crawl_options = doc_loader::CrawlOptions {
+            deadline: max_duration.map(|limit| std::time::Instant::now() + limit),
+            ignore_robots: cli.ignore_robots,
+            keep_mojibake: cli.keep_mojibake,
+            target: cli.target.clone(),
+            max_links_per_page: cli.max_links_per_page,
+            strategy: cli.crawl_strategy,
+            follow_links_fraction: cli.follow_links_fraction,
+            ..crate_config
+                .as_ref()
+                .map(|c| c.crawl_options())
+                .unwrap_or_default()
+        };
+        let store_raw_html =
+            cli.store_raw_html || crate_config.as_ref().is_some_and(|c| c.store_raw_html);
+        let languages = match &crate_config {
+            Some(config) => LanguageFilter::for_crate(config)?,
+            None => None,
+        };
+        let max_chunks = crate_config.as_ref().and_then(|c| c.embedding_limit());
+        let section_metadata = crate_config.as_ref().is_some_and(|c| c.section_metadata);
+        if let Some(config) = crate_config
+            .as_ref()
+            .filter(|c| c.rustdoc_json_url.is_none())
+        {
+            crawl_options.version =
+                pipeline::resolve_crawl_version(config, &CratesIo::new(CRATES_IO_ORIGIN)).await?;
         }
+        let rustdoc_json_url = crate_config.and_then(|c| c.rustdoc_json_url);
 
         // If test mode, just show what we loaded and exit
         if cli.test {
+            let doc_start = std::time::Instant::now();
+            let load_result = match &rustdoc_json_url {
+                Some(url) => rustdoc_json::load_documents_from_url(&crate_name, url).await?,
+                None => {
+                    doc_loader::load_documents_from_docs_rs(
+                        &crate_name,
+                        "*",
+                        cli.features.as_ref(),
+                        Some(cli.max_pages),
+                        &crawl_options,
+                    )
+                    .await?
+                }
+            };
+            let documents = load_result.documents;
+            let doc_time = doc_start.elapsed();
+
+            let total_content_size: usize = documents.iter().map(|doc| doc.content.len()).sum();
+            println!(
+                "✅ Loaded {} documents in {:.2}s ({:.1} KB total)",
+                documents.len(),
+                doc_time.as_secs_f64(),
+                total_content_size as f64 / 1024.0
+            );
+            if let Some(ref version) = load_result.version {
+                println!("📦 Detected version: {version}");
+            }
+            println!("🩺 Crawl diagnostics: {}", load_result.diagnostics);
+            print_reexport_pages(&load_result.reexport_pages);
+
             println!("\n🧪 Test mode - showing loaded documents:");
             for (i, doc) in documents.iter().enumerate() {
                 println!(
@@ -196,64 +319,112 @@ async fn main() -> Result<(), ServerError> {
             return Ok(());
         }
 
-        // Generate embeddings
-        println!("\n🧠 Generating embeddings...");
-        let embedding_start = std::time::Instant::now();
-        let (embeddings, total_tokens) = generate_embeddings(&documents).await?;
-        let embedding_time = embedding_start.elapsed();
+        let mut options = pipeline::PipelineOptions::from_env()?;
+        options.languages = languages;
+        options.section_metadata = section_metadata;
+        options.max_chunks = max_chunks;
+        options.deadline = crawl_options.deadline;
+        if cli.resume {
+            options.skip_paths = pipeline::stored_paths(&db, &crate_name).await?;
+            println!(
+                "⏩ Resuming: {} chunks already stored will not be embedded again",
+                options.skip_paths.len()
+            );
+        }
 
-        let cost_per_million = 0.02;
-        let estimated_cost = (total_tokens as f64 / 1_000_000.0) * cost_per_million;
-        println!(
-            "✅ Generated {} embeddings using {} tokens in {:.2}s (Est. Cost: ${:.6})",
-            embeddings.len(),
-            total_tokens,
-            embedding_time.as_secs_f64(),
-            estimated_cost
+        // Crawl, embed and store concurrently; each batch is committed as it lands
+        println!("\n🧠 Streaming documents into embeddings...");
+        let start = std::time::Instant::now();
+        let source = match &rustdoc_json_url {
+            Some(url) => pipeline::DocumentSource::RustdocJson {
+                crate_name: &crate_name,
+                url,
+            },
+            None => pipeline::DocumentSource::DocsRs {
+                crate_name: &crate_name,
+                features: cli.features.as_ref(),
+                max_pages: Some(cli.max_pages),
+                options: &crawl_options,
+                raw_html: store_raw_html.then_some(&db),
+            },
+        };
+        let provider = embeddings::embedding_provider()?;
+        let mut sink = pipeline::DatabaseSink::new(
+            &db,
+            &crate_name,
+            cli.target.as_deref(),
+            provider.get_model_name(),
+            None,
         );
+        let stats =
+            pipeline::run(|tx| source.crawl(tx), provider.clone(), &mut sink, &options).await?;
+        let total_time = start.elapsed();
 
-        // Insert into database
-        println!("\n💾 Storing in database...");
-        let db_start = std::time::Instant::now();
-        let crate_id = db
-            .upsert_crate(&crate_name, crate_version.as_deref())
-            .await?;
+        println!(
+            "✅ Crawled {} documents in {:.2}s ({:.1} KB total)",
+            stats.documents,
+            stats.crawl_time.as_secs_f64(),
+            stats.content_bytes as f64 / 1024.0
+        );
+        if let Some(ref version) = stats.crawl.version {
+            println!("📦 Detected version: {version}");
+        } else if stats.crawl.diagnostics.version_missing {
+            eprintln!("❌ Could not determine the crate version; it will be stored without one");
+        }
+        println!("🩺 Crawl diagnostics: {}", stats.crawl.diagnostics);
+        print_reexport_pages(&stats.crawl.reexport_pages);
+        if stats.timed_out {
+            println!(
+                "⏱️  Reached the time limit; {} chunks were stored, finish with --resume",
+                stats.stored_chunks
+            );
+        }
 
-        // Prepare batch data
-        let mut batch_data = Vec::new();
-        for (path, content, embedding) in embeddings.iter() {
-            // Calculate actual token count for this chunk
-            let token_count = bpe.encode_with_special_tokens(content).len() as i32;
-            batch_data.push((
-                path.clone(),
-                content.clone(),
-                embedding.clone(),
-                token_count,
-            ));
+        if stats.documents == 0 {
+            println!("No documents found for crate: {crate_name}");
+            return Ok(());
         }
 
-        db.insert_embeddings_batch(crate_id, &crate_name, &batch_data)
+        // The version is only known once the crawl has seen the crate root
+        if stats.stored_chunks > 0 {
+            db.upsert_crate(
+                &crate_name,
+                stats.crawl.version.as_deref(),
+                cli.target.as_deref(),
+            )
             .await?;
-        let db_time = db_start.elapsed();
-        let total_time = doc_start.elapsed();
-
-        println!(
-            "✅ Successfully stored {} embeddings for {crate_name} in {:.2}s",
-            embeddings.len(),
-            db_time.as_secs_f64()
-        );
+        }
 
+        let cost_per_million = 0.02;
+        let estimated_cost = (stats.total_tokens as f64 / 1_000_000.0) * cost_per_million;
         println!(
             "\n🎉 Complete! Total time: {:.2}s",
             total_time.as_secs_f64()
         );
         println!("📊 Final Summary:");
-        println!("  📥 Document loading: {:.2}s", doc_time.as_secs_f64());
         println!(
-            "  🧠 Embedding generation: {:.2}s",
-            embedding_time.as_secs_f64()
+            "  📥 Document loading: {:.2}s",
+            stats.crawl_time.as_secs_f64()
+        );
+        println!(
+            "  🧠 Stored {} embeddings using {} tokens",
+            stats.stored_chunks, stats.total_tokens
         );
-        println!("  💾 Database storage: {:.2}s", db_time.as_secs_f64());
+        if stats.skipped_chunks > 0 {
+            println!("  ⏩ Already stored: {} chunks", stats.skipped_chunks);
+        }
+        if stats.merged_chunks > 0 {
+            println!(
+                "  🧩 Merged into a neighbouring chunk: {} small chunks (MIN_CHUNK_TOKENS)",
+                stats.merged_chunks
+            );
+        }
+        if stats.short_documents > 0 {
+            println!(
+                "  ✂️  Too short to embed: {} chunks (MIN_DOC_CHARS/MIN_DOC_TOKENS/MIN_CHUNK_TOKENS)",
+                stats.short_documents
+            );
+        }
         println!("  💰 Estimated cost: ${estimated_cost:.6}");
     } else {
         println!(
@@ -263,3 +434,11 @@ async fn main() -> Result<(), ServerError> {
 
     Ok(())
 }
+
+fn print_reexport_pages(reexport_pages: &std::collections::HashMap<String, usize>) {
+    let mut reexport_pages: Vec<_> = reexport_pages.iter().collect();
+    reexport_pages.sort();
+    for (dep_crate, pages) in reexport_pages {
+        println!("🔗 Followed re-exports into {dep_crate}: {pages} pages");
+    }
+}
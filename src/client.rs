@@ -0,0 +1,540 @@
+//! A library-level client for embedding doc search in other Rust services
+//!
+//! [`DocsClient`] queries and populates the same database the MCP servers use, through the
+//! same search and population code ([`crate::search`], [`crate::pipeline::populate_config`]),
+//! without running a server. The embedding provider is owned by the client instead of
+//! being read from the global [`EMBEDDING_CLIENT`](crate::embeddings::EMBEDDING_CLIENT),
+//! so clients with different providers can live in one process.
+//!
+//! ```no_run
+//! use rustdocs_mcp_server::{
+//!     client::{DocsClient, PopulateOptions, QueryOptions},
+//!     embeddings::EmbeddingConfig,
+//!     error::ServerError,
+//! };
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), ServerError> {
+//! let client = DocsClient::builder()
+//!     .database_url("postgresql://localhost/rust_docs_vectors")
+//!     .embedding_config(EmbeddingConfig::VoyageAI {
+//!         api_key: std::env::var("VOYAGE_API_KEY").unwrap_or_default(),
+//!         model: "voyage-3.5".to_string(),
+//!     })
+//!     .query_cache(1000, Duration::from_secs(600))
+//!     .build()
+//!     .await?;
+//!
+//! // Crawl and embed tokio from docs.rs unless it is already populated
+//! if client.status("tokio").await.map_or(true, |s| s.stored_docs == 0) {
+//!     let report = client.populate("tokio", &PopulateOptions::default()).await?;
+//!     println!("Stored {} chunks of tokio {:?}", report.stats.stored_chunks, report.version);
+//! }
+//!
+//! let options = QueryOptions {
+//!     limit: Some(3),
+//!     ..QueryOptions::default()
+//! };
+//! for hit in client.query("tokio", "How do I spawn a task?", &options).await? {
+//!     println!("{:.3} {}", hit.similarity, hit.source_url);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    database::{CrateConfig, Database, PopulationJob, ScoredDoc},
+    doc_loader::CrawlOptions,
+    embeddings::{self, EmbeddingConfig, EmbeddingProvider},
+    error::ServerError,
+    language::{LanguageAction, LanguageFilter},
+    pipeline::{self, PopulationReport},
+    query_cache::{QueryCache, QueryCacheKey},
+    search,
+    validation::{self, MAX_QUESTION_LEN},
+};
+use ndarray::Array1;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// One search result
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub doc_path: String,
+    pub content: String,
+    /// Cosine similarity to the question (`1 - distance`)
+    pub similarity: f32,
+    /// docs.rs URL of the page
+    pub source_url: String,
+    /// Version of the crate the document was stored from
+    pub crate_version: Option<String>,
+}
+
+impl From<ScoredDoc> for SearchHit {
+    fn from(doc: ScoredDoc) -> Self {
+        Self {
+            source_url: search::source_url(&doc.doc_path),
+            doc_path: doc.doc_path,
+            content: doc.content,
+            similarity: doc.similarity,
+            crate_version: doc.crate_version,
+        }
+    }
+}
+
+/// Search options of [`DocsClient::query`]; unset ones fall back to the crate's defaults
+/// like `query_rust_docs` does
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryOptions {
+    /// Results to return (the crate's `default_limit`, else 5; at most 20)
+    pub limit: Option<usize>,
+    /// Drop results below this similarity
+    pub min_similarity: Option<f32>,
+    /// Only search doc paths starting with this prefix
+    pub path_prefix: Option<String>,
+    /// Only search chunks of this docblock section, e.g. `Panics` (crates populated with
+    /// `section_metadata`)
+    pub section: Option<String>,
+    /// Search each part of a compound question separately and merge the results
+    pub expand_query: bool,
+    /// Drop results whose content duplicates a better result
+    pub dedup: bool,
+}
+
+impl Default for QueryOptions {
+    fn default() -> Self {
+        Self {
+            limit: None,
+            min_similarity: None,
+            path_prefix: None,
+            section: None,
+            expand_query: false,
+            dedup: true,
+        }
+    }
+}
+
+/// Which configuration [`DocsClient::populate`] populates
+///
+/// An existing configuration with the same version spec and target is populated with its
+/// stored settings; otherwise one is created from these options.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PopulateOptions {
+    /// `latest` (the default) or a semver version or requirement
+    pub version_spec: Option<String>,
+    /// docs.rs build target; `None` is the default target
+    pub target: Option<String>,
+    /// Features of a new configuration
+    pub features: Vec<String>,
+    /// Most pages to crawl for a new configuration (default 10000)
+    pub max_pages: Option<i32>,
+}
+
+/// What [`DocsClient::status`] reports for a crate
+#[derive(Debug, Clone, Serialize)]
+pub struct CrateStatus {
+    pub config: CrateConfig,
+    /// Documents stored for the crate
+    pub stored_docs: usize,
+    /// `stored_docs` as a fraction of `expected_docs`
+    pub coverage: Option<f64>,
+    pub under_populated: bool,
+    pub latest_job: Option<PopulationJob>,
+}
+
+/// Builds a [`DocsClient`]
+#[derive(Default)]
+pub struct DocsClientBuilder {
+    database_url: Option<String>,
+    database: Option<Database>,
+    provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    cache_capacity: usize,
+    cache_ttl: Duration,
+    generic_penalty: f32,
+    ignore_robots: bool,
+    docs_origin: Option<String>,
+}
+
+impl DocsClientBuilder {
+    /// Database to connect to (default: `MCPDOCS_DATABASE_URL`, like the servers)
+    pub fn database_url(mut self, url: impl Into<String>) -> Self {
+        self.database_url = Some(url.into());
+        self
+    }
+
+    /// Share an existing connection pool instead of connecting
+    pub fn database(mut self, database: Database) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    /// Create the embedding provider from `config`
+    pub fn embedding_config(self, config: EmbeddingConfig) -> Self {
+        self.embedding_provider(embeddings::initialize_embedding_provider(config))
+    }
+
+    /// Use an existing embedding provider (it must match the model the crates were stored with)
+    pub fn embedding_provider(
+        mut self,
+        provider: Arc<dyn EmbeddingProvider + Send + Sync>,
+    ) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Cache up to `capacity` question embeddings for `ttl` (off by default)
+    pub fn query_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.cache_capacity = capacity;
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Demote results close to the crate's average embedding (`GENERIC_PENALTY`; 0 = off)
+    pub fn generic_penalty(mut self, weight: f32) -> Self {
+        self.generic_penalty = weight;
+        self
+    }
+
+    /// Crawl even where the docs host's robots.txt disallows it
+    pub fn ignore_robots(mut self, ignore_robots: bool) -> Self {
+        self.ignore_robots = ignore_robots;
+        self
+    }
+
+    /// Docs host to crawl instead of docs.rs (mirrors, tests)
+    pub fn docs_origin(mut self, origin: impl Into<String>) -> Self {
+        self.docs_origin = Some(origin.into());
+        self
+    }
+
+    pub async fn build(self) -> Result<DocsClient, ServerError> {
+        let provider = self.provider.ok_or_else(|| {
+            ServerError::Config(
+                "DocsClient needs an embedding provider: call embedding_config or embedding_provider"
+                    .to_string(),
+            )
+        })?;
+        let database = match (self.database, self.database_url) {
+            (Some(database), _) => database,
+            (None, Some(url)) => Database::connect(&url).await?,
+            (None, None) => Database::new().await?,
+        };
+        Ok(DocsClient {
+            database,
+            provider,
+            question_embeddings: Arc::new(QueryCache::new(self.cache_capacity, self.cache_ttl)),
+            centroids: Arc::new(Mutex::new(HashMap::new())),
+            generic_penalty: self.generic_penalty,
+            ignore_robots: self.ignore_robots,
+            docs_origin: self.docs_origin,
+        })
+    }
+}
+
+/// Mean embedding per (crate, dimension)
+type CentroidCache = HashMap<(String, usize), Arc<Vec<f32>>>;
+
+/// Doc search and population without an MCP server; cheap to clone
+#[derive(Clone)]
+pub struct DocsClient {
+    database: Database,
+    provider: Arc<dyn EmbeddingProvider + Send + Sync>,
+    question_embeddings: Arc<QueryCache<Vec<Vec<f32>>>>,
+    /// For the generic penalty
+    centroids: Arc<Mutex<CentroidCache>>,
+    generic_penalty: f32,
+    ignore_robots: bool,
+    docs_origin: Option<String>,
+}
+
+impl DocsClient {
+    pub fn builder() -> DocsClientBuilder {
+        DocsClientBuilder::default()
+    }
+
+    /// The client's database, for anything the client doesn't wrap
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    /// Documents of `crate_name` that answer `question`, best first
+    ///
+    /// Ranked like `query_rust_docs`: the crate's query hint, default options and language
+    /// filter apply, then results are merged across sub-queries, filtered by similarity,
+    /// reranked with the generic penalty and deduplicated.
+    pub async fn query(
+        &self,
+        crate_name: &str,
+        question: &str,
+        options: &QueryOptions,
+    ) -> Result<Vec<SearchHit>, ServerError> {
+        if let Some(problem) = validation::crate_name_problem(crate_name) {
+            return Err(ServerError::Config(format!("crate_name {problem}")));
+        }
+        if question.trim().is_empty() || question.chars().count() > MAX_QUESTION_LEN {
+            return Err(ServerError::Config(format!(
+                "question must be between 1 and {MAX_QUESTION_LEN} characters"
+            )));
+        }
+
+        let configs = self.database.get_crate_configs_by_name(crate_name).await?;
+        if !configs.is_empty() && configs.iter().all(|c| !c.enabled) {
+            return Err(ServerError::Config(format!(
+                "Crate '{crate_name}' is disabled"
+            )));
+        }
+        let query_hint = configs.iter().find_map(|c| c.query_hint.clone());
+        let languages = configs
+            .iter()
+            .find_map(|c| LanguageFilter::for_crate(c).ok().flatten())
+            .filter(|f| f.action() == LanguageAction::Tag);
+        let defaults = configs
+            .iter()
+            .fold(search::SearchParams::default(), |defaults, config| {
+                defaults.or(config.search_defaults())
+            });
+        let params = search::SearchParams {
+            limit: options.limit,
+            min_similarity: options.min_similarity,
+            path_prefix: options.path_prefix.clone(),
+        }
+        .or(defaults);
+        let fetch_limit = params.fetch_limit(
+            options.dedup,
+            self.generic_penalty > 0.0 || languages.is_some(),
+        );
+
+        let queries = if options.expand_query {
+            search::split_question(question)
+        } else {
+            vec![question.to_string()]
+        };
+        let queries: Vec<String> = queries
+            .iter()
+            .map(|query| search::apply_query_hint(query, query_hint.as_deref()))
+            .collect();
+        let question_embeddings = self.embed_queries(crate_name, queries).await?;
+        let dimension = question_embeddings.first().map_or(0, Vec::len);
+
+        let path_prefix = params.path_prefix();
+        let section = options.section.as_deref();
+        let searches = question_embeddings.into_iter().map(|embedding| {
+            let embedding = Array1::from_vec(embedding);
+            async move {
+                self.database
+                    .search_similar_docs_tuned(
+                        crate_name,
+                        &embedding,
+                        fetch_limit as i32,
+                        None,
+                        path_prefix,
+                        section,
+                    )
+                    .await
+            }
+        });
+        let result_sets = futures::future::try_join_all(searches).await?;
+        let results = params.filter(search::merge_results(result_sets, fetch_limit));
+        let results = match &languages {
+            Some(filter) if !results.is_empty() => {
+                let doc_paths: Vec<String> = results.iter().map(|d| d.doc_path.clone()).collect();
+                let tagged = self
+                    .database
+                    .get_document_languages(crate_name, &doc_paths)
+                    .await?;
+                search::filter_languages(results, &tagged, &filter.codes()).0
+            }
+            _ => results,
+        };
+        let results = self
+            .penalize_generic(crate_name, dimension, results)
+            .await?;
+        let results = if options.dedup {
+            search::dedup_by_content(results).0
+        } else {
+            results
+        };
+        Ok(results
+            .into_iter()
+            .take(params.result_limit())
+            .map(SearchHit::from)
+            .collect())
+    }
+
+    /// Crawl, embed and store `crate_name`, waiting for the population to finish
+    ///
+    /// Uses the server's population path, so an interrupted population resumes and the
+    /// configuration records the populated version.
+    pub async fn populate(
+        &self,
+        crate_name: &str,
+        options: &PopulateOptions,
+    ) -> Result<PopulationReport, ServerError> {
+        if let Some(problem) = validation::crate_name_problem(crate_name) {
+            return Err(ServerError::Config(format!("crate_name {problem}")));
+        }
+        let version_spec = options.version_spec.as_deref().unwrap_or("latest");
+        if !validation::is_valid_version_spec(version_spec) {
+            return Err(ServerError::Config(format!(
+                "version_spec '{version_spec}' is not 'latest' or a semver version or requirement"
+            )));
+        }
+
+        let existing = self
+            .database
+            .get_crate_configs_by_name(crate_name)
+            .await?
+            .into_iter()
+            .find(|c| c.version_spec == version_spec && c.target == options.target);
+        let config = match existing {
+            Some(config) => config,
+            None => {
+                let config = CrateConfig {
+                    features: options.features.clone(),
+                    target: options.target.clone(),
+                    max_pages: options.max_pages,
+                    ..CrateConfig::new(crate_name, version_spec)
+                };
+                self.database.upsert_crate_config(&config).await?
+            }
+        };
+        let crawl_options = CrawlOptions {
+            ignore_robots: self.ignore_robots,
+            docs_origin: self.docs_origin.clone(),
+            ..config.crawl_options()
+        };
+
+        // Run population in a blocking task to handle non-Send scraper types, like the server
+        let database = self.database.clone();
+        let provider = self.provider.clone();
+        let report = tokio::task::spawn_blocking(move || {
+            tokio::runtime::Handle::current().block_on(pipeline::populate_config(
+                &database,
+                provider,
+                &config,
+                &crawl_options,
+                None,
+                None,
+            ))
+        })
+        .await
+        .map_err(|e| ServerError::Internal(format!("Task join error: {e}")))??;
+
+        self.question_embeddings.invalidate_crate(crate_name);
+        if let Ok(mut centroids) = self.centroids.lock() {
+            centroids.retain(|(name, _), _| name != crate_name);
+        }
+        Ok(report)
+    }
+
+    /// Every crate configuration, enabled or not
+    pub async fn list_crates(&self) -> Result<Vec<CrateConfig>, ServerError> {
+        self.database.get_crate_configs(false).await
+    }
+
+    /// Configuration, stored documents and latest population job of `crate_name`
+    ///
+    /// Reports the default-target configuration when the crate has several.
+    pub async fn status(&self, crate_name: &str) -> Result<CrateStatus, ServerError> {
+        let configs = self.database.get_crate_configs_by_name(crate_name).await?;
+        let config = configs
+            .iter()
+            .find(|c| c.target.is_none())
+            .or(configs.first())
+            .cloned()
+            .ok_or_else(|| ServerError::Config(format!("Crate '{crate_name}' not found")))?;
+        let stored_docs = self
+            .database
+            .get_embeddings_presence(std::slice::from_ref(&config.name))
+            .await?
+            .get(&config.name)
+            .copied()
+            .unwrap_or(0)
+            .max(0) as usize;
+        let latest_job = self.database.get_latest_population_job(config.id).await?;
+        Ok(CrateStatus {
+            coverage: config.coverage(stored_docs),
+            under_populated: config.is_under_populated(stored_docs),
+            stored_docs,
+            latest_job,
+            config,
+        })
+    }
+
+    /// Embeddings of `queries`, cached per crate and question set when the cache is on
+    async fn embed_queries(
+        &self,
+        crate_name: &str,
+        queries: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, ServerError> {
+        let cache_key = QueryCacheKey::new(
+            crate_name,
+            &queries.join("\n"),
+            self.provider.get_model_name(),
+            "client",
+        );
+        if let Some(embeddings) = self.question_embeddings.get(&cache_key) {
+            return Ok(embeddings);
+        }
+        let (embeddings, _tokens) = self.provider.generate_embeddings(&queries).await?;
+        if embeddings.len() != queries.len() {
+            return Err(ServerError::Internal(format!(
+                "Embedding provider returned {} embeddings for {} queries",
+                embeddings.len(),
+                queries.len()
+            )));
+        }
+        self.question_embeddings
+            .insert(cache_key, embeddings.clone());
+        Ok(embeddings)
+    }
+
+    /// Rerank results with the generic-boilerplate penalty, if it is enabled
+    async fn penalize_generic(
+        &self,
+        crate_name: &str,
+        dimension: usize,
+        results: Vec<ScoredDoc>,
+    ) -> Result<Vec<ScoredDoc>, ServerError> {
+        if self.generic_penalty <= 0.0 || results.is_empty() {
+            return Ok(results);
+        }
+        let key = (crate_name.to_string(), dimension);
+        let cached = self
+            .centroids
+            .lock()
+            .ok()
+            .and_then(|c| c.get(&key).cloned());
+        let centroid = match cached {
+            Some(centroid) => centroid,
+            None => {
+                let Some(centroid) = self
+                    .database
+                    .get_crate_centroid(crate_name, dimension)
+                    .await?
+                else {
+                    return Ok(results);
+                };
+                let centroid = Arc::new(centroid);
+                if let Ok(mut centroids) = self.centroids.lock() {
+                    centroids.insert(key, centroid.clone());
+                }
+                centroid
+            }
+        };
+        let doc_paths: Vec<String> = results.iter().map(|d| d.doc_path.clone()).collect();
+        let commonness = self
+            .database
+            .centroid_similarities(crate_name, &centroid, &doc_paths)
+            .await?;
+        Ok(search::penalize_generic(
+            results,
+            &commonness,
+            self.generic_penalty,
+        ))
+    }
+}
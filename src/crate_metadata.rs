@@ -0,0 +1,146 @@
+//! Package facts (description, repository, license, ...) from the crates.io API
+//!
+//! The scraper only sees rendered docs; crates.io is the authoritative source for what a
+//! crate is, where its code lives and how it is licensed. Lookups are cached in the
+//! `crate_metadata` table, so repeated `crate_info` calls don't hit crates.io.
+
+use crate::{database::CrateMetadata, error::ServerError, version_spec::PublishedVersion};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Base URL of the crates.io API
+pub const CRATES_IO_ORIGIN: &str = "https://crates.io";
+
+/// Cached metadata older than this is refreshed on the next lookup
+pub const METADATA_MAX_AGE: Duration = Duration::from_secs(24 * 3600);
+
+/// A slow crates.io falls back to the cached row instead of holding the tool call
+const CRATES_IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+struct CrateResponse {
+    #[serde(rename = "crate")]
+    krate: CrateInfo,
+    #[serde(default)]
+    versions: Vec<VersionInfo>,
+}
+
+#[derive(Deserialize)]
+struct CrateInfo {
+    name: String,
+    description: Option<String>,
+    repository: Option<String>,
+    homepage: Option<String>,
+    #[serde(default)]
+    downloads: i64,
+    max_stable_version: Option<String>,
+    max_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct VersionInfo {
+    num: String,
+    license: Option<String>,
+    #[serde(default)]
+    yanked: bool,
+}
+
+/// Whether `metadata` is old enough to be refreshed from crates.io
+pub fn is_stale(metadata: &CrateMetadata, now: DateTime<Utc>) -> bool {
+    (now - metadata.fetched_at)
+        .to_std()
+        .is_ok_and(|age| age >= METADATA_MAX_AGE)
+}
+
+/// Client for the crates.io crate endpoint
+#[derive(Debug)]
+pub struct CratesIo {
+    client: reqwest::Client,
+    origin: String,
+}
+
+impl CratesIo {
+    /// Look crates up on `origin` (normally [`CRATES_IO_ORIGIN`])
+    pub fn new(origin: &str) -> Self {
+        // crates.io rejects requests without an identifying User-Agent
+        let client = reqwest::Client::builder()
+            .timeout(CRATES_IO_TIMEOUT)
+            .user_agent(crate::robots::user_agent())
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            origin: origin.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Current metadata for `crate_name`, or `None` if crates.io doesn't know the crate
+    pub async fn fetch(&self, crate_name: &str) -> Result<Option<CrateMetadata>, ServerError> {
+        let Some(body) = self.get_crate(crate_name).await? else {
+            return Ok(None);
+        };
+        let latest_version = body.krate.max_stable_version.or(body.krate.max_version);
+        let license = body
+            .versions
+            .iter()
+            .find(|version| Some(&version.num) == latest_version.as_ref())
+            .or_else(|| body.versions.first())
+            .and_then(|version| version.license.clone());
+        Ok(Some(CrateMetadata {
+            crate_name: body.krate.name,
+            description: body.krate.description.map(|d| d.trim().to_string()),
+            repository: body.krate.repository,
+            homepage: body.krate.homepage,
+            license,
+            latest_version,
+            downloads: body.krate.downloads,
+            fetched_at: Utc::now(),
+        }))
+    }
+
+    /// Every published version of `crate_name`, yanked ones included and flagged, or
+    /// `None` if crates.io doesn't know the crate
+    ///
+    /// Versions that aren't valid semver (only possible for ancient uploads) are left out.
+    pub async fn versions(
+        &self,
+        crate_name: &str,
+    ) -> Result<Option<Vec<PublishedVersion>>, ServerError> {
+        Ok(self.get_crate(crate_name).await?.map(|body| {
+            body.versions
+                .into_iter()
+                .filter_map(|info| {
+                    Some(PublishedVersion {
+                        version: semver::Version::parse(&info.num).ok()?,
+                        yanked: info.yanked,
+                    })
+                })
+                .collect()
+        }))
+    }
+
+    async fn get_crate(&self, crate_name: &str) -> Result<Option<CrateResponse>, ServerError> {
+        let url = format!("{}/api/v1/crates/{crate_name}", self.origin);
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| ServerError::Network(format!("crates.io request failed: {e}")))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(ServerError::Network(format!(
+                "crates.io returned HTTP {} for {crate_name}",
+                response.status()
+            )));
+        }
+        response.json().await.map(Some).map_err(|e| {
+            ServerError::Parsing(format!(
+                "Unexpected crates.io response for {crate_name}: {e}"
+            ))
+        })
+    }
+}
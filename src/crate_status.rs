@@ -0,0 +1,243 @@
+//! One user-facing population status per crate
+//!
+//! A crate's state is spread over its `crate_configs` row, its `population_jobs` and
+//! its stored embeddings. [`CrateStatus::derive`] folds those into a single answer so
+//! `check_crate_status`, `list_crates`, query errors and readiness all agree on whether
+//! a user should wait, retry, or fix something.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// What [`CrateStatus::derive`] decides from, as `Database::get_crate_statuses` reads it
+#[derive(Debug, Clone, Default)]
+pub struct CrateStatusFacts {
+    /// The crate has a `crate_configs` row
+    pub configured: bool,
+    /// Stored version (config's `current_version`, else the `crates` row's)
+    pub version: Option<String>,
+    /// When the stored docs were last (re)populated
+    pub populated_at: Option<DateTime<Utc>>,
+    /// Established document baseline, used to estimate progress
+    pub expected_docs: Option<usize>,
+    /// The most recent population job, if any
+    pub latest_job: Option<JobFacts>,
+    /// Failed jobs since the last completed one
+    pub consecutive_failures: u32,
+    /// Documents stored in `doc_embeddings`
+    pub docs: usize,
+}
+
+/// The parts of a population job that matter for the status
+#[derive(Debug, Clone)]
+pub struct JobFacts {
+    pub id: i32,
+    /// `pending`, `running`, `completed`, `partial` or `failed`
+    pub status: String,
+    pub error_message: Option<String>,
+    pub docs_populated: Option<i32>,
+}
+
+/// Where a crate stands, serialized with its name under `state`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum CrateStatus {
+    /// Nothing is known about the crate
+    NotConfigured,
+    /// Configured and waiting for a population slot; `job_id` is `None` when population
+    /// was never attempted
+    Queued { job_id: Option<i32> },
+    /// A population job is running
+    Populating {
+        job_id: i32,
+        docs_populated: usize,
+        /// Fraction of the expected documents stored so far, when a baseline exists
+        progress: Option<f64>,
+    },
+    /// The last population failed; `docs` are left over from an earlier one, if any
+    Failed {
+        reason: String,
+        attempts: u32,
+        docs: usize,
+    },
+    /// Docs are stored and current
+    Populated {
+        version: Option<String>,
+        docs: usize,
+    },
+    /// The last population stopped at its time limit; its `docs` are searchable and the
+    /// next population resumes after them
+    Partial {
+        job_id: i32,
+        docs: usize,
+        /// Fraction of the expected documents stored, when a baseline exists
+        coverage: Option<f64>,
+    },
+    /// Docs are stored but older than the staleness threshold
+    Stale {
+        version: Option<String>,
+        docs: usize,
+        age_days: i64,
+    },
+}
+
+#[allow(dead_code)] // Used by the HTTP server
+impl CrateStatus {
+    /// Fold `facts` into one status; docs older than `stale_after_days` (0 = never) are stale
+    ///
+    /// An active or failed latest job outranks stored docs: a crate being repopulated
+    /// reports `populating`, and one whose refresh failed reports `failed` with the
+    /// leftover docs still counted.
+    pub fn derive(facts: &CrateStatusFacts, now: DateTime<Utc>, stale_after_days: u32) -> Self {
+        if let Some(job) = &facts.latest_job {
+            match job.status.as_str() {
+                "pending" => {
+                    return Self::Queued {
+                        job_id: Some(job.id),
+                    }
+                }
+                "running" => {
+                    let docs_populated = job.docs_populated.unwrap_or(0).max(0) as usize;
+                    let progress = facts
+                        .expected_docs
+                        .filter(|&expected| expected > 0)
+                        .map(|expected| (docs_populated as f64 / expected as f64).min(1.0));
+                    return Self::Populating {
+                        job_id: job.id,
+                        docs_populated,
+                        progress,
+                    };
+                }
+                "partial" if facts.docs > 0 => {
+                    return Self::Partial {
+                        job_id: job.id,
+                        docs: facts.docs,
+                        coverage: facts
+                            .expected_docs
+                            .filter(|&expected| expected > 0)
+                            .map(|expected| (facts.docs as f64 / expected as f64).min(1.0)),
+                    }
+                }
+                "failed" => {
+                    return Self::Failed {
+                        reason: job
+                            .error_message
+                            .clone()
+                            .unwrap_or_else(|| "unknown error".to_string()),
+                        attempts: facts.consecutive_failures.max(1),
+                        docs: facts.docs,
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if facts.docs > 0 {
+            let age_days = facts
+                .populated_at
+                .map(|at| (now - at).num_days().max(0))
+                .unwrap_or(0);
+            if stale_after_days > 0 && age_days >= i64::from(stale_after_days) {
+                return Self::Stale {
+                    version: facts.version.clone(),
+                    docs: facts.docs,
+                    age_days,
+                };
+            }
+            return Self::Populated {
+                version: facts.version.clone(),
+                docs: facts.docs,
+            };
+        }
+
+        match (&facts.latest_job, facts.configured) {
+            (Some(_), _) => Self::Failed {
+                reason: "the last population stored no documents".to_string(),
+                attempts: facts.consecutive_failures.max(1),
+                docs: 0,
+            },
+            (None, true) => Self::Queued { job_id: None },
+            (None, false) => Self::NotConfigured,
+        }
+    }
+
+    /// The `state` name, e.g. `populating`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NotConfigured => "not_configured",
+            Self::Queued { .. } => "queued",
+            Self::Populating { .. } => "populating",
+            Self::Failed { .. } => "failed",
+            Self::Partial { .. } => "partial",
+            Self::Populated { .. } => "populated",
+            Self::Stale { .. } => "stale",
+        }
+    }
+
+    /// Documents that can be searched right now
+    pub fn docs(&self) -> usize {
+        match self {
+            Self::Failed { docs, .. }
+            | Self::Partial { docs, .. }
+            | Self::Populated { docs, .. }
+            | Self::Stale { docs, .. } => *docs,
+            _ => 0,
+        }
+    }
+
+    /// What a user asking about `crate_name` should know and do next
+    pub fn describe(&self, crate_name: &str) -> String {
+        match self {
+            Self::NotConfigured => format!(
+                "Crate '{crate_name}' is not configured; add it with the add_crate tool"
+            ),
+            Self::Queued { job_id: Some(job_id) } => format!(
+                "Crate '{crate_name}' is queued for population (job {job_id}); it starts when a population slot frees up"
+            ),
+            Self::Queued { job_id: None } => format!(
+                "Crate '{crate_name}' is configured but population was never attempted; start it with populate_pending"
+            ),
+            Self::Populating {
+                job_id,
+                docs_populated,
+                progress,
+            } => match progress {
+                Some(progress) => format!(
+                    "Crate '{crate_name}' is being populated (job {job_id}, {docs_populated} documents, ~{:.0}%); retry in a few minutes",
+                    progress * 100.0
+                ),
+                None => format!(
+                    "Crate '{crate_name}' is being populated (job {job_id}, {docs_populated} documents so far); retry in a few minutes"
+                ),
+            },
+            Self::Failed {
+                reason, attempts, ..
+            } => {
+                let attempts = match attempts {
+                    1 => "1 attempt".to_string(),
+                    n => format!("{n} attempts"),
+                };
+                format!(
+                    "Population of crate '{crate_name}' failed after {attempts}: {reason}. Fix the cause (see check_crate_status) and retry with add_crate or populate_pending"
+                )
+            }
+            Self::Partial {
+                job_id,
+                docs,
+                coverage,
+            } => {
+                let coverage = coverage
+                    .map(|coverage| format!(" (~{:.0}% of the expected documents)", coverage * 100.0))
+                    .unwrap_or_default();
+                format!(
+                    "Crate '{crate_name}' is partially populated with {docs} documents{coverage}: job {job_id} reached its time limit. They can be searched now, and the next population resumes where it stopped"
+                )
+            }
+            Self::Populated { docs, .. } => {
+                format!("Crate '{crate_name}' is populated with {docs} documents")
+            }
+            Self::Stale { docs, age_days, .. } => format!(
+                "Crate '{crate_name}' is populated with {docs} documents, last refreshed {age_days} days ago"
+            ),
+        }
+    }
+}
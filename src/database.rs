@@ -1,45 +1,1447 @@
-use crate::error::ServerError;
+use crate::{
+    crate_status::{CrateStatus, CrateStatusFacts, JobFacts},
+    doc_loader::{CrawlDiagnostics, CrawlOptions, CrawlTrace},
+    embeddings::vector_problem,
+    error::ServerError,
+    query_log::QueryLogEntry,
+    search::{self, SearchParams},
+};
+use futures::{Stream, TryStreamExt};
 use ndarray::Array1;
 use pgvector::Vector;
 use serde::{Deserialize, Serialize};
-use sqlx::{postgres::PgPoolOptions, PgPool, Row};
-use std::{env, time::Duration};
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    PgPool, Row,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    time::Duration,
+};
+
+/// Dimension of `doc_embeddings.embedding` (OpenAI text-embedding-3-large)
+pub const EMBEDDING_DIMENSION: i32 = 3072;
+
+/// Rows fetched per query by [`Database::get_crate_documents_stream`]
+pub const DOCUMENT_PAGE_SIZE: i64 = 500;
+
+/// Pages fetched per query by [`Database::get_raw_pages_stream`]
+pub const RAW_PAGE_BATCH_SIZE: i64 = 50;
+
+/// pgvector's HNSW indexes support at most this many dimensions
+const HNSW_MAX_DIMENSION: usize = 2000;
+
+/// Table holding the vectors of crates embedded with a `dimension`-sized model
+///
+/// Vectors of [`EMBEDDING_DIMENSION`] live in `doc_embeddings.embedding` itself (`None`).
+/// Any other dimension gets a `doc_embeddings_<dimension>` table keyed by
+/// `doc_embeddings.id`, while content, paths and stats stay in `doc_embeddings`, so
+/// everything except vector inserts and searches works the same for every model.
+pub fn dimension_table(dimension: usize) -> Option<String> {
+    (dimension != EMBEDDING_DIMENSION as usize).then(|| format!("doc_embeddings_{dimension}"))
+}
+
+/// Partition receiving rows of crates that have no partition of their own yet
+pub const DEFAULT_PARTITION: &str = "doc_embeddings_default";
+
+/// Table `doc_embeddings` is converted into by [`Database::partition_embeddings`] before
+/// the two are swapped
+const PARTITIONED_TABLE: &str = "doc_embeddings_partitioned";
+
+/// Name the pre-partitioning table is kept under after [`Database::partition_embeddings`]
+pub const UNPARTITIONED_TABLE: &str = "doc_embeddings_unpartitioned";
+
+/// `doc_embeddings` list partition holding one crate's rows once the table is partitioned
+///
+/// Crate names can be longer than an identifier allows and may differ only in `-`/`_`,
+/// so the name is a stable FNV-1a hash of the crate name rather than the name itself.
+pub fn crate_partition_name(crate_name: &str) -> String {
+    let hash = crate_name
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("doc_embeddings_part_{hash:016x}")
+}
+
+/// Rewrite a `pg_get_indexdef`/`pg_get_triggerdef` definition to apply to `table` instead,
+/// naming the copy `name`
+///
+/// Returns `None` for definitions of a shape this doesn't know how to move, including
+/// unique indexes, which a partitioned table only allows when they cover `crate_name`.
+pub fn retarget_definition(definition: &str, name: &str, table: &str) -> Option<String> {
+    if definition.starts_with("CREATE INDEX ") {
+        let using = definition.find(" USING ")?;
+        Some(format!(
+            "CREATE INDEX IF NOT EXISTS {name} ON {table}{}",
+            &definition[using..]
+        ))
+    } else if let Some(rest) = definition.strip_prefix("CREATE TRIGGER ") {
+        // Skip the old trigger name; what's left is "<timing/events> ON <table> FOR EACH ..."
+        let (_, rest) = rest.split_once(' ')?;
+        let for_each = rest.find(" FOR EACH ")?;
+        let on = rest[..for_each].rfind(" ON ")?;
+        Some(format!(
+            "CREATE TRIGGER {name} {} ON {table}{}",
+            &rest[..on],
+            &rest[for_each..]
+        ))
+    } else {
+        None
+    }
+}
+
+/// Quote `text` as a SQL string literal, for DDL that can't take bind parameters
+fn quote_literal(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// Columns the code relies on: (table, column, required type if its shape matters,
+/// SQL file that creates it)
+pub const REQUIRED_COLUMNS: &[(&str, &str, Option<&str>, &str)] = &[
+    ("crates", "id", None, "sql/schema.sql"),
+    ("crates", "name", None, "sql/schema.sql"),
+    ("crates", "version", None, "sql/schema.sql"),
+    ("crates", "last_updated", None, "sql/schema.sql"),
+    ("crates", "total_docs", None, "sql/schema.sql"),
+    ("crates", "total_tokens", None, "sql/schema.sql"),
+    (
+        "crates",
+        "target",
+        None,
+        "sql/migrations/add_crate_targets.sql",
+    ),
+    (
+        "crates",
+        "embedding_model",
+        None,
+        "sql/migrations/add_crate_embedding_model.sql",
+    ),
+    ("doc_embeddings", "id", None, "sql/schema.sql"),
+    ("doc_embeddings", "crate_id", None, "sql/schema.sql"),
+    ("doc_embeddings", "crate_name", None, "sql/schema.sql"),
+    ("doc_embeddings", "doc_path", None, "sql/schema.sql"),
+    ("doc_embeddings", "content", None, "sql/schema.sql"),
+    (
+        "doc_embeddings",
+        "embedding",
+        Some("vector"),
+        "sql/schema.sql",
+    ),
+    ("doc_embeddings", "token_count", None, "sql/schema.sql"),
+    ("doc_embeddings", "created_at", None, "sql/schema.sql"),
+    (
+        "doc_embeddings",
+        "content_tsv",
+        Some("tsvector"),
+        "sql/migrations/add_content_tsv.sql",
+    ),
+    (
+        "doc_embeddings",
+        "content_compressed",
+        Some("bytea"),
+        "sql/migrations/add_content_compression.sql",
+    ),
+    (
+        "doc_embeddings",
+        "content_size",
+        None,
+        "sql/migrations/add_content_compression.sql",
+    ),
+    (
+        "doc_embeddings",
+        "first_seen_at",
+        Some("timestamptz"),
+        "sql/migrations/add_document_freshness.sql",
+    ),
+    (
+        "doc_embeddings",
+        "last_updated_at",
+        Some("timestamptz"),
+        "sql/migrations/add_document_freshness.sql",
+    ),
+    (
+        "crate_configs",
+        "id",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "crate_configs",
+        "name",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "crate_configs",
+        "version_spec",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "crate_configs",
+        "current_version",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "crate_configs",
+        "features",
+        Some("_text"),
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "crate_configs",
+        "expected_docs",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "crate_configs",
+        "enabled",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "crate_configs",
+        "last_checked",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "crate_configs",
+        "last_populated",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "crate_configs",
+        "created_at",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "crate_configs",
+        "updated_at",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "crate_configs",
+        "content_selector",
+        None,
+        "sql/migrations/add_content_selector.sql",
+    ),
+    (
+        "crate_configs",
+        "include_url_patterns",
+        Some("_text"),
+        "sql/migrations/add_url_patterns.sql",
+    ),
+    (
+        "crate_configs",
+        "exclude_url_patterns",
+        Some("_text"),
+        "sql/migrations/add_url_patterns.sql",
+    ),
+    (
+        "crate_configs",
+        "follow_reexports",
+        None,
+        "sql/migrations/add_reexport_following.sql",
+    ),
+    (
+        "crate_configs",
+        "reexport_crates",
+        Some("_text"),
+        "sql/migrations/add_reexport_following.sql",
+    ),
+    (
+        "crate_configs",
+        "reexport_page_budget",
+        None,
+        "sql/migrations/add_reexport_following.sql",
+    ),
+    (
+        "crate_configs",
+        "reexport_pages_per_crate",
+        None,
+        "sql/migrations/add_reexport_following.sql",
+    ),
+    (
+        "crate_configs",
+        "rustdoc_json_url",
+        None,
+        "sql/migrations/add_rustdoc_json_url.sql",
+    ),
+    (
+        "crate_configs",
+        "target",
+        None,
+        "sql/migrations/add_crate_targets.sql",
+    ),
+    (
+        "crate_configs",
+        "auto_expected_docs",
+        None,
+        "sql/migrations/add_auto_expected_docs.sql",
+    ),
+    (
+        "crate_configs",
+        "query_hint",
+        None,
+        "sql/migrations/add_query_defaults.sql",
+    ),
+    (
+        "crate_configs",
+        "default_limit",
+        None,
+        "sql/migrations/add_query_defaults.sql",
+    ),
+    (
+        "crate_configs",
+        "default_min_similarity",
+        None,
+        "sql/migrations/add_query_defaults.sql",
+    ),
+    (
+        "crate_configs",
+        "default_path_prefix",
+        None,
+        "sql/migrations/add_query_defaults.sql",
+    ),
+    (
+        "crate_configs",
+        "max_pages",
+        None,
+        "sql/migrations/add_crate_max_pages.sql",
+    ),
+    (
+        "crate_configs",
+        "store_raw_html",
+        None,
+        "sql/migrations/add_raw_html.sql",
+    ),
+    (
+        "doc_pages",
+        "crate_name",
+        None,
+        "sql/migrations/add_raw_html.sql",
+    ),
+    (
+        "doc_pages",
+        "page_path",
+        None,
+        "sql/migrations/add_raw_html.sql",
+    ),
+    (
+        "doc_pages",
+        "raw_html",
+        None,
+        "sql/migrations/add_raw_html.sql",
+    ),
+    (
+        "crate_configs",
+        "languages",
+        None,
+        "sql/migrations/add_doc_languages.sql",
+    ),
+    (
+        "crate_configs",
+        "language_action",
+        None,
+        "sql/migrations/add_doc_languages.sql",
+    ),
+    (
+        "doc_embeddings",
+        "language",
+        None,
+        "sql/migrations/add_doc_languages.sql",
+    ),
+    (
+        "population_jobs",
+        "id",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "population_jobs",
+        "crate_config_id",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "population_jobs",
+        "status",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "population_jobs",
+        "started_at",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "population_jobs",
+        "completed_at",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "population_jobs",
+        "error_message",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "population_jobs",
+        "docs_populated",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "population_jobs",
+        "created_at",
+        None,
+        "sql/migrations/add_crate_configs.sql",
+    ),
+    (
+        "population_jobs",
+        "diagnostics",
+        Some("jsonb"),
+        "sql/migrations/add_population_job_diagnostics.sql",
+    ),
+    (
+        "crate_metadata",
+        "crate_name",
+        None,
+        "sql/migrations/add_crate_metadata.sql",
+    ),
+    (
+        "crate_metadata",
+        "description",
+        None,
+        "sql/migrations/add_crate_metadata.sql",
+    ),
+    (
+        "crate_metadata",
+        "repository",
+        None,
+        "sql/migrations/add_crate_metadata.sql",
+    ),
+    (
+        "crate_metadata",
+        "homepage",
+        None,
+        "sql/migrations/add_crate_metadata.sql",
+    ),
+    (
+        "crate_metadata",
+        "license",
+        None,
+        "sql/migrations/add_crate_metadata.sql",
+    ),
+    (
+        "crate_metadata",
+        "latest_version",
+        None,
+        "sql/migrations/add_crate_metadata.sql",
+    ),
+    (
+        "crate_metadata",
+        "downloads",
+        None,
+        "sql/migrations/add_crate_metadata.sql",
+    ),
+    (
+        "crate_metadata",
+        "fetched_at",
+        Some("timestamptz"),
+        "sql/migrations/add_crate_metadata.sql",
+    ),
+    (
+        "population_jobs",
+        "self_test",
+        Some("jsonb"),
+        "sql/migrations/add_population_self_test.sql",
+    ),
+    (
+        "backup_runs",
+        "completed_at",
+        Some("timestamptz"),
+        "sql/migrations/add_backup_runs.sql",
+    ),
+    (
+        "retrieval_feedback",
+        "useful",
+        Some("boolean"),
+        "sql/migrations/add_retrieval_feedback.sql",
+    ),
+    (
+        "crate_configs",
+        "max_embeddings",
+        None,
+        "sql/migrations/add_max_embeddings.sql",
+    ),
+    (
+        "crate_configs",
+        "truncated",
+        Some("boolean"),
+        "sql/migrations/add_max_embeddings.sql",
+    ),
+    (
+        "crate_configs",
+        "max_duration_secs",
+        None,
+        "sql/migrations/add_partial_population.sql",
+    ),
+    (
+        "crate_configs",
+        "partial_at",
+        Some("timestamptz"),
+        "sql/migrations/add_partial_population.sql",
+    ),
+    (
+        "crate_configs",
+        "low_doc_coverage",
+        Some("boolean"),
+        "sql/migrations/add_low_doc_coverage.sql",
+    ),
+    (
+        "crates",
+        "live_generation",
+        None,
+        "sql/migrations/add_generations.sql",
+    ),
+    (
+        "doc_embeddings",
+        "generation",
+        None,
+        "sql/migrations/add_generations.sql",
+    ),
+    (
+        "crate_configs",
+        "section_metadata",
+        None,
+        "sql/migrations/add_doc_sections.sql",
+    ),
+    (
+        "doc_embeddings",
+        "section",
+        None,
+        "sql/migrations/add_doc_sections.sql",
+    ),
+    (
+        "crate_configs",
+        "allow_prerelease",
+        None,
+        "sql/migrations/add_allow_prerelease.sql",
+    ),
+    (
+        "precomputed_answers",
+        "question_embedding",
+        Some("vector"),
+        "sql/migrations/add_precomputed_answers.sql",
+    ),
+    (
+        "precomputed_answers",
+        "results",
+        Some("jsonb"),
+        "sql/migrations/add_precomputed_answers.sql",
+    ),
+    (
+        "population_jobs",
+        "crawl_trace",
+        Some("jsonb"),
+        "sql/migrations/add_crawl_trace.sql",
+    ),
+    (
+        "query_log",
+        "question_hash",
+        None,
+        "sql/migrations/add_query_log.sql",
+    ),
+];
+
+/// Indexes the code relies on, e.g. as `ON CONFLICT` targets: (table, index, SQL file
+/// that creates it)
+pub const REQUIRED_INDEXES: &[(&str, &str, &str)] = &[(
+    "crates",
+    "idx_crates_name_target",
+    "sql/migrations/add_crate_targets.sql",
+)];
+
+/// What the database looks like, as far as [`schema_problems`] cares
+#[derive(Debug, Default)]
+pub struct SchemaSnapshot {
+    /// Installed pgvector version
+    pub pgvector_version: Option<String>,
+    /// Whether the server could install pgvector (`pg_available_extensions`)
+    pub pgvector_available: bool,
+    /// Type (`udt_name`) of every column of the checked tables, by (table, column)
+    pub columns: HashMap<(String, String), String>,
+    /// Declared dimension of `doc_embeddings.embedding` (`None` when unconstrained)
+    pub embedding_dimension: Option<i32>,
+    /// Which of [`REQUIRED_INDEXES`] exist
+    pub indexes: HashSet<String>,
+}
+
+/// Everything wrong with `snapshot`, each with the command that fixes it
+///
+/// These are the [`schema_findings`] that stop the servers from starting.
+pub fn schema_problems(snapshot: &SchemaSnapshot) -> Vec<String> {
+    schema_findings(snapshot, None)
+        .into_iter()
+        .filter(|finding| finding.severity == AuditSeverity::Error)
+        .map(|finding| match finding.fix {
+            Some(fix) => format!("{}: {fix}", finding.detail),
+            None => finding.detail,
+        })
+        .collect()
+}
+
+/// First pgvector release with HNSW indexes, which per-dimension tables and crate
+/// partitions are created with
+pub const MIN_PGVECTOR_FOR_HNSW: semver::Version = semver::Version::new(0, 5, 0);
+
+/// One mismatch between the database and what the code expects
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchemaFinding {
+    /// Which check produced it: `pgvector`, `pgvector_hnsw`, `columns`,
+    /// `embedding_dimension` or `provider_dimension`
+    pub check: &'static str,
+    /// Errors stop the servers from starting; warnings break some features
+    pub severity: AuditSeverity,
+    pub detail: String,
+    /// Command that resolves it, where one does
+    pub fix: Option<String>,
+}
+
+/// Every way `snapshot` differs from what the code expects
+///
+/// With `provider_dimension`, the dimension of the active embedding provider's vectors,
+/// it is also compared with `doc_embeddings.embedding`.
+pub fn schema_findings(
+    snapshot: &SchemaSnapshot,
+    provider_dimension: Option<usize>,
+) -> Vec<SchemaFinding> {
+    let mut findings = Vec::new();
+    let error = |check, detail: String, fix: String| SchemaFinding {
+        check,
+        severity: AuditSeverity::Error,
+        detail,
+        fix: Some(fix),
+    };
+    match &snapshot.pgvector_version {
+        None if snapshot.pgvector_available => findings.push(error(
+            "pgvector",
+            "pgvector extension is not enabled".to_string(),
+            "run psql \"$MCPDOCS_DATABASE_URL\" -c 'CREATE EXTENSION vector'".to_string(),
+        )),
+        None => findings.push(error(
+            "pgvector",
+            "pgvector is not installed on the PostgreSQL server".to_string(),
+            "install it (https://github.com/pgvector/pgvector#installation), then run psql \"$MCPDOCS_DATABASE_URL\" -c 'CREATE EXTENSION vector'".to_string(),
+        )),
+        Some(version) => {
+            if semver::Version::parse(version).is_ok_and(|v| v < MIN_PGVECTOR_FOR_HNSW) {
+                findings.push(SchemaFinding {
+                    check: "pgvector_hnsw",
+                    severity: AuditSeverity::Warning,
+                    detail: format!(
+                        "pgvector {version} predates HNSW indexes ({MIN_PGVECTOR_FOR_HNSW}), so creating per-dimension tables and crate partitions fails"
+                    ),
+                    fix: Some(
+                        "upgrade the pgvector package, then run psql \"$MCPDOCS_DATABASE_URL\" -c 'ALTER EXTENSION vector UPDATE'"
+                            .to_string(),
+                    ),
+                });
+            }
+        }
+    }
+
+    let has_table = |table: &str| snapshot.columns.keys().any(|(t, _)| t == table);
+    // Missing pieces grouped by the file that adds them, in the order files are applied
+    let mut missing: Vec<(&str, Vec<String>)> = Vec::new();
+    for &(table, column, expected_type, file) in REQUIRED_COLUMNS {
+        let item = if !has_table(table) {
+            format!("table {table}")
+        } else {
+            match snapshot
+                .columns
+                .get(&(table.to_string(), column.to_string()))
+            {
+                None => format!("column {table}.{column}"),
+                Some(actual) => match expected_type {
+                    Some(expected) if actual != expected => {
+                        format!("column {table}.{column} (is {actual}, expected {expected})")
+                    }
+                    _ => continue,
+                },
+            }
+        };
+        match missing.iter_mut().find(|(f, _)| *f == file) {
+            Some((_, items)) if items.contains(&item) => {}
+            Some((_, items)) => items.push(item),
+            None => missing.push((file, vec![item])),
+        }
+    }
+    for &(table, index, file) in REQUIRED_INDEXES {
+        // A missing table is reported above
+        if !has_table(table) || snapshot.indexes.contains(index) {
+            continue;
+        }
+        let item = format!("index {index}");
+        match missing.iter_mut().find(|(f, _)| *f == file) {
+            Some((_, items)) => items.push(item),
+            None => missing.push((file, vec![item])),
+        }
+    }
+    for (file, items) in missing {
+        findings.push(error(
+            "columns",
+            format!("missing or outdated {}", items.join(", ")),
+            format!("run psql \"$MCPDOCS_DATABASE_URL\" -f {file}"),
+        ));
+    }
+
+    if let Some(dimension) = snapshot.embedding_dimension {
+        if dimension != EMBEDDING_DIMENSION {
+            findings.push(error(
+                "embedding_dimension",
+                format!("doc_embeddings.embedding has {dimension} dimensions, expected {EMBEDDING_DIMENSION}"),
+                "run psql \"$MCPDOCS_DATABASE_URL\" -f sql/migrations/update_schema_3072.sql".to_string(),
+            ));
+        }
+    }
+
+    // A provider of the default dimension with another column is the error above
+    let side_table =
+        provider_dimension.and_then(|provider| Some((provider, dimension_table(provider)?)));
+    if let (Some((provider, table)), Some(column)) = (side_table, snapshot.embedding_dimension) {
+        if provider != column as usize {
+            let index = if provider > HNSW_MAX_DIMENSION {
+                ", searched without an HNSW index"
+            } else {
+                ""
+            };
+            findings.push(SchemaFinding {
+                check: "provider_dimension",
+                severity: AuditSeverity::Warning,
+                detail: format!(
+                    "the embedding provider returns {provider}-dimensional vectors but doc_embeddings.embedding is vector({column}); crates it populates are stored in {table}{index}"
+                ),
+                fix: None,
+            });
+        }
+    }
+    findings
+}
+
+/// zstd level used for stored content; favors speed since inserts happen in bulk
+const CONTENT_COMPRESSION_LEVEL: i32 = 3;
 
 #[derive(Clone)]
 pub struct Database {
     pool: PgPool,
+    /// Serves vector searches and stats; the primary pool unless `MCPDOCS_DATABASE_READ_URL` is set
+    read_pool: PgPool,
+    /// Store new content zstd-compressed (`MCPDOCS_COMPRESS_CONTENT=true`)
+    compress_content: bool,
+    /// Rows per transaction in [`Database::insert_embeddings_batch`] (`MCPDOCS_INSERT_CHUNK_SIZE`)
+    insert_chunk_size: usize,
+    /// Schema every table is read from and written to instead of the default one (see
+    /// [`Database::in_namespace`])
+    namespace: Option<String>,
+}
+
+/// Rows committed per transaction when inserting embeddings, unless configured
+pub const DEFAULT_INSERT_CHUNK_SIZE: usize = 2000;
+
+/// Content and compressed bytes to bind for one row: (content, content_compressed, content_size)
+fn encode_content(
+    content: &str,
+    compress: bool,
+) -> Result<(String, Option<Vec<u8>>, i32), ServerError> {
+    let size = content.len() as i32;
+    if !compress {
+        return Ok((content.to_string(), None, size));
+    }
+    let compressed = zstd::encode_all(content.as_bytes(), CONTENT_COMPRESSION_LEVEL)
+        .map_err(|e| ServerError::Internal(format!("Failed to compress content: {e}")))?;
+    Ok((String::new(), Some(compressed), size))
+}
+
+/// Reverse of [`encode_content`]; rows without compressed bytes are returned as-is
+fn decode_content(content: String, compressed: Option<Vec<u8>>) -> Result<String, ServerError> {
+    match compressed {
+        None => Ok(content),
+        Some(bytes) => {
+            let raw = zstd::decode_all(bytes.as_slice())
+                .map_err(|e| ServerError::Database(format!("Failed to decompress content: {e}")))?;
+            String::from_utf8(raw).map_err(|e| {
+                ServerError::Database(format!("Decompressed content is not UTF-8: {e}"))
+            })
+        }
+    }
+}
+
+async fn open_pool(database_url: &str, namespace: Option<&str>) -> Result<PgPool, sqlx::Error> {
+    let mut options: PgConnectOptions = database_url.parse()?;
+    if let Some(namespace) = namespace {
+        // pgvector's type and operators stay in public
+        options = options.options([("search_path", format!("{namespace},public"))]);
+    }
+    PgPoolOptions::new()
+        .max_connections(10) // Increased from 5
+        .idle_timeout(Duration::from_secs(300)) // Close idle after 5min
+        .max_lifetime(Duration::from_secs(1800)) // Refresh after 30min
+        .acquire_timeout(Duration::from_secs(30)) // Timeout waiting for connection
+        .connect_with(options)
+        .await
+}
+
+/// Default database when `MCPDOCS_DATABASE_URL` is unset
+fn database_url() -> String {
+    env::var("MCPDOCS_DATABASE_URL")
+        .unwrap_or_else(|_| "postgresql://jonathonfritz@localhost/rust_docs_vectors".to_string())
 }
 
 #[allow(dead_code)] // Some methods are only used by specific binaries
 impl Database {
     pub async fn new() -> Result<Self, ServerError> {
-        let database_url = env::var("MCPDOCS_DATABASE_URL").unwrap_or_else(|_| {
-            "postgresql://jonathonfritz@localhost/rust_docs_vectors".to_string()
-        });
+        let db = Self::connect(&database_url()).await?;
+        match env::var("MCPDOCS_DATABASE_READ_URL") {
+            Ok(read_url) if !read_url.trim().is_empty() => db.with_read_replica(&read_url).await,
+            _ => Ok(db),
+        }
+    }
+
+    /// [`Database::new`] with every table in the schema `namespace`, which holds a
+    /// profile's separate corpus (see `profiles`)
+    pub async fn in_namespace(namespace: &str) -> Result<Self, ServerError> {
+        let db = Self::connect_namespace(&database_url(), namespace).await?;
+        match env::var("MCPDOCS_DATABASE_READ_URL") {
+            Ok(read_url) if !read_url.trim().is_empty() => db.with_read_replica(&read_url).await,
+            _ => Ok(db),
+        }
+    }
+
+    /// Connect to a specific database instead of `MCPDOCS_DATABASE_URL`
+    ///
+    /// Only reads happen here, so a connection string for a read-only replica works.
+    pub async fn connect(database_url: &str) -> Result<Self, ServerError> {
+        Self::open(database_url, None).await
+    }
+
+    /// [`Database::connect`] with every table in the schema `namespace`
+    ///
+    /// The schema must exist and have every migration applied; tables missing from it
+    /// fail the schema check rather than falling back to the default schema's.
+    pub async fn connect_namespace(
+        database_url: &str,
+        namespace: &str,
+    ) -> Result<Self, ServerError> {
+        Self::open(database_url, Some(namespace)).await
+    }
 
-        let pool = PgPoolOptions::new()
-            .max_connections(10) // Increased from 5
-            .idle_timeout(Duration::from_secs(300)) // Close idle after 5min
-            .max_lifetime(Duration::from_secs(1800)) // Refresh after 30min
-            .acquire_timeout(Duration::from_secs(30)) // Timeout waiting for connection
-            .connect(&database_url)
+    async fn open(database_url: &str, namespace: Option<&str>) -> Result<Self, ServerError> {
+        let pool = open_pool(database_url, namespace)
             .await
             .map_err(|e| ServerError::Database(format!("Failed to connect to database: {e}")))?;
 
-        Ok(Self { pool })
+        if let Some(namespace) = namespace {
+            // A missing schema is skipped in search_path, which would silently fall back
+            // to the default schema's tables
+            let current: Option<String> = sqlx::query_scalar("SELECT current_schema()::text")
+                .fetch_one(&pool)
+                .await
+                .map_err(|e| ServerError::Database(format!("Failed to inspect schema: {e}")))?;
+            if current.as_deref() != Some(namespace) {
+                return Err(ServerError::Config(format!(
+                    "Namespace schema '{namespace}' does not exist: run psql \"$MCPDOCS_DATABASE_URL\" -c 'CREATE SCHEMA {namespace}', then apply the migrations with PGOPTIONS='-c search_path={namespace},public'"
+                )));
+            }
+        }
+
+        let compress_content = env::var("MCPDOCS_COMPRESS_CONTENT")
+            .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let insert_chunk_size = match env::var("MCPDOCS_INSERT_CHUNK_SIZE") {
+            Ok(value) if !value.trim().is_empty() => value
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| {
+                    ServerError::Config(format!(
+                        "MCPDOCS_INSERT_CHUNK_SIZE must be a positive integer, got '{value}'"
+                    ))
+                })?,
+            _ => DEFAULT_INSERT_CHUNK_SIZE,
+        };
+
+        let db = Self {
+            read_pool: pool.clone(),
+            pool,
+            compress_content,
+            insert_chunk_size,
+            namespace: namespace.map(str::to_string),
+        };
+        db.validate_schema().await?;
+        Ok(db)
+    }
+
+    /// The schema this handle reads and writes, if not the default one
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Check that the database answers, e.g. to probe for recovery after an outage
+    pub async fn ping(&self) -> Result<(), ServerError> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Database ping failed: {e}")))?;
+        Ok(())
+    }
+
+    /// Commit [`Database::insert_embeddings_batch`] rows in transactions of `rows` (at least 1)
+    pub fn with_insert_chunk_size(mut self, rows: usize) -> Self {
+        self.insert_chunk_size = rows.max(1);
+        self
+    }
+
+    /// Send vector searches and stats queries to a read replica, keeping writes and
+    /// crate configuration reads on the primary
+    pub async fn with_read_replica(mut self, read_url: &str) -> Result<Self, ServerError> {
+        self.read_pool = open_pool(read_url, self.namespace.as_deref())
+            .await
+            .map_err(|e| {
+                ServerError::Database(format!("Failed to connect to read replica: {e}"))
+            })?;
+        Ok(self)
+    }
+
+    /// Check pgvector and the tables/columns the code relies on, failing with one error
+    /// that lists everything missing and how to fix it
+    pub async fn validate_schema(&self) -> Result<(), ServerError> {
+        let snapshot = self.schema_snapshot().await?;
+        for finding in schema_findings(&snapshot, None) {
+            if finding.severity == AuditSeverity::Warning {
+                eprintln!(
+                    "⚠️  Database schema: {}{}",
+                    finding.detail,
+                    finding
+                        .fix
+                        .map(|fix| format!(" ({fix})"))
+                        .unwrap_or_default()
+                );
+            }
+        }
+        let problems = schema_problems(&snapshot);
+        if problems.is_empty() {
+            return Ok(());
+        }
+        // Binaries print errors with Debug formatting, so show the readable list here too
+        eprintln!("❌ Database schema is not ready:");
+        for problem in &problems {
+            eprintln!("  - {problem}");
+        }
+        eprintln!("See CLAUDE.md for the order to apply migrations in.");
+        Err(ServerError::Database(format!(
+            "Database schema is not ready: {}",
+            problems.join("; ")
+        )))
+    }
+
+    /// Every way the database differs from what the code expects, including how
+    /// `doc_embeddings.embedding` compares with `provider_dimension`
+    pub async fn schema_check(
+        &self,
+        provider_dimension: Option<usize>,
+    ) -> Result<Vec<SchemaFinding>, ServerError> {
+        Ok(schema_findings(
+            &self.schema_snapshot().await?,
+            provider_dimension,
+        ))
+    }
+
+    async fn schema_snapshot(&self) -> Result<SchemaSnapshot, ServerError> {
+        let map_err =
+            |e: sqlx::Error| ServerError::Database(format!("Failed to inspect schema: {e}"));
+
+        let pgvector_version: Option<String> =
+            sqlx::query_scalar("SELECT extversion FROM pg_extension WHERE extname = 'vector'")
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(map_err)?;
+        let pgvector_available: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM pg_available_extensions WHERE name = 'vector')",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_err)?;
+
+        let tables: Vec<&str> = REQUIRED_COLUMNS.iter().map(|(table, ..)| *table).collect();
+        let columns = sqlx::query(
+            r#"
+            SELECT table_name::text AS table_name, column_name::text AS column_name, udt_name::text AS udt_name
+            FROM information_schema.columns
+            WHERE table_schema = current_schema() AND table_name = ANY($1)
+            "#,
+        )
+        .bind(&tables)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_err)?
+        .into_iter()
+        .map(|row| {
+            (
+                (row.get("table_name"), row.get("column_name")),
+                row.get("udt_name"),
+            )
+        })
+        .collect();
+
+        let embedding_dimension = self.embedding_column_dimension().await?;
+
+        let names: Vec<&str> = REQUIRED_INDEXES
+            .iter()
+            .map(|(_, index, _)| *index)
+            .collect();
+        let indexes = sqlx::query_scalar(
+            "SELECT indexname::text FROM pg_indexes WHERE schemaname = current_schema() AND indexname = ANY($1)",
+        )
+        .bind(&names)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_err)?
+        .into_iter()
+        .collect();
+
+        Ok(SchemaSnapshot {
+            pgvector_version,
+            pgvector_available,
+            columns,
+            embedding_dimension,
+            indexes,
+        })
+    }
+
+    /// Declared dimension of `doc_embeddings.embedding`, if the table exists
+    pub async fn embedding_column_dimension(&self) -> Result<Option<i32>, ServerError> {
+        // pgvector stores the declared dimension as the column's type modifier
+        let dimension: Option<i32> = sqlx::query_scalar(
+            r#"
+            SELECT atttypmod FROM pg_attribute
+            WHERE attrelid = to_regclass('doc_embeddings') AND attname = 'embedding' AND NOT attisdropped
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to inspect schema: {e}")))?;
+        Ok(dimension.filter(|dimension| *dimension > 0))
+    }
+
+    /// Dimensions with their own vector table (see [`dimension_table`]), ascending
+    ///
+    /// Empty for single-dimension deployments, which only use `doc_embeddings.embedding`.
+    pub async fn embedding_dimensions(&self) -> Result<Vec<usize>, ServerError> {
+        let dimensions: Vec<i32> = sqlx::query_scalar(
+            r#"
+            SELECT substring(table_name from '^doc_embeddings_([0-9]+)$')::int AS dimension
+            FROM information_schema.tables
+            WHERE table_schema = current_schema() AND table_name ~ '^doc_embeddings_[0-9]+$'
+            ORDER BY dimension
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to list embedding tables: {e}")))?;
+        Ok(dimensions.into_iter().map(|d| d as usize).collect())
+    }
+
+    /// Create the vector table for `dimension` (and its HNSW index where pgvector
+    /// supports one) if it doesn't exist yet
+    pub async fn ensure_dimension_table(&self, dimension: usize) -> Result<(), ServerError> {
+        let Some(table) = dimension_table(dimension) else {
+            return Ok(());
+        };
+        let map_err = |e: sqlx::Error| {
+            ServerError::Database(format!("Failed to create embedding table {table}: {e}"))
+        };
+        // A partitioned doc_embeddings has no unique `id` to reference; its
+        // delete_dimension_vectors trigger does the cascading instead
+        let references = if self.is_partitioned().await? {
+            ""
+        } else {
+            " REFERENCES doc_embeddings(id) ON DELETE CASCADE"
+        };
+        let mut tx = self.pool.begin().await.map_err(map_err)?;
+        // Concurrent `CREATE TABLE IF NOT EXISTS` can still collide; serialize creators
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(&table)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?;
+        // dimension is an integer, so formatting it into the DDL is safe
+        sqlx::query(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {table} (
+                doc_id INTEGER PRIMARY KEY{references},
+                embedding vector({dimension}) NOT NULL
+            )
+            "#
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(map_err)?;
+        if dimension <= HNSW_MAX_DIMENSION {
+            sqlx::query(&format!(
+                "CREATE INDEX IF NOT EXISTS idx_{table}_hnsw ON {table} USING hnsw (embedding vector_cosine_ops)"
+            ))
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?;
+        }
+        tx.commit().await.map_err(map_err)
+    }
+
+    /// Dimension of the vectors stored for a crate: the first dimension table holding
+    /// any of its documents, else [`EMBEDDING_DIMENSION`]
+    pub async fn crate_embedding_dimension(&self, crate_name: &str) -> Result<usize, ServerError> {
+        for dimension in self.embedding_dimensions().await? {
+            let Some(table) = dimension_table(dimension) else {
+                continue;
+            };
+            let found: bool = sqlx::query_scalar(&format!(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM {table} v JOIN doc_embeddings de ON de.id = v.doc_id
+                    WHERE de.crate_name = $1
+                )
+                "#
+            ))
+            .bind(crate_name)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| {
+                ServerError::Database(format!("Failed to look up embedding dimension: {e}"))
+            })?;
+            if found {
+                return Ok(dimension);
+            }
+        }
+        Ok(EMBEDDING_DIMENSION as usize)
+    }
+
+    /// Whether `doc_embeddings` is list-partitioned by crate (see [`Database::partition_embeddings`])
+    pub async fn is_partitioned(&self) -> Result<bool, ServerError> {
+        sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM pg_partitioned_table WHERE partrelid = to_regclass('doc_embeddings'))",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to inspect partitioning: {e}")))
+    }
+
+    /// Give `crate_name` its own `doc_embeddings` partition if the table is partitioned and
+    /// the crate has none yet
+    ///
+    /// Returns whether the table is partitioned. Unpartitioned databases are left alone.
+    pub async fn ensure_crate_partition(&self, crate_name: &str) -> Result<bool, ServerError> {
+        if !self.is_partitioned().await? {
+            return Ok(false);
+        }
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to begin transaction: {e}")))?;
+        create_crate_partition(&mut tx, "doc_embeddings", crate_name).await?;
+        tx.commit()
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to commit transaction: {e}")))?;
+        Ok(true)
+    }
+
+    /// Convert `doc_embeddings` into a table list-partitioned by `crate_name`, copying each
+    /// crate into its partition `batch_rows` rows per statement while the old table keeps
+    /// serving reads and writes
+    ///
+    /// Only the final swap locks `doc_embeddings` against writes (reads continue): it
+    /// re-copies rows inserted, deleted or upserted since the copy started, moves triggers,
+    /// dependent views and the id sequence over, and keeps the old table as
+    /// [`UNPARTITIONED_TABLE`] to drop once the result checks out. Updates that don't move
+    /// `last_updated_at` (language tags, in-place recleans) made during the copy are lost.
+    /// An interrupted conversion starts over from scratch.
+    pub async fn partition_embeddings(
+        &self,
+        batch_rows: i64,
+    ) -> Result<PartitionReport, ServerError> {
+        let map_err = |e: sqlx::Error| {
+            ServerError::Database(format!("Failed to partition doc_embeddings: {e}"))
+        };
+        if self.is_partitioned().await? {
+            return Err(ServerError::Config(
+                "doc_embeddings is already partitioned".to_string(),
+            ));
+        }
+
+        // Transactions open when the copy starts stamp their rows with their own, earlier
+        // start time, so look back further than now() for rows to re-copy
+        let copy_started: chrono::DateTime<chrono::Utc> =
+            sqlx::query_scalar("SELECT now() - interval '1 hour'")
+                .fetch_one(&self.pool)
+                .await
+                .map_err(map_err)?;
+        for statement in [
+            format!("DROP TABLE IF EXISTS {PARTITIONED_TABLE} CASCADE"),
+            format!(
+                r#"
+                CREATE TABLE {PARTITIONED_TABLE} (
+                    LIKE doc_embeddings INCLUDING DEFAULTS,
+                    PRIMARY KEY (id, crate_name),
+                    UNIQUE (crate_name, crate_id, doc_path, generation),
+                    FOREIGN KEY (crate_id) REFERENCES crates(id) ON DELETE CASCADE
+                ) PARTITION BY LIST (crate_name)
+                "#
+            ),
+            format!("CREATE TABLE {DEFAULT_PARTITION} PARTITION OF {PARTITIONED_TABLE} DEFAULT"),
+        ] {
+            sqlx::query(&statement)
+                .execute(&self.pool)
+                .await
+                .map_err(map_err)?;
+        }
+
+        let crate_names: Vec<String> = sqlx::query_scalar(
+            "SELECT DISTINCT crate_name FROM doc_embeddings ORDER BY crate_name",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_err)?;
+        let copy_sql = format!(
+            r#"
+            WITH batch AS (
+                SELECT * FROM doc_embeddings WHERE crate_name = $1 AND id > $2 ORDER BY id LIMIT $3
+            ), copied AS (
+                INSERT INTO {PARTITIONED_TABLE} SELECT * FROM batch RETURNING id
+            )
+            SELECT MAX(id) AS last_id, COUNT(*) AS copied FROM copied
+            "#
+        );
+        let mut rows = 0;
+        for crate_name in &crate_names {
+            let mut tx = self.pool.begin().await.map_err(map_err)?;
+            create_crate_partition(&mut tx, PARTITIONED_TABLE, crate_name).await?;
+            tx.commit().await.map_err(map_err)?;
+
+            let mut last_id = 0;
+            loop {
+                let row = sqlx::query(&copy_sql)
+                    .bind(crate_name)
+                    .bind(last_id)
+                    .bind(batch_rows.max(1))
+                    .fetch_one(&self.pool)
+                    .await
+                    .map_err(map_err)?;
+                let copied: i64 = row.get("copied");
+                if copied == 0 {
+                    break;
+                }
+                last_id = row.get("last_id");
+                rows += copied as u64;
+            }
+            eprintln!("    📦 Copied {crate_name} into its partition ({rows} rows so far)");
+        }
+
+        // Indexes are built after the copy and before the swap, so neither waits on the other
+        let indexes = sqlx::query(
+            r#"
+            SELECT ic.relname::text AS name, pg_get_indexdef(i.indexrelid) AS definition
+            FROM pg_index i
+            JOIN pg_class ic ON ic.oid = i.indexrelid
+            WHERE i.indrelid = 'doc_embeddings'::regclass AND NOT i.indisunique
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_err)?;
+        let mut index_renames = Vec::new();
+        for index in indexes {
+            let name: String = index.get("name");
+            let definition: String = index.get("definition");
+            let copy = format!("{name}_new");
+            let Some(statement) = retarget_definition(&definition, &copy, PARTITIONED_TABLE) else {
+                eprintln!("⚠️  Not copying index {name}: {definition}");
+                continue;
+            };
+            match sqlx::query(&statement).execute(&self.pool).await {
+                Ok(_) => index_renames.push((name, copy)),
+                Err(e) => eprintln!("⚠️  Not copying index {name}: {e}"),
+            }
+        }
+
+        let mut tx = self.pool.begin().await.map_err(map_err)?;
+        sqlx::query("LOCK TABLE doc_embeddings IN EXCLUSIVE MODE")
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?;
+        // Drop copies of rows deleted, moved or re-upserted since, then copy what's missing
+        let removed = sqlx::query(&format!(
+            r#"
+            DELETE FROM {PARTITIONED_TABLE} n
+            WHERE NOT EXISTS (
+                SELECT 1 FROM doc_embeddings o
+                WHERE o.id = n.id AND o.crate_name = n.crate_name
+                    AND (o.last_updated_at IS NULL OR o.last_updated_at < $1)
+            )
+            "#
+        ))
+        .bind(copy_started)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_err)?
+        .rows_affected();
+        let caught_up = sqlx::query(&format!(
+            r#"
+            INSERT INTO {PARTITIONED_TABLE}
+            SELECT * FROM doc_embeddings o
+            WHERE NOT EXISTS (
+                SELECT 1 FROM {PARTITIONED_TABLE} n WHERE n.id = o.id AND n.crate_name = o.crate_name
+            )
+            "#
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(map_err)?
+        .rows_affected();
+        rows = rows - removed.min(rows) + caught_up;
+
+        // Triggers only now, so the copy kept stored tsvectors instead of recomputing them
+        let triggers = sqlx::query(
+            r#"
+            SELECT tgname::text AS name, pg_get_triggerdef(oid) AS definition
+            FROM pg_trigger
+            WHERE tgrelid = 'doc_embeddings'::regclass AND NOT tgisinternal
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(map_err)?;
+        for trigger in triggers {
+            let name: String = trigger.get("name");
+            let definition: String = trigger.get("definition");
+            let statement =
+                retarget_definition(&definition, &name, PARTITIONED_TABLE).ok_or_else(|| {
+                    ServerError::Database(format!("Cannot copy trigger {name}: {definition}"))
+                })?;
+            sqlx::query(&statement)
+                .execute(&mut *tx)
+                .await
+                .map_err(map_err)?;
+        }
+
+        // Views bind to the table itself, so capture their SQL before the rename and
+        // re-create them against the new table after it
+        let views: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT c.oid::regclass::text, pg_get_viewdef(c.oid)
+            FROM pg_depend d
+            JOIN pg_rewrite r ON r.oid = d.objid
+            JOIN pg_class c ON c.oid = r.ev_class
+            WHERE d.classid = 'pg_rewrite'::regclass
+                AND d.refobjid = 'doc_embeddings'::regclass
+                AND c.oid <> 'doc_embeddings'::regclass
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(map_err)?;
+        // Dimension tables can't reference a partitioned table's non-unique id
+        let foreign_keys: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT conrelid::regclass::text, conname::text
+            FROM pg_constraint
+            WHERE contype = 'f' AND confrelid = 'doc_embeddings'::regclass
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(map_err)?;
+        let sequence: Option<String> =
+            sqlx::query_scalar("SELECT pg_get_serial_sequence('doc_embeddings', 'id')")
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(map_err)?;
+
+        let mut statements: Vec<String> = foreign_keys
+            .iter()
+            .map(|(table, name)| format!("ALTER TABLE {table} DROP CONSTRAINT \"{name}\""))
+            .collect();
+        statements.push(format!(
+            "ALTER TABLE doc_embeddings RENAME TO {UNPARTITIONED_TABLE}"
+        ));
+        statements.push(format!(
+            "ALTER TABLE {PARTITIONED_TABLE} RENAME TO doc_embeddings"
+        ));
+        for (name, copy) in &index_renames {
+            statements.push(format!("ALTER INDEX {name} RENAME TO {name}_unpartitioned"));
+            statements.push(format!("ALTER INDEX {copy} RENAME TO {name}"));
+        }
+        // Otherwise dropping the old table would drop the sequence new ids come from
+        if let Some(sequence) = sequence {
+            statements.push(format!(
+                "ALTER SEQUENCE {sequence} OWNED BY doc_embeddings.id"
+            ));
+        }
+        for (name, definition) in &views {
+            statements.push(format!(
+                "CREATE OR REPLACE VIEW {name} AS {}",
+                definition.trim_end().trim_end_matches(';')
+            ));
+        }
+        statements.push(DELETE_DIMENSION_VECTORS_SQL.to_string());
+        statements.push(
+            "CREATE TRIGGER delete_dimension_vectors AFTER DELETE ON doc_embeddings \
+             FOR EACH ROW EXECUTE FUNCTION delete_dimension_vectors()"
+                .to_string(),
+        );
+        for statement in statements {
+            sqlx::query(&statement)
+                .execute(&mut *tx)
+                .await
+                .map_err(map_err)?;
+        }
+        tx.commit().await.map_err(map_err)?;
+
+        Ok(PartitionReport {
+            crates: crate_names.len(),
+            rows,
+            caught_up,
+        })
     }
 
     /// Insert or update a crate in the database
+    ///
+    /// `target` is the docs.rs build target just populated (`None` for the default target);
+    /// each target of a crate has its own row, documents and generations.
     pub async fn upsert_crate(
         &self,
         crate_name: &str,
         version: Option<&str>,
+        target: Option<&str>,
     ) -> Result<i32, ServerError> {
         let result = sqlx::query(
             r#"
-            INSERT INTO crates (name, version)
-            VALUES ($1, $2)
-            ON CONFLICT (name)
+            INSERT INTO crates (name, version, target)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (name, (COALESCE(target, '')))
             DO UPDATE SET
                 version = COALESCE($2, crates.version),
                 last_updated = CURRENT_TIMESTAMP
@@ -48,6 +1450,7 @@ impl Database {
         )
         .bind(crate_name)
         .bind(version)
+        .bind(target)
         .fetch_one(&self.pool)
         .await
         .map_err(|e| ServerError::Database(format!("Failed to upsert crate: {e}")))?;
@@ -56,395 +1459,2902 @@ impl Database {
         Ok(id)
     }
 
-    /// Check if embeddings exist for a crate
-    pub async fn has_embeddings(&self, crate_name: &str) -> Result<bool, ServerError> {
-        let result = sqlx::query(
-            r#"
-            SELECT EXISTS(
-                SELECT 1 FROM doc_embeddings WHERE crate_name = $1
-            ) as exists
+    /// The `crates` row of one target of `crate_name`, if there is one
+    pub async fn get_crate_record(
+        &self,
+        crate_name: &str,
+        target: Option<&str>,
+    ) -> Result<Option<CrateRecord>, ServerError> {
+        let row = sqlx::query(&format!(
+            "SELECT version, target, embedding_model FROM crates WHERE {}",
+            crate_target("$2")
+        ))
+        .bind(crate_name)
+        .bind(target)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get crate: {e}")))?;
+        Ok(row.map(|row| CrateRecord {
+            version: row.get("version"),
+            target: row.get("target"),
+            embedding_model: row.get("embedding_model"),
+        }))
+    }
+
+    /// The `crates` rows of every target of `crate_name`, docs.rs's default target first
+    pub async fn get_crate_records(
+        &self,
+        crate_name: &str,
+    ) -> Result<Vec<CrateRecord>, ServerError> {
+        let rows = sqlx::query(
+            "SELECT version, target, embedding_model FROM crates WHERE name = $1 ORDER BY target NULLS FIRST",
+        )
+        .bind(crate_name)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get crate: {e}")))?;
+        Ok(rows
+            .into_iter()
+            .map(|row| CrateRecord {
+                version: row.get("version"),
+                target: row.get("target"),
+                embedding_model: row.get("embedding_model"),
+            })
+            .collect())
+    }
+
+    /// Every crate name with a configuration or a `crates` row, sorted
+    pub async fn get_known_crate_names(&self) -> Result<Vec<String>, ServerError> {
+        sqlx::query_scalar(
+            "SELECT name FROM crate_configs UNION SELECT name FROM crates ORDER BY 1",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to list crates: {e}")))
+    }
+
+    /// Record which embedding model produced a crate's stored vectors
+    pub async fn set_crate_embedding_model(
+        &self,
+        crate_id: i32,
+        embedding_model: &str,
+    ) -> Result<(), ServerError> {
+        sqlx::query("UPDATE crates SET embedding_model = $2 WHERE id = $1")
+            .bind(crate_id)
+            .bind(embedding_model)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to record embedding model: {e}")))?;
+        Ok(())
+    }
+
+    /// Embedding model a crate was populated with, if one was recorded
+    pub async fn get_crate_embedding_model(
+        &self,
+        crate_name: &str,
+    ) -> Result<Option<String>, ServerError> {
+        let model: Option<Option<String>> = sqlx::query_scalar(
+            "SELECT embedding_model FROM crates WHERE name = $1 ORDER BY last_updated DESC LIMIT 1",
+        )
+        .bind(crate_name)
+        .fetch_optional(&self.read_pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get embedding model: {e}")))?;
+        Ok(model.flatten())
+    }
+
+    /// Check if embeddings exist for a crate
+    pub async fn has_embeddings(&self, crate_name: &str) -> Result<bool, ServerError> {
+        let result = sqlx::query(&format!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM doc_embeddings de WHERE de.crate_name = $1 AND {live}
+            ) as exists
+            "#,
+            live = LIVE_GENERATION,
+        ))
+        .bind(crate_name)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to check embeddings: {e}")))?;
+
+        let exists: bool = result.get("exists");
+        Ok(exists)
+    }
+
+    /// Stored document count for each of `crate_names` (0 when none), in one query
+    ///
+    /// Startup classifies every configured crate with this instead of one
+    /// [`has_embeddings`](Self::has_embeddings) round trip per crate.
+    pub async fn get_embeddings_presence(
+        &self,
+        crate_names: &[String],
+    ) -> Result<HashMap<String, i64>, ServerError> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT de.crate_name, COUNT(*) as count
+            FROM doc_embeddings de
+            WHERE de.crate_name = ANY($1) AND {live}
+            GROUP BY de.crate_name
+            "#,
+            live = LIVE_GENERATION,
+        ))
+        .bind(crate_names)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to check embeddings: {e}")))?;
+
+        let mut presence: HashMap<String, i64> =
+            crate_names.iter().map(|name| (name.clone(), 0)).collect();
+        for row in rows {
+            presence.insert(row.get("crate_name"), row.get("count"));
+        }
+        Ok(presence)
+    }
+
+    /// [`CrateStatus`] of each crate in `crate_names`, in one round trip
+    ///
+    /// Combines the crate's configuration and `crates` row (the most recently populated
+    /// ones when it has several targets), its latest population job and failed attempts since the last
+    /// success, and its stored document count. Docs older than `stale_after_days` (0 =
+    /// never) report `stale`.
+    pub async fn get_crate_statuses(
+        &self,
+        crate_names: &[String],
+        stale_after_days: u32,
+    ) -> Result<HashMap<String, CrateStatus>, ServerError> {
+        let rows = sqlx::query(&format!(
+            r#"
+            WITH names AS (
+                SELECT DISTINCT unnest($1::text[]) AS name
+            ), cfg AS (
+                SELECT DISTINCT ON (cc.name) cc.name, cc.id, cc.current_version, cc.last_populated,
+                       CASE WHEN NOT cc.auto_expected_docs OR cc.last_populated IS NOT NULL
+                            THEN cc.expected_docs END AS expected_docs
+                FROM crate_configs cc
+                WHERE cc.name = ANY($1)
+                ORDER BY cc.name, cc.last_populated DESC NULLS LAST, cc.id
+            ), c AS (
+                SELECT DISTINCT ON (c.name) c.name, c.version, c.last_updated
+                FROM crates c
+                WHERE c.name = ANY($1)
+                ORDER BY c.name, c.last_updated DESC NULLS LAST, c.id
+            ), job AS (
+                SELECT DISTINCT ON (cfg.name) cfg.name, j.id, j.status, j.error_message, j.docs_populated
+                FROM population_jobs j
+                JOIN cfg ON j.crate_config_id = cfg.id
+                ORDER BY cfg.name, j.created_at DESC, j.id DESC
+            ), failures AS (
+                SELECT cfg.name, COUNT(*) AS attempts
+                FROM population_jobs j
+                JOIN cfg ON j.crate_config_id = cfg.id
+                WHERE j.status = 'failed'
+                  AND j.id > COALESCE((
+                      SELECT MAX(done.id) FROM population_jobs done
+                      WHERE done.crate_config_id = cfg.id AND done.status IN ('completed', 'partial')
+                  ), 0)
+                GROUP BY cfg.name
+            ), docs AS (
+                SELECT de.crate_name AS name, COUNT(*) AS docs
+                FROM doc_embeddings de
+                WHERE de.crate_name = ANY($1) AND {live}
+                GROUP BY de.crate_name
+            )
+            SELECT
+                names.name,
+                cfg.id IS NOT NULL AS configured,
+                COALESCE(cfg.current_version, c.version)::text AS version,
+                COALESCE(cfg.last_populated, c.last_updated::timestamptz) AS populated_at,
+                cfg.expected_docs,
+                job.id AS job_id,
+                job.status AS job_status,
+                job.error_message,
+                job.docs_populated,
+                COALESCE(failures.attempts, 0) AS attempts,
+                COALESCE(docs.docs, 0) AS docs
+            FROM names
+            LEFT JOIN cfg ON cfg.name = names.name
+            LEFT JOIN c ON c.name = names.name
+            LEFT JOIN job ON job.name = names.name
+            LEFT JOIN failures ON failures.name = names.name
+            LEFT JOIN docs ON docs.name = names.name
+            "#,
+            live = LIVE_GENERATION,
+        ))
+        .bind(crate_names)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get crate status: {e}")))?;
+
+        let now = chrono::Utc::now();
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let job_id: Option<i32> = row.get("job_id");
+                let facts = CrateStatusFacts {
+                    configured: row.get("configured"),
+                    version: row.get("version"),
+                    populated_at: row.get("populated_at"),
+                    expected_docs: row
+                        .get::<Option<i32>, _>("expected_docs")
+                        .and_then(|docs| usize::try_from(docs).ok()),
+                    latest_job: job_id.map(|id| JobFacts {
+                        id,
+                        status: row.get("job_status"),
+                        error_message: row.get("error_message"),
+                        docs_populated: row.get("docs_populated"),
+                    }),
+                    consecutive_failures: row.get::<i64, _>("attempts") as u32,
+                    docs: row.get::<i64, _>("docs") as usize,
+                };
+                (
+                    row.get("name"),
+                    CrateStatus::derive(&facts, now, stale_after_days),
+                )
+            })
+            .collect())
+    }
+
+    /// [`Database::get_crate_statuses`] for one crate
+    pub async fn get_crate_status(
+        &self,
+        crate_name: &str,
+        stale_after_days: u32,
+    ) -> Result<CrateStatus, ServerError> {
+        Ok(self
+            .get_crate_statuses(&[crate_name.to_string()], stale_after_days)
+            .await?
+            .remove(crate_name)
+            .unwrap_or(CrateStatus::NotConfigured))
+    }
+
+    /// crates.io metadata cached for a crate by [`upsert_crate_metadata`](Self::upsert_crate_metadata)
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn get_crate_metadata(
+        &self,
+        crate_name: &str,
+    ) -> Result<Option<CrateMetadata>, ServerError> {
+        let row = sqlx::query(
+            r#"
+            SELECT crate_name, description, repository, homepage, license, latest_version,
+                   downloads, fetched_at
+            FROM crate_metadata
+            WHERE crate_name = $1
+            "#,
+        )
+        .bind(crate_name)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get crate metadata: {e}")))?;
+
+        Ok(row.map(|row| CrateMetadata {
+            crate_name: row.get("crate_name"),
+            description: row.get("description"),
+            repository: row.get("repository"),
+            homepage: row.get("homepage"),
+            license: row.get("license"),
+            latest_version: row.get("latest_version"),
+            downloads: row.get("downloads"),
+            fetched_at: row.get("fetched_at"),
+        }))
+    }
+
+    /// Store freshly fetched crates.io metadata, replacing the cached row
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn upsert_crate_metadata(&self, metadata: &CrateMetadata) -> Result<(), ServerError> {
+        sqlx::query(
+            r#"
+            INSERT INTO crate_metadata
+                (crate_name, description, repository, homepage, license, latest_version,
+                 downloads, fetched_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (crate_name) DO UPDATE SET
+                description = EXCLUDED.description,
+                repository = EXCLUDED.repository,
+                homepage = EXCLUDED.homepage,
+                license = EXCLUDED.license,
+                latest_version = EXCLUDED.latest_version,
+                downloads = EXCLUDED.downloads,
+                fetched_at = EXCLUDED.fetched_at
+            "#,
+        )
+        .bind(&metadata.crate_name)
+        .bind(&metadata.description)
+        .bind(&metadata.repository)
+        .bind(&metadata.homepage)
+        .bind(&metadata.license)
+        .bind(&metadata.latest_version)
+        .bind(metadata.downloads)
+        .bind(metadata.fetched_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to store crate metadata: {e}")))?;
+        Ok(())
+    }
+
+    /// Get all crates that have embeddings
+    pub async fn get_all_crates_with_embeddings(&self) -> Result<Vec<String>, ServerError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT crate_name FROM doc_embeddings
+            ORDER BY crate_name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get crates with embeddings: {e}")))?;
+
+        let crates: Vec<String> = rows.iter().map(|row| row.get("crate_name")).collect();
+        Ok(crates)
+    }
+
+    /// Insert a document embedding
+    pub async fn insert_embedding(
+        &self,
+        crate_id: i32,
+        crate_name: &str,
+        doc_path: &str,
+        content: &str,
+        embedding: &Array1<f32>,
+        token_count: i32,
+    ) -> Result<(), ServerError> {
+        let embedding_vec = Vector::from(embedding.to_vec());
+        let (stored_content, compressed, content_size) =
+            encode_content(content, self.compress_content)?;
+        self.ensure_crate_partition(crate_name).await?;
+
+        sqlx::query(INSERT_EMBEDDING_SQL)
+            .bind(crate_id)
+            .bind(crate_name)
+            .bind(doc_path)
+            .bind(stored_content)
+            .bind(embedding_vec)
+            .bind(token_count)
+            .bind(compressed)
+            .bind(content_size)
+            .bind(content)
+            .bind(None::<i32>)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to insert embedding: {e}")))?;
+
+        Ok(())
+    }
+
+    /// Batch insert multiple embeddings (more efficient)
+    ///
+    /// Rows are committed in transactions of `MCPDOCS_INSERT_CHUNK_SIZE` (default
+    /// [`DEFAULT_INSERT_CHUNK_SIZE`]). If a chunk fails, earlier chunks stay committed;
+    /// inserts are upserts, so retrying the whole batch is safe. Crate stats are
+    /// refreshed once, after the last chunk.
+    pub async fn insert_embeddings_batch(
+        &self,
+        crate_id: i32,
+        crate_name: &str,
+        embeddings: &[(String, String, Array1<f32>, i32)], // (path, content, embedding, token_count)
+    ) -> Result<(), ServerError> {
+        self.insert_generation_batch(crate_id, crate_name, None, embeddings)
+            .await
+    }
+
+    /// [`Database::insert_embeddings_batch`] into `generation` instead of the live one, for
+    /// a staged repopulation that queries don't see until it is promoted
+    pub async fn insert_staged_embeddings_batch(
+        &self,
+        crate_id: i32,
+        crate_name: &str,
+        generation: i32,
+        embeddings: &[(String, String, Array1<f32>, i32)],
+    ) -> Result<(), ServerError> {
+        self.insert_generation_batch(crate_id, crate_name, Some(generation), embeddings)
+            .await
+    }
+
+    async fn insert_generation_batch(
+        &self,
+        crate_id: i32,
+        crate_name: &str,
+        generation: Option<i32>,
+        embeddings: &[(String, String, Array1<f32>, i32)],
+    ) -> Result<(), ServerError> {
+        // One model per batch: the first vector decides which table the batch goes to
+        let dimension = embeddings
+            .first()
+            .map_or(EMBEDDING_DIMENSION as usize, |(_, _, embedding, _)| {
+                embedding.len()
+            });
+
+        // Second line of defense behind embed_chunk: never store vectors that match everything
+        let invalid: Vec<String> = embeddings
+            .iter()
+            .filter_map(|(doc_path, _, embedding, _)| {
+                let vector = embedding.as_slice().unwrap_or(&[]);
+                vector_problem(vector, Some(dimension))
+                    .map(|problem| format!("{doc_path} ({problem})"))
+            })
+            .collect();
+        if !invalid.is_empty() {
+            return Err(ServerError::Internal(format!(
+                "Refusing to store {} invalid embeddings for {crate_name}: {}",
+                invalid.len(),
+                invalid.join(", ")
+            )));
+        }
+
+        let table = dimension_table(dimension);
+        if table.is_some() {
+            self.ensure_dimension_table(dimension).await?;
+        }
+        self.ensure_crate_partition(crate_name).await?;
+
+        // Commit in chunks so a huge crate never becomes one long, lock-holding transaction
+        let chunk_size = self.insert_chunk_size.max(1);
+        let chunk_count = embeddings.len().div_ceil(chunk_size);
+        let mut inserted = 0;
+        for (chunk_index, chunk) in embeddings.chunks(chunk_size).enumerate() {
+            self.insert_embeddings_chunk(crate_id, crate_name, generation, table.as_deref(), chunk)
+                .await?;
+            inserted += chunk.len();
+            if chunk_count > 1 {
+                eprintln!(
+                    "    💾 Committed insert batch {}/{chunk_count} for {crate_name} ({inserted}/{} rows)",
+                    chunk_index + 1,
+                    embeddings.len()
+                );
+            }
+        }
+
+        // Update crate statistics
+        self.update_crate_stats(crate_id).await?;
+
+        Ok(())
+    }
+
+    /// Insert one transaction's worth of rows for [`Database::insert_embeddings_batch`]
+    async fn insert_embeddings_chunk(
+        &self,
+        crate_id: i32,
+        crate_name: &str,
+        generation: Option<i32>,
+        table: Option<&str>,
+        rows: &[(String, String, Array1<f32>, i32)],
+    ) -> Result<(), ServerError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to begin transaction: {e}")))?;
+
+        for (doc_path, content, embedding, token_count) in rows {
+            let embedding_vec = Vector::from(embedding.to_vec());
+            let (stored_content, compressed, content_size) =
+                encode_content(content, self.compress_content)?;
+
+            // Vectors of another dimension go to their own table; the row keeps NULL
+            let (row_embedding, table_embedding) = match table {
+                None => (Some(embedding_vec), None),
+                Some(_) => (None, Some(embedding_vec)),
+            };
+            let doc_id: i32 = sqlx::query_scalar(INSERT_EMBEDDING_SQL)
+                .bind(crate_id)
+                .bind(crate_name)
+                .bind(doc_path)
+                .bind(stored_content)
+                .bind(row_embedding)
+                .bind(*token_count)
+                .bind(compressed)
+                .bind(content_size)
+                .bind(content)
+                .bind(generation)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| ServerError::Database(format!("Failed to insert embedding: {e}")))?;
+
+            if let (Some(table), Some(embedding_vec)) = (table, table_embedding) {
+                upsert_table_embedding(&mut tx, table, doc_id, embedding_vec).await?;
+            }
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to commit transaction: {e}")))?;
+
+        Ok(())
+    }
+
+    /// Update crate statistics
+    async fn update_crate_stats(&self, crate_id: i32) -> Result<(), ServerError> {
+        sqlx::query(
+            r#"
+            UPDATE crates
+            SET total_docs = (
+                SELECT COUNT(*) FROM doc_embeddings
+                WHERE crate_id = $1 AND generation = crates.live_generation
+            ),
+            total_tokens = (
+                SELECT COALESCE(SUM(token_count), 0) FROM doc_embeddings
+                WHERE crate_id = $1 AND generation = crates.live_generation
+            )
+            WHERE id = $1
+            "#,
+        )
+        .bind(crate_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to update crate stats: {e}")))?;
+
+        Ok(())
+    }
+
+    /// Recompute `total_docs`/`total_tokens` for every target of a crate looked up by name
+    pub async fn refresh_crate_stats(&self, crate_name: &str) -> Result<(), ServerError> {
+        let crate_ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM crates WHERE name = $1")
+            .bind(crate_name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to look up crate: {e}")))?;
+
+        for id in crate_ids {
+            self.update_crate_stats(id).await?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite a stored document's content in place, optionally replacing its embedding
+    ///
+    /// Compression and the text-search vector follow the current settings, as on insert.
+    pub async fn update_document_content(
+        &self,
+        crate_name: &str,
+        doc_path: &str,
+        content: &str,
+        token_count: i32,
+        embedding: Option<&Array1<f32>>,
+    ) -> Result<(), ServerError> {
+        if let Some(problem) = embedding.and_then(|e| vector_problem(&e.to_vec(), Some(e.len()))) {
+            return Err(ServerError::Internal(format!(
+                "Refusing to store invalid embedding for {doc_path}: {problem}"
+            )));
+        }
+        let (stored_content, compressed, content_size) =
+            encode_content(content, self.compress_content)?;
+        let table = embedding.and_then(|e| dimension_table(e.len()));
+        if let Some(e) = embedding.filter(|_| table.is_some()) {
+            self.ensure_dimension_table(e.len()).await?;
+        }
+        let embedding_vec = embedding.map(|e| Vector::from(e.to_vec()));
+        let (row_embedding, table_embedding) = match &table {
+            None => (embedding_vec, None),
+            Some(_) => (None, embedding_vec),
+        };
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to begin transaction: {e}")))?;
+        let doc_id: Option<i32> = sqlx::query_scalar(
+            r#"
+            UPDATE doc_embeddings
+            SET content = $3,
+                content_compressed = $4,
+                content_size = $5,
+                content_tsv = to_tsvector('english', $6),
+                token_count = $7,
+                embedding = COALESCE($8, embedding),
+                last_updated_at = CURRENT_TIMESTAMP
+            WHERE crate_name = $1 AND doc_path = $2
+            RETURNING id
+            "#,
+        )
+        .bind(crate_name)
+        .bind(doc_path)
+        .bind(stored_content)
+        .bind(compressed)
+        .bind(content_size)
+        .bind(content)
+        .bind(token_count)
+        .bind(row_embedding)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to update document content: {e}")))?;
+
+        if let (Some(table), Some(embedding_vec), Some(doc_id)) = (&table, table_embedding, doc_id)
+        {
+            upsert_table_embedding(&mut tx, table, doc_id, embedding_vec).await?;
+        }
+        tx.commit()
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to commit transaction: {e}")))?;
+
+        Ok(())
+    }
+
+    /// Search for similar documents using vector similarity
+    pub async fn search_similar_docs(
+        &self,
+        crate_name: &str,
+        query_embedding: &Array1<f32>,
+        limit: i32,
+    ) -> Result<Vec<(String, String, f32)>, ServerError> {
+        let results = self
+            .search_similar_docs_scored(crate_name, query_embedding, limit)
+            .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|doc| (doc.doc_path, doc.content, doc.similarity))
+            .collect())
+    }
+
+    /// Open `connections` read connections and run one vector search per crate, so the
+    /// first real queries after a deploy don't pay for connection setup and cold caches
+    ///
+    /// A crate whose search fails is counted and skipped; only the connections are fatal.
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn prewarm(
+        &self,
+        crate_names: &[String],
+        connections: u32,
+    ) -> Result<PrewarmReport, ServerError> {
+        // More than the pool holds would wait out the acquire timeout
+        let connections = connections.min(self.read_pool.options().get_max_connections());
+        let start = std::time::Instant::now();
+        let acquired =
+            futures::future::try_join_all((0..connections).map(|_| self.read_pool.acquire()))
+                .await
+                .map_err(|e| {
+                    ServerError::Database(format!("Failed to open pool connections: {e}"))
+                })?;
+        // Released together so the pool keeps them idle instead of reusing one
+        drop(acquired);
+        let connect_ms = start.elapsed().as_millis() as u64;
+
+        // One constant query vector per dimension; its direction doesn't matter
+        let mut vectors: HashMap<usize, Array1<f32>> = HashMap::new();
+        let mut failed = Vec::new();
+        let search_start = std::time::Instant::now();
+        for crate_name in crate_names {
+            let warmed = async {
+                let dimension = self.crate_embedding_dimension(crate_name).await?;
+                let vector = vectors
+                    .entry(dimension)
+                    .or_insert_with(|| Array1::from_elem(dimension, 1.0));
+                self.search_similar_docs_scored(crate_name, vector, 1).await
+            }
+            .await;
+            if let Err(e) = warmed {
+                eprintln!("⚠️  Prewarm search for {crate_name} failed: {e}");
+                failed.push(crate_name.clone());
+            }
+        }
+
+        Ok(PrewarmReport {
+            connections,
+            crates: crate_names.len() - failed.len(),
+            failed,
+            connect_ms,
+            search_ms: search_start.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Search for similar documents, keeping the intermediate scores for diagnostics
+    pub async fn search_similar_docs_scored(
+        &self,
+        crate_name: &str,
+        query_embedding: &Array1<f32>,
+        limit: i32,
+    ) -> Result<Vec<ScoredDoc>, ServerError> {
+        self.search_similar_docs_tuned(crate_name, query_embedding, limit, None, None, None)
+            .await
+    }
+
+    /// [`Database::search_similar_docs_scored`] with an HNSW `ef_search` override, optionally
+    /// restricted to documents whose path starts with `path_prefix` and to chunks tagged with
+    /// the docblock `section` (case-insensitive, e.g. `panics`)
+    ///
+    /// The override is applied with `SET LOCAL` inside a transaction so it never leaks
+    /// to other queries sharing the pooled connection. It has no effect without an HNSW index.
+    /// Equal distances are ordered by `doc_path`, so ties come back in a stable order.
+    pub async fn search_similar_docs_tuned(
+        &self,
+        crate_name: &str,
+        query_embedding: &Array1<f32>,
+        limit: i32,
+        ef_search: Option<u32>,
+        path_prefix: Option<&str>,
+        section: Option<&str>,
+    ) -> Result<Vec<ScoredDoc>, ServerError> {
+        let embedding_vec = Vector::from(query_embedding.to_vec());
+
+        // The query vector's dimension picks the table, matching how the crate was stored
+        let (vectors, embedding_column) = match dimension_table(query_embedding.len()) {
+            None => (String::new(), "de.embedding"),
+            Some(table) => (format!("JOIN {table} v ON v.doc_id = de.id"), "v.embedding"),
+        };
+        let sql = format!(
+            r#"
+            SELECT
+                de.doc_path,
+                de.content,
+                de.content_compressed,
+                de.last_updated_at,
+                de.token_count,
+                c.version as crate_version,
+                {embedding_column} <=> $1 as distance
+            FROM doc_embeddings de
+            {vectors}
+            LEFT JOIN crates c ON c.id = de.crate_id
+            WHERE de.crate_name = $2
+              AND {live}
+              AND {embedding_column} IS NOT NULL
+              AND ($4::text IS NULL OR starts_with(de.doc_path, $4))
+              AND ($5::text IS NULL OR lower(de.section) = lower($5))
+            ORDER BY {embedding_column} <=> $1, de.doc_path ASC
+            LIMIT $3
+            "#,
+            live = LIVE_GENERATION,
+        );
+        let query = sqlx::query(&sql)
+            .bind(embedding_vec)
+            .bind(crate_name)
+            .bind(limit)
+            .bind(path_prefix)
+            .bind(section);
+
+        let results = match ef_search {
+            None => query.fetch_all(&self.read_pool).await,
+            Some(ef_search) => {
+                let mut tx = self.read_pool.begin().await.map_err(|e| {
+                    ServerError::Database(format!("Failed to begin transaction: {e}"))
+                })?;
+                // SET doesn't take bind parameters; ef_search is an integer so this is safe
+                sqlx::query(&format!("SET LOCAL hnsw.ef_search = {ef_search}"))
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| ServerError::Database(format!("Failed to set ef_search: {e}")))?;
+                let results = query.fetch_all(&mut *tx).await;
+                tx.commit().await.map_err(|e| {
+                    ServerError::Database(format!("Failed to commit transaction: {e}"))
+                })?;
+                results
+            }
+        }
+        .map_err(|e| match e.as_database_error().and_then(|e| e.code()) {
+            // undefined_table: nothing was ever stored with this dimension
+            Some(code) if code == "42P01" => ServerError::Config(format!(
+                "No embeddings with {} dimensions are stored; populate the crate with this model first",
+                query_embedding.len()
+            )),
+            _ => ServerError::Database(format!("Failed to search documents: {e}")),
+        })?;
+
+        results.into_iter().map(scored_doc).collect()
+    }
+
+    /// Stored `dimension`-sized vectors of a crate's `doc_paths`, for those that have one
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn get_doc_embeddings(
+        &self,
+        crate_name: &str,
+        dimension: usize,
+        doc_paths: &[String],
+    ) -> Result<HashMap<String, Vec<f32>>, ServerError> {
+        let (vectors, embedding_column) = match dimension_table(dimension) {
+            None => (String::new(), "de.embedding"),
+            Some(table) => (format!("JOIN {table} v ON v.doc_id = de.id"), "v.embedding"),
+        };
+        let sql = format!(
+            r#"
+            SELECT de.doc_path, {embedding_column} AS embedding
+            FROM doc_embeddings de
+            {vectors}
+            WHERE de.crate_name = $1 AND de.doc_path = ANY($2) AND {live}
+              AND {embedding_column} IS NOT NULL
+            "#,
+            live = LIVE_GENERATION,
+        );
+        let rows = sqlx::query(&sql)
+            .bind(crate_name)
+            .bind(doc_paths)
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to load embeddings: {e}")))?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let embedding: Vector = row.get("embedding");
+                (row.get("doc_path"), embedding.to_vec())
+            })
+            .collect())
+    }
+
+    /// One stored document of a crate target with its vector, or `None` if nothing is
+    /// stored for that target under exactly `crate_name`
+    pub async fn sample_crate_embedding(
+        &self,
+        crate_name: &str,
+        target: Option<&str>,
+    ) -> Result<Option<(String, Vec<f32>)>, ServerError> {
+        let dimension = self.crate_embedding_dimension(crate_name).await?;
+        let (vectors, embedding_column) = match dimension_table(dimension) {
+            None => (String::new(), "de.embedding"),
+            Some(table) => (format!("JOIN {table} v ON v.doc_id = de.id"), "v.embedding"),
+        };
+        let sql = format!(
+            r#"
+            SELECT de.doc_path, {embedding_column} AS embedding
+            FROM doc_embeddings de
+            {vectors}
+            WHERE de.crate_name = $1 AND {live} AND {embedding_column} IS NOT NULL
+              AND de.crate_id = (SELECT id FROM crates WHERE {target})
+            LIMIT 1
+            "#,
+            live = LIVE_GENERATION,
+            target = crate_target("$2"),
+        );
+        let row = sqlx::query(&sql)
+            .bind(crate_name)
+            .bind(target)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to sample an embedding: {e}")))?;
+        Ok(row.map(|row| {
+            let embedding: Vector = row.get("embedding");
+            (row.get("doc_path"), embedding.to_vec())
+        }))
+    }
+
+    /// Stored crate names that differ from `crate_name` only in case or `-` versus `_`
+    pub async fn crate_name_variants(&self, crate_name: &str) -> Result<Vec<String>, ServerError> {
+        sqlx::query_scalar(
+            r#"
+            SELECT name FROM crates
+            WHERE lower(replace(name, '-', '_')) = lower(replace($1, '-', '_')) AND name <> $1
+            ORDER BY name
+            "#,
+        )
+        .bind(crate_name)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to look up crate name variants: {e}")))
+    }
+
+    /// Mean of a crate's `dimension`-sized embeddings, or `None` if it has none
+    ///
+    /// Averages every stored vector of the crate, so callers should cache the result; it
+    /// only changes when the crate is repopulated.
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn get_crate_centroid(
+        &self,
+        crate_name: &str,
+        dimension: usize,
+    ) -> Result<Option<Vec<f32>>, ServerError> {
+        let (vectors, embedding_column) = match dimension_table(dimension) {
+            None => (String::new(), "de.embedding"),
+            Some(table) => (format!("JOIN {table} v ON v.doc_id = de.id"), "v.embedding"),
+        };
+        let sql = format!(
+            "SELECT AVG({embedding_column}) AS centroid FROM doc_embeddings de {vectors} WHERE de.crate_name = $1 AND {live}",
+            live = LIVE_GENERATION,
+        );
+        let centroid: Option<Vector> = sqlx::query_scalar(&sql)
+            .bind(crate_name)
+            .fetch_one(&self.read_pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to compute crate centroid: {e}")))?;
+        Ok(centroid.map(|v| v.to_vec()))
+    }
+
+    /// Centroids of several crates in one pass, as [`Self::get_crate_centroid`] computes
+    /// them; crates without `dimension`-sized embeddings are left out
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn get_crate_centroids(
+        &self,
+        crate_names: &[String],
+        dimension: usize,
+    ) -> Result<HashMap<String, Vec<f32>>, ServerError> {
+        let (vectors, embedding_column) = match dimension_table(dimension) {
+            None => (String::new(), "de.embedding"),
+            Some(table) => (format!("JOIN {table} v ON v.doc_id = de.id"), "v.embedding"),
+        };
+        let sql = format!(
+            r#"
+            SELECT de.crate_name, AVG({embedding_column}) AS centroid
+            FROM doc_embeddings de
+            {vectors}
+            WHERE de.crate_name = ANY($1) AND {live}
+            GROUP BY de.crate_name
+            "#,
+            live = LIVE_GENERATION,
+        );
+        let rows = sqlx::query(&sql)
+            .bind(crate_names)
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| {
+                ServerError::Database(format!("Failed to compute crate centroids: {e}"))
+            })?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let centroid: Option<Vector> = row.get("centroid");
+                Some((row.get("crate_name"), centroid?.to_vec()))
+            })
+            .collect())
+    }
+
+    /// Cosine similarity of each of `doc_paths` to `centroid`, for those that are stored
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn centroid_similarities(
+        &self,
+        crate_name: &str,
+        centroid: &[f32],
+        doc_paths: &[String],
+    ) -> Result<HashMap<String, f32>, ServerError> {
+        let (vectors, embedding_column) = match dimension_table(centroid.len()) {
+            None => (String::new(), "de.embedding"),
+            Some(table) => (format!("JOIN {table} v ON v.doc_id = de.id"), "v.embedding"),
+        };
+        let sql = format!(
+            r#"
+            SELECT de.doc_path, 1 - ({embedding_column} <=> $1) AS similarity
+            FROM doc_embeddings de
+            {vectors}
+            WHERE de.crate_name = $2 AND de.doc_path = ANY($3) AND {live}
+            "#,
+            live = LIVE_GENERATION,
+        );
+        let rows = sqlx::query(&sql)
+            .bind(Vector::from(centroid.to_vec()))
+            .bind(crate_name)
+            .bind(doc_paths)
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| {
+                ServerError::Database(format!("Failed to score documents against centroid: {e}"))
+            })?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let similarity: f64 = row.get("similarity");
+                (row.get("doc_path"), similarity as f32)
+            })
+            .collect())
+    }
+
+    /// Install pg_trgm and a trigram index on content and paths for [`Database::find_symbol`]
+    ///
+    /// Returns whether the index is in place. Databases without pg_trgm (or without the
+    /// rights to install it) return `false`, and `find_symbol` falls back to plain `ILIKE`.
+    pub async fn ensure_trigram_index(&self) -> bool {
+        let statements = [
+            "CREATE EXTENSION IF NOT EXISTS pg_trgm",
+            // CONCURRENTLY so a large table stays writable while the index builds
+            "CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_doc_embeddings_content_trgm ON doc_embeddings USING gin (content gin_trgm_ops)",
+            "CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_doc_embeddings_doc_path_trgm ON doc_embeddings USING gin (doc_path gin_trgm_ops)",
+        ];
+        for statement in statements {
+            if let Err(e) = sqlx::query(statement).execute(&self.pool).await {
+                eprintln!("⚠️  Trigram index unavailable, find_symbol will use ILIKE: {e}");
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Documents whose content or path contains `identifier`, best trigram matches first
+    ///
+    /// Matching is a case-insensitive substring match unless `case_sensitive`. Ranking uses
+    /// pg_trgm's `word_similarity` when the extension is installed, otherwise matches on
+    /// the path come first. Compressed rows can only match on their path, since their
+    /// content isn't stored as text.
+    pub async fn find_symbol(
+        &self,
+        crate_name: Option<&str>,
+        identifier: &str,
+        case_sensitive: bool,
+        limit: i64,
+    ) -> Result<Vec<SymbolMatch>, ServerError> {
+        // Both filter on ILIKE (which a trigram index accelerates); only the ranking differs
+        let live = LIVE_GENERATION;
+        let trigram_sql = format!(
+            r#"
+            SELECT de.crate_name, de.doc_path, de.content, de.content_compressed,
+                   GREATEST(word_similarity($1, de.content), word_similarity($1, de.doc_path))::real AS score
+            FROM doc_embeddings de
+            WHERE ($2::text IS NULL OR de.crate_name = $2) AND {live}
+              AND (de.content ILIKE $3 OR de.doc_path ILIKE $3)
+              AND (NOT $4 OR strpos(de.content, $1) > 0 OR strpos(de.doc_path, $1) > 0)
+            ORDER BY score DESC, de.doc_path
+            LIMIT $5
+            "#
+        );
+        let plain_sql = format!(
+            r#"
+            SELECT de.crate_name, de.doc_path, de.content, de.content_compressed, 0::real AS score
+            FROM doc_embeddings de
+            WHERE ($2::text IS NULL OR de.crate_name = $2) AND {live}
+              AND (de.content ILIKE $3 OR de.doc_path ILIKE $3)
+              AND (NOT $4 OR strpos(de.content, $1) > 0 OR strpos(de.doc_path, $1) > 0)
+            ORDER BY (de.doc_path ILIKE $3) DESC, length(de.content), de.doc_path
+            LIMIT $5
+            "#
+        );
+        let pattern = format!("%{}%", escape_like(identifier));
+        let run = |trigram: bool| {
+            sqlx::query(if trigram { &trigram_sql } else { &plain_sql })
+                .bind(identifier)
+                .bind(crate_name)
+                .bind(&pattern)
+                .bind(case_sensitive)
+                .bind(limit)
+                .fetch_all(&self.read_pool)
+        };
+
+        let rows = match run(true).await {
+            Ok(rows) => rows,
+            // undefined_function: pg_trgm isn't installed
+            Err(e) if e.as_database_error().and_then(|e| e.code()).as_deref() == Some("42883") => {
+                run(false).await.map_err(|e| {
+                    ServerError::Database(format!("Failed to search for symbol: {e}"))
+                })?
+            }
+            Err(e) => {
+                return Err(ServerError::Database(format!(
+                    "Failed to search for symbol: {e}"
+                )))
+            }
+        };
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(SymbolMatch {
+                    crate_name: row.get("crate_name"),
+                    doc_path: row.get("doc_path"),
+                    content: decode_content(row.get("content"), row.get("content_compressed"))?,
+                    score: row.get("score"),
+                })
+            })
+            .collect()
+    }
+
+    /// Doc paths in a crate starting with `prefix`, in path order (no content)
+    ///
+    /// Uses the `text_pattern_ops` index on (crate_name, doc_path), so it's cheap
+    /// enough to run alongside every query.
+    pub async fn get_doc_paths_with_prefix(
+        &self,
+        crate_name: &str,
+        prefix: &str,
+        limit: i64,
+    ) -> Result<Vec<String>, ServerError> {
+        let pattern = format!("{}%", escape_like(prefix));
+
+        sqlx::query_scalar(&format!(
+            r#"
+            SELECT de.doc_path
+            FROM doc_embeddings de
+            WHERE de.crate_name = $1 AND de.doc_path LIKE $2 AND {live}
+            ORDER BY de.doc_path
+            LIMIT $3
+            "#,
+            live = LIVE_GENERATION,
+        ))
+        .bind(crate_name)
+        .bind(pattern)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get related doc paths: {e}")))
+    }
+
+    /// Full content of one stored page, or `None` if the crate has no such path
+    ///
+    /// A path with a ` [chunk i/n]` suffix returns just that chunk. A base path returns
+    /// the whole page, reassembled from its chunks when it was split for embedding.
+    pub async fn get_document_by_path(
+        &self,
+        crate_name: &str,
+        doc_path: &str,
+    ) -> Result<Option<String>, ServerError> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT de.doc_path, de.content, de.content_compressed
+            FROM doc_embeddings de
+            WHERE de.crate_name = $1 AND {live}
+              AND (de.doc_path = $2 OR ($3 AND starts_with(de.doc_path, $2 || ' [chunk ')))
+            "#,
+            live = LIVE_GENERATION,
+        ))
+        .bind(crate_name)
+        .bind(doc_path)
+        .bind(search::chunk_position(doc_path).is_none())
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get document: {e}")))?;
+
+        if rows.is_empty() {
+            return Ok(None);
+        }
+        let chunks = rows
+            .into_iter()
+            .map(|row| {
+                let content = decode_content(row.get("content"), row.get("content_compressed"))?;
+                Ok((row.get("doc_path"), content))
+            })
+            .collect::<Result<Vec<(String, String)>, ServerError>>()?;
+        Ok(Some(search::reassemble_chunks(chunks)))
+    }
+
+    /// Get all documents for a crate (for loading into memory if needed)
+    ///
+    /// This materializes every row, which for large crates means gigabytes of
+    /// vectors. Prefer [`Database::get_crate_documents_stream`] when the rows can
+    /// be processed one at a time.
+    pub async fn get_crate_documents(
+        &self,
+        crate_name: &str,
+    ) -> Result<Vec<(String, String, Array1<f32>)>, ServerError> {
+        eprintln!("    🔍 Querying database for crate: {crate_name}");
+        let query_start = std::time::Instant::now();
+
+        let dimension = self.crate_embedding_dimension(crate_name).await?;
+        let documents: Vec<_> = self
+            .get_crate_documents_stream(crate_name, dimension)
+            .try_collect()
+            .await?;
+
+        let query_time = query_start.elapsed();
+        eprintln!(
+            "    📊 Found {} documents for {} in {:.3}s",
+            documents.len(),
+            crate_name,
+            query_time.as_secs_f64()
+        );
+
+        Ok(documents)
+    }
+
+    /// Stream all documents for a crate without loading them into memory at once
+    ///
+    /// Rows are fetched in pages of [`DOCUMENT_PAGE_SIZE`] ordered by path and target, so
+    /// memory use is bounded by what the consumer holds on to rather than by the crate size.
+    /// `dimension` says where the crate's vectors live ([`Database::crate_embedding_dimension`]).
+    pub fn get_crate_documents_stream<'a>(
+        &'a self,
+        crate_name: &'a str,
+        dimension: usize,
+    ) -> impl Stream<Item = Result<(String, String, Array1<f32>), ServerError>> + Send + 'a {
+        self.get_stored_documents_stream(crate_name, dimension)
+            .map_ok(|doc| (doc.doc_path, doc.content, Array1::from_vec(doc.embedding)))
+    }
+
+    /// [`Database::get_crate_documents_stream`] with every column a restore needs
+    pub fn get_stored_documents_stream<'a>(
+        &'a self,
+        crate_name: &'a str,
+        dimension: usize,
+    ) -> impl Stream<Item = Result<StoredDocument, ServerError>> + Send + 'a {
+        let (vectors, embedding_column) = match dimension_table(dimension) {
+            None => (String::new(), "de.embedding"),
+            Some(table) => (format!("JOIN {table} v ON v.doc_id = de.id"), "v.embedding"),
+        };
+        let sql = format!(
+            r#"
+            SELECT de.doc_path, de.content, de.content_compressed, de.token_count, de.language,
+                   de.section, c.target, {embedding_column} AS embedding
+            FROM doc_embeddings de
+            LEFT JOIN crates c ON c.id = de.crate_id
+            {vectors}
+            WHERE de.crate_name = $1 AND (de.doc_path, COALESCE(c.target, '')) > ($2, $3)
+              AND {live} AND {embedding_column} IS NOT NULL
+            ORDER BY de.doc_path, COALESCE(c.target, '')
+            LIMIT $4
+            "#,
+            live = LIVE_GENERATION,
+        );
+
+        // Keyset pagination: each page starts after the last (path, target) of the previous
+        // one, since targets of a crate can store the same path
+        futures::stream::try_unfold(Some((String::new(), String::new())), move |after| {
+            let sql = sql.clone();
+            async move {
+                let Some((after_path, after_target)) = after else {
+                    return Ok(None);
+                };
+                let rows = sqlx::query(&sql)
+                    .bind(crate_name)
+                    .bind(&after_path)
+                    .bind(&after_target)
+                    .bind(DOCUMENT_PAGE_SIZE)
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        ServerError::Database(format!("Failed to get crate documents: {e}"))
+                    })?;
+                let documents = rows
+                    .into_iter()
+                    .map(|row| {
+                        let embedding: Vector = row.get("embedding");
+                        Ok(StoredDocument {
+                            doc_path: row.get("doc_path"),
+                            content: decode_content(
+                                row.get("content"),
+                                row.get("content_compressed"),
+                            )?,
+                            token_count: row.get::<Option<i32>, _>("token_count").unwrap_or(0),
+                            language: row.get("language"),
+                            section: row.get("section"),
+                            target: row.get("target"),
+                            embedding: embedding.to_vec(),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, ServerError>>()?;
+                let next = (documents.len() as i64 == DOCUMENT_PAGE_SIZE)
+                    .then(|| {
+                        documents.last().map(|doc| {
+                            (doc.doc_path.clone(), doc.target.clone().unwrap_or_default())
+                        })
+                    })
+                    .flatten();
+                Ok::<_, ServerError>(Some((documents, next)))
+            }
+        })
+        .map_ok(|documents| futures::stream::iter(documents.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    /// Delete all embeddings for a crate
+    ///
+    /// A partitioned `doc_embeddings` drops the crate's partition instead of deleting rows,
+    /// so a huge crate leaves no dead tuples behind to slow down every other crate's scans.
+    pub async fn delete_crate_embeddings(&self, crate_name: &str) -> Result<(), ServerError> {
+        self.delete_precomputed_answers(crate_name).await?;
+        if self.is_partitioned().await? {
+            return self.drop_crate_partition(crate_name).await;
+        }
+        sqlx::query(
+            r#"
+            DELETE FROM doc_embeddings WHERE crate_name = $1
+            "#,
+        )
+        .bind(crate_name)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to delete embeddings: {e}")))?;
+
+        Ok(())
+    }
+
+    /// Delete one target's `crates` row and every generation of its documents, returning
+    /// how many documents were removed
+    ///
+    /// The crate's other targets keep theirs; [`Database::delete_crate_embeddings`]
+    /// removes them all.
+    pub async fn delete_crate_target(
+        &self,
+        crate_name: &str,
+        target: Option<&str>,
+    ) -> Result<u64, ServerError> {
+        let map_err =
+            |e: sqlx::Error| ServerError::Database(format!("Failed to delete crate target: {e}"));
+        self.delete_precomputed_answers(crate_name).await?;
+        let mut tx = self.pool.begin().await.map_err(map_err)?;
+        let documents = sqlx::query(&format!(
+            r#"
+            DELETE FROM doc_embeddings
+            WHERE crate_name = $1 AND crate_id = (SELECT id FROM crates WHERE {})
+            "#,
+            crate_target("$2")
+        ))
+        .bind(crate_name)
+        .bind(target)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_err)?
+        .rows_affected();
+        sqlx::query(&format!("DELETE FROM crates WHERE {}", crate_target("$2")))
+            .bind(crate_name)
+            .bind(target)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?;
+        tx.commit().await.map_err(map_err)?;
+        Ok(documents)
+    }
+
+    /// [`Database::delete_crate_embeddings`] for a partitioned `doc_embeddings`
+    async fn drop_crate_partition(&self, crate_name: &str) -> Result<(), ServerError> {
+        let map_err =
+            |e: sqlx::Error| ServerError::Database(format!("Failed to delete embeddings: {e}"));
+        let dimensions = self.embedding_dimensions().await?;
+        let mut tx = self.pool.begin().await.map_err(map_err)?;
+        // Dropping a partition skips the delete_dimension_vectors trigger
+        for table in dimensions.into_iter().filter_map(dimension_table) {
+            sqlx::query(&format!(
+                "DELETE FROM {table} v USING doc_embeddings de WHERE de.id = v.doc_id AND de.crate_name = $1"
+            ))
+            .bind(crate_name)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?;
+        }
+        sqlx::query(&format!(
+            "DROP TABLE IF EXISTS {}",
+            crate_partition_name(crate_name)
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(map_err)?;
+        // Rows written before the crate had a partition live in the default one
+        sqlx::query("DELETE FROM doc_embeddings WHERE crate_name = $1")
+            .bind(crate_name)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?;
+        tx.commit().await.map_err(map_err)
+    }
+
+    /// Delete specific documents of a crate, returning how many rows were removed
+    pub async fn delete_documents(
+        &self,
+        crate_name: &str,
+        doc_paths: &[String],
+    ) -> Result<u64, ServerError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM doc_embeddings WHERE crate_name = $1 AND doc_path = ANY($2)
+            "#,
+        )
+        .bind(crate_name)
+        .bind(doc_paths)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to delete documents: {e}")))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// The generation queries read for one target of `crate_name` and the version
+    /// recorded with it (generation 0 when the target has no `crates` row yet)
+    pub async fn get_live_generation(
+        &self,
+        crate_name: &str,
+        target: Option<&str>,
+    ) -> Result<LiveGeneration, ServerError> {
+        let row = sqlx::query(&format!(
+            "SELECT live_generation, version FROM crates WHERE {}",
+            crate_target("$2")
+        ))
+        .bind(crate_name)
+        .bind(target)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get live generation: {e}")))?;
+        Ok(
+            row.map_or_else(LiveGeneration::default, |row| LiveGeneration {
+                generation: row.get("live_generation"),
+                version: row.get("version"),
+            }),
+        )
+    }
+
+    /// Number of chunks stored in one generation of a crate target, live or staged
+    pub async fn count_generation_documents(
+        &self,
+        crate_name: &str,
+        target: Option<&str>,
+        generation: i32,
+    ) -> Result<usize, ServerError> {
+        let count: i64 = sqlx::query_scalar(&format!(
+            r#"
+            SELECT COUNT(*) FROM doc_embeddings
+            WHERE crate_name = $1 AND generation = $2
+              AND crate_id = (SELECT id FROM crates WHERE {})
+            "#,
+            crate_target("$3")
+        ))
+        .bind(crate_name)
+        .bind(generation)
+        .bind(target)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to count generation documents: {e}")))?;
+        Ok(count as usize)
+    }
+
+    /// Doc paths stored in one generation of a crate target, live or staged
+    pub async fn get_generation_doc_paths(
+        &self,
+        crate_name: &str,
+        target: Option<&str>,
+        generation: i32,
+    ) -> Result<Vec<String>, ServerError> {
+        sqlx::query_scalar(&format!(
+            r#"
+            SELECT doc_path FROM doc_embeddings
+            WHERE crate_name = $1 AND generation = $2
+              AND crate_id = (SELECT id FROM crates WHERE {})
+            "#,
+            crate_target("$3")
+        ))
+        .bind(crate_name)
+        .bind(generation)
+        .bind(target)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get generation doc paths: {e}")))
+    }
+
+    /// Delete one generation of a crate target, e.g. what a failed repopulation staged
+    pub async fn discard_generation(
+        &self,
+        crate_name: &str,
+        target: Option<&str>,
+        generation: i32,
+    ) -> Result<u64, ServerError> {
+        let result = sqlx::query(&format!(
+            r#"
+            DELETE FROM doc_embeddings
+            WHERE crate_name = $1 AND generation = $2
+              AND crate_id = (SELECT id FROM crates WHERE {})
+            "#,
+            crate_target("$3")
+        ))
+        .bind(crate_name)
+        .bind(generation)
+        .bind(target)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to discard generation: {e}")))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Make `generation` the one queries read for one target of `crate_name`, recording
+    /// `version` with it
+    ///
+    /// A single UPDATE of the target's row, so a query sees either every old document or
+    /// every new one. Returns the generation that was live before, to hand back to this
+    /// method if the swap has to be undone; the old rows stay until
+    /// [`Database::drop_stale_generations`].
+    pub async fn promote_generation(
+        &self,
+        crate_name: &str,
+        target: Option<&str>,
+        generation: i32,
+        version: Option<&str>,
+    ) -> Result<LiveGeneration, ServerError> {
+        let row = sqlx::query(&format!(
+            r#"
+            UPDATE crates c
+            SET live_generation = $2,
+                version = COALESCE($3, c.version),
+                last_updated = CURRENT_TIMESTAMP
+            FROM (SELECT id, live_generation, version FROM crates WHERE {} FOR UPDATE) old
+            WHERE c.id = old.id
+            RETURNING old.live_generation, old.version
+            "#,
+            crate_target("$4")
+        ))
+        .bind(crate_name)
+        .bind(generation)
+        .bind(version)
+        .bind(target)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to promote generation: {e}")))?
+        .ok_or_else(|| {
+            ServerError::Database(format!(
+                "Cannot promote a generation of unknown crate {crate_name}{}",
+                target.map(|t| format!(" ({t})")).unwrap_or_default()
+            ))
+        })?;
+        // Answers were searched in the generation that is no longer live
+        self.delete_precomputed_answers(crate_name).await?;
+        self.refresh_crate_stats(crate_name).await?;
+        Ok(LiveGeneration {
+            generation: row.get("live_generation"),
+            version: row.get("version"),
+        })
+    }
+
+    /// Delete every generation of one target of `crate_name` but the live one, returning
+    /// the rows removed
+    pub async fn drop_stale_generations(
+        &self,
+        crate_name: &str,
+        target: Option<&str>,
+    ) -> Result<u64, ServerError> {
+        let result = sqlx::query(&format!(
+            r#"
+            DELETE FROM doc_embeddings de
+            WHERE de.crate_name = $1 AND NOT {LIVE_GENERATION}
+              AND de.crate_id = (SELECT id FROM crates WHERE {})
+            "#,
+            crate_target("$2")
+        ))
+        .bind(crate_name)
+        .bind(target)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to drop old generations: {e}")))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Store the HTML of one crawled page, replacing an earlier copy
+    ///
+    /// Always zstd-compressed: HTML compresses far better than extracted text.
+    pub async fn upsert_raw_page(
+        &self,
+        crate_name: &str,
+        page_path: &str,
+        html: &str,
+    ) -> Result<(), ServerError> {
+        let compressed = zstd::encode_all(html.as_bytes(), CONTENT_COMPRESSION_LEVEL)
+            .map_err(|e| ServerError::Internal(format!("Failed to compress page: {e}")))?;
+        sqlx::query(
+            r#"
+            INSERT INTO doc_pages (crate_name, page_path, raw_html, html_size)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (crate_name, page_path) DO UPDATE SET
+                raw_html = EXCLUDED.raw_html,
+                html_size = EXCLUDED.html_size,
+                fetched_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(crate_name)
+        .bind(page_path)
+        .bind(compressed)
+        .bind(html.len() as i32)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to store page HTML: {e}")))?;
+        Ok(())
+    }
+
+    /// Record the detected language of stored documents, as `(doc_path, ISO 639-3 code)`
+    pub async fn set_document_languages(
+        &self,
+        crate_name: &str,
+        languages: &[(String, &str)],
+    ) -> Result<(), ServerError> {
+        let (paths, codes): (Vec<&str>, Vec<&str>) = languages
+            .iter()
+            .map(|(path, code)| (path.as_str(), *code))
+            .unzip();
+        sqlx::query(
+            r#"
+            UPDATE doc_embeddings de SET language = tagged.language
+            FROM UNNEST($2::text[], $3::text[]) AS tagged(doc_path, language)
+            WHERE de.crate_name = $1 AND de.doc_path = tagged.doc_path
+            "#,
+        )
+        .bind(crate_name)
+        .bind(&paths)
+        .bind(&codes)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to tag document languages: {e}")))?;
+        Ok(())
+    }
+
+    /// Tag stored chunks with the docblock section heading they come from
+    ///
+    /// Only rows of `generation` are tagged (`None` = the live one), so tagging a staged
+    /// repopulation leaves the chunks queries read alone.
+    pub async fn set_document_sections(
+        &self,
+        crate_name: &str,
+        generation: Option<i32>,
+        sections: &[(String, String)],
+    ) -> Result<(), ServerError> {
+        let (paths, headings): (Vec<&str>, Vec<&str>) = sections
+            .iter()
+            .map(|(path, heading)| (path.as_str(), heading.as_str()))
+            .unzip();
+        sqlx::query(
+            r#"
+            UPDATE doc_embeddings de SET section = tagged.section
+            FROM UNNEST($2::text[], $3::text[]) AS tagged(doc_path, section)
+            WHERE de.crate_name = $1 AND de.doc_path = tagged.doc_path
+              AND de.generation = COALESCE(
+                  $4, (SELECT live_generation FROM crates WHERE id = de.crate_id), 0)
+            "#,
+        )
+        .bind(crate_name)
+        .bind(&paths)
+        .bind(&headings)
+        .bind(generation)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to tag document sections: {e}")))?;
+        Ok(())
+    }
+
+    /// Detected languages of the given documents; untagged documents are left out
+    pub async fn get_document_languages(
+        &self,
+        crate_name: &str,
+        doc_paths: &[String],
+    ) -> Result<HashMap<String, String>, ServerError> {
+        let rows: Vec<(String, String)> = sqlx::query_as(&format!(
+            r#"
+            SELECT de.doc_path, de.language FROM doc_embeddings de
+            WHERE de.crate_name = $1 AND de.doc_path = ANY($2) AND de.language IS NOT NULL
+              AND {live}
+            "#,
+            live = LIVE_GENERATION,
+        ))
+        .bind(crate_name)
+        .bind(doc_paths)
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get document languages: {e}")))?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Record whether a document returned for `query_id` was useful, replacing an earlier
+    /// signal for the same query and document
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn record_retrieval_feedback(
+        &self,
+        query_id: &str,
+        crate_name: &str,
+        doc_path: &str,
+        useful: bool,
+    ) -> Result<(), ServerError> {
+        sqlx::query(
+            r#"
+            INSERT INTO retrieval_feedback (query_id, crate_name, doc_path, useful)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (query_id, crate_name, doc_path)
+            DO UPDATE SET useful = EXCLUDED.useful, created_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(query_id)
+        .bind(crate_name)
+        .bind(doc_path)
+        .bind(useful)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to record feedback: {e}")))?;
+        Ok(())
+    }
+
+    /// `(useful, not useful)` feedback counts of every document of a crate that has any
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn get_feedback_counts(
+        &self,
+        crate_name: &str,
+    ) -> Result<HashMap<String, (i64, i64)>, ServerError> {
+        let rows: Vec<(String, i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT doc_path,
+                   COUNT(*) FILTER (WHERE useful) AS useful,
+                   COUNT(*) FILTER (WHERE NOT useful) AS not_useful
+            FROM retrieval_feedback
+            WHERE crate_name = $1
+            GROUP BY doc_path
+            "#,
+        )
+        .bind(crate_name)
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get feedback: {e}")))?;
+        Ok(rows
+            .into_iter()
+            .map(|(doc_path, useful, not_useful)| (doc_path, (useful, not_useful)))
+            .collect())
+    }
+
+    /// Append one call to the query log; what it holds of the question is the caller's
+    /// policy decision (see [`crate::query_log::QueryLogSettings::store`])
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn record_query_log(&self, entry: &QueryLogEntry) -> Result<(), ServerError> {
+        sqlx::query(
+            r#"
+            INSERT INTO query_log
+                (query_id, crate_name, policy, question, question_hash, result_count,
+                 top_similarity, cached, latency_ms, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+        )
+        .bind(&entry.query_id)
+        .bind(&entry.crate_name)
+        .bind(&entry.policy)
+        .bind(&entry.question)
+        .bind(&entry.question_hash)
+        .bind(entry.result_count)
+        .bind(entry.top_similarity)
+        .bind(entry.cached)
+        .bind(entry.latency_ms)
+        .bind(entry.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to record query log entry: {e}")))?;
+        Ok(())
+    }
+
+    /// Delete query log rows created before `cutoff`, returning how many were deleted
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn delete_query_log_before(
+        &self,
+        cutoff: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, ServerError> {
+        let result = sqlx::query("DELETE FROM query_log WHERE created_at < $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to prune the query log: {e}")))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Query log rows of `crate_name`, oldest first
+    #[allow(dead_code)] // Used by tests
+    pub async fn get_query_log(&self, crate_name: &str) -> Result<Vec<QueryLogEntry>, ServerError> {
+        sqlx::query_as(
+            r#"
+            SELECT query_id, crate_name, policy, question, question_hash, result_count,
+                   top_similarity, cached, latency_ms, created_at
+            FROM query_log
+            WHERE crate_name = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(crate_name)
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to read the query log: {e}")))
+    }
+
+    /// `(rows, oldest created_at)` of the query log, for `get_server_stats`
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn query_log_summary(
+        &self,
+    ) -> Result<(i64, Option<chrono::DateTime<chrono::Utc>>), ServerError> {
+        sqlx::query_as("SELECT COUNT(*), MIN(created_at) FROM query_log")
+            .fetch_one(&self.read_pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to summarize the query log: {e}")))
+    }
+
+    /// Replace the precomputed orientation answers of `crate_name` with `answers`
+    pub async fn replace_precomputed_answers(
+        &self,
+        crate_name: &str,
+        answers: &[PrecomputedAnswer],
+    ) -> Result<(), ServerError> {
+        let map_err = |e: sqlx::Error| {
+            ServerError::Database(format!("Failed to store precomputed answers: {e}"))
+        };
+        let mut tx = self.pool.begin().await.map_err(map_err)?;
+        sqlx::query("DELETE FROM precomputed_answers WHERE crate_name = $1")
+            .bind(crate_name)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?;
+        for answer in answers {
+            sqlx::query(
+                r#"
+                INSERT INTO precomputed_answers
+                    (crate_name, question_key, question, model, question_embedding, results)
+                VALUES ($1, $2, $3, $4, $5, $6::jsonb)
+                "#,
+            )
+            .bind(crate_name)
+            .bind(&answer.question_key)
+            .bind(&answer.question)
+            .bind(&answer.model)
+            .bind(Vector::from(answer.embedding.clone()))
+            .bind(serde_json::to_string(&answer.results)?)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?;
+        }
+        tx.commit().await.map_err(map_err)
+    }
+
+    /// Forget the precomputed answers of `crate_name`, returning how many there were
+    pub async fn delete_precomputed_answers(&self, crate_name: &str) -> Result<u64, ServerError> {
+        let result = sqlx::query("DELETE FROM precomputed_answers WHERE crate_name = $1")
+            .bind(crate_name)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                ServerError::Database(format!("Failed to delete precomputed answers: {e}"))
+            })?;
+        Ok(result.rows_affected())
+    }
+
+    /// Precomputed answers of `crate_name` whose questions `model` embedded
+    pub async fn get_precomputed_answers(
+        &self,
+        crate_name: &str,
+        model: &str,
+    ) -> Result<Vec<PrecomputedAnswer>, ServerError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT question_key, question, model, question_embedding, results::text AS results
+            FROM precomputed_answers
+            WHERE crate_name = $1 AND model = $2
+            ORDER BY question_key
+            "#,
+        )
+        .bind(crate_name)
+        .bind(model)
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get precomputed answers: {e}")))?;
+        rows.into_iter()
+            .map(|row| {
+                let embedding: Vector = row.get("question_embedding");
+                let results: String = row.get("results");
+                Ok(PrecomputedAnswer {
+                    question_key: row.get("question_key"),
+                    question: row.get("question"),
+                    model: row.get("model"),
+                    embedding: embedding.to_vec(),
+                    results: serde_json::from_str(&results)?,
+                })
+            })
+            .collect()
+    }
+
+    /// The live chunks behind `answer`, ranked and scored as its search found them
+    ///
+    /// Chunks that are no longer live are left out.
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn get_precomputed_results(
+        &self,
+        crate_name: &str,
+        answer: &PrecomputedAnswer,
+    ) -> Result<Vec<ScoredDoc>, ServerError> {
+        let (doc_paths, distances): (Vec<&str>, Vec<f64>) = answer
+            .results
+            .iter()
+            .map(|(doc_path, distance)| (doc_path.as_str(), *distance))
+            .unzip();
+        let sql = format!(
+            r#"
+            SELECT
+                de.doc_path,
+                de.content,
+                de.content_compressed,
+                de.last_updated_at,
+                de.token_count,
+                c.version as crate_version,
+                r.distance
+            FROM unnest($2::text[], $3::float8[]) WITH ORDINALITY AS r(doc_path, distance, rank)
+            JOIN doc_embeddings de ON de.crate_name = $1 AND de.doc_path = r.doc_path
+            LEFT JOIN crates c ON c.id = de.crate_id
+            WHERE {live}
+            ORDER BY r.rank
+            "#,
+            live = LIVE_GENERATION,
+        );
+        sqlx::query(&sql)
+            .bind(crate_name)
+            .bind(doc_paths)
+            .bind(distances)
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to get precomputed results: {e}")))?
+            .into_iter()
+            .map(scored_doc)
+            .collect()
+    }
+
+    /// Stream the stored HTML of a crate's pages as `(page_path, html)`, ordered by path
+    ///
+    /// Pages are fetched [`RAW_PAGE_BATCH_SIZE`] at a time, since each holds a whole page.
+    pub fn get_raw_pages_stream<'a>(
+        &'a self,
+        crate_name: &'a str,
+    ) -> impl Stream<Item = Result<(String, String), ServerError>> + Send + 'a {
+        futures::stream::try_unfold(Some(String::new()), move |after| async move {
+            let Some(after) = after else {
+                return Ok(None);
+            };
+            let rows = sqlx::query(
+                r#"
+                SELECT page_path, raw_html FROM doc_pages
+                WHERE crate_name = $1 AND page_path > $2
+                ORDER BY page_path
+                LIMIT $3
+                "#,
+            )
+            .bind(crate_name)
+            .bind(&after)
+            .bind(RAW_PAGE_BATCH_SIZE)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to get stored pages: {e}")))?;
+            let pages = rows
+                .into_iter()
+                .map(|row| {
+                    let html = decode_content(String::new(), Some(row.get("raw_html")))?;
+                    Ok((row.get("page_path"), html))
+                })
+                .collect::<Result<Vec<(String, String)>, ServerError>>()?;
+            let next = (pages.len() as i64 == RAW_PAGE_BATCH_SIZE)
+                .then(|| pages.last().map(|(page_path, _)| page_path.clone()))
+                .flatten();
+            Ok::<_, ServerError>(Some((pages, next)))
+        })
+        .map_ok(|pages| futures::stream::iter(pages.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    /// Number of pages with stored HTML for a crate
+    pub async fn count_raw_pages(&self, crate_name: &str) -> Result<i64, ServerError> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM doc_pages WHERE crate_name = $1")
+            .bind(crate_name)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to count stored pages: {e}")))
+    }
+
+    /// Delete the stored HTML of a crate's pages, returning how many were removed
+    pub async fn delete_raw_pages(&self, crate_name: &str) -> Result<u64, ServerError> {
+        let result = sqlx::query("DELETE FROM doc_pages WHERE crate_name = $1")
+            .bind(crate_name)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to delete stored pages: {e}")))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Get crate statistics
+    pub async fn get_crate_stats(&self) -> Result<Vec<CrateStats>, ServerError> {
+        let results = sqlx::query(
+            r#"
+            SELECT
+                c.name,
+                c.version,
+                c.target,
+                c.last_updated,
+                c.total_docs,
+                c.total_tokens,
+                docs.oldest_doc,
+                docs.newest_doc
+            FROM crates c
+            LEFT JOIN (
+                SELECT crate_id, MIN(last_updated_at) as oldest_doc, MAX(last_updated_at) as newest_doc
+                FROM doc_embeddings
+                GROUP BY crate_id
+            ) docs ON docs.crate_id = c.id
+            ORDER BY c.name
+            "#,
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get crate stats: {e}")))?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| {
+                let name: String = row.get("name");
+                let version: Option<String> = row.get("version");
+                let target: Option<String> = row.get("target");
+                let last_updated: chrono::NaiveDateTime = row.get("last_updated");
+                let total_docs: Option<i32> = row.get("total_docs");
+                let total_tokens: Option<i32> = row.get("total_tokens");
+
+                CrateStats {
+                    name,
+                    version,
+                    target,
+                    last_updated,
+                    total_docs: total_docs.unwrap_or(0),
+                    total_tokens: total_tokens.unwrap_or(0),
+                    oldest_doc: row.get("oldest_doc"),
+                    newest_doc: row.get("newest_doc"),
+                }
+            })
+            .collect())
+    }
+
+    /// Count the live documents of one target of a crate
+    pub async fn count_target_documents(
+        &self,
+        crate_name: &str,
+        target: Option<&str>,
+    ) -> Result<usize, ServerError> {
+        let count: i64 = sqlx::query_scalar(&format!(
+            r#"
+            SELECT COUNT(*)
+            FROM doc_embeddings de
+            WHERE de.crate_name = $1 AND {LIVE_GENERATION}
+              AND de.crate_id = (SELECT id FROM crates WHERE {})
+            "#,
+            crate_target("$2")
+        ))
+        .bind(crate_name)
+        .bind(target)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to count crate documents: {e}")))?;
+        Ok(count as usize)
+    }
+
+    /// Count documents for a specific crate
+    pub async fn count_crate_documents(&self, crate_name: &str) -> Result<usize, ServerError> {
+        let result = sqlx::query(&format!(
+            r#"
+            SELECT COUNT(*) as count
+            FROM doc_embeddings de
+            WHERE de.crate_name = $1 AND {live}
+            "#,
+            live = LIVE_GENERATION,
+        ))
+        .bind(crate_name)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to count crate documents: {e}")))?;
+
+        let count: i64 = result.get("count");
+        Ok(count as usize)
+    }
+
+    // ===== Index Health Methods =====
+
+    /// Report text and vector index coverage, optionally for a single crate
+    pub async fn get_index_health(
+        &self,
+        crate_name: Option<&str>,
+    ) -> Result<IndexHealth, ServerError> {
+        let has_tsv_column: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM information_schema.columns
+                WHERE table_name = 'doc_embeddings' AND column_name = 'content_tsv'
+            )
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to check content_tsv column: {e}")))?;
+
+        // Without the column every row counts as unindexed
+        let tsv_count = if has_tsv_column {
+            "COUNT(content_tsv)"
+        } else {
+            "0::bigint"
+        };
+        let crate_rows = sqlx::query(&format!(
+            r#"
+            SELECT crate_name, COUNT(*) as total_docs, {tsv_count} as docs_with_tsv
+            FROM doc_embeddings
+            WHERE $1::text IS NULL OR crate_name = $1
+            GROUP BY crate_name
+            ORDER BY crate_name
+            "#
+        ))
+        .bind(crate_name)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get text index coverage: {e}")))?;
+
+        let crates = crate_rows
+            .into_iter()
+            .map(|row| {
+                let total_docs: i64 = row.get("total_docs");
+                let docs_with_tsv: i64 = row.get("docs_with_tsv");
+                CrateIndexHealth {
+                    crate_name: row.get("crate_name"),
+                    total_docs,
+                    docs_with_tsv,
+                }
+            })
+            .collect();
+
+        let index_rows = sqlx::query(
+            r#"
+            SELECT ic.relname as index_name, am.amname as method,
+                   i.indisvalid as is_valid, pg_get_indexdef(i.indexrelid) as definition
+            FROM pg_index i
+            JOIN pg_class ic ON ic.oid = i.indexrelid
+            JOIN pg_class tc ON tc.oid = i.indrelid
+            JOIN pg_am am ON am.oid = ic.relam
+            WHERE tc.relname = 'doc_embeddings'
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to list indexes: {e}")))?;
+
+        let mut text_index = None;
+        let mut vector_index = None;
+        for row in index_rows {
+            let method: String = row.get("method");
+            let definition: String = row.get("definition");
+            let status = IndexStatus {
+                name: row.get("index_name"),
+                method: method.clone(),
+                valid: row.get("is_valid"),
+            };
+            if method == "gin" && definition.contains("content_tsv") {
+                text_index = Some(status);
+            } else if method == "hnsw" || method == "ivfflat" {
+                vector_index = Some(status);
+            }
+        }
+
+        Ok(IndexHealth {
+            has_tsv_column,
+            text_index,
+            vector_index,
+            crates,
+        })
+    }
+
+    /// Backfill missing tsvectors, optionally for a single crate
+    ///
+    /// Returns the number of rows updated.
+    pub async fn reindex_text(&self, crate_name: Option<&str>) -> Result<u64, ServerError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE doc_embeddings
+            SET content_tsv = to_tsvector('english', content)
+            WHERE content_tsv IS NULL
+            AND content_compressed IS NULL
+            AND ($1::text IS NULL OR crate_name = $1)
+            "#,
+        )
+        .bind(crate_name)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to backfill text index: {e}")))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Stored vs. uncompressed content size per crate, plus the table's total on-disk size
+    pub async fn get_storage_report(&self) -> Result<StorageReport, ServerError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                crate_name,
+                COUNT(*) as total_docs,
+                COUNT(content_compressed) as compressed_docs,
+                SUM(octet_length(content) + COALESCE(octet_length(content_compressed), 0))::bigint as stored_bytes,
+                SUM(COALESCE(content_size, octet_length(content)))::bigint as uncompressed_bytes
+            FROM doc_embeddings
+            GROUP BY crate_name
+            ORDER BY crate_name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get storage report: {e}")))?;
+
+        let crates = rows
+            .into_iter()
+            .map(|row| CrateStorage {
+                crate_name: row.get("crate_name"),
+                total_docs: row.get("total_docs"),
+                compressed_docs: row.get("compressed_docs"),
+                stored_bytes: row.get("stored_bytes"),
+                uncompressed_bytes: row.get("uncompressed_bytes"),
+            })
+            .collect();
+
+        // Covers every partition when the table is partitioned (the parent itself is empty)
+        let table_bytes: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(pg_total_relation_size(relid)), 0)::bigint FROM pg_partition_tree('doc_embeddings')",
+        )
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| ServerError::Database(format!("Failed to get table size: {e}")))?;
+
+        Ok(StorageReport {
+            compression_enabled: self.compress_content,
+            table_bytes,
+            crates,
+        })
+    }
+
+    // ===== Crate Configuration Methods =====
+
+    /// Get all crate configurations
+    pub async fn get_crate_configs(
+        &self,
+        enabled_only: bool,
+    ) -> Result<Vec<CrateConfig>, ServerError> {
+        let query = if enabled_only {
+            "SELECT * FROM crate_configs WHERE enabled = true ORDER BY name, version_spec, target NULLS FIRST"
+        } else {
+            "SELECT * FROM crate_configs ORDER BY name, version_spec, target NULLS FIRST"
+        };
+
+        let configs = sqlx::query_as::<_, CrateConfig>(query)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to get crate configs: {e}")))?;
+
+        Ok(configs)
+    }
+
+    /// Enabled configurations still waiting for docs: never populated, stopped at their
+    /// time limit, or their crate has no stored embeddings (e.g. imported by
+    /// `migrate_config` and never crawled)
+    pub async fn get_unpopulated_crate_configs(&self) -> Result<Vec<CrateConfig>, ServerError> {
+        sqlx::query_as::<_, CrateConfig>(
+            r#"
+            SELECT * FROM crate_configs c
+            WHERE c.enabled = true
+              AND (c.last_populated IS NULL
+                   OR c.partial_at IS NOT NULL
+                   OR NOT EXISTS (SELECT 1 FROM doc_embeddings e WHERE e.crate_name = c.name))
+            ORDER BY c.name, c.version_spec, c.target NULLS FIRST
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get unpopulated crate configs: {e}")))
+    }
+
+    /// Enable or disable every configuration of a crate, returning how many changed
+    ///
+    /// Only the flag moves: embeddings, `last_populated` and the rest of the config stay.
+    pub async fn set_crate_enabled(&self, name: &str, enabled: bool) -> Result<u64, ServerError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE crate_configs
+            SET enabled = $2, updated_at = CURRENT_TIMESTAMP
+            WHERE name = $1 AND enabled <> $2
+            "#,
+        )
+        .bind(name)
+        .bind(enabled)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to update crate config: {e}")))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Rename a crate across `doc_embeddings`, `crates` and `crate_configs` in one transaction
+    ///
+    /// Embeddings are kept, so nothing is re-crawled or re-embedded. Stored `doc_path`s still
+    /// name the URLs the pages were fetched from. Fails with [`ServerError::Config`] when
+    /// `old_name` doesn't exist or `new_name` is already taken.
+    pub async fn rename_crate(
+        &self,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<RenameReport, ServerError> {
+        let map_err =
+            |e: sqlx::Error| ServerError::Database(format!("Failed to rename crate: {e}"));
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to begin transaction: {e}")))?;
+
+        let exists = |name: &str| {
+            sqlx::query_scalar::<_, bool>(
+                r#"
+                SELECT EXISTS (SELECT 1 FROM crates WHERE name = $1)
+                    OR EXISTS (SELECT 1 FROM crate_configs WHERE name = $1)
+                    OR EXISTS (SELECT 1 FROM doc_embeddings WHERE crate_name = $1)
+                "#,
+            )
+            .bind(name.to_string())
+        };
+        if !exists(old_name)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(map_err)?
+        {
+            return Err(ServerError::Config(format!(
+                "Crate '{old_name}' does not exist"
+            )));
+        }
+        if exists(new_name)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(map_err)?
+        {
+            return Err(ServerError::Config(format!(
+                "A crate named '{new_name}' already exists"
+            )));
+        }
+
+        // Rows move between partitions on update; the new name's must exist first
+        let partitioned = self.is_partitioned().await?;
+        if partitioned {
+            create_crate_partition(&mut tx, "doc_embeddings", new_name).await?;
+        }
+        let embeddings =
+            sqlx::query("UPDATE doc_embeddings SET crate_name = $2 WHERE crate_name = $1")
+                .bind(old_name)
+                .bind(new_name)
+                .execute(&mut *tx)
+                .await
+                .map_err(map_err)?
+                .rows_affected();
+        if partitioned {
+            sqlx::query(&format!(
+                "DROP TABLE IF EXISTS {}",
+                crate_partition_name(old_name)
+            ))
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?;
+        }
+        let crates = sqlx::query("UPDATE crates SET name = $2 WHERE name = $1")
+            .bind(old_name)
+            .bind(new_name)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?
+            .rows_affected();
+        sqlx::query("UPDATE doc_pages SET crate_name = $2 WHERE crate_name = $1")
+            .bind(old_name)
+            .bind(new_name)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?;
+        sqlx::query("UPDATE precomputed_answers SET crate_name = $2 WHERE crate_name = $1")
+            .bind(old_name)
+            .bind(new_name)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_err)?;
+        let configs = sqlx::query(
+            "UPDATE crate_configs SET name = $2, updated_at = CURRENT_TIMESTAMP WHERE name = $1",
+        )
+        .bind(old_name)
+        .bind(new_name)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_err)?
+        .rows_affected();
+
+        tx.commit()
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to commit transaction: {e}")))?;
+
+        Ok(RenameReport {
+            embeddings,
+            crates,
+            configs,
+        })
+    }
+
+    /// Cross-check `crates`, `doc_embeddings` and `crate_configs` for drift
+    ///
+    /// Read-only; findings come back most severe first. Only findings carrying an
+    /// [`AuditFix`] are safe to resolve automatically with [`Database::apply_audit_fix`].
+    pub async fn audit_consistency(&self) -> Result<Vec<AuditFinding>, ServerError> {
+        let map_err = |e: sqlx::Error| ServerError::Database(format!("Failed to audit: {e}"));
+        let mut findings = Vec::new();
+
+        let rows = sqlx::query(
+            r#"
+            SELECT c.id, c.name, c.total_docs, COUNT(de.crate_id) AS actual_docs
+            FROM crates c
+            LEFT JOIN doc_embeddings de ON de.crate_id = c.id AND de.generation = c.live_generation
+            GROUP BY c.id, c.name, c.total_docs
+            HAVING COALESCE(c.total_docs, 0) <> COUNT(de.crate_id)
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_err)?;
+        for row in rows {
+            let crate_id: i32 = row.get("id");
+            let total_docs: Option<i32> = row.get("total_docs");
+            let actual_docs: i64 = row.get("actual_docs");
+            findings.push(AuditFinding {
+                check: "stats_mismatch",
+                severity: AuditSeverity::Warning,
+                crate_name: row.get("name"),
+                detail: format!(
+                    "crates.total_docs is {} but {actual_docs} live embeddings reference crate id {crate_id}",
+                    total_docs.unwrap_or(0)
+                ),
+                fix_sql: format!(
+                    "UPDATE crates SET total_docs = (SELECT COUNT(*) FROM doc_embeddings WHERE crate_id = {crate_id} AND generation = crates.live_generation), \
+                     total_tokens = (SELECT COALESCE(SUM(token_count), 0) FROM doc_embeddings WHERE crate_id = {crate_id} AND generation = crates.live_generation) \
+                     WHERE id = {crate_id}"
+                ),
+                auto_fix: Some(AuditFix::RecomputeStats { crate_id }),
+            });
+        }
+
+        let rows = sqlx::query(
+            r#"
+            SELECT cc.id, cc.name, cc.version_spec, cc.target
+            FROM crate_configs cc
+            WHERE cc.last_populated IS NOT NULL
+              AND NOT EXISTS (SELECT 1 FROM doc_embeddings de WHERE de.crate_name = cc.name)
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_err)?;
+        for row in rows {
+            let config_id: i32 = row.get("id");
+            let version_spec: String = row.get("version_spec");
+            let target: Option<String> = row.get("target");
+            findings.push(AuditFinding {
+                check: "populated_without_embeddings",
+                severity: AuditSeverity::Error,
+                crate_name: row.get("name"),
+                detail: format!(
+                    "config {config_id} ({version_spec}, {}) is marked populated but has no embeddings",
+                    target.as_deref().unwrap_or("default target")
+                ),
+                // Clearing last_populated queues a full (paid) repopulation, so it's not automatic
+                fix_sql: format!(
+                    "UPDATE crate_configs SET last_populated = NULL WHERE id = {config_id}"
+                ),
+                auto_fix: None,
+            });
+        }
+
+        let rows = sqlx::query(
+            r#"
+            SELECT c.id, c.name, c.version, COUNT(de.crate_id) AS docs
+            FROM crates c
+            JOIN doc_embeddings de ON de.crate_id = c.id
+            WHERE EXISTS (
+                SELECT 1 FROM crate_configs cc
+                WHERE cc.name = c.name AND cc.current_version IS NOT NULL
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM crate_configs cc
+                WHERE cc.name = c.name AND cc.current_version IS NOT DISTINCT FROM c.version
+            )
+            GROUP BY c.id, c.name, c.version
             "#,
         )
-        .bind(crate_name)
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await
-        .map_err(|e| ServerError::Database(format!("Failed to check embeddings: {e}")))?;
-
-        let exists: bool = result.get("exists");
-        Ok(exists)
-    }
+        .map_err(map_err)?;
+        for row in rows {
+            let crate_id: i32 = row.get("id");
+            let version: Option<String> = row.get("version");
+            let docs: i64 = row.get("docs");
+            findings.push(AuditFinding {
+                check: "unreferenced_version",
+                severity: AuditSeverity::Warning,
+                crate_name: row.get("name"),
+                detail: format!(
+                    "{docs} embeddings are stored for version {} which no config's current_version references",
+                    version.as_deref().unwrap_or("unknown")
+                ),
+                fix_sql: format!(
+                    "DELETE FROM crates WHERE id = {crate_id} -- cascades to its doc_embeddings"
+                ),
+                auto_fix: None,
+            });
+        }
 
-    /// Get all crates that have embeddings
-    pub async fn get_all_crates_with_embeddings(&self) -> Result<Vec<String>, ServerError> {
         let rows = sqlx::query(
             r#"
-            SELECT DISTINCT crate_name FROM doc_embeddings
-            ORDER BY crate_name
+            SELECT crate_name, COUNT(*) AS docs
+            FROM doc_embeddings
+            WHERE crate_id IS NULL
+            GROUP BY crate_name
             "#,
         )
         .fetch_all(&self.pool)
         .await
-        .map_err(|e| ServerError::Database(format!("Failed to get crates with embeddings: {e}")))?;
-
-        let crates: Vec<String> = rows.iter().map(|row| row.get("crate_name")).collect();
-        Ok(crates)
-    }
-
-    /// Insert a document embedding
-    pub async fn insert_embedding(
-        &self,
-        crate_id: i32,
-        crate_name: &str,
-        doc_path: &str,
-        content: &str,
-        embedding: &Array1<f32>,
-        token_count: i32,
-    ) -> Result<(), ServerError> {
-        let embedding_vec = Vector::from(embedding.to_vec());
+        .map_err(map_err)?;
+        for row in rows {
+            let crate_name: String = row.get("crate_name");
+            let docs: i64 = row.get("docs");
+            findings.push(AuditFinding {
+                check: "embeddings_without_crate",
+                severity: AuditSeverity::Warning,
+                detail: format!(
+                    "{docs} embeddings have no crate_id, so they carry no version and aren't counted in crate stats"
+                ),
+                fix_sql: format!(
+                    "DELETE FROM doc_embeddings WHERE crate_id IS NULL AND crate_name = {}",
+                    sql_literal(&crate_name)
+                ),
+                crate_name,
+                auto_fix: None,
+            });
+        }
 
-        sqlx::query(
+        let rows = sqlx::query(
             r#"
-            INSERT INTO doc_embeddings (crate_id, crate_name, doc_path, content, embedding, token_count)
-            VALUES ($1, $2, $3, $4, $5, $6)
-            ON CONFLICT (crate_name, doc_path)
-            DO UPDATE SET
-                content = $4,
-                embedding = $5,
-                token_count = $6,
-                created_at = CURRENT_TIMESTAMP
-            "#
+            SELECT de.crate_name, COUNT(*) AS docs
+            FROM doc_embeddings de
+            WHERE NOT EXISTS (SELECT 1 FROM crate_configs cc WHERE cc.name = de.crate_name)
+            GROUP BY de.crate_name
+            "#,
         )
-        .bind(crate_id)
-        .bind(crate_name)
-        .bind(doc_path)
-        .bind(content)
-        .bind(embedding_vec)
-        .bind(token_count)
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await
-        .map_err(|e| ServerError::Database(format!("Failed to insert embedding: {e}")))?;
+        .map_err(map_err)?;
+        for row in rows {
+            let crate_name: String = row.get("crate_name");
+            let docs: i64 = row.get("docs");
+            findings.push(AuditFinding {
+                check: "unconfigured_crate",
+                severity: AuditSeverity::Info,
+                detail: format!(
+                    "{docs} embeddings belong to a crate with no configuration (gc_orphans removes them)"
+                ),
+                fix_sql: format!(
+                    "DELETE FROM doc_embeddings WHERE crate_name = {0}; DELETE FROM crates WHERE name = {0}",
+                    sql_literal(&crate_name)
+                ),
+                crate_name,
+                auto_fix: None,
+            });
+        }
 
-        Ok(())
+        findings.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then_with(|| a.crate_name.cmp(&b.crate_name))
+        });
+        Ok(findings)
     }
 
-    /// Batch insert multiple embeddings (more efficient)
-    pub async fn insert_embeddings_batch(
+    /// Apply the safe automatic fix for a finding from [`Database::audit_consistency`]
+    ///
+    /// Returns `false` when the finding has no automatic fix.
+    pub async fn apply_audit_fix(&self, finding: &AuditFinding) -> Result<bool, ServerError> {
+        match finding.auto_fix {
+            Some(AuditFix::RecomputeStats { crate_id }) => {
+                self.update_crate_stats(crate_id).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Remove `doc_embeddings` and `crates` rows for crate names that have no `crate_configs` row
+    ///
+    /// With `dry_run` the orphans are only counted. Crates stored by `populate_db` without
+    /// ever being configured count as orphans too. Their stored page HTML goes with them.
+    pub async fn delete_orphaned_embeddings(
         &self,
-        crate_id: i32,
-        crate_name: &str,
-        embeddings: &[(String, String, Array1<f32>, i32)], // (path, content, embedding, token_count)
-    ) -> Result<(), ServerError> {
+        dry_run: bool,
+    ) -> Result<OrphanReport, ServerError> {
         let mut tx = self
             .pool
             .begin()
             .await
             .map_err(|e| ServerError::Database(format!("Failed to begin transaction: {e}")))?;
 
-        for (doc_path, content, embedding, token_count) in embeddings {
-            let embedding_vec = Vector::from(embedding.to_vec());
+        let crate_names: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT name FROM (
+                SELECT name FROM crates
+                UNION
+                SELECT DISTINCT crate_name FROM doc_embeddings
+            ) stored
+            WHERE NOT EXISTS (SELECT 1 FROM crate_configs cc WHERE cc.name = stored.name)
+            ORDER BY name
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to find orphaned crates: {e}")))?;
 
-            sqlx::query(
-                r#"
-                INSERT INTO doc_embeddings (crate_id, crate_name, doc_path, content, embedding, token_count)
-                VALUES ($1, $2, $3, $4, $5, $6)
-                ON CONFLICT (crate_name, doc_path)
-                DO UPDATE SET
-                    content = $4,
-                    embedding = $5,
-                    token_count = $6,
-                    created_at = CURRENT_TIMESTAMP
-                "#
+        let (embeddings, crates) = if dry_run {
+            let embeddings: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM doc_embeddings WHERE crate_name = ANY($1)",
             )
-            .bind(crate_id)
-            .bind(crate_name)
-            .bind(doc_path)
-            .bind(content)
-            .bind(embedding_vec)
-            .bind(*token_count)
-            .execute(&mut *tx)
+            .bind(&crate_names)
+            .fetch_one(&mut *tx)
             .await
-            .map_err(|e| ServerError::Database(format!("Failed to insert embedding: {e}")))?;
-        }
+            .map_err(|e| ServerError::Database(format!("Failed to count orphans: {e}")))?;
+            let crates: i64 =
+                sqlx::query_scalar("SELECT COUNT(*) FROM crates WHERE name = ANY($1)")
+                    .bind(&crate_names)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .map_err(|e| ServerError::Database(format!("Failed to count orphans: {e}")))?;
+            (embeddings as u64, crates as u64)
+        } else {
+            let embeddings = sqlx::query("DELETE FROM doc_embeddings WHERE crate_name = ANY($1)")
+                .bind(&crate_names)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    ServerError::Database(format!("Failed to delete orphaned embeddings: {e}"))
+                })?
+                .rows_affected();
+            let crates = sqlx::query("DELETE FROM crates WHERE name = ANY($1)")
+                .bind(&crate_names)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    ServerError::Database(format!("Failed to delete orphaned crates: {e}"))
+                })?
+                .rows_affected();
+            // Raw HTML is only useful for re-extracting the deleted documents
+            sqlx::query("DELETE FROM doc_pages WHERE crate_name = ANY($1)")
+                .bind(&crate_names)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    ServerError::Database(format!("Failed to delete orphaned pages: {e}"))
+                })?;
+            (embeddings, crates)
+        };
 
         tx.commit()
             .await
             .map_err(|e| ServerError::Database(format!("Failed to commit transaction: {e}")))?;
 
-        // Update crate statistics
-        self.update_crate_stats(crate_id).await?;
-
-        Ok(())
+        Ok(OrphanReport {
+            crate_names,
+            embeddings,
+            crates,
+        })
     }
 
-    /// Update crate statistics
-    async fn update_crate_stats(&self, crate_id: i32) -> Result<(), ServerError> {
-        sqlx::query(
-            r#"
-            UPDATE crates
-            SET total_docs = (
-                SELECT COUNT(*) FROM doc_embeddings WHERE crate_id = $1
-            ),
-            total_tokens = (
-                SELECT COALESCE(SUM(token_count), 0) FROM doc_embeddings WHERE crate_id = $1
-            )
-            WHERE id = $1
-            "#,
+    /// Crates whose configurations are all disabled; hidden from queries despite their embeddings
+    pub async fn get_disabled_crate_names(&self) -> Result<Vec<String>, ServerError> {
+        sqlx::query_scalar(
+            "SELECT name FROM crate_configs GROUP BY name HAVING NOT bool_or(enabled) ORDER BY name",
         )
-        .bind(crate_id)
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await
-        .map_err(|e| ServerError::Database(format!("Failed to update crate stats: {e}")))?;
-
-        Ok(())
+        .map_err(|e| ServerError::Database(format!("Failed to get disabled crates: {e}")))
     }
 
-    /// Search for similar documents using vector similarity
-    pub async fn search_similar_docs(
+    /// All configurations for one crate, most recently populated first
+    pub async fn get_crate_configs_by_name(
         &self,
-        crate_name: &str,
-        query_embedding: &Array1<f32>,
-        limit: i32,
-    ) -> Result<Vec<(String, String, f32)>, ServerError> {
-        let embedding_vec = Vector::from(query_embedding.to_vec());
-
-        let results = sqlx::query(
-            r#"
-            SELECT
-                doc_path,
-                content,
-                1 - (embedding <=> $1) as similarity
-            FROM doc_embeddings
-            WHERE crate_name = $2
-            ORDER BY embedding <=> $1
-            LIMIT $3
-            "#,
+        name: &str,
+    ) -> Result<Vec<CrateConfig>, ServerError> {
+        sqlx::query_as::<_, CrateConfig>(
+            "SELECT * FROM crate_configs WHERE name = $1 ORDER BY last_populated DESC NULLS LAST",
         )
-        .bind(embedding_vec)
-        .bind(crate_name)
-        .bind(limit)
+        .bind(name)
         .fetch_all(&self.pool)
         .await
-        .map_err(|e| ServerError::Database(format!("Failed to search documents: {e}")))?;
-
-        Ok(results
-            .into_iter()
-            .map(|row| {
-                let doc_path: String = row.get("doc_path");
-                let content: String = row.get("content");
-                let similarity: f64 = row.get("similarity");
-                #[allow(clippy::cast_possible_truncation)]
-                let similarity = similarity as f32; // Convert to f32 for compatibility
-                (doc_path, content, similarity)
-            })
-            .collect())
+        .map_err(|e| ServerError::Database(format!("Failed to get crate configs: {e}")))
     }
 
-    /// Get all documents for a crate (for loading into memory if needed)
-    pub async fn get_crate_documents(
+    /// Get a specific crate configuration (`target` `None` is the default-target row)
+    pub async fn get_crate_config(
         &self,
-        crate_name: &str,
-    ) -> Result<Vec<(String, String, Array1<f32>)>, ServerError> {
-        eprintln!("    🔍 Querying database for crate: {crate_name}");
-        let query_start = std::time::Instant::now();
-
-        let results = sqlx::query(
-            r#"
-            SELECT doc_path, content, embedding
-            FROM doc_embeddings
-            WHERE crate_name = $1
-            ORDER BY doc_path
-            "#,
+        name: &str,
+        version_spec: &str,
+        target: Option<&str>,
+    ) -> Result<Option<CrateConfig>, ServerError> {
+        let config = sqlx::query_as::<_, CrateConfig>(
+            "SELECT * FROM crate_configs WHERE name = $1 AND version_spec = $2 AND target IS NOT DISTINCT FROM $3",
         )
-        .bind(crate_name)
-        .fetch_all(&self.pool)
+        .bind(name)
+        .bind(version_spec)
+        .bind(target)
+        .fetch_optional(&self.pool)
         .await
-        .map_err(|e| ServerError::Database(format!("Failed to get crate documents: {e}")))?;
-
-        let query_time = query_start.elapsed();
-        eprintln!(
-            "    📊 Found {} documents for {} in {:.3}s",
-            results.len(),
-            crate_name,
-            query_time.as_secs_f64()
-        );
-
-        let mut documents = Vec::new();
-        for (i, row) in results.iter().enumerate() {
-            let doc_path: String = row.get("doc_path");
-            let content: String = row.get("content");
-            let embedding_vec: Vector = row.get("embedding");
-            let embedding_array = Array1::from_vec(embedding_vec.to_vec());
-
-            if i < 3 || (i + 1) % 5 == 0 {
-                eprintln!(
-                    "    📄 [{}/{}] Processed: {} ({} chars, {} dims)",
-                    i + 1,
-                    results.len(),
-                    doc_path,
-                    content.len(),
-                    embedding_array.len()
-                );
-            }
-
-            documents.push((doc_path, content, embedding_array));
-        }
+        .map_err(|e| ServerError::Database(format!("Failed to get crate config: {e}")))?;
 
-        Ok(documents)
+        Ok(config)
     }
 
-    /// Delete all embeddings for a crate
-    pub async fn delete_crate_embeddings(&self, crate_name: &str) -> Result<(), ServerError> {
-        sqlx::query(
+    /// Add or update a crate configuration
+    pub async fn upsert_crate_config(
+        &self,
+        config: &CrateConfig,
+    ) -> Result<CrateConfig, ServerError> {
+        let result = sqlx::query_as::<_, CrateConfig>(
             r#"
-            DELETE FROM doc_embeddings WHERE crate_name = $1
-            "#,
+            INSERT INTO crate_configs (name, version_spec, current_version, features, expected_docs, enabled, content_selector, include_url_patterns, exclude_url_patterns, follow_reexports, reexport_crates, reexport_page_budget, reexport_pages_per_crate, rustdoc_json_url, target, auto_expected_docs, query_hint, default_limit, default_min_similarity, default_path_prefix, max_pages, store_raw_html, languages, language_action, max_embeddings, max_duration_secs, section_metadata, allow_prerelease)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28)
+            ON CONFLICT (name, version_spec, (COALESCE(target, ''))) DO UPDATE SET
+                current_version = EXCLUDED.current_version,
+                features = EXCLUDED.features,
+                expected_docs = EXCLUDED.expected_docs,
+                enabled = EXCLUDED.enabled,
+                content_selector = EXCLUDED.content_selector,
+                include_url_patterns = EXCLUDED.include_url_patterns,
+                exclude_url_patterns = EXCLUDED.exclude_url_patterns,
+                follow_reexports = EXCLUDED.follow_reexports,
+                reexport_crates = EXCLUDED.reexport_crates,
+                reexport_page_budget = EXCLUDED.reexport_page_budget,
+                reexport_pages_per_crate = EXCLUDED.reexport_pages_per_crate,
+                rustdoc_json_url = EXCLUDED.rustdoc_json_url,
+                auto_expected_docs = EXCLUDED.auto_expected_docs,
+                query_hint = EXCLUDED.query_hint,
+                default_limit = EXCLUDED.default_limit,
+                default_min_similarity = EXCLUDED.default_min_similarity,
+                default_path_prefix = EXCLUDED.default_path_prefix,
+                max_pages = EXCLUDED.max_pages,
+                store_raw_html = EXCLUDED.store_raw_html,
+                languages = EXCLUDED.languages,
+                language_action = EXCLUDED.language_action,
+                max_embeddings = EXCLUDED.max_embeddings,
+                max_duration_secs = EXCLUDED.max_duration_secs,
+                section_metadata = EXCLUDED.section_metadata,
+                allow_prerelease = EXCLUDED.allow_prerelease,
+                updated_at = CURRENT_TIMESTAMP
+            RETURNING *
+            "#
         )
-        .bind(crate_name)
-        .execute(&self.pool)
+        .bind(&config.name)
+        .bind(&config.version_spec)
+        .bind(&config.current_version)
+        .bind(&config.features)
+        .bind(config.expected_docs)
+        .bind(config.enabled)
+        .bind(&config.content_selector)
+        .bind(&config.include_url_patterns)
+        .bind(&config.exclude_url_patterns)
+        .bind(config.follow_reexports)
+        .bind(&config.reexport_crates)
+        .bind(config.reexport_page_budget)
+        .bind(config.reexport_pages_per_crate)
+        .bind(&config.rustdoc_json_url)
+        .bind(&config.target)
+        .bind(config.auto_expected_docs)
+        .bind(&config.query_hint)
+        .bind(config.default_limit)
+        .bind(config.default_min_similarity)
+        .bind(&config.default_path_prefix)
+        .bind(config.max_pages)
+        .bind(config.store_raw_html)
+        .bind(&config.languages)
+        .bind(&config.language_action)
+        .bind(config.max_embeddings)
+        .bind(config.max_duration_secs)
+        .bind(config.section_metadata)
+        .bind(config.allow_prerelease)
+        .fetch_one(&self.pool)
         .await
-        .map_err(|e| ServerError::Database(format!("Failed to delete embeddings: {e}")))?;
+        .map_err(|e| ServerError::Database(format!("Failed to upsert crate config: {e}")))?;
 
-        Ok(())
+        Ok(result)
     }
 
-    /// Get crate statistics
-    pub async fn get_crate_stats(&self) -> Result<Vec<CrateStats>, ServerError> {
-        let results = sqlx::query(
+    /// Record a successful population of a crate configuration
+    ///
+    /// `current_version` keeps the stored version when `None`; `expected_docs` replaces the
+    /// coverage baseline when given.
+    pub async fn record_crate_population(
+        &self,
+        config_id: i32,
+        current_version: Option<&str>,
+        expected_docs: Option<i32>,
+    ) -> Result<(), ServerError> {
+        sqlx::query(
             r#"
-            SELECT
-                name,
-                version,
-                last_updated,
-                total_docs,
-                total_tokens
-            FROM crates
-            ORDER BY name
+            UPDATE crate_configs
+            SET current_version = COALESCE($2, current_version),
+                expected_docs = COALESCE($3, expected_docs),
+                last_populated = CURRENT_TIMESTAMP,
+                last_checked = CURRENT_TIMESTAMP,
+                partial_at = NULL,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
             "#,
         )
-        .fetch_all(&self.pool)
+        .bind(config_id)
+        .bind(current_version)
+        .bind(expected_docs)
+        .execute(&self.pool)
         .await
-        .map_err(|e| ServerError::Database(format!("Failed to get crate stats: {e}")))?;
-
-        Ok(results
-            .into_iter()
-            .map(|row| {
-                let name: String = row.get("name");
-                let version: Option<String> = row.get("version");
-                let last_updated: chrono::NaiveDateTime = row.get("last_updated");
-                let total_docs: Option<i32> = row.get("total_docs");
-                let total_tokens: Option<i32> = row.get("total_tokens");
+        .map_err(|e| ServerError::Database(format!("Failed to record crate population: {e}")))?;
 
-                CrateStats {
-                    name,
-                    version,
-                    last_updated,
-                    total_docs: total_docs.unwrap_or(0),
-                    total_tokens: total_tokens.unwrap_or(0),
-                }
-            })
-            .collect())
+        Ok(())
     }
 
-    /// Count documents for a specific crate
-    pub async fn count_crate_documents(&self, crate_name: &str) -> Result<usize, ServerError> {
-        let result = sqlx::query(
-            r#"
-            SELECT COUNT(*) as count
-            FROM doc_embeddings
-            WHERE crate_name = $1
-            "#,
+    /// Record that a population of a configuration stopped at its deadline, so the next
+    /// one resumes after the chunks it stored
+    pub async fn record_partial_population(&self, config_id: i32) -> Result<(), ServerError> {
+        sqlx::query(
+            "UPDATE crate_configs SET partial_at = CURRENT_TIMESTAMP, last_checked = CURRENT_TIMESTAMP WHERE id = $1",
         )
-        .bind(crate_name)
-        .fetch_one(&self.pool)
+        .bind(config_id)
+        .execute(&self.pool)
         .await
-        .map_err(|e| ServerError::Database(format!("Failed to count crate documents: {e}")))?;
+        .map_err(|e| {
+            ServerError::Database(format!("Failed to record partial population: {e}"))
+        })?;
 
-        let count: i64 = result.get("count");
-        Ok(count as usize)
+        Ok(())
     }
 
-    // ===== Crate Configuration Methods =====
-
-    /// Get all crate configurations
-    pub async fn get_crate_configs(
+    /// Record whether a configuration's last population stopped at its `max_embeddings`
+    pub async fn set_crate_truncated(
         &self,
-        enabled_only: bool,
-    ) -> Result<Vec<CrateConfig>, ServerError> {
-        let query = if enabled_only {
-            "SELECT * FROM crate_configs WHERE enabled = true ORDER BY name, version_spec"
-        } else {
-            "SELECT * FROM crate_configs ORDER BY name, version_spec"
-        };
-
-        let configs = sqlx::query_as::<_, CrateConfig>(query)
-            .fetch_all(&self.pool)
+        config_id: i32,
+        truncated: bool,
+    ) -> Result<(), ServerError> {
+        sqlx::query("UPDATE crate_configs SET truncated = $2 WHERE id = $1")
+            .bind(config_id)
+            .bind(truncated)
+            .execute(&self.pool)
             .await
-            .map_err(|e| ServerError::Database(format!("Failed to get crate configs: {e}")))?;
+            .map_err(|e| {
+                ServerError::Database(format!("Failed to record crate truncation: {e}"))
+            })?;
 
-        Ok(configs)
+        Ok(())
     }
 
-    /// Get a specific crate configuration
-    pub async fn get_crate_config(
+    /// Record whether a configuration's last population fell back to the crate README
+    pub async fn set_crate_low_doc_coverage(
         &self,
-        name: &str,
-        version_spec: &str,
-    ) -> Result<Option<CrateConfig>, ServerError> {
-        let config = sqlx::query_as::<_, CrateConfig>(
-            "SELECT * FROM crate_configs WHERE name = $1 AND version_spec = $2",
-        )
-        .bind(name)
-        .bind(version_spec)
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(|e| ServerError::Database(format!("Failed to get crate config: {e}")))?;
+        config_id: i32,
+        low_doc_coverage: bool,
+    ) -> Result<(), ServerError> {
+        sqlx::query("UPDATE crate_configs SET low_doc_coverage = $2 WHERE id = $1")
+            .bind(config_id)
+            .bind(low_doc_coverage)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                ServerError::Database(format!("Failed to record low doc coverage: {e}"))
+            })?;
 
-        Ok(config)
+        Ok(())
     }
 
-    /// Add or update a crate configuration
-    pub async fn upsert_crate_config(
+    /// Put back a configuration's population timestamps, e.g. after restoring it
+    pub async fn set_crate_config_timestamps(
         &self,
-        config: &CrateConfig,
-    ) -> Result<CrateConfig, ServerError> {
-        let result = sqlx::query_as::<_, CrateConfig>(
-            r#"
-            INSERT INTO crate_configs (name, version_spec, current_version, features, expected_docs, enabled)
-            VALUES ($1, $2, $3, $4, $5, $6)
-            ON CONFLICT (name, version_spec) DO UPDATE SET
-                current_version = EXCLUDED.current_version,
-                features = EXCLUDED.features,
-                expected_docs = EXCLUDED.expected_docs,
-                enabled = EXCLUDED.enabled,
-                updated_at = CURRENT_TIMESTAMP
-            RETURNING *
-            "#
+        config_id: i32,
+        last_populated: Option<chrono::DateTime<chrono::Utc>>,
+        last_checked: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<(), ServerError> {
+        sqlx::query(
+            "UPDATE crate_configs SET last_populated = $2, last_checked = $3 WHERE id = $1",
         )
-        .bind(&config.name)
-        .bind(&config.version_spec)
-        .bind(&config.current_version)
-        .bind(&config.features)
-        .bind(config.expected_docs)
-        .bind(config.enabled)
-        .fetch_one(&self.pool)
+        .bind(config_id)
+        .bind(last_populated)
+        .bind(last_checked)
+        .execute(&self.pool)
         .await
-        .map_err(|e| ServerError::Database(format!("Failed to upsert crate config: {e}")))?;
+        .map_err(|e| {
+            ServerError::Database(format!("Failed to set crate config timestamps: {e}"))
+        })?;
+        Ok(())
+    }
 
-        Ok(result)
+    /// Record that a configuration's docs.rs version was just checked
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn mark_crate_checked(&self, config_id: i32) -> Result<(), ServerError> {
+        sqlx::query("UPDATE crate_configs SET last_checked = CURRENT_TIMESTAMP WHERE id = $1")
+            .bind(config_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to mark crate checked: {e}")))?;
+
+        Ok(())
     }
 
-    /// Delete a crate configuration
+    /// Delete a crate configuration and its population jobs (`target` `None` is the
+    /// default-target row)
     pub async fn delete_crate_config(
         &self,
         name: &str,
         version_spec: &str,
+        target: Option<&str>,
     ) -> Result<bool, ServerError> {
-        let result = sqlx::query("DELETE FROM crate_configs WHERE name = $1 AND version_spec = $2")
-            .bind(name)
-            .bind(version_spec)
-            .execute(&self.pool)
-            .await
-            .map_err(|e| ServerError::Database(format!("Failed to delete crate config: {e}")))?;
+        let result = sqlx::query(
+            r#"
+            WITH doomed AS (
+                SELECT id FROM crate_configs
+                WHERE name = $1 AND version_spec = $2 AND target IS NOT DISTINCT FROM $3
+            ), jobs AS (
+                DELETE FROM population_jobs WHERE crate_config_id IN (SELECT id FROM doomed)
+            )
+            DELETE FROM crate_configs WHERE id IN (SELECT id FROM doomed)
+            "#,
+        )
+        .bind(name)
+        .bind(version_spec)
+        .bind(target)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to delete crate config: {e}")))?;
 
         Ok(result.rows_affected() > 0)
     }
@@ -455,10 +4365,12 @@ impl Database {
             r#"
             SELECT cc.* FROM crate_configs cc
             LEFT JOIN crates c ON cc.name = c.name AND cc.current_version = c.version
+                AND COALESCE(c.target, '') = COALESCE(cc.target, '')
             WHERE cc.enabled = true
             AND (
                 c.id IS NULL  -- Crate doesn't exist
                 OR cc.last_populated IS NULL  -- Never populated
+                OR cc.partial_at IS NOT NULL  -- Stopped at its time limit; resume
                 OR (cc.version_spec = 'latest' AND cc.last_checked < CURRENT_TIMESTAMP - INTERVAL '24 hours')  -- Check for updates daily
             )
             ORDER BY cc.name
@@ -501,7 +4413,7 @@ impl Database {
 
         if status == "running" {
             query.push_str(", started_at = CURRENT_TIMESTAMP");
-        } else if status == "completed" || status == "failed" {
+        } else if matches!(status, "completed" | "partial" | "failed") {
             query.push_str(", completed_at = CURRENT_TIMESTAMP");
         }
 
@@ -534,15 +4446,724 @@ impl Database {
 
         Ok(())
     }
+
+    /// Record how many chunks a running population job has stored so far
+    pub async fn set_population_job_progress(
+        &self,
+        job_id: i32,
+        docs_populated: i32,
+    ) -> Result<(), ServerError> {
+        sqlx::query("UPDATE population_jobs SET docs_populated = $1 WHERE id = $2")
+            .bind(docs_populated)
+            .bind(job_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                ServerError::Database(format!("Failed to update population progress: {e}"))
+            })?;
+
+        Ok(())
+    }
+
+    /// Attach the crawl diagnostics of a population run to its job
+    pub async fn set_population_job_diagnostics(
+        &self,
+        job_id: i32,
+        diagnostics: &CrawlDiagnostics,
+    ) -> Result<(), ServerError> {
+        sqlx::query("UPDATE population_jobs SET diagnostics = $1::jsonb WHERE id = $2")
+            .bind(serde_json::to_string(diagnostics)?)
+            .bind(job_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                ServerError::Database(format!("Failed to store population diagnostics: {e}"))
+            })?;
+
+        Ok(())
+    }
+
+    /// Store the crawl trace of a job's population, for `crawl_trace`
+    pub async fn set_population_job_crawl_trace(
+        &self,
+        job_id: i32,
+        trace: &CrawlTrace,
+    ) -> Result<(), ServerError> {
+        sqlx::query("UPDATE population_jobs SET crawl_trace = $1::jsonb WHERE id = $2")
+            .bind(serde_json::to_string(trace)?)
+            .bind(job_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ServerError::Database(format!("Failed to store crawl trace: {e}")))?;
+
+        Ok(())
+    }
+
+    /// The crawl trace stored on a job; `None` when the job has none (it didn't crawl
+    /// docs.rs, or predates traces)
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn get_population_job_crawl_trace(
+        &self,
+        job_id: i32,
+    ) -> Result<Option<CrawlTrace>, ServerError> {
+        let trace: Option<String> =
+            sqlx::query_scalar("SELECT crawl_trace::text FROM population_jobs WHERE id = $1")
+                .bind(job_id)
+                .fetch_optional(&self.read_pool)
+                .await
+                .map_err(|e| ServerError::Database(format!("Failed to get crawl trace: {e}")))?
+                .flatten();
+        Ok(trace.map(|json| serde_json::from_str(&json)).transpose()?)
+    }
+
+    /// Record a population's retrievability self-test on its job
+    pub async fn set_population_job_self_test(
+        &self,
+        job_id: i32,
+        self_test: &SelfTest,
+    ) -> Result<(), ServerError> {
+        sqlx::query("UPDATE population_jobs SET self_test = $1::jsonb WHERE id = $2")
+            .bind(serde_json::to_string(self_test)?)
+            .bind(job_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                ServerError::Database(format!("Failed to store population self-test: {e}"))
+            })?;
+
+        Ok(())
+    }
+
+    /// Record the start of a `docs_backup` run writing to `location`
+    pub async fn start_backup_run(&self, location: &str) -> Result<i32, ServerError> {
+        sqlx::query_scalar(
+            "INSERT INTO backup_runs (status, location) VALUES ('running', $1) RETURNING id",
+        )
+        .bind(location)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to record backup run: {e}")))
+    }
+
+    /// Record how a `docs_backup` run ended: its totals, or why it failed
+    pub async fn finish_backup_run(
+        &self,
+        run_id: i32,
+        outcome: Result<(i32, i64, i64), &str>,
+    ) -> Result<(), ServerError> {
+        let (status, totals, error_message) = match outcome {
+            Ok(totals) => ("completed", Some(totals), None),
+            Err(error) => ("failed", None, Some(error)),
+        };
+        sqlx::query(
+            r#"
+            UPDATE backup_runs
+            SET status = $2, completed_at = CURRENT_TIMESTAMP,
+                crates = $3, documents = $4, bytes = $5, error_message = $6
+            WHERE id = $1
+            "#,
+        )
+        .bind(run_id)
+        .bind(status)
+        .bind(totals.map(|(crates, _, _)| crates))
+        .bind(totals.map(|(_, documents, _)| documents))
+        .bind(totals.map(|(_, _, bytes)| bytes))
+        .bind(error_message)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to record backup run: {e}")))?;
+        Ok(())
+    }
+
+    /// The most recent `docs_backup` run that completed
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn get_last_successful_backup(&self) -> Result<Option<BackupRun>, ServerError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, started_at, completed_at, status, location, crates, documents, bytes,
+                   error_message
+            FROM backup_runs
+            WHERE status = 'completed'
+            ORDER BY completed_at DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.read_pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get last backup: {e}")))?;
+        Ok(row.map(|row| BackupRun {
+            id: row.get("id"),
+            started_at: row.get("started_at"),
+            completed_at: row.get("completed_at"),
+            status: row.get("status"),
+            location: row.get("location"),
+            crates: row.get("crates"),
+            documents: row.get("documents"),
+            bytes: row.get("bytes"),
+            error_message: row.get("error_message"),
+        }))
+    }
+
+    /// Most recent population job for a crate configuration
+    pub async fn get_latest_population_job(
+        &self,
+        crate_config_id: i32,
+    ) -> Result<Option<PopulationJob>, ServerError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, crate_config_id, status, started_at, completed_at, error_message,
+                   docs_populated, diagnostics::text AS diagnostics,
+                   self_test::text AS self_test
+            FROM population_jobs
+            WHERE crate_config_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(crate_config_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get population job: {e}")))?;
+
+        Ok(row.as_ref().map(population_job_from_row))
+    }
+
+    /// A population job by id
+    #[allow(dead_code)] // Used by the HTTP server
+    pub async fn get_population_job(
+        &self,
+        job_id: i32,
+    ) -> Result<Option<PopulationJob>, ServerError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, crate_config_id, status, started_at, completed_at, error_message,
+                   docs_populated, diagnostics::text AS diagnostics,
+                   self_test::text AS self_test
+            FROM population_jobs
+            WHERE id = $1
+            "#,
+        )
+        .bind(job_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ServerError::Database(format!("Failed to get population job: {e}")))?;
+
+        Ok(row.as_ref().map(population_job_from_row))
+    }
+}
+
+fn population_job_from_row(row: &sqlx::postgres::PgRow) -> PopulationJob {
+    PopulationJob {
+        id: row.get("id"),
+        crate_config_id: row.get("crate_config_id"),
+        status: row.get("status"),
+        started_at: row.get("started_at"),
+        completed_at: row.get("completed_at"),
+        error_message: row.get("error_message"),
+        docs_populated: row.get("docs_populated"),
+        diagnostics: row
+            .get::<Option<String>, _>("diagnostics")
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        self_test: row
+            .get::<Option<String>, _>("self_test")
+            .and_then(|json| serde_json::from_str(&json).ok()),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PopulationJob {
+    pub id: i32,
+    pub crate_config_id: i32,
+    pub status: String,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub error_message: Option<String>,
+    pub docs_populated: Option<i32>,
+    /// Crawl diagnostics as stored by the populating process
+    pub diagnostics: Option<serde_json::Value>,
+    /// Retrievability check run once the documents were stored
+    pub self_test: Option<serde_json::Value>,
+}
+
+/// One stored document with everything needed to store it again elsewhere
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredDocument {
+    pub doc_path: String,
+    /// Plain text, decompressed
+    pub content: String,
+    pub token_count: i32,
+    /// Detected language, for crates that tag them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Docblock section heading, for crates that tag them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    /// docs.rs target the document was crawled for (`None` for the default one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub embedding: Vec<f32>,
+}
+
+/// The `crates` row of a crate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrateRecord {
+    pub version: Option<String>,
+    pub target: Option<String>,
+    pub embedding_model: Option<String>,
+}
+
+/// Which generation of a crate's documents queries read, see [`Database::promote_generation`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LiveGeneration {
+    pub generation: i32,
+    /// Crate version recorded with the generation
+    pub version: Option<String>,
+}
+
+/// One `docs_backup` run, as recorded in `backup_runs`
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupRun {
+    pub id: i32,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// `running`, `completed` or `failed`
+    pub status: String,
+    /// Where the archive went: a file path or object storage URL
+    pub location: String,
+    pub crates: Option<i32>,
+    pub documents: Option<i64>,
+    pub bytes: Option<i64>,
+    pub error_message: Option<String>,
+}
+
+/// Whether a finished population can be found by queries, recorded on its job
+///
+/// Built by `pipeline::self_test`, which searches one stored document's own vector under
+/// the name queries use, so the check costs no embedding call.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SelfTest {
+    pub passed: bool,
+    /// The name searched, as `query_rust_docs` is called with it
+    pub crate_name: String,
+    /// Stored document whose vector was the query
+    pub sample_doc_path: Option<String>,
+    /// Rows the search returned
+    pub hits: usize,
+    /// Similarity of the top row; about 1.0 when the sample finds itself
+    pub top_similarity: Option<f32>,
+    /// Why the check failed
+    pub error: Option<String>,
+}
+
+/// What [`Database::prewarm`] did and how long it took
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // Used by the HTTP server
+pub struct PrewarmReport {
+    /// Connections opened up front
+    pub connections: u32,
+    /// Crates whose warmup search succeeded
+    pub crates: usize,
+    /// Crates whose warmup search failed
+    pub failed: Vec<String>,
+    pub connect_ms: u64,
+    pub search_ms: u64,
+}
+
+/// A search hit that keeps the raw scores instead of collapsing them
+#[derive(Debug, Clone)]
+pub struct ScoredDoc {
+    pub doc_path: String,
+    pub content: String,
+    /// Raw cosine distance reported by pgvector (`embedding <=> query`)
+    pub distance: f64,
+    /// Normalized similarity (`1 - distance`)
+    pub similarity: f32,
+    /// When this document was last (re-)indexed
+    #[allow(dead_code)] // Used by the HTTP server's freshness footer
+    pub last_updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Version of the crate as last populated
+    #[allow(dead_code)] // Used by the HTTP server's freshness footer
+    pub crate_version: Option<String>,
+    /// Tokens in the stored chunk (0 when unknown)
+    pub token_count: i32,
+}
+
+/// A [`ScoredDoc`] from a row with the columns of a similarity search
+fn scored_doc(row: sqlx::postgres::PgRow) -> Result<ScoredDoc, ServerError> {
+    let distance: f64 = row.get("distance");
+    #[allow(clippy::cast_possible_truncation)]
+    let similarity = (1.0 - distance) as f32; // Convert to f32 for compatibility
+    Ok(ScoredDoc {
+        doc_path: row.get("doc_path"),
+        content: decode_content(row.get("content"), row.get("content_compressed"))?,
+        distance,
+        similarity,
+        last_updated_at: row.get("last_updated_at"),
+        crate_version: row.get("crate_version"),
+        token_count: row.get::<Option<i32>, _>("token_count").unwrap_or(0),
+    })
+}
+
+/// The search behind an orientation question, run when its crate finished populating
+/// (see [`crate::orientation`])
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecomputedAnswer {
+    /// Which built-in question, e.g. `getting_started`
+    pub question_key: String,
+    pub question: String,
+    /// Model that embedded the question
+    pub model: String,
+    pub embedding: Vec<f32>,
+    /// Hits as `(doc_path, distance)`, best first
+    pub results: Vec<(String, f64)>,
+}
+
+/// Upsert for one document row. `$4` is the stored (possibly empty) content and
+/// `$9` the plain text, so the tsvector is right even when the row is compressed.
+/// `first_seen_at` keeps its insert-time default; `last_updated_at` moves on every upsert.
+/// `$10` is the generation written to; NULL writes to the crate's live generation
+const INSERT_EMBEDDING_SQL: &str = r#"
+    INSERT INTO doc_embeddings (crate_id, crate_name, doc_path, content, embedding, token_count, content_compressed, content_size, content_tsv, generation)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, to_tsvector('english', $9),
+            COALESCE($10, (SELECT live_generation FROM crates WHERE id = $1), 0))
+    ON CONFLICT (crate_name, crate_id, doc_path, generation)
+    DO UPDATE SET
+        content = $4,
+        embedding = $5,
+        token_count = $6,
+        content_compressed = $7,
+        content_size = $8,
+        content_tsv = to_tsvector('english', $9),
+        -- Tagged again by set_document_sections if the new content has a section
+        section = NULL,
+        created_at = CURRENT_TIMESTAMP,
+        last_updated_at = CURRENT_TIMESTAMP
+    RETURNING id
+"#;
+
+/// SQL condition keeping the `doc_embeddings de` rows of their crate's live generation
+///
+/// Looked up through `de.crate_id`, since every docs.rs target of a crate has its own
+/// `crates` row and generations. Rows of a repopulation still being staged stay
+/// invisible until [`Database::promote_generation`] swaps them in.
+const LIVE_GENERATION: &str = "de.generation = COALESCE((SELECT live.live_generation FROM crates live WHERE live.id = de.crate_id), 0)";
+
+/// SQL condition picking the `crates` row of one target: `$1` is the crate name and
+/// `target` the bind parameter holding the target (NULL for docs.rs's default one)
+fn crate_target(target: &str) -> String {
+    format!("name = $1 AND COALESCE(target, '') = COALESCE({target}, '')")
+}
+
+/// Escape `LIKE` wildcards so `text` matches literally
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// A crate's package facts from crates.io, as cached in `crate_metadata`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(dead_code)] // Used by the HTTP server
+pub struct CrateMetadata {
+    pub crate_name: String,
+    pub description: Option<String>,
+    pub repository: Option<String>,
+    pub homepage: Option<String>,
+    /// SPDX expression of the latest version
+    pub license: Option<String>,
+    /// Newest stable release, or the newest release when there is none
+    pub latest_version: Option<String>,
+    /// All-time downloads
+    pub downloads: i64,
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A document whose content or path contains a searched identifier
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // Used by the HTTP server
+pub struct SymbolMatch {
+    pub crate_name: String,
+    pub doc_path: String,
+    pub content: String,
+    /// Trigram word similarity of the identifier to the content or path (0 without pg_trgm)
+    pub score: f32,
+}
+
+/// Store a document's vector in a dimension table (see [`dimension_table`])
+async fn upsert_table_embedding(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    table: &str,
+    doc_id: i32,
+    embedding: Vector,
+) -> Result<(), ServerError> {
+    sqlx::query(&format!(
+        "INSERT INTO {table} (doc_id, embedding) VALUES ($1, $2) \
+         ON CONFLICT (doc_id) DO UPDATE SET embedding = EXCLUDED.embedding"
+    ))
+    .bind(doc_id)
+    .bind(embedding)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| ServerError::Database(format!("Failed to insert embedding into {table}: {e}")))?;
+    Ok(())
+}
+
+/// Create and attach `crate_name`'s partition of `parent` unless it exists, moving the
+/// crate's rows out of [`DEFAULT_PARTITION`] first (an attached partition's values must
+/// not also sit in the default one)
+///
+/// The partition inherits the parent's indexes and gets its own HNSW index where pgvector
+/// supports the `embedding` column's dimension.
+async fn create_crate_partition(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    parent: &str,
+    crate_name: &str,
+) -> Result<(), ServerError> {
+    let partition = crate_partition_name(crate_name);
+    let map_err = |e: sqlx::Error| {
+        ServerError::Database(format!(
+            "Failed to create partition {partition} for {crate_name}: {e}"
+        ))
+    };
+    // Concurrent populations of the same crate would otherwise both try to attach it
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(&partition)
+        .execute(&mut **tx)
+        .await
+        .map_err(map_err)?;
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+        .bind(&partition)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(map_err)?;
+    if exists {
+        return Ok(());
+    }
+
+    sqlx::query(&format!(
+        "CREATE TABLE {partition} (LIKE {parent} INCLUDING DEFAULTS)"
+    ))
+    .execute(&mut **tx)
+    .await
+    .map_err(map_err)?;
+    // The rows live on in the new partition; keep delete_dimension_vectors off their vectors
+    sqlx::query("SET LOCAL mcpdocs.moving_rows = 'on'")
+        .execute(&mut **tx)
+        .await
+        .map_err(map_err)?;
+    sqlx::query(&format!(
+        r#"
+        WITH moved AS (DELETE FROM {DEFAULT_PARTITION} WHERE crate_name = $1 RETURNING *)
+        INSERT INTO {partition} SELECT * FROM moved
+        "#
+    ))
+    .bind(crate_name)
+    .execute(&mut **tx)
+    .await
+    .map_err(map_err)?;
+    sqlx::query("SET LOCAL mcpdocs.moving_rows = 'off'")
+        .execute(&mut **tx)
+        .await
+        .map_err(map_err)?;
+    sqlx::query(&format!(
+        "ALTER TABLE {parent} ATTACH PARTITION {partition} FOR VALUES IN ({})",
+        quote_literal(crate_name)
+    ))
+    .execute(&mut **tx)
+    .await
+    .map_err(map_err)?;
+
+    let dimension: Option<i32> = sqlx::query_scalar(
+        r#"
+        SELECT atttypmod FROM pg_attribute
+        WHERE attrelid = to_regclass($1) AND attname = 'embedding' AND NOT attisdropped
+        "#,
+    )
+    .bind(parent)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(map_err)?;
+    if dimension.is_some_and(|d| d > 0 && d as usize <= HNSW_MAX_DIMENSION) {
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS idx_{partition}_hnsw ON {partition} USING hnsw (embedding vector_cosine_ops)"
+        ))
+        .execute(&mut **tx)
+        .await
+        .map_err(map_err)?;
+    }
+    Ok(())
+}
+
+/// Row trigger standing in for the dimension tables' `ON DELETE CASCADE` once
+/// `doc_embeddings` is partitioned. An UPDATE moving a row to another crate's partition
+/// fires it too, so it only deletes vectors whose row is really gone. `create_crate_partition`
+/// moves rows out of the default partition by hand and sets `mcpdocs.moving_rows` to skip it.
+const DELETE_DIMENSION_VECTORS_SQL: &str = r#"
+    CREATE OR REPLACE FUNCTION delete_dimension_vectors() RETURNS trigger AS $$
+    DECLARE
+        vector_table text;
+    BEGIN
+        IF current_setting('mcpdocs.moving_rows', true) = 'on'
+            OR EXISTS (SELECT 1 FROM doc_embeddings WHERE id = OLD.id) THEN
+            RETURN OLD;
+        END IF;
+        FOR vector_table IN
+            SELECT table_name FROM information_schema.tables
+            WHERE table_schema = current_schema() AND table_name ~ '^doc_embeddings_[0-9]+$'
+        LOOP
+            EXECUTE format('DELETE FROM %I WHERE doc_id = $1', vector_table) USING OLD.id;
+        END LOOP;
+        RETURN OLD;
+    END;
+    $$ LANGUAGE plpgsql
+"#;
+
+/// Outcome of [`Database::partition_embeddings`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PartitionReport {
+    /// Crates that got their own partition
+    pub crates: usize,
+    /// Rows in the partitioned table after the swap
+    pub rows: u64,
+    /// Rows written during the copy and carried over while the swap held its lock
+    pub caught_up: u64,
+}
+
+/// Content storage usage, for judging what compression saves
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageReport {
+    pub compression_enabled: bool,
+    /// `pg_total_relation_size` of doc_embeddings and its partitions (includes vectors and indexes)
+    pub table_bytes: i64,
+    pub crates: Vec<CrateStorage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrateStorage {
+    pub crate_name: String,
+    pub total_docs: i64,
+    pub compressed_docs: i64,
+    /// Bytes of content as stored (compressed where applicable)
+    pub stored_bytes: i64,
+    /// Bytes the same content would take uncompressed
+    pub uncompressed_bytes: i64,
+}
+
+/// State of the text and vector search indexes on `doc_embeddings`
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexHealth {
+    /// Whether the `content_tsv` column has been added
+    pub has_tsv_column: bool,
+    /// GIN index over `content_tsv`, if present
+    pub text_index: Option<IndexStatus>,
+    /// HNSW/IVFFlat index over `embedding`, if present
+    pub vector_index: Option<IndexStatus>,
+    pub crates: Vec<CrateIndexHealth>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStatus {
+    pub name: String,
+    pub method: String,
+    /// False while a concurrent build is in progress or after it failed
+    pub valid: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrateIndexHealth {
+    pub crate_name: String,
+    pub total_docs: i64,
+    pub docs_with_tsv: i64,
+}
+
+#[allow(dead_code)] // Used by the HTTP server
+impl CrateIndexHealth {
+    /// Whether some rows would be invisible to text search
+    pub fn is_partially_indexed(&self) -> bool {
+        self.docs_with_tsv < self.total_docs
+    }
+}
+
+/// What [`Database::delete_orphaned_embeddings`] found (or removed)
+#[allow(dead_code)] // Used by the HTTP server
+#[derive(Debug)]
+pub struct OrphanReport {
+    /// Stored crate names without any configuration
+    pub crate_names: Vec<String>,
+    /// `doc_embeddings` rows belonging to those crates
+    pub embeddings: u64,
+    /// `crates` rows belonging to those crates
+    pub crates: u64,
+}
+
+/// How urgently an audit finding needs attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for AuditSeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AuditSeverity::Info => "info",
+            AuditSeverity::Warning => "warning",
+            AuditSeverity::Error => "error",
+        })
+    }
+}
+
+/// A fix that only rewrites derived data and is safe to apply unattended
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditFix {
+    /// Recompute `crates.total_docs`/`total_tokens` from the stored embeddings
+    RecomputeStats { crate_id: i32 },
+}
+
+/// One inconsistency found by [`Database::audit_consistency`]
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditFinding {
+    /// Which check produced it, e.g. `stats_mismatch`
+    pub check: &'static str,
+    pub severity: AuditSeverity,
+    pub crate_name: String,
+    pub detail: String,
+    /// SQL that resolves the finding; review it before running anything without `auto_fix`
+    pub fix_sql: String,
+    pub auto_fix: Option<AuditFix>,
+}
+
+/// Quote a string as a SQL literal for the `fix_sql` of audit findings
+fn sql_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Rows touched by [`Database::rename_crate`]
+#[allow(dead_code)] // Used by the HTTP server
+#[derive(Debug)]
+pub struct RenameReport {
+    pub embeddings: u64,
+    pub crates: u64,
+    pub configs: u64,
 }
 
 #[derive(Debug)]
 pub struct CrateStats {
     pub name: String,
     pub version: Option<String>,
+    #[allow(dead_code)] // Used by populate_db and backfill_versions
+    pub target: Option<String>,
     pub last_updated: chrono::NaiveDateTime,
     pub total_docs: i32,
     pub total_tokens: i32,
+    /// Oldest and newest document `last_updated_at`; far apart means a partial refresh
+    #[allow(dead_code)] // Used by populate_db
+    pub oldest_doc: Option<chrono::DateTime<chrono::Utc>>,
+    #[allow(dead_code)] // Used by populate_db
+    pub newest_doc: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -558,4 +5179,211 @@ pub struct CrateConfig {
     pub last_populated: Option<chrono::DateTime<chrono::Utc>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// CSS selector overriding the default rustdoc content selector
+    #[sqlx(default)]
+    pub content_selector: Option<String>,
+    /// Regexes a page URL must match to be crawled (empty = all pages)
+    #[sqlx(default)]
+    pub include_url_patterns: Vec<String>,
+    /// Regexes for page URLs that are never crawled
+    #[sqlx(default)]
+    pub exclude_url_patterns: Vec<String>,
+    /// Follow links into other crates this one re-exports (facade crates)
+    #[sqlx(default)]
+    pub follow_reexports: bool,
+    /// Crates that may be followed into (empty = any linked crate)
+    #[sqlx(default)]
+    pub reexport_crates: Vec<String>,
+    /// Total pages fetched from other crates (NULL = loader default)
+    #[sqlx(default)]
+    pub reexport_page_budget: Option<i32>,
+    /// Pages fetched from any single other crate (NULL = loader default)
+    #[sqlx(default)]
+    pub reexport_pages_per_crate: Option<i32>,
+    /// Populate from this rustdoc JSON file (optionally gzipped) instead of crawling docs.rs
+    #[sqlx(default)]
+    pub rustdoc_json_url: Option<String>,
+    /// docs.rs build target to document (NULL = docs.rs default target)
+    #[sqlx(default)]
+    pub target: Option<String>,
+    /// Replace `expected_docs` with the stored document count after the first population
+    #[sqlx(default)]
+    pub auto_expected_docs: bool,
+    /// Prepended to questions before embedding to disambiguate the crate's domain
+    #[sqlx(default)]
+    pub query_hint: Option<String>,
+    /// Results returned by `query_rust_docs` when the caller doesn't pass `limit`
+    #[sqlx(default)]
+    pub default_limit: Option<i32>,
+    /// Minimum similarity applied when the caller doesn't pass `min_similarity`
+    #[sqlx(default)]
+    pub default_min_similarity: Option<f32>,
+    /// Doc path prefix applied when the caller doesn't pass `path_prefix`
+    #[sqlx(default)]
+    pub default_path_prefix: Option<String>,
+    /// Most docs.rs pages crawled when populating (NULL = the populating tool's default)
+    #[sqlx(default)]
+    pub max_pages: Option<i32>,
+    /// Keep each crawled page's HTML in `doc_pages` for re-extraction without re-crawling
+    #[sqlx(default)]
+    pub store_raw_html: bool,
+    /// ISO 639-3 codes of the document languages kept (empty = keep everything)
+    #[sqlx(default)]
+    pub languages: Vec<String>,
+    /// `drop` (default) or `tag`: what happens to documents in other `languages`
+    #[sqlx(default)]
+    pub language_action: Option<String>,
+    /// Most chunks stored for this configuration (NULL = unlimited)
+    #[sqlx(default)]
+    pub max_embeddings: Option<i32>,
+    /// The last population stopped at `max_embeddings`, so the index is partial by design
+    #[sqlx(default)]
+    pub truncated: bool,
+    /// Longest one population may run before it stops and keeps what it stored (NULL = unlimited)
+    #[sqlx(default)]
+    pub max_duration_secs: Option<i32>,
+    /// When a population last stopped at its deadline; the next one resumes after the
+    /// chunks already stored. Cleared by a completed population
+    #[sqlx(default)]
+    pub partial_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The last population found almost no API documentation and stored the crate
+    /// README and root page text instead (archives written before the flag existed lack it)
+    #[sqlx(default)]
+    #[serde(default)]
+    pub low_doc_coverage: bool,
+    /// Tag chunks with the docblock section heading they come from (`Panics`, `Safety`, ...)
+    /// so queries can search one kind of section
+    #[sqlx(default)]
+    #[serde(default)]
+    pub section_metadata: bool,
+    /// Let `latest` and version requirements resolve to pre-releases (`1.0.0-rc.1`)
+    #[sqlx(default)]
+    #[serde(default)]
+    pub allow_prerelease: bool,
+}
+
+/// Coverage below this share of `expected_docs` is reported as under-populated
+pub const UNDER_POPULATED_COVERAGE: f64 = 0.8;
+
+#[allow(dead_code)] // Used by the population binaries
+impl CrateConfig {
+    /// A configuration of `name` at `version_spec` with every optional setting at its
+    /// default, ready for [`Database::upsert_crate_config`]
+    pub fn new(name: &str, version_spec: &str) -> Self {
+        Self {
+            id: 0, // Will be set by database
+            name: name.to_string(),
+            version_spec: version_spec.to_string(),
+            current_version: None,
+            features: Vec::new(),
+            expected_docs: 1000,
+            enabled: true,
+            last_checked: None,
+            last_populated: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            content_selector: None,
+            include_url_patterns: Vec::new(),
+            exclude_url_patterns: Vec::new(),
+            follow_reexports: false,
+            reexport_crates: Vec::new(),
+            reexport_page_budget: None,
+            reexport_pages_per_crate: None,
+            rustdoc_json_url: None,
+            target: None,
+            auto_expected_docs: true,
+            query_hint: None,
+            default_limit: None,
+            default_min_similarity: None,
+            default_path_prefix: None,
+            max_pages: None,
+            store_raw_html: false,
+            languages: Vec::new(),
+            language_action: None,
+            max_embeddings: None,
+            truncated: false,
+            max_duration_secs: None,
+            partial_at: None,
+            low_doc_coverage: false,
+            section_metadata: false,
+            allow_prerelease: false,
+        }
+    }
+
+    /// New `expected_docs` after a population that stored `stored_docs` documents, if the
+    /// baseline should be established now (first population with `auto_expected_docs`)
+    pub fn expected_docs_baseline(&self, stored_docs: usize) -> Option<i32> {
+        (self.auto_expected_docs && self.last_populated.is_none() && stored_docs > 0)
+            .then(|| i32::try_from(stored_docs).unwrap_or(i32::MAX))
+    }
+
+    /// Stored documents as a fraction of `expected_docs` (`None` without a baseline)
+    pub fn coverage(&self, stored_docs: usize) -> Option<f64> {
+        (self.expected_docs > 0).then(|| stored_docs as f64 / f64::from(self.expected_docs))
+    }
+
+    /// Whether `stored_docs` falls well short of an established baseline, e.g. after a
+    /// broken docs.rs build; the placeholder of a never-populated auto config doesn't count,
+    /// and neither does a crate cut short by its own `max_embeddings`
+    pub fn is_under_populated(&self, stored_docs: usize) -> bool {
+        !self.truncated
+            && self.established_expected_docs().is_some()
+            && self
+                .coverage(stored_docs)
+                .is_some_and(|coverage| coverage < UNDER_POPULATED_COVERAGE)
+    }
+
+    /// `expected_docs`, unless it is still the placeholder of a never-populated auto config
+    pub fn established_expected_docs(&self) -> Option<usize> {
+        let baseline_established = !self.auto_expected_docs || self.last_populated.is_some();
+        baseline_established
+            .then(|| usize::try_from(self.expected_docs).ok())
+            .flatten()
+    }
+
+    /// Crawl settings for populating this crate
+    pub fn crawl_options(&self) -> CrawlOptions {
+        CrawlOptions {
+            content_selector: self.content_selector.clone(),
+            include_url_patterns: self.include_url_patterns.clone(),
+            exclude_url_patterns: self.exclude_url_patterns.clone(),
+            follow_reexports: self.follow_reexports,
+            reexport_crates: self.reexport_crates.clone(),
+            reexport_page_budget: self.reexport_page_budget.map(|n| n.max(0) as usize),
+            reexport_pages_per_crate: self.reexport_pages_per_crate.map(|n| n.max(0) as usize),
+            target: self.target.clone(),
+            ..CrawlOptions::default()
+        }
+    }
+
+    /// Page limit for crawling this crate: its own `max_pages`, else `default`
+    pub fn max_pages_or(&self, default: usize) -> usize {
+        self.max_pages.map_or(default, |n| n.max(1) as usize)
+    }
+
+    /// Chunk limit for populating this crate, if it sets `max_embeddings`
+    pub fn embedding_limit(&self) -> Option<usize> {
+        self.max_embeddings.map(|n| n.max(1) as usize)
+    }
+
+    /// Time limit for populating this crate, if it sets `max_duration_secs`
+    pub fn max_duration(&self) -> Option<Duration> {
+        self.max_duration_secs
+            .map(|secs| Duration::from_secs(secs.max(1) as u64))
+    }
+
+    /// Whether the next population should skip the chunks already stored: the
+    /// configuration never finished populating, or its last population hit its deadline
+    pub fn resumes_population(&self) -> bool {
+        self.last_populated.is_none() || self.partial_at.is_some()
+    }
+
+    /// Search parameters `query_rust_docs` falls back to when the caller leaves them unset
+    pub fn search_defaults(&self) -> SearchParams {
+        SearchParams {
+            limit: self.default_limit.map(|n| n.max(1) as usize),
+            min_similarity: self.default_min_similarity,
+            path_prefix: self.default_path_prefix.clone(),
+        }
+    }
 }
@@ -0,0 +1,180 @@
+//! Circuit breaker for the HTTP server's background database access
+//!
+//! When Postgres restarts, background tasks (auto-population, the scheduled audit, the
+//! readiness probe) would otherwise retry at full speed and log every failure. After
+//! `failure_threshold` consecutive failures the breaker opens: attempts are spaced out
+//! with exponential backoff up to `max_backoff`, the outage and the recovery are each
+//! logged once, and the server reports itself degraded until a probe succeeds again.
+
+use crate::error::ServerError;
+use serde::Serialize;
+use std::{
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Consecutive failures that open the breaker unless configured otherwise
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+/// Longest wait between attempts while the database is unavailable, unless configured otherwise
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Wait after the failure that opens the breaker; it doubles with every further failure
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    /// While open, no attempt is made before this instant
+    next_attempt: Option<Instant>,
+    last_error: Option<String>,
+}
+
+/// What `get_server_stats` reports about the database
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BreakerStatus {
+    pub degraded: bool,
+    pub consecutive_failures: u32,
+    /// Seconds until the next attempt while degraded
+    pub retry_in_secs: Option<f64>,
+    pub last_error: Option<String>,
+}
+
+/// Tracks consecutive database failures of background tasks and spaces out their retries
+#[derive(Debug)]
+pub struct DbBreaker {
+    failure_threshold: u32,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    degraded: AtomicBool,
+    state: Mutex<BreakerState>,
+}
+
+impl Default for DbBreaker {
+    fn default() -> Self {
+        Self::new(DEFAULT_FAILURE_THRESHOLD, DEFAULT_MAX_BACKOFF)
+    }
+}
+
+impl DbBreaker {
+    /// Open after `failure_threshold` (at least 1) consecutive failures, waiting at most
+    /// `max_backoff` between attempts
+    pub fn new(failure_threshold: u32, max_backoff: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            base_backoff: BASE_BACKOFF.min(max_backoff),
+            max_backoff,
+            degraded: AtomicBool::new(false),
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Start backing off at `base_backoff` instead of one second
+    pub fn with_base_backoff(mut self, base_backoff: Duration) -> Self {
+        self.base_backoff = base_backoff.min(self.max_backoff);
+        self
+    }
+
+    /// Whether the database is considered unavailable
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    /// Wait before the next attempt after `consecutive_failures` failures in a row
+    pub fn backoff(&self, consecutive_failures: u32) -> Duration {
+        let doublings = consecutive_failures.saturating_sub(self.failure_threshold);
+        let factor = 2u32.saturating_pow(doublings.min(31));
+        self.base_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// How long until the next attempt is allowed, if one has to wait
+    pub fn retry_in(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .next_attempt
+            .map(|at| at.saturating_duration_since(Instant::now()))
+            .filter(|wait| !wait.is_zero())
+    }
+
+    /// Record a successful database call; returns whether this ended an outage
+    pub fn record_success(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let failures = std::mem::take(&mut *state).consecutive_failures;
+        let recovered = self.degraded.swap(false, Ordering::Relaxed);
+        if recovered {
+            info!("✅ Database is reachable again after {failures} failed attempts; resuming background work");
+        }
+        recovered
+    }
+
+    /// Record a failed database call; returns whether this started an outage
+    pub fn record_failure(&self, error: &impl fmt::Display) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        state.last_error = Some(error.to_string());
+        if state.consecutive_failures < self.failure_threshold {
+            return false;
+        }
+        let backoff = self.backoff(state.consecutive_failures);
+        state.next_attempt = Some(Instant::now() + backoff);
+        let tripped = !self.degraded.swap(true, Ordering::Relaxed);
+        if tripped {
+            warn!(
+                "🔌 Database unavailable after {} consecutive failures ({error}); background tasks back off up to {:?} and the server reports degraded",
+                state.consecutive_failures, self.max_backoff
+            );
+        }
+        tripped
+    }
+
+    /// Sleep until the next attempt is allowed
+    pub async fn wait(&self) {
+        while let Some(wait) = self.retry_in() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Sleep until the database is no longer considered unavailable
+    pub async fn wait_for_recovery(&self) {
+        while self.is_degraded() {
+            tokio::time::sleep(self.retry_in().unwrap_or(self.base_backoff)).await;
+        }
+    }
+
+    /// Run one database operation once the backoff allows it, recording its outcome
+    pub async fn call<T, F>(&self, operation: F) -> Result<T, ServerError>
+    where
+        F: Future<Output = Result<T, ServerError>>,
+    {
+        self.wait().await;
+        let result = operation.await;
+        match &result {
+            Ok(_) => {
+                self.record_success();
+            }
+            Err(e) => {
+                self.record_failure(e);
+            }
+        }
+        result
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let retry_in_secs = self.retry_in().map(|wait| wait.as_secs_f64());
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        BreakerStatus {
+            degraded: self.is_degraded(),
+            consecutive_failures: state.consecutive_failures,
+            retry_in_secs,
+            last_error: state.last_error.clone(),
+        }
+    }
+}
@@ -1,7 +1,14 @@
-use scraper::{Html, Selector};
-use std::collections::{HashSet, VecDeque};
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::time::Duration;
 use thiserror::Error;
+use tokio::sync::mpsc;
+use tracing::debug;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
 
 #[derive(Debug, Error)]
 #[allow(dead_code)] // Some variants are only used in specific contexts
@@ -16,6 +23,12 @@ pub enum DocLoaderError {
     Network(String),
     #[error("Rate limited: {0}")]
     RateLimited(String),
+    #[error("Invalid URL pattern: {0}")]
+    InvalidPattern(String),
+    #[error("Disallowed by robots.txt: {0}")]
+    RobotsDisallowed(String),
+    #[error("Unsupported format: {0}")]
+    UnsupportedFormat(String),
 }
 
 // Simple struct to hold document content
@@ -23,6 +36,18 @@ pub enum DocLoaderError {
 pub struct Document {
     pub path: String,
     pub content: String,
+    /// Heading of the docblock section the content comes from (`Panics`, `Safety`, ...)
+    #[allow(dead_code)] // Used by the population pipeline
+    pub section: Option<String>,
+}
+
+/// The HTML of one fetched page, for storing and re-extracting later
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // Used by the population pipeline
+pub struct RawPage {
+    /// Page URL relative to the docs origin, the prefix of its documents' paths
+    pub path: String,
+    pub html: String,
 }
 
 // Result struct that includes version information
@@ -31,44 +56,1045 @@ pub struct Document {
 pub struct LoadResult {
     pub documents: Vec<Document>,
     pub version: Option<String>,
+    /// Pages fetched from other crates per crate name, when following re-exports
+    pub reexport_pages: HashMap<String, usize>,
+    pub diagnostics: CrawlDiagnostics,
+}
+
+/// Everything a crawl produces besides the documents themselves
+#[derive(Debug, Default)]
+pub struct CrawlSummary {
+    pub version: Option<String>,
+    /// Pages fetched from other crates per crate name, when following re-exports
+    pub reexport_pages: HashMap<String, usize>,
+    pub diagnostics: CrawlDiagnostics,
+}
+
+/// What happened during a crawl, to explain a low document count
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CrawlDiagnostics {
+    /// Pages downloaded successfully
+    pub pages_fetched: usize,
+    /// Pages never requested, by reason (`filtered`, `robots`)
+    pub pages_skipped: BTreeMap<String, usize>,
+    /// Pages that could not be downloaded, by reason
+    /// (`not_found`, `client_error`, `server_error`, `rate_limited`, `network`)
+    pub pages_failed: BTreeMap<String, usize>,
+    /// HTTP 429 responses, including ones that succeeded on retry
+    pub rate_limited_responses: usize,
+    pub bytes_downloaded: u64,
+    /// Fetched pages where the content selector matched nothing
+    pub empty_pages: usize,
+    /// Empty pages that still linked to item pages, whose children were crawled anyway
+    pub index_only_pages: usize,
+    /// Whether the crawl stopped at `max_pages` with pages still queued
+    pub hit_max_pages: bool,
+    /// Most pages waiting in the crawl queue at once
+    pub peak_queue_size: usize,
+    /// Neither the docs.rs redirect nor the page revealed the crate version, so the
+    /// populated crate has no version recorded
+    pub version_missing: bool,
+    /// Documents by detected language (ISO 639-3, `unknown` when not reliable); only
+    /// counted for crates that set `languages`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub documents_by_language: BTreeMap<String, usize>,
+    /// Documents not embedded because the crate doesn't keep their language
+    pub language_dropped: usize,
+    /// Chunks below the minimum size merged into a neighbouring chunk of their page
+    pub small_chunks_merged: usize,
+    /// Chunks below the minimum size dropped because no neighbour could take them
+    pub small_chunks_dropped: usize,
+    /// Whether population stopped at the crate's `max_embeddings` with documents still
+    /// coming; the chunks stored are the crawl's earliest (shallowest, breadth-first)
+    pub hit_max_embeddings: bool,
+    /// Whether the crawl or population stopped at its `max_duration` deadline
+    pub hit_deadline: bool,
+    /// Fewer than [`LOW_DOC_COVERAGE_THRESHOLD`] documents came out of the docblocks
+    /// (macro-only and proc-macro crates), so the crate README and the root page's
+    /// full text were added instead
+    pub low_doc_coverage: bool,
+    /// Every URL the crawl considered with what became of it; stored apart from the
+    /// diagnostics, since it grows with the crate
+    #[serde(skip)]
+    pub trace: CrawlTrace,
+}
+
+impl CrawlDiagnostics {
+    fn skip(&mut self, url: &str, reason: &str) {
+        *self.pages_skipped.entry(reason.to_string()).or_default() += 1;
+        let outcome = match reason {
+            "robots" => TraceOutcome::SkippedRobots,
+            _ => TraceOutcome::SkippedFilter,
+        };
+        self.trace.record(url, outcome, None);
+    }
+
+    fn fail(&mut self, url: &str, reason: &str) {
+        *self.pages_failed.entry(reason.to_string()).or_default() += 1;
+        match reason {
+            "not_found" => self.trace.record(url, TraceOutcome::NotFound, None),
+            _ => self.trace.record(url, TraceOutcome::Error, Some(reason)),
+        }
+    }
+}
+
+/// Most URLs a [`CrawlTrace`] keeps; later ones are only counted
+pub const MAX_TRACE_ENTRIES: usize = 10_000;
+
+/// What became of one URL the crawler considered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceOutcome {
+    /// Downloaded, and content was extracted from it (`links_only` when the include
+    /// patterns don't keep it and it was only fetched for its links)
+    Fetched,
+    /// Downloaded, but the content selector matched nothing
+    Empty,
+    /// Not requested: not a documentation page of the crate, or excluded by the crate's
+    /// URL patterns
+    SkippedFilter,
+    /// Not requested: disallowed by robots.txt
+    SkippedRobots,
+    /// HTTP 404
+    NotFound,
+    /// Any other failure, named by the entry's `detail` (`client_error`, `server_error`,
+    /// `rate_limited`, `network`)
+    Error,
+}
+
+#[allow(dead_code)] // Used by the HTTP server
+impl TraceOutcome {
+    pub const ALL: [TraceOutcome; 6] = [
+        TraceOutcome::Fetched,
+        TraceOutcome::Empty,
+        TraceOutcome::SkippedFilter,
+        TraceOutcome::SkippedRobots,
+        TraceOutcome::NotFound,
+        TraceOutcome::Error,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TraceOutcome::Fetched => "fetched",
+            TraceOutcome::Empty => "empty",
+            TraceOutcome::SkippedFilter => "skipped_filter",
+            TraceOutcome::SkippedRobots => "skipped_robots",
+            TraceOutcome::NotFound => "not_found",
+            TraceOutcome::Error => "error",
+        }
+    }
+
+    /// The outcome named `name`, as written by [`TraceOutcome::as_str`]
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|outcome| outcome.as_str() == name)
+    }
+}
+
+/// One URL of a [`CrawlTrace`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub url: String,
+    pub outcome: TraceOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// The URLs a crawl considered, in the order it considered them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CrawlTrace {
+    pub entries: Vec<TraceEntry>,
+    /// URLs past [`MAX_TRACE_ENTRIES`], left out of `entries`
+    #[serde(default)]
+    pub dropped: usize,
+}
+
+impl CrawlTrace {
+    fn record(&mut self, url: &str, outcome: TraceOutcome, detail: Option<&str>) {
+        if self.entries.len() >= MAX_TRACE_ENTRIES {
+            self.dropped += 1;
+            return;
+        }
+        self.entries.push(TraceEntry {
+            url: url.to_string(),
+            outcome,
+            detail: detail.map(str::to_string),
+        });
+    }
+
+    /// Entries per outcome
+    #[allow(dead_code)] // Used by the HTTP server
+    pub fn counts(&self) -> BTreeMap<TraceOutcome, usize> {
+        let mut counts = BTreeMap::new();
+        for entry in &self.entries {
+            *counts.entry(entry.outcome).or_default() += 1;
+        }
+        counts
+    }
+}
+
+impl std::fmt::Display for CrawlDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let failed: usize = self.pages_failed.values().sum();
+        let skipped: usize = self.pages_skipped.values().sum();
+        write!(
+            f,
+            "{} pages fetched ({:.1} KB), {} empty, {failed} failed {:?}, {skipped} skipped {:?}, {} rate-limited responses",
+            self.pages_fetched,
+            self.bytes_downloaded as f64 / 1024.0,
+            self.empty_pages,
+            self.pages_failed,
+            self.pages_skipped,
+            self.rate_limited_responses
+        )?;
+        if self.index_only_pages > 0 {
+            write!(f, ", {} index-only pages traversed", self.index_only_pages)?;
+        }
+        if self.peak_queue_size > 0 {
+            write!(f, ", peak queue {}", self.peak_queue_size)?;
+        }
+        if self.hit_max_pages {
+            write!(f, ", stopped at max pages")?;
+        }
+        if self.hit_max_embeddings {
+            write!(f, ", stopped at max embeddings")?;
+        }
+        if self.hit_deadline {
+            write!(f, ", stopped at the time limit")?;
+        }
+        if self.version_missing {
+            write!(f, ", version unknown")?;
+        }
+        if self.low_doc_coverage {
+            write!(f, ", low doc coverage (README fallback)")?;
+        }
+        if self.small_chunks_merged + self.small_chunks_dropped > 0 {
+            write!(
+                f,
+                ", {} small chunks merged ({} dropped)",
+                self.small_chunks_merged, self.small_chunks_dropped
+            )?;
+        }
+        if !self.documents_by_language.is_empty() {
+            write!(
+                f,
+                ", documents by language {:?} ({} dropped)",
+                self.documents_by_language, self.language_dropped
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Selector for rustdoc's main content blocks, used when a crate config has no override
+pub const DEFAULT_CONTENT_SELECTOR: &str = "div.docblock, section.docblock, .rustdoc .docblock";
+
+/// A crawl extracting fewer documents than this falls back to the crate README and the
+/// root page's full text
+pub const LOW_DOC_COVERAGE_THRESHOLD: usize = 3;
+
+/// Shown with query results and status of crates populated from the README fallback
+pub const LOW_DOC_COVERAGE_NOTE: &str =
+    "Note: this crate has minimal API documentation; answers are based on its README.";
+
+/// Broader selectors for a crate root page without docblocks, tried in order
+const ROOT_TEXT_SELECTORS: &[&str] = &["#main-content", "main", "body"];
+
+/// Where the docs.rs crate page (`/crate/<name>/<version>`) renders the README
+const README_SELECTOR: &str = "#main, .package-details";
+
+/// Per-crate crawl settings taken from the crate configuration
+#[derive(Debug, Clone, Default)]
+pub struct CrawlOptions {
+    /// CSS selector for content blocks (defaults to [`DEFAULT_CONTENT_SELECTOR`])
+    pub content_selector: Option<String>,
+    /// Regexes a page URL must match (any of) for its documents to be kept; empty keeps
+    /// all. Module index pages are still crawled for their links
+    pub include_url_patterns: Vec<String>,
+    /// Regexes that exclude a page URL from crawling
+    pub exclude_url_patterns: Vec<String>,
+    /// Follow links into other crates on docs.rs (for facade crates that re-export)
+    pub follow_reexports: bool,
+    /// Crates that may be followed into; empty allows any crate linked from this one
+    pub reexport_crates: Vec<String>,
+    /// Total pages fetched from other crates (default: [`DEFAULT_REEXPORT_PAGE_BUDGET`])
+    pub reexport_page_budget: Option<usize>,
+    /// Pages fetched from any single other crate (default: [`DEFAULT_REEXPORT_PAGES_PER_CRATE`])
+    pub reexport_pages_per_crate: Option<usize>,
+    /// Crawl even where the docs host's robots.txt disallows it
+    pub ignore_robots: bool,
+    /// Docs host to crawl instead of [`DOCS_RS_ORIGIN`] (mirrors, tests)
+    pub docs_origin: Option<String>,
+    /// docs.rs build target to crawl (e.g. `x86_64-pc-windows-msvc`); `None` is the default target
+    pub target: Option<String>,
+    /// Concrete version to crawl, such as a resolved `version_spec`; `None` crawls `latest`
+    pub version: Option<String>,
+    /// New links queued from any one page; `None` queues every qualifying link
+    pub max_links_per_page: Option<usize>,
+    /// Order in which queued pages are visited
+    pub strategy: CrawlStrategy,
+    /// Share of `max_pages` whose links are followed (default: [`DEFAULT_FOLLOW_LINKS_FRACTION`]);
+    /// past it only index-only pages hand over their item links
+    pub follow_links_fraction: Option<f64>,
+    /// Store pages as decoded, without [`repair_mojibake`]
+    pub keep_mojibake: bool,
+    /// Stop fetching once this time passes, keeping what was crawled (time-boxed populations)
+    pub deadline: Option<std::time::Instant>,
+}
+
+/// Order in which the crawler visits queued pages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CrawlStrategy {
+    /// Visit pages in the order they were found, covering each level before going deeper
+    #[default]
+    BreadthFirst,
+    /// Visit the most recently found pages first, following one module down before the next
+    DepthFirst,
+}
+
+impl std::fmt::Display for CrawlStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CrawlStrategy::BreadthFirst => "breadth-first",
+            CrawlStrategy::DepthFirst => "depth-first",
+        })
+    }
+}
+
+/// Share of `max_pages` whose links are followed unless a crawl overrides it
+pub const DEFAULT_FOLLOW_LINKS_FRACTION: f64 = 0.75;
+
+/// Origin crawled for crate documentation unless overridden
+pub const DOCS_RS_ORIGIN: &str = "https://docs.rs";
+
+/// Minimum pause between page fetches, raised to the host's robots.txt crawl-delay
+pub const PAGE_DELAY: Duration = Duration::from_millis(500);
+
+pub const DEFAULT_REEXPORT_PAGE_BUDGET: usize = 500;
+pub const DEFAULT_REEXPORT_PAGES_PER_CRATE: usize = 100;
+
+/// Concrete version segment of a docs URL (`https://docs.rs/<crate>/<version>/...`)
+///
+/// docs.rs redirects `/<crate>/latest/` to the released version, so the final URL of
+/// the first request names the version being crawled. Only full versions count, so
+/// `latest` and requirements such as `^1.2` yield `None`.
+pub fn version_from_docs_url(url: &str, origin_prefix: &str) -> Option<String> {
+    let version = url.strip_prefix(origin_prefix)?.split('/').nth(1)?;
+    semver::Version::parse(version)
+        .ok()
+        .map(|_| version.to_string())
+}
+
+/// First full version in text such as the docs.rs header (`demo 1.2.3`, `v1.2.3`)
+pub fn version_from_text(text: &str) -> Option<String> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ','))
+        .map(|word| word.trim_start_matches('v'))
+        .find(|word| semver::Version::parse(word).is_ok())
+        .map(str::to_string)
+}
+
+/// URL of a crate's docs root at `version` (`None` = `latest`)
+///
+/// docs.rs serves non-default targets under `/<crate>/<version>/<target>/<crate>/`.
+pub fn crate_root_url(
+    origin_prefix: &str,
+    crate_name: &str,
+    version: Option<&str>,
+    target: Option<&str>,
+) -> Result<String, DocLoaderError> {
+    let version = match version {
+        Some(version) => semver::Version::parse(version)
+            .map_err(|e| {
+                DocLoaderError::Parsing(format!("Invalid crate version '{version}': {e}"))
+            })?
+            .to_string(),
+        None => "latest".to_string(),
+    };
+    Ok(match target {
+        Some(target) => {
+            validate_target(target)?;
+            format!("{origin_prefix}{crate_name}/{version}/{target}/{crate_name}/")
+        }
+        None => format!("{origin_prefix}{crate_name}/{version}/{crate_name}/"),
+    })
+}
+
+/// Version the docs host currently serves as a crate's `latest`, without crawling
+///
+/// One HEAD request to the crate root: `/<crate>/latest/` redirects to the released
+/// version, whose URL names it. `None` when the host doesn't redirect to a version.
+#[allow(dead_code)] // Used by the HTTP server
+pub async fn fetch_latest_version(
+    crate_name: &str,
+    options: &CrawlOptions,
+) -> Result<Option<String>, DocLoaderError> {
+    let origin = options
+        .docs_origin
+        .as_deref()
+        .unwrap_or(DOCS_RS_ORIGIN)
+        .trim_end_matches('/');
+    let origin_prefix = format!("{origin}/");
+    let url = crate_root_url(&origin_prefix, crate_name, None, options.target.as_deref())?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .user_agent(crate::robots::user_agent())
+        .build()
+        .map_err(|e| DocLoaderError::Network(e.to_string()))?;
+    let response = client
+        .head(&url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| DocLoaderError::Network(format!("{url}: {e}")))?;
+    Ok(version_from_docs_url(
+        response.url().as_str(),
+        &origin_prefix,
+    ))
+}
+
+/// Crate name segment of a docs URL (`https://docs.rs/<crate>/<version>/...`)
+fn docs_rs_crate<'a>(url: &'a str, origin_prefix: &str) -> Option<&'a str> {
+    url.strip_prefix(origin_prefix)?
+        .split('/')
+        .next()
+        .filter(|segment| !segment.is_empty())
+}
+
+/// Check a target triple before it is spliced into docs.rs URLs
+pub fn validate_target(target: &str) -> Result<(), DocLoaderError> {
+    let valid = !target.is_empty()
+        && target
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && target.contains('-');
+    if valid {
+        Ok(())
+    } else {
+        Err(DocLoaderError::Parsing(format!(
+            "Invalid target '{target}': expected a target triple like 'x86_64-pc-windows-msvc'"
+        )))
+    }
+}
+
+/// Crate names compare equal regardless of `-`/`_`, as cargo treats them
+fn same_crate(a: &str, b: &str) -> bool {
+    a.replace('-', "_") == b.replace('-', "_")
+}
+
+/// Compiled include/exclude URL filters for a crawl
+#[derive(Debug, Default)]
+pub struct UrlFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl UrlFilter {
+    /// Compile the patterns, rejecting the first one that isn't a valid regex
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, DocLoaderError> {
+        fn compile(patterns: &[String]) -> Result<Vec<Regex>, DocLoaderError> {
+            patterns
+                .iter()
+                .map(|p| {
+                    Regex::new(p).map_err(|e| DocLoaderError::InvalidPattern(format!("'{p}': {e}")))
+                })
+                .collect()
+        }
+
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// Whether a URL passes the filters (exclusions win over inclusions), i.e. whether
+    /// its documents are kept
+    pub fn allows(&self, url: &str) -> bool {
+        if self.exclude.iter().any(|re| re.is_match(url)) {
+            return false;
+        }
+        self.include.is_empty() || self.include.iter().any(|re| re.is_match(url))
+    }
+
+    /// Whether a crawl may fetch a URL: anything not excluded that is either kept or a
+    /// module index page, since included pages deeper down are only linked from those
+    pub fn allows_visit(&self, url: &str) -> bool {
+        self.allows(url)
+            || (url.ends_with("/index.html") && !self.exclude.iter().any(|re| re.is_match(url)))
+    }
+}
+
+/// Parse a content selector override, falling back to the default rustdoc selector
+pub fn parse_content_selector(selector: Option<&str>) -> Result<Selector, DocLoaderError> {
+    let selector = selector
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or(DEFAULT_CONTENT_SELECTOR);
+    Selector::parse(selector)
+        .map_err(|e| DocLoaderError::Selector(format!("Invalid selector '{selector}': {e}")))
+}
+
+/// Elements whose `id` marks the start of a linkable section on a rustdoc page
+const ANCHOR_SELECTOR: &str = "h1[id], h2[id], h3[id], h4[id], h5[id], h6[id], \
+     section.impl[id], section.method[id], section.variant[id], \
+     section.associatedtype[id], section.associatedconstant[id], .structfield[id]";
+
+/// rustdoc UI chrome that can appear inside content blocks and carries no documentation
+const BOILERPLATE_SELECTOR: &str = "a.doc-anchor, a.anchor, a.test-arrow, a.src, .src-link, \
+     button, .copy-path, .tooltip, .notable-traits-tooltip, script, style, noscript, \
+     #help, #settings, rustdoc-toolbar, rustdoc-search";
+
+/// Whole lines left behind by rustdoc UI elements (anchors, buttons, toggles, help text)
+const BOILERPLATE_LINES: &[&str] = &[
+    "§",
+    "Run",
+    "source",
+    "Source",
+    "ⓘ",
+    "Copy item path",
+    "Expand description",
+    "Collapse description",
+    "Show all",
+    "Hide all",
+    "Keyboard Shortcuts",
+    "Search Tricks",
+];
+
+/// The `charset=` label in a `Content-Type` value or a `<meta>` tag, if any
+fn charset_label(text: &str) -> Option<&str> {
+    let start = text.to_ascii_lowercase().find("charset=")? + "charset=".len();
+    let label = text[start..].trim_start_matches(['"', '\'', ' ']);
+    let end = label
+        .find(|c: char| matches!(c, '"' | '\'' | ';' | '>' | '/') || c.is_whitespace())
+        .unwrap_or(label.len());
+    Some(&label[..end]).filter(|label| !label.is_empty())
+}
+
+/// Encoding of an HTML page: the `Content-Type` charset, else a `<meta>` charset in the
+/// first 1024 bytes, else UTF-8. A byte order mark overrides both when decoding.
+pub fn html_encoding(body: &[u8], content_type: Option<&str>) -> &'static Encoding {
+    let for_label = |label: &str| Encoding::for_label(label.as_bytes());
+    content_type
+        .and_then(charset_label)
+        .and_then(for_label)
+        .or_else(|| {
+            // Labels are ASCII, so a lossy look at the head is enough to find one
+            let head = String::from_utf8_lossy(&body[..body.len().min(1024)]);
+            charset_label(&head).and_then(for_label)
+        })
+        // A page can't really be UTF-16 if its charset was readable as ASCII
+        .filter(|encoding| encoding.is_ascii_compatible())
+        .unwrap_or(UTF_8)
+}
+
+/// Decode a page body to clean UTF-8 using [`html_encoding`]
+///
+/// Byte sequences invalid in the encoding and stray control characters are dropped
+/// rather than kept as U+FFFD, so they never reach the stored text.
+pub fn decode_html(body: &[u8], content_type: Option<&str>) -> String {
+    let (text, _, _) = html_encoding(body, content_type).decode(body);
+    text.chars()
+        .filter(|&c| c != '\u{fffd}' && (!c.is_control() || matches!(c, '\n' | '\r' | '\t')))
+        .collect()
+}
+
+/// Undo UTF-8 text that was decoded as Windows-1252 somewhere upstream, e.g. `donâ€™t`
+///
+/// Each run of non-ASCII characters is re-encoded as Windows-1252 and kept re-read as
+/// UTF-8 only if that is valid, so genuine accented text is left alone.
+pub fn repair_mojibake(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let mut repaired = String::with_capacity(text.len());
+    let mut changed = false;
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| !c.is_ascii()) {
+        let (ascii, run) = rest.split_at(start);
+        let end = run.find(|c: char| c.is_ascii()).unwrap_or(run.len());
+        let (run, tail) = run.split_at(end);
+        repaired.push_str(ascii);
+        match reread_as_utf8(run) {
+            Some(fixed) => {
+                repaired.push_str(&fixed);
+                changed = true;
+            }
+            None => repaired.push_str(run),
+        }
+        rest = tail;
+    }
+    if !changed {
+        return Cow::Borrowed(text);
+    }
+    repaired.push_str(rest);
+    Cow::Owned(repaired)
+}
+
+/// `run` as the UTF-8 it was before being mis-decoded as Windows-1252, if it was
+fn reread_as_utf8(run: &str) -> Option<String> {
+    let (bytes, _, unmappable) = WINDOWS_1252.encode(run);
+    if unmappable {
+        return None;
+    }
+    let fixed = std::str::from_utf8(&bytes).ok()?;
+    (fixed != run).then(|| fixed.to_string())
+}
+
+/// UTF-8 punctuation decoded as Windows-1252, plus typographic punctuation, mapped to ASCII
+const PUNCTUATION_REPLACEMENTS: &[(&str, &str)] = &[
+    ("â€™", "'"),
+    ("â€˜", "'"),
+    ("â€œ", "\""),
+    ("â€\u{9d}", "\""),
+    ("â€”", "-"),
+    ("â€“", "-"),
+    ("â€¦", "..."),
+    ("Â\u{a0}", " "),
+    ("Â§", ""),
+    ("\u{2018}", "'"),
+    ("\u{2019}", "'"),
+    ("\u{201c}", "\""),
+    ("\u{201d}", "\""),
+    ("\u{2013}", "-"),
+    ("\u{2014}", "-"),
+    ("\u{2026}", "..."),
+    ("\u{a0}", " "),
+    ("\u{200b}", ""),
+];
+
+/// Remove rustdoc UI boilerplate from extracted text, collapse repeated whitespace
+/// and normalize punctuation (including mojibake) to ASCII.
+///
+/// Text is brought to Unicode NFC first, so a precomposed `é` and `e` + combining accent
+/// store, tokenize and match alike. Idempotent, so it can be re-applied to content stored
+/// by older extractors.
+pub fn clean_content(text: &str) -> String {
+    let mut normalized = to_nfc(text);
+    for (from, to) in PUNCTUATION_REPLACEMENTS {
+        if normalized.contains(from) {
+            normalized = normalized.replace(from, to);
+        }
+    }
+
+    let mut cleaned = String::with_capacity(normalized.len());
+    let mut blank_run = 0;
+    for line in normalized.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        let line = line.strip_prefix("§ ").unwrap_or(&line);
+        let line = line.strip_prefix('§').unwrap_or(line);
+
+        if BOILERPLATE_LINES.contains(&line) {
+            continue;
+        }
+        if line.is_empty() {
+            blank_run += 1;
+            // Keep single blank lines (block separators) but drop leading/repeated ones
+            if blank_run > 1 || cleaned.is_empty() {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+
+        if !cleaned.is_empty() {
+            cleaned.push('\n');
+        }
+        cleaned.push_str(line);
+    }
+
+    cleaned.trim_end().to_string()
+}
+
+/// `text` in Unicode Normalization Form C
+pub fn to_nfc(text: &str) -> String {
+    if is_nfc(text) {
+        text.to_string()
+    } else {
+        text.nfc().collect()
+    }
+}
+
+/// Extracted content sharing the same nearest preceding heading anchor
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageSection {
+    /// `id` of the nearest preceding heading, or `None` before the first one
+    pub anchor: Option<String>,
+    /// Text of that heading when it is a heading inside a content block, such as a
+    /// docblock's `Examples` or `Panics`; subheadings keep their parent's section
+    pub section: Option<String>,
+    pub content: String,
+    /// Number of content blocks (or parts of blocks split at a heading) merged into this section
+    pub blocks: usize,
+}
+
+/// Extract content blocks in document order, grouped by the `id` of the nearest
+/// preceding heading so results can deep-link into the page. Headings inside a
+/// content block split it, so a long module docblock yields one section per heading,
+/// tagged with the heading's text.
+pub fn extract_sections(document: &Html, content_selector: &Selector) -> Vec<PageSection> {
+    let mut extractor = SectionExtractor {
+        content_selector,
+        anchor_selector: Selector::parse(ANCHOR_SELECTOR).expect("valid anchor selector"),
+        boilerplate_selector: Selector::parse(BOILERPLATE_SELECTOR)
+            .expect("valid boilerplate selector"),
+        anchor: None,
+        section: None,
+        lines: Vec::new(),
+        sections: Vec::new(),
+    };
+    extractor.walk(document.root_element(), false);
+    extractor.flush();
+    extractor.sections
+}
+
+struct SectionExtractor<'s> {
+    content_selector: &'s Selector,
+    anchor_selector: Selector,
+    boilerplate_selector: Selector,
+    anchor: Option<String>,
+    /// Heading text and level of the content block section the current anchor starts
+    section: Option<(String, u8)>,
+    /// Text lines collected for the current anchor but not yet added to a section
+    lines: Vec<String>,
+    sections: Vec<PageSection>,
+}
+
+impl SectionExtractor<'_> {
+    fn walk(&mut self, element: ElementRef<'_>, inside_content: bool) {
+        if self.boilerplate_selector.matches(&element) {
+            return;
+        }
+
+        if self.anchor_selector.matches(&element) {
+            self.flush();
+            self.anchor = element.value().id().map(str::to_string);
+            self.section = match (inside_content, heading_level(&element)) {
+                // A subheading (`Basic usage` under `Examples`) stays in its parent section
+                (true, Some(level)) => match self.section.take() {
+                    Some((parent, parent_level)) if parent_level < level => {
+                        Some((parent, parent_level))
+                    }
+                    _ => Some((heading_text(&element), level)).filter(|(text, _)| !text.is_empty()),
+                },
+                _ => None,
+            };
+        }
+
+        let starts_content = !inside_content && self.content_selector.matches(&element);
+        let inside_content = inside_content || starts_content;
+        if starts_content {
+            // Heading levels are only comparable within one block
+            self.section = self.section.take().map(|(text, _)| (text, u8::MAX));
+        }
+
+        for child in element.children() {
+            if let Some(child_element) = ElementRef::wrap(child) {
+                self.walk(child_element, inside_content);
+            } else if let Some(text) = child.value().as_text() {
+                let text = text.trim();
+                if inside_content && !text.is_empty() {
+                    self.lines.push(text.to_string());
+                }
+            }
+        }
+
+        if starts_content {
+            self.flush();
+        }
+    }
+
+    /// Move collected lines into the section for the current anchor
+    fn flush(&mut self) {
+        if self.lines.is_empty() {
+            return;
+        }
+        let text = clean_content(&self.lines.join("\n"));
+        self.lines.clear();
+        if text.is_empty() {
+            return;
+        }
+
+        let section = self.section.as_ref().map(|(text, _)| text.clone());
+        match self.sections.last_mut() {
+            Some(last) if last.anchor == self.anchor => {
+                last.content.push_str("\n\n");
+                last.content.push_str(&text);
+                last.blocks += 1;
+            }
+            _ => self.sections.push(PageSection {
+                anchor: self.anchor.clone(),
+                section,
+                content: text,
+                blocks: 1,
+            }),
+        }
+    }
+}
+
+/// The documents of one page: one per section, with the section's anchor appended to
+/// `relative_path` as `#anchor`
+pub fn page_documents(
+    relative_path: &str,
+    document: &Html,
+    content_selector: &Selector,
+) -> Vec<Document> {
+    extract_sections(document, content_selector)
+        .into_iter()
+        .map(|section| Document {
+            path: match &section.anchor {
+                Some(anchor) => format!("{relative_path}#{anchor}"),
+                None => relative_path.to_string(),
+            },
+            content: section.content,
+            section: section.section,
+        })
+        .collect()
+}
+
+/// `1`-`6` for `h1`-`h6`, `None` for other elements
+fn heading_level(element: &ElementRef<'_>) -> Option<u8> {
+    match element.value().name().as_bytes() {
+        [b'h', level @ b'1'..=b'6'] => Some(level - b'0'),
+        _ => None,
+    }
+}
+
+/// A heading's text without rustdoc's `§` anchor link, whitespace collapsed
+fn heading_text(element: &ElementRef<'_>) -> String {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .filter(|word| *word != "§")
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The documents of a page without docblocks, from the first of [`ROOT_TEXT_SELECTORS`]
+/// that finds any text
+pub fn full_page_documents(relative_path: &str, document: &Html) -> Vec<Document> {
+    ROOT_TEXT_SELECTORS
+        .iter()
+        .map(|selector| {
+            let selector = Selector::parse(selector).expect("valid root text selector");
+            page_documents(relative_path, document, &selector)
+        })
+        .find(|docs| !docs.is_empty())
+        .unwrap_or_default()
+}
+
+/// The README of a docs.rs crate page as one document at `relative_path`
+///
+/// Rendered READMEs carry their heading ids on anchor links rather than the headings, so
+/// sections would only pick up the page title's anchor; the chunker splits long READMEs.
+pub fn readme_document(relative_path: &str, document: &Html) -> Option<Document> {
+    let selector = Selector::parse(README_SELECTOR).expect("valid README selector");
+    let content = extract_sections(document, &selector)
+        .into_iter()
+        .map(|section| section.content)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    (!content.is_empty()).then(|| Document {
+        path: relative_path.to_string(),
+        content,
+        section: None,
+    })
+}
+
+/// rustdoc file name prefixes of item pages (`struct.Foo.html`, `fn.bar.html`, ...)
+const ITEM_PAGE_PREFIXES: &[&str] = &[
+    "struct.",
+    "enum.",
+    "union.",
+    "trait.",
+    "traitalias.",
+    "fn.",
+    "macro.",
+    "type.",
+    "constant.",
+    "static.",
+    "attr.",
+    "derive.",
+];
+
+/// Whether `href` points at a child item page: an item below the current page, or a
+/// submodule's `index.html`
+pub fn is_item_page_link(href: &str) -> bool {
+    let href = href.split(['#', '?']).next().unwrap_or_default();
+    if href.is_empty() || href.starts_with("../") || href.contains("://") || href.starts_with('/') {
+        return false;
+    }
+    let file = href.rsplit('/').next().unwrap_or(href);
+    if file == "index.html" {
+        return href.contains('/');
+    }
+    file.ends_with(".html") && ITEM_PAGE_PREFIXES.iter().any(|p| file.starts_with(p))
 }
 
 /// Load documentation from docs.rs for a given crate
 #[allow(dead_code)] // Used by binaries
 pub async fn load_documents_from_docs_rs(
+    crate_name: &str,
+    version: &str,
+    features: Option<&Vec<String>>,
+    max_pages: Option<usize>,
+    options: &CrawlOptions,
+) -> Result<LoadResult, DocLoaderError> {
+    let (tx, mut rx) = mpsc::channel(64);
+    let collect = async {
+        let mut documents = Vec::new();
+        while let Some(doc) = rx.recv().await {
+            documents.push(doc);
+        }
+        documents
+    };
+    let (summary, documents) = tokio::join!(
+        crawl_docs_rs(crate_name, version, features, max_pages, options, tx, None),
+        collect
+    );
+    let summary = summary?;
+    Ok(LoadResult {
+        documents,
+        version: summary.version,
+        reexport_pages: summary.reexport_pages,
+        diagnostics: summary.diagnostics,
+    })
+}
+
+/// Crawl docs.rs for a given crate, sending each document as soon as its page is parsed
+///
+/// Sending waits while `documents` is full, so a slow consumer slows the crawl down
+/// instead of letting pages pile up in memory. The crawl stops early if the receiver
+/// is dropped. With `pages`, the HTML of every kept page is sent there as well, before
+/// its documents; a dropped `pages` receiver doesn't stop the crawl.
+pub async fn crawl_docs_rs(
     crate_name: &str,
     _version: &str,
     _features: Option<&Vec<String>>,
     max_pages: Option<usize>,
-) -> Result<LoadResult, DocLoaderError> {
-    println!("Fetching documentation from docs.rs for crate: {crate_name}");
+    options: &CrawlOptions,
+    documents: mpsc::Sender<Document>,
+    pages: Option<mpsc::Sender<RawPage>>,
+) -> Result<CrawlSummary, DocLoaderError> {
+    let origin = options
+        .docs_origin
+        .as_deref()
+        .unwrap_or(DOCS_RS_ORIGIN)
+        .trim_end_matches('/');
+    let origin_prefix = format!("{origin}/");
+    println!("Fetching documentation from {origin} for crate: {crate_name}");
 
-    let base_url = format!("https://docs.rs/{crate_name}/latest/{crate_name}/");
+    if let Some(target) = &options.target {
+        println!("Using docs.rs target: {target}");
+    }
+    let base_url = crate_root_url(
+        &origin_prefix,
+        crate_name,
+        options.version.as_deref(),
+        options.target.as_deref(),
+    )?;
+    if let Some(version) = &options.version {
+        println!("Crawling version {version}");
+    }
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(30))
+        .user_agent(crate::robots::user_agent())
         .build()
         .map_err(|e| DocLoaderError::Network(e.to_string()))?;
 
-    let mut documents = Vec::new();
+    // robots.txt is always consulted so a disallowed crawl is reported even when ignored
+    let robots = crate::robots::fetch_robots(&client, origin).await;
+    let robots_allows = |url: &str| {
+        reqwest::Url::parse(url).is_ok_and(|u| {
+            let path = match u.query() {
+                Some(query) => format!("{}?{query}", u.path()),
+                None => u.path().to_string(),
+            };
+            robots.is_allowed(&path)
+        })
+    };
+    if !robots_allows(&base_url) {
+        if options.ignore_robots {
+            eprintln!("⚠️  robots.txt for {origin} disallows {base_url}; crawling anyway (--ignore-robots)");
+        } else {
+            eprintln!("⚠️  robots.txt for {origin} disallows {base_url}; pass --ignore-robots to crawl anyway");
+            return Err(DocLoaderError::RobotsDisallowed(base_url));
+        }
+    }
+    let page_delay = match robots.crawl_delay() {
+        Some(delay) if !options.ignore_robots && delay > PAGE_DELAY => {
+            eprintln!("Honoring robots.txt crawl-delay of {delay:?} for {origin}");
+            delay
+        }
+        _ => PAGE_DELAY,
+    };
+    let mut diagnostics = CrawlDiagnostics::default();
+
+    let mut doc_count = 0;
     let mut visited = HashSet::new();
     let mut to_visit = VecDeque::new();
+    // Every URL ever queued, so a page linked from many others is queued only once
+    let mut queued = HashSet::new();
     to_visit.push_back(base_url.clone());
+    queued.insert(base_url.clone());
     let mut extracted_version = None;
+    // The redirect is authoritative; a version from the page or requested URL is only a
+    // stand-in until a redirected URL of the crate names one
+    let mut version_from_redirect = false;
+    // Full text of a crate root without docblocks, used if the crawl finds too little
+    let mut root_text = Vec::new();
 
     // Define the CSS selector for the main content area
-    let content_selector = Selector::parse("div.docblock, section.docblock, .rustdoc .docblock")
-        .map_err(|e| DocLoaderError::Selector(e.to_string()))?;
+    let content_selector = parse_content_selector(options.content_selector.as_deref())?;
+    if let Some(selector) = &options.content_selector {
+        eprintln!("Using custom content selector: {selector}");
+    }
+
+    let url_filter = UrlFilter::new(&options.include_url_patterns, &options.exclude_url_patterns)?;
 
     let max_pages = max_pages.unwrap_or(10000); // Default to 10000 pages if not specified
     let mut processed = 0;
 
+    let follow_links_fraction = options
+        .follow_links_fraction
+        .unwrap_or(DEFAULT_FOLLOW_LINKS_FRACTION)
+        .clamp(0.0, 1.0);
+    let follow_links_pages = (max_pages as f64 * follow_links_fraction) as usize;
+    eprintln!(
+        "Crawl strategy: {}, following links on the first {follow_links_pages} of {max_pages} pages, {} links per page",
+        options.strategy,
+        options
+            .max_links_per_page
+            .map_or_else(|| "all".to_string(), |n| format!("up to {n}"))
+    );
+
+    // Re-export following: pages queued and fetched per other crate, bounded by budgets
+    let reexport_budget = options
+        .reexport_page_budget
+        .unwrap_or(DEFAULT_REEXPORT_PAGE_BUDGET);
+    let reexport_per_crate = options
+        .reexport_pages_per_crate
+        .unwrap_or(DEFAULT_REEXPORT_PAGES_PER_CRATE);
+    let mut reexport_queued: HashMap<String, usize> = HashMap::new();
+    let mut reexport_fetched: HashMap<String, usize> = HashMap::new();
+    if options.follow_reexports {
+        eprintln!(
+            "Following re-exports into other crates (budget: {reexport_budget} pages, {reexport_per_crate} per crate)"
+        );
+    }
+
     // Helper function to check if a URL should be processed (filter out source code and other non-docs)
-    fn should_process_url(url: &str) -> bool {
+    // The crate root is always crawled so include patterns still have a starting point for links.
+    fn should_process_url(url: &str, base_url: &str, url_filter: &UrlFilter) -> bool {
         // Skip source code pages
         if url.contains("/src/") {
             return false;
         }
 
+        // Apply the crate's exclude patterns; include patterns decide which pages are kept
+        if url != base_url && !url_filter.allows_visit(url) {
+            return false;
+        }
+
         // Skip specific non-documentation patterns
         if url.contains("#method.")
             || url.contains("#impl-")
@@ -82,9 +1108,25 @@ pub async fn load_documents_from_docs_rs(
         true
     }
 
-    while let Some(url) = to_visit.pop_front() {
+    loop {
+        let next = match options.strategy {
+            CrawlStrategy::BreadthFirst => to_visit.pop_front(),
+            CrawlStrategy::DepthFirst => to_visit.pop_back(),
+        };
+        let Some(url) = next else {
+            break;
+        };
         if processed >= max_pages {
             eprintln!("Reached maximum page limit ({max_pages}), stopping");
+            diagnostics.hit_max_pages = true;
+            break;
+        }
+        if options
+            .deadline
+            .is_some_and(|deadline| std::time::Instant::now() >= deadline)
+        {
+            eprintln!("Reached the crawl deadline after {processed} pages, stopping");
+            diagnostics.hit_deadline = true;
             break;
         }
 
@@ -93,96 +1135,175 @@ pub async fn load_documents_from_docs_rs(
         }
 
         // Skip non-documentation URLs
-        if !should_process_url(&url) {
+        if !should_process_url(&url, &base_url, &url_filter) {
+            visited.insert(url.clone());
+            diagnostics.skip(&url, "filtered");
+            continue;
+        }
+
+        if !options.ignore_robots && !robots_allows(&url) {
+            debug!("Skipping (disallowed by robots.txt): {url}");
             visited.insert(url.clone());
+            diagnostics.skip(&url, "robots");
             continue;
         }
 
         visited.insert(url.clone());
         processed += 1;
 
-        eprintln!("Processing page {processed}/{max_pages}: {url}");
+        debug!(
+            "Processing page {processed}/{max_pages} ({} queued): {url}",
+            to_visit.len()
+        );
+        if processed % 100 == 0 {
+            eprintln!(
+                "Crawled {processed}/{max_pages} pages, {} queued",
+                to_visit.len()
+            );
+        }
 
         // Fetch the page with retry logic
-        let html_content = match fetch_with_retry(&client, &url, 3).await {
-            Ok(content) => content,
-            Err(e) => {
-                eprintln!("Failed to fetch {url} after retries: {e}");
-                continue;
-            }
+        let (final_url, html_content) =
+            match fetch_with_retry(&client, &url, 3, &mut diagnostics).await {
+                Ok(page) => page,
+                Err(e) => {
+                    eprintln!("Failed to fetch {url} after retries: {e}");
+                    continue;
+                }
+            };
+        diagnostics.pages_fetched += 1;
+        diagnostics.bytes_downloaded += html_content.len() as u64;
+        let html_content = if options.keep_mojibake {
+            html_content
+        } else {
+            repair_mojibake(&html_content).into_owned()
         };
 
         let document = Html::parse_document(&html_content);
+        let page_crate = docs_rs_crate(&url, &origin_prefix)
+            .unwrap_or(crate_name)
+            .to_string();
+        if options.follow_reexports && !same_crate(&page_crate, crate_name) {
+            *reexport_fetched.entry(page_crate.clone()).or_default() += 1;
+        }
 
-        // Extract version from the first page (usually in the header)
-        if extracted_version.is_none() && processed == 1 {
-            // Try to find version in the docs.rs header
-            // docs.rs shows version in format "crate-name 1.2.3"
-            if let Ok(version_selector) = Selector::parse(".version") {
-                if let Some(version_elem) = document.select(&version_selector).next() {
-                    let version_text = version_elem.text().collect::<String>();
-                    extracted_version = Some(version_text.trim().to_string());
-                    eprintln!("Extracted version: {extracted_version:?}");
+        // docs.rs redirects /latest/ to the released version, so the final URL is the
+        // source of truth; re-exported crates' pages name their own versions
+        if !version_from_redirect && same_crate(&page_crate, crate_name) {
+            if let Some(version) = version_from_docs_url(&final_url, &origin_prefix) {
+                if extracted_version.as_ref() != Some(&version) {
+                    eprintln!("Extracted version from redirect: {version}");
                 }
+                extracted_version = Some(version);
+                version_from_redirect = true;
             }
 
-            // Alternative: Look in the title or URL path
+            // Fall back to the docs.rs header, which shows "crate-name 1.2.3"
             if extracted_version.is_none() {
-                // The URL might contain version like /crate-name/1.2.3/
-                if let Some(version_match) = url.split('/').nth_back(2) {
-                    if version_match != "latest" && version_match.chars().any(|c| c.is_numeric()) {
-                        extracted_version = Some(version_match.to_string());
-                        eprintln!("Extracted version from URL: {extracted_version:?}");
+                if let Ok(version_selector) = Selector::parse(".version") {
+                    if let Some(version_elem) = document.select(&version_selector).next() {
+                        let version_text = version_elem.text().collect::<String>();
+                        extracted_version = version_from_text(&version_text);
+                        eprintln!("Extracted version from page: {extracted_version:?}");
                     }
                 }
             }
-        }
 
-        // Extract text content from documentation blocks
-        let mut page_content = Vec::new();
-        for element in document.select(&content_selector) {
-            let text_content: String = element
-                .text()
-                .map(|s| s.trim())
-                .filter(|s| !s.is_empty())
-                .collect::<Vec<&str>>()
-                .join("\n");
+            // Last resort: a version pinned in the requested URL (a target may follow it)
+            if extracted_version.is_none() {
+                extracted_version = version_from_docs_url(&url, &origin_prefix);
+                if extracted_version.is_some() {
+                    eprintln!("Extracted version from URL: {extracted_version:?}");
+                }
+            }
+        }
 
-            if !text_content.is_empty() {
-                page_content.push(text_content);
+        let relative_path = url.strip_prefix(&origin_prefix).unwrap_or(&url).to_string();
+        // Module index pages outside the include patterns are only fetched for their links
+        let keep = url == base_url || url_filter.allows(&url);
+        // Every kept page is stored, even empty ones: a better selector may find content
+        if let Some(pages) = pages.as_ref().filter(|_| keep) {
+            let page = RawPage {
+                path: relative_path.clone(),
+                html: html_content.clone(),
+            };
+            if pages.send(page).await.is_err() {
+                debug!("Raw page receiver dropped; {relative_path} is not stored");
             }
         }
 
-        if !page_content.is_empty() {
-            let relative_path = url
-                .strip_prefix("https://docs.rs/")
-                .unwrap_or(&url)
-                .to_string();
+        // Extract text content from documentation blocks, grouped by section anchor
+        let page_docs = page_documents(&relative_path, &document, &content_selector);
+        let sections_empty = page_docs.is_empty();
+        if sections_empty && url == base_url {
+            root_text = full_page_documents(&relative_path, &document);
+        }
 
-            let blocks = page_content.len();
-            let chars = page_content.join("\n\n").len();
-            eprintln!(
-                "  -> Extracted content from: {relative_path} ({blocks} blocks, {chars} chars)"
+        if !sections_empty && keep {
+            let chars: usize = page_docs.iter().map(|d| d.content.len()).sum();
+            debug!(
+                "Extracted content from: {relative_path} ({} sections, {chars} chars)",
+                page_docs.len()
             );
 
-            documents.push(Document {
-                path: relative_path,
-                content: page_content.join("\n\n"),
-            });
+            for doc in page_docs {
+                if documents.send(doc).await.is_err() {
+                    debug!("Document receiver dropped; stopping crawl of {crate_name}");
+                    return Ok(CrawlSummary {
+                        version: extracted_version,
+                        reexport_pages: reexport_fetched,
+                        diagnostics,
+                    });
+                }
+                doc_count += 1;
+            }
+        }
+
+        let link_selector = Selector::parse("a").unwrap();
+
+        // Index pages whose listing rustdoc fills in with JS have no static docblocks, but
+        // their item links still lead to pages with real content
+        let index_only = sections_empty
+            && document
+                .select(&link_selector)
+                .filter_map(|link| link.value().attr("href"))
+                .any(is_item_page_link);
+        if sections_empty {
+            debug!("No content extracted from: {url}");
+            diagnostics.empty_pages += 1;
+            if index_only {
+                debug!("Index-only page, following its item links: {url}");
+                diagnostics.index_only_pages += 1;
+            }
+            let detail = index_only.then_some("index_only");
+            diagnostics.trace.record(&url, TraceOutcome::Empty, detail);
         } else {
-            eprintln!("  -> No content extracted from: {url}");
+            let detail = (!keep).then_some("links_only");
+            diagnostics
+                .trace
+                .record(&url, TraceOutcome::Fetched, detail);
         }
 
         // Extract links to other documentation pages within the same crate
-        // Follow links for first 75% of pages to get deeper coverage
-        if processed < (max_pages * 3 / 4) {
-            let link_selector = Selector::parse("a").unwrap();
+        // Follow links for the first `follow_links_fraction` of pages to get deeper coverage;
+        // past that, index-only pages still hand over their item pages so they aren't dead ends
+        let follow_all = processed < follow_links_pages;
+        if follow_all || index_only {
             let mut found_links = 0;
-            let mut added_links = 0;
+            let mut new_links = Vec::new();
 
             for link in document.select(&link_selector) {
+                if options
+                    .max_links_per_page
+                    .is_some_and(|cap| new_links.len() >= cap)
+                {
+                    break;
+                }
                 if let Some(href) = link.value().attr("href") {
                     found_links += 1;
+                    if !follow_all && !is_item_page_link(href) {
+                        continue;
+                    }
 
                     // Follow various types of relative links
                     let should_follow = href.starts_with("./") ||
@@ -191,79 +1312,188 @@ pub async fn load_documents_from_docs_rs(
                                        (!href.starts_with("http") &&
                                         !href.starts_with("#") &&
                                         !href.starts_with("/") &&
-                                        href.ends_with(".html"));
+                                        href.ends_with(".html")) ||
+                                       // Re-exported items link to other crates absolutely
+                                       (options.follow_reexports && href.starts_with(&origin_prefix));
 
                     if should_follow {
                         if let Ok(absolute_url) = reqwest::Url::parse(&url) {
                             if let Ok(new_url) = absolute_url.join(href) {
                                 let new_url_str = new_url.to_string();
-                                if new_url_str.contains("docs.rs")
-                                    && new_url_str.contains(crate_name)
-                                    && !visited.contains(&new_url_str)
-                                    && should_process_url(&new_url_str)
+                                let target_crate =
+                                    docs_rs_crate(&new_url_str, &origin_prefix).unwrap_or_default();
+                                let is_foreign = options.follow_reexports
+                                    && !target_crate.is_empty()
+                                    && !same_crate(target_crate, crate_name);
+
+                                // Other crates are entered only from the facade's own pages
+                                // (no transitive expansion) and within the page budgets
+                                let in_scope = if is_foreign {
+                                    let allowed = options.reexport_crates.is_empty()
+                                        || options
+                                            .reexport_crates
+                                            .iter()
+                                            .any(|c| same_crate(c, target_crate));
+                                    let from_here = same_crate(&page_crate, crate_name)
+                                        || same_crate(&page_crate, target_crate);
+                                    let queued_total: usize = reexport_queued.values().sum();
+                                    let queued_for_crate =
+                                        reexport_queued.get(target_crate).copied().unwrap_or(0);
+                                    allowed
+                                        && from_here
+                                        && queued_total < reexport_budget
+                                        && queued_for_crate < reexport_per_crate
+                                } else {
+                                    new_url_str.contains(crate_name)
+                                };
+
+                                if new_url_str.starts_with(&origin_prefix)
+                                    && in_scope
+                                    && !queued.contains(&new_url_str)
+                                    && should_process_url(&new_url_str, &base_url, &url_filter)
                                 {
-                                    to_visit.push_back(new_url_str.clone());
-                                    added_links += 1;
-                                    if added_links <= 5 {
-                                        // Only show first 5 for brevity
-                                        eprintln!("  -> Adding link: {href}");
+                                    if is_foreign {
+                                        *reexport_queued
+                                            .entry(target_crate.to_string())
+                                            .or_default() += 1;
                                     }
+                                    queued.insert(new_url_str.clone());
+                                    new_links.push(new_url_str);
                                 }
                             }
                         }
                     }
                 }
             }
-            eprintln!("  Found {found_links} links, added {added_links} new ones to visit");
+            let added_links = new_links.len();
+            // Depth-first pops from the back, so queue in reverse to visit in page order
+            if options.strategy == CrawlStrategy::DepthFirst {
+                new_links.reverse();
+            }
+            to_visit.extend(new_links);
+            diagnostics.peak_queue_size = diagnostics.peak_queue_size.max(to_visit.len());
+            debug!(
+                "Found {found_links} links, added {added_links} new ones to visit ({} queued)",
+                to_visit.len()
+            );
         }
 
         // Add a longer delay to be respectful to docs.rs and avoid rate limiting
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        tokio::time::sleep(page_delay).await;
     }
 
-    let doc_count = documents.len();
-    eprintln!("Finished loading {doc_count} documents from docs.rs");
-    Ok(LoadResult {
-        documents,
+    // Macro-only and proc-macro crates have (almost) no docblocks; their README and
+    // root page are all there is to answer from
+    if doc_count < LOW_DOC_COVERAGE_THRESHOLD
+        && diagnostics.pages_fetched > 0
+        && !diagnostics.hit_deadline
+    {
+        let version = extracted_version.as_deref().unwrap_or("latest");
+        let readme_url = format!("{origin_prefix}crate/{crate_name}/{version}");
+        let mut fallback = root_text;
+        if options.ignore_robots || robots_allows(&readme_url) {
+            match fetch_with_retry(&client, &readme_url, 3, &mut diagnostics).await {
+                Ok((_, html_content)) => {
+                    diagnostics.pages_fetched += 1;
+                    diagnostics
+                        .trace
+                        .record(&readme_url, TraceOutcome::Fetched, None);
+                    diagnostics.bytes_downloaded += html_content.len() as u64;
+                    let relative_path = readme_url
+                        .strip_prefix(&origin_prefix)
+                        .unwrap_or(&readme_url)
+                        .to_string();
+                    let document = Html::parse_document(&html_content);
+                    fallback.extend(readme_document(&relative_path, &document));
+                }
+                Err(e) => eprintln!("Failed to fetch the README page {readme_url}: {e}"),
+            }
+        } else {
+            diagnostics.skip(&readme_url, "robots");
+        }
+
+        if !fallback.is_empty() {
+            eprintln!(
+                "⚠️  Only {doc_count} documents extracted from {crate_name}; adding {} from its README and root page",
+                fallback.len()
+            );
+            diagnostics.low_doc_coverage = true;
+            for doc in fallback {
+                if documents.send(doc).await.is_err() {
+                    debug!("Document receiver dropped; stopping crawl of {crate_name}");
+                    break;
+                }
+                doc_count += 1;
+            }
+        }
+    }
+
+    if extracted_version.is_none() && diagnostics.pages_fetched > 0 {
+        diagnostics.version_missing = true;
+        eprintln!("❌ Could not determine the version of {crate_name} from the docs.rs redirect or page; it will be stored without a version");
+    }
+    eprintln!("Finished loading {doc_count} documents from {origin}");
+    eprintln!("Crawl diagnostics: {diagnostics}");
+    if !reexport_fetched.is_empty() {
+        let mut summary: Vec<_> = reexport_fetched.iter().collect();
+        summary.sort();
+        eprintln!("Pages fetched from re-exported crates: {summary:?}");
+    }
+    Ok(CrawlSummary {
         version: extracted_version,
+        reexport_pages: reexport_fetched,
+        diagnostics,
     })
 }
 
-/// Synchronous wrapper that uses current tokio runtime
+/// Blocking wrapper around [`load_documents_from_docs_rs`] that works from any context
+///
+/// Outside a tokio runtime the crawl runs on a runtime created for the call. Inside one,
+/// nesting `block_on` would panic, so the crawl runs on a separate thread with its own
+/// runtime (the same way `populate_crate` keeps the non-`Send` crawler off the async
+/// workers). Either way the calling thread blocks until the crawl finishes; async code
+/// that can `.await` should call [`load_documents_from_docs_rs`] instead.
 #[allow(dead_code)] // Available for future use
 pub fn load_documents(
     crate_name: &str,
     crate_version_req: &str,
     features: Option<&Vec<String>>,
+    options: &CrawlOptions,
 ) -> Result<LoadResult, DocLoaderError> {
-    // Check if we're already in a tokio runtime
-    if tokio::runtime::Handle::try_current().is_ok() {
-        // We're in a runtime, but we can't use block_on.
-        // We need to make this function async or use a different approach.
-        // For now, let's return an error suggesting the async version
-        return Err(DocLoaderError::Parsing(
-            "Cannot run synchronous load_documents from within async context. Use load_documents_from_docs_rs directly.".to_string()
-        ));
-    }
-
-    let rt = tokio::runtime::Runtime::new()
-        .map_err(|e| DocLoaderError::Parsing(format!("Failed to create tokio runtime: {e}")))?;
+    let crawl = || {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| DocLoaderError::Parsing(format!("Failed to create tokio runtime: {e}")))?;
+        rt.block_on(load_documents_from_docs_rs(
+            crate_name,
+            crate_version_req,
+            features,
+            None,
+            options,
+        ))
+    };
 
-    rt.block_on(load_documents_from_docs_rs(
-        crate_name,
-        crate_version_req,
-        features,
-        None,
-    ))
+    if tokio::runtime::Handle::try_current().is_err() {
+        return crawl();
+    }
+    std::thread::scope(|scope| {
+        scope.spawn(crawl).join().unwrap_or_else(|_| {
+            Err(DocLoaderError::Parsing(
+                "Document loader panicked".to_string(),
+            ))
+        })
+    })
 }
 
 /// Fetch a URL with retry logic and rate limiting
+///
+/// Returns the URL the response came from after redirects, along with the body.
 #[allow(dead_code)] // Used internally
 async fn fetch_with_retry(
     client: &reqwest::Client,
     url: &str,
     max_retries: usize,
-) -> Result<String, DocLoaderError> {
+    diagnostics: &mut CrawlDiagnostics,
+) -> Result<(String, String), DocLoaderError> {
     let mut attempts = 0;
     let mut delay = Duration::from_millis(1000); // Start with 1 second
 
@@ -271,21 +1501,32 @@ async fn fetch_with_retry(
         match client.get(url).send().await {
             Ok(response) => {
                 if response.status().is_success() {
-                    match response.text().await {
-                        Ok(text) => return Ok(text),
+                    let final_url = response.url().to_string();
+                    let content_type = response
+                        .headers()
+                        .get(reqwest::header::CONTENT_TYPE)
+                        .and_then(|value| value.to_str().ok())
+                        .map(str::to_string);
+                    match response.bytes().await {
+                        Ok(body) => {
+                            return Ok((final_url, decode_html(&body, content_type.as_deref())))
+                        }
                         Err(e) => {
                             eprintln!("Failed to read response body for {url}: {e}");
                             if attempts >= max_retries {
+                                diagnostics.fail(url, "network");
                                 return Err(DocLoaderError::Http(e));
                             }
                         }
                     }
                 } else if response.status() == 429 {
                     // Rate limited
+                    diagnostics.rate_limited_responses += 1;
                     let retry_num = attempts + 1;
                     let max_retries_plus = max_retries + 1;
                     eprintln!("Rate limited for {url}, waiting {delay:?} before retry {retry_num}/{max_retries_plus}");
                     if attempts >= max_retries {
+                        diagnostics.fail(url, "rate_limited");
                         return Err(DocLoaderError::RateLimited(format!(
                             "Rate limited after {} attempts",
                             attempts + 1
@@ -294,6 +1535,7 @@ async fn fetch_with_retry(
                 } else if response.status() == 404 {
                     // 404 is a permanent failure - don't retry
                     eprintln!("⚠️  Page not found (404): {url} - skipping");
+                    diagnostics.fail(url, "not_found");
                     return Err(DocLoaderError::Network(format!(
                         "HTTP {}",
                         response.status()
@@ -301,6 +1543,7 @@ async fn fetch_with_retry(
                 } else if response.status().is_client_error() {
                     // Other 4xx errors are also permanent failures - don't retry
                     eprintln!("⚠️  Client error ({}): {url} - skipping", response.status());
+                    diagnostics.fail(url, "client_error");
                     return Err(DocLoaderError::Network(format!(
                         "HTTP {}",
                         response.status()
@@ -309,6 +1552,7 @@ async fn fetch_with_retry(
                     // 5xx server errors should be retried
                     eprintln!("HTTP error for {}: {}", url, response.status());
                     if attempts >= max_retries {
+                        diagnostics.fail(url, "server_error");
                         return Err(DocLoaderError::Network(format!(
                             "HTTP {}",
                             response.status()
@@ -319,6 +1563,7 @@ async fn fetch_with_retry(
             Err(e) => {
                 eprintln!("Network error for {url}: {e}");
                 if attempts >= max_retries {
+                    diagnostics.fail(url, "network");
                     return Err(DocLoaderError::Http(e));
                 }
             }
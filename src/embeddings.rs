@@ -34,6 +34,11 @@ pub trait EmbeddingProvider {
     ) -> Result<(Vec<Vec<f32>>, usize), ServerError>;
 
     fn get_model_name(&self) -> &str;
+
+    /// Short provider name as accepted by `--embedding-provider`
+    fn get_provider_name(&self) -> &str {
+        "custom"
+    }
 }
 
 /// OpenAI embedding provider
@@ -47,6 +52,140 @@ pub struct VoyageAIEmbeddingProvider {
     client: reqwest::Client,
     api_key: String,
     model: String,
+    api_base: String,
+    limits: VoyageLimits,
+    bpe: Option<Arc<tiktoken_rs::CoreBPE>>,
+    /// Earliest time the next request may be sent, from Voyage's rate-limit headers
+    not_before: std::sync::Mutex<Option<std::time::Instant>>,
+}
+
+/// Default Voyage AI endpoint; override with `VOYAGE_API_BASE` (e.g. for a proxy)
+pub const VOYAGE_API_BASE: &str = "https://api.voyageai.com/v1";
+
+/// Rate-limited requests are retried this many times before giving up
+const VOYAGE_MAX_RETRIES: u32 = 3;
+
+/// Per-request limits of a Voyage embedding model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoyageLimits {
+    /// Inputs per request
+    pub max_items: usize,
+    /// Total tokens across all inputs of a request
+    pub max_batch_tokens: usize,
+    /// Context length; longer inputs are truncated by Voyage
+    pub max_input_tokens: usize,
+}
+
+impl VoyageLimits {
+    /// Published limits for `model`, with the strictest current limits for unknown models
+    pub fn for_model(model: &str) -> Self {
+        let (max_batch_tokens, max_input_tokens) = match model {
+            "voyage-3.5-lite" | "voyage-3-lite" => (1_000_000, 32_000),
+            "voyage-3.5" | "voyage-3" | "voyage-2" => (320_000, 32_000),
+            "voyage-large-2" | "voyage-code-2" => (120_000, 16_000),
+            _ => (120_000, 32_000),
+        };
+        Self {
+            max_items: 1000,
+            max_batch_tokens,
+            max_input_tokens,
+        }
+    }
+}
+
+/// Split inputs into request batches that respect `limits`
+///
+/// `token_counts` are estimates, so batches are planned against 90% of the token
+/// limit. Inputs longer than the context length count as the context length, since
+/// Voyage truncates them. Returns index ranges into the inputs.
+pub fn plan_voyage_batches(
+    token_counts: &[usize],
+    limits: &VoyageLimits,
+) -> Vec<std::ops::Range<usize>> {
+    let token_budget = (limits.max_batch_tokens * 9 / 10).max(1);
+    let max_items = limits.max_items.max(1);
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut batch_tokens = 0;
+    for (i, &tokens) in token_counts.iter().enumerate() {
+        let tokens = tokens.min(limits.max_input_tokens);
+        let full = i - start >= max_items || batch_tokens + tokens > token_budget;
+        if i > start && full {
+            batches.push(start..i);
+            start = i;
+            batch_tokens = 0;
+        }
+        batch_tokens += tokens;
+    }
+    if start < token_counts.len() {
+        batches.push(start..token_counts.len());
+    }
+    batches
+}
+
+/// Turn a failed Voyage response into a message that says what to do about it
+pub fn voyage_error_message(status: reqwest::StatusCode, body: &str, model: &str) -> String {
+    // Voyage reports errors as {"detail": "..."}
+    let detail = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v.get("detail").and_then(|d| d.as_str()).map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string());
+    let hint = match status.as_u16() {
+        401 | 403 => "check VOYAGE_API_KEY".to_string(),
+        429 => "rate limit still exceeded after retries; lower PIPELINE_EMBED_CONCURRENCY or raise your Voyage rate limits".to_string(),
+        400 if detail.to_lowercase().contains("token") => format!(
+            "a batch exceeded {model}'s token limits; if this model is new, its limits may be missing from VoyageLimits::for_model"
+        ),
+        400 if detail.to_lowercase().contains("model") => {
+            format!("check EMBEDDING_MODEL ('{model}' may not be a Voyage embedding model)")
+        }
+        500..=599 => "Voyage AI is having problems; retry later".to_string(),
+        _ => "see https://docs.voyageai.com/docs/error-codes".to_string(),
+    };
+    format!("Voyage AI API error {status}: {detail} ({hint})")
+}
+
+/// Delay requested by a rate-limited response: `retry-after` seconds, or Voyage's
+/// `x-ratelimit-reset-*` durations once the remaining quota is exhausted
+fn voyage_rate_limit_delay(headers: &reqwest::header::HeaderMap) -> Option<std::time::Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(seconds) = header("retry-after").and_then(|v| v.trim().parse::<f64>().ok()) {
+        return Some(std::time::Duration::from_secs_f64(seconds.max(0.0)));
+    }
+    ["requests", "tokens"]
+        .iter()
+        .filter(|kind| header(&format!("x-ratelimit-remaining-{kind}")) == Some("0"))
+        .filter_map(|kind| header(&format!("x-ratelimit-reset-{kind}")).and_then(parse_reset))
+        .max()
+}
+
+/// Parse a reset duration such as `1s`, `250ms` or `1m30s`
+fn parse_reset(value: &str) -> Option<std::time::Duration> {
+    let mut total = 0.0;
+    let mut number = String::new();
+    let mut chars = value.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let amount: f64 = number.parse().ok()?;
+        number.clear();
+        total += match c {
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                amount / 1000.0
+            }
+            'h' => amount * 3600.0,
+            'm' => amount * 60.0,
+            's' => amount,
+            _ => return None,
+        };
+    }
+    if !number.is_empty() {
+        total += number.parse::<f64>().ok()?;
+    }
+    Some(std::time::Duration::from_secs_f64(total))
 }
 
 /// Voyage AI API response structures
@@ -59,7 +198,6 @@ struct VoyageEmbeddingResponse {
 #[derive(Deserialize)]
 struct VoyageEmbeddingData {
     embedding: Vec<f32>,
-    #[allow(dead_code)]
     index: usize,
 }
 
@@ -69,10 +207,11 @@ struct VoyageUsage {
 }
 
 #[derive(Serialize)]
-struct VoyageEmbeddingRequest {
-    input: Vec<String>,
-    model: String,
-    input_type: String,
+struct VoyageEmbeddingRequest<'a> {
+    input: &'a [String],
+    model: &'a str,
+    input_type: &'a str,
+    truncation: bool,
 }
 
 #[async_trait::async_trait]
@@ -102,6 +241,10 @@ impl EmbeddingProvider for OpenAIEmbeddingProvider {
     fn get_model_name(&self) -> &str {
         &self.model
     }
+
+    fn get_provider_name(&self) -> &str {
+        "openai"
+    }
 }
 
 #[async_trait::async_trait]
@@ -110,49 +253,31 @@ impl EmbeddingProvider for VoyageAIEmbeddingProvider {
         &self,
         texts: &[String],
     ) -> Result<(Vec<Vec<f32>>, usize), ServerError> {
-        let request = VoyageEmbeddingRequest {
-            input: texts.to_vec(),
-            model: self.model.clone(),
-            input_type: "document".to_string(), // Default to document type
-        };
-
-        let response = self
-            .client
-            .post("https://api.voyageai.com/v1/embeddings")
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| ServerError::Network(format!("Voyage AI API request failed: {e}")))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(ServerError::Network(format!(
-                "Voyage AI API error {status}: {error_text}"
-            )));
-        }
-
-        let voyage_response: VoyageEmbeddingResponse = response.json().await.map_err(|e| {
-            ServerError::Parsing(format!("Failed to parse Voyage AI response: {e}"))
-        })?;
-
-        let embeddings: Vec<Vec<f32>> = voyage_response
-            .data
-            .into_iter()
-            .map(|data| data.embedding)
+        let token_counts: Vec<usize> = texts
+            .iter()
+            .map(|text| match &self.bpe {
+                Some(bpe) => bpe.encode_with_special_tokens(text).len(),
+                None => text.len() / 3 + 1,
+            })
             .collect();
 
-        Ok((embeddings, voyage_response.usage.total_tokens))
+        let mut embeddings = Vec::with_capacity(texts.len());
+        let mut total_tokens = 0;
+        for batch in plan_voyage_batches(&token_counts, &self.limits) {
+            let (batch_embeddings, tokens) = self.embed_batch(&texts[batch]).await?;
+            embeddings.extend(batch_embeddings);
+            total_tokens += tokens;
+        }
+        Ok((embeddings, total_tokens))
     }
 
     fn get_model_name(&self) -> &str {
         &self.model
     }
+
+    fn get_provider_name(&self) -> &str {
+        "voyage"
+    }
 }
 
 impl OpenAIEmbeddingProvider {
@@ -162,11 +287,112 @@ impl OpenAIEmbeddingProvider {
 }
 
 impl VoyageAIEmbeddingProvider {
+    /// Provider for `model`, sending requests to `VOYAGE_API_BASE` if set
     pub fn new(api_key: String, model: String) -> Self {
+        let api_base = std::env::var("VOYAGE_API_BASE")
+            .ok()
+            .filter(|base| !base.trim().is_empty())
+            .unwrap_or_else(|| VOYAGE_API_BASE.to_string());
         Self {
             client: reqwest::Client::new(),
             api_key,
+            limits: VoyageLimits::for_model(&model),
             model,
+            api_base: api_base.trim_end_matches('/').to_string(),
+            bpe: cl100k_base().ok().map(Arc::new),
+            not_before: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Send requests to `api_base` (e.g. `http://proxy/v1`) instead
+    #[allow(dead_code)] // Used by tests
+    pub fn with_api_base(mut self, api_base: &str) -> Self {
+        self.api_base = api_base.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Override the model's request limits
+    #[allow(dead_code)] // Used by tests
+    pub fn with_limits(mut self, limits: VoyageLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Embed one batch that fits the model's limits, retrying rate-limited requests
+    async fn embed_batch(&self, texts: &[String]) -> Result<(Vec<Vec<f32>>, usize), ServerError> {
+        let request = VoyageEmbeddingRequest {
+            input: texts,
+            model: &self.model,
+            input_type: "document",
+            truncation: true,
+        };
+
+        let mut attempt = 0;
+        loop {
+            let wait = self
+                .not_before
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .take()
+                .and_then(|at| at.checked_duration_since(std::time::Instant::now()));
+            if let Some(wait) = wait {
+                tokio::time::sleep(wait).await;
+            }
+
+            let response = self
+                .client
+                .post(format!("{}/embeddings", self.api_base))
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .json(&request)
+                .send()
+                .await
+                .map_err(|e| ServerError::Network(format!("Voyage AI API request failed: {e}")))?;
+
+            // Pace the next request when the quota is exhausted
+            let delay = voyage_rate_limit_delay(response.headers());
+            let status = response.status();
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS && attempt < VOYAGE_MAX_RETRIES {
+                attempt += 1;
+                let wait = delay.unwrap_or(std::time::Duration::from_secs(1 << attempt));
+                eprintln!(
+                    "    ⏳ Voyage AI rate limit hit, retrying in {wait:?} (attempt {attempt}/{VOYAGE_MAX_RETRIES})"
+                );
+                tokio::time::sleep(wait).await;
+                continue;
+            }
+            if let Some(delay) = delay {
+                *self.not_before.lock().unwrap_or_else(|e| e.into_inner()) =
+                    Some(std::time::Instant::now() + delay);
+            }
+
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                return Err(ServerError::Network(voyage_error_message(
+                    status,
+                    &body,
+                    &self.model,
+                )));
+            }
+
+            let mut voyage_response: VoyageEmbeddingResponse =
+                response.json().await.map_err(|e| {
+                    ServerError::Parsing(format!("Failed to parse Voyage AI response: {e}"))
+                })?;
+            if voyage_response.data.len() != texts.len() {
+                return Err(ServerError::Parsing(format!(
+                    "Voyage AI returned {} embeddings for {} inputs",
+                    voyage_response.data.len(),
+                    texts.len()
+                )));
+            }
+            voyage_response.data.sort_by_key(|data| data.index);
+            let embeddings = voyage_response
+                .data
+                .into_iter()
+                .map(|data| data.embedding)
+                .collect();
+            return Ok((embeddings, voyage_response.usage.total_tokens));
         }
     }
 }
@@ -185,18 +411,7 @@ pub fn initialize_embedding_provider(
     }
 }
 
-use bincode::{Decode, Encode};
-
-// Define a struct containing path, content, and embedding for caching
-#[derive(Serialize, Deserialize, Debug, Encode, Decode)]
-pub struct CachedDocumentEmbedding {
-    pub path: String,
-    pub content: String, // Add the extracted document content
-    pub vector: Vec<f32>,
-}
-
 /// Calculates the cosine similarity between two vectors.
-#[allow(dead_code)] // Available for future use
 pub fn cosine_similarity(v1: ArrayView1<f32>, v2: ArrayView1<f32>) -> f32 {
     let dot_product = v1.dot(&v2);
     let norm_v1 = v1.dot(&v1).sqrt();
@@ -209,6 +424,141 @@ pub fn cosine_similarity(v1: ArrayView1<f32>, v2: ArrayView1<f32>) -> f32 {
     }
 }
 
+/// Largest input the embedding models accept, in cl100k tokens
+pub const MODEL_TOKEN_LIMIT: usize = 8191;
+
+/// Which part of an over-long embedding input is dropped to fit the model limit
+///
+/// Only applies to inputs that still exceed [`MODEL_TOKEN_LIMIT`] when they reach
+/// [`embed_chunk`]; the stored content is never truncated, only the text sent for embedding.
+/// Population chunks documents to [`TOKEN_LIMIT`] first, so in practice this covers
+/// stored rows re-embedded without chunking (`db_maintenance reclean --re-embed`,
+/// `find-bad-vectors --re-embed`), such as rows written by an older chunker.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncationStrategy {
+    /// Drop the end of the input (previous behaviour)
+    #[default]
+    Tail,
+    /// Drop the start of the input
+    Head,
+    /// Keep both ends and drop the middle, preserving signature and trailing examples
+    Middle,
+}
+
+impl std::str::FromStr for TruncationStrategy {
+    type Err = ServerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "tail" => Ok(Self::Tail),
+            "head" => Ok(Self::Head),
+            "middle" => Ok(Self::Middle),
+            other => Err(ServerError::Config(format!(
+                "Unknown truncation strategy: {other}. Use 'tail', 'head' or 'middle'"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for TruncationStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Tail => "tail",
+            Self::Head => "head",
+            Self::Middle => "middle",
+        })
+    }
+}
+
+impl TruncationStrategy {
+    /// Read `EMBEDDING_TRUNCATION`, defaulting to [`TruncationStrategy::Tail`]
+    pub fn from_env() -> Result<Self, ServerError> {
+        match std::env::var("EMBEDDING_TRUNCATION") {
+            Ok(value) if !value.trim().is_empty() => value.parse(),
+            _ => Ok(Self::default()),
+        }
+    }
+}
+
+/// Decode a token slice, shrinking it at the cut edge until it ends on a UTF-8 boundary
+fn decode_trimmed(
+    bpe: &tiktoken_rs::CoreBPE,
+    tokens: &[tiktoken_rs::Rank],
+    trim_front: bool,
+) -> String {
+    let mut slice = tokens;
+    while !slice.is_empty() {
+        if let Ok(text) = bpe.decode(slice.to_vec()) {
+            return text;
+        }
+        slice = if trim_front {
+            &slice[1..]
+        } else {
+            &slice[..slice.len() - 1]
+        };
+    }
+    String::new()
+}
+
+/// Truncate `content` to at most `token_limit` tokens using `strategy`
+///
+/// Returns `None` when the content already fits.
+pub fn truncate_for_embedding(
+    content: &str,
+    bpe: &tiktoken_rs::CoreBPE,
+    token_limit: usize,
+    strategy: TruncationStrategy,
+) -> Option<String> {
+    let tokens = bpe.encode_with_special_tokens(content);
+    if tokens.len() <= token_limit {
+        return None;
+    }
+
+    Some(match strategy {
+        TruncationStrategy::Tail => decode_trimmed(bpe, &tokens[..token_limit], false),
+        TruncationStrategy::Head => {
+            decode_trimmed(bpe, &tokens[tokens.len() - token_limit..], true)
+        }
+        TruncationStrategy::Middle => {
+            const SEPARATOR: &str = "\n...\n";
+            let budget =
+                token_limit.saturating_sub(bpe.encode_with_special_tokens(SEPARATOR).len());
+            let head = budget / 2;
+            let tail = budget - head;
+            format!(
+                "{}{SEPARATOR}{}",
+                decode_trimmed(bpe, &tokens[..head], false),
+                decode_trimmed(bpe, &tokens[tokens.len() - tail..], true)
+            )
+        }
+    })
+}
+
+/// Decode `tokens` as consecutive pieces of at most `limit` tokens
+///
+/// One character can span several tokens (emoji, CJK), so each piece ends at the last
+/// token boundary that is also a UTF-8 boundary and the rest carries into the next piece.
+fn decode_in_pieces(
+    bpe: &tiktoken_rs::CoreBPE,
+    tokens: &[tiktoken_rs::Rank],
+    limit: usize,
+) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    while start < tokens.len() {
+        let within_limit = (start + limit.max(1)).min(tokens.len());
+        // The longest piece within the limit that decodes, else the shortest one past it
+        let end = (start + 1..=within_limit)
+            .rev()
+            .chain(within_limit + 1..=tokens.len())
+            .find(|&end| bpe.decode(tokens[start..end].to_vec()).is_ok())
+            .unwrap_or(tokens.len());
+        pieces.push(decode_trimmed(bpe, &tokens[start..end], false));
+        start = end;
+    }
+    pieces
+}
+
 /// Splits content into chunks that fit within the token limit
 fn _chunk_content(content: &str, bpe: &tiktoken_rs::CoreBPE, token_limit: usize) -> Vec<String> {
     let tokens = bpe.encode_with_special_tokens(content);
@@ -249,15 +599,7 @@ fn _chunk_content(content: &str, bpe: &tiktoken_rs::CoreBPE, token_limit: usize)
             eprintln!("Warning: Single sentence exceeds token limit, splitting by tokens");
 
             // Split by tokens directly
-            let mut start = 0;
-            while start < tokens.len() {
-                let end = std::cmp::min(start + token_limit, tokens.len());
-                let chunk_tokens = &tokens[start..end];
-                if let Ok(chunk_text) = bpe.decode(chunk_tokens.to_vec()) {
-                    chunks.push(chunk_text);
-                }
-                start = end;
-            }
+            chunks.extend(decode_in_pieces(bpe, &sentence_tokens, token_limit));
             continue;
         }
 
@@ -274,6 +616,318 @@ fn _chunk_content(content: &str, bpe: &tiktoken_rs::CoreBPE, token_limit: usize)
     chunks
 }
 
+/// Documents above this many tokens are split into chunks
+pub const TOKEN_LIMIT: usize = 8000; // Keep a buffer below the 8192 limit
+/// Token overlap between chunks for context
+const CHUNK_OVERLAP: usize = 200;
+
+/// The configured embedding provider, once [`EMBEDDING_CLIENT`] has been initialized
+pub fn embedding_provider() -> Result<Arc<dyn EmbeddingProvider + Send + Sync>, ServerError> {
+    EMBEDDING_CLIENT
+        .get()
+        .cloned()
+        .ok_or_else(|| ServerError::Internal("Embedding provider not initialized".to_string()))
+}
+
+/// Text embedded by [`probe_provider`]
+pub const PROBE_TEXT: &str = "Returns the number of elements in the vector.";
+
+/// What a successful provider probe measured
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderProbe {
+    pub provider: String,
+    pub model: String,
+    /// Length of the returned vector, i.e. the `vector(N)` column size the model needs
+    pub dimension: usize,
+    pub latency_ms: u128,
+}
+
+/// Embed [`PROBE_TEXT`] to check that `provider` is reachable and measure its dimension
+#[allow(dead_code)] // Used by the HTTP server
+pub async fn probe_provider(
+    provider: &(dyn EmbeddingProvider + Send + Sync),
+) -> Result<ProviderProbe, ServerError> {
+    let start = std::time::Instant::now();
+    let (embeddings, _) = provider
+        .generate_embeddings(&[PROBE_TEXT.to_string()])
+        .await?;
+    let latency_ms = start.elapsed().as_millis();
+    let vector = embeddings.into_iter().next().ok_or_else(|| {
+        ServerError::Internal("Embedding provider returned no vector for the probe".to_string())
+    })?;
+    if let Some(problem) = vector_problem(&vector, None) {
+        return Err(ServerError::Internal(format!(
+            "Embedding provider returned an invalid probe vector: {problem}"
+        )));
+    }
+    Ok(ProviderProbe {
+        provider: provider.get_provider_name().to_string(),
+        model: provider.get_model_name().to_string(),
+        dimension: vector.len(),
+        latency_ms,
+    })
+}
+
+/// Probe vectors of a query and a document model whose cosine similarity is below this
+/// come from different spaces; matched models agree closely, unrelated ones are near 0
+pub const SAME_SPACE_MIN_SIMILARITY: f32 = 0.5;
+
+/// How a query-time model's vectors compare with the document model's for the same text
+#[derive(Debug, Clone)]
+pub struct ModelComparison {
+    pub document_model: String,
+    pub query_model: String,
+    pub document_dimension: usize,
+    pub query_dimension: usize,
+    /// Cosine similarity of the two probe vectors, `None` when the dimensions differ
+    pub similarity: Option<f32>,
+}
+
+impl ModelComparison {
+    /// Why queries embedded with the query model can't search the document model's vectors
+    pub fn problem(&self) -> Option<String> {
+        match self.similarity {
+            None => Some(format!(
+                "query model {} returns {}-dimensional vectors but document model {} returns {}; searches will fail",
+                self.query_model, self.query_dimension, self.document_model, self.document_dimension
+            )),
+            Some(similarity) if similarity < SAME_SPACE_MIN_SIMILARITY => Some(format!(
+                "query model {} and document model {} don't share a vector space (probe similarity {similarity:.2} < {SAME_SPACE_MIN_SIMILARITY}); results will be meaningless",
+                self.query_model, self.document_model
+            )),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Embed [`PROBE_TEXT`] with both models to check the query model can search documents
+/// embedded with the document model
+pub async fn compare_models(
+    document: &(dyn EmbeddingProvider + Send + Sync),
+    query: &(dyn EmbeddingProvider + Send + Sync),
+) -> Result<ModelComparison, ServerError> {
+    let probe = [PROBE_TEXT.to_string()];
+    let (document_vectors, _) = document.generate_embeddings(&probe).await?;
+    let (query_vectors, _) = query.generate_embeddings(&probe).await?;
+    let (Some(document_vector), Some(query_vector)) =
+        (document_vectors.first(), query_vectors.first())
+    else {
+        return Err(ServerError::Internal(
+            "Embedding provider returned no vector for the probe".to_string(),
+        ));
+    };
+
+    let similarity = (document_vector.len() == query_vector.len()).then(|| {
+        cosine_similarity(
+            ArrayView1::from(document_vector.as_slice()),
+            ArrayView1::from(query_vector.as_slice()),
+        )
+    });
+    Ok(ModelComparison {
+        document_model: document.get_model_name().to_string(),
+        query_model: query.get_model_name().to_string(),
+        document_dimension: document_vector.len(),
+        query_dimension: query_vector.len(),
+        similarity,
+    })
+}
+
+/// Parse an ordered provider list such as `voyage:voyage-3.5,openai`
+///
+/// Each entry is `provider[:model]`; a missing model means the provider's default.
+#[allow(dead_code)] // Used by the HTTP server
+pub fn parse_provider_list(spec: &str) -> Result<Vec<(String, Option<String>)>, ServerError> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (provider, model) = match entry.split_once(':') {
+                Some((provider, model)) => (provider.trim(), Some(model.trim().to_string())),
+                None => (entry, None),
+            };
+            match provider.to_lowercase().as_str() {
+                name @ ("openai" | "voyage") => {
+                    Ok((name.to_string(), model.filter(|m| !m.is_empty())))
+                }
+                _ => Err(ServerError::Config(format!(
+                    "Unsupported embedding provider in '{entry}'. Use 'openai' or 'voyage'"
+                ))),
+            }
+        })
+        .collect()
+}
+
+/// Embed query texts with the primary provider, falling back down the list on failure
+///
+/// Vectors from different models live in different spaces, so a fallback is only
+/// tried when its model is the one `crate_model` (the model the crate was populated
+/// with) names. Crates without a recorded model only ever use the primary. When the
+/// primary is a different model from the crate's, a matching fallback is tried first,
+/// since only it produces vectors of the crate's dimension. Returns the embeddings
+/// together with the model that produced them.
+#[allow(dead_code)] // Used by the HTTP server
+pub async fn embed_with_fallback(
+    primary: &Arc<dyn EmbeddingProvider + Send + Sync>,
+    fallbacks: &[Arc<dyn EmbeddingProvider + Send + Sync>],
+    crate_model: Option<&str>,
+    texts: &[String],
+) -> Result<(Vec<Vec<f32>>, String), ServerError> {
+    let compatible = fallbacks
+        .iter()
+        .filter(|provider| crate_model == Some(provider.get_model_name()));
+    let mut candidates: Vec<_> = std::iter::once(primary).chain(compatible).collect();
+    // Stable, so the primary still leads whenever it matches (or nothing is recorded)
+    candidates.sort_by_key(|provider| {
+        crate_model.is_some_and(|model| model != provider.get_model_name())
+    });
+    let mut errors = Vec::new();
+    for provider in candidates {
+        let model = provider.get_model_name();
+        match provider.generate_embeddings(texts).await {
+            Ok((embeddings, _tokens)) if embeddings.len() == texts.len() => {
+                if !Arc::ptr_eq(provider, primary) {
+                    eprintln!("🔁 Embedded query with fallback model {model}");
+                }
+                return Ok((embeddings, model.to_string()));
+            }
+            Ok((embeddings, _tokens)) => {
+                let error = format!(
+                    "{model}: expected {} embeddings, got {}",
+                    texts.len(),
+                    embeddings.len()
+                );
+                eprintln!("⚠️  Embedding failed with {error}");
+                errors.push(error);
+            }
+            Err(e) => {
+                eprintln!("⚠️  Embedding failed with {model}: {e}");
+                errors.push(format!("{model}: {e}"));
+            }
+        }
+    }
+
+    let skipped = fallbacks.len() + 1 - errors.len();
+    let note = if skipped > 0 {
+        format!(
+            " ({skipped} fallback provider(s) skipped: the crate was embedded with {})",
+            crate_model.unwrap_or("an unrecorded model")
+        )
+    } else {
+        String::new()
+    };
+    Err(ServerError::Internal(format!(
+        "All embedding providers failed: {}{note}",
+        errors.join("; ")
+    )))
+}
+
+/// Split a document into `(path, content)` chunks that fit the embedding token limit
+///
+/// Documents that fit are returned whole under their own path; split documents get
+/// `"{path} [chunk i/n]"` paths.
+pub fn chunk_document(doc: &Document, bpe: &tiktoken_rs::CoreBPE) -> Vec<(String, String)> {
+    let token_count = bpe.encode_with_special_tokens(&doc.content).len();
+    if token_count <= TOKEN_LIMIT {
+        return vec![(doc.path.clone(), doc.content.clone())];
+    }
+
+    eprintln!(
+        "    Document ({token_count} tokens) exceeds limit, chunking: {}",
+        doc.path
+    );
+    let chunks = _chunk_content(&doc.content, bpe, TOKEN_LIMIT - CHUNK_OVERLAP);
+    let chunk_count = chunks.len();
+    eprintln!("    Split into {chunk_count} chunks");
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(chunk_index, chunk)| {
+            let chunk_path = if chunk_count > 1 {
+                format!("{} [chunk {}/{}]", doc.path, chunk_index + 1, chunk_count)
+            } else {
+                doc.path.clone()
+            };
+            (chunk_path, chunk)
+        })
+        .collect()
+}
+
+/// Why an embedding can't be stored, or `None` if it looks sane
+///
+/// Provider hiccups have returned all-zero and NaN vectors; stored, those match every
+/// query or break the cosine ordering. `expected_dimension` also checks the length.
+pub fn vector_problem(vector: &[f32], expected_dimension: Option<usize>) -> Option<String> {
+    if let Some(expected) = expected_dimension {
+        if vector.len() != expected {
+            return Some(format!(
+                "has {} dimensions, expected {expected}",
+                vector.len()
+            ));
+        }
+    }
+    if vector.is_empty() {
+        return Some("is empty".to_string());
+    }
+    if vector.iter().any(|v| !v.is_finite()) {
+        return Some("contains NaN or infinite values".to_string());
+    }
+    if vector.iter().all(|&v| v == 0.0) {
+        return Some("is all zeros".to_string());
+    }
+    None
+}
+
+/// Embed one chunk, truncating only what the model would reject
+///
+/// Chunks from [`chunk_document`] always fit; callers embedding stored content directly
+/// rely on this to cut what doesn't.
+///
+/// A zero or non-finite vector is requested once more before the chunk fails.
+pub async fn embed_chunk(
+    provider: &(dyn EmbeddingProvider + Send + Sync),
+    bpe: &tiktoken_rs::CoreBPE,
+    path: &str,
+    content: String,
+    truncation: TruncationStrategy,
+) -> Result<Array1<f32>, ServerError> {
+    let input = match truncate_for_embedding(&content, bpe, MODEL_TOKEN_LIMIT, truncation) {
+        Some(truncated) => {
+            eprintln!(
+                "    ✂️  Truncated {path} to {MODEL_TOKEN_LIMIT} tokens (strategy: {truncation})"
+            );
+            truncated
+        }
+        None => content,
+    };
+
+    let mut retried = false;
+    loop {
+        let (embeddings, _tokens) = provider
+            .generate_embeddings(std::slice::from_ref(&input))
+            .await?;
+        if embeddings.len() != 1 {
+            return Err(ServerError::Internal(format!(
+                "Mismatch in response length for {path}. Expected 1, got {}.",
+                embeddings.len()
+            )));
+        }
+        let embedding = embeddings.into_iter().next().unwrap(); // Safe unwrap due to check above
+        match vector_problem(&embedding, None) {
+            None => return Ok(Array1::from(embedding)),
+            Some(problem) if !retried => {
+                eprintln!("    ⚠️  Embedding for {path} {problem}; retrying once");
+                retried = true;
+            }
+            Some(problem) => {
+                return Err(ServerError::Internal(format!(
+                    "Invalid embedding for {path}: {problem} (after retry)"
+                )));
+            }
+        }
+    }
+}
+
 /// Generates embeddings for a list of documents using the configured provider with chunking support.
 #[allow(dead_code)]
 pub async fn generate_embeddings(
@@ -281,9 +935,7 @@ pub async fn generate_embeddings(
 ) -> Result<(Vec<(String, String, Array1<f32>)>, usize), ServerError> {
     // Return tuple: (path, content, embedding), total_tokens
     // Get the embedding provider
-    let provider = EMBEDDING_CLIENT
-        .get()
-        .ok_or_else(|| ServerError::Internal("Embedding provider not initialized".to_string()))?;
+    let provider = embedding_provider()?;
 
     let model = provider.get_model_name();
     eprintln!(
@@ -294,39 +946,15 @@ pub async fn generate_embeddings(
 
     // Get the tokenizer for the model and wrap in Arc
     let bpe = Arc::new(cl100k_base().map_err(|e| ServerError::Tiktoken(e.to_string()))?);
+    let truncation = TruncationStrategy::from_env()?;
 
     const CONCURRENCY_LIMIT: usize = 8; // Number of concurrent requests
-    const TOKEN_LIMIT: usize = 8000; // Keep a buffer below the 8192 limit
-    const CHUNK_OVERLAP: usize = 200; // Token overlap between chunks for context
 
     // First, prepare all chunks with their metadata
     let mut all_chunks = Vec::new();
     for (doc_index, doc) in documents.iter().enumerate() {
-        let token_count = bpe.encode_with_special_tokens(&doc.content).len();
-
-        if token_count > TOKEN_LIMIT {
-            eprintln!(
-                "    Document {}/{} ({} tokens) exceeds limit, chunking: {}",
-                doc_index + 1,
-                documents.len(),
-                token_count,
-                doc.path
-            );
-
-            let chunks = _chunk_content(&doc.content, &bpe, TOKEN_LIMIT - CHUNK_OVERLAP);
-            let chunk_count = chunks.len();
-            eprintln!("    Split into {chunk_count} chunks");
-
-            for (chunk_index, chunk) in chunks.into_iter().enumerate() {
-                let chunk_path = if chunk_count > 1 {
-                    format!("{} [chunk {}/{}]", doc.path, chunk_index + 1, chunk_count)
-                } else {
-                    doc.path.clone()
-                };
-                all_chunks.push((doc_index, chunk_path, chunk));
-            }
-        } else {
-            all_chunks.push((doc_index, doc.path.clone(), doc.content.clone()));
+        for (chunk_path, chunk) in chunk_document(doc, &bpe) {
+            all_chunks.push((doc_index, chunk_path, chunk));
         }
     }
 
@@ -340,7 +968,7 @@ pub async fn generate_embeddings(
     let results = stream::iter(all_chunks.into_iter().enumerate())
         .map(|(chunk_index, (_doc_index, path, content))| {
             // Clone provider and other data for the async block
-            let provider = Arc::clone(provider);
+            let provider = Arc::clone(&provider);
             let bpe = Arc::clone(&bpe); // Clone the Arc pointer
             let content_clone = content.clone(); // Clone content for returning
 
@@ -348,9 +976,6 @@ pub async fn generate_embeddings(
                 // Calculate token count for this chunk
                 let token_count = bpe.encode_with_special_tokens(&content).len();
 
-                // Prepare input for this chunk
-                let inputs: Vec<String> = vec![content];
-
                 if chunk_index % 10 == 0 || chunk_index == total_chunks - 1 {
                     eprintln!(
                         "    Processing chunk {}/{} ({} tokens): {}",
@@ -361,20 +986,8 @@ pub async fn generate_embeddings(
                     );
                 }
 
-                // Use the provider to generate embeddings
-                let (embeddings, _tokens) = provider.generate_embeddings(&inputs).await?;
-
-                if embeddings.len() != 1 {
-                    return Err(ServerError::Internal(format!(
-                        "Mismatch in response length for chunk {}. Expected 1, got {}.",
-                        chunk_index + 1,
-                        embeddings.len()
-                    )));
-                }
-
-                // Process result
-                let embedding_data = embeddings.into_iter().next().unwrap(); // Safe unwrap due to check above
-                let embedding_array = Array1::from(embedding_data);
+                let embedding_array =
+                    embed_chunk(provider.as_ref(), &bpe, &path, content, truncation).await?;
                 // Return successful embedding with path, content, and token count
                 Ok((path, content_clone, embedding_array, token_count))
             }
@@ -0,0 +1,409 @@
+//! Population estimates: what adding a crate will cost before anything is crawled or stored
+//!
+//! [`estimate_population`] fetches the crate's `all.html` item index, which rustdoc
+//! generates for every crate, to count the pages a crawl would visit, then fetches a few of
+//! them spread over the index to measure tokens and chunks per page. [`project`] scales the
+//! sample to the whole crate and prices it. Nothing is written to the database.
+
+use crate::{
+    doc_loader::{self, CrawlOptions, DocLoaderError, UrlFilter},
+    embeddings,
+};
+use scraper::{Html, Selector};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+/// Pages measured unless the caller asks for another number
+pub const DEFAULT_SAMPLE_PAGES: usize = 5;
+
+/// Most pages one estimate fetches, so estimating stays cheaper than crawling
+pub const MAX_SAMPLE_PAGES: usize = 20;
+
+/// USD per million input tokens of the embedding models with published prices
+pub const EMBEDDING_PRICES: &[(&str, f64)] = &[
+    ("text-embedding-3-large", 0.13),
+    ("text-embedding-3-small", 0.02),
+    ("text-embedding-ada-002", 0.10),
+    ("voyage-3-large", 0.18),
+    ("voyage-3.5", 0.06),
+    ("voyage-3.5-lite", 0.02),
+    ("voyage-3", 0.06),
+    ("voyage-3-lite", 0.02),
+    ("voyage-code-3", 0.18),
+    ("voyage-code-2", 0.12),
+    ("voyage-large-2", 0.12),
+    ("voyage-2", 0.10),
+];
+
+/// Assumed round trip of one embedding request; the pipeline embeds one chunk per request
+pub const EMBED_REQUEST_SECS: f64 = 0.4;
+
+/// Price of `model` in USD per million tokens, if it is in [`EMBEDDING_PRICES`]
+pub fn price_per_million(model: &str) -> Option<f64> {
+    EMBEDDING_PRICES
+        .iter()
+        .find(|(name, _)| *name == model)
+        .map(|&(_, price)| price)
+}
+
+/// Pages a crawl of the crate would visit, relative to its docs root
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ItemIndex {
+    /// `index.html` of the root and of every module that has items
+    pub modules: Vec<String>,
+    /// Item pages (`struct.Foo.html`, `sync/fn.bar.html`) in the order `all.html` lists them
+    pub items: Vec<String>,
+}
+
+impl ItemIndex {
+    /// Module pages first, root leading, then items
+    pub fn pages(&self) -> Vec<String> {
+        self.modules.iter().chain(&self.items).cloned().collect()
+    }
+}
+
+/// The pages listed by a crate's `all.html`
+///
+/// `all.html` lists items, not modules, so each directory an item lives in adds that
+/// module's `index.html`.
+pub fn parse_all_items(html: &str) -> ItemIndex {
+    let document = Html::parse_document(html);
+    let links = Selector::parse("a[href]").expect("valid link selector");
+    let mut seen = BTreeSet::new();
+    let mut items = Vec::new();
+    let mut modules = BTreeSet::new();
+    for href in document
+        .select(&links)
+        .filter_map(|link| link.value().attr("href"))
+    {
+        let href = href.split(['#', '?']).next().unwrap_or_default();
+        if href.ends_with("index.html") || !doc_loader::is_item_page_link(href) {
+            continue;
+        }
+        if seen.insert(href.to_string()) {
+            items.push(href.to_string());
+            let mut dirs: Vec<&str> = href.split('/').collect();
+            dirs.pop();
+            for depth in 1..=dirs.len() {
+                modules.insert(format!("{}/index.html", dirs[..depth].join("/")));
+            }
+        }
+    }
+    ItemIndex {
+        modules: std::iter::once("index.html".to_string())
+            .chain(modules)
+            .collect(),
+        items,
+    }
+}
+
+/// Indices of `count` pages spread evenly over `len`, starting with the first
+pub fn sample_indices(len: usize, count: usize) -> Vec<usize> {
+    if count >= len {
+        return (0..len).collect();
+    }
+    (0..count).map(|i| i * len / count).collect()
+}
+
+/// One fetched page of the sample
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageSample {
+    pub url: String,
+    /// Tokens the page's chunks would be embedded with
+    pub tokens: usize,
+    /// Chunks the page yields, one per docblock section
+    pub chunks: usize,
+    pub fetch_ms: u64,
+}
+
+/// What the estimate assumes about the population
+#[derive(Debug, Clone, PartialEq)]
+pub struct EstimateSettings {
+    /// Pages to measure, at most [`MAX_SAMPLE_PAGES`]
+    pub sample_pages: usize,
+    /// The crawl's page limit
+    pub max_pages: usize,
+    /// Embedding model, for pricing
+    pub model: String,
+    /// Embedding requests in flight at once (`PIPELINE_EMBED_CONCURRENCY`)
+    pub embed_concurrency: usize,
+    /// Pause between page fetches, raised to the host's robots.txt crawl-delay
+    pub page_delay: Duration,
+}
+
+/// How far the sample can be trusted
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Confidence {
+    /// `high`, `medium` or `low`
+    pub level: &'static str,
+    /// Standard error of the token total relative to it; `None` below two samples of a
+    /// crate with more pages
+    pub relative_error: Option<f64>,
+}
+
+/// Projected wall time of the population, in seconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DurationEstimate {
+    /// Fetching every page one after another, with the page delay between fetches
+    pub crawl_secs: f64,
+    /// Embedding every chunk at the configured concurrency
+    pub embed_secs: f64,
+    /// Crawling and embedding overlap, so the slower of the two
+    pub total_secs: f64,
+}
+
+/// The projected size, cost and duration of populating a crate
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PopulationEstimate {
+    /// Version docs.rs served the index for
+    pub version: Option<String>,
+    /// Pages the crawl would visit, after URL patterns and `max_pages`
+    pub pages: usize,
+    /// Item pages `all.html` lists
+    pub listed_items: usize,
+    /// The crawl would stop at `max_pages` before every listed page
+    pub capped_at_max_pages: bool,
+    pub avg_tokens_per_page: f64,
+    pub avg_chunks_per_page: f64,
+    pub total_tokens: u64,
+    /// Two standard errors either side of `total_tokens`
+    pub total_tokens_range: Option<(u64, u64)>,
+    pub chunks: u64,
+    pub model: String,
+    pub usd_per_million_tokens: Option<f64>,
+    /// `None` when the model's price is unknown
+    pub estimated_cost_usd: Option<f64>,
+    pub duration: DurationEstimate,
+    pub confidence: Confidence,
+    pub sampled_pages: Vec<PageSample>,
+    /// What the estimate leaves out
+    pub notes: Vec<String>,
+}
+
+/// Scale `samples` of a crate with `pages` pages to the whole crate
+pub fn project(
+    pages: usize,
+    listed_items: usize,
+    samples: Vec<PageSample>,
+    settings: &EstimateSettings,
+) -> PopulationEstimate {
+    let n = samples.len();
+    let mean = |value: fn(&PageSample) -> f64| {
+        if n == 0 {
+            0.0
+        } else {
+            samples.iter().map(value).sum::<f64>() / n as f64
+        }
+    };
+    let avg_tokens = mean(|s| s.tokens as f64);
+    let avg_chunks = mean(|s| s.chunks as f64);
+    let avg_fetch_secs = mean(|s| s.fetch_ms as f64 / 1000.0);
+    let total_tokens = (avg_tokens * pages as f64).round() as u64;
+    let chunks = (avg_chunks * pages as f64).round() as u64;
+
+    let relative_error = if n >= pages && n > 0 {
+        Some(0.0)
+    } else if n < 2 || avg_tokens == 0.0 {
+        None
+    } else {
+        let variance = samples
+            .iter()
+            .map(|s| (s.tokens as f64 - avg_tokens).powi(2))
+            .sum::<f64>()
+            / (n - 1) as f64;
+        // Sampling without replacement from a finite set of pages
+        let correction = ((pages - n) as f64 / (pages - 1) as f64).sqrt();
+        Some(variance.sqrt() / avg_tokens / (n as f64).sqrt() * correction)
+    };
+    let level = match relative_error {
+        Some(error) if error <= 0.15 => "high",
+        Some(error) if error <= 0.35 => "medium",
+        _ => "low",
+    };
+    let total_tokens_range = relative_error.map(|error| {
+        let spread = total_tokens as f64 * 2.0 * error;
+        (
+            (total_tokens as f64 - spread).max(0.0).round() as u64,
+            (total_tokens as f64 + spread).round() as u64,
+        )
+    });
+
+    let price = price_per_million(&settings.model);
+    let crawl_secs = pages as f64 * (settings.page_delay.as_secs_f64() + avg_fetch_secs);
+    let embed_secs = chunks as f64 * EMBED_REQUEST_SECS / settings.embed_concurrency.max(1) as f64;
+    let mut notes = Vec::new();
+    if price.is_none() {
+        notes.push(format!(
+            "no published price for {}; see estimate::EMBEDDING_PRICES",
+            settings.model
+        ));
+    }
+    PopulationEstimate {
+        version: None,
+        pages,
+        listed_items,
+        capped_at_max_pages: false,
+        avg_tokens_per_page: avg_tokens,
+        avg_chunks_per_page: avg_chunks,
+        total_tokens,
+        total_tokens_range,
+        chunks,
+        model: settings.model.clone(),
+        usd_per_million_tokens: price,
+        estimated_cost_usd: price.map(|price| total_tokens as f64 / 1_000_000.0 * price),
+        duration: DurationEstimate {
+            crawl_secs,
+            embed_secs,
+            total_secs: crawl_secs.max(embed_secs),
+        },
+        confidence: Confidence {
+            level,
+            relative_error,
+        },
+        sampled_pages: samples,
+        notes,
+    }
+}
+
+/// Tokens and chunks of one page, as the crawler would extract them
+fn measure_page(
+    html: &str,
+    relative_path: &str,
+    content_selector: &Selector,
+    bpe: &tiktoken_rs::CoreBPE,
+) -> (usize, usize) {
+    let document = Html::parse_document(html);
+    let documents = doc_loader::page_documents(relative_path, &document, content_selector);
+    let tokens = documents
+        .iter()
+        .map(|doc| {
+            bpe.encode_with_special_tokens(&doc.content)
+                .len()
+                .min(embeddings::TOKEN_LIMIT)
+        })
+        .sum();
+    (tokens, documents.len())
+}
+
+/// Estimate populating `crate_name` as `options` would crawl it, from its `all.html` and
+/// a sample of the pages it lists
+///
+/// Follows robots.txt like the crawler does. Fails when the index can't be fetched, or
+/// when none of the sampled pages can.
+pub async fn estimate_population(
+    crate_name: &str,
+    options: &CrawlOptions,
+    settings: &EstimateSettings,
+) -> Result<PopulationEstimate, DocLoaderError> {
+    let origin = options
+        .docs_origin
+        .as_deref()
+        .unwrap_or(doc_loader::DOCS_RS_ORIGIN)
+        .trim_end_matches('/');
+    let origin_prefix = format!("{origin}/");
+    let base_url = doc_loader::crate_root_url(
+        &origin_prefix,
+        crate_name,
+        options.version.as_deref(),
+        options.target.as_deref(),
+    )?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent(crate::robots::user_agent())
+        .build()
+        .map_err(|e| DocLoaderError::Network(e.to_string()))?;
+    let robots = crate::robots::fetch_robots(&client, origin).await;
+    let path = reqwest::Url::parse(&base_url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_default();
+    if !options.ignore_robots && !robots.is_allowed(&path) {
+        return Err(DocLoaderError::RobotsDisallowed(base_url));
+    }
+    let page_delay = match robots.crawl_delay() {
+        Some(delay) if !options.ignore_robots && delay > settings.page_delay => delay,
+        _ => settings.page_delay,
+    };
+    let settings = &EstimateSettings {
+        page_delay,
+        ..settings.clone()
+    };
+
+    let index_url = format!("{base_url}all.html");
+    let response = client
+        .get(&index_url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| DocLoaderError::Network(format!("{index_url}: {e}")))?;
+    let version = doc_loader::version_from_docs_url(response.url().as_str(), &origin_prefix);
+    let index = parse_all_items(&response.text().await?);
+
+    let filter = UrlFilter::new(&options.include_url_patterns, &options.exclude_url_patterns)?;
+    let mut pages: Vec<String> = index
+        .pages()
+        .into_iter()
+        .filter(|page| page == "index.html" || filter.allows(&format!("{base_url}{page}")))
+        .collect();
+    let capped = pages.len() > settings.max_pages;
+    pages.truncate(settings.max_pages.max(1));
+
+    let content_selector = doc_loader::parse_content_selector(options.content_selector.as_deref())?;
+    let bpe = tiktoken_rs::cl100k_base().map_err(|e| DocLoaderError::Parsing(e.to_string()))?;
+    let mut samples = Vec::new();
+    let mut failed = Vec::new();
+    for (i, index) in sample_indices(pages.len(), settings.sample_pages.min(MAX_SAMPLE_PAGES))
+        .into_iter()
+        .enumerate()
+    {
+        if i > 0 {
+            tokio::time::sleep(page_delay).await;
+        }
+        let url = format!("{base_url}{}", pages[index]);
+        let started = Instant::now();
+        let body = match client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(response) => response.text().await,
+            Err(e) => Err(e),
+        };
+        match body {
+            Ok(html) => {
+                let fetch_ms = started.elapsed().as_millis() as u64;
+                let (tokens, chunks) = measure_page(&html, &pages[index], &content_selector, &bpe);
+                samples.push(PageSample {
+                    url,
+                    tokens,
+                    chunks,
+                    fetch_ms,
+                });
+            }
+            Err(e) => failed.push(format!("{url}: {e}")),
+        }
+    }
+    if samples.is_empty() && !pages.is_empty() {
+        return Err(DocLoaderError::Network(format!(
+            "none of the sampled pages of {crate_name} could be fetched: {}",
+            failed.join("; ")
+        )));
+    }
+
+    let mut estimate = project(pages.len(), index.items.len(), samples, settings);
+    estimate.version = version;
+    estimate.capped_at_max_pages = capped;
+    estimate.notes.extend(
+        failed
+            .into_iter()
+            .map(|failure| format!("sample page skipped: {failure}")),
+    );
+    if options.follow_reexports {
+        estimate.notes.push(
+            "pages of re-exported crates are not counted; follow_reexports adds up to reexport_page_budget more"
+                .to_string(),
+        );
+    }
+    Ok(estimate)
+}
@@ -0,0 +1,158 @@
+use crate::database::ScoredDoc;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+/// Days after population before query results carry a staleness note
+pub const DEFAULT_STALE_AFTER_DAYS: u32 = 90;
+
+/// How long a docs.rs latest-version lookup (including a failed one) is reused
+const LATEST_VERSION_TTL: Duration = Duration::from_secs(3600);
+
+/// Queries wait at most this long for docs.rs before answering without the version check
+const LATEST_VERSION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Note to append to query results when a crate's docs may be outdated
+///
+/// Returns `None` while the docs are younger than `stale_after_days` and, when the
+/// latest release is known, the stored version still matches it.
+pub fn staleness_note(
+    populated_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    stale_after_days: u32,
+    populated_version: Option<&str>,
+    latest_version: Option<&str>,
+) -> Option<String> {
+    let age_days = (now - populated_at).num_days().max(0);
+    let outdated = match (populated_version, latest_version) {
+        (Some(populated), Some(latest)) => populated != latest,
+        _ => false,
+    };
+    if age_days < i64::from(stale_after_days) && !outdated {
+        return None;
+    }
+
+    let mut details = vec![match age_days {
+        0 => "populated today".to_string(),
+        1 => "populated 1 day ago".to_string(),
+        days => format!("populated {days} days ago"),
+    }];
+    if let Some(populated) = populated_version.filter(|_| outdated) {
+        details.push(format!("stored version is {populated}"));
+    }
+    if let Some(latest) = latest_version {
+        details.push(format!("current latest is {latest}"));
+    }
+    Some(format!(
+        "⚠️ Note: docs may be stale ({})",
+        details.join("; ")
+    ))
+}
+
+/// Footer naming the crate version and indexing date(s) behind a set of results,
+/// e.g. `docs from tokio 1.38.0, indexed 2024-05-02`
+///
+/// A date range means the shown results come from different refreshes.
+pub fn format_freshness_footer(crate_name: &str, docs: &[ScoredDoc]) -> Option<String> {
+    let version = docs.iter().find_map(|doc| doc.crate_version.as_deref());
+    let dates = docs.iter().filter_map(|doc| doc.last_updated_at);
+    let indexed = match (dates.clone().min(), dates.max()) {
+        (Some(oldest), Some(newest)) if oldest.date_naive() != newest.date_naive() => {
+            Some(format!(
+                "indexed {} to {}",
+                oldest.format("%Y-%m-%d"),
+                newest.format("%Y-%m-%d")
+            ))
+        }
+        (_, Some(newest)) => Some(format!("indexed {}", newest.format("%Y-%m-%d"))),
+        _ => None,
+    };
+
+    let source = match version {
+        Some(version) => format!("docs from {crate_name} {version}"),
+        None => format!("docs from {crate_name}"),
+    };
+    match (version, indexed) {
+        (_, Some(indexed)) => Some(format!("{source}, {indexed}")),
+        (Some(_), None) => Some(source),
+        (None, None) => None,
+    }
+}
+
+#[derive(Deserialize)]
+struct DocsRsStatus {
+    version: String,
+}
+
+/// Latest released version per crate from docs.rs, cached for an hour
+#[derive(Debug)]
+pub struct LatestVersions {
+    client: reqwest::Client,
+    origin: String,
+    cache: Mutex<HashMap<String, (Instant, Option<String>)>>,
+}
+
+impl LatestVersions {
+    /// Look versions up on `origin` (normally [`crate::doc_loader::DOCS_RS_ORIGIN`])
+    pub fn new(origin: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(LATEST_VERSION_TIMEOUT)
+            .user_agent(crate::robots::user_agent())
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            origin: origin.trim_end_matches('/').to_string(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Latest version docs.rs has built for `crate_name`, or `None` if it can't be determined
+    pub async fn latest_version(&self, crate_name: &str) -> Option<String> {
+        if let Some((checked_at, version)) = self
+            .cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(crate_name)
+        {
+            if checked_at.elapsed() < LATEST_VERSION_TTL {
+                return version.clone();
+            }
+        }
+
+        let url = format!("{}/crate/{crate_name}/latest/status.json", self.origin);
+        let version = match self.client.get(&url).send().await {
+            Ok(response) if response.status().is_success() => {
+                match response.json::<DocsRsStatus>().await {
+                    Ok(status) => Some(status.version),
+                    Err(e) => {
+                        debug!("Unexpected docs.rs status response for {crate_name}: {e}");
+                        None
+                    }
+                }
+            }
+            Ok(response) => {
+                debug!(
+                    "docs.rs status for {crate_name} returned HTTP {}",
+                    response.status()
+                );
+                None
+            }
+            Err(e) => {
+                debug!("Failed to check latest version of {crate_name}: {e}");
+                None
+            }
+        };
+
+        self.cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(crate_name.to_string(), (Instant::now(), version.clone()));
+        version
+    }
+}
@@ -0,0 +1,130 @@
+//! Hypothetical-document (HyDE) query expansion
+//!
+//! Vague questions often embed far from the docs that answer them. Asking an LLM to draft
+//! a short doc snippet that *would* answer the question and embedding that draft alongside
+//! the question tends to land closer to the real pages. Each expansion costs one chat
+//! completion call (typically 1-3 s and a few hundred tokens), so it is opt-in per query.
+
+use crate::error::ServerError;
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+        CreateChatCompletionRequestArgs,
+    },
+    Client as OpenAIClient,
+};
+use std::{env, time::Duration};
+
+/// Chat model for drafts when neither `HYDE_MODEL` nor `LLM_MODEL` is set
+pub const DEFAULT_HYDE_MODEL: &str = "gpt-4o-mini-2024-07-18";
+
+/// Longest a draft may take before the query goes ahead with the plain question
+pub const DEFAULT_HYDE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Drafts are snippets, not essays; this also bounds the cost per query
+const HYDE_MAX_TOKENS: u32 = 200;
+
+/// Drafts hypothetical documentation snippets with an OpenAI-compatible chat model
+#[derive(Clone)]
+pub struct HydeClient {
+    client: OpenAIClient<OpenAIConfig>,
+    model: String,
+    timeout: Duration,
+}
+
+impl HydeClient {
+    pub fn new(client: OpenAIClient<OpenAIConfig>, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+            timeout: DEFAULT_HYDE_TIMEOUT,
+        }
+    }
+
+    /// Client for `OPENAI_API_BASE`/`OPENAI_API_KEY`, the same endpoint the stdio server
+    /// uses for its answers
+    pub fn from_env(model: Option<String>) -> Self {
+        let client = match env::var("OPENAI_API_BASE") {
+            Ok(api_base) => OpenAIClient::with_config(OpenAIConfig::new().with_api_base(api_base)),
+            Err(_) => OpenAIClient::new(),
+        };
+        let model = model
+            .or_else(|| env::var("LLM_MODEL").ok())
+            .unwrap_or_else(|| DEFAULT_HYDE_MODEL.to_string());
+        Self::new(client, &model)
+    }
+
+    #[allow(dead_code)] // Used by tests
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    #[allow(dead_code)] // Used by the HTTP server
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Draft a short doc snippet answering `question` about `crate_name`
+    pub async fn draft(&self, crate_name: &str, question: &str) -> Result<String, ServerError> {
+        let system_prompt = format!(
+            "You write rustdoc documentation for the Rust crate '{crate_name}'. \
+             Write the short documentation passage (at most 120 words, optionally with a \
+             one-line code example) that would answer the user's question. \
+             Write only the passage, as it would appear on docs.rs."
+        );
+        let request = CreateChatCompletionRequestArgs::default()
+            .model(&self.model)
+            .max_completion_tokens(HYDE_MAX_TOKENS)
+            .messages(vec![
+                ChatCompletionRequestSystemMessageArgs::default()
+                    .content(system_prompt)
+                    .build()
+                    .map_err(|e| {
+                        ServerError::Internal(format!("Failed to build HyDE prompt: {e}"))
+                    })?
+                    .into(),
+                ChatCompletionRequestUserMessageArgs::default()
+                    .content(question)
+                    .build()
+                    .map_err(|e| {
+                        ServerError::Internal(format!("Failed to build HyDE prompt: {e}"))
+                    })?
+                    .into(),
+            ])
+            .build()
+            .map_err(|e| ServerError::Internal(format!("Failed to build HyDE request: {e}")))?;
+
+        let response = tokio::time::timeout(self.timeout, self.client.chat().create(request))
+            .await
+            .map_err(|_| {
+                ServerError::Network(format!("HyDE draft timed out after {:?}", self.timeout))
+            })?
+            .map_err(ServerError::OpenAI)?;
+
+        response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .map(|draft| draft.trim().to_string())
+            .filter(|draft| !draft.is_empty())
+            .ok_or_else(|| ServerError::Internal("HyDE draft was empty".to_string()))
+    }
+
+    /// `queries` plus a hypothetical answer to `question`, or `queries` unchanged when
+    /// the draft fails, so a flaky LLM never fails the search
+    pub async fn expand(
+        &self,
+        crate_name: &str,
+        question: &str,
+        mut queries: Vec<String>,
+    ) -> Vec<String> {
+        match self.draft(crate_name, question).await {
+            Ok(draft) => queries.push(draft),
+            Err(e) => eprintln!("⚠️  HyDE draft failed, searching with the question only: {e}"),
+        }
+        queries
+    }
+}
@@ -0,0 +1,113 @@
+//! Per-crate language filtering of documents before they are embedded
+//!
+//! Some crates ship doc sections in Chinese, Japanese or Russian next to the English ones.
+//! English-tuned embedding models place those poorly and they crowd out results for
+//! English questions, so a crate configuration can list the languages it keeps
+//! (`languages`, ISO 639-3 codes such as `eng`). With `language_action` `drop` (the
+//! default), documents detected as another language are not embedded; with `tag` they
+//! are stored with their language and `query_rust_docs` leaves them out. Text whose
+//! language can't be detected reliably (short snippets, mostly code) is always kept.
+
+use crate::{database::CrateConfig, error::ServerError};
+use std::fmt;
+use whatlang::Lang;
+
+/// What population does with documents in a language the crate doesn't keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageAction {
+    /// Leave them out of the index
+    Drop,
+    /// Store them with their language so queries can filter them out
+    Tag,
+}
+
+impl LanguageAction {
+    /// `drop` or `tag`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim() {
+            "drop" => Ok(Self::Drop),
+            "tag" => Ok(Self::Tag),
+            other => Err(format!(
+                "language_action must be 'drop' or 'tag', got '{other}'"
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Drop => "drop",
+            Self::Tag => "tag",
+        }
+    }
+}
+
+impl fmt::Display for LanguageAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Why `code` is not a language code the detector knows, if it isn't
+pub fn language_code_problem(code: &str) -> Option<&'static str> {
+    if Lang::from_code(code.trim()).is_some() {
+        None
+    } else {
+        Some("must be an ISO 639-3 language code the detector supports, e.g. 'eng', 'cmn', 'rus'")
+    }
+}
+
+/// The language of `text`, if it can be detected reliably
+pub fn detect(text: &str) -> Option<Lang> {
+    whatlang::detect(text)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang())
+}
+
+/// The languages a crate keeps and what happens to documents in other languages
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageFilter {
+    allowed: Vec<Lang>,
+    action: LanguageAction,
+}
+
+impl LanguageFilter {
+    /// A filter keeping `codes` (ISO 639-3), or `None` when no languages are listed
+    pub fn new(codes: &[String], action: Option<&str>) -> Result<Option<Self>, String> {
+        if codes.is_empty() {
+            return Ok(None);
+        }
+        let allowed = codes
+            .iter()
+            .map(|code| {
+                Lang::from_code(code.trim()).ok_or_else(|| {
+                    format!("'{code}' is not an ISO 639-3 language code the detector supports")
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let action = match action.map(str::trim).filter(|a| !a.is_empty()) {
+            Some(action) => LanguageAction::parse(action)?,
+            None => LanguageAction::Drop,
+        };
+        Ok(Some(Self { allowed, action }))
+    }
+
+    /// The filter of a crate configuration, if it lists languages
+    pub fn for_crate(config: &CrateConfig) -> Result<Option<Self>, ServerError> {
+        Self::new(&config.languages, config.language_action.as_deref())
+            .map_err(|e| ServerError::Config(format!("{}: {e}", config.name)))
+    }
+
+    pub fn action(&self) -> LanguageAction {
+        self.action
+    }
+
+    /// Whether documents in `lang` are kept as they are
+    pub fn allows(&self, lang: Lang) -> bool {
+        self.allowed.contains(&lang)
+    }
+
+    /// ISO 639-3 codes of the kept languages
+    pub fn codes(&self) -> Vec<&'static str> {
+        self.allowed.iter().map(|lang| lang.code()).collect()
+    }
+}
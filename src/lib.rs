@@ -1,8 +1,39 @@
 #![allow(clippy::uninlined_format_args)] // Allow format! style for consistency
 
 // Expose modules for use by binaries
+pub mod backup;
+pub mod client;
+pub mod crate_metadata;
+pub mod crate_status;
 pub mod database;
+pub mod db_breaker;
 pub mod doc_loader;
 pub mod embeddings;
 pub mod error;
+pub mod estimate;
+pub mod freshness;
+pub mod hyde;
+pub mod language;
+pub mod object_store;
+pub mod orientation;
+pub mod pipeline;
+pub mod profiles;
+pub mod provenance;
+pub mod proxy_config;
+pub mod query_cache;
+pub mod query_defaults;
+pub mod query_log;
+pub mod rate_limit;
+pub mod refresh;
+pub mod request_context;
+pub mod response;
+pub mod robots;
+pub mod rustdoc_json;
+pub mod search;
 pub mod server;
+pub mod sse;
+pub mod summarize;
+pub mod threshold;
+pub mod tool_args;
+pub mod validation;
+pub mod version_spec;
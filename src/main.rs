@@ -1,21 +1,35 @@
 // Declare modules
+mod crate_status;
 mod database;
 mod doc_loader;
 mod embeddings;
 mod error;
+mod hyde;
+mod query_cache;
+mod query_defaults;
+#[allow(dead_code)] // Used by the HTTP server
+mod query_log;
+mod request_context;
+mod robots;
+mod search;
 mod server;
+mod summarize;
+mod validation;
+mod version_spec;
 
 // Use necessary items from modules and crates
 use crate::{
     database::Database,
     embeddings::{initialize_embedding_provider, EmbeddingConfig, EMBEDDING_CLIENT},
     error::ServerError,
+    query_cache::QueryCache,
     server::RustDocsServer,
 };
 use async_openai::{config::OpenAIConfig, Client as OpenAIClient};
 use clap::Parser;
 use rmcp::{transport::io::stdio, ServiceExt};
 use std::env;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use std::collections::HashMap;
 
@@ -40,10 +54,63 @@ struct Cli {
     /// Embedding model to use
     #[arg(long)]
     embedding_model: Option<String>,
+
+    /// Embed questions with this model instead of the document model; it must return
+    /// vectors in the same space (checked at startup)
+    #[arg(long, env = "QUERY_EMBEDDING_MODEL")]
+    query_embedding_model: Option<String>,
+
+    /// Question embeddings kept so a retried query skips the embedding call (0 disables)
+    #[arg(long, default_value_t = 1000, env = "QUERY_CACHE_SIZE")]
+    query_cache_size: usize,
+
+    /// How long a cached question embedding stays valid, in seconds
+    #[arg(long, default_value_t = 3600, env = "QUERY_CACHE_TTL_SECS")]
+    query_cache_ttl_secs: u64,
+}
+
+/// Provider settings for `openai` or `voyage`, using the provider's default model when none is given
+fn embedding_config(
+    provider_name: &str,
+    model: Option<String>,
+) -> Result<EmbeddingConfig, ServerError> {
+    match provider_name {
+        "openai" => {
+            let model = model.unwrap_or_else(|| "text-embedding-3-large".to_string());
+            let openai_client = if let Ok(api_base) = env::var("OPENAI_API_BASE") {
+                let config = OpenAIConfig::new().with_api_base(api_base);
+                OpenAIClient::with_config(config)
+            } else {
+                OpenAIClient::new()
+            };
+            Ok(EmbeddingConfig::OpenAI {
+                client: openai_client,
+                model,
+            })
+        }
+        "voyage" => {
+            let api_key = env::var("VOYAGE_API_KEY")
+                .map_err(|_| ServerError::MissingEnvVar("VOYAGE_API_KEY".to_string()))?;
+            let model = model.unwrap_or_else(|| "voyage-3.5".to_string());
+            Ok(EmbeddingConfig::VoyageAI { api_key, model })
+        }
+        _ => Err(ServerError::Config(format!(
+            "Unsupported embedding provider: {provider_name}. Use 'openai' or 'voyage'"
+        ))),
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), ServerError> {
+    // Initialize tracing on stderr (stdout carries the MCP protocol)
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "rustdocs_mcp_server=info".into()),
+        )
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+
     // Load .env file if present
     dotenvy::dotenv().ok();
 
@@ -122,14 +189,15 @@ async fn main() -> Result<(), ServerError> {
 
     // Check if all crates exist in database
     eprintln!("🔍 Checking if crates exist in database...");
+    let presence = db.get_embeddings_presence(&crate_names).await?;
     let mut missing_crates = Vec::new();
     for crate_name in &crate_names {
-        eprintln!("  Checking: {crate_name}");
-        if !db.has_embeddings(crate_name).await? {
-            missing_crates.push(crate_name.clone());
-            eprintln!("  ❌ Missing: {crate_name}");
-        } else {
-            eprintln!("  ✅ Found: {crate_name}");
+        match presence.get(crate_name).copied().unwrap_or(0) {
+            0 => {
+                missing_crates.push(crate_name.clone());
+                eprintln!("  ❌ Missing: {crate_name}");
+            }
+            docs => eprintln!("  ✅ Found: {crate_name} ({docs} docs)"),
         }
     }
 
@@ -153,38 +221,37 @@ async fn main() -> Result<(), ServerError> {
     let provider_name = cli.embedding_provider.to_lowercase();
     eprintln!("🤖 Initializing {provider_name} embedding provider...");
 
-    let embedding_config = match provider_name.as_str() {
-        "openai" => {
-            let model = cli
-                .embedding_model
-                .unwrap_or_else(|| "text-embedding-3-large".to_string());
-            let openai_client = if let Ok(api_base) = env::var("OPENAI_API_BASE") {
-                let config = OpenAIConfig::new().with_api_base(api_base);
-                OpenAIClient::with_config(config)
-            } else {
-                OpenAIClient::new()
-            };
-            EmbeddingConfig::OpenAI {
-                client: openai_client,
-                model,
+    let document_provider = initialize_embedding_provider(embedding_config(
+        &provider_name,
+        cli.embedding_model.clone(),
+    )?);
+    // Queries may use a cheaper model from the same family as the one docs were embedded with
+    let provider = match cli
+        .query_embedding_model
+        .filter(|model| model != document_provider.get_model_name())
+    {
+        Some(query_model) => {
+            let query_provider =
+                initialize_embedding_provider(embedding_config(&provider_name, Some(query_model))?);
+            eprintln!(
+                "🔎 Query embedding model: {} (documents: {})",
+                query_provider.get_model_name(),
+                document_provider.get_model_name()
+            );
+            match embeddings::compare_models(document_provider.as_ref(), query_provider.as_ref())
+                .await
+            {
+                Ok(comparison) => {
+                    if let Some(problem) = comparison.problem() {
+                        eprintln!("🚨 QUERY_EMBEDDING_MODEL mismatch: {problem}");
+                    }
+                }
+                Err(e) => eprintln!("⚠️  Could not compare query and document models: {e}"),
             }
+            query_provider
         }
-        "voyage" => {
-            let api_key = env::var("VOYAGE_API_KEY")
-                .map_err(|_| ServerError::MissingEnvVar("VOYAGE_API_KEY".to_string()))?;
-            let model = cli
-                .embedding_model
-                .unwrap_or_else(|| "voyage-3.5".to_string());
-            EmbeddingConfig::VoyageAI { api_key, model }
-        }
-        _ => {
-            return Err(ServerError::Config(format!(
-                "Unsupported embedding provider: {provider_name}. Use 'openai' or 'voyage'"
-            )));
-        }
+        None => document_provider,
     };
-
-    let provider = initialize_embedding_provider(embedding_config);
     if EMBEDDING_CLIENT.set(provider).is_err() {
         return Err(ServerError::Internal(
             "Failed to set embedding provider".to_string(),
@@ -272,13 +339,21 @@ async fn main() -> Result<(), ServerError> {
         format!("multi-crate[{crates_joined}]")
     };
 
+    let query_defaults = query_defaults::QueryDefaults::from_env()?;
+    eprintln!("{}", query_defaults.describe(server::STDIO_RESULT_LIMIT));
+
     let service = RustDocsServer::new(
         combined_crate_name.clone(),
         vec![], // No documents in memory - use database search
         vec![], // No embeddings in memory - generate on demand
         db,
         startup_message,
-    )?;
+    )?
+    .with_question_cache(QueryCache::new(
+        cli.query_cache_size,
+        std::time::Duration::from_secs(cli.query_cache_ttl_secs),
+    ))
+    .with_query_defaults(query_defaults);
 
     eprintln!("Rust Docs MCP server starting via stdio...");
 
@@ -0,0 +1,313 @@
+//! Minimal S3-compatible object storage for backup archives
+//!
+//! Objects are addressed as `s3://bucket/key` (path-style against `AWS_ENDPOINT_URL`, or
+//! `https://s3.<AWS_REGION>.amazonaws.com` when unset) or as a plain `http(s)://` object
+//! URL for MinIO, R2 and similar. Requests are signed with AWS Signature Version 4 from
+//! `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and optional `AWS_SESSION_TOKEN`, with
+//! an unsigned payload so archives stream from and to disk. Uploads are a single `PUT`,
+//! which S3 caps at 5 GiB.
+
+use crate::error::ServerError;
+use chrono::{DateTime, Utc};
+use futures::stream;
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Payload hash sent instead of hashing the streamed archive
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Read size when streaming an upload from disk
+const UPLOAD_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// Access key, secret and optional session token
+#[derive(Clone)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An object's resolved URL and the region its requests are signed for
+#[derive(Debug, Clone)]
+pub struct ObjectLocation {
+    pub url: Url,
+    pub region: String,
+}
+
+impl ObjectLocation {
+    /// Resolve `s3://bucket/key` or an `http(s)://` object URL
+    pub fn parse(location: &str) -> Result<Self, ServerError> {
+        let region = std::env::var("AWS_REGION")
+            .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|_| "us-east-1".to_string());
+
+        let url = if let Some(rest) = location.strip_prefix("s3://") {
+            let (bucket, key) = rest
+                .split_once('/')
+                .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+                .ok_or_else(|| {
+                    ServerError::Config(format!(
+                        "Invalid object location {location}: expected s3://bucket/key"
+                    ))
+                })?;
+            let endpoint = std::env::var("AWS_ENDPOINT_URL")
+                .unwrap_or_else(|_| format!("https://s3.{region}.amazonaws.com"));
+            let mut url = Url::parse(&endpoint).map_err(|e| {
+                ServerError::Config(format!("Invalid AWS_ENDPOINT_URL {endpoint}: {e}"))
+            })?;
+            url.path_segments_mut()
+                .map_err(|_| ServerError::Config(format!("Invalid AWS_ENDPOINT_URL {endpoint}")))?
+                .pop_if_empty()
+                .push(bucket)
+                .extend(key.split('/'));
+            url
+        } else {
+            Url::parse(location).map_err(|e| {
+                ServerError::Config(format!("Invalid object location {location}: {e}"))
+            })?
+        };
+
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            return Err(ServerError::Config(format!(
+                "Invalid object location {location}: expected s3://bucket/key or an http(s) URL"
+            )));
+        }
+        Ok(Self { url, region })
+    }
+}
+
+impl Credentials {
+    /// Read credentials from the standard AWS environment variables
+    pub fn from_env() -> Result<Self, ServerError> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| {
+                    ServerError::Config(format!("{name} must be set for object storage"))
+                })
+        };
+        Ok(Self {
+            access_key_id: var("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: var("AWS_SECRET_ACCESS_KEY")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+        })
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// `Authorization` header for an S3 request signed with Signature Version 4
+///
+/// `headers` are the lowercase names and values to sign; they must include `host`,
+/// `x-amz-date` and `x-amz-content-sha256` (whose value is `payload_hash`).
+pub fn authorization(
+    credentials: &Credentials,
+    region: &str,
+    method: &str,
+    url: &Url,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let mut headers: Vec<(String, String)> = headers
+        .iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    headers.sort();
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{value}\n"))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let mut query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    query.sort();
+    let canonical_query = query
+        .iter()
+        .map(|(name, value)| format!("{}={}", uri_encode(name), uri_encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let canonical_request = format!(
+        "{method}\n{}\n{canonical_query}\n{canonical_headers}\n{signed_headers}\n{payload_hash}",
+        url.path()
+    );
+
+    let date = now.format("%Y%m%d").to_string();
+    let scope = format!("{date}/{region}/s3/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{scope}\n{}",
+        now.format("%Y%m%dT%H%M%SZ"),
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = [region, "s3", "aws4_request"].iter().fold(
+        hmac(
+            format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+            &date,
+        ),
+        |key, part| hmac(&key, part),
+    );
+    let signature = hex(&hmac(&key, &string_to_sign));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+        credentials.access_key_id
+    )
+}
+
+/// Percent-encode a query component the way Signature Version 4 expects
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn signed_request(
+    client: &reqwest::Client,
+    credentials: &Credentials,
+    location: &ObjectLocation,
+    method: reqwest::Method,
+) -> reqwest::RequestBuilder {
+    let now = Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let host = match location.url.port() {
+        Some(port) => format!("{}:{port}", location.url.host_str().unwrap_or_default()),
+        None => location.url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut headers = vec![
+        ("host", host.as_str()),
+        ("x-amz-content-sha256", UNSIGNED_PAYLOAD),
+        ("x-amz-date", amz_date.as_str()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.as_str()));
+    }
+    let authorization = authorization(
+        credentials,
+        &location.region,
+        method.as_str(),
+        &location.url,
+        &headers,
+        UNSIGNED_PAYLOAD,
+        now,
+    );
+
+    let mut request = client
+        .request(method, location.url.clone())
+        .header("authorization", authorization);
+    for (name, value) in headers {
+        if name != "host" {
+            request = request.header(name, value);
+        }
+    }
+    request
+}
+
+async fn check_response(
+    response: reqwest::Response,
+    action: &str,
+    location: &ObjectLocation,
+) -> Result<reqwest::Response, ServerError> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    Err(ServerError::Network(format!(
+        "Failed to {action} {}: HTTP {status}: {}",
+        location.url,
+        body.chars().take(500).collect::<String>()
+    )))
+}
+
+/// Stream the file at `path` to `location`, returning the bytes sent
+pub async fn upload(
+    client: &reqwest::Client,
+    credentials: &Credentials,
+    location: &ObjectLocation,
+    path: &Path,
+) -> Result<u64, ServerError> {
+    let file = tokio::fs::File::open(path).await?;
+    let size = file.metadata().await?.len();
+    let body = stream::unfold(file, |mut file| async move {
+        let mut buffer = vec![0u8; UPLOAD_CHUNK_BYTES];
+        match file.read(&mut buffer).await {
+            Ok(0) => None,
+            Ok(read) => {
+                buffer.truncate(read);
+                Some((Ok::<_, std::io::Error>(buffer), file))
+            }
+            Err(e) => Some((Err(e), file)),
+        }
+    });
+
+    let response = signed_request(client, credentials, location, reqwest::Method::PUT)
+        .header("content-length", size)
+        .header("content-type", "application/zstd")
+        .body(reqwest::Body::wrap_stream(body))
+        .send()
+        .await
+        .map_err(|e| ServerError::Network(format!("Failed to upload to {}: {e}", location.url)))?;
+    check_response(response, "upload to", location).await?;
+    Ok(size)
+}
+
+/// Stream the object at `location` into a new file at `path`, returning the bytes received
+pub async fn download(
+    client: &reqwest::Client,
+    credentials: &Credentials,
+    location: &ObjectLocation,
+    path: &Path,
+) -> Result<u64, ServerError> {
+    let response = signed_request(client, credentials, location, reqwest::Method::GET)
+        .send()
+        .await
+        .map_err(|e| ServerError::Network(format!("Failed to download {}: {e}", location.url)))?;
+    let mut response = check_response(response, "download", location).await?;
+
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut received = 0u64;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| ServerError::Network(format!("Failed to download {}: {e}", location.url)))?
+    {
+        file.write_all(&chunk).await?;
+        received += chunk.len() as u64;
+    }
+    file.flush().await?;
+    Ok(received)
+}
@@ -0,0 +1,146 @@
+//! Precomputed answers to the orientation questions agents ask first about a crate
+//!
+//! Nearly every first question about a newly added crate is a variation of "how do I get
+//! started" or "what are the main types". When a population completes, [`warm`] embeds a
+//! small built-in set of such questions, searches each and stores the hits in
+//! `precomputed_answers`. `query_rust_docs` compares a question's embedding with the
+//! stored question vectors and, on a close enough match ([`best_match`]), ranks the stored
+//! hits instead of searching.
+
+use crate::{
+    database::{Database, PrecomputedAnswer},
+    embeddings::{self, EmbeddingProvider},
+    error::ServerError,
+};
+use ndarray::{Array1, ArrayView1};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The built-in orientation questions as `(question_key, question)`
+pub const ORIENTATION_QUESTIONS: &[(&str, &str)] = &[
+    ("getting_started", "How do I get started with this crate?"),
+    (
+        "overview",
+        "What does this crate do and when should I use it?",
+    ),
+    (
+        "main_types",
+        "What are the main types and traits of this crate?",
+    ),
+    ("basic_example", "Show a basic example of using this crate"),
+    (
+        "error_handling",
+        "How does this crate report and handle errors?",
+    ),
+];
+
+/// Cosine similarity a question needs to one of [`ORIENTATION_QUESTIONS`] to be served
+/// its precomputed answer
+///
+/// High on purpose: "show a basic example" should match, "show an example of a custom
+/// codec" should not.
+pub const DEFAULT_MATCH_THRESHOLD: f32 = 0.9;
+
+/// Hits stored per question: enough for the largest `limit` with room for reranking
+pub const PRECOMPUTED_RESULTS: usize = 60;
+
+/// The precomputed answer whose question is most similar to `embedding`, with that
+/// similarity, if it reaches `threshold`
+///
+/// Answers embedded with a different dimension never match.
+pub fn best_match<'a>(
+    embedding: &[f32],
+    answers: &'a [PrecomputedAnswer],
+    threshold: f32,
+) -> Option<(&'a PrecomputedAnswer, f32)> {
+    let question = ArrayView1::from(embedding);
+    answers
+        .iter()
+        .filter(|answer| answer.embedding.len() == embedding.len())
+        .map(|answer| {
+            let similarity =
+                embeddings::cosine_similarity(question, ArrayView1::from(&answer.embedding));
+            (answer, similarity)
+        })
+        .filter(|&(_, similarity)| similarity >= threshold)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Embed and search every orientation question for `crate_name`, replacing its stored
+/// answers; returns how many were stored
+///
+/// Run when a population completes, with the provider that embedded the crate's chunks.
+pub async fn warm(
+    db: &Database,
+    provider: &(dyn EmbeddingProvider + Send + Sync),
+    crate_name: &str,
+) -> Result<usize, ServerError> {
+    let questions: Vec<String> = ORIENTATION_QUESTIONS
+        .iter()
+        .map(|(_, question)| question.to_string())
+        .collect();
+    let (embeddings, _usage) = provider.generate_embeddings(&questions).await?;
+    let mut answers = Vec::with_capacity(questions.len());
+    for ((question_key, question), embedding) in ORIENTATION_QUESTIONS.iter().zip(embeddings) {
+        let hits = db
+            .search_similar_docs_tuned(
+                crate_name,
+                &Array1::from_vec(embedding.clone()),
+                PRECOMPUTED_RESULTS as i32,
+                None,
+                None,
+                None,
+            )
+            .await?;
+        answers.push(PrecomputedAnswer {
+            question_key: question_key.to_string(),
+            question: question.to_string(),
+            model: provider.get_model_name().to_string(),
+            embedding,
+            results: hits
+                .into_iter()
+                .map(|hit| (hit.doc_path, hit.distance))
+                .collect(),
+        });
+    }
+    db.replace_precomputed_answers(crate_name, &answers).await?;
+    Ok(answers.len())
+}
+
+/// How often questions were served a precomputed answer
+#[derive(Debug, Default)]
+pub struct OrientationStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Snapshot of [`OrientationStats`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OrientationStatsSnapshot {
+    pub hits: u64,
+    pub misses: u64,
+    /// `hits / (hits + misses)`, 0 before any lookup
+    pub hit_rate: f64,
+}
+
+impl OrientationStats {
+    pub fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> OrientationStatsSnapshot {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        OrientationStatsSnapshot {
+            hits,
+            misses,
+            hit_rate: if lookups == 0 {
+                0.0
+            } else {
+                hits as f64 / lookups as f64
+            },
+        }
+    }
+}
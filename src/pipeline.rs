@@ -0,0 +1,1451 @@
+use crate::{
+    crate_metadata::{CratesIo, CRATES_IO_ORIGIN},
+    database::{CrateConfig, Database, LiveGeneration, SelfTest},
+    doc_loader::{self, CrawlOptions, CrawlSummary, Document, RawPage},
+    embeddings::{self, EmbeddingProvider, TruncationStrategy},
+    error::ServerError,
+    language::{self, LanguageAction, LanguageFilter},
+    orientation, rustdoc_json, search,
+    version_spec::VersionSpec,
+};
+use futures::stream::{self, StreamExt};
+use ndarray::Array1;
+use scraper::Html;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    future::Future,
+    sync::{
+        atomic::{AtomicU8, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+/// Channel capacities and batching for the population pipeline
+///
+/// Population streams crawler → chunker → embedder → batch inserter through bounded
+/// channels, so a slow stage holds the earlier ones back instead of buffering a whole
+/// crate in memory.
+#[derive(Debug, Clone)]
+pub struct PipelineOptions {
+    /// Crawled documents waiting to be chunked
+    pub document_capacity: usize,
+    /// Chunks waiting to be embedded
+    pub chunk_capacity: usize,
+    /// Embedded chunks waiting to be inserted
+    pub embedding_capacity: usize,
+    /// Chunks per insert transaction
+    pub batch_size: usize,
+    /// Embedding requests in flight at once
+    pub embed_concurrency: usize,
+    /// Documents with fewer characters (after trimming) are not embedded
+    pub min_doc_chars: usize,
+    /// Documents with fewer tokens are not embedded (0 = no token check)
+    pub min_doc_tokens: usize,
+    /// Chunks with fewer tokens are merged into a neighbouring chunk of their page, or
+    /// dropped if none can take them (0 = no floor beyond the document minimums)
+    pub min_chunk_tokens: usize,
+    /// Chunk paths that are already stored and are not embedded again, for resuming
+    /// an interrupted population
+    pub skip_paths: HashSet<String>,
+    /// The crate's `languages` setting; without it no language is detected
+    pub languages: Option<LanguageFilter>,
+    /// Store each chunk's docblock section heading (the crate's `section_metadata`)
+    pub section_metadata: bool,
+    /// Most chunks the crate may have stored, counting `skip_paths`; the chunker stops
+    /// there and the crawl is cut short (`None` = unlimited)
+    pub max_chunks: Option<usize>,
+    /// When the chunker stops taking documents; chunks already taken are still embedded
+    /// and stored, so a run cut short can be resumed with `skip_paths`
+    pub deadline: Option<Instant>,
+    /// Live counters updated as the run goes, for reporting on it while it is in flight
+    pub progress: Option<Arc<Progress>>,
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        Self {
+            document_capacity: 32,
+            chunk_capacity: 64,
+            embedding_capacity: 128,
+            batch_size: 50,
+            embed_concurrency: 8,
+            min_doc_chars: DEFAULT_MIN_DOC_CHARS,
+            min_doc_tokens: 0,
+            min_chunk_tokens: DEFAULT_MIN_CHUNK_TOKENS,
+            skip_paths: HashSet::new(),
+            languages: None,
+            section_metadata: false,
+            max_chunks: None,
+            deadline: None,
+            progress: None,
+        }
+    }
+}
+
+/// Shorter documents are one-liners like "A type alias." that only dilute search results
+pub const DEFAULT_MIN_DOC_CHARS: usize = 20;
+
+/// Chunks below this are a bare "Deprecated" or a two-word summary, whose vectors match
+/// every query weakly
+pub const DEFAULT_MIN_CHUNK_TOKENS: usize = 4;
+
+impl PipelineOptions {
+    /// Read `PIPELINE_DOCUMENT_BUFFER`, `PIPELINE_CHUNK_BUFFER`, `PIPELINE_EMBEDDING_BUFFER`,
+    /// `PIPELINE_BATCH_SIZE`, `PIPELINE_EMBED_CONCURRENCY`, `MIN_DOC_CHARS`,
+    /// `MIN_DOC_TOKENS` and `MIN_CHUNK_TOKENS`, defaulting unset ones
+    pub fn from_env() -> Result<Self, ServerError> {
+        let defaults = Self::default();
+        Ok(Self {
+            document_capacity: env_size("PIPELINE_DOCUMENT_BUFFER", defaults.document_capacity)?,
+            chunk_capacity: env_size("PIPELINE_CHUNK_BUFFER", defaults.chunk_capacity)?,
+            embedding_capacity: env_size("PIPELINE_EMBEDDING_BUFFER", defaults.embedding_capacity)?,
+            batch_size: env_size("PIPELINE_BATCH_SIZE", defaults.batch_size)?,
+            embed_concurrency: env_size("PIPELINE_EMBED_CONCURRENCY", defaults.embed_concurrency)?,
+            min_doc_chars: env_threshold("MIN_DOC_CHARS", defaults.min_doc_chars)?,
+            min_doc_tokens: env_threshold("MIN_DOC_TOKENS", defaults.min_doc_tokens)?,
+            min_chunk_tokens: env_threshold("MIN_CHUNK_TOKENS", defaults.min_chunk_tokens)?,
+            skip_paths: HashSet::new(),
+            languages: None,
+            section_metadata: false,
+            max_chunks: None,
+            deadline: None,
+            progress: None,
+        })
+    }
+
+    /// Whether `doc` is below the minimum length and should not be embedded
+    pub fn is_too_short(&self, doc: &Document, bpe: &tiktoken_rs::CoreBPE) -> bool {
+        let content = doc.content.trim();
+        content.chars().count() < self.min_doc_chars
+            || (self.min_doc_tokens > 0
+                && bpe.encode_with_special_tokens(content).len() < self.min_doc_tokens)
+    }
+
+    /// Whether a chunk of `tokens` tokens is too small to embed on its own, under the
+    /// document minimums or [`PipelineOptions::min_chunk_tokens`]
+    pub fn is_small_chunk(&self, content: &str, tokens: usize) -> bool {
+        content.trim().chars().count() < self.min_doc_chars
+            || (self.min_doc_tokens > 0 && tokens < self.min_doc_tokens)
+            || tokens < self.min_chunk_tokens
+    }
+}
+
+fn env_size(name: &str, default: usize) -> Result<usize, ServerError> {
+    match env_threshold(name, default)? {
+        0 => Err(ServerError::Config(format!(
+            "{name} must be a positive integer, got '0'"
+        ))),
+        size => Ok(size),
+    }
+}
+
+/// A non-negative integer from the environment, where 0 turns the check off
+fn env_threshold(name: &str, default: usize) -> Result<usize, ServerError> {
+    match std::env::var(name) {
+        Ok(value) if !value.trim().is_empty() => value.trim().parse::<usize>().map_err(|_| {
+            ServerError::Config(format!(
+                "{name} must be a non-negative integer, got '{value}'"
+            ))
+        }),
+        _ => Ok(default),
+    }
+}
+
+/// Where a population run is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Waiting for a population slot
+    Queued,
+    /// The crawler is still sending documents; chunks are embedded and stored meanwhile
+    Crawling,
+    /// The crawl is done; the remaining chunks are being embedded and stored
+    Embedding,
+    /// Everything is stored; the crate's version and statistics are being recorded
+    Finishing,
+}
+
+impl Stage {
+    const ALL: [Stage; 4] = [
+        Stage::Queued,
+        Stage::Crawling,
+        Stage::Embedding,
+        Stage::Finishing,
+    ];
+}
+
+/// Live counters of one pipeline run
+///
+/// The stages update these as they go, so a population can be inspected while it is
+/// still running instead of only through its final statistics.
+#[derive(Debug)]
+pub struct Progress {
+    stage: AtomicU8,
+    /// When the run left the queue
+    started: Mutex<Option<Instant>>,
+    documents: AtomicUsize,
+    chunks_embedded: AtomicUsize,
+    chunks_stored: AtomicUsize,
+    chunks_skipped: AtomicUsize,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            stage: AtomicU8::new(Stage::Queued as u8),
+            started: Mutex::new(None),
+            documents: AtomicUsize::new(0),
+            chunks_embedded: AtomicUsize::new(0),
+            chunks_stored: AtomicUsize::new(0),
+            chunks_skipped: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Progress {
+    pub fn stage(&self) -> Stage {
+        Stage::ALL[usize::from(self.stage.load(Ordering::Relaxed))]
+    }
+
+    /// Move to `stage`; leaving the queue starts the clock
+    pub fn set_stage(&self, stage: Stage) {
+        if stage != Stage::Queued {
+            self.started
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get_or_insert_with(Instant::now);
+        }
+        self.stage.store(stage as u8, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        let started = *self.started.lock().unwrap_or_else(|e| e.into_inner());
+        ProgressSnapshot {
+            stage: self.stage(),
+            documents_loaded: self.documents.load(Ordering::Relaxed),
+            chunks_embedded: self.chunks_embedded.load(Ordering::Relaxed),
+            chunks_stored: self.chunks_stored.load(Ordering::Relaxed),
+            chunks_skipped: self.chunks_skipped.load(Ordering::Relaxed),
+            elapsed_secs: started.map(|at| at.elapsed().as_secs_f64()),
+        }
+    }
+}
+
+/// The counters of a [`Progress`] at one point in time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProgressSnapshot {
+    pub stage: Stage,
+    /// Documents received from the crawler
+    pub documents_loaded: usize,
+    /// Chunks embedded, stored or not
+    pub chunks_embedded: usize,
+    /// Chunks stored in this run
+    pub chunks_stored: usize,
+    /// Chunks skipped because an interrupted run had already stored them
+    pub chunks_skipped: usize,
+    /// Time since the run left the queue (`None` while queued)
+    pub elapsed_secs: Option<f64>,
+}
+
+impl ProgressSnapshot {
+    /// Time left until `expected_chunks` are stored, at the rate chunks were stored so far
+    ///
+    /// `None` before anything was stored or once the expectation is met, since the
+    /// crate's real size is unknown from then on.
+    pub fn estimated_remaining(&self, expected_chunks: usize) -> Option<Duration> {
+        let elapsed = self.elapsed_secs.filter(|secs| *secs > 0.0)?;
+        let remaining = expected_chunks.checked_sub(self.chunks_stored + self.chunks_skipped)?;
+        if self.chunks_stored == 0 || remaining == 0 {
+            return None;
+        }
+        let per_chunk = elapsed / self.chunks_stored as f64;
+        Some(Duration::from_secs_f64(per_chunk * remaining as f64))
+    }
+}
+
+/// A chunk with its embedding, ready to be stored
+#[derive(Debug, Clone)]
+pub struct EmbeddedChunk {
+    pub path: String,
+    pub content: String,
+    pub embedding: Array1<f32>,
+    pub token_count: usize,
+    /// ISO 639-3 code of the document's language, set for crates that tag languages
+    pub language: Option<&'static str>,
+    /// Docblock section heading of the document, set for crates with `section_metadata`
+    pub section: Option<String>,
+}
+
+/// Where the batch inserter stage writes embedded chunks
+#[async_trait::async_trait]
+pub trait ChunkSink: Send {
+    /// Store one batch; a batch is either stored completely or not at all
+    async fn insert_batch(&mut self, batch: Vec<EmbeddedChunk>) -> Result<(), ServerError>;
+}
+
+/// Stores batches in `doc_embeddings`, one transaction per batch, and reports progress
+/// in the population job's `docs_populated` counter
+pub struct DatabaseSink<'a> {
+    db: &'a Database,
+    crate_name: &'a str,
+    target: Option<&'a str>,
+    /// Recorded on the crate so queries only fall back to providers with the same model
+    embedding_model: &'a str,
+    job_id: Option<i32>,
+    crate_id: Option<i32>,
+    /// Staged generation written to; `None` writes to the live one
+    generation: Option<i32>,
+    inserted: usize,
+}
+
+impl<'a> DatabaseSink<'a> {
+    pub fn new(
+        db: &'a Database,
+        crate_name: &'a str,
+        target: Option<&'a str>,
+        embedding_model: &'a str,
+        job_id: Option<i32>,
+    ) -> Self {
+        Self {
+            db,
+            crate_name,
+            target,
+            embedding_model,
+            job_id,
+            crate_id: None,
+            generation: None,
+            inserted: 0,
+        }
+    }
+
+    /// Write to the generation `staging` planned instead of the live one
+    pub fn with_staging(mut self, staging: &Staging) -> Self {
+        self.generation = staging.is_staged().then_some(staging.generation);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl ChunkSink for DatabaseSink<'_> {
+    async fn insert_batch(&mut self, batch: Vec<EmbeddedChunk>) -> Result<(), ServerError> {
+        // The crate row is created with the first batch so an empty crawl leaves nothing behind
+        let crate_id = match self.crate_id {
+            Some(id) => id,
+            None => {
+                let id = self
+                    .db
+                    .upsert_crate(self.crate_name, None, self.target)
+                    .await?;
+                // Chunks are about to change under the answers searched from them; a staged
+                // generation drops them when it is promoted
+                if self.generation.is_none() {
+                    self.db.delete_precomputed_answers(self.crate_name).await?;
+                }
+                self.db
+                    .set_crate_embedding_model(id, self.embedding_model)
+                    .await?;
+                self.crate_id = Some(id);
+                id
+            }
+        };
+
+        let count = batch.len();
+        let languages: Vec<(String, &str)> = batch
+            .iter()
+            .filter_map(|chunk| chunk.language.map(|lang| (chunk.path.clone(), lang)))
+            .collect();
+        let sections: Vec<(String, String)> = batch
+            .iter()
+            .filter_map(|chunk| Some((chunk.path.clone(), chunk.section.clone()?)))
+            .collect();
+        let rows: Vec<_> = batch
+            .into_iter()
+            .map(|chunk| {
+                (
+                    chunk.path,
+                    chunk.content,
+                    chunk.embedding,
+                    chunk.token_count as i32,
+                )
+            })
+            .collect();
+        match self.generation {
+            Some(generation) => {
+                self.db
+                    .insert_staged_embeddings_batch(crate_id, self.crate_name, generation, &rows)
+                    .await?
+            }
+            None => {
+                self.db
+                    .insert_embeddings_batch(crate_id, self.crate_name, &rows)
+                    .await?
+            }
+        }
+        if !languages.is_empty() {
+            self.db
+                .set_document_languages(self.crate_name, &languages)
+                .await?;
+        }
+        if !sections.is_empty() {
+            self.db
+                .set_document_sections(self.crate_name, self.generation, &sections)
+                .await?;
+        }
+        self.inserted += count;
+        eprintln!(
+            "    💾 Stored {} chunks for {} so far",
+            self.inserted, self.crate_name
+        );
+
+        if let Some(job_id) = self.job_id {
+            if let Err(e) = self
+                .db
+                .set_population_job_progress(job_id, self.inserted as i32)
+                .await
+            {
+                eprintln!("⚠️  Failed to update progress of population job {job_id}: {e}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where the crawler stage gets documents from
+pub enum DocumentSource<'a> {
+    /// Crawl docs.rs (or another configured docs origin)
+    DocsRs {
+        crate_name: &'a str,
+        features: Option<&'a Vec<String>>,
+        max_pages: Option<usize>,
+        options: &'a CrawlOptions,
+        /// Store every fetched page's HTML here (`store_raw_html` configurations)
+        raw_html: Option<&'a Database>,
+    },
+    /// Download a rustdoc JSON artifact
+    RustdocJson { crate_name: &'a str, url: &'a str },
+    /// Re-extract the page HTML stored by an earlier `raw_html` crawl, without fetching
+    StoredHtml {
+        db: &'a Database,
+        crate_name: &'a str,
+        /// CSS selector for content blocks (defaults to [`doc_loader::DEFAULT_CONTENT_SELECTOR`])
+        content_selector: Option<&'a str>,
+    },
+}
+
+impl DocumentSource<'_> {
+    /// Send the source's documents to `documents` as they become available
+    pub async fn crawl(
+        &self,
+        documents: mpsc::Sender<Document>,
+    ) -> Result<CrawlSummary, ServerError> {
+        match self {
+            Self::DocsRs {
+                crate_name,
+                features,
+                max_pages,
+                options,
+                raw_html: None,
+            } => Ok(doc_loader::crawl_docs_rs(
+                crate_name, "*", *features, *max_pages, options, documents, None,
+            )
+            .await?),
+            Self::DocsRs {
+                crate_name,
+                features,
+                max_pages,
+                options,
+                raw_html: Some(db),
+            } => {
+                let (page_tx, mut page_rx) = mpsc::channel::<RawPage>(8);
+                let crawl = doc_loader::crawl_docs_rs(
+                    crate_name,
+                    "*",
+                    *features,
+                    *max_pages,
+                    options,
+                    documents,
+                    Some(page_tx),
+                );
+                // A page that fails to store only costs its re-extraction, not the population
+                let store = async {
+                    let (mut stored, mut failed) = (0, 0);
+                    while let Some(page) = page_rx.recv().await {
+                        match db.upsert_raw_page(crate_name, &page.path, &page.html).await {
+                            Ok(()) => stored += 1,
+                            Err(e) => {
+                                if failed == 0 {
+                                    eprintln!("⚠️  Failed to store HTML of {}: {e}", page.path);
+                                }
+                                failed += 1;
+                            }
+                        }
+                    }
+                    (stored, failed)
+                };
+                let (summary, (stored, failed)) = tokio::join!(crawl, store);
+                eprintln!("🗄️  Stored HTML of {stored} pages for {crate_name} ({failed} failed)");
+                Ok(summary?)
+            }
+            Self::StoredHtml {
+                db,
+                crate_name,
+                content_selector,
+            } => {
+                let selector = doc_loader::parse_content_selector(*content_selector)?;
+                let mut diagnostics = doc_loader::CrawlDiagnostics::default();
+                let mut pages = std::pin::pin!(db.get_raw_pages_stream(crate_name));
+                'pages: while let Some(page) = pages.next().await {
+                    let (page_path, html) = page?;
+                    diagnostics.pages_fetched += 1;
+                    // Html isn't Send, so it must not live across an await
+                    let docs = doc_loader::page_documents(
+                        &page_path,
+                        &Html::parse_document(&html),
+                        &selector,
+                    );
+                    if docs.is_empty() {
+                        diagnostics.empty_pages += 1;
+                    }
+                    for doc in docs {
+                        if documents.send(doc).await.is_err() {
+                            break 'pages;
+                        }
+                    }
+                }
+                Ok(CrawlSummary {
+                    version: None,
+                    reexport_pages: Default::default(),
+                    diagnostics,
+                })
+            }
+            Self::RustdocJson { crate_name, url } => {
+                // rustdoc JSON arrives as one file, so only the later stages stream
+                let result = rustdoc_json::load_documents_from_url(crate_name, url).await?;
+                for doc in result.documents {
+                    if documents.send(doc).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(CrawlSummary {
+                    version: result.version,
+                    reexport_pages: result.reexport_pages,
+                    diagnostics: result.diagnostics,
+                })
+            }
+        }
+    }
+}
+
+/// What a pipeline run did
+#[derive(Debug, Default)]
+pub struct PipelineStats {
+    pub crawl: CrawlSummary,
+    /// Documents received from the crawler
+    pub documents: usize,
+    /// Bytes of document content received from the crawler
+    pub content_bytes: usize,
+    /// Chunks not embedded because they were below the minimum size and no neighbouring
+    /// chunk of their page could take them
+    pub short_documents: usize,
+    /// Chunks below the minimum size merged into a neighbouring chunk of their page
+    pub merged_chunks: usize,
+    /// Chunks skipped because they were already stored
+    pub skipped_chunks: usize,
+    /// Chunks embedded and stored
+    pub stored_chunks: usize,
+    /// Tokens of the stored chunks
+    pub total_tokens: usize,
+    /// Time until the crawler finished
+    pub crawl_time: Duration,
+    /// Embedding stopped at [`PipelineOptions::max_chunks`] with documents still coming
+    pub truncated: bool,
+    /// The crawl or chunker stopped at its deadline before every page was taken in
+    pub timed_out: bool,
+}
+
+/// Run crawler → chunker → embedder → batch inserter concurrently over bounded channels
+///
+/// `crawl` receives the sending end of the document channel. Everything inserted before
+/// an error stays stored, so a failed run can be resumed with
+/// [`PipelineOptions::skip_paths`].
+pub async fn run<C, F, S>(
+    crawl: C,
+    provider: Arc<dyn EmbeddingProvider + Send + Sync>,
+    sink: &mut S,
+    options: &PipelineOptions,
+) -> Result<PipelineStats, ServerError>
+where
+    C: FnOnce(mpsc::Sender<Document>) -> F,
+    F: Future<Output = Result<CrawlSummary, ServerError>>,
+    S: ChunkSink,
+{
+    let bpe = tiktoken_rs::cl100k_base().map_err(|e| ServerError::Tiktoken(e.to_string()))?;
+    let truncation = TruncationStrategy::from_env()?;
+
+    let (doc_tx, doc_rx) = mpsc::channel::<Document>(options.document_capacity.max(1));
+    let (chunk_tx, chunk_rx) =
+        mpsc::channel::<(String, String, Option<&'static str>, Option<String>)>(
+            options.chunk_capacity.max(1),
+        );
+    let (embedded_tx, mut embedded_rx) =
+        mpsc::channel::<EmbeddedChunk>(options.embedding_capacity.max(1));
+
+    let progress = options.progress.as_deref();
+    let count = |counter: fn(&Progress) -> &AtomicUsize, n: usize| {
+        if let Some(progress) = progress {
+            counter(progress).fetch_add(n, Ordering::Relaxed);
+        }
+    };
+
+    let start = Instant::now();
+    if let Some(progress) = progress {
+        progress.set_stage(Stage::Crawling);
+    }
+    let crawler = async {
+        let summary = crawl(doc_tx).await?;
+        if let Some(progress) = progress {
+            progress.set_stage(Stage::Embedding);
+        }
+        Ok::<_, ServerError>((summary, start.elapsed()))
+    };
+
+    let chunker = async {
+        let chunk_tx = chunk_tx;
+        let mut doc_rx = doc_rx;
+        let mut counts = ChunkerCounts::default();
+        let mut sent = 0;
+        // Chunks of the page being read, held back until the next page starts so small
+        // ones can be merged into a neighbour
+        let mut page = Vec::<(PageChunk, Option<&'static str>, Option<String>)>::new();
+        'docs: loop {
+            let next = match options.deadline {
+                Some(deadline) => tokio::time::timeout_at(deadline.into(), doc_rx.recv()).await,
+                None => Ok(doc_rx.recv().await),
+            };
+            let doc = match next {
+                Ok(doc) => doc,
+                Err(_) => {
+                    counts.timed_out = true;
+                    None
+                }
+            };
+            let page_done = match (&doc, page.first()) {
+                (Some(doc), Some((first, ..))) => page_of(&doc.path) != page_of(&first.path),
+                _ => true,
+            };
+            if page_done && !page.is_empty() {
+                let chunks: Vec<PageChunk> = page.iter().map(|(chunk, ..)| chunk.clone()).collect();
+                let merge = merge_small_chunks(&chunks, embeddings::TOKEN_LIMIT);
+                counts.short += merge.dropped.len();
+                let mut extras: Vec<_> = page
+                    .drain(..)
+                    .map(|(_, language, section)| Some((language, section)))
+                    .collect();
+                for merged in merge.chunks {
+                    counts.merged += merged.absorbed.len();
+                    let (language, section) = extras[merged.index].take().unwrap_or_default();
+                    // Dropping the receiver stops the crawl instead of fetching pages for nothing
+                    if options
+                        .max_chunks
+                        .is_some_and(|max| counts.skipped + sent >= max)
+                    {
+                        counts.truncated = true;
+                        break 'docs;
+                    }
+                    if options.skip_paths.contains(&merged.path) {
+                        counts.skipped += 1;
+                        count(|p| &p.chunks_skipped, 1);
+                        continue;
+                    }
+                    if chunk_tx
+                        .send((merged.path, merged.content, language, section))
+                        .await
+                        .is_err()
+                    {
+                        return Ok(counts);
+                    }
+                    sent += 1;
+                }
+            }
+            let Some(doc) = doc else {
+                break;
+            };
+            counts.documents += 1;
+            count(|p| &p.documents, 1);
+            counts.content_bytes += doc.content.len();
+            // Too short as a whole: every chunk is small, and not worth detecting a language
+            let short = options.is_too_short(&doc, &bpe);
+            let mut language = None;
+            if let Some(filter) = options.languages.as_ref().filter(|_| !short) {
+                let detected = language::detect(&doc.content);
+                *counts
+                    .languages
+                    .entry(detected.map_or("unknown", |lang| lang.code()).to_string())
+                    .or_default() += 1;
+                match (detected, filter.action()) {
+                    (Some(lang), LanguageAction::Drop) if !filter.allows(lang) => {
+                        counts.language_dropped += 1;
+                        continue;
+                    }
+                    (Some(lang), LanguageAction::Tag) => language = Some(lang.code()),
+                    _ => {}
+                }
+            }
+            let section = doc.section.clone().filter(|_| options.section_metadata);
+            for (path, content) in embeddings::chunk_document(&doc, &bpe) {
+                let tokens = bpe.encode_with_special_tokens(&content).len();
+                let small = short || options.is_small_chunk(&content, tokens);
+                let chunk = PageChunk {
+                    path,
+                    content,
+                    tokens,
+                    small,
+                };
+                page.push((chunk, language, section.clone()));
+            }
+        }
+        if counts.timed_out {
+            eprintln!(
+                "Reached the time limit after {} documents; the chunks taken so far are still stored",
+                counts.documents
+            );
+        }
+        if counts.truncated {
+            eprintln!(
+                "Stopped at max_embeddings ({} chunks); later documents were not embedded",
+                counts.skipped + sent
+            );
+        }
+        if counts.merged > 0 {
+            eprintln!(
+                "Merged {} chunks below the minimum size into a neighbouring chunk",
+                counts.merged
+            );
+        }
+        if counts.short > 0 {
+            eprintln!(
+                "Skipped {} chunks below the minimum size with no neighbour to merge into",
+                counts.short
+            );
+        }
+        if counts.language_dropped > 0 {
+            eprintln!(
+                "Skipped {} documents in languages other than {:?}",
+                counts.language_dropped,
+                options.languages.as_ref().map(LanguageFilter::codes)
+            );
+        }
+        Ok::<_, ServerError>(counts)
+    };
+
+    let embedder = async {
+        let embedded_tx = embedded_tx;
+        let mut results = std::pin::pin!(stream::unfold(chunk_rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        })
+        .map(|(path, content, language, section)| {
+            let (provider, bpe) = (&provider, &bpe);
+            async move {
+                let token_count = bpe.encode_with_special_tokens(&content).len();
+                let embedding = embeddings::embed_chunk(
+                    provider.as_ref(),
+                    bpe,
+                    &path,
+                    content.clone(),
+                    truncation,
+                )
+                .await?;
+                Ok::<_, ServerError>(EmbeddedChunk {
+                    path,
+                    content,
+                    embedding,
+                    token_count,
+                    language,
+                    section,
+                })
+            }
+        })
+        .buffer_unordered(options.embed_concurrency.max(1)));
+
+        while let Some(result) = results.next().await {
+            let chunk = result?;
+            count(|p| &p.chunks_embedded, 1);
+            if embedded_tx.send(chunk).await.is_err() {
+                break;
+            }
+        }
+        Ok::<_, ServerError>(())
+    };
+
+    let inserter = async {
+        let batch_size = options.batch_size.max(1);
+        let (mut stored, mut tokens) = (0, 0);
+        let mut batch = Vec::with_capacity(batch_size);
+        while let Some(chunk) = embedded_rx.recv().await {
+            batch.push(chunk);
+            if batch.len() >= batch_size {
+                let size = batch.len();
+                stored += size;
+                tokens += batch.iter().map(|c| c.token_count).sum::<usize>();
+                sink.insert_batch(std::mem::replace(
+                    &mut batch,
+                    Vec::with_capacity(batch_size),
+                ))
+                .await?;
+                count(|p| &p.chunks_stored, size);
+            }
+        }
+        if !batch.is_empty() {
+            let size = batch.len();
+            stored += size;
+            tokens += batch.iter().map(|c| c.token_count).sum::<usize>();
+            sink.insert_batch(batch).await?;
+            count(|p| &p.chunks_stored, size);
+        }
+        Ok::<_, ServerError>((stored, tokens))
+    };
+
+    let ((mut crawl, crawl_time), counts, (), (stored_chunks, total_tokens)) =
+        tokio::try_join!(crawler, chunker, embedder, inserter)?;
+    crawl.diagnostics.documents_by_language = counts.languages;
+    crawl.diagnostics.language_dropped = counts.language_dropped;
+    crawl.diagnostics.small_chunks_merged = counts.merged;
+    crawl.diagnostics.small_chunks_dropped = counts.short;
+    crawl.diagnostics.hit_max_embeddings = counts.truncated;
+    crawl.diagnostics.hit_deadline |= counts.timed_out;
+
+    Ok(PipelineStats {
+        timed_out: crawl.diagnostics.hit_deadline,
+        crawl,
+        documents: counts.documents,
+        content_bytes: counts.content_bytes,
+        short_documents: counts.short,
+        merged_chunks: counts.merged,
+        skipped_chunks: counts.skipped,
+        stored_chunks,
+        total_tokens,
+        crawl_time,
+        truncated: counts.truncated,
+    })
+}
+
+/// What the chunker stage saw
+#[derive(Default)]
+struct ChunkerCounts {
+    documents: usize,
+    content_bytes: usize,
+    short: usize,
+    merged: usize,
+    languages: BTreeMap<String, usize>,
+    language_dropped: usize,
+    skipped: usize,
+    truncated: bool,
+    timed_out: bool,
+}
+
+/// The page a chunk belongs to: its path without the chunk suffix or item anchor
+pub fn page_of(doc_path: &str) -> &str {
+    let path = search::base_doc_path(doc_path);
+    path.split_once('#').map_or(path, |(page, _)| page)
+}
+
+/// One chunk of a page, in page order, for [`merge_small_chunks`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageChunk {
+    pub path: String,
+    pub content: String,
+    pub tokens: usize,
+    /// Below the minimum size to be embedded on its own
+    pub small: bool,
+}
+
+/// A chunk left to embed by [`merge_small_chunks`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedChunk {
+    /// The chunk's position on its page; a merged chunk keeps this chunk's path,
+    /// language and section
+    pub index: usize,
+    pub path: String,
+    /// The chunk's content joined with the small chunks it absorbed, in page order
+    pub content: String,
+    /// Paths of the small chunks merged into this one
+    pub absorbed: Vec<String>,
+}
+
+/// What [`merge_small_chunks`] made of a page
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PageMerge {
+    /// Chunks to embed, in page order
+    pub chunks: Vec<MergedChunk>,
+    /// Paths of small chunks that no neighbour could take
+    pub dropped: Vec<String>,
+}
+
+/// Apply the minimum-content policy to one page's chunks
+///
+/// Each small chunk merges into the next chunk of the page that isn't small, or failing
+/// that the previous one, as long as the result stays within `max_tokens`; with neither
+/// it is dropped. Small chunks never absorb each other and nothing merges across pages,
+/// so a page of only small chunks is dropped whole.
+pub fn merge_small_chunks(page: &[PageChunk], max_tokens: usize) -> PageMerge {
+    let mut tokens: Vec<usize> = page.iter().map(|chunk| chunk.tokens).collect();
+    let mut absorbed = vec![Vec::new(); page.len()];
+    let mut merge = PageMerge::default();
+    for (i, chunk) in page.iter().enumerate().filter(|(_, chunk)| chunk.small) {
+        let next = (i + 1..page.len()).find(|&j| !page[j].small);
+        let previous = (0..i).rev().find(|&j| !page[j].small);
+        match [next, previous]
+            .into_iter()
+            .flatten()
+            .find(|&j| tokens[j] + chunk.tokens <= max_tokens)
+        {
+            Some(j) => {
+                tokens[j] += chunk.tokens;
+                absorbed[j].push(i);
+            }
+            None => merge.dropped.push(chunk.path.clone()),
+        }
+    }
+    for (j, chunk) in page.iter().enumerate().filter(|(_, chunk)| !chunk.small) {
+        let content = if absorbed[j].is_empty() {
+            chunk.content.clone()
+        } else {
+            let mut parts = absorbed[j].clone();
+            parts.push(j);
+            parts.sort_unstable();
+            parts
+                .iter()
+                .map(|&k| page[k].content.trim())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        };
+        merge.chunks.push(MergedChunk {
+            index: j,
+            path: chunk.path.clone(),
+            content,
+            absorbed: absorbed[j].iter().map(|&k| page[k].path.clone()).collect(),
+        });
+    }
+    merge
+}
+
+/// Chunk paths already stored for `crate_name`, to skip when resuming a population
+pub async fn stored_paths(db: &Database, crate_name: &str) -> Result<HashSet<String>, ServerError> {
+    Ok(db
+        .get_doc_paths_with_prefix(crate_name, "", i64::MAX)
+        .await?
+        .into_iter()
+        .collect())
+}
+
+/// Which generation of a crate's documents a population writes
+///
+/// A crate that completed a population before is repopulated into the next generation,
+/// which queries don't see until [`Staging::promote`] swaps it in, so they never read a
+/// mix of old and new documents. A first population writes straight to the live
+/// generation: there is nothing to keep serving, and its stored chunks are searchable
+/// (and resumable) as they land.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Staging {
+    /// Generation queries read when the population starts
+    pub live: LiveGeneration,
+    /// Generation the population writes to
+    pub generation: i32,
+    /// docs.rs target populated (`None` for the default one); every target of a crate
+    /// has its own generations
+    pub target: Option<String>,
+}
+
+impl Staging {
+    /// Plan the population of `config`
+    ///
+    /// A resumed repopulation continues the generation it staged before; otherwise
+    /// whatever an earlier failed repopulation staged is discarded first.
+    pub async fn plan(db: &Database, config: &CrateConfig) -> Result<Self, ServerError> {
+        let crate_name = config.name.as_str();
+        let target = config.target.as_deref();
+        let live = db.get_live_generation(crate_name, target).await?;
+        let staged = config.last_populated.is_some()
+            && db
+                .count_generation_documents(crate_name, target, live.generation)
+                .await?
+                > 0;
+        let generation = if staged {
+            live.generation + 1
+        } else {
+            live.generation
+        };
+        if staged && !config.resumes_population() {
+            let discarded = db
+                .discard_generation(crate_name, target, generation)
+                .await?;
+            if discarded > 0 {
+                eprintln!("🧹 Discarded {discarded} chunks an earlier repopulation of {crate_name} left staged");
+            }
+        }
+        Ok(Self {
+            live,
+            generation,
+            target: config.target.clone(),
+        })
+    }
+
+    /// Whether the population writes to a generation queries don't read yet
+    pub fn is_staged(&self) -> bool {
+        self.generation != self.live.generation
+    }
+
+    /// Chunk paths already stored in the generation written to, to skip when resuming
+    pub async fn stored_paths(
+        &self,
+        db: &Database,
+        crate_name: &str,
+    ) -> Result<HashSet<String>, ServerError> {
+        Ok(db
+            .get_generation_doc_paths(crate_name, self.target.as_deref(), self.generation)
+            .await?
+            .into_iter()
+            .collect())
+    }
+
+    /// Make the staged generation live, recording `version` with it
+    pub async fn promote(
+        &self,
+        db: &Database,
+        crate_name: &str,
+        version: Option<&str>,
+    ) -> Result<(), ServerError> {
+        db.promote_generation(crate_name, self.target.as_deref(), self.generation, version)
+            .await?;
+        eprintln!(
+            "🔀 Swapped in generation {} of {crate_name} (was {})",
+            self.generation, self.live.generation
+        );
+        Ok(())
+    }
+
+    /// Undo [`Staging::promote`], serving the generation that was live before again
+    pub async fn revert(&self, db: &Database, crate_name: &str) -> Result<(), ServerError> {
+        db.promote_generation(
+            crate_name,
+            self.target.as_deref(),
+            self.live.generation,
+            self.live.version.as_deref(),
+        )
+        .await?;
+        eprintln!(
+            "↩️  Restored generation {} of {crate_name}",
+            self.live.generation
+        );
+        Ok(())
+    }
+
+    /// Delete the generation the promoted one replaced
+    pub async fn collect_garbage(&self, db: &Database, crate_name: &str) {
+        match db
+            .drop_stale_generations(crate_name, self.target.as_deref())
+            .await
+        {
+            Ok(removed) => {
+                eprintln!("🧹 Removed {removed} chunks of the previous generation of {crate_name}")
+            }
+            // Invisible to queries; the next repopulation discards them
+            Err(e) => {
+                eprintln!("⚠️  Failed to remove the previous generation of {crate_name}: {e}")
+            }
+        }
+    }
+}
+
+/// Judge a self-test search: it passes when searching `sample_doc_path`'s own vector
+/// under `crate_name` returned any `hits`
+///
+/// `variants` are stored names differing only in case or `-`/`_`, named in the error when
+/// nothing was stored under `crate_name` itself.
+pub fn judge_self_test(
+    crate_name: &str,
+    sample_doc_path: Option<String>,
+    hits: &[(String, f32)],
+    variants: &[String],
+) -> SelfTest {
+    let error = match (&sample_doc_path, hits.is_empty()) {
+        (None, _) if variants.is_empty() => Some(format!(
+            "no embeddings are stored under the name '{crate_name}'"
+        )),
+        (None, _) => Some(format!(
+            "no embeddings are stored under the name '{crate_name}', but there are under {}; the loader stored them under a different spelling than queries use",
+            variants.join(", ")
+        )),
+        (Some(sample), true) => Some(format!(
+            "searching '{crate_name}' with the stored vector of {sample} returned no rows; check the crate's vector table and index"
+        )),
+        (Some(_), false) => None,
+    };
+    SelfTest {
+        passed: error.is_none(),
+        crate_name: crate_name.to_string(),
+        sample_doc_path,
+        hits: hits.len(),
+        top_similarity: hits.first().map(|(_, similarity)| *similarity),
+        error,
+    }
+}
+
+/// Check that queries for `crate_name` find the documents stored for `target`
+///
+/// Searches with one of the target's stored documents' own vector instead of embedding a
+/// question, so it costs no provider call.
+pub async fn self_test(
+    db: &Database,
+    crate_name: &str,
+    target: Option<&str>,
+) -> Result<SelfTest, ServerError> {
+    let Some((sample_doc_path, vector)) = db.sample_crate_embedding(crate_name, target).await?
+    else {
+        let variants = db.crate_name_variants(crate_name).await?;
+        return Ok(judge_self_test(crate_name, None, &[], &variants));
+    };
+    let hits: Vec<(String, f32)> = db
+        .search_similar_docs(crate_name, &Array1::from_vec(vector), 1)
+        .await?
+        .into_iter()
+        .map(|(doc_path, _, similarity)| (doc_path, similarity))
+        .collect();
+    Ok(judge_self_test(
+        crate_name,
+        Some(sample_doc_path),
+        &hits,
+        &[],
+    ))
+}
+
+/// Precompute the crate's answers to the orientation questions; failing only costs
+/// queries the shortcut
+pub async fn warm_orientation(
+    db: &Database,
+    provider: &(dyn EmbeddingProvider + Send + Sync),
+    crate_name: &str,
+) {
+    match orientation::warm(db, provider, crate_name).await {
+        Ok(count) => {
+            eprintln!("🧭 Precomputed answers to {count} orientation questions for {crate_name}")
+        }
+        Err(e) => eprintln!("⚠️  Failed to precompute orientation answers for {crate_name}: {e}"),
+    }
+}
+
+/// Serve the previous generation again after a promoted one failed its self-test
+async fn revert_staging(db: &Database, staging: &Staging, crate_name: &str) {
+    if !staging.is_staged() {
+        return;
+    }
+    if let Err(e) = staging.revert(db, crate_name).await {
+        eprintln!("⚠️  Failed to restore the previous generation of {crate_name}: {e}");
+    }
+}
+
+/// What [`populate_config`] did for one crate configuration
+///
+/// `stats.timed_out` means the population stopped at the crate's `max_duration_secs`;
+/// its job should be marked `partial` rather than `completed`.
+#[derive(Debug)]
+pub struct PopulationReport {
+    pub crate_name: String,
+    /// Version the crawl resolved, if it could tell
+    pub version: Option<String>,
+    pub stats: PipelineStats,
+    /// Wall time of the whole population
+    pub total_time: Duration,
+}
+
+/// The concrete version to crawl for `config`'s `version_spec`, or `None` for docs.rs's `latest`
+///
+/// docs.rs's `latest` already is the newest stable release that isn't yanked, so a plain
+/// `latest` needs no lookup and an exact pin is crawled as given. Requirements, and
+/// `latest` with `allow_prerelease`, are resolved against the versions crates.io lists.
+pub async fn resolve_crawl_version(
+    config: &CrateConfig,
+    crates_io: &CratesIo,
+) -> Result<Option<String>, ServerError> {
+    resolve_version_spec(
+        &config.name,
+        &config.version_spec,
+        config.allow_prerelease,
+        crates_io,
+    )
+    .await
+}
+
+/// [`resolve_crawl_version`] for a crate that has no configuration yet
+pub async fn resolve_version_spec(
+    crate_name: &str,
+    version_spec: &str,
+    allow_prerelease: bool,
+    crates_io: &CratesIo,
+) -> Result<Option<String>, ServerError> {
+    let spec = VersionSpec::parse(version_spec)
+        .map_err(|e| ServerError::Config(format!("{crate_name}: {e}")))?;
+    let spec = match spec {
+        VersionSpec::Latest if !allow_prerelease => return Ok(None),
+        VersionSpec::Exact(version) => return Ok(Some(version.to_string())),
+        spec => spec,
+    };
+    let published = crates_io.versions(crate_name).await?.ok_or_else(|| {
+        ServerError::Config(format!(
+            "{crate_name} is not published on crates.io, so version_spec '{version_spec}' can't be resolved"
+        ))
+    })?;
+    let version = spec.resolve(&published, allow_prerelease).ok_or_else(|| {
+        let hint = if allow_prerelease {
+            ""
+        } else {
+            "; pre-releases only match with allow_prerelease"
+        };
+        ServerError::Config(format!(
+            "No unyanked version of {crate_name} matches version_spec '{version_spec}'{hint}"
+        ))
+    })?;
+    eprintln!("📌 Resolved {crate_name} {version_spec} to {version}");
+    Ok(Some(version.to_string()))
+}
+
+/// Crawl, embed and store one crate configuration, then record the population on it
+///
+/// The single population path of the HTTP server and [`crate::client::DocsClient`]. A
+/// configuration that never finished populating resumes after the chunks already stored.
+/// `job_id` receives progress and crawl diagnostics; marking the job running, completed,
+/// partial or failed is up to the caller.
+pub async fn populate_config(
+    db: &Database,
+    provider: Arc<dyn EmbeddingProvider + Send + Sync>,
+    config: &CrateConfig,
+    crawl_options: &CrawlOptions,
+    job_id: Option<i32>,
+    progress: Option<Arc<Progress>>,
+) -> Result<PopulationReport, ServerError> {
+    let total_start = Instant::now();
+    let crate_name = config.name.as_str();
+    let target = config.target.as_deref();
+    eprintln!(
+        "📥 Loading documentation for crate: {crate_name} with features: {:?}",
+        config.features
+    );
+    let features = (!config.features.is_empty()).then_some(&config.features);
+    // The crawler and the chunker share one deadline, so the whole run stays within it
+    let deadline = config.max_duration().map(|limit| total_start + limit);
+    let version = match &crawl_options.version {
+        Some(version) => Some(version.clone()),
+        None if config.rustdoc_json_url.is_none() => {
+            resolve_crawl_version(config, &CratesIo::new(CRATES_IO_ORIGIN)).await?
+        }
+        None => None,
+    };
+    let crawl_options = &CrawlOptions {
+        deadline: deadline.or(crawl_options.deadline),
+        version,
+        ..crawl_options.clone()
+    };
+    let source = match &config.rustdoc_json_url {
+        Some(url) => DocumentSource::RustdocJson { crate_name, url },
+        None => DocumentSource::DocsRs {
+            crate_name,
+            features,
+            max_pages: Some(config.max_pages_or(10000)),
+            options: crawl_options,
+            raw_html: config.store_raw_html.then_some(db),
+        },
+    };
+
+    let mut options = PipelineOptions::from_env()?;
+    options.languages = LanguageFilter::for_crate(config)?;
+    options.section_metadata = config.section_metadata;
+    options.progress = progress.clone();
+    options.max_chunks = config.embedding_limit();
+    options.deadline = crawl_options.deadline;
+    let staging = Staging::plan(db, config).await?;
+    if staging.is_staged() {
+        eprintln!(
+            "🗂️  Repopulating {crate_name} into generation {}; queries keep reading generation {} until it completes",
+            staging.generation, staging.live.generation
+        );
+    }
+    // A config that never finished populating, or whose last population hit its time
+    // limit, already has stored chunks; pick up where it left off instead of
+    // re-embedding everything
+    if config.resumes_population() {
+        options.skip_paths = staging.stored_paths(db, crate_name).await?;
+        if !options.skip_paths.is_empty() {
+            eprintln!(
+                "⏩ Resuming interrupted population of {crate_name}: {} chunks already stored",
+                options.skip_paths.len()
+            );
+        }
+    }
+
+    // Crawl, embed and store concurrently; each batch is committed as it lands
+    eprintln!("🧠 Streaming documents for {crate_name} into embeddings...");
+    let mut sink = DatabaseSink::new(db, crate_name, target, provider.get_model_name(), job_id)
+        .with_staging(&staging);
+    let stats = run(|tx| source.crawl(tx), provider.clone(), &mut sink, &options).await?;
+    if let Some(progress) = &progress {
+        progress.set_stage(Stage::Finishing);
+    }
+    let diagnostics = &stats.crawl.diagnostics;
+    eprintln!("🩺 Crawl diagnostics for {crate_name}: {diagnostics}");
+    if diagnostics.version_missing {
+        eprintln!("❌ Could not determine the version of {crate_name}; the crate will be stored without one (recorded in the job diagnostics)");
+    }
+    if let Some(job_id) = job_id {
+        if let Err(e) = db.set_population_job_diagnostics(job_id, diagnostics).await {
+            eprintln!("⚠️  Failed to store crawl diagnostics for job {job_id}: {e}");
+        }
+        if !diagnostics.trace.entries.is_empty() {
+            if let Err(e) = db
+                .set_population_job_crawl_trace(job_id, &diagnostics.trace)
+                .await
+            {
+                eprintln!("⚠️  Failed to store the crawl trace for job {job_id}: {e}");
+            }
+        }
+    }
+
+    eprintln!(
+        "✅ Crawled {} documents in {:.2}s ({:.1} KB total, {} too short to embed)",
+        stats.documents,
+        stats.crawl_time.as_secs_f64(),
+        stats.content_bytes as f64 / 1024.0,
+        stats.short_documents
+    );
+    if stats.documents == 0 {
+        return Err(ServerError::Config(format!(
+            "No documents found for crate: {crate_name} ({diagnostics})"
+        )));
+    }
+
+    // The version is only known once the crawl has seen the crate root
+    let version = stats.crawl.version.clone();
+    if staging.is_staged() {
+        // A staged run cut short stays staged; the next population resumes it
+        if stats.timed_out {
+            eprintln!(
+                "⏱️  {crate_name} reached its time limit with {} chunks staged in this run; generation {} stays live",
+                stats.stored_chunks, staging.live.generation
+            );
+            if let Err(e) = db.record_partial_population(config.id).await {
+                eprintln!("⚠️  Failed to record partial population of {crate_name}: {e}");
+            }
+            return Ok(PopulationReport {
+                crate_name: crate_name.to_string(),
+                version,
+                stats,
+                total_time: total_start.elapsed(),
+            });
+        }
+        staging.promote(db, crate_name, version.as_deref()).await?;
+    } else if stats.stored_chunks > 0 {
+        db.upsert_crate(crate_name, version.as_deref(), target)
+            .await?;
+    }
+
+    // Inserted rows aren't proof that queries find them. A promoted generation is tested
+    // through the same path queries take, and swapped back out if it fails
+    let self_test = match self_test(db, crate_name, target).await {
+        Ok(self_test) => self_test,
+        Err(e) => {
+            revert_staging(db, &staging, crate_name).await;
+            return Err(e);
+        }
+    };
+    if let Some(job_id) = job_id {
+        if let Err(e) = db.set_population_job_self_test(job_id, &self_test).await {
+            eprintln!("⚠️  Failed to store self-test result for job {job_id}: {e}");
+        }
+    }
+    if let Some(error) = &self_test.error {
+        eprintln!("❌ Self-test of {crate_name} failed: {error}");
+        revert_staging(db, &staging, crate_name).await;
+        return Err(ServerError::Internal(format!(
+            "Population of {crate_name} failed its self-test: {error}"
+        )));
+    }
+    if staging.is_staged() {
+        staging.collect_garbage(db, crate_name).await;
+    }
+    eprintln!(
+        "🔎 Self-test of {crate_name} found {} with similarity {:.3}",
+        self_test.sample_doc_path.as_deref().unwrap_or_default(),
+        self_test.top_similarity.unwrap_or_default()
+    );
+
+    // A run cut short keeps what it stored; leaving last_populated alone makes the next
+    // population resume after it
+    if stats.timed_out {
+        eprintln!(
+            "⏱️  {crate_name} reached its time limit with {} chunks stored in this run; marked partial",
+            stats.stored_chunks
+        );
+        if let Err(e) = db.record_partial_population(config.id).await {
+            eprintln!("⚠️  Failed to record partial population of {crate_name}: {e}");
+        }
+        return Ok(PopulationReport {
+            crate_name: crate_name.to_string(),
+            version,
+            stats,
+            total_time: total_start.elapsed(),
+        });
+    }
+
+    // Record what was populated and when, for staleness warnings and update checks
+    let stored_docs = db
+        .count_target_documents(crate_name, target)
+        .await
+        .unwrap_or(0);
+    let expected_docs = config.expected_docs_baseline(stored_docs);
+    if let Some(expected) = expected_docs {
+        eprintln!("📏 Set expected_docs for {crate_name} to {expected} from its first population");
+    }
+    if let Err(e) = db
+        .record_crate_population(config.id, version.as_deref(), expected_docs)
+        .await
+    {
+        eprintln!("⚠️  Failed to record population of {crate_name}: {e}");
+    }
+    if stats.truncated {
+        eprintln!(
+            "✂️  {crate_name} stopped at its max_embeddings of {}; the index is partial",
+            config.max_embeddings.unwrap_or_default()
+        );
+    }
+    if let Err(e) = db.set_crate_truncated(config.id, stats.truncated).await {
+        eprintln!("⚠️  Failed to record truncation of {crate_name}: {e}");
+    }
+    let low_doc_coverage = stats.crawl.diagnostics.low_doc_coverage;
+    if low_doc_coverage {
+        eprintln!("📄 {crate_name} has minimal API documentation; its README was stored instead");
+    }
+    if let Err(e) = db
+        .set_crate_low_doc_coverage(config.id, low_doc_coverage)
+        .await
+    {
+        eprintln!("⚠️  Failed to record low doc coverage of {crate_name}: {e}");
+    }
+    warm_orientation(db, provider.as_ref(), crate_name).await;
+
+    let total_time = total_start.elapsed();
+    eprintln!(
+        "🎉 Successfully populated crate {crate_name} with {} embeddings ({} tokens, {} already stored) in {:.2}s total",
+        stats.stored_chunks,
+        stats.total_tokens,
+        stats.skipped_chunks,
+        total_time.as_secs_f64()
+    );
+    Ok(PopulationReport {
+        crate_name: crate_name.to_string(),
+        version,
+        stats,
+        total_time,
+    })
+}
@@ -0,0 +1,124 @@
+//! Documentation profiles: separate corpora served by one HTTP server
+//!
+//! A profile names a corpus, e.g. `internal` next to the open-source crates, and maps it
+//! to a namespace: a PostgreSQL schema holding its own copy of every table, so crates,
+//! configurations, jobs and feedback of one profile are invisible to the others. A
+//! profile may also embed with its own provider and model. The HTTP server serves each
+//! profile's tools on `/sse/{name}`, while `/sse` keeps serving the default schema.
+//!
+//! Profiles come from `MCPDOCS_PROFILES`, either a JSON array or the path of a file
+//! holding one:
+//!
+//! ```json
+//! [{"name": "internal", "namespace": "internal_docs", "embedding_provider": "voyage"}]
+//! ```
+
+use crate::error::ServerError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Environment variable holding the profiles, inline or as a file path
+pub const PROFILES_ENV: &str = "MCPDOCS_PROFILES";
+
+/// One corpus served next to the default one
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Path segment the profile is served on (`/sse/{name}`): lowercase letters, digits,
+    /// `-` and `_`
+    pub name: String,
+    /// PostgreSQL schema holding the profile's tables
+    pub namespace: String,
+    /// `openai` or `voyage`; unset embeds with the server's provider
+    #[serde(default)]
+    pub embedding_provider: Option<String>,
+    /// Model for the profile's provider; unset uses that provider's default, or the
+    /// server's model when the provider is the server's too
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+}
+
+impl Profile {
+    /// The SSE path serving this profile under `sse_path`, e.g. `/sse/internal`
+    pub fn sse_path(&self, sse_path: &str) -> String {
+        format!("{}/{}", sse_path.trim_end_matches('/'), self.name)
+    }
+
+    /// Whether the profile embeds with a provider or model of its own
+    pub fn has_own_embedding(&self) -> bool {
+        self.embedding_provider.is_some() || self.embedding_model.is_some()
+    }
+}
+
+/// Parse and check a JSON array of profiles
+///
+/// Names and namespaces must be unique, and no profile may use the default `public`
+/// schema or PostgreSQL's own `pg_` ones.
+pub fn parse_profiles(json: &str) -> Result<Vec<Profile>, ServerError> {
+    let profiles: Vec<Profile> = serde_json::from_str(json)
+        .map_err(|e| ServerError::Config(format!("{PROFILES_ENV} is not valid: {e}")))?;
+    let mut names = HashSet::new();
+    let mut namespaces = HashSet::new();
+    for profile in &profiles {
+        if !is_valid_name(&profile.name) {
+            return Err(ServerError::Config(format!(
+                "Profile name '{}' must be 1-63 lowercase letters, digits, '-' or '_'",
+                profile.name
+            )));
+        }
+        if !is_valid_namespace(&profile.namespace) {
+            return Err(ServerError::Config(format!(
+                "Profile '{}' namespace '{}' must be a lowercase schema name (letters, digits and '_', not starting with a digit), other than public and pg_*",
+                profile.name, profile.namespace
+            )));
+        }
+        if !names.insert(profile.name.as_str()) {
+            return Err(ServerError::Config(format!(
+                "Profile '{}' is defined twice",
+                profile.name
+            )));
+        }
+        if !namespaces.insert(profile.namespace.as_str()) {
+            return Err(ServerError::Config(format!(
+                "Namespace '{}' is used by more than one profile; profiles can't share data",
+                profile.namespace
+            )));
+        }
+    }
+    Ok(profiles)
+}
+
+/// Profiles from [`PROFILES_ENV`]; none when it is unset or empty
+pub fn load_profiles() -> Result<Vec<Profile>, ServerError> {
+    let value = match std::env::var(PROFILES_ENV) {
+        Ok(value) if !value.trim().is_empty() => value,
+        _ => return Ok(Vec::new()),
+    };
+    if value.trim_start().starts_with('[') {
+        return parse_profiles(&value);
+    }
+    let json = std::fs::read_to_string(value.trim()).map_err(|e| {
+        ServerError::Config(format!(
+            "{PROFILES_ENV} is neither a JSON array nor a readable file ({}): {e}",
+            value.trim()
+        ))
+    })?;
+    parse_profiles(&json)
+}
+
+fn is_valid_name(name: &str) -> bool {
+    (1..=63).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+fn is_valid_namespace(namespace: &str) -> bool {
+    (1..=63).contains(&namespace.len())
+        && !namespace.starts_with(|c: char| c.is_ascii_digit())
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && namespace != "public"
+        && !namespace.starts_with("pg_")
+}
@@ -0,0 +1,183 @@
+//! Retrieval provenance for `query_rust_docs`
+//!
+//! With `include_provenance` a query answers with a JSON record of how its results were
+//! produced: the embedding model and dimension, the retrieval strategy, the parameters the
+//! SQL search ran with, the crate versions searched, every candidate document considered
+//! (and why those not returned were left out) and the time spent per stage. The record has
+//! a fixed shape, versioned by [`SCHEMA_VERSION`], so audits can compare answers over time.
+
+use crate::database::ScoredDoc;
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashSet},
+    time::Duration,
+};
+
+/// Bumped whenever a field of [`Provenance`] is added, renamed or removed
+pub const SCHEMA_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Provenance {
+    pub schema_version: u32,
+    pub crate_name: String,
+    /// Crate versions of the candidate documents, as last populated
+    pub crate_versions: Vec<String>,
+    pub embedding: EmbeddingProvenance,
+    pub strategy: StrategyProvenance,
+    pub parameters: ParameterProvenance,
+    /// Every document a search returned, best first, with what became of it
+    pub candidates: Vec<Candidate>,
+    pub timings_ms: StageTimings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmbeddingProvenance {
+    /// Model that embedded the queries (a fallback provider's when the primary failed)
+    pub model: String,
+    pub dimension: usize,
+    /// Whether a fallback provider stood in for the configured one
+    pub fallback: bool,
+    /// Texts that were embedded and searched, after query hints, splitting and HyDE
+    pub queries: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StrategyProvenance {
+    /// How candidates were retrieved; always `vector` (pgvector cosine distance) for now
+    pub retrieval: &'static str,
+    pub expand_query: bool,
+    pub hyde: bool,
+    /// Reordering applied after retrieval, e.g. `generic_penalty`
+    pub reranking: Vec<&'static str>,
+    pub dedup: bool,
+    /// Languages kept for crates that tag them (ISO 639-3)
+    pub languages: Option<Vec<&'static str>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParameterProvenance {
+    /// Results returned
+    pub limit: usize,
+    /// Rows fetched per query from the database
+    pub fetch_limit: usize,
+    pub min_similarity: Option<f32>,
+    /// Shortest chunk kept, in tokens (0 = no minimum)
+    pub min_tokens: u32,
+    pub path_prefix: Option<String>,
+    /// Docblock section searched (`None` = every chunk)
+    pub section: Option<String>,
+    /// HNSW `ef_search` override (`None` = the database default)
+    pub ef_search: Option<u32>,
+    pub query_hint: Option<String>,
+    pub generic_penalty: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Candidate {
+    pub doc_path: String,
+    /// Best similarity across the queries that found it
+    pub similarity: f32,
+    pub outcome: Outcome,
+}
+
+/// What became of a candidate document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Returned,
+    /// Ranked below the number of results kept
+    NotInTop,
+    BelowMinSimilarity,
+    /// Shorter than the requested `min_tokens`
+    BelowMinTokens,
+    /// Tagged with a language the crate doesn't keep
+    OtherLanguage,
+    /// Repeated the text of a higher-ranked result
+    Duplicate,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StageTimings {
+    /// Splitting the question and drafting a HyDE answer
+    pub query_expansion: f64,
+    pub embedding: f64,
+    pub search: f64,
+    /// Filtering, reranking and deduplication
+    pub ranking: f64,
+    pub total: f64,
+}
+
+/// `elapsed` in milliseconds, to a tenth
+pub fn millis(elapsed: Duration) -> f64 {
+    (elapsed.as_secs_f64() * 10_000.0).round() / 10.0
+}
+
+/// Follows every candidate document through the ranking stages
+#[derive(Debug, Default)]
+pub struct CandidateTrail {
+    candidates: Vec<(Candidate, Option<String>)>,
+    decided: HashSet<String>,
+}
+
+impl CandidateTrail {
+    /// Record the documents one search returned
+    pub fn consider<'a>(&mut self, docs: impl IntoIterator<Item = &'a ScoredDoc>) {
+        for doc in docs {
+            match self
+                .candidates
+                .iter_mut()
+                .find(|(c, _)| c.doc_path == doc.doc_path)
+            {
+                Some((candidate, _)) => {
+                    candidate.similarity = candidate.similarity.max(doc.similarity)
+                }
+                None => self.candidates.push((
+                    Candidate {
+                        doc_path: doc.doc_path.clone(),
+                        similarity: doc.similarity,
+                        outcome: Outcome::NotInTop,
+                    },
+                    doc.crate_version.clone(),
+                )),
+            }
+        }
+    }
+
+    /// Candidates still undecided that a stage left out of `kept` get `outcome`
+    pub fn left_out(&mut self, outcome: Outcome, kept: &[ScoredDoc]) {
+        let kept: HashSet<&str> = kept.iter().map(|doc| doc.doc_path.as_str()).collect();
+        for (candidate, _) in &mut self.candidates {
+            if !kept.contains(candidate.doc_path.as_str())
+                && self.decided.insert(candidate.doc_path.clone())
+            {
+                candidate.outcome = outcome;
+            }
+        }
+    }
+
+    /// The candidates, best first, with `returned` marked, and the crate versions they came from
+    pub fn finish(self, returned: &[ScoredDoc]) -> (Vec<Candidate>, Vec<String>) {
+        let returned: HashSet<&str> = returned.iter().map(|doc| doc.doc_path.as_str()).collect();
+        let versions: BTreeSet<String> = self
+            .candidates
+            .iter()
+            .filter_map(|(_, version)| version.clone())
+            .collect();
+        let mut candidates: Vec<Candidate> = self
+            .candidates
+            .into_iter()
+            .map(|(mut candidate, _)| {
+                if returned.contains(candidate.doc_path.as_str()) {
+                    candidate.outcome = Outcome::Returned;
+                }
+                candidate
+            })
+            .collect();
+        candidates.sort_by(|a, b| {
+            b.similarity
+                .total_cmp(&a.similarity)
+                .then_with(|| a.doc_path.cmp(&b.doc_path))
+        });
+        (candidates, versions.into_iter().collect())
+    }
+}
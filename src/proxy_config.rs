@@ -0,0 +1,102 @@
+//! The legacy `proxy-config.json` that `migrate_config` imports into `crate_configs`
+//!
+//! The file predates the database and grew fields over time, so parsing keeps anything it
+//! doesn't recognize in `unknown` instead of silently dropping it; the migration warns about
+//! each one so a setting that would be lost on import is at least visible.
+
+use crate::{database::CrateConfig, error::ServerError, validation};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ProxyConfig {
+    pub rustdocs_binary_path: String,
+    pub crates: Vec<OldCrateConfig>,
+    /// Top-level fields this version doesn't know
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OldCrateConfig {
+    pub name: String,
+    pub features: Option<Vec<String>>,
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_docs: Option<usize>,
+    /// Version pin, e.g. "1.35.0" or "^0.12" (absent = latest)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Most docs.rs pages to crawl for this crate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_pages: Option<u32>,
+    /// Per-crate fields this version doesn't know
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, Value>,
+}
+
+impl ProxyConfig {
+    pub fn parse(content: &str) -> Result<Self, ServerError> {
+        serde_json::from_str(content)
+            .map_err(|e| ServerError::Config(format!("Failed to parse proxy-config.json: {e}")))
+    }
+
+    /// Paths of every field that will not be migrated, e.g. `crates[2].max_depth`
+    pub fn unknown_fields(&self) -> Vec<String> {
+        let top_level = self.unknown.keys().cloned();
+        let per_crate = self.crates.iter().enumerate().flat_map(|(i, krate)| {
+            krate
+                .unknown
+                .keys()
+                .map(move |field| format!("crates[{i}].{field}"))
+        });
+        top_level.chain(per_crate).collect()
+    }
+}
+
+impl OldCrateConfig {
+    /// The configuration's `version_spec`: the pin, or `latest` without one
+    pub fn version_spec(&self) -> &str {
+        self.version
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .unwrap_or("latest")
+    }
+
+    /// The database configuration for this entry
+    ///
+    /// With `existing`, only the fields the file sets are replaced, so settings added
+    /// through `add_crate` since the last migration (selectors, hints, ...) survive.
+    pub fn to_crate_config(
+        &self,
+        existing: Option<CrateConfig>,
+    ) -> Result<CrateConfig, ServerError> {
+        let version_spec = self.version_spec();
+        if !validation::is_valid_version_spec(version_spec) {
+            return Err(ServerError::Config(format!(
+                "{}: version '{version_spec}' is not 'latest' or a semver version or requirement",
+                self.name
+            )));
+        }
+        let max_pages = self
+            .max_pages
+            .map(|n| {
+                i32::try_from(n).map_err(|_| {
+                    ServerError::Config(format!("{}: max_pages {n} is too large", self.name))
+                })
+            })
+            .transpose()?;
+
+        let mut config = existing.unwrap_or_else(|| CrateConfig::new(&self.name, version_spec));
+        config.features = self.features.clone().unwrap_or_default();
+        config.enabled = self.enabled;
+        if let Some(expected_docs) = self.expected_docs {
+            config.expected_docs = i32::try_from(expected_docs).unwrap_or(i32::MAX);
+            config.auto_expected_docs = false;
+        }
+        config.max_pages = max_pages;
+        Ok(config)
+    }
+}
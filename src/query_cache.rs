@@ -0,0 +1,289 @@
+use serde::Serialize;
+use std::{
+    collections::hash_map::DefaultHasher,
+    collections::{HashMap, VecDeque},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Identifies a query result: crate, normalized question, embedding model and output options
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryCacheKey {
+    crate_name: String,
+    question_hash: u64,
+    model: String,
+    params: String,
+}
+
+impl QueryCacheKey {
+    /// Build a key; `params` covers any argument that changes the formatted output
+    pub fn new(crate_name: &str, question: &str, model: &str, params: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        normalize_question(question).hash(&mut hasher);
+        Self {
+            crate_name: crate_name.to_string(),
+            question_hash: hasher.finish(),
+            model: model.to_string(),
+            params: params.to_string(),
+        }
+    }
+}
+
+/// Lowercase and collapse whitespace so trivially different phrasings share an entry
+pub fn normalize_question(question: &str) -> String {
+    question
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug)]
+struct CacheEntry<V> {
+    response: V,
+    inserted_at: Instant,
+    last_used: Instant,
+}
+
+/// Hit/miss counters and current size of a [`QueryCache`]
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryCacheStats {
+    pub entries: usize,
+    pub capacity: usize,
+    pub ttl_secs: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub invalidations: u64,
+    /// Hits over lookups, 0.0 before any lookup
+    pub hit_rate: f64,
+}
+
+/// Formatted query results with a TTL, evicting the least recently used entry when full
+///
+/// A capacity of 0 disables caching. Values default to formatted responses; the stdio
+/// server stores question embeddings instead.
+#[derive(Debug)]
+pub struct QueryCache<V = String> {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<HashMap<QueryCacheKey, CacheEntry<V>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl<V: Clone> QueryCache<V> {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Cached response for `key`, if present and not expired
+    pub fn get(&self, key: &QueryCacheKey) -> Option<V> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let response = match entries.get_mut(key) {
+            Some(entry) if now.duration_since(entry.inserted_at) < self.ttl => {
+                entry.last_used = now;
+                Some(entry.response.clone())
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        let counter = if response.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        response
+    }
+
+    pub fn insert(&self, key: QueryCacheKey, response: V) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            // Drop expired entries first, then the least recently used one
+            entries.retain(|_, entry| now.duration_since(entry.inserted_at) < self.ttl);
+            if entries.len() >= self.capacity {
+                let lru = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| key.clone());
+                if let Some(lru) = lru {
+                    entries.remove(&lru);
+                    self.evictions.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        entries.insert(
+            key,
+            CacheEntry {
+                response,
+                inserted_at: now,
+                last_used: now,
+            },
+        );
+    }
+
+    /// Drop every entry for a crate, returning how many were removed
+    #[allow(dead_code)] // Used by the HTTP server
+    pub fn invalidate_crate(&self, crate_name: &str) -> usize {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let before = entries.len();
+        entries.retain(|key, _| key.crate_name != crate_name);
+        let removed = before - entries.len();
+        self.invalidations
+            .fetch_add(removed as u64, Ordering::Relaxed);
+        removed
+    }
+
+    #[allow(dead_code)] // Used by the HTTP server
+    pub fn stats(&self) -> QueryCacheStats {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner()).len();
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        QueryCacheStats {
+            entries,
+            capacity: self.capacity,
+            ttl_secs: self.ttl.as_secs(),
+            hits,
+            misses,
+            evictions: self.evictions.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            hit_rate: if lookups == 0 {
+                0.0
+            } else {
+                hits as f64 / lookups as f64
+            },
+        }
+    }
+}
+
+#[allow(dead_code)] // Used by the HTTP server
+#[derive(Debug)]
+struct RecentQuestion {
+    key: QueryCacheKey,
+    response: String,
+    asked_at: Instant,
+}
+
+/// Questions each connection asked recently, so exact repeats are answered without a search
+///
+/// Unlike [`QueryCache`], entries belong to one connection and a hit changes the
+/// response: the earlier answer comes back with a note saying when it was asked.
+/// Each connection keeps at most `per_connection` questions; a zero window disables it.
+#[allow(dead_code)] // Used by the HTTP server
+#[derive(Debug)]
+pub struct RecentQuestions {
+    window: Duration,
+    per_connection: usize,
+    sessions: Mutex<HashMap<String, VecDeque<RecentQuestion>>>,
+}
+
+#[allow(dead_code)] // Used by the HTTP server
+impl RecentQuestions {
+    pub fn new(window: Duration, per_connection: usize) -> Self {
+        Self {
+            window,
+            per_connection,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.window.is_zero() && self.per_connection > 0
+    }
+
+    /// The answer this connection got for the same question within the window, and its age
+    pub fn repeat_of(
+        &self,
+        connection_id: &str,
+        key: &QueryCacheKey,
+    ) -> Option<(String, Duration)> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        let questions = sessions.get_mut(connection_id)?;
+        let now = Instant::now();
+        questions.retain(|question| now.duration_since(question.asked_at) < self.window);
+        questions
+            .iter()
+            .find(|question| &question.key == key)
+            .map(|question| {
+                (
+                    question.response.clone(),
+                    now.duration_since(question.asked_at),
+                )
+            })
+    }
+
+    /// Remember the answer a connection got, dropping its oldest question when full
+    pub fn record(&self, connection_id: &str, key: QueryCacheKey, response: String) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        let questions = sessions.entry(connection_id.to_string()).or_default();
+        let now = Instant::now();
+        questions.retain(|question| {
+            question.key != key && now.duration_since(question.asked_at) < self.window
+        });
+        questions.push_back(RecentQuestion {
+            key,
+            response,
+            asked_at: now,
+        });
+        while questions.len() > self.per_connection {
+            questions.pop_front();
+        }
+    }
+
+    /// Drop a connection's questions once it has closed
+    pub fn forget_connection(&self, connection_id: &str) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.remove(connection_id);
+    }
+}
+
+/// Note appended to an answer returned for a repeated question
+#[allow(dead_code)] // Used by the HTTP server
+pub fn repeat_note(age: Duration) -> String {
+    format!(
+        "Note: repeat of a question asked {}s ago on this connection, so these are the same results. Rephrase the question or raise `limit` to see other documents.",
+        age.as_secs()
+    )
+}
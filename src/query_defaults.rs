@@ -0,0 +1,136 @@
+//! Deployment-wide defaults for `query_rust_docs`
+//!
+//! Some clients can't pass per-request options, so a deployment can set its own defaults
+//! from the environment. They only fill in what neither the request nor the crate's
+//! configured defaults set: request, then crate configuration, then environment, then
+//! the built-in value.
+
+use crate::{error::ServerError, search::SearchParams, search::MAX_RESULT_LIMIT};
+use serde::Serialize;
+use std::{env, str::FromStr};
+
+/// Query defaults read from `QUERY_DEFAULT_LIMIT`, `QUERY_MIN_SIMILARITY`,
+/// `QUERY_SUMMARIZE` and `QUERY_MAX_RESPONSE_TOKENS`; `None` means "not set"
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryDefaults {
+    pub limit: Option<usize>,
+    pub min_similarity: Option<f32>,
+    /// Whether the stdio server answers with the LLM instead of returning the documents
+    pub summarize: Option<bool>,
+    /// Token budget for one response
+    pub max_response_tokens: Option<usize>,
+}
+
+/// The defaults a request that sets nothing gets, as reported to clients
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectiveQueryDefaults {
+    pub limit: usize,
+    pub min_similarity: Option<f32>,
+    pub summarize: bool,
+    pub max_response_tokens: Option<usize>,
+}
+
+impl QueryDefaults {
+    /// Read the defaults from the process environment
+    pub fn from_env() -> Result<Self, ServerError> {
+        Self::from_lookup(|name| env::var(name).ok())
+    }
+
+    /// Read the defaults through `lookup`; blank values count as unset
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ServerError> {
+        let limit = parse(&lookup, "QUERY_DEFAULT_LIMIT", |n: &usize| {
+            (1..=MAX_RESULT_LIMIT).contains(n)
+        })?;
+        let min_similarity = parse(&lookup, "QUERY_MIN_SIMILARITY", |s: &f32| {
+            (0.0..=1.0).contains(s)
+        })?;
+        let summarize = match lookup("QUERY_SUMMARIZE").filter(|v| !v.trim().is_empty()) {
+            None => None,
+            Some(value) => Some(match value.trim().to_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => true,
+                "0" | "false" | "no" | "off" => false,
+                _ => {
+                    return Err(ServerError::Config(format!(
+                        "QUERY_SUMMARIZE must be true or false, got '{value}'"
+                    )))
+                }
+            }),
+        };
+        let max_response_tokens = parse(&lookup, "QUERY_MAX_RESPONSE_TOKENS", |n: &usize| *n > 0)?;
+        Ok(Self {
+            limit,
+            min_similarity,
+            summarize,
+            max_response_tokens,
+        })
+    }
+
+    /// Search defaults to apply after the crate's own, e.g. `params.or(crate).or(this)`
+    pub fn search_params(&self) -> SearchParams {
+        SearchParams {
+            limit: self.limit,
+            min_similarity: self.min_similarity,
+            path_prefix: None,
+        }
+    }
+
+    /// Whether to summarize, unless the request says
+    pub fn summarize(&self, requested: Option<bool>) -> bool {
+        requested.or(self.summarize).unwrap_or(true)
+    }
+
+    /// Response token budget, unless the request sets one (an explicit 0 means unlimited)
+    pub fn max_response_tokens(&self, requested: Option<u32>) -> Option<usize> {
+        requested
+            .map(|n| n as usize)
+            .or(self.max_response_tokens)
+            .filter(|&n| n > 0)
+    }
+
+    /// What a request that sets nothing gets on a server whose built-in limit is `builtin_limit`
+    pub fn effective(&self, builtin_limit: usize) -> EffectiveQueryDefaults {
+        EffectiveQueryDefaults {
+            limit: self.limit.unwrap_or(builtin_limit),
+            min_similarity: self.min_similarity,
+            summarize: self.summarize(None),
+            max_response_tokens: self.max_response_tokens,
+        }
+    }
+
+    /// One line for server instructions
+    pub fn describe(&self, builtin_limit: usize) -> String {
+        let effective = self.effective(builtin_limit);
+        format!(
+            "Query defaults (used when a request and the crate's configuration leave them unset): limit {}, min_similarity {}, summarize {}, max response tokens {}.",
+            effective.limit,
+            effective
+                .min_similarity
+                .map_or_else(|| "none".to_string(), |s| s.to_string()),
+            if effective.summarize { "on" } else { "off" },
+            effective
+                .max_response_tokens
+                .map_or_else(|| "unlimited".to_string(), |n| n.to_string()),
+        )
+    }
+}
+
+/// Parse `name`, rejecting values that don't parse or fail `valid`
+fn parse<T>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    name: &str,
+    valid: impl Fn(&T) -> bool,
+) -> Result<Option<T>, ServerError>
+where
+    T: FromStr,
+{
+    match lookup(name).filter(|v| !v.trim().is_empty()) {
+        None => Ok(None),
+        Some(value) => value
+            .trim()
+            .parse::<T>()
+            .ok()
+            .filter(|parsed| valid(parsed))
+            .map(Some)
+            .ok_or_else(|| ServerError::Config(format!("{name} is not a valid value: '{value}'"))),
+    }
+}
@@ -0,0 +1,221 @@
+//! What the query log keeps of each question, and for how long
+//!
+//! The HTTP server records every `query_rust_docs` call in `query_log` (see
+//! `sql/migrations/add_query_log.sql`) when `QUERY_LOG_POLICY` is set. The policy decides
+//! what is kept of the question text: all of it, a keyed hash that lets repeats be counted
+//! without the text being recoverable, or nothing. Every row records the policy it was
+//! written under, and both question columns are nullable, so switching policy needs no
+//! schema change. Rows older than `QUERY_LOG_RETENTION_DAYS` are deleted periodically.
+
+use crate::{error::ServerError, query_cache::normalize_question};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::{env, fmt, str::FromStr, time::Duration};
+
+/// Days a row is kept unless `QUERY_LOG_RETENTION_DAYS` says otherwise
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// How often rows past the retention window are deleted
+pub const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Shortest `QUERY_LOG_HMAC_KEY` accepted, so hashes can't be reversed by guessing the key
+pub const MIN_HMAC_KEY_LEN: usize = 16;
+
+/// What the query log keeps of a question
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)] // Named after the QUERY_LOG_POLICY values
+pub enum QueryLogPolicy {
+    /// The question as asked
+    StoreFull,
+    /// HMAC-SHA256 of the normalized question, keyed with `QUERY_LOG_HMAC_KEY`
+    StoreHashed,
+    /// Nothing of the question; the row still records the crate, results and timing
+    StoreNone,
+}
+
+impl QueryLogPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::StoreFull => "store_full",
+            Self::StoreHashed => "store_hashed",
+            Self::StoreNone => "store_none",
+        }
+    }
+}
+
+impl fmt::Display for QueryLogPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for QueryLogPolicy {
+    type Err = ServerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "store_full" => Ok(Self::StoreFull),
+            "store_hashed" => Ok(Self::StoreHashed),
+            "store_none" => Ok(Self::StoreNone),
+            _ => Err(ServerError::Config(format!(
+                "QUERY_LOG_POLICY must be store_full, store_hashed, store_none or off, got '{value}'"
+            ))),
+        }
+    }
+}
+
+/// The question columns of one row under a policy
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StoredQuestion {
+    pub question: Option<String>,
+    pub question_hash: Option<String>,
+}
+
+/// Query log settings read from `QUERY_LOG_POLICY`, `QUERY_LOG_HMAC_KEY` and
+/// `QUERY_LOG_RETENTION_DAYS`
+#[derive(Clone, Default)]
+pub struct QueryLogSettings {
+    /// `None` when the log is off
+    pub policy: Option<QueryLogPolicy>,
+    hmac_key: Option<Vec<u8>>,
+    /// Days rows are kept (0 keeps them forever)
+    pub retention_days: u32,
+}
+
+// The key must never end up in logs
+impl fmt::Debug for QueryLogSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryLogSettings")
+            .field("policy", &self.policy)
+            .field("hmac_key", &self.hmac_key.as_ref().map(|_| "<redacted>"))
+            .field("retention_days", &self.retention_days)
+            .finish()
+    }
+}
+
+/// The query log settings as reported by `get_server_stats`; the key is never included
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryLogStatus {
+    /// `store_full`, `store_hashed`, `store_none` or `off`
+    pub policy: &'static str,
+    pub retention_days: u32,
+    /// Seconds between retention cleanups, `None` when rows are kept forever
+    pub cleanup_interval_secs: Option<u64>,
+}
+
+impl QueryLogSettings {
+    /// Read the settings from the process environment
+    pub fn from_env() -> Result<Self, ServerError> {
+        Self::from_lookup(|name| env::var(name).ok())
+    }
+
+    /// Read the settings through `lookup`; blank values count as unset
+    ///
+    /// `store_hashed` without a key of at least [`MIN_HMAC_KEY_LEN`] bytes is an error
+    /// rather than a silent fallback to another policy.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ServerError> {
+        let set = |name: &str| lookup(name).filter(|v| !v.trim().is_empty());
+        let policy = match set("QUERY_LOG_POLICY") {
+            None => None,
+            Some(value) if value.trim().eq_ignore_ascii_case("off") => None,
+            Some(value) => Some(value.parse::<QueryLogPolicy>()?),
+        };
+        let hmac_key = set("QUERY_LOG_HMAC_KEY").map(String::into_bytes);
+        if policy == Some(QueryLogPolicy::StoreHashed)
+            && hmac_key
+                .as_ref()
+                .is_none_or(|key| key.len() < MIN_HMAC_KEY_LEN)
+        {
+            return Err(ServerError::Config(format!(
+                "QUERY_LOG_POLICY=store_hashed needs QUERY_LOG_HMAC_KEY of at least {MIN_HMAC_KEY_LEN} bytes"
+            )));
+        }
+        let retention_days = match set("QUERY_LOG_RETENTION_DAYS") {
+            None => DEFAULT_RETENTION_DAYS,
+            Some(value) => value.trim().parse().map_err(|_| {
+                ServerError::Config(format!(
+                    "QUERY_LOG_RETENTION_DAYS must be a whole number of days, got '{value}'"
+                ))
+            })?,
+        };
+        Ok(Self {
+            policy,
+            hmac_key,
+            retention_days,
+        })
+    }
+
+    /// Settings with `policy` and an explicit key, e.g. for tests
+    pub fn new(
+        policy: Option<QueryLogPolicy>,
+        hmac_key: Option<&[u8]>,
+        retention_days: u32,
+    ) -> Self {
+        Self {
+            policy,
+            hmac_key: hmac_key.map(<[u8]>::to_vec),
+            retention_days,
+        }
+    }
+
+    /// What a row written now keeps of `question`
+    pub fn store(&self, question: &str) -> StoredQuestion {
+        match (self.policy, &self.hmac_key) {
+            (Some(QueryLogPolicy::StoreFull), _) => StoredQuestion {
+                question: Some(question.to_string()),
+                question_hash: None,
+            },
+            (Some(QueryLogPolicy::StoreHashed), Some(key)) => StoredQuestion {
+                question: None,
+                question_hash: Some(question_hash(key, question)),
+            },
+            _ => StoredQuestion::default(),
+        }
+    }
+
+    /// Rows created before this are past retention; `None` when rows are kept forever
+    pub fn retention_cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        (self.retention_days > 0)
+            .then(|| now - chrono::Duration::days(i64::from(self.retention_days)))
+    }
+
+    pub fn status(&self) -> QueryLogStatus {
+        QueryLogStatus {
+            policy: self.policy.map_or("off", QueryLogPolicy::as_str),
+            retention_days: self.retention_days,
+            cleanup_interval_secs: (self.retention_days > 0).then_some(CLEANUP_INTERVAL.as_secs()),
+        }
+    }
+}
+
+/// Hex HMAC-SHA256 of the normalized `question`, so rephrasings that only differ in case or
+/// spacing count as repeats
+pub fn question_hash(key: &[u8], question: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(normalize_question(question).as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// One `query_rust_docs` call as stored in `query_log`
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct QueryLogEntry {
+    pub query_id: String,
+    pub crate_name: String,
+    /// Policy the row was written under
+    pub policy: String,
+    pub question: Option<String>,
+    pub question_hash: Option<String>,
+    /// Results shown; `None` when the answer was served from the cache or as a repeat
+    pub result_count: Option<i32>,
+    pub top_similarity: Option<f32>,
+    pub cached: bool,
+    pub latency_ms: i64,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,80 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// `query_rust_docs` calls per minute per connection unless `QUERY_RATE_LIMIT_PER_MIN` says otherwise
+pub const DEFAULT_QUERY_LIMIT_PER_MINUTE: u32 = 60;
+
+/// `add_crate`/`add_crates` calls per minute per connection unless
+/// `ADD_CRATE_RATE_LIMIT_PER_MIN` says otherwise
+pub const DEFAULT_ADD_CRATE_LIMIT_PER_MINUTE: u32 = 5;
+
+/// Sliding-window call limiter keyed by (connection, bucket)
+///
+/// Each bucket is usually a tool name; tools that should share a budget (such as
+/// `add_crate` and `add_crates`) can use the same bucket.
+#[derive(Debug)]
+pub struct RateLimiter {
+    window: Duration,
+    calls: Mutex<HashMap<(String, &'static str), VecDeque<Instant>>>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(60))
+    }
+}
+
+impl RateLimiter {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a call if it fits within `limit` calls per window.
+    ///
+    /// A `limit` of 0 disables limiting for the bucket. When the limit is
+    /// exceeded, returns how long until the oldest call leaves the window.
+    pub fn check(
+        &self,
+        connection_id: &str,
+        bucket: &'static str,
+        limit: u32,
+    ) -> Result<(), Duration> {
+        if limit == 0 {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+        let history = calls
+            .entry((connection_id.to_string(), bucket))
+            .or_default();
+
+        while let Some(oldest) = history.front() {
+            if now.duration_since(*oldest) >= self.window {
+                history.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if history.len() >= limit as usize {
+            let oldest = history.front().copied().unwrap_or(now);
+            return Err(self.window.saturating_sub(now.duration_since(oldest)));
+        }
+
+        history.push_back(now);
+        Ok(())
+    }
+
+    /// Drop all counters for a connection once it has closed
+    pub fn forget_connection(&self, connection_id: &str) {
+        let mut calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+        calls.retain(|(id, _), _| id != connection_id);
+    }
+}
@@ -0,0 +1,124 @@
+//! Scheduled re-population of `latest`-tracked crates whose docs.rs version changed
+//!
+//! Every refresh run asks docs.rs which version `latest` currently resolves to for each
+//! enabled, populated crate configuration with `version_spec` `latest`, compares it with
+//! the configuration's `current_version`, and re-populates only the crates that moved.
+//! Unchanged crates cost one HEAD request instead of a crawl and re-embedding. The version
+//! comes from the same redirect the crawler records as `current_version`
+//! ([`crate::doc_loader::fetch_latest_version`]), so both sides compare like for like, per
+//! build target. Every decision of the last run is kept for the `refresh_status` tool.
+
+use crate::database::CrateConfig;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// What a refresh run decided for one crate configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum Decision {
+    /// docs.rs serves a different version; a population job was queued
+    Updated { from: Option<String>, to: String },
+    /// docs.rs still serves the stored version
+    Unchanged { version: String },
+    /// Not checked or not re-populated, and why
+    Skipped { reason: String },
+}
+
+impl Decision {
+    fn skipped(reason: impl Into<String>) -> Self {
+        Self::Skipped {
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Why `config` is left out of version checks, if it is
+///
+/// Pinned versions never change, rustdoc JSON crates aren't on docs.rs, and crates that
+/// were never populated are auto-population's and `populate_pending`'s business.
+pub fn skip_reason(config: &CrateConfig) -> Option<&'static str> {
+    if !config.enabled {
+        Some("disabled")
+    } else if config.version_spec != "latest" {
+        Some("pinned to a version")
+    } else if config.rustdoc_json_url.is_some() {
+        Some("populated from rustdoc JSON, not docs.rs")
+    } else if config.last_populated.is_none() {
+        Some("never populated")
+    } else {
+        None
+    }
+}
+
+/// Compare the version docs.rs serves for `config` with the one it was populated from
+pub fn decide(config: &CrateConfig, docs_rs_version: Option<&str>) -> Decision {
+    match docs_rs_version {
+        None => Decision::skipped("docs.rs did not report a version"),
+        Some(version) if config.current_version.as_deref() == Some(version) => {
+            Decision::Unchanged {
+                version: version.to_string(),
+            }
+        }
+        Some(version) => Decision::Updated {
+            from: config.current_version.clone(),
+            to: version.to_string(),
+        },
+    }
+}
+
+/// One crate configuration's outcome in a refresh run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CrateDecision {
+    pub crate_name: String,
+    pub target: Option<String>,
+    #[serde(flatten)]
+    pub decision: Decision,
+    /// Population job queued for an updated crate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<i32>,
+}
+
+/// The last refresh run, as reported by `refresh_status`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RefreshStatus {
+    /// Seconds between runs (`None` while refreshing is off)
+    pub interval_secs: Option<u64>,
+    pub runs: u64,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    pub next_run_at: Option<DateTime<Utc>>,
+    /// Configurations whose docs.rs version was compared with the stored one in the last run
+    pub checked: usize,
+    /// Configurations re-populated because their version changed
+    pub updated: usize,
+    /// Configurations not compared: disabled, pinned, never populated, populating, or the
+    /// lookup failed
+    pub skipped: usize,
+    pub decisions: Vec<CrateDecision>,
+}
+
+impl RefreshStatus {
+    /// Replace the last run's decisions with those of a finished run
+    pub fn record_run(
+        &mut self,
+        started_at: DateTime<Utc>,
+        decisions: Vec<CrateDecision>,
+        next_run_at: Option<DateTime<Utc>>,
+    ) {
+        self.runs += 1;
+        self.last_started_at = Some(started_at);
+        self.last_finished_at = Some(Utc::now());
+        self.next_run_at = next_run_at;
+        self.updated = decisions
+            .iter()
+            .filter(|d| matches!(d.decision, Decision::Updated { .. }))
+            .count();
+        self.checked = self.updated
+            + decisions
+                .iter()
+                .filter(|d| matches!(d.decision, Decision::Unchanged { .. }))
+                .count();
+        self.skipped = decisions.len() - self.checked;
+        self.decisions = decisions;
+    }
+}
@@ -0,0 +1,84 @@
+use rmcp::{model::CallToolResult, Error as McpError};
+use std::{future::Future, time::Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, info_span, warn, Instrument};
+
+/// Short random identifier for a single tool call
+pub fn new_request_id() -> String {
+    format!("{:08x}", rand::random::<u32>())
+}
+
+/// Tag a client-facing error with the request ID so it can be matched to server logs
+///
+/// The ID is prepended to the message and added as `request_id` to the error data.
+pub fn tag_error(mut error: McpError, request_id: &str) -> McpError {
+    error.message = format!("[req {request_id}] {}", error.message).into();
+    error.data = Some(match error.data.take() {
+        Some(serde_json::Value::Object(mut map)) => {
+            map.insert("request_id".to_string(), request_id.into());
+            serde_json::Value::Object(map)
+        }
+        Some(other) => serde_json::json!({ "request_id": request_id, "details": other }),
+        None => serde_json::json!({ "request_id": request_id }),
+    });
+    error
+}
+
+/// Run a tool call inside a span carrying the connection and request IDs
+///
+/// Every tracing event emitted while the call runs inherits both IDs, and any
+/// error returned to the client is tagged with the request ID. When
+/// `disconnected` is given and fires first, the call is dropped mid-flight and
+/// logged as aborted rather than failed.
+pub async fn dispatch<F>(
+    connection_id: &str,
+    tool_name: &str,
+    disconnected: Option<&CancellationToken>,
+    call: F,
+) -> Result<CallToolResult, McpError>
+where
+    F: Future<Output = Result<CallToolResult, McpError>>,
+{
+    let request_id = new_request_id();
+    let span = info_span!("tool_call", connection_id, request_id = %request_id, tool = tool_name);
+
+    async {
+        let start = Instant::now();
+        info!("🔧 Tool call started");
+        let disconnect = async {
+            match disconnected {
+                Some(token) => token.cancelled().await,
+                None => std::future::pending().await,
+            }
+        };
+        let result = tokio::select! {
+            result = call => result,
+            _ = disconnect => {
+                info!(
+                    "🔌 Tool call aborted after {:?}: client disconnected",
+                    start.elapsed()
+                );
+                return Err(tag_error(
+                    McpError::internal_error("Request aborted: client disconnected", None),
+                    &request_id,
+                ));
+            }
+        };
+        match result {
+            Ok(result) => {
+                info!("✅ Tool call finished in {:?}", start.elapsed());
+                Ok(result)
+            }
+            Err(e) => {
+                warn!(
+                    "❌ Tool call failed after {:?}: {}",
+                    start.elapsed(),
+                    e.message
+                );
+                Err(tag_error(e, &request_id))
+            }
+        }
+    }
+    .instrument(span)
+    .await
+}
@@ -0,0 +1,129 @@
+//! Splitting large tool responses into several MCP content items
+//!
+//! Some MCP clients truncate a single oversized text item but concatenate a content array,
+//! so big responses are sent as ordered parts. Parts end at paragraph or line boundaries and
+//! never inside a fenced code block.
+
+/// Default size above which a tool response is split into parts
+pub const DEFAULT_MAX_CONTENT_ITEM_BYTES: usize = 32 * 1024;
+
+/// Split `text` into parts of at most `max_bytes` each (0 disables splitting)
+///
+/// Text that fits is returned as a single part without a marker. Otherwise every part
+/// starts with `[part i/n]`. A fenced code block longer than `max_bytes` stays whole in
+/// an oversized part rather than being cut.
+pub fn split_response(text: &str, max_bytes: usize) -> Vec<String> {
+    if max_bytes == 0 || text.len() <= max_bytes {
+        return vec![text.to_string()];
+    }
+
+    let mut parts: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for paragraph in paragraphs(text) {
+        if current.len() + paragraph.len() <= max_bytes {
+            current.push_str(&paragraph);
+            continue;
+        }
+        if !current.is_empty() {
+            parts.push(std::mem::take(&mut current));
+        }
+        if paragraph.len() <= max_bytes {
+            current = paragraph;
+            continue;
+        }
+        // Paragraph too big on its own: fall back to line (or whole code block) units
+        for unit in line_units(&paragraph) {
+            if current.len() + unit.len() <= max_bytes {
+                current.push_str(&unit);
+                continue;
+            }
+            if !current.is_empty() {
+                parts.push(std::mem::take(&mut current));
+            }
+            if unit.len() <= max_bytes || is_fence_line(&unit) {
+                current = unit;
+            } else {
+                // A single line longer than a part; cut it at character boundaries
+                let mut rest = unit.as_str();
+                while rest.len() > max_bytes {
+                    let cut = floor_char_boundary(rest, max_bytes);
+                    parts.push(rest[..cut].to_string());
+                    rest = &rest[cut..];
+                }
+                current = rest.to_string();
+            }
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    let total = parts.len();
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(i, part)| format!("[part {}/{total}]\n{part}", i + 1))
+        .collect()
+}
+
+fn is_fence_line(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// Runs of lines ending in a blank line outside a code fence (the blank line included)
+fn paragraphs(text: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut current = String::new();
+    let mut in_fence = false;
+    for line in text.split_inclusive('\n') {
+        if is_fence_line(line) {
+            in_fence = !in_fence;
+        }
+        current.push_str(line);
+        if !in_fence && line.trim().is_empty() {
+            paragraphs.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current);
+    }
+    paragraphs
+}
+
+/// Single lines, except that a fenced code block is one unit
+fn line_units(paragraph: &str) -> Vec<String> {
+    let mut units = Vec::new();
+    let mut fence = String::new();
+    let mut in_fence = false;
+    for line in paragraph.split_inclusive('\n') {
+        if is_fence_line(line) {
+            in_fence = !in_fence;
+            fence.push_str(line);
+            if !in_fence {
+                units.push(std::mem::take(&mut fence));
+            }
+        } else if in_fence {
+            fence.push_str(line);
+        } else {
+            units.push(line.to_string());
+        }
+    }
+    // An unclosed fence runs to the end of the text
+    if !fence.is_empty() {
+        units.push(fence);
+    }
+    units
+}
+
+/// Largest index `<= max` on a character boundary of `text`, but at least one character
+fn floor_char_boundary(text: &str, max: usize) -> usize {
+    let mut cut = max.min(text.len());
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    if cut == 0 {
+        cut = text.chars().next().map_or(text.len(), char::len_utf8);
+    }
+    cut
+}
@@ -0,0 +1,216 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// Product token matched against `User-agent` lines in robots.txt
+pub const ROBOTS_AGENT_TOKEN: &str = "rustdocs-mcp-server";
+
+/// How long a fetched robots.txt is reused before being fetched again
+const ROBOTS_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// User-Agent sent with every crawler request
+///
+/// Defaults to `rustdocs-mcp-server/{version} (+{repository})`; set
+/// `MCPDOCS_USER_AGENT` to override it.
+pub fn user_agent() -> String {
+    match std::env::var("MCPDOCS_USER_AGENT") {
+        Ok(agent) if !agent.trim().is_empty() => agent,
+        _ => format!(
+            "{ROBOTS_AGENT_TOKEN}/{} (+{})",
+            env!("CARGO_PKG_VERSION"),
+            env!("CARGO_PKG_REPOSITORY")
+        ),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// Parsed robots.txt rules that apply to this crawler
+#[derive(Debug, Clone, Default)]
+pub struct RobotsTxt {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsTxt {
+    /// Rules that allow everything (missing or unreachable robots.txt)
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Parse robots.txt, keeping the group for `agent_token` or else the `*` group
+    pub fn parse(body: &str, agent_token: &str) -> Self {
+        let agent_token = agent_token.to_lowercase();
+        let mut specific = RobotsTxt::default();
+        let mut wildcard = RobotsTxt::default();
+        let mut has_specific = false;
+
+        // Agents named by the current group, and whether rules have started
+        // (a User-agent line after rules opens a new group)
+        let mut group_agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            if key == "user-agent" {
+                if in_rules {
+                    group_agents.clear();
+                    in_rules = false;
+                }
+                group_agents.push(value.to_lowercase());
+                continue;
+            }
+
+            in_rules = true;
+            let matches_us = group_agents.iter().any(|agent| {
+                !agent.is_empty() && agent != "*" && agent_token.contains(agent.as_str())
+            });
+            let matches_any = group_agents.iter().any(|agent| agent == "*");
+            has_specific |= matches_us;
+
+            let targets: Vec<&mut RobotsTxt> = match (matches_us, matches_any) {
+                (true, true) => vec![&mut specific, &mut wildcard],
+                (true, false) => vec![&mut specific],
+                (false, true) => vec![&mut wildcard],
+                (false, false) => continue,
+            };
+            for target in targets {
+                match key.as_str() {
+                    "allow" | "disallow" if !value.is_empty() => target.rules.push(Rule {
+                        allow: key == "allow",
+                        pattern: value.to_string(),
+                    }),
+                    "crawl-delay" => {
+                        if let Ok(secs) = value.parse::<f64>() {
+                            if secs.is_finite() && secs >= 0.0 {
+                                target.crawl_delay = Some(Duration::from_secs_f64(secs));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if has_specific {
+            specific
+        } else {
+            wildcard
+        }
+    }
+
+    /// Whether `path` (including any query string) may be fetched
+    ///
+    /// The longest matching rule wins; `Allow` wins a tie.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let mut best: Option<(usize, bool)> = None;
+        for rule in &self.rules {
+            if pattern_matches(&rule.pattern, path) {
+                let len = rule.pattern.len();
+                best = match best {
+                    Some((best_len, best_allow))
+                        if best_len > len || (best_len == len && best_allow) =>
+                    {
+                        Some((best_len, best_allow))
+                    }
+                    _ => Some((len, rule.allow)),
+                };
+            }
+        }
+        best.is_none_or(|(_, allow)| allow)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// Match a robots.txt path pattern supporting `*` wildcards and a trailing `$` anchor
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+/// Parsed robots.txt per origin, with the time it was fetched
+type RobotsCache = Mutex<HashMap<String, (Instant, Arc<RobotsTxt>)>>;
+
+fn cache() -> &'static RobotsCache {
+    static CACHE: OnceLock<RobotsCache> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Fetch and parse `{origin}/robots.txt`, reusing a cached copy for an hour
+///
+/// A missing robots.txt allows everything; so does one that cannot be fetched,
+/// with a warning, so a docs host outage doesn't look like a disallow.
+pub async fn fetch_robots(client: &reqwest::Client, origin: &str) -> Arc<RobotsTxt> {
+    if let Some((fetched_at, robots)) = cache()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(origin)
+    {
+        if fetched_at.elapsed() < ROBOTS_CACHE_TTL {
+            return Arc::clone(robots);
+        }
+    }
+
+    let url = format!("{origin}/robots.txt");
+    let robots = match client.get(&url).send().await {
+        Ok(response) if response.status().is_success() => match response.text().await {
+            Ok(body) => RobotsTxt::parse(&body, ROBOTS_AGENT_TOKEN),
+            Err(e) => {
+                eprintln!("⚠️  Failed to read {url}: {e} - assuming no restrictions");
+                RobotsTxt::allow_all()
+            }
+        },
+        Ok(response) if response.status().is_client_error() => RobotsTxt::allow_all(),
+        Ok(response) => {
+            eprintln!(
+                "⚠️  {url} returned HTTP {} - assuming no restrictions",
+                response.status()
+            );
+            RobotsTxt::allow_all()
+        }
+        Err(e) => {
+            eprintln!("⚠️  Failed to fetch {url}: {e} - assuming no restrictions");
+            RobotsTxt::allow_all()
+        }
+    };
+
+    let robots = Arc::new(robots);
+    cache()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(origin.to_string(), (Instant::now(), Arc::clone(&robots)));
+    robots
+}
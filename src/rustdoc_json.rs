@@ -0,0 +1,274 @@
+use crate::doc_loader::{self, CrawlDiagnostics, DocLoaderError, Document, LoadResult};
+use serde_json::Value;
+use std::{collections::HashMap, io::Read, ops::RangeInclusive};
+
+/// rustdoc JSON `format_version`s whose item layout this loader understands
+pub const SUPPORTED_FORMAT_VERSIONS: RangeInclusive<u64> = 30..=60;
+
+/// gzip magic bytes, for artifacts published as `.json.gz`
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Check that a configured rustdoc JSON source is an http(s) URL
+pub fn validate_url(url: &str) -> Result<(), DocLoaderError> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+        Ok(parsed) => Err(DocLoaderError::Parsing(format!(
+            "Unsupported rustdoc JSON URL scheme '{}': use http or https",
+            parsed.scheme()
+        ))),
+        Err(e) => Err(DocLoaderError::Parsing(format!(
+            "Invalid rustdoc JSON URL '{url}': {e}"
+        ))),
+    }
+}
+
+/// Download a rustdoc JSON file (optionally gzipped) and turn it into documents
+///
+/// Nothing is scraped from docs.rs and cargo is never invoked, so this works for
+/// private crates whose CI publishes `cargo rustdoc --output-format json` output.
+pub async fn load_documents_from_url(
+    crate_name: &str,
+    url: &str,
+) -> Result<LoadResult, DocLoaderError> {
+    eprintln!("Fetching rustdoc JSON for crate {crate_name} from {url}");
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(300))
+        .user_agent(crate::robots::user_agent())
+        .build()
+        .map_err(|e| DocLoaderError::Network(e.to_string()))?;
+    let response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        return Err(DocLoaderError::Network(format!(
+            "HTTP {} fetching {url}",
+            response.status()
+        )));
+    }
+    let bytes = response.bytes().await?;
+
+    let mut result = parse_rustdoc_json(&bytes, crate_name)?;
+    result.diagnostics.pages_fetched = 1;
+    result.diagnostics.bytes_downloaded = bytes.len() as u64;
+    eprintln!(
+        "Loaded {} documents from rustdoc JSON for {crate_name}",
+        result.documents.len()
+    );
+    Ok(result)
+}
+
+/// Parse rustdoc JSON (plain or gzipped) into one document per documented item
+///
+/// Document paths mirror docs.rs URLs (`crate/latest/crate/mod/struct.Name.html#method.x`)
+/// so search results, related items and source links behave as for crawled crates.
+pub fn parse_rustdoc_json(bytes: &[u8], crate_name: &str) -> Result<LoadResult, DocLoaderError> {
+    let json: Value = if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(bytes)
+            .read_to_end(&mut decompressed)
+            .map_err(|e| DocLoaderError::Parsing(format!("Invalid gzip data: {e}")))?;
+        serde_json::from_slice(&decompressed)
+    } else {
+        serde_json::from_slice(bytes)
+    }
+    .map_err(|e| DocLoaderError::Parsing(format!("Invalid rustdoc JSON: {e}")))?;
+
+    let format_version = json
+        .get("format_version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| {
+            DocLoaderError::Parsing("Not rustdoc JSON: missing format_version".to_string())
+        })?;
+    if !SUPPORTED_FORMAT_VERSIONS.contains(&format_version) {
+        return Err(DocLoaderError::UnsupportedFormat(format!(
+            "rustdoc JSON format_version {format_version} is not supported (supported: {}-{})",
+            SUPPORTED_FORMAT_VERSIONS.start(),
+            SUPPORTED_FORMAT_VERSIONS.end()
+        )));
+    }
+
+    let empty = serde_json::Map::new();
+    let index = json
+        .get("index")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+    let paths = json
+        .get("paths")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+
+    // Items with their own page: id -> (doc path, kind, full item path)
+    let mut pages: HashMap<String, (String, String, String)> = HashMap::new();
+    for (id, summary) in paths {
+        if summary.get("crate_id").and_then(Value::as_u64) != Some(0) {
+            continue;
+        }
+        let kind = summary
+            .get("kind")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let segments: Vec<&str> = summary
+            .get("path")
+            .and_then(Value::as_array)
+            .map(|p| p.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        if let Some(page) = page_path(crate_name, kind, &segments) {
+            pages.insert(id.clone(), (page, kind.to_string(), segments.join("::")));
+        }
+    }
+
+    // Items documented on their parent's page: id -> (doc path with anchor, kind, full path)
+    let mut anchored: HashMap<String, (String, String, String)> = HashMap::new();
+    for (id, (page, _, parent_path)) in &pages {
+        let Some(item) = index.get(id) else {
+            continue;
+        };
+        for (child_id, anchor_kind) in child_items(index, item) {
+            let Some(name) = index
+                .get(&child_id)
+                .and_then(|child| child.get("name"))
+                .and_then(Value::as_str)
+            else {
+                continue;
+            };
+            anchored.entry(child_id).or_insert_with(|| {
+                (
+                    format!("{page}#{anchor_kind}.{name}"),
+                    anchor_kind.to_string(),
+                    format!("{parent_path}::{name}"),
+                )
+            });
+        }
+    }
+
+    let mut documents: Vec<Document> = index
+        .iter()
+        .filter_map(|(id, item)| {
+            let docs = item.get("docs").and_then(Value::as_str)?.trim();
+            if docs.is_empty() {
+                return None;
+            }
+            let (path, kind, full_path) = pages.get(id).or_else(|| anchored.get(id))?;
+            Some(Document {
+                path: path.clone(),
+                content: doc_loader::to_nfc(&format!("{kind} {full_path}\n\n{docs}")),
+                section: None,
+            })
+        })
+        .collect();
+    documents.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(LoadResult {
+        documents,
+        version: json
+            .get("crate_version")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        reexport_pages: HashMap::new(),
+        diagnostics: CrawlDiagnostics::default(),
+    })
+}
+
+/// docs.rs page for an item, from its `paths` kind and full path
+fn page_path(crate_name: &str, kind: &str, segments: &[&str]) -> Option<String> {
+    let (name, parents) = segments.split_last()?;
+    let dir = if parents.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", parents.join("/"))
+    };
+    if kind == "module" {
+        return Some(format!("{crate_name}/latest/{dir}{name}/index.html"));
+    }
+    let prefix = match kind {
+        "struct" => "struct",
+        "enum" => "enum",
+        "union" => "union",
+        "trait" => "trait",
+        "trait_alias" => "traitalias",
+        "function" => "fn",
+        "macro" => "macro",
+        "constant" => "constant",
+        "static" => "static",
+        "type_alias" | "typedef" => "type",
+        "proc_attribute" => "attr",
+        "proc_derive" => "derive",
+        _ => return None,
+    };
+    Some(format!("{crate_name}/latest/{dir}{prefix}.{name}.html"))
+}
+
+/// Ids of items documented on `item`'s page, with the rustdoc anchor kind for each
+fn child_items(
+    index: &serde_json::Map<String, Value>,
+    item: &Value,
+) -> Vec<(String, &'static str)> {
+    let Some((kind, inner)) = item
+        .get("inner")
+        .and_then(Value::as_object)
+        .and_then(|inner| inner.iter().next())
+    else {
+        return Vec::new();
+    };
+
+    let mut children = Vec::new();
+    match kind.as_str() {
+        "struct" | "union" => {
+            let fields = inner
+                .pointer("/kind/plain/fields")
+                .or_else(|| inner.get("fields"));
+            children.extend(ids(fields).map(|id| (id, "structfield")));
+        }
+        "enum" => children.extend(ids(inner.get("variants")).map(|id| (id, "variant"))),
+        "trait" => {
+            for id in ids(inner.get("items")) {
+                let anchor = index.get(&id).map_or("method", assoc_anchor);
+                children.push((id, anchor));
+            }
+        }
+        _ => {}
+    }
+
+    // Inherent and trait impl members are listed under the type's impls
+    for impl_id in ids(inner.get("impls")) {
+        let impl_items = index
+            .get(&impl_id)
+            .and_then(|imp| imp.pointer("/inner/impl/items"));
+        for id in ids(impl_items) {
+            let anchor = index.get(&id).map_or("method", assoc_anchor);
+            children.push((id, anchor));
+        }
+    }
+    children
+}
+
+/// Anchor kind for an associated item (`method`, `tymethod`, ...)
+fn assoc_anchor(item: &Value) -> &'static str {
+    match item
+        .get("inner")
+        .and_then(Value::as_object)
+        .and_then(|inner| inner.iter().next())
+    {
+        Some((kind, inner))
+            if kind == "function"
+                && inner.get("has_body").and_then(Value::as_bool) == Some(false) =>
+        {
+            "tymethod"
+        }
+        Some((kind, _)) if kind == "assoc_const" => "associatedconstant",
+        Some((kind, _)) if kind == "assoc_type" => "associatedtype",
+        _ => "method",
+    }
+}
+
+/// Item ids from a JSON array; ids are strings in older formats and integers in newer ones
+fn ids(value: Option<&Value>) -> impl Iterator<Item = String> + '_ {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|id| match id {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        })
+}
@@ -0,0 +1,745 @@
+use crate::{
+    database::{Database, ScoredDoc},
+    embeddings::cosine_similarity,
+    error::ServerError,
+};
+use ndarray::ArrayView1;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Common English words that carry no signal when explaining a match
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "how", "what", "with", "can", "does", "this", "that", "from", "into",
+    "are", "use", "using", "when", "which", "why", "you", "your", "its", "has", "have", "not",
+    "but", "all", "any", "there", "their", "about", "should", "would", "could", "get", "set",
+];
+
+/// Split a question into lowercase search terms, dropping short words and stop words
+pub fn query_terms(question: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    question
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map(str::to_lowercase)
+        .filter(|term| term.chars().count() >= 3 && !STOP_WORDS.contains(&term.as_str()))
+        .filter(|term| seen.insert(term.clone()))
+        .collect()
+}
+
+/// Query terms that also appear (case-insensitively) in the document content
+pub fn overlapping_terms(question: &str, content: &str) -> Vec<String> {
+    let content_terms: HashSet<String> = content
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map(str::to_lowercase)
+        .collect();
+
+    query_terms(question)
+        .into_iter()
+        .filter(|term| content_terms.contains(term))
+        .collect()
+}
+
+/// Words that join the parts of a compound question
+const CONJUNCTIONS: &[&str] = &["and", "then", "also", "plus"];
+
+/// A sub-query needs this many search terms to stand on its own; shorter parts
+/// ("serialize and deserialize") stay attached to their neighbour
+const MIN_SUB_QUERY_TERMS: usize = 2;
+
+/// Upper bound on sub-queries per question, so expansion costs at most this many searches
+pub const MAX_SUB_QUERIES: usize = 4;
+
+/// Split a compound question into independently searchable parts
+///
+/// Parts are separated at sentence ends (`?`, `.`, `;`) and at conjunctions. A part
+/// with fewer than two search terms is merged back into its neighbour, and anything
+/// past [`MAX_SUB_QUERIES`] is folded into the last part. A question with a single
+/// part comes back unchanged.
+pub fn split_question(question: &str) -> Vec<String> {
+    let words: Vec<&str> = question.split_whitespace().collect();
+    let mut parts: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+    for (i, word) in words.iter().enumerate() {
+        let bare = word
+            .trim_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase();
+        if CONJUNCTIONS.contains(&bare.as_str()) {
+            if i > start {
+                parts.push((start, i));
+            }
+            start = i + 1;
+        } else if word.ends_with(['?', '.', ';']) {
+            parts.push((start, i + 1));
+            start = i + 1;
+        }
+    }
+    if start < words.len() {
+        parts.push((start, words.len()));
+    }
+
+    let text = |(start, end): (usize, usize)| {
+        words[start..end]
+            .join(" ")
+            .trim_end_matches([',', ';'])
+            .to_string()
+    };
+    let standalone = |part: (usize, usize)| query_terms(&text(part)).len() >= MIN_SUB_QUERY_TERMS;
+
+    // Merging spans the original words, conjunction included, so the text stays as asked
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for part in parts {
+        match merged.last_mut() {
+            Some(last) if !standalone(*last) || !standalone(part) => last.1 = part.1,
+            _ => merged.push(part),
+        }
+    }
+    while merged.len() > MAX_SUB_QUERIES {
+        let (_, end) = merged.pop().unwrap_or_default();
+        if let Some(last) = merged.last_mut() {
+            last.1 = end;
+        }
+    }
+
+    if merged.len() <= 1 {
+        return vec![question.trim().to_string()];
+    }
+    merged.into_iter().map(text).collect()
+}
+
+/// Merge the hits of several searches into one ranked list without duplicates
+///
+/// Lists are interleaved rank by rank so every sub-query's best hits make the cut
+/// instead of one strong sub-query filling all the slots. A document found by more
+/// than one search keeps its first position and its best score.
+pub fn merge_results(result_sets: Vec<Vec<ScoredDoc>>, limit: usize) -> Vec<ScoredDoc> {
+    let mut merged: Vec<ScoredDoc> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut iters: Vec<_> = result_sets.into_iter().map(Vec::into_iter).collect();
+
+    loop {
+        let mut exhausted = true;
+        for iter in &mut iters {
+            let Some(doc) = iter.next() else {
+                continue;
+            };
+            exhausted = false;
+            match positions.get(&doc.doc_path) {
+                Some(&i) => {
+                    if doc.distance < merged[i].distance {
+                        merged[i].distance = doc.distance;
+                        merged[i].similarity = doc.similarity;
+                    }
+                }
+                None => {
+                    positions.insert(doc.doc_path.clone(), merged.len());
+                    merged.push(doc);
+                }
+            }
+        }
+        if exhausted {
+            break;
+        }
+    }
+
+    merged.truncate(limit);
+    merged
+}
+
+/// Identity of a document's text for [`dedup_by_content`]: case and whitespace are
+/// ignored, so the same docblock re-exported under another path collapses
+fn content_key(content: &str) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for word in content.split_whitespace() {
+        word.to_lowercase().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Drop results whose content repeats a better-ranked result, keeping the order
+///
+/// Returns the remaining results and how many were dropped.
+#[allow(dead_code)] // Used by the HTTP server
+pub fn dedup_by_content(results: Vec<ScoredDoc>) -> (Vec<ScoredDoc>, usize) {
+    let mut seen = HashSet::new();
+    let total = results.len();
+    let kept: Vec<ScoredDoc> = results
+        .into_iter()
+        .filter(|doc| seen.insert(content_key(&doc.content)))
+        .collect();
+    let dropped = total - kept.len();
+    (kept, dropped)
+}
+
+/// Rerank results so that ones close to the crate's centroid drop behind specific matches
+///
+/// Boilerplate shared by many pages (trait impl blocks, "Auto Trait Implementations",
+/// re-export stubs) sits near the average of all of a crate's embeddings and matches
+/// almost any question. Each result is ordered by `similarity - weight * commonness`,
+/// where commonness is its similarity to the centroid from `commonness`; results without
+/// an entry are not penalized. Displayed similarities are left unchanged.
+#[allow(dead_code)] // Used by the HTTP server
+pub fn penalize_generic(
+    results: Vec<ScoredDoc>,
+    commonness: &HashMap<String, f32>,
+    weight: f32,
+) -> Vec<ScoredDoc> {
+    let mut scored: Vec<(f32, ScoredDoc)> = results
+        .into_iter()
+        .map(|doc| {
+            let penalty = weight * commonness.get(&doc.doc_path).copied().unwrap_or(0.0);
+            (doc.similarity - penalty, doc)
+        })
+        .collect();
+    // Stable, so equally scored results keep their search order
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().map(|(_, doc)| doc).collect()
+}
+
+/// Largest `FEEDBACK_WEIGHT`: feedback may move a result past neighbours at most this
+/// much more similar, never past clearly better matches
+pub const MAX_FEEDBACK_WEIGHT: f32 = 0.1;
+
+/// Votes added to every document's count before its prior is taken, so a single vote
+/// only nudges it
+#[allow(dead_code)] // Used by the HTTP server
+const FEEDBACK_PRIOR_SMOOTHING: f32 = 5.0;
+
+/// A document's feedback prior in `(-1, 1)` from its useful and not-useful votes
+///
+/// `(useful - not_useful) / (useful + not_useful + 5)`: 0 without feedback, approaching
+/// ±1 only as one-sided votes pile up.
+#[allow(dead_code)] // Used by the HTTP server
+pub fn feedback_prior(useful: i64, not_useful: i64) -> f32 {
+    let (useful, not_useful) = (useful.max(0) as f32, not_useful.max(0) as f32);
+    (useful - not_useful) / (useful + not_useful + FEEDBACK_PRIOR_SMOOTHING)
+}
+
+/// Rerank results by `similarity + weight * prior`, promoting documents that helped before
+///
+/// `priors` maps base doc paths (without ` [chunk i/n]`) to [`feedback_prior`]s; documents
+/// without feedback score their plain similarity. `weight` is clamped to
+/// [`MAX_FEEDBACK_WEIGHT`] and 0 leaves the order alone. Displayed similarities are left
+/// unchanged.
+#[allow(dead_code)] // Used by the HTTP server
+pub fn apply_feedback(
+    results: Vec<ScoredDoc>,
+    priors: &HashMap<String, f32>,
+    weight: f32,
+) -> Vec<ScoredDoc> {
+    let weight = weight.clamp(0.0, MAX_FEEDBACK_WEIGHT);
+    if weight == 0.0 || priors.is_empty() {
+        return results;
+    }
+    let mut scored: Vec<(f32, ScoredDoc)> = results
+        .into_iter()
+        .map(|doc| {
+            let prior = priors
+                .get(base_doc_path(&doc.doc_path))
+                .copied()
+                .unwrap_or(0.0);
+            (doc.similarity + weight * prior.clamp(-1.0, 1.0), doc)
+        })
+        .collect();
+    // Stable, so equally scored results keep their search order
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().map(|(_, doc)| doc).collect()
+}
+
+/// A fresh ID for one `query_rust_docs` answer, which `submit_feedback` refers back to
+#[allow(dead_code)] // Used by the HTTP server
+pub fn new_query_id() -> String {
+    format!("q-{:08x}", rand::random::<u32>())
+}
+
+/// `response` with its query ID appended, for clients that report results' usefulness
+#[allow(dead_code)] // Used by the HTTP server
+pub fn with_query_id(response: &str, query_id: &str) -> String {
+    format!("{response}\n\nQuery ID: {query_id} (report which results helped with submit_feedback)")
+}
+
+/// Drop results tagged with a language outside `allowed` (ISO 639-3 codes)
+///
+/// `languages` maps doc paths to their tagged language; untagged results are kept, since
+/// their language couldn't be detected. Returns the kept results and how many were dropped.
+#[allow(dead_code)] // Used by the HTTP server
+pub fn filter_languages(
+    results: Vec<ScoredDoc>,
+    languages: &HashMap<String, String>,
+    allowed: &[&str],
+) -> (Vec<ScoredDoc>, usize) {
+    let total = results.len();
+    let kept: Vec<ScoredDoc> = results
+        .into_iter()
+        .filter(|doc| {
+            languages
+                .get(&doc.doc_path)
+                .is_none_or(|lang| allowed.contains(&lang.as_str()))
+        })
+        .collect();
+    let dropped = total - kept.len();
+    (kept, dropped)
+}
+
+/// Results `query_rust_docs` shows when neither the caller nor the crate config sets a limit
+#[allow(dead_code)] // Used by the HTTP server
+pub const DEFAULT_RESULT_LIMIT: usize = 5;
+
+/// Upper bound on results per `query_rust_docs` call
+#[allow(dead_code)] // Used by the HTTP server
+pub const MAX_RESULT_LIMIT: usize = 20;
+
+/// Search parameters for one `query_rust_docs` call; `None` means "not specified"
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchParams {
+    pub limit: Option<usize>,
+    pub min_similarity: Option<f32>,
+    pub path_prefix: Option<String>,
+}
+
+#[allow(dead_code)] // Used by the HTTP server
+impl SearchParams {
+    /// Fill the fields left unset from `defaults` (a crate's configured defaults)
+    ///
+    /// Explicit values always win, so a caller can pass `path_prefix: ""` or
+    /// `min_similarity: 0` to turn a configured default off.
+    pub fn or(self, defaults: SearchParams) -> SearchParams {
+        SearchParams {
+            limit: self.limit.or(defaults.limit),
+            min_similarity: self.min_similarity.or(defaults.min_similarity),
+            path_prefix: self.path_prefix.or(defaults.path_prefix),
+        }
+    }
+
+    /// Number of results to show, within `1..=MAX_RESULT_LIMIT`
+    pub fn result_limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_RESULT_LIMIT)
+            .clamp(1, MAX_RESULT_LIMIT)
+    }
+
+    /// Candidates to fetch per search for [`Self::result_limit`] results
+    ///
+    /// At least 10, so sub-query merging and similarity filtering have room; twice the
+    /// limit when deduplicating so dropped duplicates are backfilled, and three times when
+    /// results are `reordered` (generic penalty) or filtered by language.
+    pub fn fetch_limit(&self, dedup: bool, reordered: bool) -> usize {
+        let result_limit = self.result_limit();
+        let fetch_limit = if dedup {
+            (result_limit * 2).max(10)
+        } else {
+            result_limit.max(10)
+        };
+        if reordered {
+            fetch_limit.max(result_limit * 3)
+        } else {
+            fetch_limit
+        }
+    }
+
+    /// The path prefix to filter on, if any (an empty prefix filters nothing)
+    pub fn path_prefix(&self) -> Option<&str> {
+        self.path_prefix
+            .as_deref()
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+    }
+
+    /// Drop results below the minimum similarity, if one is set
+    pub fn filter(&self, docs: Vec<ScoredDoc>) -> Vec<ScoredDoc> {
+        match self.min_similarity {
+            Some(min) => docs.into_iter().filter(|d| d.similarity >= min).collect(),
+            None => docs,
+        }
+    }
+}
+
+/// Drop results shorter than `min_tokens`, such as one-line stubs and type aliases
+///
+/// A `min_tokens` of 0 keeps everything.
+#[allow(dead_code)] // Used by the HTTP server
+pub fn drop_short(docs: Vec<ScoredDoc>, min_tokens: u32) -> Vec<ScoredDoc> {
+    if min_tokens == 0 {
+        return docs;
+    }
+    docs.into_iter()
+        .filter(|d| d.token_count >= min_tokens as i32)
+        .collect()
+}
+
+/// The leading results whose stored chunks fit in `max_tokens` together
+///
+/// The first result is always kept, even when it alone exceeds the budget.
+pub fn within_token_budget(docs: &[ScoredDoc], max_tokens: usize) -> &[ScoredDoc] {
+    let mut used = 0;
+    let fits = docs
+        .iter()
+        .take_while(|doc| {
+            used += doc.token_count.max(0) as usize;
+            used <= max_tokens
+        })
+        .count();
+    &docs[..fits.max(1).min(docs.len())]
+}
+
+/// The text embedded for a question: the crate's retrieval hint, if any, then the question
+///
+/// Only the embedded text changes; term matching in diagnostics still uses the question.
+#[allow(dead_code)] // Used by the HTTP server
+pub fn apply_query_hint(question: &str, hint: Option<&str>) -> String {
+    match hint.map(str::trim).filter(|h| !h.is_empty()) {
+        Some(hint) => format!("{hint}: {question}"),
+        None => question.to_string(),
+    }
+}
+
+/// Crates `smart_query` searches when the caller doesn't say
+#[allow(dead_code)] // Used by the HTTP server
+pub const DEFAULT_SMART_QUERY_CRATES: usize = 3;
+
+/// Upper bound on the crates one `smart_query` call searches
+#[allow(dead_code)] // Used by the HTTP server
+pub const MAX_SMART_QUERY_CRATES: usize = 10;
+
+/// A crate chosen by [`rank_crates`], with its centroid's similarity to the question
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrateCandidate {
+    pub crate_name: String,
+    pub similarity: f32,
+}
+
+/// The `top` crates whose centroids are most similar to `question`
+///
+/// A crate's centroid is the mean of its embeddings, so it stands for what the crate
+/// documents as a whole. Centroids of another dimension than the question can't be
+/// compared and are skipped; equally similar crates are ordered by name.
+#[allow(dead_code)] // Used by the HTTP server
+pub fn rank_crates(
+    question: &[f32],
+    centroids: &HashMap<String, Arc<Vec<f32>>>,
+    top: usize,
+) -> Vec<CrateCandidate> {
+    let question = ArrayView1::from(question);
+    let mut ranked: Vec<CrateCandidate> = centroids
+        .iter()
+        .filter(|(_, centroid)| centroid.len() == question.len())
+        .map(|(crate_name, centroid)| CrateCandidate {
+            crate_name: crate_name.clone(),
+            similarity: cosine_similarity(question, ArrayView1::from(centroid.as_slice())),
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.crate_name.cmp(&b.crate_name))
+    });
+    ranked.truncate(top);
+    ranked
+}
+
+/// Combine per-crate results into one list ranked by similarity, keeping each result's crate
+///
+/// `result_sets` come in crate rank order, which breaks ties between equally similar results.
+/// With `max_per_crate`, a crate's results past its quota give way to the next best of
+/// other crates, so one large crate can't take every slot.
+#[allow(dead_code)] // Used by the HTTP server
+pub fn merge_crate_results(
+    result_sets: Vec<(String, Vec<ScoredDoc>)>,
+    limit: usize,
+    max_per_crate: Option<usize>,
+) -> Vec<(String, ScoredDoc)> {
+    let mut merged: Vec<(String, ScoredDoc)> = result_sets
+        .into_iter()
+        .flat_map(|(crate_name, docs)| docs.into_iter().map(move |doc| (crate_name.clone(), doc)))
+        .collect();
+    // Stable, so crate rank decides between equal similarities
+    merged.sort_by(|a, b| b.1.similarity.total_cmp(&a.1.similarity));
+    if let Some(quota) = max_per_crate {
+        let mut taken: HashMap<String, usize> = HashMap::new();
+        merged.retain(|(crate_name, _)| {
+            let count = taken.entry(crate_name.clone()).or_default();
+            *count += 1;
+            *count <= quota
+        });
+    }
+    merged.truncate(limit);
+    merged
+}
+
+/// Results shown per crate by `compare_crates` when the caller doesn't say
+#[allow(dead_code)] // Used by the HTTP server
+pub const DEFAULT_COMPARE_RESULTS: usize = 2;
+
+/// Upper bound on results per crate in one `compare_crates` call
+#[allow(dead_code)] // Used by the HTTP server
+pub const MAX_COMPARE_RESULTS: usize = 5;
+
+/// Upper bound on the crates one `compare_crates` call compares
+#[allow(dead_code)] // Used by the HTTP server
+pub const MAX_COMPARE_CRATES: usize = 5;
+
+/// Side-by-side `compare_crates` answer: one section per crate, in the order asked
+///
+/// Unlike [`merge_crate_results`], results never move between crates, so a crate whose
+/// docs match less well still shows its best answer, and one with no match says so.
+#[allow(dead_code)] // Used by the HTTP server
+pub fn format_comparison(question: &str, result_sets: &[(String, Vec<ScoredDoc>)]) -> String {
+    let names: Vec<&str> = result_sets.iter().map(|(name, _)| name.as_str()).collect();
+    let mut response = format!("Comparing {} for: {question}", names.join(" vs "));
+    for (crate_name, docs) in result_sets {
+        response.push_str(&format!("\n\n## {crate_name}\n"));
+        if docs.is_empty() {
+            response.push_str("No relevant documentation found");
+            continue;
+        }
+        let formatted: Vec<String> = docs
+            .iter()
+            .enumerate()
+            .map(|(i, doc)| {
+                format!(
+                    "{}. {} (similarity: {:.3})\nSource: {}",
+                    i + 1,
+                    doc.content.trim(),
+                    doc.similarity,
+                    source_url(&doc.doc_path)
+                )
+            })
+            .collect();
+        response.push_str(&formatted.join("\n\n"));
+    }
+    response
+}
+
+/// `query_rust_docs`'s `best_only` answer: the top result's text, then its source and
+/// similarity, with no header or numbering
+#[allow(dead_code)] // Used by the HTTP server
+pub fn format_best_result(doc: &ScoredDoc) -> String {
+    format!(
+        "{}\n\nSource: {} (similarity: {:.3})",
+        doc.content.trim(),
+        source_url(&doc.doc_path),
+        doc.similarity
+    )
+}
+
+/// `best_only`'s answer when no result is left, naming the threshold that removed them
+#[allow(dead_code)] // Used by the HTTP server
+pub fn no_best_result(crate_name: &str, min_similarity: Option<f32>) -> String {
+    match min_similarity {
+        Some(threshold) => format!(
+            "No relevant result in crate '{crate_name}' above the similarity threshold of {threshold}"
+        ),
+        None => format!("No relevant result in crate '{crate_name}'"),
+    }
+}
+
+/// A navigation hint pointing at an item near a search hit
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelatedItem {
+    /// Readable item name, e.g. `method shutdown_timeout` or `struct Runtime`
+    pub label: String,
+    pub doc_path: String,
+}
+
+/// Strip the ` [chunk i/n]` suffix added when a long document was split for embedding
+pub fn base_doc_path(doc_path: &str) -> &str {
+    doc_path
+        .split_once(" [chunk ")
+        .map_or(doc_path, |(path, _)| path)
+}
+
+/// `(index, count)` from a ` [chunk i/n]` suffix, or `None` for an unsplit document
+pub fn chunk_position(doc_path: &str) -> Option<(usize, usize)> {
+    let (_, suffix) = doc_path.split_once(" [chunk ")?;
+    let (index, count) = suffix.strip_suffix(']')?.split_once('/')?;
+    Some((index.parse().ok()?, count.parse().ok()?))
+}
+
+/// Overlaps shorter than this are treated as coincidence rather than repeated text
+const MIN_CHUNK_OVERLAP_CHARS: usize = 20;
+
+/// Longest overlap searched for between neighbouring chunks (well above 200 tokens)
+const MAX_CHUNK_OVERLAP_CHARS: usize = 4000;
+
+/// Stitch a split document's `(doc_path, content)` chunks back into the full page
+///
+/// Chunks are ordered by their ` [chunk i/n]` index and text repeated at the start
+/// of a chunk from the end of the previous one is dropped. A single unsplit row is
+/// returned as-is.
+pub fn reassemble_chunks(mut chunks: Vec<(String, String)>) -> String {
+    chunks.sort_by_key(|(path, _)| chunk_position(path).map_or(0, |(index, _)| index));
+
+    let mut page = String::new();
+    for (_, content) in chunks {
+        if page.is_empty() {
+            page = content;
+            continue;
+        }
+        let overlap = (MIN_CHUNK_OVERLAP_CHARS..=MAX_CHUNK_OVERLAP_CHARS.min(content.len()))
+            .rev()
+            .filter(|&len| content.is_char_boundary(len))
+            .find(|&len| page.ends_with(&content[..len]));
+        match overlap {
+            Some(len) => page.push_str(&content[len..]),
+            None => {
+                page.push(' ');
+                page.push_str(&content);
+            }
+        }
+    }
+    page
+}
+
+/// Path prefix shared by a hit's siblings: other anchors on the same page for
+/// section hits (methods on a struct), otherwise the enclosing module directory.
+pub fn related_prefix(doc_path: &str) -> Option<String> {
+    let path = base_doc_path(doc_path);
+    if let Some((page, _anchor)) = path.split_once('#') {
+        return Some(format!("{page}#"));
+    }
+    path.rfind('/').map(|i| path[..=i].to_string())
+}
+
+/// Turn candidate paths under `prefix` into deduplicated related items, skipping the hit itself
+pub fn related_items(
+    prefix: &str,
+    hit_path: &str,
+    candidates: &[String],
+    max_items: usize,
+) -> Vec<RelatedItem> {
+    let hit = base_doc_path(hit_path);
+    let hit_page = hit.split('#').next().unwrap_or(hit);
+    let mut seen = HashSet::new();
+    let mut items = Vec::new();
+
+    for candidate in candidates {
+        let Some(rest) = base_doc_path(candidate).strip_prefix(prefix) else {
+            continue;
+        };
+        // Within a module, group a page's anchors under the page itself
+        let name = if prefix.ends_with('#') {
+            rest
+        } else {
+            rest.split('#').next().unwrap_or(rest)
+        };
+        let doc_path = format!("{prefix}{name}");
+        if name.is_empty()
+            || doc_path == hit
+            || doc_path == hit_page
+            || !seen.insert(doc_path.clone())
+        {
+            continue;
+        }
+
+        items.push(RelatedItem {
+            label: item_label(name),
+            doc_path,
+        });
+        if items.len() >= max_items {
+            break;
+        }
+    }
+
+    items
+}
+
+/// `struct.Runtime.html` -> `struct Runtime`, `method.shutdown` -> `method shutdown`,
+/// `sync/index.html` -> `mod sync`, `index.html` -> `module overview`
+fn item_label(name: &str) -> String {
+    if name == "index.html" {
+        return "module overview".to_string();
+    }
+    if let Some(module) = name.strip_suffix("/index.html") {
+        return format!("mod {module}");
+    }
+    let name = name.strip_suffix(".html").unwrap_or(name);
+    match name.split_once('.') {
+        Some((kind, item)) => format!("{kind} {item}"),
+        None => name.to_string(),
+    }
+}
+
+/// Candidate rows fetched per suggestion; anchors and chunks collapse into fewer items
+const RELATED_CANDIDATE_LIMIT: i64 = 500;
+
+/// Items sharing the hit's parent path, found with a prefix query rather than embeddings
+pub async fn suggest_related(
+    database: &Database,
+    crate_name: &str,
+    hit_path: &str,
+    max_items: usize,
+) -> Result<Vec<RelatedItem>, ServerError> {
+    let Some(prefix) = related_prefix(hit_path) else {
+        return Ok(Vec::new());
+    };
+    let candidates = database
+        .get_doc_paths_with_prefix(crate_name, &prefix, RELATED_CANDIDATE_LIMIT)
+        .await?;
+    Ok(related_items(&prefix, hit_path, &candidates, max_items))
+}
+
+/// Render related items as a short navigation list appended to query results
+pub fn format_related(items: &[RelatedItem]) -> String {
+    let lines: Vec<String> = items
+        .iter()
+        .map(|item| format!("- {} ({})", item.label, source_url(&item.doc_path)))
+        .collect();
+    format!("Related items:\n{}", lines.join("\n"))
+}
+
+/// docs.rs URL for a stored doc path, keeping any `#anchor` so links land on the section
+///
+/// Chunk suffixes added during embedding (` [chunk 1/3]`) are dropped.
+pub fn source_url(doc_path: &str) -> String {
+    let path = base_doc_path(doc_path);
+    if path.starts_with("http://") || path.starts_with("https://") {
+        path.to_string()
+    } else {
+        format!("https://docs.rs/{path}")
+    }
+}
+
+/// Render the per-result diagnostics shown when a query is run with `debug: true`
+pub fn format_debug_diagnostics(question: &str, doc: &ScoredDoc) -> String {
+    let terms = overlapping_terms(question, &doc.content);
+    let matched = if terms.is_empty() {
+        "none".to_string()
+    } else {
+        terms.join(", ")
+    };
+
+    format!(
+        "[debug] path: {}, distance: {:.4}, similarity: {:.4}, matched terms: {matched}",
+        doc.doc_path, doc.distance, doc.similarity
+    )
+}
+
+/// Lines around the first line of `content` containing `identifier`, for `find_symbol`
+///
+/// Matching is case-insensitive unless `case_sensitive`. `None` when no line contains
+/// the identifier (e.g. the match was on the doc path only).
+#[allow(dead_code)] // Used by the HTTP server
+pub fn symbol_context(
+    content: &str,
+    identifier: &str,
+    case_sensitive: bool,
+    radius: usize,
+) -> Option<String> {
+    let needle = if case_sensitive {
+        identifier.to_string()
+    } else {
+        identifier.to_lowercase()
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let hit = lines.iter().position(|line| {
+        if case_sensitive {
+            line.contains(&needle)
+        } else {
+            line.to_lowercase().contains(&needle)
+        }
+    })?;
+    let start = hit.saturating_sub(radius);
+    let end = (hit + radius + 1).min(lines.len());
+    Some(lines[start..end].join("\n"))
+}
@@ -1,21 +1,22 @@
 use crate::{
-    database::Database,
-    doc_loader::Document,
+    database::{Database, ScoredDoc},
+    doc_loader::{Document, LOW_DOC_COVERAGE_NOTE},
     embeddings::EMBEDDING_CLIENT,
     error::ServerError, // Keep ServerError for ::new()
-};
-use async_openai::{
-    config::OpenAIConfig,
-    types::{
-        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
-        CreateChatCompletionRequestArgs,
-    },
-    Client as OpenAIClient,
+    hyde::HydeClient,
+    query_cache::{QueryCache, QueryCacheKey},
+    query_defaults::QueryDefaults,
+    request_context,
+    search,
+    summarize::{self, Answer, Summarizer},
+    validation::{Validate, ValidationError, Validator, MAX_QUESTION_LEN},
 };
 use ndarray::Array1;
 use rmcp::model::AnnotateAble; // Import trait for .no_annotation()
 use rmcp::{
+    handler::server::tool::ToolCallContext,
     model::{
+        CallToolRequestParam,
         CallToolResult,
         Content,
         GetPromptRequestParam,
@@ -24,6 +25,7 @@ use rmcp::{
         ListPromptsResult, // Removed EmptyObject, ErrorCode
         ListResourceTemplatesResult,
         ListResourcesResult,
+        ListToolsResult,
         LoggingLevel, // Uncommented ListToolsResult
         LoggingMessageNotification,
         LoggingMessageNotificationMethod,
@@ -50,17 +52,58 @@ use rmcp::{
 use schemars::JsonSchema; // Import JsonSchema
 use serde::Deserialize; // Import Deserialize
 use serde_json::json;
-use std::{/* borrow::Cow, */ env, sync::Arc}; // Removed borrow::Cow
+use std::{/* borrow::Cow, */ sync::Arc, time::Duration}; // Removed borrow::Cow
 use tokio::sync::Mutex;
 
 // --- Argument Struct for the Tool ---
 
 #[derive(Debug, Deserialize, JsonSchema)]
-struct QueryRustDocsArgs {
+pub struct QueryRustDocsArgs {
     #[schemars(description = "The crate to search in (e.g., \"axum\", \"tokio\", \"serde\")")]
     crate_name: String,
     #[schemars(description = "The specific question about the crate's API or usage.")]
     question: String,
+    #[schemars(
+        description = "Include per-result diagnostics (distance, similarity, matched terms)."
+    )]
+    #[serde(default)]
+    debug: bool,
+    #[schemars(
+        description = "List sibling items of the top result (same struct or module) as navigation hints."
+    )]
+    #[serde(default)]
+    suggest_related: bool,
+    #[schemars(
+        description = "Split a multi-part question into sub-queries, search each and merge the results."
+    )]
+    #[serde(default)]
+    expand_query: bool,
+    #[schemars(
+        description = "Also search with an LLM-drafted hypothetical answer (HyDE); helps vague questions at the cost of one extra LLM call."
+    )]
+    #[serde(default)]
+    hyde: bool,
+    #[schemars(
+        description = "Answer with the LLM (true) or return the matching documentation snippets as-is (false). Default: QUERY_SUMMARIZE, else true."
+    )]
+    #[serde(default)]
+    summarize: Option<bool>,
+}
+
+/// Documents the stdio server answers from when neither the crate nor `QUERY_DEFAULT_LIMIT` sets a limit
+pub const STDIO_RESULT_LIMIT: usize = 3;
+
+impl Validate for QueryRustDocsArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.crate_name("crate_name", &self.crate_name).length(
+            "question",
+            &self.question,
+            1,
+            MAX_QUESTION_LEN,
+        );
+        v.finish()
+    }
 }
 
 // --- Main Server Struct ---
@@ -74,7 +117,10 @@ pub struct RustDocsServer {
     peer: Arc<Mutex<Option<Peer<RoleServer>>>>,  // Uses tokio::sync::Mutex
     startup_message: Arc<Mutex<Option<String>>>, // Keep the message itself
     startup_message_sent: Arc<Mutex<bool>>,      // Flag to track if sent (using tokio::sync::Mutex)
-                                                 // tool_name and info are handled by ServerHandler/macros now
+    summarizer: Summarizer,
+    question_embeddings: Arc<QueryCache<Vec<Vec<f32>>>>,
+    query_defaults: Arc<QueryDefaults>,
+    // tool_name and info are handled by ServerHandler/macros now
 }
 
 impl RustDocsServer {
@@ -94,6 +140,9 @@ impl RustDocsServer {
             peer: Arc::new(Mutex::new(None)), // Uses tokio::sync::Mutex
             startup_message: Arc::new(Mutex::new(Some(startup_message))), // Initialize message
             startup_message_sent: Arc::new(Mutex::new(false)), // Initialize flag to false
+            summarizer: Summarizer::from_env(),
+            question_embeddings: Arc::new(QueryCache::new(0, Duration::ZERO)),
+            query_defaults: Arc::new(QueryDefaults::default()),
         })
     }
 
@@ -123,6 +172,139 @@ impl RustDocsServer {
         });
     }
 
+    /// Cache question embeddings so a client retrying a query skips HyDE and embedding
+    pub fn with_question_cache(mut self, cache: QueryCache<Vec<Vec<f32>>>) -> Self {
+        self.question_embeddings = Arc::new(cache);
+        self
+    }
+
+    /// Environment defaults for what requests and crate configurations leave unset
+    pub fn with_query_defaults(mut self, defaults: QueryDefaults) -> Self {
+        let max_tokens = defaults.max_response_tokens(None).map(|n| n as u32);
+        self.summarizer = self.summarizer.with_max_tokens(max_tokens);
+        self.query_defaults = Arc::new(defaults);
+        self
+    }
+
+    /// Search parameters for a crate: its configured defaults, then the environment's
+    async fn search_params(&self, target_crate: &str) -> search::SearchParams {
+        let configs = self
+            .database
+            .get_crate_configs_by_name(target_crate)
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("⚠️  Failed to get query defaults for {target_crate}: {e}");
+                Vec::new()
+            });
+        configs
+            .iter()
+            .fold(search::SearchParams::default(), |defaults, config| {
+                defaults.or(config.search_defaults())
+            })
+            .or(self.query_defaults.search_params())
+    }
+
+    /// Whether the crate was populated from its README for lack of API documentation
+    async fn low_doc_coverage(&self, target_crate: &str) -> bool {
+        self.database
+            .get_crate_configs_by_name(target_crate)
+            .await
+            .is_ok_and(|configs| configs.iter().any(|c| c.low_doc_coverage))
+    }
+
+    /// The queries searched for a question (sub-queries, HyDE draft) and their embeddings
+    ///
+    /// Cached per question and options, so an immediate retry after a failed call reuses
+    /// them instead of paying for HyDE and the embedding call again.
+    async fn embed_question(&self, args: &QueryRustDocsArgs) -> Result<Vec<Vec<f32>>, McpError> {
+        let target_crate = &args.crate_name;
+        let question = &args.question;
+        let embedding_provider = EMBEDDING_CLIENT
+            .get()
+            .ok_or_else(|| McpError::internal_error("Embedding provider not initialized", None))?;
+
+        let cache_key = QueryCacheKey::new(
+            target_crate,
+            question,
+            embedding_provider.get_model_name(),
+            &format!("expand={};hyde={}", args.expand_query, args.hyde),
+        );
+        if let Some(embeddings) = self.question_embeddings.get(&cache_key) {
+            self.send_log(
+                LoggingLevel::Info,
+                "Reusing cached question embedding".to_string(),
+            );
+            return Ok(embeddings);
+        }
+
+        let queries = if args.expand_query {
+            let queries = search::split_question(question);
+            self.send_log(
+                LoggingLevel::Info,
+                format!("Expanded question into {} sub-queries", queries.len()),
+            );
+            queries
+        } else {
+            vec![question.to_string()]
+        };
+        let queries = if args.hyde {
+            let hyde = HydeClient::from_env(None);
+            let expanded = hyde.expand(target_crate, question, queries).await;
+            self.send_log(
+                LoggingLevel::Info,
+                format!("Searching with {} queries (HyDE)", expanded.len()),
+            );
+            expanded
+        } else {
+            queries
+        };
+
+        // Generate embedding for the question using the configured provider
+        let (embeddings, _tokens) = embedding_provider
+            .generate_embeddings(&queries)
+            .await
+            .map_err(|e| McpError::internal_error(format!("Embedding API error: {e}"), None))?;
+
+        if embeddings.len() != queries.len() {
+            return Err(McpError::internal_error(
+                "Failed to get embedding for question",
+                None,
+            ));
+        }
+
+        self.question_embeddings
+            .insert(cache_key, embeddings.clone());
+        Ok(embeddings)
+    }
+
+    /// Documents closest to any of the question embeddings, best first
+    async fn search_candidates(
+        &self,
+        target_crate: &str,
+        embeddings: Vec<Vec<f32>>,
+    ) -> Result<Vec<ScoredDoc>, McpError> {
+        let params = self.search_params(target_crate).await;
+        let limit = params
+            .limit
+            .unwrap_or(STDIO_RESULT_LIMIT)
+            .clamp(1, search::MAX_RESULT_LIMIT);
+        let searches = embeddings.into_iter().map(|embedding| {
+            let question_vector = Array1::from(embedding);
+            async move {
+                self.database
+                    .search_similar_docs_scored(target_crate, &question_vector, limit as i32)
+                    .await
+            }
+        });
+        futures::future::try_join_all(searches)
+            .await
+            .map(|result_sets| params.filter(search::merge_results(result_sets, limit)))
+            .map_err(|e| {
+                self.send_log(LoggingLevel::Error, format!("Database search failed: {e}"));
+                McpError::internal_error(format!("Database search error: {e}"), None)
+            })
+    }
+
     // Helper for creating simple text resources (like in counter example)
     fn _create_resource_text(&self, uri: &str, name: &str) -> Resource {
         RawResource::new(uri, name.to_string()).no_annotation()
@@ -144,6 +326,8 @@ impl RustDocsServer {
         #[tool(aggr)] // Aggregate arguments into the struct
         args: QueryRustDocsArgs,
     ) -> Result<CallToolResult, McpError> {
+        args.validate()?;
+
         // --- Send Startup Message (if not already sent) ---
         let mut sent_guard = self.startup_message_sent.lock().await;
         if !*sent_guard {
@@ -167,73 +351,50 @@ impl RustDocsServer {
         // Use the explicitly provided crate name
         let target_crate = crate_name;
 
+        // Checked per query so a crate disabled after startup is hidden right away
+        let disabled = self
+            .database
+            .get_disabled_crate_names()
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to check crate status: {e}"), None)
+            })?;
+        if disabled.contains(target_crate) {
+            return Err(McpError::invalid_params(
+                format!("Crate '{target_crate}' is disabled"),
+                None,
+            ));
+        }
+
         // Log received query via MCP
         self.send_log(
             LoggingLevel::Info,
             format!("Searching in crate '{target_crate}' for: {question}"),
         );
 
-        // --- Embedding Generation for Question ---
-        let embedding_provider = EMBEDDING_CLIENT
-            .get()
-            .ok_or_else(|| McpError::internal_error("Embedding provider not initialized", None))?;
+        // --- Stage 1: question embeddings (reused on a retry) ---
+        let embeddings = self.embed_question(&args).await?;
 
-        // Generate embedding for the question using the configured provider
-        let (embeddings, _tokens) = embedding_provider
-            .generate_embeddings(&[question.to_string()])
-            .await
-            .map_err(|e| McpError::internal_error(format!("Embedding API error: {e}"), None))?;
-
-        let question_embedding = embeddings.into_iter().next().ok_or_else(|| {
-            McpError::internal_error("Failed to get embedding for question", None)
-        })?;
-
-        let question_vector = Array1::from(question_embedding);
-
-        // --- Search for similar documents using database ---
+        // --- Stage 2: candidate documents ---
         self.send_log(
             LoggingLevel::Info,
             format!("Performing vector search in database for crate '{target_crate}'"),
         );
+        let search_results = self.search_candidates(target_crate, embeddings).await?;
 
-        let search_results = self
-            .database
-            .search_similar_docs(target_crate, &question_vector, 3)
-            .await
-            .map_err(|e| {
-                self.send_log(LoggingLevel::Error, format!("Database search failed: {e}"));
-                McpError::internal_error(format!("Database search error: {e}"), None)
-            })?;
-
-        // --- Generate Response using LLM ---
+        // --- Stage 3: LLM answer, or the raw snippets when it fails ---
         let response_text = if !search_results.is_empty() {
-            let (best_path, best_content, best_score) = &search_results[0];
-
+            let best = &search_results[0];
             self.send_log(
                 LoggingLevel::Info,
                 format!(
-                    "Found {} relevant documents via vector DB. Best match: {best_path} (similarity: {best_score:.3})",
-                    search_results.len()
+                    "Found {} relevant documents via vector DB. Best match: {} (similarity: {:.3})",
+                    search_results.len(),
+                    best.doc_path,
+                    best.similarity
                 ),
             );
 
-            // Combine top results for better context
-            let combined_context = if search_results.len() > 1 {
-                search_results
-                    .iter()
-                    .enumerate()
-                    .map(|(i, (path, content, score))| {
-                        format!(
-                            "--- Document {} (similarity: {score:.3}) ---\nPath: {path}\n\n{content}",
-                            i + 1
-                        )
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n\n")
-            } else {
-                best_content.clone()
-            };
-
             // Check if this is an in-memory fallback or actual DB result
             let source = if self.embeddings.is_empty() {
                 "vector database"
@@ -247,74 +408,41 @@ impl RustDocsServer {
                 format!("Using {result_count} results from {source} for LLM context"),
             );
 
-            {
-                // Get OpenAI client for LLM chat completion (separate from embedding provider)
-                let openai_client = if let Ok(api_base) = env::var("OPENAI_API_BASE") {
-                    let config = OpenAIConfig::new().with_api_base(api_base);
-                    OpenAIClient::with_config(config)
-                } else {
-                    OpenAIClient::new()
+            if !self.query_defaults.summarize(args.summarize) {
+                let shown = match self.query_defaults.max_response_tokens(None) {
+                    Some(budget) => search::within_token_budget(&search_results, budget),
+                    None => &search_results[..],
                 };
-
-                let system_prompt = format!(
-                        "You are an expert technical assistant for the Rust crate '{target_crate}'. \
-                         Answer the user's question based *only* on the provided context. \
-                         If the context does not contain the answer, say so. \
-                         Do not make up information. Be clear, concise, and comprehensive providing example usage code when possible."
-                    );
-                let user_prompt =
-                    format!("Context:\n---\n{combined_context}\n---\n\nQuestion: {question}");
-
-                let llm_model: String =
-                    env::var("LLM_MODEL").unwrap_or_else(|_| "gpt-4o-mini-2024-07-18".to_string());
-                let chat_request = CreateChatCompletionRequestArgs::default()
-                    .model(llm_model)
-                    .messages(vec![
-                        ChatCompletionRequestSystemMessageArgs::default()
-                            .content(system_prompt)
-                            .build()
-                            .map_err(|e| {
-                                McpError::internal_error(
-                                    format!("Failed to build system message: {e}"),
-                                    None,
-                                )
-                            })?
-                            .into(),
-                        ChatCompletionRequestUserMessageArgs::default()
-                            .content(user_prompt)
-                            .build()
-                            .map_err(|e| {
-                                McpError::internal_error(
-                                    format!("Failed to build user message: {e}"),
-                                    None,
-                                )
-                            })?
-                            .into(),
-                    ])
-                    .build()
-                    .map_err(|e| {
-                        McpError::internal_error(format!("Failed to build chat request: {e}"), None)
-                    })?;
-
-                let chat_response =
-                    openai_client
-                        .chat()
-                        .create(chat_request)
-                        .await
-                        .map_err(|e| {
-                            McpError::internal_error(format!("OpenAI chat API error: {e}"), None)
-                        })?;
-
                 self.send_log(
                     LoggingLevel::Info,
-                    "Generating response using LLM based on vector DB results".to_string(),
+                    format!("Returning {} snippets without summarizing", shown.len()),
                 );
-
-                chat_response
-                    .choices
-                    .first()
-                    .and_then(|choice| choice.message.content.clone())
-                    .unwrap_or_else(|| "Error: No response from LLM.".to_string())
+                summarize::format_snippets(shown)
+            } else {
+                let answer = self
+                    .summarizer
+                    .answer(target_crate, question, &search_results)
+                    .await
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                match answer {
+                    Answer::Summary(summary) => {
+                        self.send_log(
+                            LoggingLevel::Info,
+                            "Generating response using LLM based on vector DB results".to_string(),
+                        );
+                        summary
+                    }
+                    Answer::Snippets { text, warning } => {
+                        self.send_log(
+                            LoggingLevel::Warning,
+                            format!(
+                                "{warning} ({} fallbacks since startup)",
+                                self.summarizer.fallback_count()
+                            ),
+                        );
+                        format!("⚠️ {warning}\n\n{text}")
+                    }
+                }
             }
         } else {
             self.send_log(
@@ -325,12 +453,56 @@ impl RustDocsServer {
         };
 
         // --- Format and Return Result ---
-        let final_response = if !search_results.is_empty() {
+        let mut final_response = if !search_results.is_empty() {
             format!("From {target_crate} docs (via vector database search): {response_text}")
         } else {
             format!("From {target_crate} docs: {response_text}")
         };
 
+        if !search_results.is_empty() {
+            let sources: Vec<String> = search_results
+                .iter()
+                .map(|doc| format!("- {}", search::source_url(&doc.doc_path)))
+                .collect();
+            final_response.push_str("\n\nSources:\n");
+            final_response.push_str(&sources.join("\n"));
+        }
+
+        if args.suggest_related && !search_results.is_empty() {
+            match search::suggest_related(
+                &self.database,
+                target_crate,
+                &search_results[0].doc_path,
+                10,
+            )
+            .await
+            {
+                Ok(items) if !items.is_empty() => {
+                    final_response.push_str("\n\n");
+                    final_response.push_str(&search::format_related(&items));
+                }
+                Ok(_) => {}
+                Err(e) => self.send_log(
+                    LoggingLevel::Warning,
+                    format!("Failed to find related items: {e}"),
+                ),
+            }
+        }
+
+        if args.debug && !search_results.is_empty() {
+            let diagnostics: Vec<String> = search_results
+                .iter()
+                .map(|doc| search::format_debug_diagnostics(question, doc))
+                .collect();
+            final_response.push_str("\n\n");
+            final_response.push_str(&diagnostics.join("\n"));
+        }
+
+        if self.low_doc_coverage(target_crate).await {
+            final_response.push_str("\n\n");
+            final_response.push_str(LOW_DOC_COVERAGE_NOTE);
+        }
+
         self.send_log(
             LoggingLevel::Info,
             "Successfully generated response".to_string(),
@@ -342,7 +514,6 @@ impl RustDocsServer {
 
 // --- ServerHandler Implementation ---
 
-#[tool(tool_box)] // Use imported tool macro directly
 impl ServerHandler for RustDocsServer {
     fn get_info(&self) -> ServerInfo {
         // Define capabilities using the builder
@@ -363,12 +534,35 @@ impl ServerHandler for RustDocsServer {
             instructions: Some(format!(
                 "This server provides tools to query documentation for the '{}' crate. \
                  Use the 'query_rust_docs' tool with a specific question to get information \
-                 about its API, usage, and examples, derived from its official documentation.",
-                self.crate_name
+                 about its API, usage, and examples, derived from its official documentation. {}",
+                self.crate_name,
+                self.query_defaults.describe(STDIO_RESULT_LIMIT)
             )),
         }
     }
 
+    async fn list_tools(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult {
+            next_cursor: None,
+            tools: Self::tool_box().list(),
+        })
+    }
+
+    // Tool dispatch goes through request_context so errors carry a request ID
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let tool_name = request.name.clone();
+        let context = ToolCallContext::new(self, request, context);
+        request_context::dispatch("stdio", &tool_name, None, Self::tool_box().call(context)).await
+    }
+
     // --- Placeholder Implementations for other ServerHandler methods ---
     // Implement these properly if resource/prompt features are added later.
 
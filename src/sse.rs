@@ -0,0 +1,505 @@
+//! SSE transport for the HTTP MCP server.
+//!
+//! This mirrors `rmcp::transport::sse_server`, but adds keep-alive comments on
+//! the event stream and reaps sessions that have gone idle or whose client has
+//! disconnected. The upstream transport exposes neither knob, and abandoned
+//! sessions there live until the process restarts.
+
+use axum::{
+    extract::{ConnectInfo, Query, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use rmcp::{
+    model::ClientJsonRpcMessage,
+    service::{RxJsonRpcMessage, TxJsonRpcMessage},
+    RoleServer,
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::io;
+use tokio_util::sync::{CancellationToken, DropGuard, PollSender};
+use tracing::{debug, error, info, warn};
+
+type SessionId = Arc<str>;
+type TxStore =
+    Arc<tokio::sync::RwLock<HashMap<SessionId, tokio::sync::mpsc::Sender<ClientJsonRpcMessage>>>>;
+type InboundStream = Pin<Box<dyn Stream<Item = RxJsonRpcMessage<RoleServer>> + Send>>;
+
+/// Why a session's transport stopped delivering client messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    /// No client message arrived within the configured idle timeout
+    IdleTimeout(Duration),
+    /// The client dropped the SSE stream
+    ClientDisconnected,
+}
+
+impl fmt::Display for CloseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IdleTimeout(timeout) => write!(f, "idle for more than {timeout:?}"),
+            Self::ClientDisconnected => write!(f, "client disconnected"),
+        }
+    }
+}
+
+/// Cheap handle to a session that outlives its transport being moved into the service
+#[derive(Debug, Clone)]
+pub struct SessionHandle {
+    session_id: SessionId,
+    peer_addr: SocketAddr,
+    profile: Option<Arc<str>>,
+    close_reason: Arc<OnceLock<CloseReason>>,
+    disconnected: CancellationToken,
+}
+
+impl SessionHandle {
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// The profile whose SSE path the session connected to (`None` for the default path)
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// The reason the transport closed, if it was closed by the server side
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.close_reason.get().copied()
+    }
+
+    /// Token cancelled as soon as the client drops the SSE stream
+    ///
+    /// Unlike the session's own shutdown, this fires while tool calls are still
+    /// running, so they can stop work whose result can no longer be delivered.
+    pub fn disconnected(&self) -> CancellationToken {
+        self.disconnected.clone()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SseServerConfig {
+    pub bind: SocketAddr,
+    pub sse_path: String,
+    pub post_path: String,
+    pub ct: CancellationToken,
+    /// Interval between keep-alive comments on the event stream (`None` disables them)
+    pub keep_alive: Option<Duration>,
+    /// Close sessions that have sent no message for this long (`None` disables reaping)
+    pub idle_timeout: Option<Duration>,
+    /// Reject new sessions with 503 while this many are live (`None` means unlimited)
+    pub max_connections: Option<usize>,
+    /// Profiles served on `{sse_path}/{profile}` next to `sse_path`; their sessions post
+    /// to the same `post_path` and are told apart by [`SessionHandle::profile`]
+    pub profiles: Vec<String>,
+}
+
+/// Response header telling nginx not to buffer the event stream
+const X_ACCEL_BUFFERING: header::HeaderName = header::HeaderName::from_static("x-accel-buffering");
+
+/// Seconds clients are asked to wait before reconnecting after a 503 rejection
+const RETRY_AFTER_SECS: u64 = 10;
+
+/// Live connection gauge and lifetime counters for the SSE server
+#[derive(Debug, Default)]
+pub struct ConnectionStats {
+    active: AtomicUsize,
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl ConnectionStats {
+    /// Number of sessions whose transport is still alive
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Total sessions accepted since startup
+    pub fn accepted(&self) -> u64 {
+        self.accepted.load(Ordering::Relaxed)
+    }
+
+    /// Total sessions rejected because the connection limit was reached
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Reserve a slot for a new session, failing if the limit is reached
+    fn try_acquire(&self, max_connections: Option<usize>) -> bool {
+        let acquired = self
+            .active
+            .fetch_update(
+                Ordering::AcqRel,
+                Ordering::Acquire,
+                |active| match max_connections {
+                    Some(max) if active >= max => None,
+                    _ => Some(active + 1),
+                },
+            )
+            .is_ok();
+        if acquired {
+            self.accepted.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        acquired
+    }
+
+    fn release(&self) {
+        self.active.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[derive(Clone)]
+struct App {
+    txs: TxStore,
+    transport_tx: tokio::sync::mpsc::UnboundedSender<SseServerTransport>,
+    post_path: Arc<str>,
+    keep_alive: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    max_connections: Option<usize>,
+    stats: Arc<ConnectionStats>,
+}
+
+fn session_id() -> SessionId {
+    let id = format!("{:016x}", rand::random::<u128>());
+    Arc::from(id)
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostEventQuery {
+    session_id: String,
+}
+
+async fn post_event_handler(
+    State(app): State<App>,
+    Query(PostEventQuery { session_id }): Query<PostEventQuery>,
+    Json(message): Json<ClientJsonRpcMessage>,
+) -> Result<StatusCode, StatusCode> {
+    debug!(session_id, ?message, "new client message");
+    let tx = {
+        let sessions = app.txs.read().await;
+        sessions
+            .get(session_id.as_str())
+            .ok_or(StatusCode::NOT_FOUND)?
+            .clone()
+    };
+    if tx.send(message).await.is_err() {
+        error!("Failed to forward client message to session {session_id}");
+        return Err(StatusCode::GONE);
+    }
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn sse_handler(
+    State(app): State<App>,
+    ConnectInfo(peer_addr): ConnectInfo<SocketAddr>,
+) -> Result<impl IntoResponse, Response<String>> {
+    open_session(app, peer_addr, None).await
+}
+
+async fn open_session(
+    app: App,
+    peer_addr: SocketAddr,
+    profile: Option<Arc<str>>,
+) -> Result<impl IntoResponse, Response<String>> {
+    if !app.stats.try_acquire(app.max_connections) {
+        let active = app.stats.active();
+        let rejected = app.stats.rejected();
+        warn!(
+            %peer_addr,
+            active,
+            rejected,
+            "Rejecting SSE connection: connection limit reached"
+        );
+        let mut response = Response::new(format!(
+            "Connection limit reached ({active} active connections), retry in {RETRY_AFTER_SECS}s"
+        ));
+        *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, RETRY_AFTER_SECS.into());
+        return Err(response);
+    }
+
+    let session = session_id();
+    info!(%session, %peer_addr, profile = profile.as_deref(), "sse connection");
+
+    let (from_client_tx, from_client_rx) = tokio::sync::mpsc::channel(64);
+    let (to_client_tx, to_client_rx) = tokio::sync::mpsc::channel(64);
+    app.txs
+        .write()
+        .await
+        .insert(session.clone(), from_client_tx);
+
+    // Cancelled when axum drops the response stream, i.e. the client went away
+    let disconnected = CancellationToken::new();
+    let close_reason = Arc::new(OnceLock::new());
+    let inbound = inbound_stream(
+        from_client_rx,
+        app.idle_timeout,
+        disconnected.clone(),
+        close_reason.clone(),
+    );
+
+    let transport = SseServerTransport {
+        inbound,
+        sink: PollSender::new(to_client_tx),
+        handle: SessionHandle {
+            session_id: session.clone(),
+            peer_addr,
+            profile,
+            close_reason,
+            disconnected: disconnected.clone(),
+        },
+        tx_store: app.txs.clone(),
+        stats: app.stats.clone(),
+    };
+    if app.transport_tx.send(transport).is_err() {
+        warn!("Failed to hand off SSE transport, server is shutting down");
+        let mut response = Response::new("SSE server is shutting down".to_string());
+        *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        return Err(response);
+    }
+
+    let post_path = app.post_path.as_ref();
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("{post_path}?sessionId={session}"));
+    let outbound = outbound_stream(to_client_rx, app.keep_alive, disconnected.drop_guard());
+    // nginx buffers proxied responses by default, which would hold keep-alives back
+    // until the buffer fills and let its idle timeout close the connection anyway
+    Ok((
+        [(X_ACCEL_BUFFERING, "no")],
+        Sse::new(futures::stream::once(futures::future::ok(endpoint)).chain(outbound)),
+    ))
+}
+
+/// Events sent to the client, interleaved with keep-alive comments.
+///
+/// Keep-alives are produced here rather than via axum's `KeepAlive` so that the
+/// response type does not depend on whether they are enabled. The drop guard
+/// lives as long as the stream, so axum dropping it marks the client as gone.
+fn outbound_stream(
+    rx: tokio::sync::mpsc::Receiver<TxJsonRpcMessage<RoleServer>>,
+    keep_alive: Option<Duration>,
+    disconnect_guard: DropGuard,
+) -> impl Stream<Item = Result<Event, io::Error>> {
+    let keep_alive = keep_alive.map(|period| {
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval
+    });
+
+    futures::stream::unfold(
+        (rx, keep_alive, disconnect_guard),
+        |(mut rx, mut keep_alive, guard)| async move {
+            let tick = async {
+                match keep_alive.as_mut() {
+                    Some(interval) => {
+                        interval.tick().await;
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+            let event = tokio::select! {
+                message = rx.recv() => match serde_json::to_string(&message?) {
+                    Ok(bytes) => Ok(Event::default().event("message").data(&bytes)),
+                    Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                },
+                _ = tick => Ok(Event::default().comment("keep-alive")),
+            };
+            Some((event, (rx, keep_alive, guard)))
+        },
+    )
+}
+
+/// Stream of client messages that ends when the session idles out or the client disconnects
+fn inbound_stream(
+    rx: tokio::sync::mpsc::Receiver<ClientJsonRpcMessage>,
+    idle_timeout: Option<Duration>,
+    disconnected: CancellationToken,
+    close_reason: Arc<OnceLock<CloseReason>>,
+) -> InboundStream {
+    Box::pin(futures::stream::unfold(rx, move |mut rx| {
+        let disconnected = disconnected.clone();
+        let close_reason = close_reason.clone();
+        async move {
+            // The idle timer restarts with every message received from the client
+            let idle = async {
+                match idle_timeout {
+                    Some(timeout) => tokio::time::sleep(timeout).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                message = rx.recv() => message.map(|message| (message, rx)),
+                _ = idle => {
+                    if let Some(timeout) = idle_timeout {
+                        let _ = close_reason.set(CloseReason::IdleTimeout(timeout));
+                    }
+                    None
+                }
+                _ = disconnected.cancelled() => {
+                    let _ = close_reason.set(CloseReason::ClientDisconnected);
+                    None
+                }
+            }
+        }
+    }))
+}
+
+pub struct SseServerTransport {
+    inbound: InboundStream,
+    sink: PollSender<TxJsonRpcMessage<RoleServer>>,
+    handle: SessionHandle,
+    tx_store: TxStore,
+    stats: Arc<ConnectionStats>,
+}
+
+impl SseServerTransport {
+    pub fn handle(&self) -> SessionHandle {
+        self.handle.clone()
+    }
+}
+
+impl Drop for SseServerTransport {
+    fn drop(&mut self) {
+        self.stats.release();
+        let session_id = self.handle.session_id.clone();
+        let tx_store = self.tx_store.clone();
+        tokio::spawn(async move {
+            tx_store.write().await.remove(&session_id);
+        });
+    }
+}
+
+impl Sink<TxJsonRpcMessage<RoleServer>> for SseServerTransport {
+    type Error = io::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.sink.poll_ready_unpin(cx).map_err(io::Error::other)
+    }
+
+    fn start_send(
+        mut self: Pin<&mut Self>,
+        item: TxJsonRpcMessage<RoleServer>,
+    ) -> Result<(), Self::Error> {
+        self.sink.start_send_unpin(item).map_err(io::Error::other)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.sink.poll_flush_unpin(cx).map_err(io::Error::other)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.sink.poll_close_unpin(cx).map_err(io::Error::other)
+    }
+}
+
+impl Stream for SseServerTransport {
+    type Item = RxJsonRpcMessage<RoleServer>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inbound.as_mut().poll_next(cx)
+    }
+}
+
+pub struct SseServer {
+    transport_rx: tokio::sync::mpsc::UnboundedReceiver<SseServerTransport>,
+    stats: Arc<ConnectionStats>,
+    pub config: SseServerConfig,
+}
+
+impl SseServer {
+    /// Bind `config.bind` and start serving; binding port 0 picks a free port, which is
+    /// written back to `config.bind`
+    pub async fn serve_with_config(mut config: SseServerConfig) -> io::Result<Self> {
+        let (transport_tx, transport_rx) = tokio::sync::mpsc::unbounded_channel();
+        let stats = Arc::new(ConnectionStats::default());
+        let app = App {
+            txs: Default::default(),
+            transport_tx,
+            post_path: config.post_path.clone().into(),
+            keep_alive: config.keep_alive,
+            idle_timeout: config.idle_timeout,
+            max_connections: config.max_connections,
+            stats: stats.clone(),
+        };
+
+        let listener = tokio::net::TcpListener::bind(config.bind).await?;
+        config.bind = listener.local_addr()?;
+        let mut service = Router::new()
+            .route(&config.sse_path, get(sse_handler))
+            .route(&config.post_path, post(post_event_handler));
+        for profile in &config.profiles {
+            let path = format!("{}/{profile}", config.sse_path.trim_end_matches('/'));
+            let profile: Arc<str> = Arc::from(profile.as_str());
+            service = service.route(
+                &path,
+                get(
+                    move |State(app): State<App>,
+                          ConnectInfo(peer_addr): ConnectInfo<SocketAddr>| {
+                        open_session(app, peer_addr, Some(profile.clone()))
+                    },
+                ),
+            );
+        }
+        let service = service.with_state(app);
+        let ct = config.ct.child_token();
+        let server = axum::serve(
+            listener,
+            service.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move {
+            ct.cancelled().await;
+            info!("SSE server cancelled");
+        });
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                error!(error = %e, "SSE server shutdown with error");
+            }
+        });
+
+        Ok(Self {
+            transport_rx,
+            stats,
+            config,
+        })
+    }
+
+    /// Connection gauge and counters shared with the HTTP handlers
+    pub fn stats(&self) -> Arc<ConnectionStats> {
+        self.stats.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.config.ct.cancel();
+    }
+
+    pub async fn next_transport(&mut self) -> Option<SseServerTransport> {
+        self.transport_rx.recv().await
+    }
+}
@@ -0,0 +1,199 @@
+//! LLM answers for the stdio server's `query_rust_docs`
+//!
+//! Summarizing is the last and least reliable stage of a query: by the time it runs the
+//! question is embedded and the matching docs are found. When the chat call fails or times
+//! out, the found snippets are returned as-is, marked as unsummarized, instead of failing
+//! the whole tool call and making the client pay for the embedding and search again.
+
+use crate::{database::ScoredDoc, error::ServerError, search};
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+        CreateChatCompletionRequest, CreateChatCompletionRequestArgs,
+    },
+    Client as OpenAIClient,
+};
+use std::{
+    env,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Chat model for answers when `LLM_MODEL` is not set
+pub const DEFAULT_LLM_MODEL: &str = "gpt-4o-mini-2024-07-18";
+
+/// Longest an answer may take before the raw snippets are returned instead
+pub const DEFAULT_SUMMARY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What `query_rust_docs` shows for the documents it found
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    /// The LLM's answer, based only on the documents
+    Summary(String),
+    /// The documents themselves, because summarizing failed for the reason in `warning`
+    Snippets { text: String, warning: String },
+}
+
+/// Answers questions from found documents with an OpenAI-compatible chat model
+#[derive(Clone)]
+pub struct Summarizer {
+    client: OpenAIClient<OpenAIConfig>,
+    model: String,
+    timeout: Duration,
+    /// Longest answer in tokens (`None` leaves it to the model)
+    max_tokens: Option<u32>,
+    fallbacks: Arc<AtomicU64>,
+}
+
+impl Summarizer {
+    pub fn new(client: OpenAIClient<OpenAIConfig>, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+            timeout: DEFAULT_SUMMARY_TIMEOUT,
+            max_tokens: None,
+            fallbacks: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Client for `OPENAI_API_BASE`/`OPENAI_API_KEY` and `LLM_MODEL`
+    pub fn from_env() -> Self {
+        let client = match env::var("OPENAI_API_BASE") {
+            Ok(api_base) => OpenAIClient::with_config(OpenAIConfig::new().with_api_base(api_base)),
+            Err(_) => OpenAIClient::new(),
+        };
+        let model = env::var("LLM_MODEL").unwrap_or_else(|_| DEFAULT_LLM_MODEL.to_string());
+        Self::new(client, &model)
+    }
+
+    #[allow(dead_code)] // Used by tests
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Cap answers at `max_tokens` (`QUERY_MAX_RESPONSE_TOKENS`)
+    pub fn with_max_tokens(mut self, max_tokens: Option<u32>) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// How many answers fell back to raw snippets since startup
+    pub fn fallback_count(&self) -> u64 {
+        self.fallbacks.load(Ordering::Relaxed)
+    }
+
+    /// Chat request answering `question` about `crate_name` from `context` alone
+    fn request(
+        &self,
+        crate_name: &str,
+        question: &str,
+        context: &str,
+    ) -> Result<CreateChatCompletionRequest, ServerError> {
+        let system_prompt = format!(
+            "You are an expert technical assistant for the Rust crate '{crate_name}'. \
+             Answer the user's question based *only* on the provided context. \
+             If the context does not contain the answer, say so. \
+             Do not make up information. Be clear, concise, and comprehensive providing example usage code when possible."
+        );
+        let user_prompt = format!("Context:\n---\n{context}\n---\n\nQuestion: {question}");
+        let mut request = CreateChatCompletionRequestArgs::default();
+        if let Some(max_tokens) = self.max_tokens {
+            request.max_completion_tokens(max_tokens);
+        }
+        request
+            .model(&self.model)
+            .messages(vec![
+                ChatCompletionRequestSystemMessageArgs::default()
+                    .content(system_prompt)
+                    .build()
+                    .map_err(|e| {
+                        ServerError::Internal(format!("Failed to build system message: {e}"))
+                    })?
+                    .into(),
+                ChatCompletionRequestUserMessageArgs::default()
+                    .content(user_prompt)
+                    .build()
+                    .map_err(|e| {
+                        ServerError::Internal(format!("Failed to build user message: {e}"))
+                    })?
+                    .into(),
+            ])
+            .build()
+            .map_err(|e| ServerError::Internal(format!("Failed to build chat request: {e}")))
+    }
+
+    async fn send(&self, request: CreateChatCompletionRequest) -> Result<String, ServerError> {
+        let response = tokio::time::timeout(self.timeout, self.client.chat().create(request))
+            .await
+            .map_err(|_| {
+                ServerError::Network(format!("LLM answer timed out after {:?}", self.timeout))
+            })?
+            .map_err(ServerError::OpenAI)?;
+
+        response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .filter(|answer| !answer.trim().is_empty())
+            .ok_or_else(|| ServerError::Internal("LLM answer was empty".to_string()))
+    }
+
+    /// Summarize `results`, or return them as raw snippets when the chat call fails
+    ///
+    /// Only failures of the chat call itself (timeout, network, API error, empty answer)
+    /// fall back; a request that cannot even be built is still an error.
+    pub async fn answer(
+        &self,
+        crate_name: &str,
+        question: &str,
+        results: &[ScoredDoc],
+    ) -> Result<Answer, ServerError> {
+        let request = self.request(crate_name, question, &format_context(results))?;
+        match self.send(request).await {
+            Ok(summary) => Ok(Answer::Summary(summary)),
+            Err(e) => {
+                let fallbacks = self.fallbacks.fetch_add(1, Ordering::Relaxed) + 1;
+                eprintln!(
+                    "⚠️  LLM answer failed, returning raw snippets ({fallbacks} so far): {e}"
+                );
+                Ok(Answer::Snippets {
+                    text: format_snippets(results),
+                    warning: format!("LLM summarization failed ({e}); showing the raw documentation snippets instead."),
+                })
+            }
+        }
+    }
+}
+
+/// Found documents numbered with their similarity, path and docs.rs link
+pub fn format_snippets(results: &[ScoredDoc]) -> String {
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, doc)| {
+            format!(
+                "--- Document {} (similarity: {:.3}) ---\nPath: {}\nURL: {}\n\n{}",
+                i + 1,
+                doc.similarity,
+                doc.doc_path,
+                search::source_url(&doc.doc_path),
+                doc.content
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// LLM context for `results`: a single document is passed bare
+fn format_context(results: &[ScoredDoc]) -> String {
+    match results {
+        [only] => only.content.clone(),
+        _ => format_snippets(results),
+    }
+}
@@ -0,0 +1,119 @@
+//! Data-driven `min_similarity` suggestions from a crate's score distribution
+//!
+//! Useful similarities depend on the embedding model: one model's relevant matches score
+//! 0.45, another's 0.8. `suggest_threshold` searches a crate with sample questions an
+//! operator expects it to answer, plus [`OFF_TOPIC_PROBES`] it can't answer, and compares
+//! the two distributions of top-result similarities. When every probe scores below most
+//! sample questions, the threshold goes in the middle of that gap; when they overlap, it
+//! falls back to a low percentile of the sample questions, so most of them keep their
+//! top result. The suggestion is advisory and nothing is changed.
+
+use serde::Serialize;
+
+/// Questions no Rust crate's docs answer; their top similarities show what "irrelevant"
+/// scores look like with the current model
+pub const OFF_TOPIC_PROBES: &[&str] = &[
+    "What is a good recipe for sourdough bread?",
+    "Who won the football world cup in 1998?",
+    "How do I repot an orchid without damaging the roots?",
+    "What are the symptoms of the common cold?",
+    "Which oil paints are best for landscape painting?",
+];
+
+/// Sample questions' percentile used when probes and questions overlap
+pub const DEFAULT_PERCENTILE: f32 = 10.0;
+
+/// Summary of a set of similarities
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub min: f32,
+    pub p10: f32,
+    pub p25: f32,
+    pub median: f32,
+    pub p75: f32,
+    pub p90: f32,
+    pub max: f32,
+}
+
+impl Distribution {
+    /// `None` for no scores
+    pub fn of(scores: &[f32]) -> Option<Self> {
+        let sorted = sorted(scores);
+        Some(Self {
+            count: sorted.len(),
+            min: *sorted.first()?,
+            p10: percentile(&sorted, 10.0)?,
+            p25: percentile(&sorted, 25.0)?,
+            median: percentile(&sorted, 50.0)?,
+            p75: percentile(&sorted, 75.0)?,
+            p90: percentile(&sorted, 90.0)?,
+            max: *sorted.last()?,
+        })
+    }
+}
+
+/// How a threshold was chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    /// Midway between the best off-topic probe and the sample questions' percentile
+    Gap,
+    /// The sample questions' percentile, since the probes overlap them
+    Percentile,
+}
+
+/// A suggested `min_similarity` and the evidence for it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Suggestion {
+    pub threshold: f32,
+    pub method: Method,
+    pub questions: Distribution,
+    pub off_topic: Option<Distribution>,
+    /// Sample questions whose top result the threshold keeps, as a fraction
+    pub questions_kept: f64,
+    /// Off-topic probes whose top result the threshold keeps, as a fraction
+    pub off_topic_kept: Option<f64>,
+}
+
+/// Suggest a threshold from the top similarities of sample questions and off-topic probes
+///
+/// `percentile` (1-50) is the share of sample questions allowed to fall below the
+/// threshold. `None` without sample scores.
+pub fn suggest(questions: &[f32], off_topic: &[f32], percentile: f32) -> Option<Suggestion> {
+    let sorted_questions = sorted(questions);
+    let low_question = self::percentile(&sorted_questions, percentile)?;
+    let best_off_topic = sorted(off_topic).last().copied();
+    let (threshold, method) = match best_off_topic {
+        Some(best) if best < low_question => ((best + low_question) / 2.0, Method::Gap),
+        _ => (low_question, Method::Percentile),
+    };
+    // Rounded, so the value can be copied into a config as-is
+    let threshold = (threshold * 1000.0).round() / 1000.0;
+    let kept = |scores: &[f32]| {
+        scores.iter().filter(|&&s| s >= threshold).count() as f64 / scores.len() as f64
+    };
+    Some(Suggestion {
+        threshold,
+        method,
+        questions: Distribution::of(questions)?,
+        off_topic: Distribution::of(off_topic),
+        questions_kept: kept(questions),
+        off_topic_kept: (!off_topic.is_empty()).then(|| kept(off_topic)),
+    })
+}
+
+/// `p`th percentile (0-100) of ascending `sorted`, interpolating between neighbours
+pub fn percentile(sorted: &[f32], p: f32) -> Option<f32> {
+    let last = sorted.len().checked_sub(1)?;
+    let rank = (p.clamp(0.0, 100.0) / 100.0) * last as f32;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    let weight = rank - lower as f32;
+    Some(sorted[lower] + (sorted[upper] - sorted[lower]) * weight)
+}
+
+fn sorted(scores: &[f32]) -> Vec<f32> {
+    let mut sorted: Vec<f32> = scores.iter().copied().filter(|s| !s.is_nan()).collect();
+    sorted.sort_by(f32::total_cmp);
+    sorted
+}
@@ -0,0 +1,1049 @@
+//! Arguments of the HTTP server's MCP tools and the checks they must pass
+//!
+//! The structs double as the tools' JSON schemas (field doc comments become the parameter
+//! descriptions), and each implements [`Validate`] so a tool can reject a bad call with
+//! every offending field before touching the database.
+
+use crate::{
+    doc_loader, estimate, language, rustdoc_json,
+    search::{MAX_COMPARE_CRATES, MAX_COMPARE_RESULTS, MAX_RESULT_LIMIT, MAX_SMART_QUERY_CRATES},
+    validation::{Validate, ValidationError, Validator, MAX_QUESTION_LEN, MAX_TEXT_LEN},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct QueryRustDocsArgs {
+    /// The crate to search in (e.g., "axum", "tokio", "serde")
+    pub crate_name: String,
+    /// The specific question about the crate's API or usage.
+    pub question: String,
+    /// Include per-result diagnostics (distance, similarity, matched terms) (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<bool>,
+    /// List sibling items of the top result (same struct or module) as navigation hints (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggest_related: Option<bool>,
+    /// Split a multi-part question into sub-queries, search each and merge the results (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expand_query: Option<bool>,
+    /// Number of results to return, 1-20 (default: the crate's configured default, else 5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Drop results below this similarity (default: the crate's configured default, else none; 0 disables)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_similarity: Option<f32>,
+    /// Only search documents whose path starts with this, e.g. 'tokio/latest/tokio/sync/' (default: the crate's configured default; '' disables)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_prefix: Option<String>,
+    /// Only search chunks of this docblock section, e.g. 'Panics', 'Safety' or 'Examples' (case-insensitive; crates added with section_metadata)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    /// Drop results whose stored chunk has fewer tokens than this, e.g. 50 to skip one-line stubs and type aliases (default: 0, keeps all)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_tokens: Option<u32>,
+    /// Leave out trailing results once their stored chunks exceed this many tokens; the top result is always kept (default: QUERY_MAX_RESPONSE_TOKENS, else unlimited; 0 disables)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_response_tokens: Option<u32>,
+    /// Also search with an LLM-drafted hypothetical answer (HyDE); helps vague questions but adds an LLM call (~1-3 s) (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hyde: Option<bool>,
+    /// Drop results whose text repeats a higher-ranked result, e.g. re-exported docs (default: true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<bool>,
+    /// Append a JSON record of how the results were produced: model, strategy, search parameters, crate versions, every candidate considered and per-stage timings (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_provenance: Option<bool>,
+    /// Return only the top result's text with its source and similarity, unnumbered, instead of a list; limit and suggest_related are ignored (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_only: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct SmartQueryArgs {
+    /// The question about a Rust API, when you don't know which crate documents it
+    pub question: String,
+    /// How many of the closest crates to search, 1-10 (default: 3)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crates: Option<u32>,
+    /// Number of results to return across those crates, 1-20 (default: 5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Drop results below this similarity (default: none)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_similarity: Option<f32>,
+    /// At most this many results from any one crate, 1-20, so the results spread across
+    /// crates (default: no quota)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_per_crate: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct CompareCratesArgs {
+    /// Alternative crates to compare, 2-5 (e.g. ['reqwest', 'ureq']); results stay grouped per crate
+    pub crate_names: Vec<String>,
+    /// The concept to look up in each, e.g. 'how to make an async request'
+    pub question: String,
+    /// Results shown per crate, 1-5 (default: 2)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Drop results below this similarity (default: none)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_similarity: Option<f32>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct GetDocArgs {
+    /// The crate the document belongs to
+    pub crate_name: String,
+    /// Document path or docs.rs URL as shown in query results' "Source:" lines; a path
+    /// ending in " [chunk i/n]" returns only that chunk
+    pub doc_path: String,
+}
+
+/// Longest accepted `query_id`; the server's are a few characters
+pub const MAX_QUERY_ID_LEN: usize = 64;
+
+/// Upper bound on `min_tokens`; no stored chunk is longer than the embedding model's input
+pub const MAX_MIN_TOKENS: u32 = crate::embeddings::MODEL_TOKEN_LIMIT as u32;
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct SubmitFeedbackArgs {
+    /// The "Query ID" shown under the query_rust_docs results being rated
+    pub query_id: String,
+    /// The crate that was queried
+    pub crate_name: String,
+    /// Document path or docs.rs URL of the result, as shown in its "Source:" line
+    pub doc_path: String,
+    /// true if the result helped answer the question (e.g. it was cited), false if it was noise
+    pub useful: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct FindSymbolArgs {
+    /// Identifier to look for, e.g. 'poll_ready' or 'JoinHandle'
+    pub identifier: String,
+    /// Only search this crate (default: all crates)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crate_name: Option<String>,
+    /// Match case exactly (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub case_sensitive: Option<bool>,
+    /// Number of documents to return (default: 10, max: 20)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct CrateInfoArgs {
+    /// Crate name as published on crates.io, e.g. 'tokio'
+    pub crate_name: String,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct AddCrateArgs {
+    /// The crate name (e.g., 'tokio', 'serde')
+    pub crate_name: String,
+    /// Version specification: 'latest', an exact version (e.g. '1.35.0', '0.12.0-rc.1') or a semver requirement (e.g. '^0.12', '~1.2', '>=1.0, <2'); yanked versions are skipped
+    pub version_spec: String,
+    /// Let 'latest' and version requirements resolve to pre-releases such as '1.0.0-rc.1' (default: false; exact versions are always honored)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_prerelease: Option<bool>,
+    /// Optional features to enable (e.g., ['full', 'macros']); when omitted, the server's DEFAULT_CRATE_FEATURES are used, and an empty list enables none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+    /// Whether the crate is enabled (default: true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Expected number of documents, the baseline for coverage checks (default: set from the first population)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_docs: Option<i32>,
+    /// Set expected_docs to the stored document count after the first successful population (default: true unless expected_docs is given)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_expected_docs: Option<bool>,
+    /// CSS selector for doc content, for crates with custom rustdoc themes (default: rustdoc docblocks)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_selector: Option<String>,
+    /// Regexes a page URL must match for its docs to be kept (e.g. ['/sync/']); module index pages on the way are still crawled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_url_patterns: Option<Vec<String>>,
+    /// Regexes for page URLs to skip (e.g. ['/operation/'])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_url_patterns: Option<Vec<String>>,
+    /// Follow links into re-exported dependency crates, for facade crates like 'bevy' (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follow_reexports: Option<bool>,
+    /// Only follow into these crates (e.g. ['bevy_ecs', 'bevy_app']; default: any linked crate)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reexport_crates: Option<Vec<String>>,
+    /// Keep each crawled page's HTML so extraction can be re-run without re-crawling; roughly doubles storage (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_raw_html: Option<bool>,
+    /// Tag chunks with the docblock section they come from (Examples, Panics, Safety, Errors, ...) so query_rust_docs can search one section (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section_metadata: Option<bool>,
+    /// ISO 639-3 codes of the doc languages to keep, e.g. ['eng'] (default: keep every language)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub languages: Option<Vec<String>>,
+    /// 'drop' to leave documents in other languages out (default) or 'tag' to store them tagged and filter them at query time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_action: Option<String>,
+    /// Maximum pages fetched from re-exported crates in total (default: 500)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reexport_page_budget: Option<i32>,
+    /// Maximum pages fetched from any single re-exported crate (default: 100)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reexport_pages_per_crate: Option<i32>,
+    /// Populate from this rustdoc JSON file (optionally .json.gz) instead of crawling docs.rs, e.g. CI-published docs for private crates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rustdoc_json_url: Option<String>,
+    /// docs.rs build target to document, e.g. 'x86_64-pc-windows-msvc' for Windows-only APIs (default: docs.rs default target). Add one config per target to cover several.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Text prepended to questions before embedding to disambiguate the crate, e.g. 'async Rust SQL toolkit' for sqlx
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_hint: Option<String>,
+    /// Results query_rust_docs returns when the caller doesn't pass limit (1-20)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_limit: Option<i32>,
+    /// Minimum similarity query_rust_docs applies when the caller doesn't pass min_similarity (0-1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_min_similarity: Option<f32>,
+    /// Doc path prefix query_rust_docs applies when the caller doesn't pass path_prefix
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_path_prefix: Option<String>,
+    /// Most chunks stored for the crate; population stops there, keeping the earliest (shallowest) pages, and marks the crate truncated (default: unlimited)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_embeddings: Option<i32>,
+    /// Longest one population may run, in seconds; at the limit what was stored is kept, the job is marked partial and the next population resumes (default: unlimited)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_duration_secs: Option<i32>,
+    /// Only estimate the population's pages, tokens, cost and duration, as estimate_population does; nothing is saved or crawled (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate_only: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct EstimatePopulationArgs {
+    /// The crate name (e.g., 'tokio', 'serde')
+    pub crate_name: String,
+    /// Version specification as for add_crate (default: 'latest')
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_spec: Option<String>,
+    /// Let 'latest' and version requirements resolve to pre-releases (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_prerelease: Option<bool>,
+    /// Features the crate would be added with; docs.rs builds its own feature set, so they don't change the estimate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+    /// docs.rs build target to estimate (default: docs.rs default target)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// CSS selector for doc content, as for add_crate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_selector: Option<String>,
+    /// Regexes a page URL must match to be counted, as for add_crate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_url_patterns: Option<Vec<String>>,
+    /// Regexes for page URLs not to count, as for add_crate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_url_patterns: Option<Vec<String>>,
+    /// Pages fetched to measure tokens per page (default: 5, max: 20); more pages take longer and give a tighter estimate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_pages: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct ListCratesArgs {
+    /// Only show enabled crates (default: true unless include_disabled is set)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled_only: Option<bool>,
+    /// Also list disabled crates (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_disabled: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct RenameCrateArgs {
+    /// The crate's current name
+    pub old_name: String,
+    /// The name to store it under; must not already be in use
+    pub new_name: String,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct SetCrateEnabledArgs {
+    /// The crate to show to or hide from queries
+    pub crate_name: String,
+    /// false hides the crate from queries and list_crates; its embeddings and configuration are kept
+    pub enabled: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct GcOrphansArgs {
+    /// Only report what would be deleted (default: true); pass false to delete
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct PopulatePendingArgs {
+    /// Only list the pending configurations without starting anything (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<bool>,
+}
+
+/// Upper bound on rows per `raw_search` call; every row can carry a full vector
+pub const MAX_RAW_SEARCH_LIMIT: u32 = 300;
+
+/// Upper bound on crates searched by one `raw_search` call
+pub const MAX_RAW_SEARCH_CRATES: usize = 20;
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct RawSearchArgs {
+    /// Crates to search; their rows are ranked together by distance
+    pub crate_names: Vec<String>,
+    /// Text to embed with the server's query model; pass either this or embedding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub question: Option<String>,
+    /// Query vector used as-is; must have the dimension of every crate's stored vectors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+    /// Rows to return, 1-300 (default: 20)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Include each document's text (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_content: Option<bool>,
+    /// Include each document's last_updated_at and crate_version (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_metadata: Option<bool>,
+    /// Include each document's stored embedding vector; large (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_vectors: Option<bool>,
+}
+
+/// What a `raw_search` call searches with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RawQuery<'a> {
+    /// Embedded with the server's query model first
+    Question(&'a str),
+    /// Searched with directly
+    Embedding(&'a [f32]),
+}
+
+impl RawSearchArgs {
+    /// The query to search with; `None` unless exactly one of question and embedding is set
+    pub fn query(&self) -> Option<RawQuery<'_>> {
+        match (&self.question, &self.embedding) {
+            (Some(question), None) => Some(RawQuery::Question(question)),
+            (None, Some(embedding)) => Some(RawQuery::Embedding(embedding)),
+            _ => None,
+        }
+    }
+
+    /// Reject a query vector whose dimension differs from the vectors stored for `crate_name`
+    ///
+    /// A mismatched vector would otherwise search another model's table, or none at all.
+    pub fn check_dimension(
+        &self,
+        crate_name: &str,
+        stored: usize,
+        query: usize,
+    ) -> Result<(), ValidationError> {
+        let field = if self.embedding.is_some() {
+            "embedding"
+        } else {
+            "question"
+        };
+        let constraint = if field == "embedding" {
+            format!("must have {stored} dimensions like the vectors stored for {crate_name}, not {query}")
+        } else {
+            format!("embeds to {query} dimensions with the query model but {crate_name} is stored with {stored}")
+        };
+        let mut v = Validator::new();
+        v.check(field, query, stored == query, &constraint);
+        v.finish()
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct CheckCrateStatusArgs {
+    /// The crate name to check status for
+    pub crate_name: String,
+    /// Build target of the configuration to check (default: the default-target configuration)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct JobStatusArgs {
+    /// The population job to inspect, as returned by add_crate or check_crate_status
+    pub job_id: i32,
+}
+
+/// URLs one `crawl_trace` call returns unless `limit` says otherwise
+pub const DEFAULT_TRACE_LIMIT: u32 = 200;
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct CrawlTraceArgs {
+    /// The population job whose crawl to show, as returned by add_crate or check_crate_status
+    pub job_id: i32,
+    /// Only URLs with this outcome: fetched, empty, skipped_filter, skipped_robots,
+    /// not_found or error
+    pub outcome: Option<String>,
+    /// Most URLs to return, 1-10000 (default: 200); the per-outcome counts always cover
+    /// the whole crawl
+    pub limit: Option<u32>,
+}
+
+/// Most sample questions one `suggest_threshold` call embeds
+pub const MAX_THRESHOLD_QUESTIONS: usize = 50;
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct SuggestThresholdArgs {
+    /// The crate to measure
+    pub crate_name: String,
+    /// Questions the crate's docs should answer, 1-50; more give a steadier suggestion
+    pub questions: Vec<String>,
+    /// Share of the questions (1-50 percent) allowed to lose their top result when the
+    /// off-topic probes overlap them (default: 10)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percentile: Option<f32>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct IndexHealthArgs {
+    /// Only report on this crate (default: all crates)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crate_name: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct StorageReportArgs {
+    /// Only report on this crate (default: all crates)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crate_name: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct RemoveCrateArgs {
+    /// The crate name to remove
+    pub crate_name: String,
+    /// Version specification (default: 'latest')
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_spec: Option<String>,
+    /// Build target of the configuration to remove (default: the default-target configuration)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct CrateSpec {
+    /// The crate name (e.g., 'tokio', 'serde')
+    pub crate_name: String,
+    /// Version specification: 'latest', an exact version (e.g. '1.35.0', '0.12.0-rc.1') or a semver requirement (e.g. '^0.12', '~1.2', '>=1.0, <2'); yanked versions are skipped
+    #[serde(default = "default_version_spec")]
+    pub version_spec: String,
+    /// Let 'latest' and version requirements resolve to pre-releases such as '1.0.0-rc.1' (default: false; exact versions are always honored)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_prerelease: Option<bool>,
+    /// Optional features to enable (e.g., ['full', 'macros']); when omitted, the server's DEFAULT_CRATE_FEATURES are used, and an empty list enables none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+    /// Whether the crate is enabled (default: true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Expected number of documents, the baseline for coverage checks (default: set from the first population)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_docs: Option<i32>,
+    /// Set expected_docs to the stored document count after the first successful population (default: true unless expected_docs is given)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_expected_docs: Option<bool>,
+    /// CSS selector for doc content, for crates with custom rustdoc themes (default: rustdoc docblocks)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_selector: Option<String>,
+    /// Regexes a page URL must match for its docs to be kept (e.g. ['/sync/']); module index pages on the way are still crawled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_url_patterns: Option<Vec<String>>,
+    /// Regexes for page URLs to skip (e.g. ['/operation/'])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_url_patterns: Option<Vec<String>>,
+    /// Follow links into re-exported dependency crates, for facade crates like 'bevy' (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follow_reexports: Option<bool>,
+    /// Only follow into these crates (e.g. ['bevy_ecs', 'bevy_app']; default: any linked crate)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reexport_crates: Option<Vec<String>>,
+    /// Keep each crawled page's HTML so extraction can be re-run without re-crawling; roughly doubles storage (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_raw_html: Option<bool>,
+    /// Tag chunks with the docblock section they come from (Examples, Panics, Safety, Errors, ...) so query_rust_docs can search one section (default: false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section_metadata: Option<bool>,
+    /// ISO 639-3 codes of the doc languages to keep, e.g. ['eng'] (default: keep every language)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub languages: Option<Vec<String>>,
+    /// 'drop' to leave documents in other languages out (default) or 'tag' to store them tagged and filter them at query time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_action: Option<String>,
+    /// Maximum pages fetched from re-exported crates in total (default: 500)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reexport_page_budget: Option<i32>,
+    /// Maximum pages fetched from any single re-exported crate (default: 100)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reexport_pages_per_crate: Option<i32>,
+    /// Populate from this rustdoc JSON file (optionally .json.gz) instead of crawling docs.rs, e.g. CI-published docs for private crates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rustdoc_json_url: Option<String>,
+    /// docs.rs build target to document, e.g. 'x86_64-pc-windows-msvc' for Windows-only APIs (default: docs.rs default target). Add one config per target to cover several.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Text prepended to questions before embedding to disambiguate the crate, e.g. 'async Rust SQL toolkit' for sqlx
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_hint: Option<String>,
+    /// Results query_rust_docs returns when the caller doesn't pass limit (1-20)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_limit: Option<i32>,
+    /// Minimum similarity query_rust_docs applies when the caller doesn't pass min_similarity (0-1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_min_similarity: Option<f32>,
+    /// Doc path prefix query_rust_docs applies when the caller doesn't pass path_prefix
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_path_prefix: Option<String>,
+    /// Most chunks stored for the crate; population stops there, keeping the earliest (shallowest) pages, and marks the crate truncated (default: unlimited)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_embeddings: Option<i32>,
+    /// Longest one population may run, in seconds; at the limit what was stored is kept, the job is marked partial and the next population resumes (default: unlimited)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_duration_secs: Option<i32>,
+}
+
+fn default_version_spec() -> String {
+    "latest".to_string()
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct AddCratesArgs {
+    /// List of crates to add/configure
+    pub crates: Vec<CrateSpec>,
+    /// Whether to fail fast on first error (default: false - best effort)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail_fast: Option<bool>,
+}
+
+impl Validate for QueryRustDocsArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.crate_name("crate_name", &self.crate_name).length(
+            "question",
+            &self.question,
+            1,
+            MAX_QUESTION_LEN,
+        );
+        if let Some(limit) = self.limit {
+            v.range("limit", limit, 1, MAX_RESULT_LIMIT as u32);
+        }
+        if let Some(min_similarity) = self.min_similarity {
+            v.range("min_similarity", min_similarity, 0.0, 1.0);
+        }
+        if let Some(path_prefix) = &self.path_prefix {
+            v.length("path_prefix", path_prefix, 0, MAX_TEXT_LEN);
+        }
+        if let Some(section) = &self.section {
+            v.length("section", section, 1, MAX_TEXT_LEN);
+        }
+        if let Some(min_tokens) = self.min_tokens {
+            v.range("min_tokens", min_tokens, 0, MAX_MIN_TOKENS);
+        }
+        v.finish()
+    }
+}
+
+impl Validate for SmartQueryArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.length("question", &self.question, 1, MAX_QUESTION_LEN);
+        if let Some(crates) = self.crates {
+            v.range("crates", crates, 1, MAX_SMART_QUERY_CRATES as u32);
+        }
+        if let Some(limit) = self.limit {
+            v.range("limit", limit, 1, MAX_RESULT_LIMIT as u32);
+        }
+        if let Some(min_similarity) = self.min_similarity {
+            v.range("min_similarity", min_similarity, 0.0, 1.0);
+        }
+        if let Some(max_per_crate) = self.max_per_crate {
+            v.range("max_per_crate", max_per_crate, 1, MAX_RESULT_LIMIT as u32);
+        }
+        v.finish()
+    }
+}
+
+impl Validate for CompareCratesArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.check(
+            "crate_names",
+            self.crate_names.len(),
+            (2..=MAX_COMPARE_CRATES).contains(&self.crate_names.len()),
+            &format!("must list 2 to {MAX_COMPARE_CRATES} crates"),
+        );
+        for (i, crate_name) in self.crate_names.iter().enumerate() {
+            v.crate_name(&format!("crate_names[{i}]"), crate_name);
+        }
+        let distinct: std::collections::HashSet<&str> =
+            self.crate_names.iter().map(|c| c.trim()).collect();
+        v.check(
+            "crate_names",
+            self.crate_names.len(),
+            distinct.len() == self.crate_names.len(),
+            "must not list a crate twice",
+        );
+        v.length("question", &self.question, 1, MAX_QUESTION_LEN);
+        if let Some(limit) = self.limit {
+            v.range("limit", limit, 1, MAX_COMPARE_RESULTS as u32);
+        }
+        if let Some(min_similarity) = self.min_similarity {
+            v.range("min_similarity", min_similarity, 0.0, 1.0);
+        }
+        v.finish()
+    }
+}
+
+impl Validate for SubmitFeedbackArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.length("query_id", &self.query_id, 1, MAX_QUERY_ID_LEN)
+            .crate_name("crate_name", &self.crate_name)
+            .length("doc_path", &self.doc_path, 1, MAX_TEXT_LEN);
+        v.finish()
+    }
+}
+
+impl Validate for GetDocArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.crate_name("crate_name", &self.crate_name).length(
+            "doc_path",
+            &self.doc_path,
+            1,
+            MAX_TEXT_LEN,
+        );
+        v.finish()
+    }
+}
+
+impl Validate for FindSymbolArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.length("identifier", &self.identifier, 2, MAX_TEXT_LEN);
+        // An empty crate_name means "all crates"
+        if let Some(crate_name) = self.crate_name.as_deref().filter(|c| !c.trim().is_empty()) {
+            v.crate_name("crate_name", crate_name);
+        }
+        if let Some(limit) = self.limit {
+            v.range("limit", limit, 1, MAX_RESULT_LIMIT);
+        }
+        v.finish()
+    }
+}
+
+impl Validate for CrateInfoArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.crate_name("crate_name", &self.crate_name);
+        v.finish()
+    }
+}
+
+impl Validate for EstimatePopulationArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        CrateSettings {
+            crate_name: &self.crate_name,
+            version_spec: self.version_spec.as_deref().unwrap_or("latest"),
+            features: self.features.as_deref(),
+            expected_docs: None,
+            content_selector: self.content_selector.as_deref(),
+            include_url_patterns: self.include_url_patterns.as_deref(),
+            exclude_url_patterns: self.exclude_url_patterns.as_deref(),
+            reexport_crates: None,
+            reexport_page_budget: None,
+            reexport_pages_per_crate: None,
+            rustdoc_json_url: None,
+            target: self.target.as_deref(),
+            query_hint: None,
+            default_limit: None,
+            default_min_similarity: None,
+            default_path_prefix: None,
+            languages: None,
+            language_action: None,
+            max_embeddings: None,
+            max_duration_secs: None,
+            sample_pages: self.sample_pages,
+        }
+        .validate()
+    }
+}
+
+/// The settings `add_crate` and each `add_crates` entry share
+struct CrateSettings<'a> {
+    crate_name: &'a str,
+    version_spec: &'a str,
+    features: Option<&'a [String]>,
+    expected_docs: Option<i32>,
+    content_selector: Option<&'a str>,
+    include_url_patterns: Option<&'a [String]>,
+    exclude_url_patterns: Option<&'a [String]>,
+    reexport_crates: Option<&'a [String]>,
+    reexport_page_budget: Option<i32>,
+    reexport_pages_per_crate: Option<i32>,
+    rustdoc_json_url: Option<&'a str>,
+    target: Option<&'a str>,
+    query_hint: Option<&'a str>,
+    default_limit: Option<i32>,
+    default_min_similarity: Option<f32>,
+    default_path_prefix: Option<&'a str>,
+    languages: Option<&'a [String]>,
+    language_action: Option<&'a str>,
+    max_embeddings: Option<i32>,
+    max_duration_secs: Option<i32>,
+    /// Pages an estimate measures (estimate_population only)
+    sample_pages: Option<usize>,
+}
+
+impl CrateSettings<'_> {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.crate_name("crate_name", self.crate_name)
+            .version_spec("version_spec", self.version_spec);
+        check_features(&mut v, "features", self.features.unwrap_or_default());
+        if let Some(expected_docs) = self.expected_docs {
+            v.range("expected_docs", expected_docs, 0, i32::MAX);
+        }
+        if let Some(selector) = self.content_selector {
+            v.result(
+                "content_selector",
+                selector,
+                doc_loader::parse_content_selector(Some(selector)),
+            );
+        }
+        for (name, patterns) in [
+            ("include_url_patterns", self.include_url_patterns),
+            ("exclude_url_patterns", self.exclude_url_patterns),
+        ] {
+            for (i, pattern) in patterns.unwrap_or_default().iter().enumerate() {
+                v.result(
+                    &format!("{name}[{i}]"),
+                    pattern,
+                    doc_loader::UrlFilter::new(std::slice::from_ref(pattern), &[]),
+                );
+            }
+        }
+        for (i, name) in self.reexport_crates.unwrap_or_default().iter().enumerate() {
+            v.crate_name(&format!("reexport_crates[{i}]"), name);
+        }
+        if let Some(budget) = self.reexport_page_budget {
+            v.range("reexport_page_budget", budget, 0, i32::MAX);
+        }
+        if let Some(budget) = self.reexport_pages_per_crate {
+            v.range("reexport_pages_per_crate", budget, 0, i32::MAX);
+        }
+        let rustdoc_json_url = self.rustdoc_json_url.filter(|url| !url.trim().is_empty());
+        if let Some(url) = rustdoc_json_url {
+            v.result("rustdoc_json_url", url, rustdoc_json::validate_url(url));
+        }
+        if let Some(target) = self.target.filter(|t| !t.trim().is_empty()) {
+            v.result("target", target, doc_loader::validate_target(target))
+                .check(
+                    "target",
+                    target,
+                    rustdoc_json_url.is_none(),
+                    "selects a docs.rs build and cannot be combined with rustdoc_json_url",
+                );
+        }
+        if let Some(hint) = self.query_hint {
+            v.length("query_hint", hint, 0, MAX_TEXT_LEN);
+        }
+        if let Some(limit) = self.default_limit {
+            v.range("default_limit", limit, 1, MAX_RESULT_LIMIT as i32);
+        }
+        if let Some(min_similarity) = self.default_min_similarity {
+            v.range("default_min_similarity", min_similarity, 0.0, 1.0);
+        }
+        if let Some(prefix) = self.default_path_prefix {
+            v.length("default_path_prefix", prefix, 0, MAX_TEXT_LEN);
+        }
+        for (i, code) in self.languages.unwrap_or_default().iter().enumerate() {
+            if let Some(problem) = language::language_code_problem(code) {
+                v.check(&format!("languages[{i}]"), code, false, problem);
+            }
+        }
+        if let Some(action) = self.language_action.filter(|a| !a.trim().is_empty()) {
+            v.result(
+                "language_action",
+                action,
+                language::LanguageAction::parse(action),
+            );
+        }
+        if let Some(max_embeddings) = self.max_embeddings {
+            v.range("max_embeddings", max_embeddings, 1, i32::MAX);
+        }
+        if let Some(max_duration_secs) = self.max_duration_secs {
+            v.range("max_duration_secs", max_duration_secs, 1, i32::MAX);
+        }
+        if let Some(sample_pages) = self.sample_pages {
+            v.range("sample_pages", sample_pages, 1, estimate::MAX_SAMPLE_PAGES);
+        }
+        v.finish()
+    }
+}
+
+/// Check that every entry of the `field` list is a feature name
+fn check_features(v: &mut Validator, field: &str, features: &[String]) {
+    for (i, feature) in features.iter().enumerate() {
+        v.check(
+            &format!("{field}[{i}]"),
+            feature,
+            !feature.is_empty() && !feature.contains(char::is_whitespace),
+            "must be a feature name without whitespace",
+        );
+    }
+}
+
+/// The features of `DEFAULT_CRATE_FEATURES`, a comma-separated list that `add_crate` and
+/// `add_crates` store for crates whose call leaves `features` unset; blank entries are skipped
+pub fn parse_default_features(list: &str) -> Result<Vec<String>, ValidationError> {
+    let features: Vec<String> = list
+        .split(',')
+        .map(str::trim)
+        .filter(|feature| !feature.is_empty())
+        .map(String::from)
+        .collect();
+    let mut v = Validator::new();
+    check_features(&mut v, "DEFAULT_CRATE_FEATURES", &features);
+    v.finish()?;
+    Ok(features)
+}
+
+/// Borrow the [`CrateSettings`] of an `add_crate` call or `add_crates` entry
+macro_rules! crate_settings {
+    ($args:expr) => {
+        CrateSettings {
+            crate_name: &$args.crate_name,
+            version_spec: &$args.version_spec,
+            features: $args.features.as_deref(),
+            expected_docs: $args.expected_docs,
+            content_selector: $args.content_selector.as_deref(),
+            include_url_patterns: $args.include_url_patterns.as_deref(),
+            exclude_url_patterns: $args.exclude_url_patterns.as_deref(),
+            reexport_crates: $args.reexport_crates.as_deref(),
+            reexport_page_budget: $args.reexport_page_budget,
+            reexport_pages_per_crate: $args.reexport_pages_per_crate,
+            rustdoc_json_url: $args.rustdoc_json_url.as_deref(),
+            target: $args.target.as_deref(),
+            query_hint: $args.query_hint.as_deref(),
+            default_limit: $args.default_limit,
+            default_min_similarity: $args.default_min_similarity,
+            default_path_prefix: $args.default_path_prefix.as_deref(),
+            languages: $args.languages.as_deref(),
+            language_action: $args.language_action.as_deref(),
+            max_embeddings: $args.max_embeddings,
+            max_duration_secs: $args.max_duration_secs,
+            sample_pages: None,
+        }
+    };
+}
+
+impl Validate for AddCrateArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        crate_settings!(self).validate()
+    }
+}
+
+impl Validate for CrateSpec {
+    fn validate(&self) -> Result<(), ValidationError> {
+        crate_settings!(self).validate()
+    }
+}
+
+impl Validate for AddCratesArgs {
+    /// Only the list itself; each entry is validated on its own so one bad entry doesn't
+    /// fail a best-effort batch
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.check(
+            "crates",
+            self.crates.len(),
+            !self.crates.is_empty(),
+            "must list at least one crate",
+        );
+        v.finish()
+    }
+}
+
+impl Validate for RawSearchArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.check(
+            "crate_names",
+            self.crate_names.len(),
+            (1..=MAX_RAW_SEARCH_CRATES).contains(&self.crate_names.len()),
+            &format!("must list 1 to {MAX_RAW_SEARCH_CRATES} crates"),
+        );
+        for (i, crate_name) in self.crate_names.iter().enumerate() {
+            v.length(&format!("crate_names[{i}]"), crate_name, 1, MAX_TEXT_LEN);
+        }
+        match (&self.question, &self.embedding) {
+            (None, None) => {
+                v.check("question", (), false, "question or embedding is required");
+            }
+            (Some(_), Some(embedding)) => {
+                v.check(
+                    "embedding",
+                    embedding.len(),
+                    false,
+                    "must not be combined with question",
+                );
+            }
+            (Some(question), None) => {
+                v.length("question", question, 1, MAX_QUESTION_LEN);
+            }
+            (None, Some(embedding)) => {
+                // The vector itself can be thousands of numbers; report its size instead
+                v.check(
+                    "embedding",
+                    embedding.len(),
+                    !embedding.is_empty() && embedding.iter().all(|x| x.is_finite()),
+                    "must be a non-empty list of finite numbers",
+                );
+            }
+        }
+        if let Some(limit) = self.limit {
+            v.range("limit", limit, 1, MAX_RAW_SEARCH_LIMIT);
+        }
+        v.finish()
+    }
+}
+
+// Tools that look up an existing configuration accept any stored name, so crates added
+// before names were validated can still be renamed or removed
+
+impl Validate for RenameCrateArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.length("old_name", &self.old_name, 1, MAX_TEXT_LEN)
+            .crate_name("new_name", &self.new_name)
+            .check(
+                "new_name",
+                &self.new_name,
+                self.old_name.trim() != self.new_name.trim(),
+                "must differ from old_name",
+            );
+        v.finish()
+    }
+}
+
+impl Validate for SetCrateEnabledArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.length("crate_name", &self.crate_name, 1, MAX_TEXT_LEN);
+        v.finish()
+    }
+}
+
+impl Validate for CheckCrateStatusArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.length("crate_name", &self.crate_name, 1, MAX_TEXT_LEN);
+        if let Some(target) = self.target.as_deref().filter(|t| !t.trim().is_empty()) {
+            v.result("target", target, doc_loader::validate_target(target));
+        }
+        v.finish()
+    }
+}
+
+impl Validate for JobStatusArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.range("job_id", self.job_id, 1, i32::MAX);
+        v.finish()
+    }
+}
+
+impl Validate for CrawlTraceArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.range("job_id", self.job_id, 1, i32::MAX);
+        if let Some(outcome) = &self.outcome {
+            let known: Vec<_> = doc_loader::TraceOutcome::ALL
+                .iter()
+                .map(|outcome| outcome.as_str())
+                .collect();
+            v.check(
+                "outcome",
+                outcome,
+                doc_loader::TraceOutcome::parse(outcome).is_some(),
+                &format!("must be one of {}", known.join(", ")),
+            );
+        }
+        if let Some(limit) = self.limit {
+            v.range("limit", limit, 1, doc_loader::MAX_TRACE_ENTRIES as u32);
+        }
+        v.finish()
+    }
+}
+
+impl Validate for SuggestThresholdArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.crate_name("crate_name", &self.crate_name);
+        v.check(
+            "questions",
+            self.questions.len(),
+            (1..=MAX_THRESHOLD_QUESTIONS).contains(&self.questions.len()),
+            &format!("must list 1 to {MAX_THRESHOLD_QUESTIONS} questions"),
+        );
+        for (i, question) in self.questions.iter().enumerate() {
+            v.length(&format!("questions[{i}]"), question, 1, MAX_QUESTION_LEN);
+        }
+        if let Some(percentile) = self.percentile {
+            v.range("percentile", percentile, 1.0, 50.0);
+        }
+        v.finish()
+    }
+}
+
+impl Validate for RemoveCrateArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        v.length("crate_name", &self.crate_name, 1, MAX_TEXT_LEN);
+        if let Some(version_spec) = &self.version_spec {
+            v.length("version_spec", version_spec, 1, MAX_TEXT_LEN);
+        }
+        if let Some(target) = self.target.as_deref().filter(|t| !t.trim().is_empty()) {
+            v.result("target", target, doc_loader::validate_target(target));
+        }
+        v.finish()
+    }
+}
+
+impl Validate for IndexHealthArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        if let Some(crate_name) = &self.crate_name {
+            v.length("crate_name", crate_name, 0, MAX_TEXT_LEN);
+        }
+        v.finish()
+    }
+}
+
+impl Validate for StorageReportArgs {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::new();
+        if let Some(crate_name) = &self.crate_name {
+            v.length("crate_name", crate_name, 0, MAX_TEXT_LEN);
+        }
+        v.finish()
+    }
+}
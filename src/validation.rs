@@ -0,0 +1,197 @@
+//! Argument checks shared by the MCP tools of both servers
+//!
+//! Every tool validates its whole argument struct before doing any work and reports all
+//! problems at once: the `invalid_params` error carries one entry per offending field with
+//! its path, the value it had and the constraint it broke, so a client can fix a call in a
+//! single round trip instead of discovering the problems one by one.
+
+use crate::version_spec::{VersionSpec, VERSION_SPEC_SYNTAX};
+use rmcp::Error as McpError;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
+
+/// Longest crate name crates.io accepts
+pub const MAX_CRATE_NAME_LEN: usize = 64;
+
+/// Longest question `query_rust_docs` embeds
+pub const MAX_QUESTION_LEN: usize = 2000;
+
+/// Longest free-text argument: doc paths, path prefixes, identifiers, query hints
+#[allow(dead_code)] // Used by the HTTP server
+pub const MAX_TEXT_LEN: usize = 500;
+
+/// One argument that broke a constraint
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    /// Path of the argument, e.g. `limit` or `include_url_patterns[1]`
+    pub field: String,
+    /// The value that was passed
+    pub value: Value,
+    /// What the value must satisfy
+    pub constraint: String,
+}
+
+/// Every argument of a tool call that broke a constraint
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationError {
+    /// Paths of the offending fields, in the order they were checked
+    #[allow(dead_code)] // Used by tests
+    pub fn fields(&self) -> Vec<&str> {
+        self.errors.iter().map(|e| e.field.as_str()).collect()
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problems: Vec<String> = self
+            .errors
+            .iter()
+            .map(|e| format!("{}: {}", e.field, e.constraint))
+            .collect();
+        write!(f, "Invalid arguments: {}", problems.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl From<ValidationError> for McpError {
+    fn from(e: ValidationError) -> Self {
+        let data = json!({ "errors": e.errors });
+        McpError::invalid_params(e.to_string(), Some(data))
+    }
+}
+
+/// Tool arguments that can check themselves before the tool runs
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationError>;
+}
+
+/// Collects the constraint failures of one argument struct
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `field` broke `constraint` unless `ok`
+    pub fn check(
+        &mut self,
+        field: &str,
+        value: impl Serialize,
+        ok: bool,
+        constraint: &str,
+    ) -> &mut Self {
+        if !ok {
+            self.errors.push(FieldError {
+                field: field.to_string(),
+                value: serde_json::to_value(value).unwrap_or(Value::Null),
+                constraint: constraint.to_string(),
+            });
+        }
+        self
+    }
+
+    /// Record the error of a check implemented elsewhere (selectors, regexes, URLs)
+    #[allow(dead_code)] // Used by the HTTP server
+    pub fn result<T, E: fmt::Display>(
+        &mut self,
+        field: &str,
+        value: impl Serialize,
+        result: Result<T, E>,
+    ) -> &mut Self {
+        match result {
+            Ok(_) => self,
+            Err(e) => self.check(field, value, false, &e.to_string()),
+        }
+    }
+
+    /// A crates.io crate name
+    pub fn crate_name(&mut self, field: &str, name: &str) -> &mut Self {
+        match crate_name_problem(name.trim()) {
+            Some(problem) => self.check(field, name, false, problem),
+            None => self,
+        }
+    }
+
+    /// `latest`, an exact version such as `0.12.0-rc.1` or a requirement such as `^0.12`
+    #[allow(dead_code)] // Used by the HTTP server
+    pub fn version_spec(&mut self, field: &str, spec: &str) -> &mut Self {
+        match VersionSpec::parse(spec) {
+            Ok(_) => self,
+            Err(e) => self.check(
+                field,
+                spec,
+                false,
+                &format!("must be {VERSION_SPEC_SYNTAX} ({})", e.reason),
+            ),
+        }
+    }
+
+    /// Text of `min..=max` characters, ignoring surrounding whitespace
+    pub fn length(&mut self, field: &str, value: &str, min: usize, max: usize) -> &mut Self {
+        let len = value.trim().chars().count();
+        let constraint = if min == 0 {
+            format!("must be at most {max} characters")
+        } else {
+            format!("must be {min} to {max} characters")
+        };
+        self.check(field, value, (min..=max).contains(&len), &constraint)
+    }
+
+    /// A number in `min..=max`; NaN is always out of range
+    #[allow(dead_code)] // Used by the HTTP server
+    pub fn range<T>(&mut self, field: &str, value: T, min: T, max: T) -> &mut Self
+    where
+        T: PartialOrd + Serialize + fmt::Display,
+    {
+        let ok = value >= min && value <= max;
+        let constraint = format!("must be between {min} and {max}");
+        self.check(field, value, ok, &constraint)
+    }
+
+    /// `Ok` when every check passed, otherwise all the failures
+    pub fn finish(self) -> Result<(), ValidationError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError {
+                errors: self.errors,
+            })
+        }
+    }
+}
+
+/// Why `name` is not a valid crates.io crate name, if it isn't
+///
+/// crates.io names are 1 to 64 ASCII letters, digits, `-` and `_`, starting with a letter.
+pub fn crate_name_problem(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        Some("must not be empty")
+    } else if name.len() > MAX_CRATE_NAME_LEN {
+        Some("must be at most 64 characters")
+    } else if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        Some("must start with an ASCII letter")
+    } else if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Some("may only contain ASCII letters, digits, '-' and '_'")
+    } else {
+        None
+    }
+}
+
+/// Whether `spec` is `latest` or parses as a semver version or requirement
+#[allow(dead_code)] // Used by the HTTP server
+pub fn is_valid_version_spec(spec: &str) -> bool {
+    VersionSpec::parse(spec).is_ok()
+}
@@ -0,0 +1,103 @@
+//! Crate configurations' `version_spec`: parsing and resolving it to a concrete version
+//!
+//! A spec is `latest`, an exact version (`1.35.0`, `0.12.0-rc.1`) or a semver requirement
+//! (`^0.12`, `~1.2`, `>=1.0, <2`). Requirements resolve to the highest published version
+//! that matches and isn't yanked; pre-releases only count for configurations with
+//! `allow_prerelease`, or when pinned exactly.
+
+use semver::{Prerelease, Version, VersionReq};
+use std::fmt;
+
+/// What a `version_spec` may be, for error messages
+pub const VERSION_SPEC_SYNTAX: &str = "'latest', an exact version such as '1.35.0' or '0.12.0-rc.1', or a requirement such as '^0.12', '~1.2' or '>=1.0, <2'";
+
+/// A parsed `version_spec`
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionSpec {
+    /// The newest release
+    Latest,
+    /// This version and no other, pre-release or yanked alike
+    Exact(Version),
+    /// The newest release matching the requirement
+    Requirement(VersionReq),
+}
+
+/// A `version_spec` that is none of [`VERSION_SPEC_SYNTAX`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidVersionSpec {
+    pub spec: String,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidVersionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "version_spec '{}' is invalid ({}); use {VERSION_SPEC_SYNTAX}",
+            self.spec, self.reason
+        )
+    }
+}
+
+impl std::error::Error for InvalidVersionSpec {}
+
+/// One version of a crate as published on crates.io
+#[derive(Debug, Clone, PartialEq)]
+#[allow(dead_code)] // Used by the population pipeline
+pub struct PublishedVersion {
+    pub version: Version,
+    pub yanked: bool,
+}
+
+impl VersionSpec {
+    /// Parse `spec`; a bare version is an exact pin, not Cargo's implicit caret
+    pub fn parse(spec: &str) -> Result<Self, InvalidVersionSpec> {
+        let spec = spec.trim();
+        if spec == "latest" {
+            return Ok(Self::Latest);
+        }
+        if let Ok(version) = Version::parse(spec) {
+            return Ok(Self::Exact(version));
+        }
+        VersionReq::parse(spec)
+            .map(Self::Requirement)
+            .map_err(|e| InvalidVersionSpec {
+                spec: spec.to_string(),
+                reason: e.to_string(),
+            })
+    }
+
+    /// The version to populate among `published`, or `None` if nothing qualifies
+    ///
+    /// Yanked versions never qualify unless pinned exactly. Pre-releases qualify with
+    /// `allow_prerelease`, matching a requirement as their release would (`^1.2` takes
+    /// `1.3.0-beta.1`), and otherwise only as semver allows, i.e. when the requirement
+    /// names a pre-release of the same version.
+    #[allow(dead_code)] // Used by the population pipeline
+    pub fn resolve(
+        &self,
+        published: &[PublishedVersion],
+        allow_prerelease: bool,
+    ) -> Option<Version> {
+        let matches = |version: &Version| match self {
+            Self::Latest => allow_prerelease || version.pre.is_empty(),
+            Self::Exact(exact) => version == exact,
+            Self::Requirement(req) => {
+                req.matches(version)
+                    || (allow_prerelease
+                        && !version.pre.is_empty()
+                        && req.matches(&Version {
+                            pre: Prerelease::EMPTY,
+                            ..version.clone()
+                        }))
+            }
+        };
+        published
+            .iter()
+            .filter(|p| !p.yanked || matches!(self, Self::Exact(_)))
+            .map(|p| &p.version)
+            .filter(|version| matches(version))
+            .max()
+            .cloned()
+    }
+}
@@ -0,0 +1,216 @@
+use rustdocs_mcp_server::{
+    backup::{read_archive, ArchiveWriter, CrateSnapshot, Record, Verifier},
+    database::{CrateRecord, StoredDocument},
+    error::ServerError,
+};
+
+fn doc(path: &str, content: &str) -> StoredDocument {
+    StoredDocument {
+        doc_path: path.to_string(),
+        content: content.to_string(),
+        token_count: content.len() as i32,
+        language: None,
+        section: None,
+        target: None,
+        embedding: vec![0.25, -0.5, 1.0],
+    }
+}
+
+fn snapshot(name: &str) -> CrateSnapshot {
+    CrateSnapshot {
+        name: name.to_string(),
+        records: Vec::new(),
+        record: None,
+        configs: Vec::new(),
+        metadata: None,
+    }
+}
+
+/// An archive of two crates, the second with a tagged language
+fn archive() -> Vec<u8> {
+    let mut writer = ArchiveWriter::new(Vec::new(), 3).unwrap();
+    writer.begin_crate(snapshot("serde")).unwrap();
+    writer
+        .doc(doc("serde/trait.Serialize.html", "Serialize"))
+        .unwrap();
+    writer
+        .doc(doc("serde/trait.Deserialize.html", "Deserialize"))
+        .unwrap();
+    writer.end_crate().unwrap();
+    writer.begin_crate(snapshot("tokio")).unwrap();
+    let mut spawn = doc("tokio/fn.spawn.html", "Spawns a task");
+    spawn.language = Some("eng".to_string());
+    writer.doc(spawn).unwrap();
+    writer.end_crate().unwrap();
+    let (manifest, bytes) = writer.finish().unwrap();
+    assert_eq!(manifest.len(), 2);
+    bytes
+}
+
+fn records(bytes: &[u8]) -> Vec<Record> {
+    read_archive(bytes)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap()
+}
+
+/// Re-compress `records` as an archive, as if someone had edited it
+fn rewrite(records: &[Record]) -> Vec<u8> {
+    let mut encoder = zstd::Encoder::new(Vec::new(), 3).unwrap();
+    let header = serde_json::json!({
+        "type": "header", "format": "mcpdocs-backup", "version": 1,
+        "created_at": "2026-01-01T00:00:00Z"
+    });
+    let mut lines = vec![header.to_string()];
+    lines.extend(records.iter().map(|r| serde_json::to_string(r).unwrap()));
+    std::io::Write::write_all(&mut encoder, lines.join("\n").as_bytes()).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn verify(bytes: &[u8]) -> Result<Vec<(String, u64)>, ServerError> {
+    let mut verifier = Verifier::default();
+    for record in read_archive(bytes)? {
+        verifier.check(&record?)?;
+    }
+    Ok(verifier
+        .finish()?
+        .into_iter()
+        .map(|summary| (summary.name, summary.docs))
+        .collect())
+}
+
+#[test]
+fn an_archive_reads_back_what_was_written_and_verifies() {
+    let bytes = archive();
+    let docs: Vec<StoredDocument> = records(&bytes)
+        .into_iter()
+        .filter_map(|record| match record {
+            Record::Doc(doc) => Some(doc),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(docs.len(), 3);
+    assert_eq!(docs[0], doc("serde/trait.Serialize.html", "Serialize"));
+    assert_eq!(docs[2].language.as_deref(), Some("eng"));
+
+    assert_eq!(
+        verify(&bytes).unwrap(),
+        vec![("serde".to_string(), 2), ("tokio".to_string(), 1)]
+    );
+}
+
+#[test]
+fn an_altered_document_fails_verification() {
+    let mut records = records(&archive());
+    for record in &mut records {
+        if let Record::Doc(doc) = record {
+            doc.embedding[0] = 0.26;
+            break;
+        }
+    }
+    let error = verify(&rewrite(&records)).unwrap_err().to_string();
+    assert!(error.contains("crate serde"), "{error}");
+}
+
+#[test]
+fn a_dropped_document_fails_verification() {
+    let mut records = records(&archive());
+    let doc = records
+        .iter()
+        .position(|record| matches!(record, Record::Doc(_)))
+        .unwrap();
+    records.remove(doc);
+    let error = verify(&rewrite(&records)).unwrap_err().to_string();
+    assert!(error.contains("1 documents"), "{error}");
+}
+
+#[test]
+fn a_truncated_archive_fails_verification() {
+    let mut records = records(&archive());
+    records.pop(); // the manifest
+    let error = verify(&rewrite(&records)).unwrap_err().to_string();
+    assert!(error.contains("cut short"), "{error}");
+
+    // A whole crate missing from the middle is caught by the manifest
+    let records: Vec<Record> = self::records(&archive())
+        .into_iter()
+        .filter(|record| match record {
+            Record::Crate(snapshot) => snapshot.name != "tokio",
+            Record::Doc(doc) => !doc.doc_path.starts_with("tokio/"),
+            Record::CrateEnd(summary) => summary.name != "tokio",
+            _ => true,
+        })
+        .collect();
+    let error = verify(&rewrite(&records)).unwrap_err().to_string();
+    assert!(error.contains("manifest lists 2 crates"), "{error}");
+}
+
+#[test]
+fn every_target_keeps_its_row_and_documents() {
+    let windows = Some("x86_64-pc-windows-msvc".to_string());
+    let record = |target: &Option<String>| CrateRecord {
+        version: Some("1.0.0".to_string()),
+        target: target.clone(),
+        embedding_model: None,
+    };
+    let mut writer = ArchiveWriter::new(Vec::new(), 3).unwrap();
+    writer
+        .begin_crate(CrateSnapshot {
+            records: vec![record(&None), record(&windows)],
+            ..snapshot("winapi")
+        })
+        .unwrap();
+    // Both targets store the README under the same path
+    writer.doc(doc("crate/winapi/1.0.0", "README")).unwrap();
+    writer
+        .doc(StoredDocument {
+            target: windows.clone(),
+            ..doc("crate/winapi/1.0.0", "README")
+        })
+        .unwrap();
+    writer.end_crate().unwrap();
+    let (_, bytes) = writer.finish().unwrap();
+
+    let records = records(&bytes);
+    let Record::Crate(snapshot) = &records[0] else {
+        panic!("expected the crate first: {records:?}");
+    };
+    let targets: Vec<_> = snapshot.records().map(|r| r.target.clone()).collect();
+    assert_eq!(targets, vec![None, windows.clone()]);
+    let docs: Vec<_> = records
+        .iter()
+        .filter_map(|record| match record {
+            Record::Doc(doc) => Some(doc.target.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(docs, vec![None, windows]);
+    assert_eq!(verify(&bytes).unwrap(), vec![("winapi".to_string(), 2)]);
+
+    // Moving a document to another target is an alteration like any other
+    let mut altered = records;
+    if let Record::Doc(doc) = &mut altered[2] {
+        doc.target = None;
+    }
+    assert!(verify(&rewrite(&altered)).is_err());
+}
+
+#[test]
+fn version_1_snapshots_read_their_single_row() {
+    let snapshot: CrateSnapshot = serde_json::from_value(serde_json::json!({
+        "name": "serde",
+        "record": { "version": "1.0.0", "target": null, "embedding_model": null },
+        "configs": [],
+        "metadata": null
+    }))
+    .unwrap();
+    let versions: Vec<_> = snapshot.records().map(|r| r.version.clone()).collect();
+    assert_eq!(versions, vec![Some("1.0.0".to_string())]);
+}
+
+#[test]
+fn other_files_are_not_archives() {
+    let plain = zstd::encode_all(&b"{\"type\":\"doc\"}\n"[..], 3).unwrap();
+    assert!(read_archive(&plain[..]).is_err());
+    assert!(read_archive(&b"not zstd"[..]).is_err());
+}
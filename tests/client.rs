@@ -0,0 +1,147 @@
+//! `DocsClient` end to end: population from a local docs mirror, then status and queries.
+//! The database tests only run when `MCPDOCS_TEST_DATABASE_URL` points at a migrated,
+//! disposable database.
+
+use rustdocs_mcp_server::{
+    client::{DocsClient, PopulateOptions, QueryOptions},
+    database::{Database, EMBEDDING_DIMENSION},
+    embeddings::EmbeddingProvider,
+    error::ServerError,
+};
+use std::sync::Arc;
+
+/// Points texts about tasks and texts about everything else in different directions
+struct TopicProvider;
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for TopicProvider {
+    async fn generate_embeddings(
+        &self,
+        texts: &[String],
+    ) -> Result<(Vec<Vec<f32>>, usize), ServerError> {
+        let embeddings = texts
+            .iter()
+            .map(|text| {
+                let mut embedding = vec![0.0; EMBEDDING_DIMENSION as usize];
+                let axis = usize::from(!text.to_lowercase().contains("task"));
+                embedding[axis] = 1.0;
+                embedding
+            })
+            .collect();
+        Ok((embeddings, texts.len()))
+    }
+
+    fn get_model_name(&self) -> &str {
+        "topic"
+    }
+}
+
+async fn test_database() -> Option<Database> {
+    let url = std::env::var("MCPDOCS_TEST_DATABASE_URL").ok()?;
+    Some(Database::connect(&url).await.unwrap())
+}
+
+/// Serves a two-page crate whose `latest` redirects to 0.3.0
+async fn docs_mirror() -> String {
+    use axum::{
+        http::Uri,
+        response::{Html as HtmlResponse, IntoResponse, Redirect, Response},
+        Router,
+    };
+
+    async fn docs(uri: Uri) -> Response {
+        let path = uri.path();
+        if path == "/clientdemo/latest/clientdemo/" {
+            Redirect::temporary("/clientdemo/0.3.0/clientdemo/").into_response()
+        } else if path.ends_with("/clientdemo/") {
+            HtmlResponse(
+                "<div class=\"docblock\"><p>Clientdemo parses configuration files into typed settings.</p></div>\
+                 <a href=\"task/index.html\">task</a>",
+            )
+            .into_response()
+        } else if path.ends_with("/task/index.html") {
+            HtmlResponse(
+                "<div class=\"docblock\"><p>Spawn a background task and await its result.</p></div>",
+            )
+            .into_response()
+        } else {
+            HtmlResponse("").into_response()
+        }
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+    origin
+}
+
+#[tokio::test]
+async fn a_client_needs_an_embedding_provider() {
+    let error = DocsClient::builder()
+        .database_url("postgresql://localhost/unused")
+        .build()
+        .await
+        .err()
+        .expect("building without a provider fails");
+
+    assert!(
+        matches!(&error, ServerError::Config(message) if message.contains("embedding provider")),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn populated_crates_are_queryable_through_the_client() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let crate_name = "clientdemo";
+    let client = DocsClient::builder()
+        .database(db.clone())
+        .embedding_provider(Arc::new(TopicProvider))
+        .query_cache(10, std::time::Duration::from_secs(60))
+        .docs_origin(docs_mirror().await)
+        .ignore_robots(true)
+        .build()
+        .await
+        .unwrap();
+
+    let report = client
+        .populate(crate_name, &PopulateOptions::default())
+        .await
+        .unwrap();
+    let status = client.status(crate_name).await.unwrap();
+    let listed = client.list_crates().await.unwrap();
+    let hits = client
+        .query(
+            crate_name,
+            "How do I spawn a task?",
+            &QueryOptions::default(),
+        )
+        .await
+        .unwrap();
+    let empty_question = client.query(crate_name, "", &QueryOptions::default()).await;
+    db.delete_crate_embeddings(crate_name).await.unwrap();
+    db.delete_crate_config(crate_name, "latest", None)
+        .await
+        .unwrap();
+
+    assert_eq!(report.version.as_deref(), Some("0.3.0"));
+    assert_eq!(report.stats.stored_chunks, 2);
+    assert_eq!(status.stored_docs, 2);
+    assert_eq!(status.config.current_version.as_deref(), Some("0.3.0"));
+    // The first population establishes the expected document count
+    assert_eq!(status.config.expected_docs, 2);
+    assert!(listed.iter().any(|c| c.name == crate_name));
+    assert_eq!(
+        hits[0].doc_path,
+        "clientdemo/latest/clientdemo/task/index.html"
+    );
+    assert_eq!(
+        hits[0].source_url,
+        "https://docs.rs/clientdemo/latest/clientdemo/task/index.html"
+    );
+    assert!(hits[0].similarity > hits[1].similarity);
+    assert!(matches!(empty_question, Err(ServerError::Config(_))));
+}
@@ -0,0 +1,79 @@
+use rustdocs_mcp_server::{database::CrateConfig, search};
+
+fn config(expected_docs: i32, auto_expected_docs: bool, populated: bool) -> CrateConfig {
+    CrateConfig {
+        expected_docs,
+        auto_expected_docs,
+        last_populated: populated.then(chrono::Utc::now),
+        ..CrateConfig::new("demo", "latest")
+    }
+}
+
+#[test]
+fn first_population_sets_the_expected_docs_baseline() {
+    assert_eq!(
+        config(1000, true, false).expected_docs_baseline(1234),
+        Some(1234)
+    );
+    // Later populations, explicit baselines and empty crawls leave it alone
+    assert_eq!(config(1234, true, true).expected_docs_baseline(90), None);
+    assert_eq!(
+        config(1000, false, false).expected_docs_baseline(1234),
+        None
+    );
+    assert_eq!(config(1000, true, false).expected_docs_baseline(0), None);
+}
+
+#[test]
+fn under_population_is_judged_against_an_established_baseline() {
+    // The placeholder of a never-populated auto config is not a baseline
+    assert!(!config(1000, true, false).is_under_populated(10));
+
+    let established = config(1000, true, true);
+    assert!(established.is_under_populated(500));
+    assert!(!established.is_under_populated(950));
+    assert_eq!(established.coverage(500), Some(0.5));
+
+    assert!(config(1000, false, false).is_under_populated(10));
+    assert_eq!(config(0, false, true).coverage(10), None);
+
+    // A crate cut short by its own max_embeddings is partial by design
+    let truncated = CrateConfig {
+        max_embeddings: Some(500),
+        truncated: true,
+        ..config(1000, false, true)
+    };
+    assert!(!truncated.is_under_populated(500));
+    assert_eq!(truncated.embedding_limit(), Some(500));
+}
+
+#[test]
+fn unfinished_and_time_boxed_populations_resume() {
+    assert!(config(1000, true, false).resumes_population());
+    assert!(!config(1000, true, true).resumes_population());
+
+    let partial = CrateConfig {
+        max_duration_secs: Some(7200),
+        partial_at: Some(chrono::Utc::now()),
+        ..config(1000, true, true)
+    };
+    assert!(partial.resumes_population());
+    assert_eq!(
+        partial.max_duration(),
+        Some(std::time::Duration::from_secs(7200))
+    );
+}
+
+#[test]
+fn configured_query_defaults_become_search_defaults() {
+    let mut config = config(1000, false, true);
+    assert_eq!(config.search_defaults(), search::SearchParams::default());
+
+    config.default_limit = Some(8);
+    config.default_min_similarity = Some(0.35);
+    config.default_path_prefix = Some("sqlx/latest/sqlx/".to_string());
+    let defaults = config.search_defaults();
+    assert_eq!(defaults.limit, Some(8));
+    assert_eq!(defaults.min_similarity, Some(0.35));
+    assert_eq!(defaults.path_prefix(), Some("sqlx/latest/sqlx/"));
+}
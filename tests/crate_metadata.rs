@@ -0,0 +1,129 @@
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+use chrono::{Duration, Utc};
+use rustdocs_mcp_server::{
+    crate_metadata::{self, CratesIo},
+    database::CrateMetadata,
+};
+use serde_json::json;
+
+async fn mock_crates_io(router: Router) -> CratesIo {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    CratesIo::new(&origin)
+}
+
+#[tokio::test]
+async fn metadata_comes_from_the_latest_stable_version() {
+    let crates_io = mock_crates_io(Router::new().route(
+        "/api/v1/crates/{name}",
+        get(|Path(name): Path<String>| async move {
+            if name != "tokio" {
+                return StatusCode::NOT_FOUND.into_response();
+            }
+            Json(json!({
+                "crate": {
+                    "name": "tokio",
+                    "description": "An event-driven, non-blocking I/O platform.\n",
+                    "repository": "https://github.com/tokio-rs/tokio",
+                    "homepage": "https://tokio.rs",
+                    "downloads": 123456,
+                    "max_version": "2.0.0-alpha.1",
+                    "max_stable_version": "1.38.0"
+                },
+                "versions": [
+                    {"num": "2.0.0-alpha.1", "license": "Apache-2.0"},
+                    {"num": "1.38.0", "license": "MIT"}
+                ]
+            }))
+            .into_response()
+        }),
+    ))
+    .await;
+
+    let metadata = crates_io.fetch("tokio").await.unwrap().unwrap();
+    assert_eq!(metadata.crate_name, "tokio");
+    assert_eq!(
+        metadata.description.as_deref(),
+        Some("An event-driven, non-blocking I/O platform.")
+    );
+    assert_eq!(
+        metadata.repository.as_deref(),
+        Some("https://github.com/tokio-rs/tokio")
+    );
+    assert_eq!(metadata.homepage.as_deref(), Some("https://tokio.rs"));
+    assert_eq!(metadata.latest_version.as_deref(), Some("1.38.0"));
+    assert_eq!(metadata.license.as_deref(), Some("MIT"));
+    assert_eq!(metadata.downloads, 123456);
+
+    assert_eq!(crates_io.fetch("no-such-crate").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn server_errors_are_reported_as_failures() {
+    let crates_io = mock_crates_io(Router::new().route(
+        "/api/v1/crates/{name}",
+        get(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+    ))
+    .await;
+
+    let error = crates_io.fetch("tokio").await.unwrap_err();
+    assert!(error.to_string().contains("503"), "{error}");
+}
+
+#[tokio::test]
+async fn versions_list_every_published_release_with_its_yank_status() {
+    let crates_io = mock_crates_io(Router::new().route(
+        "/api/v1/crates/{name}",
+        get(|Path(name): Path<String>| async move {
+            if name != "demo" {
+                return StatusCode::NOT_FOUND.into_response();
+            }
+            Json(json!({
+                "crate": {"name": "demo", "downloads": 1},
+                "versions": [
+                    {"num": "1.1.0", "yanked": true},
+                    {"num": "1.0.0", "yanked": false},
+                    {"num": "1.0.0-rc.1"},
+                    {"num": "not-a-version"}
+                ]
+            }))
+            .into_response()
+        }),
+    ))
+    .await;
+
+    let versions = crates_io.versions("demo").await.unwrap().unwrap();
+    let listed: Vec<_> = versions
+        .iter()
+        .map(|v| (v.version.to_string(), v.yanked))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            ("1.1.0".to_string(), true),
+            ("1.0.0".to_string(), false),
+            ("1.0.0-rc.1".to_string(), false),
+        ]
+    );
+
+    assert_eq!(crates_io.versions("no-such-crate").await.unwrap(), None);
+}
+
+#[test]
+fn metadata_older_than_a_day_is_stale() {
+    let now = Utc::now();
+    let fetched = |hours_ago| CrateMetadata {
+        crate_name: "tokio".to_string(),
+        description: None,
+        repository: None,
+        homepage: None,
+        license: None,
+        latest_version: None,
+        downloads: 0,
+        fetched_at: now - Duration::hours(hours_ago),
+    };
+
+    assert!(!crate_metadata::is_stale(&fetched(1), now));
+    assert!(crate_metadata::is_stale(&fetched(25), now));
+}
@@ -0,0 +1,197 @@
+use chrono::{Duration, Utc};
+use rustdocs_mcp_server::crate_status::{CrateStatus, CrateStatusFacts, JobFacts};
+
+fn job(id: i32, status: &str) -> JobFacts {
+    JobFacts {
+        id,
+        status: status.to_string(),
+        error_message: None,
+        docs_populated: None,
+    }
+}
+
+#[test]
+fn a_population_lifecycle_moves_through_every_state() {
+    let now = Utc::now();
+    let status = |facts: &CrateStatusFacts| CrateStatus::derive(facts, now, 90);
+
+    let mut facts = CrateStatusFacts::default();
+    assert_eq!(status(&facts), CrateStatus::NotConfigured);
+
+    facts.configured = true;
+    assert_eq!(status(&facts), CrateStatus::Queued { job_id: None });
+
+    facts.latest_job = Some(job(1, "pending"));
+    assert_eq!(status(&facts), CrateStatus::Queued { job_id: Some(1) });
+
+    facts.latest_job = Some(JobFacts {
+        docs_populated: Some(50),
+        ..job(1, "running")
+    });
+    assert_eq!(
+        status(&facts),
+        CrateStatus::Populating {
+            job_id: 1,
+            docs_populated: 50,
+            progress: None,
+        }
+    );
+
+    facts.latest_job = Some(JobFacts {
+        error_message: Some("docs.rs returned 503".to_string()),
+        ..job(1, "failed")
+    });
+    facts.consecutive_failures = 1;
+    assert_eq!(
+        status(&facts),
+        CrateStatus::Failed {
+            reason: "docs.rs returned 503".to_string(),
+            attempts: 1,
+            docs: 0,
+        }
+    );
+
+    facts.latest_job = Some(job(2, "failed"));
+    facts.consecutive_failures = 2;
+    assert_eq!(
+        status(&facts),
+        CrateStatus::Failed {
+            reason: "unknown error".to_string(),
+            attempts: 2,
+            docs: 0,
+        }
+    );
+
+    facts.latest_job = Some(job(3, "completed"));
+    facts.consecutive_failures = 0;
+    facts.docs = 400;
+    facts.version = Some("1.38.0".to_string());
+    facts.populated_at = Some(now - Duration::days(3));
+    assert_eq!(
+        status(&facts),
+        CrateStatus::Populated {
+            version: Some("1.38.0".to_string()),
+            docs: 400,
+        }
+    );
+
+    facts.populated_at = Some(now - Duration::days(120));
+    assert_eq!(
+        status(&facts),
+        CrateStatus::Stale {
+            version: Some("1.38.0".to_string()),
+            docs: 400,
+            age_days: 120,
+        }
+    );
+
+    // A refresh that fails keeps the old docs searchable but reports the failure
+    facts.latest_job = Some(job(4, "failed"));
+    facts.consecutive_failures = 1;
+    let refresh_failed = status(&facts);
+    assert_eq!(refresh_failed.name(), "failed");
+    assert_eq!(refresh_failed.docs(), 400);
+}
+
+#[test]
+fn progress_is_measured_against_an_established_baseline() {
+    let facts = CrateStatusFacts {
+        configured: true,
+        expected_docs: Some(200),
+        latest_job: Some(JobFacts {
+            docs_populated: Some(50),
+            ..job(7, "running")
+        }),
+        ..Default::default()
+    };
+
+    let status = CrateStatus::derive(&facts, Utc::now(), 90);
+
+    assert_eq!(
+        status,
+        CrateStatus::Populating {
+            job_id: 7,
+            docs_populated: 50,
+            progress: Some(0.25),
+        }
+    );
+    assert!(status.describe("tokio").contains("~25%"));
+}
+
+#[test]
+fn a_completed_population_without_documents_counts_as_failed() {
+    let facts = CrateStatusFacts {
+        configured: true,
+        latest_job: Some(job(9, "completed")),
+        ..Default::default()
+    };
+
+    let status = CrateStatus::derive(&facts, Utc::now(), 90);
+
+    assert_eq!(status.name(), "failed");
+    assert!(status
+        .describe("empty-crate")
+        .contains("stored no documents"));
+}
+
+#[test]
+fn a_time_boxed_population_reports_partial_coverage() {
+    let facts = CrateStatusFacts {
+        configured: true,
+        expected_docs: Some(1000),
+        latest_job: Some(job(4, "partial")),
+        docs: 800,
+        ..Default::default()
+    };
+
+    let status = CrateStatus::derive(&facts, Utc::now(), 90);
+
+    assert_eq!(
+        status,
+        CrateStatus::Partial {
+            job_id: 4,
+            docs: 800,
+            coverage: Some(0.8),
+        }
+    );
+    assert_eq!(status.docs(), 800);
+    let description = status.describe("huge-crate");
+    assert!(description.contains("~80%"), "{description}");
+    assert!(description.contains("resumes"), "{description}");
+
+    // A deadline that hit before anything was stored leaves nothing to search
+    let empty = CrateStatusFacts { docs: 0, ..facts };
+    assert_eq!(CrateStatus::derive(&empty, Utc::now(), 90).name(), "failed");
+}
+
+#[test]
+fn legacy_crates_with_docs_but_no_config_are_populated_and_never_stale_when_disabled() {
+    let facts = CrateStatusFacts {
+        docs: 10,
+        populated_at: Some(Utc::now() - Duration::days(1000)),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        CrateStatus::derive(&facts, Utc::now(), 0),
+        CrateStatus::Populated {
+            version: None,
+            docs: 10,
+        }
+    );
+}
+
+#[test]
+fn statuses_serialize_with_their_state_name() {
+    let status = CrateStatus::Failed {
+        reason: "boom".to_string(),
+        attempts: 3,
+        docs: 0,
+    };
+
+    let json = serde_json::to_value(&status).unwrap();
+
+    assert_eq!(json["state"], "failed");
+    assert_eq!(json["attempts"], 3);
+    assert!(status.describe("x").contains("after 3 attempts: boom"));
+}
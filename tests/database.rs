@@ -0,0 +1,1579 @@
+//! Tests against a real PostgreSQL + pgvector database. They only run when
+//! `MCPDOCS_TEST_DATABASE_URL` points at a migrated, disposable database.
+
+use futures::TryStreamExt;
+use ndarray::Array1;
+use rustdocs_mcp_server::{
+    crate_status::CrateStatus,
+    database::{
+        AuditFix, AuditSeverity, CrateConfig, CrateMetadata, Database, PrecomputedAnswer,
+        EMBEDDING_DIMENSION, REQUIRED_COLUMNS,
+    },
+    pipeline,
+    proxy_config::ProxyConfig,
+    query_log::{QueryLogEntry, QueryLogPolicy, QueryLogSettings},
+};
+use std::collections::{BTreeSet, HashMap};
+
+async fn test_database() -> Option<Database> {
+    let url = std::env::var("MCPDOCS_TEST_DATABASE_URL").ok()?;
+    Some(Database::connect(&url).await.unwrap())
+}
+
+#[tokio::test]
+async fn identical_similarity_results_are_ordered_by_path() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let crate_name = "ordering-tiebreak-test";
+    let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    let crate_id = db.upsert_crate(crate_name, None, None).await.unwrap();
+    // Inserted in reverse path order so insertion order can't explain the result
+    let rows: Vec<_> = ["b.html", "a.html"]
+        .iter()
+        .map(|path| {
+            (
+                format!("{crate_name}/latest/{path}"),
+                "Identical duplicated content.".to_string(),
+                embedding.clone(),
+                4,
+            )
+        })
+        .collect();
+    db.insert_embeddings_batch(crate_id, crate_name, &rows)
+        .await
+        .unwrap();
+
+    let mut orders = Vec::new();
+    for _ in 0..5 {
+        let results = db
+            .search_similar_docs_scored(crate_name, &embedding, 10)
+            .await
+            .unwrap();
+        orders.push(
+            results
+                .into_iter()
+                .map(|doc| doc.doc_path)
+                .collect::<Vec<_>>(),
+        );
+    }
+    db.delete_crate_embeddings(crate_name).await.unwrap();
+
+    for order in &orders {
+        assert_eq!(
+            order,
+            &[
+                format!("{crate_name}/latest/a.html"),
+                format!("{crate_name}/latest/b.html")
+            ]
+        );
+    }
+}
+
+#[tokio::test]
+async fn renaming_keeps_embeddings_and_refuses_taken_names() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let (old_name, new_name, taken) = ("rename-test-old", "rename-test-new", "rename-test-taken");
+    let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    for name in [old_name, taken] {
+        let crate_id = db.upsert_crate(name, None, None).await.unwrap();
+        let rows = vec![(
+            format!("{name}/latest/index.html"),
+            "Crate root documentation.".to_string(),
+            embedding.clone(),
+            3,
+        )];
+        db.insert_embeddings_batch(crate_id, name, &rows)
+            .await
+            .unwrap();
+    }
+
+    assert!(db.rename_crate(old_name, taken).await.is_err());
+    let report = db.rename_crate(old_name, new_name).await.unwrap();
+    let renamed = db.count_crate_documents(new_name).await.unwrap();
+    let left_behind = db.count_crate_documents(old_name).await.unwrap();
+    for name in [new_name, taken] {
+        db.delete_crate_embeddings(name).await.unwrap();
+    }
+
+    assert_eq!(report.embeddings, 1);
+    assert_eq!(report.crates, 1);
+    assert_eq!(renamed, 1);
+    assert_eq!(left_behind, 0);
+}
+
+#[tokio::test]
+async fn embeddings_presence_counts_every_requested_crate() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let populated = "presence-test-populated";
+    let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    let crate_id = db.upsert_crate(populated, None, None).await.unwrap();
+    let rows: Vec<_> = ["a.html", "b.html"]
+        .iter()
+        .map(|path| {
+            (
+                format!("{populated}/latest/{path}"),
+                "Crate documentation.".to_string(),
+                embedding.clone(),
+                3,
+            )
+        })
+        .collect();
+    db.insert_embeddings_batch(crate_id, populated, &rows)
+        .await
+        .unwrap();
+
+    // Many configured crates, only one of them populated
+    let mut names: Vec<String> = (0..80).map(|i| format!("presence-test-{i}")).collect();
+    names.push(populated.to_string());
+    let presence = db.get_embeddings_presence(&names).await.unwrap();
+    db.delete_crate_embeddings(populated).await.unwrap();
+
+    assert_eq!(presence.len(), names.len());
+    assert_eq!(presence[populated], 2);
+    assert_eq!(presence["presence-test-0"], 0);
+    assert!(db.get_embeddings_presence(&[]).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn crates_with_different_dimensions_are_stored_and_searched_side_by_side() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let (wide, narrow) = ("dimension-test-wide", "dimension-test-narrow");
+    let wide_embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    let narrow_embedding = Array1::from_vec(vec![0.1_f32, 0.2, 0.3, 0.4]);
+    for (name, embedding) in [(wide, &wide_embedding), (narrow, &narrow_embedding)] {
+        let crate_id = db.upsert_crate(name, None, None).await.unwrap();
+        let rows = vec![(
+            format!("{name}/latest/index.html"),
+            format!("{name} documentation."),
+            embedding.clone(),
+            3,
+        )];
+        db.insert_embeddings_batch(crate_id, name, &rows)
+            .await
+            .unwrap();
+    }
+
+    let wide_hits = db
+        .search_similar_docs_scored(wide, &wide_embedding, 5)
+        .await
+        .unwrap();
+    let narrow_hits = db
+        .search_similar_docs_scored(narrow, &narrow_embedding, 5)
+        .await
+        .unwrap();
+    // Querying with the other model's vector finds nothing instead of failing
+    let crossed = db
+        .search_similar_docs_scored(narrow, &wide_embedding, 5)
+        .await
+        .unwrap();
+    let dimensions = db.embedding_dimensions().await.unwrap();
+    let narrow_dimension = db.crate_embedding_dimension(narrow).await.unwrap();
+    let wide_dimension = db.crate_embedding_dimension(wide).await.unwrap();
+    let streamed: Vec<_> = db
+        .get_crate_documents_stream(narrow, narrow_dimension)
+        .try_collect()
+        .await
+        .unwrap();
+    let narrow_count = db.count_crate_documents(narrow).await.unwrap();
+    for name in [wide, narrow] {
+        db.delete_crate_embeddings(name).await.unwrap();
+    }
+    let narrow_after_delete = db
+        .search_similar_docs_scored(narrow, &narrow_embedding, 5)
+        .await
+        .unwrap();
+
+    assert_eq!(wide_hits.len(), 1);
+    assert_eq!(narrow_hits.len(), 1);
+    assert_eq!(
+        narrow_hits[0].doc_path,
+        format!("{narrow}/latest/index.html")
+    );
+    assert!(narrow_hits[0].similarity > 0.99);
+    assert!(crossed.is_empty());
+    assert!(dimensions.contains(&4));
+    assert_eq!(narrow_dimension, 4);
+    assert_eq!(wide_dimension, EMBEDDING_DIMENSION as usize);
+    assert_eq!(streamed.len(), 1);
+    assert_eq!(streamed[0].2.len(), 4);
+    assert_eq!(narrow_count, 1);
+    assert!(narrow_after_delete.is_empty());
+}
+
+#[tokio::test]
+async fn centroid_scores_docs_by_how_typical_they_are_of_the_crate() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let crate_name = "centroid-test";
+    let crate_id = db.upsert_crate(crate_name, None, None).await.unwrap();
+    let rows: Vec<_> = [
+        ("impl-a.html", vec![1.0_f32, 0.0, 0.0, 0.0]),
+        ("impl-b.html", vec![1.0, 0.0, 0.0, 0.0]),
+        ("specific.html", vec![0.0, 1.0, 0.0, 0.0]),
+    ]
+    .into_iter()
+    .map(|(path, embedding)| {
+        (
+            format!("{crate_name}/latest/{path}"),
+            format!("{path} documentation."),
+            Array1::from_vec(embedding),
+            3,
+        )
+    })
+    .collect();
+    db.insert_embeddings_batch(crate_id, crate_name, &rows)
+        .await
+        .unwrap();
+
+    let centroid = db.get_crate_centroid(crate_name, 4).await.unwrap().unwrap();
+    let paths: Vec<String> = rows.iter().map(|row| row.0.clone()).collect();
+    let commonness = db
+        .centroid_similarities(crate_name, &centroid, &paths)
+        .await
+        .unwrap();
+    let other_dimension = db
+        .get_crate_centroid(crate_name, EMBEDDING_DIMENSION as usize)
+        .await
+        .unwrap();
+    db.delete_crate_embeddings(crate_name).await.unwrap();
+
+    let expected = [2.0_f32 / 3.0, 1.0 / 3.0, 0.0, 0.0];
+    for (actual, expected) in centroid.iter().zip(expected) {
+        assert!((actual - expected).abs() < 1e-5, "{centroid:?}");
+    }
+    assert_eq!(commonness.len(), 3);
+    let generic = commonness[&format!("{crate_name}/latest/impl-a.html")];
+    let specific = commonness[&format!("{crate_name}/latest/specific.html")];
+    assert!(generic > specific, "{commonness:?}");
+    assert!(other_dimension.is_none());
+}
+
+#[tokio::test]
+async fn detected_languages_are_stored_per_document() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let crate_name = "languages-test";
+    let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    let crate_id = db.upsert_crate(crate_name, None, None).await.unwrap();
+    let paths = [
+        format!("{crate_name}/latest/index.html"),
+        format!("{crate_name}/latest/index.html#中文"),
+        format!("{crate_name}/latest/fn.run.html"),
+    ];
+    let rows: Vec<_> = paths
+        .iter()
+        .map(|path| {
+            (
+                path.clone(),
+                format!("{path} documentation."),
+                embedding.clone(),
+                3,
+            )
+        })
+        .collect();
+    db.insert_embeddings_batch(crate_id, crate_name, &rows)
+        .await
+        .unwrap();
+
+    db.set_document_languages(
+        crate_name,
+        &[(paths[0].clone(), "eng"), (paths[1].clone(), "cmn")],
+    )
+    .await
+    .unwrap();
+    let languages = db.get_document_languages(crate_name, &paths).await.unwrap();
+    db.delete_crate_embeddings(crate_name).await.unwrap();
+
+    // Documents without a detected language are left out
+    assert_eq!(
+        languages,
+        HashMap::from([
+            (paths[0].clone(), "eng".to_string()),
+            (paths[1].clone(), "cmn".to_string()),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn raw_pages_round_trip_compressed() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let crate_name = "raw-pages-test";
+    let page = "<html><body><div class=\"docblock\"><p>Stored page.</p></div></body></html>";
+    db.upsert_raw_page(crate_name, "raw-pages-test/latest/b.html", "old")
+        .await
+        .unwrap();
+    db.upsert_raw_page(crate_name, "raw-pages-test/latest/b.html", page)
+        .await
+        .unwrap();
+    db.upsert_raw_page(crate_name, "raw-pages-test/latest/a.html", "")
+        .await
+        .unwrap();
+
+    let count = db.count_raw_pages(crate_name).await.unwrap();
+    let pages: Vec<(String, String)> = db
+        .get_raw_pages_stream(crate_name)
+        .try_collect()
+        .await
+        .unwrap();
+    let deleted = db.delete_raw_pages(crate_name).await.unwrap();
+
+    assert_eq!(count, 2);
+    assert_eq!(
+        pages,
+        [
+            ("raw-pages-test/latest/a.html".to_string(), String::new()),
+            ("raw-pages-test/latest/b.html".to_string(), page.to_string()),
+        ]
+    );
+    assert_eq!(deleted, 2);
+}
+
+#[tokio::test]
+async fn stored_vectors_are_returned_for_requested_paths() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let crate_name = "raw-vectors-test";
+    let crate_id = db.upsert_crate(crate_name, None, None).await.unwrap();
+    let rows: Vec<_> = [
+        ("near.html", vec![1.0_f32, 0.0, 0.0]),
+        ("far.html", vec![0.0, 0.0, 1.0]),
+    ]
+    .into_iter()
+    .map(|(path, embedding)| {
+        (
+            format!("{crate_name}/latest/{path}"),
+            format!("{path} documentation."),
+            Array1::from_vec(embedding),
+            3,
+        )
+    })
+    .collect();
+    db.insert_embeddings_batch(crate_id, crate_name, &rows)
+        .await
+        .unwrap();
+
+    // A raw query vector searches the table of its dimension
+    let query = Array1::from_vec(vec![0.9_f32, 0.1, 0.0]);
+    let hits = db
+        .search_similar_docs_scored(crate_name, &query, 10)
+        .await
+        .unwrap();
+    let paths: Vec<String> = hits.iter().map(|doc| doc.doc_path.clone()).collect();
+    let vectors = db
+        .get_doc_embeddings(crate_name, 3, &paths[..1])
+        .await
+        .unwrap();
+    let dimension = db.crate_embedding_dimension(crate_name).await.unwrap();
+    db.delete_crate_embeddings(crate_name).await.unwrap();
+
+    assert_eq!(
+        paths,
+        [
+            format!("{crate_name}/latest/near.html"),
+            format!("{crate_name}/latest/far.html")
+        ]
+    );
+    assert_eq!(dimension, 3);
+    assert_eq!(vectors.len(), 1);
+    assert_eq!(vectors[&paths[0]], vec![1.0, 0.0, 0.0]);
+}
+
+#[tokio::test]
+async fn large_inserts_are_committed_in_chunks_with_stats_at_the_end() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let db = db.with_insert_chunk_size(2);
+    let crate_name = "insert-chunk-test";
+    let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    let crate_id = db.upsert_crate(crate_name, None, None).await.unwrap();
+    let rows: Vec<_> = (0..5)
+        .map(|i| {
+            (
+                format!("{crate_name}/latest/page{i}.html"),
+                format!("Page {i}."),
+                embedding.clone(),
+                2,
+            )
+        })
+        .collect();
+
+    db.insert_embeddings_batch(crate_id, crate_name, &rows)
+        .await
+        .unwrap();
+    let stored = db.count_crate_documents(crate_name).await.unwrap();
+    let stats = db.get_crate_stats().await.unwrap();
+    db.delete_crate_embeddings(crate_name).await.unwrap();
+
+    let stat = stats.iter().find(|s| s.name == crate_name).unwrap();
+    assert_eq!(stored, 5);
+    assert_eq!(stat.total_docs, 5);
+    assert_eq!(stat.total_tokens, 10);
+}
+
+#[tokio::test]
+async fn find_symbol_matches_substrings_and_honors_case() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let crate_name = "find-symbol-test";
+    let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    let crate_id = db.upsert_crate(crate_name, None, None).await.unwrap();
+    let rows: Vec<_> = [
+        (
+            "trait.Service.html",
+            "fn poll_ready(&mut self)\nChecks readiness.",
+        ),
+        ("fn.other.html", "Unrelated 100% documentation_text."),
+    ]
+    .iter()
+    .map(|(path, content)| {
+        (
+            format!("{crate_name}/latest/{path}"),
+            content.to_string(),
+            embedding.clone(),
+            4,
+        )
+    })
+    .collect();
+    db.insert_embeddings_batch(crate_id, crate_name, &rows)
+        .await
+        .unwrap();
+
+    let partial = db
+        .find_symbol(Some(crate_name), "POLL_REA", false, 10)
+        .await
+        .unwrap();
+    let exact_case = db
+        .find_symbol(Some(crate_name), "POLL_REA", true, 10)
+        .await
+        .unwrap();
+    let by_path = db
+        .find_symbol(Some(crate_name), "Service", true, 10)
+        .await
+        .unwrap();
+    // LIKE wildcards in the identifier match literally
+    let wildcard = db
+        .find_symbol(Some(crate_name), "100%", false, 10)
+        .await
+        .unwrap();
+    let underscore = db
+        .find_symbol(Some(crate_name), "poll_r", false, 10)
+        .await
+        .unwrap();
+    db.delete_crate_embeddings(crate_name).await.unwrap();
+
+    assert_eq!(partial.len(), 1);
+    assert!(partial[0].doc_path.ends_with("trait.Service.html"));
+    assert!(exact_case.is_empty());
+    assert_eq!(by_path.len(), 1);
+    assert_eq!(wildcard.len(), 1);
+    assert!(wildcard[0].doc_path.ends_with("fn.other.html"));
+    assert_eq!(underscore.len(), 1);
+}
+
+#[tokio::test]
+async fn multibyte_content_round_trips_unchanged() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let crate_name = "unicode-round-trip-test";
+    let content = "🦀 非同期ランタイム 👩‍👩‍👧‍👦 Café e\u{301} a\u{308}\u{323} 비동기";
+    let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    let crate_id = db.upsert_crate(crate_name, None, None).await.unwrap();
+    let rows = vec![(
+        format!("{crate_name}/latest/struct.Runtime.html"),
+        content.to_string(),
+        embedding,
+        30,
+    )];
+    db.insert_embeddings_batch(crate_id, crate_name, &rows)
+        .await
+        .unwrap();
+
+    let found = db
+        .find_symbol(Some(crate_name), "非同期", false, 10)
+        .await
+        .unwrap();
+    db.delete_crate_embeddings(crate_name).await.unwrap();
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].content, content);
+}
+
+#[tokio::test]
+async fn query_log_rows_keep_their_policy_and_expire_after_retention() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let crate_name = "query-log-retention-test";
+    let now = chrono::Utc::now();
+    let policies = [
+        QueryLogPolicy::StoreFull,
+        QueryLogPolicy::StoreHashed,
+        QueryLogPolicy::StoreNone,
+    ];
+    // One row per policy inside the window, and one past it
+    for (i, (policy, age_days)) in policies
+        .iter()
+        .map(|p| (*p, 1))
+        .chain([(QueryLogPolicy::StoreFull, 45)])
+        .enumerate()
+    {
+        let settings = QueryLogSettings::new(Some(policy), Some(b"query-log-test-key"), 30);
+        let stored = settings.store("How do I spawn a task?");
+        db.record_query_log(&QueryLogEntry {
+            query_id: format!("q-{i}"),
+            crate_name: crate_name.to_string(),
+            policy: policy.as_str().to_string(),
+            question: stored.question,
+            question_hash: stored.question_hash,
+            result_count: Some(3),
+            top_similarity: Some(0.8),
+            cached: false,
+            latency_ms: 12,
+            created_at: now - chrono::Duration::days(age_days),
+        })
+        .await
+        .unwrap();
+    }
+
+    let cutoff = QueryLogSettings::new(None, None, 30)
+        .retention_cutoff(now)
+        .unwrap();
+    assert!(db.delete_query_log_before(cutoff).await.unwrap() >= 1);
+    let rows = db.get_query_log(crate_name).await.unwrap();
+    db.delete_query_log_before(now + chrono::Duration::days(1))
+        .await
+        .unwrap();
+
+    assert_eq!(rows.len(), 3);
+    assert!(rows.iter().all(|row| row.created_at >= cutoff));
+    let by_policy: HashMap<&str, &QueryLogEntry> =
+        rows.iter().map(|row| (row.policy.as_str(), row)).collect();
+    let full = by_policy["store_full"];
+    assert_eq!(full.question.as_deref(), Some("How do I spawn a task?"));
+    assert_eq!(full.question_hash, None);
+    let hashed = by_policy["store_hashed"];
+    assert_eq!(hashed.question, None);
+    assert_eq!(hashed.question_hash.as_ref().map(String::len), Some(64));
+    let none = by_policy["store_none"];
+    assert_eq!(
+        (none.question.as_ref(), none.question_hash.as_ref()),
+        (None, None)
+    );
+    assert_eq!(none.result_count, Some(3));
+}
+
+fn unpopulated_config(name: &str) -> CrateConfig {
+    CrateConfig {
+        expected_docs: 10,
+        auto_expected_docs: false,
+        ..CrateConfig::new(name, "latest")
+    }
+}
+
+#[tokio::test]
+async fn audit_reports_seeded_inconsistencies() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let (stale_stats, empty_config) = ("audit-test-stale-stats", "audit-test-empty-config");
+
+    // A crate nobody configured, with a row deleted behind the stats' back
+    let crate_id = db.upsert_crate(stale_stats, None, None).await.unwrap();
+    let rows: Vec<_> = ["a.html", "b.html"]
+        .iter()
+        .map(|path| {
+            (
+                format!("{stale_stats}/latest/{path}"),
+                "Crate documentation.".to_string(),
+                Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32),
+                3,
+            )
+        })
+        .collect();
+    db.insert_embeddings_batch(crate_id, stale_stats, &rows)
+        .await
+        .unwrap();
+    db.delete_documents(stale_stats, &[rows[0].0.clone()])
+        .await
+        .unwrap();
+    // A config marked populated that never stored anything
+    let config = db
+        .upsert_crate_config(&unpopulated_config(empty_config))
+        .await
+        .unwrap();
+    db.record_crate_population(config.id, Some("1.0.0"), None)
+        .await
+        .unwrap();
+
+    let findings = db.audit_consistency().await.unwrap();
+    let found = |check: &str, name: &str| {
+        findings
+            .iter()
+            .find(|f| f.check == check && f.crate_name == name)
+            .cloned()
+    };
+    let stats = found("stats_mismatch", stale_stats);
+    let empty = found("populated_without_embeddings", empty_config);
+    let unconfigured = found("unconfigured_crate", stale_stats);
+
+    if let Some(stats) = &stats {
+        assert!(db.apply_audit_fix(stats).await.unwrap());
+    }
+    let after_fix = db.audit_consistency().await.unwrap();
+
+    db.delete_crate_embeddings(stale_stats).await.unwrap();
+    db.delete_crate_config(empty_config, "latest", None)
+        .await
+        .unwrap();
+
+    let stats = stats.expect("stats mismatch not reported");
+    assert_eq!(stats.severity, AuditSeverity::Warning);
+    assert_eq!(stats.auto_fix, Some(AuditFix::RecomputeStats { crate_id }));
+    let empty = empty.expect("empty populated config not reported");
+    assert_eq!(empty.severity, AuditSeverity::Error);
+    assert!(empty.auto_fix.is_none());
+    assert!(empty.fix_sql.contains(&config.id.to_string()));
+    assert_eq!(
+        unconfigured
+            .expect("unconfigured crate not reported")
+            .severity,
+        AuditSeverity::Info
+    );
+    assert!(!after_fix
+        .iter()
+        .any(|f| f.check == "stats_mismatch" && f.crate_name == stale_stats));
+}
+
+#[tokio::test]
+async fn unpopulated_configs_are_the_ones_without_stored_docs() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let (never, empty, done) = (
+        "pending-test-never-populated",
+        "pending-test-populated-empty",
+        "pending-test-populated",
+    );
+
+    db.upsert_crate_config(&unpopulated_config(never))
+        .await
+        .unwrap();
+    let empty_config = db
+        .upsert_crate_config(&unpopulated_config(empty))
+        .await
+        .unwrap();
+    db.record_crate_population(empty_config.id, Some("1.0.0"), None)
+        .await
+        .unwrap();
+    let done_config = db
+        .upsert_crate_config(&unpopulated_config(done))
+        .await
+        .unwrap();
+    let crate_id = db.upsert_crate(done, None, None).await.unwrap();
+    let rows = vec![(
+        format!("{done}/latest/index.html"),
+        "Crate documentation.".to_string(),
+        Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32),
+        3,
+    )];
+    db.insert_embeddings_batch(crate_id, done, &rows)
+        .await
+        .unwrap();
+    db.record_crate_population(done_config.id, Some("1.0.0"), None)
+        .await
+        .unwrap();
+
+    let pending: Vec<String> = db
+        .get_unpopulated_crate_configs()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|c| c.name)
+        .filter(|name| name.starts_with("pending-test-"))
+        .collect();
+
+    db.delete_crate_embeddings(done).await.unwrap();
+    for name in [never, empty, done] {
+        db.delete_crate_config(name, "latest", None).await.unwrap();
+    }
+
+    assert_eq!(pending, vec![empty.to_string(), never.to_string()]);
+}
+
+#[tokio::test]
+async fn migrated_pins_and_page_limits_round_trip_through_the_database() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let config = ProxyConfig::parse(
+        r#"{"rustdocs_binary_path": "", "crates": [
+            {"name": "migrate-test-pinned", "features": ["full"], "enabled": true, "version": "1.35.0", "max_pages": 300}
+        ]}"#,
+    )
+    .unwrap();
+    let old = &config.crates[0];
+
+    let saved = db
+        .upsert_crate_config(&old.to_crate_config(None).unwrap())
+        .await
+        .unwrap();
+    let mut changed = old.clone();
+    changed.max_pages = Some(400);
+    let existing = db
+        .get_crate_config(&old.name, "1.35.0", None)
+        .await
+        .unwrap();
+    let updated = db
+        .upsert_crate_config(&changed.to_crate_config(existing).unwrap())
+        .await
+        .unwrap();
+    db.delete_crate_config(&old.name, "1.35.0", None)
+        .await
+        .unwrap();
+
+    assert_eq!(saved.version_spec, "1.35.0");
+    assert_eq!(saved.max_pages, Some(300));
+    assert_eq!(saved.features, vec!["full"]);
+    assert_eq!(updated.id, saved.id);
+    assert_eq!(updated.max_pages, Some(400));
+}
+
+#[tokio::test]
+async fn crate_metadata_is_replaced_on_refresh() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let mut metadata = CrateMetadata {
+        crate_name: "metadata-cache-test".to_string(),
+        description: Some("Old description".to_string()),
+        repository: Some("https://example.com/repo".to_string()),
+        homepage: None,
+        license: Some("MIT".to_string()),
+        latest_version: Some("0.1.0".to_string()),
+        downloads: 10,
+        fetched_at: chrono::Utc::now() - chrono::Duration::days(2),
+    };
+    db.upsert_crate_metadata(&metadata).await.unwrap();
+
+    metadata.description = Some("New description".to_string());
+    metadata.latest_version = Some("0.2.0".to_string());
+    metadata.downloads = 20;
+    metadata.fetched_at = chrono::Utc::now();
+    db.upsert_crate_metadata(&metadata).await.unwrap();
+
+    let cached = db
+        .get_crate_metadata("metadata-cache-test")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(cached.description.as_deref(), Some("New description"));
+    assert_eq!(cached.latest_version.as_deref(), Some("0.2.0"));
+    assert_eq!(cached.downloads, 20);
+    assert_eq!(db.get_crate_metadata("never-fetched").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn prewarm_searches_each_crate_and_caps_connections_at_the_pool_size() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let crate_name = "prewarm-test";
+    let crate_id = db.upsert_crate(crate_name, None, None).await.unwrap();
+    let rows = vec![(
+        format!("{crate_name}/latest/index.html"),
+        "Prewarm test document.".to_string(),
+        Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32),
+        4,
+    )];
+    db.insert_embeddings_batch(crate_id, crate_name, &rows)
+        .await
+        .unwrap();
+
+    let report = db.prewarm(&[crate_name.to_string()], 1000).await.unwrap();
+
+    assert_eq!(report.crates, 1);
+    assert!(report.failed.is_empty());
+    assert!(report.connections < 1000);
+}
+
+#[tokio::test]
+async fn crate_status_follows_a_simulated_population() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let name = "status-test-lifecycle";
+    let state = || async { db.get_crate_status(name, 90).await.unwrap() };
+
+    assert_eq!(state().await, CrateStatus::NotConfigured);
+
+    let config = db
+        .upsert_crate_config(&unpopulated_config(name))
+        .await
+        .unwrap();
+    assert_eq!(state().await, CrateStatus::Queued { job_id: None });
+
+    let first = db.create_population_job(config.id).await.unwrap();
+    db.update_population_job(first, "pending", None, None)
+        .await
+        .unwrap();
+    assert_eq!(state().await.name(), "queued");
+    db.update_population_job(first, "running", None, None)
+        .await
+        .unwrap();
+    assert_eq!(state().await.name(), "populating");
+    db.update_population_job(first, "failed", Some("docs.rs returned 503"), None)
+        .await
+        .unwrap();
+    let second = db.create_population_job(config.id).await.unwrap();
+    db.update_population_job(second, "failed", Some("docs.rs returned 503"), None)
+        .await
+        .unwrap();
+    let failed = state().await;
+
+    let third = db.create_population_job(config.id).await.unwrap();
+    let crate_id = db.upsert_crate(name, Some("1.0.0"), None).await.unwrap();
+    let rows = vec![(
+        format!("{name}/latest/index.html"),
+        "Crate documentation.".to_string(),
+        Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32),
+        3,
+    )];
+    db.insert_embeddings_batch(crate_id, name, &rows)
+        .await
+        .unwrap();
+    db.update_population_job(third, "completed", None, Some(1))
+        .await
+        .unwrap();
+    db.record_crate_population(config.id, Some("1.0.0"), None)
+        .await
+        .unwrap();
+    let populated = state().await;
+
+    db.delete_crate_embeddings(name).await.unwrap();
+    db.delete_crate_config(name, "latest", None).await.unwrap();
+
+    assert_eq!(
+        failed,
+        CrateStatus::Failed {
+            reason: "docs.rs returned 503".to_string(),
+            attempts: 2,
+            docs: 0,
+        }
+    );
+    assert_eq!(
+        populated,
+        CrateStatus::Populated {
+            version: Some("1.0.0".to_string()),
+            docs: 1,
+        }
+    );
+}
+
+#[tokio::test]
+async fn self_test_searches_under_the_queried_name() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let stored = "self_test_variant";
+    let crate_id = db.upsert_crate(stored, None, None).await.unwrap();
+    let rows = vec![(
+        format!("{stored}/latest/index.html"),
+        "Crate documentation.".to_string(),
+        Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32),
+        3,
+    )];
+    db.insert_embeddings_batch(crate_id, stored, &rows)
+        .await
+        .unwrap();
+
+    let found = pipeline::self_test(&db, stored, None).await.unwrap();
+    let misspelled = pipeline::self_test(&db, "Self-Test-Variant", None)
+        .await
+        .unwrap();
+    db.delete_crate_embeddings(stored).await.unwrap();
+
+    assert!(found.passed, "{found:?}");
+    assert_eq!(found.hits, 1);
+    assert!(!misspelled.passed);
+    assert!(misspelled.error.unwrap().contains(stored));
+}
+
+#[tokio::test]
+async fn resubmitted_feedback_replaces_the_earlier_vote() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    // Feedback rows outlive the test, so each run votes in a crate of its own
+    let crate_name = &format!("feedback_votes_{:08x}", rand::random::<u32>());
+    let page = "feedback_votes/latest/feedback_votes/fn.run.html";
+    db.record_retrieval_feedback("q-1", crate_name, page, true)
+        .await
+        .unwrap();
+    db.record_retrieval_feedback("q-1", crate_name, page, false)
+        .await
+        .unwrap();
+    db.record_retrieval_feedback("q-2", crate_name, page, false)
+        .await
+        .unwrap();
+
+    let counts = db.get_feedback_counts(crate_name).await.unwrap();
+    assert_eq!(counts.get(page), Some(&(0, 2)));
+}
+
+fn generation_rows(
+    name: &str,
+    paths: &[&str],
+    content: &str,
+) -> Vec<(String, String, Array1<f32>, i32)> {
+    paths
+        .iter()
+        .map(|path| {
+            (
+                format!("{name}/latest/{path}"),
+                format!("{content} {path}"),
+                Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32),
+                4,
+            )
+        })
+        .collect()
+}
+
+/// Store `paths` as a populated crate and return the config the next population sees
+async fn populated_generation(db: &Database, name: &str, paths: &[&str]) -> CrateConfig {
+    let crate_id = db.upsert_crate(name, Some("1.0.0"), None).await.unwrap();
+    db.insert_embeddings_batch(crate_id, name, &generation_rows(name, paths, "old"))
+        .await
+        .unwrap();
+    let config = db
+        .upsert_crate_config(&unpopulated_config(name))
+        .await
+        .unwrap();
+    db.record_crate_population(config.id, Some("1.0.0"), None)
+        .await
+        .unwrap();
+    db.get_crate_config(name, "latest", None)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn queries_see_one_whole_generation_during_a_repopulation() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let name = "generation-swap-test";
+    db.delete_crate_embeddings(name).await.unwrap();
+    let config = populated_generation(&db, name, &["a.html", "b.html", "removed.html"]).await;
+    let crate_id = db.upsert_crate(name, Some("1.0.0"), None).await.unwrap();
+
+    let staging = pipeline::Staging::plan(&db, &config).await.unwrap();
+    assert!(staging.is_staged());
+    assert_eq!(staging.generation, staging.live.generation + 1);
+
+    let done = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let reader = {
+        let db = db.clone();
+        let done = done.clone();
+        tokio::spawn(async move {
+            let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+            let mut seen = Vec::new();
+            loop {
+                let finished = done.load(std::sync::atomic::Ordering::SeqCst);
+                let mut contents: Vec<String> = db
+                    .search_similar_docs(name, &embedding, 10)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|(_, content, _)| content)
+                    .collect();
+                contents.sort();
+                seen.push(contents);
+                if finished {
+                    return seen;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        })
+    };
+
+    // Written a batch at a time, like a crawl, with queries running in between
+    for path in ["a.html", "b.html"] {
+        tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+        db.insert_staged_embeddings_batch(
+            crate_id,
+            name,
+            staging.generation,
+            &generation_rows(name, &[path], "new"),
+        )
+        .await
+        .unwrap();
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+    staging.promote(&db, name, Some("2.0.0")).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+    staging.collect_garbage(&db, name).await;
+    done.store(true, std::sync::atomic::Ordering::SeqCst);
+    let seen = reader.await.unwrap();
+
+    let live = db.get_live_generation(name, None).await.unwrap();
+    let documents = db.count_crate_documents(name).await.unwrap();
+    let stale = db
+        .get_generation_doc_paths(name, None, staging.live.generation)
+        .await
+        .unwrap();
+    db.delete_crate_embeddings(name).await.unwrap();
+    db.delete_crate_config(name, "latest", None).await.unwrap();
+
+    let old = vec!["old a.html", "old b.html", "old removed.html"];
+    let new = vec!["new a.html", "new b.html"];
+    assert_eq!(seen.first().unwrap(), &old);
+    assert_eq!(seen.last().unwrap(), &new);
+    assert!(
+        seen.iter()
+            .all(|contents| *contents == old || *contents == new),
+        "a query saw a mix of generations: {seen:?}"
+    );
+    assert_eq!(live.generation, staging.generation);
+    assert_eq!(live.version.as_deref(), Some("2.0.0"));
+    assert_eq!(documents, 2);
+    assert!(stale.is_empty());
+}
+
+#[tokio::test]
+async fn failed_repopulations_leave_the_live_generation_untouched() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let name = "generation-failure-test";
+    db.delete_crate_embeddings(name).await.unwrap();
+    let config = populated_generation(&db, name, &["a.html", "b.html"]).await;
+    let crate_id = db.upsert_crate(name, Some("1.0.0"), None).await.unwrap();
+    let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    let contents = || async {
+        let mut contents: Vec<String> = db
+            .search_similar_docs(name, &embedding, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, content, _)| content)
+            .collect();
+        contents.sort();
+        contents
+    };
+
+    // A crawl that dies halfway through its batches
+    let staging = pipeline::Staging::plan(&db, &config).await.unwrap();
+    db.insert_staged_embeddings_batch(
+        crate_id,
+        name,
+        staging.generation,
+        &generation_rows(name, &["a.html"], "new"),
+    )
+    .await
+    .unwrap();
+    let during_failure = contents().await;
+    let live_after_failure = db.get_live_generation(name, None).await.unwrap();
+
+    // The next attempt starts over rather than building on the leftovers
+    let retry = pipeline::Staging::plan(&db, &config).await.unwrap();
+    let leftovers = retry.stored_paths(&db, name).await.unwrap();
+
+    // A self-test failure after the swap puts the old generation back
+    retry.promote(&db, name, Some("2.0.0")).await.unwrap();
+    retry.revert(&db, name).await.unwrap();
+    let reverted = contents().await;
+    let live_after_revert = db.get_live_generation(name, None).await.unwrap();
+    let documents = db.count_crate_documents(name).await.unwrap();
+
+    db.delete_crate_embeddings(name).await.unwrap();
+    db.delete_crate_config(name, "latest", None).await.unwrap();
+
+    let old = vec!["old a.html", "old b.html"];
+    assert_eq!(during_failure, old);
+    assert_eq!(live_after_failure, staging.live);
+    assert_eq!(retry.generation, staging.generation);
+    assert!(leftovers.is_empty());
+    assert_eq!(reverted, old);
+    assert_eq!(live_after_revert, staging.live);
+    assert_eq!(documents, 2);
+}
+
+#[tokio::test]
+async fn every_target_of_a_crate_keeps_its_own_row_and_generations() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let name = "multi-target-test";
+    let windows = "x86_64-pc-windows-msvc";
+    let windows_path = format!("{windows}/a.html");
+    db.delete_crate_embeddings(name).await.unwrap();
+    let default_config = populated_generation(&db, name, &["a.html"]).await;
+    let windows_id = db
+        .upsert_crate(name, Some("1.0.0"), Some(windows))
+        .await
+        .unwrap();
+    db.insert_embeddings_batch(
+        windows_id,
+        name,
+        &generation_rows(name, &[&windows_path], "old"),
+    )
+    .await
+    .unwrap();
+    let windows_config = db
+        .upsert_crate_config(&CrateConfig {
+            target: Some(windows.to_string()),
+            ..unpopulated_config(name)
+        })
+        .await
+        .unwrap();
+    db.record_crate_population(windows_config.id, Some("1.0.0"), None)
+        .await
+        .unwrap();
+    let windows_config = db
+        .get_crate_config(name, "latest", Some(windows))
+        .await
+        .unwrap()
+        .unwrap();
+    let default_id = db.upsert_crate(name, None, None).await.unwrap();
+    let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    let contents = || async {
+        let mut contents: Vec<String> = db
+            .search_similar_docs(name, &embedding, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, content, _)| content)
+            .collect();
+        contents.sort();
+        contents
+    };
+    let before = contents().await;
+
+    // Repopulating one target swaps its generation alone
+    let staging = pipeline::Staging::plan(&db, &windows_config).await.unwrap();
+    db.insert_staged_embeddings_batch(
+        windows_id,
+        name,
+        staging.generation,
+        &generation_rows(name, &[&windows_path], "new"),
+    )
+    .await
+    .unwrap();
+    staging.promote(&db, name, Some("2.0.0")).await.unwrap();
+    staging.collect_garbage(&db, name).await;
+    let after = contents().await;
+    let windows_only: Vec<String> = db
+        .search_similar_docs_tuned(name, &embedding, 10, None, Some(windows), None)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|doc| doc.doc_path)
+        .collect();
+    let default_record = db.get_crate_record(name, None).await.unwrap().unwrap();
+    let windows_record = db
+        .get_crate_record(name, Some(windows))
+        .await
+        .unwrap()
+        .unwrap();
+    let default_live = db.get_live_generation(name, None).await.unwrap();
+    let default_plan = pipeline::Staging::plan(&db, &default_config).await.unwrap();
+
+    db.delete_crate_embeddings(name).await.unwrap();
+    db.delete_crate_config(name, "latest", None).await.unwrap();
+    db.delete_crate_config(name, "latest", Some(windows))
+        .await
+        .unwrap();
+
+    assert_ne!(default_id, windows_id);
+    let old = format!("old {windows_path}");
+    assert_eq!(before, vec!["old a.html", old.as_str()]);
+    let new = format!("new {windows_path}");
+    assert_eq!(after, vec!["old a.html", new.as_str()]);
+    assert_eq!(windows_only, vec![windows_path.clone()]);
+    assert!(staging.is_staged());
+    assert_eq!(default_record.target, None);
+    assert_eq!(default_record.version.as_deref(), Some("1.0.0"));
+    assert_eq!(windows_record.target.as_deref(), Some(windows));
+    assert_eq!(windows_record.version.as_deref(), Some("2.0.0"));
+    assert_eq!(default_live.generation, staging.live.generation);
+    assert_eq!(default_plan.generation, default_live.generation + 1);
+}
+
+#[tokio::test]
+async fn targets_storing_the_same_path_keep_separate_rows() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let name = "multi-target-overlap-test";
+    let windows = "x86_64-pc-windows-msvc";
+    // The README fallback has no target segment
+    let readme = format!("crate/{name}/1.0.0");
+    db.delete_crate_embeddings(name).await.unwrap();
+    let default_id = db.upsert_crate(name, Some("1.0.0"), None).await.unwrap();
+    let windows_id = db
+        .upsert_crate(name, Some("1.0.0"), Some(windows))
+        .await
+        .unwrap();
+    for (crate_id, content) in [(default_id, "default"), (windows_id, "windows")] {
+        db.insert_embeddings_batch(crate_id, name, &generation_rows(name, &[&readme], content))
+            .await
+            .unwrap();
+    }
+
+    let default_docs = db.count_target_documents(name, None).await.unwrap();
+    let windows_docs = db
+        .count_target_documents(name, Some(windows))
+        .await
+        .unwrap();
+    let dimension = db.crate_embedding_dimension(name).await.unwrap();
+    let mut stored: Vec<(Option<String>, String)> = db
+        .get_stored_documents_stream(name, dimension)
+        .map_ok(|doc| (doc.target, doc.content))
+        .try_collect()
+        .await
+        .unwrap();
+    stored.sort();
+    let sample = db
+        .sample_crate_embedding(name, Some(windows))
+        .await
+        .unwrap()
+        .map(|(doc_path, _)| doc_path);
+
+    // Removing one target leaves the other's copy alone
+    let removed = db.delete_crate_target(name, Some(windows)).await.unwrap();
+    let remaining = db.count_crate_documents(name).await.unwrap();
+    let windows_record = db.get_crate_record(name, Some(windows)).await.unwrap();
+    db.delete_crate_embeddings(name).await.unwrap();
+
+    assert_ne!(default_id, windows_id);
+    assert_eq!((default_docs, windows_docs), (1, 1));
+    assert_eq!(
+        stored,
+        vec![
+            (None, format!("default {readme}")),
+            (Some(windows.to_string()), format!("windows {readme}")),
+        ]
+    );
+    assert_eq!(sample, Some(readme));
+    assert_eq!(removed, 1);
+    assert_eq!(remaining, 1);
+    assert_eq!(windows_record, None);
+}
+
+#[tokio::test]
+async fn searches_can_be_limited_to_one_docblock_section() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let name = "section-filter-test";
+    let crate_id = db.upsert_crate(name, None, None).await.unwrap();
+    let rows = generation_rows(
+        name,
+        &["push.html", "push.html#panics", "pop.html#safety"],
+        "doc",
+    );
+    db.insert_embeddings_batch(crate_id, name, &rows)
+        .await
+        .unwrap();
+    db.set_document_sections(
+        name,
+        None,
+        &[
+            (
+                format!("{name}/latest/push.html#panics"),
+                "Panics".to_string(),
+            ),
+            (
+                format!("{name}/latest/pop.html#safety"),
+                "Safety".to_string(),
+            ),
+        ],
+    )
+    .await
+    .unwrap();
+    let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    let search = |section: Option<&'static str>| {
+        let (db, embedding) = (&db, &embedding);
+        async move {
+            db.search_similar_docs_tuned(name, embedding, 10, None, None, section)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|doc| doc.doc_path)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let all = search(None).await;
+    let panics = search(Some("panics")).await;
+    let missing = search(Some("Errors")).await;
+    db.delete_crate_embeddings(name).await.unwrap();
+
+    assert_eq!(all.len(), 3);
+    assert_eq!(panics, vec![format!("{name}/latest/push.html#panics")]);
+    assert!(missing.is_empty());
+}
+
+#[tokio::test]
+async fn precomputed_answers_serve_live_chunks_until_a_new_generation_goes_live() {
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let name = "precomputed-answers-test";
+    let crate_id = db.upsert_crate(name, None, None).await.unwrap();
+    db.insert_embeddings_batch(
+        crate_id,
+        name,
+        &generation_rows(name, &["a", "b", "c"], "doc"),
+    )
+    .await
+    .unwrap();
+    let answer = PrecomputedAnswer {
+        question_key: "getting_started".to_string(),
+        question: "How do I get started with this crate?".to_string(),
+        model: "test-model".to_string(),
+        embedding: vec![0.6, 0.8, 0.0],
+        results: vec![
+            (format!("{name}/latest/c"), 0.125),
+            (format!("{name}/latest/gone"), 0.25),
+            (format!("{name}/latest/a"), 0.5),
+        ],
+    };
+    db.replace_precomputed_answers(name, std::slice::from_ref(&answer))
+        .await
+        .unwrap();
+
+    let other_model = db
+        .get_precomputed_answers(name, "other-model")
+        .await
+        .unwrap();
+    let stored = db
+        .get_precomputed_answers(name, "test-model")
+        .await
+        .unwrap();
+    let results = db.get_precomputed_results(name, &stored[0]).await.unwrap();
+    let live = db.get_live_generation(name, None).await.unwrap();
+    db.promote_generation(name, None, live.generation, None)
+        .await
+        .unwrap();
+    let after_promotion = db
+        .get_precomputed_answers(name, "test-model")
+        .await
+        .unwrap();
+    db.delete_crate_embeddings(name).await.unwrap();
+
+    assert!(other_model.is_empty());
+    assert_eq!(stored, vec![answer]);
+    // Stored rank order, stored scores, and chunks that are gone left out
+    let ranked: Vec<_> = results
+        .iter()
+        .map(|doc| (doc.doc_path.clone(), doc.similarity))
+        .collect();
+    assert_eq!(
+        ranked,
+        vec![
+            (format!("{name}/latest/c"), 0.875),
+            (format!("{name}/latest/a"), 0.5)
+        ]
+    );
+    assert_eq!(results[0].content, "doc c");
+    assert!(after_promotion.is_empty());
+}
+
+#[tokio::test]
+async fn crawl_traces_are_stored_per_job() {
+    use rustdocs_mcp_server::doc_loader::{CrawlTrace, TraceEntry, TraceOutcome};
+
+    let Some(db) = test_database().await else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let name = "crawl-trace-test";
+    let config = db
+        .upsert_crate_config(&unpopulated_config(name))
+        .await
+        .unwrap();
+    let job_id = db.create_population_job(config.id).await.unwrap();
+    let before = db.get_population_job_crawl_trace(job_id).await.unwrap();
+    let trace = CrawlTrace {
+        entries: vec![
+            TraceEntry {
+                url: "https://docs.rs/crawl-trace-test/latest/crawl_trace_test/".to_string(),
+                outcome: TraceOutcome::Fetched,
+                detail: None,
+            },
+            TraceEntry {
+                url: "https://docs.rs/crawl-trace-test/latest/crawl_trace_test/io/".to_string(),
+                outcome: TraceOutcome::Error,
+                detail: Some("server_error".to_string()),
+            },
+        ],
+        dropped: 3,
+    };
+    db.set_population_job_crawl_trace(job_id, &trace)
+        .await
+        .unwrap();
+    let stored = db.get_population_job_crawl_trace(job_id).await.unwrap();
+    let missing_job = db.get_population_job_crawl_trace(i32::MAX).await.unwrap();
+    db.delete_crate_config(name, "latest", None).await.unwrap();
+
+    assert_eq!(before, None);
+    assert_eq!(stored, Some(trace));
+    assert_eq!(missing_job, None);
+}
+
+/// Recreate the schema `namespace` with an empty copy of every table the code uses
+async fn empty_namespace(url: &str, namespace: &str) {
+    let pool = sqlx::PgPool::connect(url).await.unwrap();
+    sqlx::query(&format!("DROP SCHEMA IF EXISTS {namespace} CASCADE"))
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query(&format!("CREATE SCHEMA {namespace}"))
+        .execute(&pool)
+        .await
+        .unwrap();
+    let tables: BTreeSet<&str> = REQUIRED_COLUMNS.iter().map(|(table, ..)| *table).collect();
+    for table in tables {
+        sqlx::query(&format!(
+            "CREATE TABLE {namespace}.{table} (LIKE public.{table} INCLUDING ALL)"
+        ))
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn profile_namespaces_keep_data_and_configuration_apart() {
+    let Ok(url) = std::env::var("MCPDOCS_TEST_DATABASE_URL") else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    empty_namespace(&url, "profile_test_oss").await;
+    empty_namespace(&url, "profile_test_internal").await;
+    let oss = Database::connect_namespace(&url, "profile_test_oss")
+        .await
+        .unwrap();
+    let internal = Database::connect_namespace(&url, "profile_test_internal")
+        .await
+        .unwrap();
+    let default = Database::connect(&url).await.unwrap();
+    assert_eq!(oss.namespace(), Some("profile_test_oss"));
+    assert_eq!(default.namespace(), None);
+
+    // The same crate name is configured independently in each profile
+    let name = "profile-isolation-test";
+    oss.upsert_crate_config(&unpopulated_config(name))
+        .await
+        .unwrap();
+    internal
+        .upsert_crate_config(&CrateConfig {
+            query_hint: Some("internal build".to_string()),
+            ..unpopulated_config(name)
+        })
+        .await
+        .unwrap();
+    let crate_id = oss.upsert_crate(name, None, None).await.unwrap();
+    let embedding = Array1::from_elem(EMBEDDING_DIMENSION as usize, 0.5_f32);
+    oss.insert_embeddings_batch(
+        crate_id,
+        name,
+        &[(
+            format!("{name}/latest/index.html"),
+            "Open-source crate documentation.".to_string(),
+            embedding.clone(),
+            4,
+        )],
+    )
+    .await
+    .unwrap();
+
+    let hint = |configs: Vec<CrateConfig>| {
+        configs
+            .into_iter()
+            .find(|config| config.name == name)
+            .map(|config| config.query_hint)
+    };
+    assert_eq!(
+        hint(oss.get_crate_configs(false).await.unwrap()),
+        Some(None)
+    );
+    assert_eq!(
+        hint(internal.get_crate_configs(false).await.unwrap()),
+        Some(Some("internal build".to_string()))
+    );
+    assert_eq!(hint(default.get_crate_configs(false).await.unwrap()), None);
+    assert!(oss
+        .get_all_crates_with_embeddings()
+        .await
+        .unwrap()
+        .contains(&name.to_string()));
+    for other in [&internal, &default] {
+        assert!(!other
+            .get_all_crates_with_embeddings()
+            .await
+            .unwrap()
+            .contains(&name.to_string()));
+        assert!(other
+            .search_similar_docs_tuned(name, &embedding, 5, None, None, None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    // A namespace without a schema must not fall back to the default tables
+    assert!(Database::connect_namespace(&url, "profile_test_missing")
+        .await
+        .is_err());
+
+    let pool = sqlx::PgPool::connect(&url).await.unwrap();
+    for namespace in ["profile_test_oss", "profile_test_internal"] {
+        sqlx::query(&format!("DROP SCHEMA {namespace} CASCADE"))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,80 @@
+use rustdocs_mcp_server::{db_breaker::DbBreaker, error::ServerError};
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+fn outage() -> ServerError {
+    ServerError::Database("connection refused".to_string())
+}
+
+#[test]
+fn a_long_outage_trips_the_breaker_once() {
+    let breaker = DbBreaker::new(3, Duration::from_secs(60));
+
+    assert!(!breaker.record_failure(&outage()));
+    assert!(!breaker.record_failure(&outage()));
+    assert!(!breaker.is_degraded());
+
+    // Thousands of failures change state (and warn) exactly once
+    let trips = (0..5000)
+        .filter(|_| breaker.record_failure(&outage()))
+        .count();
+    assert_eq!(trips, 1);
+    assert!(breaker.is_degraded());
+
+    let status = breaker.status();
+    assert_eq!(status.consecutive_failures, 5002);
+    assert_eq!(
+        status.last_error.as_deref(),
+        Some("Database Error: connection refused")
+    );
+    assert!(status.retry_in_secs.is_some_and(|secs| secs <= 60.0));
+
+    assert!(breaker.record_success());
+    assert!(!breaker.record_success());
+    assert!(!breaker.is_degraded());
+    assert_eq!(breaker.status().consecutive_failures, 0);
+}
+
+#[test]
+fn backoff_doubles_up_to_the_maximum() {
+    let breaker = DbBreaker::new(3, Duration::from_secs(60));
+    let waits: Vec<u64> = (3..=10).map(|n| breaker.backoff(n).as_secs()).collect();
+
+    assert_eq!(waits, [1, 2, 4, 8, 16, 32, 60, 60]);
+    assert_eq!(breaker.backoff(u32::MAX), Duration::from_secs(60));
+}
+
+#[tokio::test]
+async fn calls_wait_out_the_backoff_and_recover_on_success() {
+    let breaker =
+        DbBreaker::new(1, Duration::from_millis(200)).with_base_backoff(Duration::from_millis(50));
+    let attempts = AtomicUsize::new(0);
+    let attempt = |ok: bool| {
+        attempts.fetch_add(1, Ordering::Relaxed);
+        async move {
+            if ok {
+                Ok(())
+            } else {
+                Err(outage())
+            }
+        }
+    };
+
+    assert!(breaker.call(attempt(false)).await.is_err());
+    assert!(breaker.is_degraded());
+    assert!(breaker.retry_in().is_some());
+
+    let started = std::time::Instant::now();
+    breaker.call(attempt(true)).await.unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(40));
+    assert!(!breaker.is_degraded());
+    assert_eq!(breaker.retry_in(), None);
+    assert_eq!(attempts.load(Ordering::Relaxed), 2);
+
+    // Waiting for recovery returns at once while the database is healthy
+    tokio::time::timeout(Duration::from_millis(10), breaker.wait_for_recovery())
+        .await
+        .unwrap();
+}
@@ -0,0 +1,940 @@
+use rustdocs_mcp_server::doc_loader;
+use scraper::Html;
+
+fn extract(html: &str) -> Vec<doc_loader::PageSection> {
+    let document = Html::parse_document(html);
+    let selector = doc_loader::parse_content_selector(None).unwrap();
+    doc_loader::extract_sections(&document, &selector)
+}
+
+fn fixture_sections() -> Vec<doc_loader::PageSection> {
+    extract(include_str!("fixtures/sections.html"))
+}
+
+#[test]
+fn content_before_first_heading_has_no_anchor() {
+    let sections = fixture_sections();
+    let first = &sections[0];
+
+    assert_eq!(first.anchor, None);
+    assert!(first.content.starts_with("The Tokio runtime."));
+    assert!(!first.content.contains("Most applications"));
+}
+
+#[test]
+fn headings_inside_a_docblock_split_it_into_sections() {
+    let sections = fixture_sections();
+    let anchors: Vec<Option<&str>> = sections.iter().map(|s| s.anchor.as_deref()).collect();
+
+    assert_eq!(
+        anchors,
+        vec![
+            None,
+            Some("usage"),
+            Some("shutdown"),
+            Some("method.shutdown_timeout"),
+        ]
+    );
+
+    let shutdown = &sections[2];
+    assert!(shutdown.content.contains("Dropping the runtime waits"));
+    assert!(!shutdown.content.contains("Most applications"));
+}
+
+#[test]
+fn docblock_headings_tag_the_sections_they_start() {
+    let sections = fixture_sections();
+    let tags: Vec<Option<&str>> = sections.iter().map(|s| s.section.as_deref()).collect();
+    // Headings outside docblocks (item sections, methods) aren't docblock sections
+    assert_eq!(tags, vec![None, Some("Usage"), Some("Shutdown"), None]);
+
+    let sections = extract(include_str!("fixtures/method_sections.html"));
+    let tagged: Vec<(Option<&str>, Option<&str>)> = sections
+        .iter()
+        .map(|s| (s.anchor.as_deref(), s.section.as_deref()))
+        .collect();
+    assert_eq!(
+        tagged,
+        vec![
+            (None, None),
+            (Some("examples"), Some("Examples")),
+            // A subheading stays part of its parent's section
+            (Some("with-capacity"), Some("Examples")),
+            (Some("method.push"), None),
+            (Some("panics"), Some("Panics")),
+            (Some("method.pop_unchecked"), None),
+            (Some("safety"), Some("Safety")),
+        ]
+    );
+    assert!(sections[4].content.contains("exceeds\nisize::MAX"));
+}
+
+#[test]
+fn consecutive_blocks_under_one_heading_are_merged() {
+    let sections = fixture_sections();
+    let method = sections.last().unwrap();
+
+    assert_eq!(method.blocks, 2);
+    assert!(method.content.contains("waiting for at most"));
+    assert!(method.content.contains("still running after the timeout"));
+}
+
+#[test]
+fn rustdoc_ui_boilerplate_is_stripped_from_extracted_content() {
+    let sections = extract(include_str!("fixtures/boilerplate.html"));
+    let content: Vec<&str> = sections.iter().map(|s| s.content.as_str()).collect();
+    let all = content.join("\n\n");
+
+    for boilerplate in [
+        "§",
+        "Run",
+        "source",
+        "Copy item path",
+        "Expand description",
+        "ⓘ",
+    ] {
+        assert!(
+            !all.lines().any(|line| line == boilerplate),
+            "found {boilerplate:?} in {all}"
+        );
+    }
+    assert!(!all.contains("Keyboard Shortcuts"));
+    assert!(!all.contains("window.rootPath"));
+
+    assert_eq!(
+        content,
+        vec![
+            "The Tokio runtime.\nThe runtime provides an I/O driver, task scheduler, and timer.",
+            "Examples\nlet rt = Runtime::new()?;\nIt's dropped when the last handle goes away - see\nshutdown\n.",
+        ]
+    );
+}
+
+#[test]
+fn clean_content_fixes_text_stored_by_the_old_extractor() {
+    let before = "§Examples\nlet rt = Runtime::new()?;\nRun\n\n\n\nIt\u{2019}s  dropped \u{2014} see\nCopy item path\nâ€œshutdownâ€\u{9d}\n";
+    let after = doc_loader::clean_content(before);
+
+    assert_eq!(
+        after,
+        "Examples\nlet rt = Runtime::new()?;\n\nIt's dropped - see\n\"shutdown\""
+    );
+    assert_eq!(doc_loader::clean_content(&after), after);
+}
+
+#[test]
+fn targets_must_look_like_target_triples() {
+    assert!(doc_loader::validate_target("x86_64-pc-windows-msvc").is_ok());
+    assert!(doc_loader::validate_target("thumbv7em-none-eabihf").is_ok());
+    assert!(doc_loader::validate_target("windows").is_err());
+    assert!(doc_loader::validate_target("x86_64-pc-windows-msvc/../../other").is_err());
+}
+
+#[tokio::test]
+async fn target_crawls_start_from_the_target_specific_docs() {
+    use axum::{http::Uri, response::Html as HtmlResponse, Router};
+
+    async fn docs(uri: Uri) -> HtmlResponse<&'static str> {
+        HtmlResponse(match uri.path() {
+            "/demo/latest/x86_64-pc-windows-msvc/demo/" => {
+                "<div class=\"docblock\"><p>Windows root.</p></div><a href=\"os/index.html\">os</a>"
+            }
+            "/demo/latest/x86_64-pc-windows-msvc/demo/os/index.html" => {
+                "<div class=\"docblock\"><p>Windows-only APIs.</p></div>"
+            }
+            _ => "<div class=\"docblock\"><p>Default target.</p></div>",
+        })
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        target: Some("x86_64-pc-windows-msvc".to_string()),
+        ..doc_loader::CrawlOptions::default()
+    };
+    let result = doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(10), &options)
+        .await
+        .unwrap();
+
+    let paths: Vec<&str> = result.documents.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "demo/latest/x86_64-pc-windows-msvc/demo/",
+            "demo/latest/x86_64-pc-windows-msvc/demo/os/index.html",
+        ]
+    );
+    assert_eq!(result.version, None);
+}
+
+#[test]
+fn item_page_links_are_child_items_and_submodules() {
+    assert!(doc_loader::is_item_page_link("struct.Foo.html"));
+    assert!(doc_loader::is_item_page_link("fn.bar.html#examples"));
+    assert!(doc_loader::is_item_page_link("io/trait.Read.html"));
+    assert!(doc_loader::is_item_page_link("io/index.html"));
+    assert!(!doc_loader::is_item_page_link("../struct.Foo.html"));
+    assert!(!doc_loader::is_item_page_link("#structs"));
+    assert!(!doc_loader::is_item_page_link("all.html"));
+    assert!(!doc_loader::is_item_page_link(
+        "https://docs.rs/other/struct.Foo.html"
+    ));
+}
+
+#[tokio::test]
+async fn index_only_pages_still_lead_to_their_items() {
+    use axum::{http::Uri, response::Html as HtmlResponse, Router};
+
+    async fn docs(uri: Uri) -> HtmlResponse<&'static str> {
+        HtmlResponse(match uri.path() {
+            "/demo/latest/demo/" => {
+                "<div class=\"docblock\"><p>Crate root.</p></div>\
+                 <a href=\"io/index.html\">io</a><a href=\"net/index.html\">net</a>"
+            }
+            "/demo/latest/demo/io/index.html" => "<div class=\"docblock\"><p>I/O docs.</p></div>",
+            // Listing rendered by JS: only navigation and item links in the static HTML
+            "/demo/latest/demo/net/index.html" => {
+                "<nav><a href=\"#structs\">Structs</a></nav>\
+                 <a href=\"struct.Socket.html\">Socket</a><a href=\"../all.html\">All items</a>"
+            }
+            "/demo/latest/demo/net/struct.Socket.html" => {
+                "<div class=\"docblock\"><p>A network socket.</p></div>"
+            }
+            _ => "",
+        })
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        ..doc_loader::CrawlOptions::default()
+    };
+    // With 4 pages, the net index is crawled after ordinary link following has stopped
+    let result = doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(4), &options)
+        .await
+        .unwrap();
+
+    let paths: Vec<&str> = result.documents.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "demo/latest/demo/",
+            "demo/latest/demo/io/index.html",
+            "demo/latest/demo/net/struct.Socket.html",
+        ]
+    );
+    assert_eq!(result.diagnostics.empty_pages, 1);
+    assert_eq!(result.diagnostics.index_only_pages, 1);
+    assert!(!result.diagnostics.low_doc_coverage);
+}
+
+#[tokio::test]
+async fn the_crawl_trace_records_what_became_of_each_url() {
+    use axum::{
+        http::{StatusCode, Uri},
+        response::{Html as HtmlResponse, IntoResponse, Response},
+        Router,
+    };
+    use doc_loader::TraceOutcome;
+
+    async fn docs(uri: Uri) -> Response {
+        match uri.path() {
+            "/demo/latest/demo/" => HtmlResponse(
+                "<div class=\"docblock\"><p>Crate root.</p></div>\
+                 <a href=\"struct.Gone.html\">Gone</a><a href=\"struct.Blank.html\">Blank</a>",
+            )
+            .into_response(),
+            "/demo/latest/demo/struct.Blank.html" => HtmlResponse("<nav></nav>").into_response(),
+            _ => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        ..doc_loader::CrawlOptions::default()
+    };
+    let result = doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(10), &options)
+        .await
+        .unwrap();
+
+    let trace = &result.diagnostics.trace;
+    let outcome_of = |suffix: &str| {
+        trace
+            .entries
+            .iter()
+            .find(|entry| entry.url.ends_with(suffix))
+            .map(|entry| entry.outcome)
+    };
+    assert_eq!(
+        outcome_of("/demo/latest/demo/"),
+        Some(TraceOutcome::Fetched)
+    );
+    assert_eq!(outcome_of("struct.Gone.html"), Some(TraceOutcome::NotFound));
+    assert_eq!(outcome_of("struct.Blank.html"), Some(TraceOutcome::Empty));
+    assert_eq!(trace.entries[0].outcome, TraceOutcome::Fetched);
+    assert_eq!(trace.dropped, 0);
+    // Every counted fetch and failure is in the trace
+    let counts = trace.counts();
+    let failed: usize = result.diagnostics.pages_failed.values().sum();
+    assert_eq!(
+        counts.get(&TraceOutcome::NotFound).copied().unwrap_or(0)
+            + counts.get(&TraceOutcome::Error).copied().unwrap_or(0),
+        failed
+    );
+    assert_eq!(
+        counts.get(&TraceOutcome::Fetched).copied().unwrap_or(0)
+            + counts.get(&TraceOutcome::Empty).copied().unwrap_or(0),
+        result.diagnostics.pages_fetched
+    );
+    // The trace is stored apart from the diagnostics
+    let diagnostics = serde_json::to_value(&result.diagnostics).unwrap();
+    assert!(diagnostics.get("trace").is_none());
+    assert_eq!(
+        TraceOutcome::parse("skipped_filter"),
+        Some(TraceOutcome::SkippedFilter)
+    );
+    assert_eq!(TraceOutcome::parse("404"), None);
+}
+
+#[tokio::test]
+async fn url_patterns_keep_deep_pages_and_never_fetch_excluded_ones() {
+    use axum::{extract::State, http::Uri, response::Html as HtmlResponse, Router};
+    use std::sync::{Arc, Mutex};
+
+    type Requested = Arc<Mutex<Vec<String>>>;
+
+    async fn docs(State(requested): State<Requested>, uri: Uri) -> HtmlResponse<&'static str> {
+        requested.lock().unwrap().push(uri.path().to_string());
+        HtmlResponse(match uri.path() {
+            "/demo/latest/demo/" => {
+                "<div class=\"docblock\"><p>Crate root.</p></div>\
+                 <a href=\"sync/index.html\">sync</a><a href=\"fs/index.html\">fs</a>\
+                 <a href=\"fs/fn.read.html\">read</a><a href=\"struct.Root.html\">Root</a>"
+            }
+            "/demo/latest/demo/sync/index.html" => {
+                "<div class=\"docblock\"><p>Sync docs.</p></div>\
+                 <a href=\"mpsc/index.html\">mpsc</a><a href=\"struct.Mutex.html\">Mutex</a>"
+            }
+            "/demo/latest/demo/sync/mpsc/index.html" => {
+                "<div class=\"docblock\"><p>Channels.</p></div><a href=\"fn.channel.html\">channel</a>"
+            }
+            "/demo/latest/demo/sync/mpsc/fn.channel.html" => {
+                "<div class=\"docblock\"><p>Creates a channel.</p></div>"
+            }
+            _ => "<div class=\"docblock\"><p>Elsewhere.</p></div>",
+        })
+    }
+
+    let requested = Requested::default();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    let app = Router::new().fallback(docs).with_state(requested.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        include_url_patterns: vec!["/sync/mpsc/".to_string()],
+        exclude_url_patterns: vec!["/fs/".to_string()],
+        ..doc_loader::CrawlOptions::default()
+    };
+    let result = doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(20), &options)
+        .await
+        .unwrap();
+
+    let mut requested = requested.lock().unwrap().clone();
+    requested.retain(|path| path != "/robots.txt");
+    requested.sort();
+    assert_eq!(
+        requested,
+        [
+            "/demo/latest/demo/",
+            "/demo/latest/demo/sync/index.html",
+            "/demo/latest/demo/sync/mpsc/fn.channel.html",
+            "/demo/latest/demo/sync/mpsc/index.html",
+        ]
+    );
+    // Module indexes outside the include patterns lead the way without being kept
+    let mut paths: Vec<&str> = result.documents.iter().map(|d| d.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        [
+            "demo/latest/demo/",
+            "demo/latest/demo/sync/mpsc/fn.channel.html",
+            "demo/latest/demo/sync/mpsc/index.html",
+        ]
+    );
+    let sync = result
+        .diagnostics
+        .trace
+        .entries
+        .iter()
+        .find(|entry| entry.url.ends_with("/sync/index.html"))
+        .unwrap();
+    assert_eq!(sync.outcome, doc_loader::TraceOutcome::Fetched);
+    assert_eq!(sync.detail.as_deref(), Some("links_only"));
+}
+
+#[tokio::test]
+async fn crates_without_docblocks_fall_back_to_their_readme() {
+    use axum::{http::Uri, response::Html as HtmlResponse, Router};
+
+    async fn docs(uri: Uri) -> HtmlResponse<&'static str> {
+        HtmlResponse(match uri.path() {
+            "/demo_derive/latest/demo_derive/" => include_str!("fixtures/proc_macro_root.html"),
+            "/demo_derive/latest/demo_derive/derive.Demo.html" => {
+                "<main><section id=\"main-content\"><pre class=\"rust item-decl\">\
+                 <code>#[derive(Demo)]</code></pre></section></main>"
+            }
+            "/crate/demo_derive/0.4.1" => include_str!("fixtures/proc_macro_crate_page.html"),
+            _ => "",
+        })
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        ..doc_loader::CrawlOptions::default()
+    };
+    let result =
+        doc_loader::load_documents_from_docs_rs("demo_derive", "*", None, Some(10), &options)
+            .await
+            .unwrap();
+
+    assert!(result.diagnostics.low_doc_coverage);
+    assert!(result
+        .diagnostics
+        .to_string()
+        .contains("low doc coverage (README fallback)"));
+
+    // The root page's full text, by section, then the README from the page of the
+    // crawled version
+    let paths: Vec<&str> = result.documents.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "demo_derive/latest/demo_derive/",
+            "demo_derive/latest/demo_derive/#derives",
+            "crate/demo_derive/0.4.1",
+        ]
+    );
+    assert!(result.documents[1].content.contains("Demo"));
+    let readme = &result.documents[2].content;
+    assert!(readme.contains("implements the"));
+    assert!(readme.contains("#[demo(rename"));
+    // The crate page's navigation and dependency menu are not part of the README
+    assert!(!readme.contains("Dependencies"));
+}
+
+#[test]
+fn root_text_uses_the_narrowest_selector_that_finds_anything() {
+    let document = Html::parse_document(include_str!("fixtures/proc_macro_root.html"));
+    let docs = doc_loader::full_page_documents("demo_derive/latest/demo_derive/", &document);
+    // #main-content matched, so the sidebar stays out
+    assert!(docs.iter().all(|d| !d.content.contains("0.4.1")));
+
+    let document = Html::parse_document("<body><p>Just text.</p></body>");
+    let docs = doc_loader::full_page_documents("demo/latest/demo/", &document);
+    assert_eq!(docs.len(), 1);
+    assert_eq!(docs[0].content, "Just text.");
+}
+
+async fn serve_tree() -> String {
+    use axum::{http::Uri, response::Html as HtmlResponse, Router};
+
+    async fn docs(uri: Uri) -> HtmlResponse<&'static str> {
+        HtmlResponse(match uri.path() {
+            "/demo/latest/demo/" => {
+                "<div class=\"docblock\"><p>Crate root.</p></div>\
+                 <a href=\"io/index.html\">io</a><a href=\"net/index.html\">net</a>"
+            }
+            "/demo/latest/demo/io/index.html" => {
+                "<div class=\"docblock\"><p>I/O docs.</p></div><a href=\"struct.Reader.html\">Reader</a>"
+            }
+            "/demo/latest/demo/io/struct.Reader.html" => {
+                "<div class=\"docblock\"><p>A reader.</p></div><a href=\"../index.html\">demo</a>"
+            }
+            "/demo/latest/demo/net/index.html" => "<div class=\"docblock\"><p>Net docs.</p></div>",
+            _ => "",
+        })
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+    origin
+}
+
+async fn crawl_order(options: doc_loader::CrawlOptions) -> Vec<String> {
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(serve_tree().await),
+        ..options
+    };
+    doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(10), &options)
+        .await
+        .unwrap()
+        .documents
+        .into_iter()
+        .map(|d| d.path)
+        .collect()
+}
+
+#[tokio::test]
+async fn crawl_strategy_controls_the_visit_order() {
+    let breadth_first = crawl_order(doc_loader::CrawlOptions::default()).await;
+    assert_eq!(
+        breadth_first,
+        [
+            "demo/latest/demo/",
+            "demo/latest/demo/io/index.html",
+            "demo/latest/demo/net/index.html",
+            "demo/latest/demo/io/struct.Reader.html",
+        ]
+    );
+
+    let depth_first = crawl_order(doc_loader::CrawlOptions {
+        strategy: doc_loader::CrawlStrategy::DepthFirst,
+        ..doc_loader::CrawlOptions::default()
+    })
+    .await;
+    assert_eq!(
+        depth_first,
+        [
+            "demo/latest/demo/",
+            "demo/latest/demo/io/index.html",
+            "demo/latest/demo/io/struct.Reader.html",
+            "demo/latest/demo/net/index.html",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn max_links_per_page_caps_what_each_page_queues() {
+    let capped = crawl_order(doc_loader::CrawlOptions {
+        max_links_per_page: Some(1),
+        ..doc_loader::CrawlOptions::default()
+    })
+    .await;
+
+    // The root queues only its first link, so the net module is never reached
+    assert_eq!(
+        capped,
+        [
+            "demo/latest/demo/",
+            "demo/latest/demo/io/index.html",
+            "demo/latest/demo/io/struct.Reader.html",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn follow_links_fraction_stops_link_following_early() {
+    // 10 pages at 0.2 follows links on the first page only
+    let shallow = crawl_order(doc_loader::CrawlOptions {
+        follow_links_fraction: Some(0.2),
+        ..doc_loader::CrawlOptions::default()
+    })
+    .await;
+
+    assert_eq!(
+        shallow,
+        [
+            "demo/latest/demo/",
+            "demo/latest/demo/io/index.html",
+            "demo/latest/demo/net/index.html",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn a_passed_deadline_stops_the_crawl() {
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(serve_tree().await),
+        deadline: Some(std::time::Instant::now()),
+        ..doc_loader::CrawlOptions::default()
+    };
+
+    let result = doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(10), &options)
+        .await
+        .unwrap();
+
+    assert!(result.documents.is_empty());
+    assert!(result.diagnostics.hit_deadline);
+    assert_eq!(result.diagnostics.pages_fetched, 0);
+}
+
+#[tokio::test]
+async fn stored_pages_re_extract_to_the_crawled_documents() {
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(serve_tree().await),
+        ..doc_loader::CrawlOptions::default()
+    };
+    let (doc_tx, mut doc_rx) = tokio::sync::mpsc::channel(64);
+    let (page_tx, mut page_rx) = tokio::sync::mpsc::channel(64);
+    doc_loader::crawl_docs_rs("demo", "*", None, Some(10), &options, doc_tx, Some(page_tx))
+        .await
+        .unwrap();
+
+    let mut crawled = Vec::new();
+    while let Some(doc) = doc_rx.recv().await {
+        crawled.push((doc.path, doc.content));
+    }
+    let selector = doc_loader::parse_content_selector(None).unwrap();
+    let mut pages = Vec::new();
+    let mut re_extracted = Vec::new();
+    while let Some(page) = page_rx.recv().await {
+        let html = Html::parse_document(&page.html);
+        for doc in doc_loader::page_documents(&page.path, &html, &selector) {
+            re_extracted.push((doc.path, doc.content));
+        }
+        pages.push(page.path);
+    }
+
+    // The empty page is stored as well, a different selector might find content on it
+    assert_eq!(pages.len(), 5);
+    assert!(pages.contains(&"demo/latest/demo/index.html".to_string()));
+    assert_eq!(re_extracted, crawled);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocking_load_documents_works_inside_a_runtime() {
+    use axum::{response::Html as HtmlResponse, Router};
+
+    async fn docs() -> HtmlResponse<&'static str> {
+        HtmlResponse("<div class=\"docblock\"><p>Crate root.</p></div>")
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        ..doc_loader::CrawlOptions::default()
+    };
+    let result = doc_loader::load_documents("demo", "*", None, &options).unwrap();
+
+    let paths: Vec<&str> = result.documents.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(paths, ["demo/latest/demo/"]);
+}
+
+#[test]
+fn versions_come_from_the_docs_url_path() {
+    let origin = "https://docs.rs/";
+    assert_eq!(
+        doc_loader::version_from_docs_url("https://docs.rs/tokio/1.38.0/tokio/", origin),
+        Some("1.38.0".to_string())
+    );
+    assert_eq!(
+        doc_loader::version_from_docs_url(
+            "https://docs.rs/tokio/1.38.0/x86_64-pc-windows-msvc/tokio/",
+            origin
+        ),
+        Some("1.38.0".to_string())
+    );
+    assert_eq!(
+        doc_loader::version_from_docs_url("https://docs.rs/tokio/latest/tokio/", origin),
+        None
+    );
+    assert_eq!(
+        doc_loader::version_from_docs_url("https://example.com/tokio/1.0.0/", origin),
+        None
+    );
+    // Requirements are not versions
+    assert_eq!(
+        doc_loader::version_from_docs_url("https://docs.rs/tokio/^1.38/tokio/", origin),
+        None
+    );
+    assert_eq!(
+        doc_loader::version_from_docs_url("https://docs.rs/tokio/1.0.0-rc.1/tokio/", origin),
+        Some("1.0.0-rc.1".to_string())
+    );
+}
+
+#[test]
+fn header_versions_are_picked_out_of_the_surrounding_text() {
+    assert_eq!(
+        doc_loader::version_from_text("  demo 1.2.3  "),
+        Some("1.2.3".to_string())
+    );
+    assert_eq!(
+        doc_loader::version_from_text("v0.4.0 (a1b2c3 2024-05-01)"),
+        Some("0.4.0".to_string())
+    );
+    assert_eq!(doc_loader::version_from_text("Docs.rs"), None);
+}
+
+#[tokio::test]
+async fn a_later_redirect_overrides_the_version_read_from_the_page() {
+    use axum::{
+        http::Uri,
+        response::{Html as HtmlResponse, IntoResponse, Redirect, Response},
+        Router,
+    };
+
+    // The root isn't redirected and its header shows an outdated version
+    async fn docs(uri: Uri) -> Response {
+        match uri.path() {
+            "/demo/latest/demo/" => HtmlResponse(
+                "<span class=\"version\">demo 0.9.0</span>\
+                 <div class=\"docblock\"><p>Crate root.</p></div>\
+                 <a href=\"struct.Widget.html\">Widget</a>",
+            )
+            .into_response(),
+            "/demo/latest/demo/struct.Widget.html" => {
+                Redirect::temporary("/demo/1.0.0/demo/struct.Widget.html").into_response()
+            }
+            "/demo/1.0.0/demo/struct.Widget.html" => {
+                HtmlResponse("<div class=\"docblock\"><p>A widget.</p></div>").into_response()
+            }
+            _ => HtmlResponse("").into_response(),
+        }
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        ..doc_loader::CrawlOptions::default()
+    };
+    let result = doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(5), &options)
+        .await
+        .unwrap();
+
+    assert_eq!(result.version.as_deref(), Some("1.0.0"));
+    assert_eq!(result.documents.len(), 2);
+}
+
+#[tokio::test]
+async fn the_version_is_taken_from_the_latest_redirect() {
+    use axum::{
+        http::Uri,
+        response::{Html as HtmlResponse, IntoResponse, Redirect, Response},
+        Router,
+    };
+
+    async fn docs(uri: Uri) -> Response {
+        match uri.path() {
+            "/demo/latest/demo/" => Redirect::temporary("/demo/1.2.3/demo/").into_response(),
+            "/demo/1.2.3/demo/" => {
+                HtmlResponse("<div class=\"docblock\"><p>Crate root.</p></div>").into_response()
+            }
+            _ => HtmlResponse("").into_response(),
+        }
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        ..doc_loader::CrawlOptions::default()
+    };
+    let result = doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(5), &options)
+        .await
+        .unwrap();
+
+    assert_eq!(result.version.as_deref(), Some("1.2.3"));
+    assert!(!result.diagnostics.version_missing);
+    // Stored paths keep the stable /latest/ form
+    assert_eq!(result.documents[0].path, "demo/latest/demo/");
+}
+
+#[tokio::test]
+async fn a_resolved_version_is_crawled_instead_of_latest() {
+    use axum::{
+        http::{StatusCode, Uri},
+        response::{Html as HtmlResponse, IntoResponse, Response},
+        Router,
+    };
+
+    async fn docs(uri: Uri) -> Response {
+        match uri.path() {
+            "/demo/0.12.0-rc.1/demo/" => {
+                HtmlResponse("<div class=\"docblock\"><p>Release candidate.</p></div>")
+                    .into_response()
+            }
+            _ => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        version: Some("0.12.0-rc.1".to_string()),
+        ..doc_loader::CrawlOptions::default()
+    };
+    let result = doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(5), &options)
+        .await
+        .unwrap();
+
+    assert_eq!(result.version.as_deref(), Some("0.12.0-rc.1"));
+    assert_eq!(result.documents[0].path, "demo/0.12.0-rc.1/demo/");
+    assert!(result.documents[0].content.contains("Release candidate."));
+
+    let invalid = doc_loader::CrawlOptions {
+        version: Some("^0.12".to_string()),
+        ..options
+    };
+    assert!(
+        doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(5), &invalid)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn a_crawl_without_any_version_is_flagged_in_diagnostics() {
+    use axum::{response::Html as HtmlResponse, Router};
+
+    async fn docs() -> HtmlResponse<&'static str> {
+        HtmlResponse("<div class=\"docblock\"><p>Crate root.</p></div>")
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        ..doc_loader::CrawlOptions::default()
+    };
+    let result = doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(5), &options)
+        .await
+        .unwrap();
+
+    assert_eq!(result.version, None);
+    assert!(result.diagnostics.version_missing);
+    assert!(result.diagnostics.to_string().contains("version unknown"));
+}
+
+#[tokio::test]
+async fn latest_version_comes_from_the_docs_redirect() {
+    use axum::{
+        http::Uri,
+        response::{IntoResponse, Redirect, Response},
+        Router,
+    };
+
+    async fn docs(uri: Uri) -> Response {
+        match uri.path() {
+            "/demo/latest/demo/" => Redirect::temporary("/demo/0.4.1/demo/").into_response(),
+            "/demo/latest/x86_64-pc-windows-msvc/demo/" => {
+                Redirect::temporary("/demo/0.4.0/x86_64-pc-windows-msvc/demo/").into_response()
+            }
+            "/unbuilt/latest/unbuilt/" => "No docs built yet".into_response(),
+            _ => axum::http::StatusCode::OK.into_response(),
+        }
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        ..doc_loader::CrawlOptions::default()
+    };
+    let windows = doc_loader::CrawlOptions {
+        target: Some("x86_64-pc-windows-msvc".to_string()),
+        ..options.clone()
+    };
+
+    let latest = |name: &'static str, options: &doc_loader::CrawlOptions| {
+        let options = options.clone();
+        async move {
+            doc_loader::fetch_latest_version(name, &options)
+                .await
+                .unwrap()
+        }
+    };
+    assert_eq!(latest("demo", &options).await.as_deref(), Some("0.4.1"));
+    assert_eq!(latest("demo", &windows).await.as_deref(), Some("0.4.0"));
+    assert_eq!(latest("unbuilt", &options).await, None);
+}
+
+#[test]
+fn pages_are_decoded_with_their_declared_charset() {
+    // ISO-8859-1 bytes, declared only in a <meta> tag
+    let body = include_bytes!("fixtures/latin1.html");
+
+    // The <meta> charset applies when the response doesn't name one
+    let text = doc_loader::decode_html(body, Some("text/html"));
+    assert!(
+        text.contains("Grüße aus München: a naïve façade kept at 10°C."),
+        "{text}"
+    );
+
+    // A Content-Type charset wins; bytes invalid in it are dropped, not kept as U+FFFD
+    let text = doc_loader::decode_html(body, Some("text/html; charset=utf-8"));
+    assert!(text.contains("Gre aus Mnchen"), "{text}");
+    assert!(!text.contains('\u{fffd}'));
+
+    let quoted =
+        doc_loader::decode_html(b"\x93quoted\x94", Some("text/html; charset=windows-1252"));
+    assert_eq!(quoted, "\u{201c}quoted\u{201d}");
+}
+
+#[test]
+fn mojibake_is_repaired_without_touching_genuine_accents() {
+    assert_eq!(
+        doc_loader::repair_mojibake("donâ€™t block â€” itâ€™s naÃ¯ve"),
+        "don\u{2019}t block \u{2014} it\u{2019}s naïve"
+    );
+    let genuine = "Grüße aus München, déjà vu → done";
+    assert!(matches!(
+        doc_loader::repair_mojibake(genuine),
+        std::borrow::Cow::Borrowed(text) if text == genuine
+    ));
+}
+
+#[tokio::test]
+async fn crawled_latin1_pages_are_stored_as_clean_utf8() {
+    use axum::{http::header, Router};
+
+    async fn docs() -> ([(header::HeaderName, &'static str); 1], &'static [u8]) {
+        (
+            [(header::CONTENT_TYPE, "text/html; charset=iso-8859-1")],
+            include_bytes!("fixtures/latin1.html"),
+        )
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+
+    let options = doc_loader::CrawlOptions {
+        docs_origin: Some(origin),
+        ..doc_loader::CrawlOptions::default()
+    };
+    let result = doc_loader::load_documents_from_docs_rs("demo", "*", None, Some(1), &options)
+        .await
+        .unwrap();
+
+    assert_eq!(result.documents.len(), 1);
+    assert!(
+        result.documents[0]
+            .content
+            .contains("Grüße aus München: a naïve façade kept at 10°C."),
+        "{}",
+        result.documents[0].content
+    );
+}
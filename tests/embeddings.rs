@@ -0,0 +1,536 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use rustdocs_mcp_server::{
+    doc_loader::Document,
+    embeddings::{
+        chunk_document, compare_models, embed_chunk, embed_with_fallback, parse_provider_list,
+        plan_voyage_batches, probe_provider, truncate_for_embedding, vector_problem,
+        EmbeddingProvider, TruncationStrategy, VoyageAIEmbeddingProvider, VoyageLimits,
+        MODEL_TOKEN_LIMIT,
+    },
+    error::ServerError,
+};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+fn long_doc() -> String {
+    let body = "filler words about the runtime ".repeat(200);
+    format!("pub fn block_on<F: Future>(&self, future: F) -> F::Output\n{body}\nExample: rt.block_on(async {{}})")
+}
+
+#[test]
+fn content_within_limit_is_not_truncated() {
+    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    assert!(truncate_for_embedding("short", &bpe, 100, TruncationStrategy::Middle).is_none());
+}
+
+#[test]
+fn strategies_keep_the_expected_ends() {
+    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let doc = long_doc();
+    let limit = 100;
+
+    let tail = truncate_for_embedding(&doc, &bpe, limit, TruncationStrategy::Tail).unwrap();
+    assert!(tail.starts_with("pub fn block_on"));
+    assert!(!tail.contains("Example:"));
+
+    let head = truncate_for_embedding(&doc, &bpe, limit, TruncationStrategy::Head).unwrap();
+    assert!(!head.contains("pub fn block_on"));
+    assert!(head.ends_with("rt.block_on(async {})"));
+
+    let middle = truncate_for_embedding(&doc, &bpe, limit, TruncationStrategy::Middle).unwrap();
+    assert!(middle.starts_with("pub fn block_on"));
+    assert!(middle.ends_with("rt.block_on(async {})"));
+
+    for text in [tail, head, middle] {
+        assert!(bpe.encode_with_special_tokens(&text).len() <= limit);
+    }
+}
+
+/// Records what it was asked to embed
+#[derive(Default)]
+struct RecordingProvider {
+    texts: Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for RecordingProvider {
+    async fn generate_embeddings(
+        &self,
+        texts: &[String],
+    ) -> Result<(Vec<Vec<f32>>, usize), ServerError> {
+        self.texts.lock().unwrap().extend_from_slice(texts);
+        Ok((vec![vec![0.5; 4]; texts.len()], texts.len()))
+    }
+
+    fn get_model_name(&self) -> &str {
+        "recording"
+    }
+}
+
+/// A signature, about 9000 tokens of body and a trailing example, as a stored row written
+/// without the chunker might hold
+fn oversized_row() -> String {
+    let body = "filler words about the runtime ".repeat(1800);
+    format!("pub fn block_on<F: Future>(&self, future: F) -> F::Output\n{body}\nExample: rt.block_on(async {{}})")
+}
+
+#[test]
+fn chunked_documents_never_need_truncation() {
+    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let doc = Document {
+        path: "demo/latest/demo/fn.block_on.html".to_string(),
+        content: oversized_row().replace("runtime ", "runtime. "),
+        section: None,
+    };
+    let chunks = chunk_document(&doc, &bpe);
+    assert!(chunks.len() > 1);
+    for (path, chunk) in &chunks {
+        assert!(
+            truncate_for_embedding(chunk, &bpe, MODEL_TOKEN_LIMIT, TruncationStrategy::Tail)
+                .is_none(),
+            "{path} would be truncated"
+        );
+    }
+}
+
+#[tokio::test]
+async fn oversized_stored_rows_are_truncated_by_strategy_before_embedding() {
+    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let row = oversized_row();
+    assert!(bpe.encode_with_special_tokens(&row).len() > MODEL_TOKEN_LIMIT);
+
+    let provider = RecordingProvider::default();
+    for strategy in [
+        TruncationStrategy::Tail,
+        TruncationStrategy::Head,
+        TruncationStrategy::Middle,
+    ] {
+        embed_chunk(
+            &provider,
+            &bpe,
+            "demo/latest/demo/fn.block_on.html",
+            row.clone(),
+            strategy,
+        )
+        .await
+        .unwrap();
+    }
+
+    let texts = provider.texts.lock().unwrap();
+    let [tail, head, middle] = &texts[..] else {
+        panic!("expected one input per strategy, got {}", texts.len());
+    };
+    for text in [tail, head, middle] {
+        assert!(bpe.encode_with_special_tokens(text).len() <= MODEL_TOKEN_LIMIT);
+    }
+    assert!(tail.starts_with("pub fn block_on") && !tail.contains("Example:"));
+    assert!(!head.starts_with("pub fn block_on") && head.ends_with("rt.block_on(async {})"));
+    assert!(middle.starts_with("pub fn block_on") && middle.ends_with("rt.block_on(async {})"));
+}
+
+#[test]
+fn strategy_parses_case_insensitively() {
+    assert_eq!(
+        "Middle".parse::<TruncationStrategy>().unwrap(),
+        TruncationStrategy::Middle
+    );
+    assert!("both".parse::<TruncationStrategy>().is_err());
+}
+
+#[test]
+fn voyage_batches_respect_item_and_token_limits() {
+    let limits = VoyageLimits {
+        max_items: 3,
+        max_batch_tokens: 100, // planned against 90
+        max_input_tokens: 50,
+    };
+
+    assert_eq!(plan_voyage_batches(&[10; 7], &limits), [0..3, 3..6, 6..7]);
+    assert_eq!(plan_voyage_batches(&[40, 40, 40, 5], &limits), [0..2, 2..4]);
+    // Over-long inputs are truncated by Voyage, so they only take the context length
+    assert_eq!(plan_voyage_batches(&[500, 40, 40], &limits), [0..2, 2..3]);
+    assert!(plan_voyage_batches(&[], &limits).is_empty());
+}
+
+#[test]
+fn known_voyage_models_have_their_published_limits() {
+    assert_eq!(
+        VoyageLimits::for_model("voyage-3.5").max_batch_tokens,
+        320_000
+    );
+    assert_eq!(
+        VoyageLimits::for_model("voyage-3.5-lite").max_batch_tokens,
+        1_000_000
+    );
+    assert_eq!(
+        VoyageLimits::for_model("voyage-code-3").max_batch_tokens,
+        120_000
+    );
+}
+
+/// Requests seen by the mock Voyage API: inputs per request
+type BatchLog = Arc<Mutex<Vec<usize>>>;
+
+async fn mock_voyage(
+    State((log, rate_limit_first)): State<(BatchLog, Arc<Mutex<bool>>)>,
+    Json(request): Json<Value>,
+) -> Response {
+    if std::mem::take(&mut *rate_limit_first.lock().unwrap()) {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", "0".parse().unwrap());
+        return (StatusCode::TOO_MANY_REQUESTS, headers, "slow down").into_response();
+    }
+    let inputs: Vec<String> = serde_json::from_value(request["input"].clone()).unwrap();
+    if inputs.iter().any(|i| i == "boom") {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"detail": "Request to model 'voyage-3.5' failed. The max allowed tokens per submitted batch is 320000."})),
+        )
+            .into_response();
+    }
+    log.lock().unwrap().push(inputs.len());
+
+    // Answer out of order; the provider must restore input order from `index`
+    let data: Vec<Value> = inputs
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(index, input)| json!({"embedding": [input.len() as f32], "index": index}))
+        .collect();
+    Json(json!({"data": data, "usage": {"total_tokens": inputs.len() * 2}})).into_response()
+}
+
+async fn serve_voyage(rate_limit_first: bool) -> (VoyageAIEmbeddingProvider, BatchLog) {
+    let log = BatchLog::default();
+    let app = Router::new()
+        .route("/v1/embeddings", post(mock_voyage))
+        .with_state((log.clone(), Arc::new(Mutex::new(rate_limit_first))));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}/v1", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let provider = VoyageAIEmbeddingProvider::new("key".to_string(), "voyage-3.5".to_string())
+        .with_api_base(&base)
+        .with_limits(VoyageLimits {
+            max_items: 2,
+            max_batch_tokens: 1000,
+            max_input_tokens: 1000,
+        });
+    (provider, log)
+}
+
+#[tokio::test]
+async fn voyage_requests_are_split_and_results_keep_input_order() {
+    let (provider, log) = serve_voyage(false).await;
+    let texts: Vec<String> = ["a", "bb", "ccc", "dddd", "eeeee"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+    let (embeddings, tokens) = provider.generate_embeddings(&texts).await.unwrap();
+
+    assert_eq!(*log.lock().unwrap(), [2, 2, 1]);
+    let firsts: Vec<f32> = embeddings.iter().map(|e| e[0]).collect();
+    assert_eq!(firsts, [1.0, 2.0, 3.0, 4.0, 5.0]);
+    assert_eq!(tokens, 10);
+}
+
+#[tokio::test]
+async fn voyage_errors_are_translated_into_actionable_messages() {
+    let (provider, _log) = serve_voyage(false).await;
+
+    let err = provider
+        .generate_embeddings(&["boom".to_string()])
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(err.contains("400"), "{err}");
+    assert!(
+        err.contains("max allowed tokens per submitted batch"),
+        "{err}"
+    );
+    assert!(err.contains("token limits"), "{err}");
+    assert!(!err.contains("{\"detail\""), "{err}");
+}
+
+#[tokio::test]
+async fn rate_limited_voyage_requests_are_retried() {
+    let (provider, log) = serve_voyage(true).await;
+
+    let (embeddings, _) = provider
+        .generate_embeddings(&["retry".to_string()])
+        .await
+        .unwrap();
+
+    assert_eq!(embeddings.len(), 1);
+    assert_eq!(*log.lock().unwrap(), [1]);
+}
+
+/// Provider for fallback tests: fails every call when `healthy` is false
+struct StubProvider {
+    model: &'static str,
+    healthy: bool,
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for StubProvider {
+    async fn generate_embeddings(
+        &self,
+        texts: &[String],
+    ) -> Result<(Vec<Vec<f32>>, usize), ServerError> {
+        if self.healthy {
+            Ok((vec![vec![1.0; 2]; texts.len()], texts.len()))
+        } else {
+            Err(ServerError::Network(format!("{} is down", self.model)))
+        }
+    }
+
+    fn get_model_name(&self) -> &str {
+        self.model
+    }
+}
+
+fn stub(model: &'static str, healthy: bool) -> Arc<dyn EmbeddingProvider + Send + Sync> {
+    Arc::new(StubProvider { model, healthy })
+}
+
+/// Provider returning the same vector for every text
+struct FixedProvider {
+    model: &'static str,
+    vector: Vec<f32>,
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for FixedProvider {
+    async fn generate_embeddings(
+        &self,
+        texts: &[String],
+    ) -> Result<(Vec<Vec<f32>>, usize), ServerError> {
+        Ok((vec![self.vector.clone(); texts.len()], texts.len()))
+    }
+
+    fn get_model_name(&self) -> &str {
+        self.model
+    }
+}
+
+#[tokio::test]
+async fn query_models_must_match_the_document_space() {
+    let document = FixedProvider {
+        model: "docs-large",
+        vector: vec![1.0, 0.2, 0.0],
+    };
+    let matched = FixedProvider {
+        model: "docs-lite",
+        vector: vec![0.9, 0.3, 0.1],
+    };
+    let comparison = compare_models(&document, &matched).await.unwrap();
+    assert!(comparison.similarity.unwrap() > 0.9);
+    assert_eq!(comparison.problem(), None);
+
+    let unrelated = FixedProvider {
+        model: "other",
+        vector: vec![0.0, 0.0, 1.0],
+    };
+    let problem = compare_models(&document, &unrelated)
+        .await
+        .unwrap()
+        .problem()
+        .unwrap();
+    assert!(problem.contains("don't share a vector space"), "{problem}");
+
+    let smaller = FixedProvider {
+        model: "small",
+        vector: vec![1.0, 0.2],
+    };
+    let comparison = compare_models(&document, &smaller).await.unwrap();
+    assert_eq!(comparison.similarity, None);
+    assert!(comparison.problem().unwrap().contains("2-dimensional"));
+}
+
+#[tokio::test]
+async fn probes_report_the_dimension_or_the_failure() {
+    let probe = probe_provider(stub("stub-model", true).as_ref())
+        .await
+        .unwrap();
+    assert_eq!(probe.provider, "custom");
+    assert_eq!(probe.model, "stub-model");
+    assert_eq!(probe.dimension, 2);
+
+    let error = probe_provider(stub("stub-model", false).as_ref())
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("stub-model is down"));
+
+    let zeros = FlakyProvider {
+        bad_calls: 1,
+        calls: Mutex::new(0),
+    };
+    assert!(probe_provider(&zeros).await.is_err());
+}
+
+#[tokio::test]
+async fn voyage_probes_name_the_provider() {
+    let (provider, _) = serve_voyage(false).await;
+
+    let probe = probe_provider(&provider).await.unwrap();
+
+    assert_eq!(probe.provider, "voyage");
+    assert_eq!(probe.model, "voyage-3.5");
+    assert_eq!(probe.dimension, 1);
+}
+
+#[test]
+fn provider_lists_parse_in_order_with_optional_models() {
+    assert_eq!(
+        parse_provider_list("voyage:voyage-3.5, OpenAI").unwrap(),
+        [
+            ("voyage".to_string(), Some("voyage-3.5".to_string())),
+            ("openai".to_string(), None)
+        ]
+    );
+    assert!(parse_provider_list("ollama:nomic-embed-text").is_err());
+}
+
+#[tokio::test]
+async fn queries_fall_back_only_to_providers_matching_the_crate_model() {
+    let texts = vec!["spawn a task".to_string()];
+    let primary = stub("text-embedding-3-large", false);
+    let fallbacks = vec![
+        stub("voyage-3.5", true),
+        stub("text-embedding-3-large", true),
+    ];
+
+    // The crate's vectors came from the OpenAI model, so Voyage is skipped
+    let (embeddings, model) =
+        embed_with_fallback(&primary, &fallbacks, Some("text-embedding-3-large"), &texts)
+            .await
+            .unwrap();
+    assert_eq!(embeddings.len(), 1);
+    assert_eq!(model, "text-embedding-3-large");
+
+    let err = embed_with_fallback(
+        &primary,
+        &fallbacks[..1],
+        Some("text-embedding-3-large"),
+        &texts,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("1 fallback provider(s) skipped"));
+
+    // Without a recorded model there is nothing to check a fallback against
+    assert!(embed_with_fallback(&primary, &fallbacks, None, &texts)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn a_fallback_matching_the_crate_model_is_tried_before_the_primary() {
+    let texts = vec!["spawn a task".to_string()];
+    let (_, model) = embed_with_fallback(
+        &stub("text-embedding-3-large", true),
+        &[stub("voyage-3.5", true)],
+        Some("voyage-3.5"),
+        &texts,
+    )
+    .await
+    .unwrap();
+    assert_eq!(model, "voyage-3.5");
+}
+
+#[tokio::test]
+async fn a_healthy_primary_is_used_without_fallback() {
+    let texts = vec!["spawn a task".to_string()];
+    let (_, model) = embed_with_fallback(
+        &stub("voyage-3.5", true),
+        &[stub("voyage-3.5", true)],
+        Some("voyage-3.5"),
+        &texts,
+    )
+    .await
+    .unwrap();
+    assert_eq!(model, "voyage-3.5");
+}
+
+#[test]
+fn zero_non_finite_and_wrong_dimension_vectors_are_rejected() {
+    assert_eq!(vector_problem(&[0.1, -0.2, 0.0], Some(3)), None);
+    assert_eq!(vector_problem(&[0.0; 3], None).unwrap(), "is all zeros");
+    assert!(vector_problem(&[0.1, f32::NAN], None)
+        .unwrap()
+        .contains("NaN"));
+    assert!(vector_problem(&[0.1, f32::INFINITY], None).is_some());
+    assert_eq!(
+        vector_problem(&[0.1; 4], Some(3)).unwrap(),
+        "has 4 dimensions, expected 3"
+    );
+    assert!(vector_problem(&[], None).is_some());
+}
+
+/// Returns zero vectors for the first `bad_calls` requests, then real ones
+struct FlakyProvider {
+    bad_calls: usize,
+    calls: Mutex<usize>,
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for FlakyProvider {
+    async fn generate_embeddings(
+        &self,
+        texts: &[String],
+    ) -> Result<(Vec<Vec<f32>>, usize), ServerError> {
+        let mut calls = self.calls.lock().unwrap();
+        *calls += 1;
+        let value = if *calls <= self.bad_calls { 0.0 } else { 0.5 };
+        Ok((vec![vec![value; 4]; texts.len()], texts.len()))
+    }
+
+    fn get_model_name(&self) -> &str {
+        "flaky"
+    }
+}
+
+#[tokio::test]
+async fn zero_embeddings_are_retried_once_before_failing() {
+    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let path = "demo/latest/demo/fn.run.html";
+
+    let recovers = FlakyProvider {
+        bad_calls: 1,
+        calls: Mutex::new(0),
+    };
+    let embedding = embed_chunk(
+        &recovers,
+        &bpe,
+        path,
+        "Runs the demo.".to_string(),
+        TruncationStrategy::Tail,
+    )
+    .await
+    .unwrap();
+    assert_eq!(embedding.to_vec(), [0.5; 4]);
+
+    let broken = FlakyProvider {
+        bad_calls: 2,
+        calls: Mutex::new(0),
+    };
+    let err = embed_chunk(
+        &broken,
+        &bpe,
+        path,
+        "Runs the demo.".to_string(),
+        TruncationStrategy::Tail,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains(path), "{err}");
+    assert_eq!(*broken.calls.lock().unwrap(), 2);
+}
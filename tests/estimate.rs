@@ -0,0 +1,138 @@
+use rustdocs_mcp_server::{
+    doc_loader::CrawlOptions,
+    estimate::{self, EstimateSettings, PageSample},
+};
+use std::time::Duration;
+
+fn settings(model: &str) -> EstimateSettings {
+    EstimateSettings {
+        sample_pages: 5,
+        max_pages: 10000,
+        model: model.to_string(),
+        embed_concurrency: 8,
+        page_delay: Duration::ZERO,
+    }
+}
+
+fn sample(tokens: usize, chunks: usize) -> PageSample {
+    PageSample {
+        url: format!("https://docs.rs/demo/1.0.0/demo/{tokens}.html"),
+        tokens,
+        chunks,
+        fetch_ms: 100,
+    }
+}
+
+#[test]
+fn all_html_lists_items_and_implies_their_modules() {
+    let index = estimate::parse_all_items(
+        r#"<ul class="all-items">
+            <li><a href="struct.Runtime.html">Runtime</a></li>
+            <li><a href="sync/mpsc/fn.channel.html">sync::mpsc::channel</a></li>
+            <li><a href="sync/struct.Mutex.html">sync::Mutex</a></li>
+            <li><a href="struct.Runtime.html#examples">Runtime</a></li>
+            <li><a href="../other/struct.Foreign.html">other::Foreign</a></li>
+            <li><a href="all.html">All items</a></li>
+        </ul>"#,
+    );
+
+    assert_eq!(
+        index.modules,
+        ["index.html", "sync/index.html", "sync/mpsc/index.html"]
+    );
+    assert_eq!(
+        index.items,
+        [
+            "struct.Runtime.html",
+            "sync/mpsc/fn.channel.html",
+            "sync/struct.Mutex.html"
+        ]
+    );
+    assert_eq!(index.pages().len(), 6);
+}
+
+#[test]
+fn samples_spread_over_the_pages_starting_with_the_root() {
+    assert_eq!(estimate::sample_indices(100, 5), [0, 20, 40, 60, 80]);
+    assert_eq!(estimate::sample_indices(3, 5), [0, 1, 2]);
+}
+
+#[test]
+fn projections_scale_the_sample_and_price_it() {
+    let samples = vec![sample(900, 3), sample(1000, 4), sample(1100, 5)];
+    let estimate = estimate::project(1000, 990, samples, &settings("text-embedding-3-large"));
+
+    assert_eq!(estimate.total_tokens, 1_000_000);
+    assert_eq!(estimate.chunks, 4000);
+    assert_eq!(estimate.estimated_cost_usd, Some(0.13));
+    // 0.1s per fetch over 1000 pages; 4000 requests of 0.4s, 8 at a time
+    assert!((estimate.duration.crawl_secs - 100.0).abs() < 1e-6);
+    assert!((estimate.duration.embed_secs - 200.0).abs() < 1e-6);
+    assert_eq!(estimate.duration.total_secs, estimate.duration.embed_secs);
+    assert_eq!(estimate.confidence.level, "high");
+    let (low, high) = estimate.total_tokens_range.unwrap();
+    assert!(low < 1_000_000 && high > 1_000_000);
+}
+
+#[test]
+fn uneven_or_tiny_samples_lower_the_confidence() {
+    let uneven = vec![sample(100, 1), sample(4000, 9), sample(300, 2)];
+    let estimate = estimate::project(1000, 990, uneven, &settings("voyage-3.5"));
+    assert_eq!(estimate.confidence.level, "low");
+
+    let single = estimate::project(1000, 990, vec![sample(500, 2)], &settings("voyage-3.5"));
+    assert_eq!(single.confidence.level, "low");
+    assert_eq!(single.confidence.relative_error, None);
+
+    let every_page = estimate::project(2, 1, vec![sample(500, 2), sample(50, 1)], &settings("x"));
+    assert_eq!(every_page.confidence.level, "high");
+    assert_eq!(every_page.estimated_cost_usd, None);
+    assert!(every_page.notes[0].contains("no published price for x"));
+}
+
+#[tokio::test]
+async fn estimates_come_from_all_html_and_sampled_pages() {
+    use axum::{http::Uri, response::Html as HtmlResponse, Router};
+
+    async fn docs(uri: Uri) -> HtmlResponse<&'static str> {
+        HtmlResponse(match uri.path() {
+            "/demo/1.0.0/demo/all.html" => {
+                r#"<ul class="all-items"><li><a href="struct.A.html">A</a></li><li><a href="io/fn.b.html">io::b</a></li><li><a href="io/fn.c.html">io::c</a></li></ul>"#
+            }
+            "/robots.txt" => "",
+            _ => r#"<div class="docblock"><p>Some documentation for this page.</p></div>"#,
+        })
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+
+    let options = CrawlOptions {
+        docs_origin: Some(origin),
+        version: Some("1.0.0".to_string()),
+        exclude_url_patterns: vec!["fn\\.c".to_string()],
+        ..CrawlOptions::default()
+    };
+    let settings = EstimateSettings {
+        sample_pages: 2,
+        ..settings("text-embedding-3-small")
+    };
+    let estimate = estimate::estimate_population("demo", &options, &settings)
+        .await
+        .unwrap();
+
+    // Root, io/index.html, struct.A.html and io/fn.b.html; fn.c is excluded
+    assert_eq!(estimate.pages, 4);
+    assert_eq!(estimate.listed_items, 3);
+    assert_eq!(estimate.version.as_deref(), Some("1.0.0"));
+    assert_eq!(estimate.sampled_pages.len(), 2);
+    assert!(estimate.sampled_pages[0]
+        .url
+        .ends_with("/demo/1.0.0/demo/index.html"));
+    assert!(estimate
+        .sampled_pages
+        .iter()
+        .all(|s| s.chunks == 1 && s.tokens > 0));
+    assert!(estimate.total_tokens > 0);
+}
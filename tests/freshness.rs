@@ -0,0 +1,96 @@
+use axum::{http::Uri, response::IntoResponse, Json, Router};
+use chrono::{Duration, TimeZone, Utc};
+use rustdocs_mcp_server::{
+    database::ScoredDoc,
+    freshness::{format_freshness_footer, staleness_note, LatestVersions},
+};
+
+#[test]
+fn recent_docs_matching_the_latest_release_get_no_note() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let populated = now - Duration::days(10);
+
+    assert_eq!(
+        staleness_note(populated, now, 90, Some("1.2.0"), None),
+        None
+    );
+    assert_eq!(
+        staleness_note(populated, now, 90, Some("1.2.0"), Some("1.2.0")),
+        None
+    );
+}
+
+#[test]
+fn old_or_outdated_docs_are_flagged() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+
+    assert_eq!(
+        staleness_note(now - Duration::days(120), now, 90, Some("1.2.0"), None).unwrap(),
+        "⚠️ Note: docs may be stale (populated 120 days ago)"
+    );
+    assert_eq!(
+        staleness_note(now - Duration::days(3), now, 90, Some("1.2.0"), Some("1.4.1")).unwrap(),
+        "⚠️ Note: docs may be stale (populated 3 days ago; stored version is 1.2.0; current latest is 1.4.1)"
+    );
+}
+
+#[tokio::test]
+async fn latest_versions_come_from_docs_rs_status() {
+    async fn status(uri: Uri) -> axum::response::Response {
+        match uri.path() {
+            "/crate/demo/latest/status.json" => {
+                Json(serde_json::json!({ "version": "0.9.3", "doc_status": true })).into_response()
+            }
+            _ => axum::http::StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(status)).await });
+
+    let versions = LatestVersions::new(&origin);
+    assert_eq!(
+        versions.latest_version("demo").await.as_deref(),
+        Some("0.9.3")
+    );
+    assert_eq!(versions.latest_version("missing").await, None);
+}
+
+fn scored(version: Option<&str>, indexed: Option<&str>) -> ScoredDoc {
+    ScoredDoc {
+        doc_path: "tokio/latest/tokio/runtime/index.html".to_string(),
+        content: String::new(),
+        distance: 0.2,
+        similarity: 0.8,
+        last_updated_at: indexed.map(|d| d.parse().unwrap()),
+        crate_version: version.map(str::to_string),
+        token_count: 0,
+    }
+}
+
+#[test]
+fn footer_names_the_version_and_indexing_dates() {
+    let docs = [
+        scored(Some("1.38.0"), Some("2024-05-02T10:00:00Z")),
+        scored(Some("1.38.0"), Some("2024-05-02T11:30:00Z")),
+    ];
+    assert_eq!(
+        format_freshness_footer("tokio", &docs).unwrap(),
+        "docs from tokio 1.38.0, indexed 2024-05-02"
+    );
+
+    let partially_refreshed = [
+        scored(Some("1.38.0"), Some("2024-05-02T10:00:00Z")),
+        scored(Some("1.38.0"), Some("2024-01-15T10:00:00Z")),
+    ];
+    assert_eq!(
+        format_freshness_footer("tokio", &partially_refreshed).unwrap(),
+        "docs from tokio 1.38.0, indexed 2024-01-15 to 2024-05-02"
+    );
+
+    assert_eq!(
+        format_freshness_footer("tokio", &[scored(None, None)]),
+        None
+    );
+}
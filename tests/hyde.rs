@@ -0,0 +1,84 @@
+use async_openai::{config::OpenAIConfig, Client as OpenAIClient};
+use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+use rustdocs_mcp_server::hyde::HydeClient;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+async fn mock_llm(router: Router) -> HydeClient {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let api_base = format!("http://{}/v1", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    let config = OpenAIConfig::new()
+        .with_api_base(api_base)
+        .with_api_key("test-key");
+    HydeClient::new(OpenAIClient::with_config(config), "mock-model")
+        .with_timeout(Duration::from_secs(5))
+}
+
+#[tokio::test]
+async fn the_hypothetical_answer_is_searched_alongside_the_question() {
+    let hyde = mock_llm(Router::new().route(
+        "/v1/chat/completions",
+        post(|Json(request): Json<Value>| async move {
+            assert_eq!(request["model"], "mock-model");
+            Json(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "mock-model",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "  Use `timeout` to cancel a future after a duration.  "
+                    },
+                    "finish_reason": "stop"
+                }]
+            }))
+        }),
+    ))
+    .await;
+
+    let question = "how do I give up on slow things?";
+    let queries = hyde
+        .expand("tokio", question, vec![question.to_string()])
+        .await;
+    assert_eq!(
+        queries,
+        [
+            question.to_string(),
+            "Use `timeout` to cancel a future after a duration.".to_string()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn a_failing_llm_falls_back_to_the_plain_question() {
+    let hyde = mock_llm(Router::new().route(
+        "/v1/chat/completions",
+        post(|| async {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": {
+                        "message": "model not found",
+                        "type": "invalid_request_error",
+                        "param": null,
+                        "code": null
+                    }
+                })),
+            )
+                .into_response()
+        }),
+    ))
+    .await;
+
+    let question = "how do I give up on slow things?";
+    assert!(hyde.draft("tokio", question).await.is_err());
+    assert_eq!(
+        hyde.expand("tokio", question, vec![question.to_string()])
+            .await,
+        [question.to_string()]
+    );
+}
@@ -0,0 +1,61 @@
+use rustdocs_mcp_server::{
+    doc_loader::{self, Document},
+    language::{self, LanguageAction, LanguageFilter},
+};
+use scraper::Html;
+
+/// One document per section of the mixed-language fixture page
+fn fixture_documents() -> Vec<Document> {
+    let html = Html::parse_document(include_str!("fixtures/mixed_languages.html"));
+    let selector = doc_loader::parse_content_selector(None).unwrap();
+    doc_loader::page_documents("demo/latest/demo/codec/index.html", &html, &selector)
+}
+
+#[test]
+fn sections_are_detected_by_language() {
+    let detected: Vec<(String, Option<&str>)> = fixture_documents()
+        .iter()
+        .map(|doc| {
+            let anchor = doc.path.split_once('#').map_or("", |(_, a)| a);
+            (
+                anchor.to_string(),
+                language::detect(&doc.content).map(|l| l.code()),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        detected,
+        [
+            (String::new(), Some("eng")),
+            ("usage".to_string(), Some("eng")),
+            ("中文".to_string(), Some("cmn")),
+            ("русский".to_string(), Some("rus")),
+            // A code snippet has no reliable language and is always kept
+            ("example".to_string(), None),
+        ]
+    );
+}
+
+#[test]
+fn filters_are_built_from_codes_and_an_action() {
+    assert_eq!(LanguageFilter::new(&[], Some("tag")), Ok(None));
+
+    let filter = LanguageFilter::new(&["eng".to_string()], None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(filter.action(), LanguageAction::Drop);
+    assert_eq!(filter.codes(), ["eng"]);
+    assert!(filter.allows(whatlang::Lang::Eng));
+    assert!(!filter.allows(whatlang::Lang::Cmn));
+
+    let tagging = LanguageFilter::new(&["eng".to_string(), "rus".to_string()], Some("tag"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(tagging.action(), LanguageAction::Tag);
+
+    assert!(LanguageFilter::new(&["english".to_string()], None).is_err());
+    assert!(LanguageFilter::new(&["eng".to_string()], Some("translate")).is_err());
+    assert_eq!(language::language_code_problem("eng"), None);
+    assert!(language::language_code_problem("en").is_some());
+}
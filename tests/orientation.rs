@@ -0,0 +1,77 @@
+use rustdocs_mcp_server::{
+    database::PrecomputedAnswer,
+    orientation::{self, OrientationStats, DEFAULT_MATCH_THRESHOLD},
+};
+
+fn answer(question_key: &str, embedding: Vec<f32>) -> PrecomputedAnswer {
+    PrecomputedAnswer {
+        question_key: question_key.to_string(),
+        question: format!("{question_key}?"),
+        model: "test-model".to_string(),
+        embedding,
+        results: Vec::new(),
+    }
+}
+
+/// A unit vector at `degrees` from the x axis; cos(25.84°) ≈ 0.9
+fn at_angle(degrees: f32) -> Vec<f32> {
+    let radians = degrees.to_radians();
+    vec![radians.cos(), radians.sin(), 0.0]
+}
+
+#[test]
+fn questions_match_the_closest_answer_at_or_above_the_threshold() {
+    let answers = [
+        answer("getting_started", at_angle(0.0)),
+        answer("main_types", at_angle(90.0)),
+    ];
+
+    let (matched, similarity) =
+        orientation::best_match(&at_angle(10.0), &answers, DEFAULT_MATCH_THRESHOLD).unwrap();
+    assert_eq!(matched.question_key, "getting_started");
+    assert!((similarity - 10.0_f32.to_radians().cos()).abs() < 1e-6);
+
+    let (matched, _) =
+        orientation::best_match(&at_angle(85.0), &answers, DEFAULT_MATCH_THRESHOLD).unwrap();
+    assert_eq!(matched.question_key, "main_types");
+
+    // Just inside and just outside cos⁻¹(0.9)
+    assert!(orientation::best_match(&at_angle(25.0), &answers, 0.9).is_some());
+    assert!(orientation::best_match(&at_angle(27.0), &answers, 0.9).is_none());
+    // Halfway between the two is close to neither
+    assert!(orientation::best_match(&at_angle(45.0), &answers, 0.9).is_none());
+    // A threshold above 1 never matches, not even the question itself
+    assert!(orientation::best_match(&at_angle(0.0), &answers, 1.01).is_none());
+}
+
+#[test]
+fn answers_embedded_with_another_dimension_never_match() {
+    let answers = [answer("getting_started", vec![1.0, 0.0])];
+    assert!(orientation::best_match(&[1.0, 0.0, 0.0], &answers, 0.5).is_none());
+    assert!(orientation::best_match(&[], &answers, 0.0).is_none());
+}
+
+#[test]
+fn hit_rate_counts_every_lookup() {
+    let stats = OrientationStats::default();
+    assert_eq!(stats.snapshot().hit_rate, 0.0);
+
+    stats.record(true);
+    stats.record(false);
+    stats.record(false);
+    stats.record(true);
+    let snapshot = stats.snapshot();
+    assert_eq!((snapshot.hits, snapshot.misses), (2, 2));
+    assert_eq!(snapshot.hit_rate, 0.5);
+}
+
+#[test]
+fn orientation_questions_have_unique_keys() {
+    let mut keys: Vec<_> = orientation::ORIENTATION_QUESTIONS
+        .iter()
+        .map(|(key, _)| *key)
+        .collect();
+    keys.sort_unstable();
+    keys.dedup();
+    assert_eq!(keys.len(), orientation::ORIENTATION_QUESTIONS.len());
+}
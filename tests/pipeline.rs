@@ -0,0 +1,789 @@
+use rustdocs_mcp_server::{
+    database::{CrateConfig, Database, EMBEDDING_DIMENSION},
+    doc_loader::{self, CrawlSummary, Document},
+    embeddings::EmbeddingProvider,
+    error::ServerError,
+    language::LanguageFilter,
+    pipeline::{self, ChunkSink, EmbeddedChunk, PageChunk, PipelineOptions, Progress, Stage},
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Returns a constant vector per input without any network access
+struct MockProvider;
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for MockProvider {
+    async fn generate_embeddings(
+        &self,
+        texts: &[String],
+    ) -> Result<(Vec<Vec<f32>>, usize), ServerError> {
+        Ok((vec![vec![0.5; 4]; texts.len()], texts.len()))
+    }
+
+    fn get_model_name(&self) -> &str {
+        "mock"
+    }
+}
+
+/// Pipeline events in the order they happened
+type EventLog = Arc<Mutex<Vec<String>>>;
+
+struct RecordingSink {
+    log: EventLog,
+}
+
+#[async_trait::async_trait]
+impl ChunkSink for RecordingSink {
+    async fn insert_batch(&mut self, batch: Vec<EmbeddedChunk>) -> Result<(), ServerError> {
+        let mut log = self.log.lock().unwrap();
+        for chunk in batch {
+            let mut event = format!("insert {}", chunk.path);
+            if let Some(language) = chunk.language {
+                event.push_str(&format!(" [{language}]"));
+            }
+            if let Some(section) = chunk.section {
+                event.push_str(&format!(" {{{section}}}"));
+            }
+            log.push(event);
+        }
+        Ok(())
+    }
+}
+
+async fn slow_crawl(
+    log: EventLog,
+    pages: usize,
+    documents: tokio::sync::mpsc::Sender<Document>,
+) -> Result<CrawlSummary, ServerError> {
+    for page in 0..pages {
+        let doc = Document {
+            path: format!("demo/latest/demo/page{page}.html"),
+            content: format!("Documentation for page {page} of the demo crate."),
+            section: None,
+        };
+        documents.send(doc).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    log.lock().unwrap().push("crawl finished".to_string());
+    Ok(CrawlSummary::default())
+}
+
+fn options(skip_paths: HashSet<String>) -> PipelineOptions {
+    PipelineOptions {
+        document_capacity: 2,
+        chunk_capacity: 2,
+        embedding_capacity: 2,
+        batch_size: 2,
+        embed_concurrency: 2,
+        skip_paths,
+        ..PipelineOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn inserts_begin_before_crawling_completes() {
+    let log = EventLog::default();
+    let mut sink = RecordingSink { log: log.clone() };
+
+    let stats = pipeline::run(
+        |tx| slow_crawl(log.clone(), 10, tx),
+        Arc::new(MockProvider),
+        &mut sink,
+        &options(HashSet::new()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.documents, 10);
+    assert_eq!(stats.stored_chunks, 10);
+    let log = log.lock().unwrap();
+    let first_insert = log.iter().position(|e| e.starts_with("insert")).unwrap();
+    let crawl_finished = log.iter().position(|e| e == "crawl finished").unwrap();
+    assert!(
+        first_insert < crawl_finished,
+        "first insert should land while crawling: {log:?}"
+    );
+    assert_eq!(log.iter().filter(|e| e.starts_with("insert")).count(), 10);
+}
+
+#[tokio::test]
+async fn resuming_skips_chunks_that_are_already_stored() {
+    let log = EventLog::default();
+    let mut sink = RecordingSink { log: log.clone() };
+    let stored: HashSet<String> = (0..3)
+        .map(|page| format!("demo/latest/demo/page{page}.html"))
+        .collect();
+
+    let stats = pipeline::run(
+        |tx| slow_crawl(log.clone(), 5, tx),
+        Arc::new(MockProvider),
+        &mut sink,
+        &options(stored),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.documents, 5);
+    assert_eq!(stats.skipped_chunks, 3);
+    assert_eq!(stats.stored_chunks, 2);
+    let inserted: Vec<_> = log
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|e| e.starts_with("insert"))
+        .cloned()
+        .collect();
+    assert_eq!(
+        inserted.len(),
+        2,
+        "only pages 3 and 4 should be embedded: {inserted:?}"
+    );
+    assert!(inserted
+        .iter()
+        .all(|e| e.contains("page3") || e.contains("page4")));
+}
+
+#[tokio::test]
+async fn max_chunks_keeps_the_earliest_pages_and_stops_the_crawl() {
+    let log = EventLog::default();
+    let mut sink = RecordingSink { log: log.clone() };
+    let crawl = |tx: tokio::sync::mpsc::Sender<Document>| {
+        let log = log.clone();
+        async move {
+            for page in 0..20 {
+                let doc = Document {
+                    path: format!("demo/latest/demo/page{page}.html"),
+                    content: format!("Documentation for page {page} of the demo crate."),
+                    section: None,
+                };
+                if tx.send(doc).await.is_err() {
+                    log.lock()
+                        .unwrap()
+                        .push(format!("crawl stopped at page{page}"));
+                    break;
+                }
+            }
+            Ok(CrawlSummary::default())
+        }
+    };
+    // page0 is already stored and counts toward the limit
+    let options = PipelineOptions {
+        max_chunks: Some(4),
+        ..options(HashSet::from(["demo/latest/demo/page0.html".to_string()]))
+    };
+
+    let stats = pipeline::run(crawl, Arc::new(MockProvider), &mut sink, &options)
+        .await
+        .unwrap();
+
+    assert!(stats.truncated);
+    assert!(stats.crawl.diagnostics.hit_max_embeddings);
+    assert_eq!(stats.skipped_chunks + stats.stored_chunks, 4);
+    let log = log.lock().unwrap();
+    let inserted: Vec<_> = log.iter().filter(|e| e.starts_with("insert")).collect();
+    assert_eq!(
+        inserted,
+        [
+            "insert demo/latest/demo/page1.html",
+            "insert demo/latest/demo/page2.html",
+            "insert demo/latest/demo/page3.html"
+        ]
+    );
+    assert!(
+        log.iter().any(|e| e.starts_with("crawl stopped")),
+        "the crawl should stop once the limit is reached: {log:?}"
+    );
+}
+
+#[tokio::test]
+async fn a_crate_within_max_chunks_is_not_truncated() {
+    let mut sink = RecordingSink {
+        log: EventLog::default(),
+    };
+    let options = PipelineOptions {
+        max_chunks: Some(5),
+        ..options(HashSet::new())
+    };
+    let stats = pipeline::run(
+        |tx| slow_crawl(EventLog::default(), 5, tx),
+        Arc::new(MockProvider),
+        &mut sink,
+        &options,
+    )
+    .await
+    .unwrap();
+    assert!(!stats.truncated);
+    assert_eq!(stats.stored_chunks, 5);
+}
+
+#[tokio::test]
+async fn a_deadline_keeps_what_was_chunked_and_stops_the_crawl() {
+    let log = EventLog::default();
+    let mut sink = RecordingSink { log: log.clone() };
+    let crawl = |tx: tokio::sync::mpsc::Sender<Document>| async move {
+        for page in 0..100 {
+            let doc = Document {
+                path: format!("demo/latest/demo/page{page}.html"),
+                content: format!("Documentation for page {page} of the demo crate."),
+                section: None,
+            };
+            if tx.send(doc).await.is_err() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        Ok(CrawlSummary::default())
+    };
+    let options = PipelineOptions {
+        deadline: Some(std::time::Instant::now() + Duration::from_millis(200)),
+        ..options(HashSet::new())
+    };
+
+    let stats = pipeline::run(crawl, Arc::new(MockProvider), &mut sink, &options)
+        .await
+        .unwrap();
+
+    assert!(stats.timed_out);
+    assert!(stats.crawl.diagnostics.hit_deadline);
+    assert!(stats.crawl_time < Duration::from_secs(1), "{stats:?}");
+    // Every document taken in before the deadline was embedded and stored
+    assert!(stats.documents > 0 && stats.documents < 100, "{stats:?}");
+    assert_eq!(stats.stored_chunks, stats.documents);
+    let inserted = log.lock().unwrap().len();
+    assert_eq!(inserted, stats.documents);
+}
+
+#[tokio::test]
+async fn progress_counts_what_each_stage_has_done() {
+    let log = EventLog::default();
+    let mut sink = RecordingSink { log: log.clone() };
+    let progress = Arc::new(Progress::default());
+    assert_eq!(progress.snapshot().stage, Stage::Queued);
+    assert_eq!(progress.snapshot().elapsed_secs, None);
+    let options = PipelineOptions {
+        progress: Some(progress.clone()),
+        ..options(HashSet::from(["demo/latest/demo/page0.html".to_string()]))
+    };
+
+    let crawl = |tx| {
+        let progress = progress.clone();
+        async move {
+            let summary = slow_crawl(log.clone(), 6, tx).await;
+            // Chunks are stored while the crawler is still sending documents
+            assert_eq!(progress.stage(), Stage::Crawling);
+            summary
+        }
+    };
+    let stats = pipeline::run(crawl, Arc::new(MockProvider), &mut sink, &options)
+        .await
+        .unwrap();
+
+    let snapshot = progress.snapshot();
+    assert_eq!(snapshot.stage, Stage::Embedding);
+    assert_eq!(snapshot.documents_loaded, stats.documents);
+    assert_eq!(snapshot.chunks_embedded, 5);
+    assert_eq!(snapshot.chunks_stored, stats.stored_chunks);
+    assert_eq!(snapshot.chunks_skipped, 1);
+    assert!(snapshot.elapsed_secs.is_some_and(|secs| secs > 0.0));
+}
+
+#[test]
+fn completion_is_estimated_from_the_storage_rate() {
+    let snapshot = pipeline::ProgressSnapshot {
+        stage: Stage::Crawling,
+        documents_loaded: 120,
+        chunks_embedded: 110,
+        chunks_stored: 100,
+        chunks_skipped: 100,
+        elapsed_secs: Some(50.0),
+    };
+
+    // 200 of 1000 chunks done, storing 2 per second
+    assert_eq!(
+        snapshot.estimated_remaining(1000),
+        Some(Duration::from_secs(400))
+    );
+    // Past the expected size there is nothing left to estimate from
+    assert_eq!(snapshot.estimated_remaining(200), None);
+    assert_eq!(snapshot.estimated_remaining(150), None);
+    let just_started = pipeline::ProgressSnapshot {
+        chunks_stored: 0,
+        ..snapshot
+    };
+    assert_eq!(just_started.estimated_remaining(1000), None);
+}
+
+#[tokio::test]
+async fn documents_below_the_minimum_length_are_not_embedded() {
+    let log = EventLog::default();
+    let mut sink = RecordingSink { log: log.clone() };
+    let crawl = |tx: tokio::sync::mpsc::Sender<Document>| async move {
+        for (path, content) in [
+            ("demo/latest/demo/type.Alias.html", "A type alias."),
+            (
+                "demo/latest/demo/fn.run.html",
+                "Runs the demo until every task completes.",
+            ),
+        ] {
+            let doc = Document {
+                path: path.to_string(),
+                content: content.to_string(),
+                section: None,
+            };
+            tx.send(doc).await.unwrap();
+        }
+        Ok(CrawlSummary::default())
+    };
+
+    let stats = pipeline::run(
+        crawl,
+        Arc::new(MockProvider),
+        &mut sink,
+        &options(HashSet::new()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.documents, 2);
+    assert_eq!(stats.short_documents, 1);
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["insert demo/latest/demo/fn.run.html"]
+    );
+
+    // Token thresholds apply on top of the character threshold
+    let options = PipelineOptions {
+        min_doc_chars: 0,
+        min_doc_tokens: 1000,
+        ..PipelineOptions::default()
+    };
+    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let doc = Document {
+        path: "demo/latest/demo/fn.run.html".to_string(),
+        content: "Runs the demo until every task completes.".to_string(),
+        section: None,
+    };
+    assert!(options.is_too_short(&doc, &bpe));
+}
+
+fn page_chunk(path: &str, content: &str, small: bool) -> PageChunk {
+    PageChunk {
+        path: path.to_string(),
+        content: content.to_string(),
+        tokens: content.split_whitespace().count(),
+        small,
+    }
+}
+
+#[test]
+fn small_chunks_merge_forward_then_backward_within_their_page() {
+    let page = [
+        page_chunk("demo/struct.Foo.html#method.new", "Deprecated", true),
+        page_chunk(
+            "demo/struct.Foo.html#method.len",
+            "Returns the number of elements.",
+            false,
+        ),
+        page_chunk(
+            "demo/struct.Foo.html#method.clear",
+            "Removes every element, keeping the allocation.",
+            false,
+        ),
+        page_chunk("demo/struct.Foo.html#method.iter", "An iterator.", true),
+    ];
+
+    let merge = pipeline::merge_small_chunks(&page, 100);
+
+    // The first chunk on the page has no previous neighbour and merges forward; the last
+    // has no next one and merges backward
+    assert!(merge.dropped.is_empty());
+    assert_eq!(merge.chunks.len(), 2);
+    assert_eq!(merge.chunks[0].path, "demo/struct.Foo.html#method.len");
+    assert_eq!(
+        merge.chunks[0].content,
+        "Deprecated\n\nReturns the number of elements."
+    );
+    assert_eq!(
+        merge.chunks[0].absorbed,
+        ["demo/struct.Foo.html#method.new"]
+    );
+    assert_eq!(merge.chunks[1].index, 2);
+    assert_eq!(
+        merge.chunks[1].content,
+        "Removes every element, keeping the allocation.\n\nAn iterator."
+    );
+
+    // A next neighbour that would exceed the token limit gives way to the previous one,
+    // and a chunk neither can take is dropped
+    let crowded = [page[1].clone(), page[0].clone(), page[2].clone()];
+    let merge = pipeline::merge_small_chunks(&crowded, 6);
+    assert_eq!(
+        merge.chunks[0].content,
+        "Returns the number of elements.\n\nDeprecated"
+    );
+    assert!(merge.chunks[1].absorbed.is_empty());
+    let merge = pipeline::merge_small_chunks(&page[2..], 6);
+    assert_eq!(merge.dropped, ["demo/struct.Foo.html#method.iter"]);
+}
+
+#[test]
+fn small_chunks_alone_on_their_page_are_dropped() {
+    let merge = pipeline::merge_small_chunks(
+        &[
+            page_chunk("demo/type.Alias.html", "A type alias.", true),
+            page_chunk("demo/type.Alias.html#deprecated", "Deprecated", true),
+        ],
+        100,
+    );
+    assert!(merge.chunks.is_empty());
+    assert_eq!(
+        merge.dropped,
+        ["demo/type.Alias.html", "demo/type.Alias.html#deprecated"]
+    );
+}
+
+#[tokio::test]
+async fn small_chunks_never_merge_across_pages() {
+    let log = EventLog::default();
+    let mut sink = RecordingSink { log: log.clone() };
+    let crawl = |tx: tokio::sync::mpsc::Sender<Document>| async move {
+        for (path, content) in [
+            (
+                "demo/latest/demo/fn.run.html",
+                "Runs the demo until every task completes.",
+            ),
+            ("demo/latest/demo/struct.Foo.html#method.new", "Deprecated"),
+            (
+                "demo/latest/demo/struct.Foo.html#method.len",
+                "Returns the number of elements in the collection.",
+            ),
+            ("demo/latest/demo/type.Alias.html", "A type alias."),
+        ] {
+            let doc = Document {
+                path: path.to_string(),
+                content: content.to_string(),
+                section: None,
+            };
+            tx.send(doc).await.unwrap();
+        }
+        Ok(CrawlSummary::default())
+    };
+
+    let stats = pipeline::run(
+        crawl,
+        Arc::new(MockProvider),
+        &mut sink,
+        &options(HashSet::new()),
+    )
+    .await
+    .unwrap();
+
+    // "Deprecated" leads its page and merges forward rather than into fn.run; the alias
+    // is alone on its page and is dropped rather than merged backward
+    assert_eq!(stats.merged_chunks, 1);
+    assert_eq!(stats.short_documents, 1);
+    assert_eq!(stats.crawl.diagnostics.small_chunks_merged, 1);
+    assert_eq!(stats.crawl.diagnostics.small_chunks_dropped, 1);
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            "insert demo/latest/demo/fn.run.html",
+            "insert demo/latest/demo/struct.Foo.html#method.len"
+        ]
+    );
+}
+
+/// Serves a one-page `demo` crate whose `latest` redirects to 0.4.1
+async fn redirecting_docs_mirror() -> String {
+    use axum::{
+        http::Uri,
+        response::{Html as HtmlResponse, IntoResponse, Redirect, Response},
+        Router,
+    };
+
+    async fn docs(uri: Uri) -> Response {
+        match uri.path() {
+            "/demo/latest/demo/" => Redirect::temporary("/demo/0.4.1/demo/").into_response(),
+            _ => {
+                HtmlResponse("<div class=\"docblock\"><p>Demo crate root documentation.</p></div>")
+                    .into_response()
+            }
+        }
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().fallback(docs)).await });
+    origin
+}
+
+#[tokio::test]
+async fn docs_rs_crawls_report_the_redirected_version() {
+    use rustdocs_mcp_server::doc_loader::CrawlOptions;
+
+    let crawl_options = CrawlOptions {
+        docs_origin: Some(redirecting_docs_mirror().await),
+        ..CrawlOptions::default()
+    };
+    let source = pipeline::DocumentSource::DocsRs {
+        crate_name: "demo",
+        features: None,
+        max_pages: Some(1),
+        options: &crawl_options,
+        raw_html: None,
+    };
+    let log = EventLog::default();
+    let mut sink = RecordingSink { log: log.clone() };
+
+    let stats = pipeline::run(
+        |tx| source.crawl(tx),
+        Arc::new(MockProvider),
+        &mut sink,
+        &options(HashSet::new()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.stored_chunks, 1);
+    assert_eq!(stats.crawl.version.as_deref(), Some("0.4.1"));
+}
+
+/// Embeds everything with the stored dimension, so chunks can be written to the database
+struct StoredDimensionProvider;
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for StoredDimensionProvider {
+    async fn generate_embeddings(
+        &self,
+        texts: &[String],
+    ) -> Result<(Vec<Vec<f32>>, usize), ServerError> {
+        Ok((
+            vec![vec![0.5; EMBEDDING_DIMENSION as usize]; texts.len()],
+            texts.len(),
+        ))
+    }
+
+    fn get_model_name(&self) -> &str {
+        "stored-dimension"
+    }
+}
+
+/// Needs `MCPDOCS_TEST_DATABASE_URL` pointing at a migrated, disposable database
+#[tokio::test]
+async fn populating_a_latest_config_stores_the_redirected_version_on_the_crate() {
+    use rustdocs_mcp_server::doc_loader::CrawlOptions;
+
+    let Ok(url) = std::env::var("MCPDOCS_TEST_DATABASE_URL") else {
+        eprintln!("MCPDOCS_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let db = Database::connect(&url).await.unwrap();
+    let config = db
+        .upsert_crate_config(&CrateConfig {
+            max_pages: Some(1),
+            ..CrateConfig::new("demo", "latest")
+        })
+        .await
+        .unwrap();
+    let crawl_options = CrawlOptions {
+        docs_origin: Some(redirecting_docs_mirror().await),
+        ignore_robots: true,
+        ..CrawlOptions::default()
+    };
+
+    let report = pipeline::populate_config(
+        &db,
+        Arc::new(StoredDimensionProvider),
+        &config,
+        &crawl_options,
+        None,
+        None,
+    )
+    .await;
+    let record = db.get_crate_record("demo", None).await.unwrap();
+    db.delete_crate_embeddings("demo").await.unwrap();
+    db.delete_crate_config("demo", "latest", None)
+        .await
+        .unwrap();
+
+    assert_eq!(report.unwrap().version.as_deref(), Some("0.4.1"));
+    // `latest` is only resolved by the crawl, and upsert_crate is what records it
+    assert_eq!(record.unwrap().version.as_deref(), Some("0.4.1"));
+}
+
+/// Sends the sections of a page documented in English, Chinese and Russian
+async fn mixed_language_crawl(
+    documents: tokio::sync::mpsc::Sender<Document>,
+) -> Result<CrawlSummary, ServerError> {
+    let docs = {
+        let html = scraper::Html::parse_document(include_str!("fixtures/mixed_languages.html"));
+        let selector = doc_loader::parse_content_selector(None).unwrap();
+        doc_loader::page_documents("demo/latest/demo/codec/index.html", &html, &selector)
+    };
+    for doc in docs {
+        documents.send(doc).await.unwrap();
+    }
+    Ok(CrawlSummary::default())
+}
+
+async fn run_with_languages(action: &str) -> (Vec<String>, pipeline::PipelineStats) {
+    let log = EventLog::default();
+    let mut sink = RecordingSink { log: log.clone() };
+    let options = PipelineOptions {
+        languages: LanguageFilter::new(&["eng".to_string()], Some(action)).unwrap(),
+        ..options(HashSet::new())
+    };
+    let stats = pipeline::run(
+        mixed_language_crawl,
+        Arc::new(MockProvider),
+        &mut sink,
+        &options,
+    )
+    .await
+    .unwrap();
+    // Embedding runs concurrently, so inserts can arrive in any order
+    let mut events = log.lock().unwrap().clone();
+    events.sort();
+    (events, stats)
+}
+
+#[tokio::test]
+async fn documents_in_other_languages_are_dropped_and_counted() {
+    let (events, stats) = run_with_languages("drop").await;
+
+    assert_eq!(
+        events,
+        [
+            "insert demo/latest/demo/codec/index.html",
+            "insert demo/latest/demo/codec/index.html#usage",
+        ]
+    );
+    let diagnostics = &stats.crawl.diagnostics;
+    assert_eq!(
+        diagnostics.documents_by_language,
+        BTreeMap::from([
+            ("cmn".to_string(), 1),
+            ("eng".to_string(), 2),
+            ("rus".to_string(), 1),
+        ])
+    );
+    assert_eq!(diagnostics.language_dropped, 2);
+    assert!(diagnostics.to_string().contains("(2 dropped)"));
+}
+
+#[tokio::test]
+async fn tagging_keeps_every_document_with_its_language() {
+    let (events, stats) = run_with_languages("tag").await;
+
+    assert_eq!(
+        events,
+        [
+            "insert demo/latest/demo/codec/index.html [eng]",
+            "insert demo/latest/demo/codec/index.html#usage [eng]",
+            "insert demo/latest/demo/codec/index.html#русский [rus]",
+            "insert demo/latest/demo/codec/index.html#中文 [cmn]",
+        ]
+    );
+    assert_eq!(stats.crawl.diagnostics.language_dropped, 0);
+}
+
+#[tokio::test]
+async fn without_languages_nothing_is_detected() {
+    let log = EventLog::default();
+    let mut sink = RecordingSink { log: log.clone() };
+    let stats = pipeline::run(
+        mixed_language_crawl,
+        Arc::new(MockProvider),
+        &mut sink,
+        &options(HashSet::new()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.stored_chunks, 4);
+    assert!(stats.crawl.diagnostics.documents_by_language.is_empty());
+    assert!(log.lock().unwrap().iter().all(|e| !e.contains('[')));
+}
+
+#[test]
+fn self_test_explains_why_queries_would_find_nothing() {
+    let passed = pipeline::judge_self_test(
+        "tokio",
+        Some("tokio/latest/tokio/index.html".to_string()),
+        &[("tokio/latest/tokio/index.html".to_string(), 1.0)],
+        &[],
+    );
+    assert!(passed.passed);
+    assert_eq!((passed.hits, passed.top_similarity), (1, Some(1.0)));
+    assert_eq!(passed.error, None);
+
+    let renamed = pipeline::judge_self_test("serde-json", None, &[], &["serde_json".to_string()]);
+    assert!(!renamed.passed);
+    let error = renamed.error.unwrap();
+    assert!(
+        error.contains("'serde-json'") && error.contains("serde_json"),
+        "{error}"
+    );
+
+    let unsearchable = pipeline::judge_self_test(
+        "tokio",
+        Some("tokio/latest/tokio/index.html".to_string()),
+        &[],
+        &[],
+    );
+    assert!(!unsearchable.passed);
+    assert!(unsearchable
+        .error
+        .unwrap()
+        .contains("tokio/latest/tokio/index.html"));
+}
+
+async fn run_with_sections(section_metadata: bool) -> Vec<String> {
+    let log = EventLog::default();
+    let mut sink = RecordingSink { log: log.clone() };
+    let crawl = |tx: tokio::sync::mpsc::Sender<Document>| async move {
+        let docs = {
+            let html = scraper::Html::parse_document(include_str!("fixtures/method_sections.html"));
+            let selector = doc_loader::parse_content_selector(None).unwrap();
+            doc_loader::page_documents("demo/latest/demo/struct.Stack.html", &html, &selector)
+        };
+        for doc in docs {
+            tx.send(doc).await.unwrap();
+        }
+        Ok(CrawlSummary::default())
+    };
+    let options = PipelineOptions {
+        section_metadata,
+        ..options(HashSet::new())
+    };
+    pipeline::run(crawl, Arc::new(MockProvider), &mut sink, &options)
+        .await
+        .unwrap();
+    let mut events = log.lock().unwrap().clone();
+    events.sort();
+    events
+}
+
+#[tokio::test]
+async fn section_headings_are_stored_only_for_crates_that_ask_for_them() {
+    let tagged = run_with_sections(true).await;
+    assert!(
+        tagged.contains(&"insert demo/latest/demo/struct.Stack.html#panics {Panics}".to_string())
+    );
+    assert!(
+        tagged.contains(&"insert demo/latest/demo/struct.Stack.html#safety {Safety}".to_string())
+    );
+    assert!(tagged.contains(&"insert demo/latest/demo/struct.Stack.html#method.push".to_string()));
+
+    // Off by default: the same chunks, untagged
+    let plain = run_with_sections(false).await;
+    let untagged: Vec<String> = tagged
+        .iter()
+        .map(|event| event.split(" {").next().unwrap().to_string())
+        .collect();
+    assert_eq!(plain, untagged);
+}
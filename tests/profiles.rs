@@ -0,0 +1,61 @@
+use rustdocs_mcp_server::profiles::{self, Profile};
+
+#[test]
+fn two_profiles_keep_their_own_namespace_and_embedding_configuration() {
+    let profiles = profiles::parse_profiles(
+        r#"[
+            {"name": "oss", "namespace": "oss_docs"},
+            {"name": "internal", "namespace": "internal_docs",
+             "embedding_provider": "voyage", "embedding_model": "voyage-3.5"}
+        ]"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        profiles,
+        [
+            Profile {
+                name: "oss".to_string(),
+                namespace: "oss_docs".to_string(),
+                embedding_provider: None,
+                embedding_model: None,
+            },
+            Profile {
+                name: "internal".to_string(),
+                namespace: "internal_docs".to_string(),
+                embedding_provider: Some("voyage".to_string()),
+                embedding_model: Some("voyage-3.5".to_string()),
+            },
+        ]
+    );
+    assert!(!profiles[0].has_own_embedding());
+    assert!(profiles[1].has_own_embedding());
+    assert_eq!(profiles[1].sse_path("/sse"), "/sse/internal");
+    assert_eq!(profiles[0].sse_path("/sse/"), "/sse/oss");
+}
+
+#[test]
+fn profiles_that_would_share_or_escape_their_data_are_rejected() {
+    let rejected = |json: &str| profiles::parse_profiles(json).expect_err(json).to_string();
+
+    assert!(rejected(
+        r#"[{"name": "a", "namespace": "docs"}, {"name": "b", "namespace": "docs"}]"#
+    )
+    .contains("used by more than one profile"));
+    assert!(
+        rejected(r#"[{"name": "a", "namespace": "one"}, {"name": "a", "namespace": "two"}]"#)
+            .contains("defined twice")
+    );
+    for namespace in ["public", "pg_catalog", "Docs", "1docs", "docs;drop", ""] {
+        let json = format!(r#"[{{"name": "a", "namespace": "{namespace}"}}]"#);
+        assert!(rejected(&json).contains("namespace"), "{namespace}");
+    }
+    for name in ["", "Internal", "a/b", "a b"] {
+        let json = format!(r#"[{{"name": "{name}", "namespace": "docs"}}]"#);
+        assert!(rejected(&json).contains("Profile name"), "{name}");
+    }
+    assert!(
+        rejected(r#"[{"name": "a", "namespace": "docs", "schema": "x"}]"#).contains("not valid")
+    );
+    assert_eq!(profiles::parse_profiles("[]").unwrap(), []);
+}
@@ -0,0 +1,192 @@
+use rustdocs_mcp_server::{
+    database::ScoredDoc,
+    provenance::{
+        CandidateTrail, EmbeddingProvenance, Outcome, ParameterProvenance, Provenance,
+        StageTimings, StrategyProvenance, SCHEMA_VERSION,
+    },
+    search,
+};
+use serde_json::Value;
+
+fn hit(doc_path: &str, distance: f64, version: &str) -> ScoredDoc {
+    ScoredDoc {
+        doc_path: doc_path.to_string(),
+        content: format!("Docs of {doc_path}"),
+        distance,
+        similarity: (1.0 - distance) as f32,
+        last_updated_at: None,
+        crate_version: Some(version.to_string()),
+        token_count: 0,
+    }
+}
+
+/// Dotted paths of every key in `value`, descending into the first array element
+fn key_paths(value: &Value, prefix: &str, paths: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = format!("{prefix}{key}");
+                paths.push(path.clone());
+                key_paths(value, &format!("{path}."), paths);
+            }
+        }
+        Value::Array(items) => {
+            if let Some(first) = items.first() {
+                key_paths(first, prefix, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[test]
+fn every_candidate_is_recorded_with_why_it_was_left_out() {
+    let spawn = vec![
+        hit("tokio/latest/tokio/fn.spawn.html", 0.1, "1.38.0"),
+        hit("tokio/latest/tokio/task/index.html", 0.3, "1.38.0"),
+        hit("tokio/latest/tokio/fn.spawn_blocking.html", 0.5, "1.38.0"),
+    ];
+    let join = vec![
+        hit("tokio/latest/tokio/task/index.html", 0.2, "1.38.0"),
+        hit(
+            "tokio/latest/tokio/task/struct.JoinHandle.html",
+            0.35,
+            "1.37.0",
+        ),
+        hit("tokio/latest/tokio/io/index.html", 0.9, "1.38.0"),
+    ];
+    let mut trail = CandidateTrail::default();
+    trail.consider(spawn.iter().chain(&join));
+
+    let merged = search::merge_results(vec![spawn, join], 4);
+    trail.left_out(Outcome::NotInTop, &merged);
+    let filtered: Vec<_> = merged.into_iter().filter(|d| d.similarity >= 0.6).collect();
+    trail.left_out(Outcome::BelowMinSimilarity, &filtered);
+    let deduped: Vec<_> = filtered
+        .into_iter()
+        .filter(|d| !d.doc_path.ends_with("JoinHandle.html"))
+        .collect();
+    trail.left_out(Outcome::Duplicate, &deduped);
+    let (candidates, versions) = trail.finish(&deduped[..2]);
+
+    let outcomes: Vec<(&str, Outcome)> = candidates
+        .iter()
+        .map(|c| {
+            (
+                c.doc_path.trim_start_matches("tokio/latest/tokio/"),
+                c.outcome,
+            )
+        })
+        .collect();
+    assert_eq!(
+        outcomes,
+        [
+            ("fn.spawn.html", Outcome::Returned),
+            ("task/index.html", Outcome::Returned),
+            ("task/struct.JoinHandle.html", Outcome::Duplicate),
+            ("fn.spawn_blocking.html", Outcome::BelowMinSimilarity),
+            ("io/index.html", Outcome::NotInTop),
+        ]
+    );
+    // The best similarity across queries is kept
+    assert_eq!(candidates[1].similarity, 0.8);
+    assert_eq!(versions, ["1.37.0", "1.38.0"]);
+}
+
+#[test]
+fn the_record_keeps_a_stable_schema() {
+    let mut trail = CandidateTrail::default();
+    let docs = [hit(
+        "serde/latest/serde/trait.Serialize.html",
+        0.2,
+        "1.0.200",
+    )];
+    trail.consider(&docs);
+    let (candidates, crate_versions) = trail.finish(&docs);
+    let provenance = Provenance {
+        schema_version: SCHEMA_VERSION,
+        crate_name: "serde".to_string(),
+        crate_versions,
+        embedding: EmbeddingProvenance {
+            model: "text-embedding-3-large".to_string(),
+            dimension: 3072,
+            fallback: false,
+            queries: vec!["serialize a struct".to_string()],
+        },
+        strategy: StrategyProvenance {
+            retrieval: "vector",
+            expand_query: false,
+            hyde: false,
+            reranking: vec!["generic_penalty"],
+            dedup: true,
+            languages: None,
+        },
+        parameters: ParameterProvenance {
+            limit: 5,
+            fetch_limit: 15,
+            min_similarity: None,
+            min_tokens: 0,
+            path_prefix: None,
+            section: None,
+            ef_search: None,
+            query_hint: None,
+            generic_penalty: 0.05,
+        },
+        candidates,
+        timings_ms: StageTimings::default(),
+    };
+
+    let mut paths = Vec::new();
+    key_paths(&serde_json::to_value(&provenance).unwrap(), "", &mut paths);
+    paths.sort();
+    // Changing this list means bumping SCHEMA_VERSION
+    assert_eq!(SCHEMA_VERSION, 3);
+    assert_eq!(
+        paths,
+        [
+            "candidates",
+            "candidates.doc_path",
+            "candidates.outcome",
+            "candidates.similarity",
+            "crate_name",
+            "crate_versions",
+            "embedding",
+            "embedding.dimension",
+            "embedding.fallback",
+            "embedding.model",
+            "embedding.queries",
+            "parameters",
+            "parameters.ef_search",
+            "parameters.fetch_limit",
+            "parameters.generic_penalty",
+            "parameters.limit",
+            "parameters.min_similarity",
+            "parameters.min_tokens",
+            "parameters.path_prefix",
+            "parameters.query_hint",
+            "parameters.section",
+            "schema_version",
+            "strategy",
+            "strategy.dedup",
+            "strategy.expand_query",
+            "strategy.hyde",
+            "strategy.languages",
+            "strategy.reranking",
+            "strategy.retrieval",
+            "timings_ms",
+            "timings_ms.embedding",
+            "timings_ms.query_expansion",
+            "timings_ms.ranking",
+            "timings_ms.search",
+            "timings_ms.total",
+        ]
+    );
+    assert_eq!(
+        serde_json::to_value(&provenance.candidates).unwrap(),
+        serde_json::json!([{
+            "doc_path": "serde/latest/serde/trait.Serialize.html",
+            "similarity": 0.8_f32,
+            "outcome": "returned"
+        }])
+    );
+}
@@ -0,0 +1,104 @@
+use rustdocs_mcp_server::proxy_config::{OldCrateConfig, ProxyConfig};
+use serde_json::json;
+
+fn fixture() -> ProxyConfig {
+    ProxyConfig::parse(include_str!("fixtures/proxy-config.json")).unwrap()
+}
+
+#[test]
+fn every_field_survives_a_round_trip() {
+    let config = fixture();
+    let serialized = serde_json::to_string(&config).unwrap();
+    assert_eq!(ProxyConfig::parse(&serialized).unwrap(), config);
+
+    let value: serde_json::Value = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(value["log_level"], "debug");
+    assert_eq!(value["crates"][3]["version"], "^1.2");
+    assert_eq!(value["crates"][3]["max_pages"], 300);
+    assert_eq!(value["crates"][5]["max_depth"], 3);
+}
+
+#[test]
+fn unknown_fields_are_reported_with_their_paths() {
+    assert_eq!(
+        fixture().unknown_fields(),
+        vec!["log_level", "crates[5].max_depth", "crates[5].notes"]
+    );
+}
+
+#[test]
+fn pins_and_page_limits_carry_into_the_crate_config() {
+    // (name, version_spec, max_pages, expected_docs, auto_expected_docs, features, enabled)
+    let expected = [
+        ("serde", "latest", None, 1000, true, vec![], true),
+        ("tokio", "latest", None, 1200, false, vec!["full"], true),
+        ("axum", "0.7.5", None, 1000, true, vec![], true),
+        (
+            "hyper",
+            "^1.2",
+            Some(300),
+            1000,
+            true,
+            vec!["client", "http1"],
+            false,
+        ),
+        ("bevy", "latest", Some(5000), 9000, false, vec![], true),
+        ("sqlx", "latest", None, 1000, true, vec!["postgres"], true),
+    ];
+    let config = fixture();
+    for (old, (name, version_spec, max_pages, expected_docs, auto, features, enabled)) in
+        config.crates.iter().zip(expected)
+    {
+        let migrated = old.to_crate_config(None).unwrap();
+        assert_eq!(migrated.name, name);
+        assert_eq!(migrated.version_spec, version_spec, "{name}");
+        assert_eq!(migrated.max_pages, max_pages, "{name}");
+        assert_eq!(migrated.expected_docs, expected_docs, "{name}");
+        assert_eq!(migrated.auto_expected_docs, auto, "{name}");
+        assert_eq!(migrated.features, features, "{name}");
+        assert_eq!(migrated.enabled, enabled, "{name}");
+        assert_eq!(migrated.target, None);
+    }
+}
+
+#[test]
+fn invalid_pins_are_rejected() {
+    let config = fixture();
+    let broken = config.crates.last().unwrap();
+    let error = broken.to_crate_config(None).unwrap_err().to_string();
+    assert!(
+        error.contains("broken-pin") && error.contains("one.two"),
+        "{error}"
+    );
+}
+
+#[test]
+fn updating_keeps_settings_the_file_does_not_know() {
+    let config = fixture();
+    let hyper = &config.crates[3];
+    let mut existing = hyper.to_crate_config(None).unwrap();
+    existing.id = 42;
+    existing.content_selector = Some("main".to_string());
+    existing.query_hint = Some("HTTP client and server".to_string());
+    existing.max_pages = Some(10);
+
+    let changed: OldCrateConfig = serde_json::from_value(json!({
+        "name": "hyper",
+        "features": ["client"],
+        "enabled": true,
+        "version": "^1.2",
+        "max_pages": 400
+    }))
+    .unwrap();
+    let updated = changed.to_crate_config(Some(existing)).unwrap();
+
+    assert_eq!(updated.id, 42);
+    assert_eq!(updated.max_pages, Some(400));
+    assert_eq!(updated.features, vec!["client"]);
+    assert!(updated.enabled);
+    assert_eq!(updated.content_selector.as_deref(), Some("main"));
+    assert_eq!(
+        updated.query_hint.as_deref(),
+        Some("HTTP client and server")
+    );
+}
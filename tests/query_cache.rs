@@ -0,0 +1,147 @@
+use rustdocs_mcp_server::query_cache::{repeat_note, QueryCache, QueryCacheKey, RecentQuestions};
+use std::time::Duration;
+
+fn key(crate_name: &str, question: &str) -> QueryCacheKey {
+    QueryCacheKey::new(
+        crate_name,
+        question,
+        "text-embedding-3-large",
+        "debug=false",
+    )
+}
+
+#[test]
+fn normalized_questions_share_an_entry_and_count_as_hits() {
+    let cache = QueryCache::new(10, Duration::from_secs(60));
+    cache.insert(
+        key("tokio", "How do I spawn a task?"),
+        "spawn docs".to_string(),
+    );
+
+    assert_eq!(
+        cache
+            .get(&key("tokio", "  how do i   SPAWN a task? "))
+            .as_deref(),
+        Some("spawn docs")
+    );
+    assert_eq!(cache.get(&key("axum", "How do I spawn a task?")), None);
+    assert_ne!(
+        key("tokio", "spawn"),
+        QueryCacheKey::new("tokio", "spawn", "voyage-3.5", "debug=false")
+    );
+
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses), (1, 1));
+    assert_eq!(stats.hit_rate, 0.5);
+}
+
+#[test]
+fn expired_entries_are_not_returned() {
+    let cache = QueryCache::new(10, Duration::ZERO);
+    cache.insert(key("tokio", "spawn"), "spawn docs".to_string());
+    assert_eq!(cache.get(&key("tokio", "spawn")), None);
+}
+
+#[test]
+fn least_recently_used_entry_is_evicted_when_full() {
+    let cache = QueryCache::new(2, Duration::from_secs(60));
+    cache.insert(key("tokio", "a"), "a".to_string());
+    std::thread::sleep(Duration::from_millis(2));
+    cache.insert(key("tokio", "b"), "b".to_string());
+    std::thread::sleep(Duration::from_millis(2));
+    cache.get(&key("tokio", "a"));
+    cache.insert(key("tokio", "c"), "c".to_string());
+
+    assert!(cache.get(&key("tokio", "a")).is_some());
+    assert!(cache.get(&key("tokio", "b")).is_none());
+    assert_eq!(cache.stats().evictions, 1);
+}
+
+#[test]
+fn invalidating_a_crate_leaves_other_crates_cached() {
+    let cache = QueryCache::new(10, Duration::from_secs(60));
+    cache.insert(key("tokio", "spawn"), "tokio".to_string());
+    cache.insert(key("tokio", "sleep"), "tokio".to_string());
+    cache.insert(key("axum", "router"), "axum".to_string());
+
+    assert_eq!(cache.invalidate_crate("tokio"), 2);
+    assert!(cache.get(&key("tokio", "spawn")).is_none());
+    assert!(cache.get(&key("axum", "router")).is_some());
+}
+
+#[test]
+fn zero_capacity_disables_caching() {
+    let cache = QueryCache::new(0, Duration::from_secs(60));
+    cache.insert(key("tokio", "spawn"), "tokio".to_string());
+    assert!(cache.get(&key("tokio", "spawn")).is_none());
+    assert_eq!(cache.stats().misses, 0);
+}
+
+#[test]
+fn question_embeddings_can_be_cached() {
+    let cache: QueryCache<Vec<Vec<f32>>> = QueryCache::new(10, Duration::from_secs(60));
+    cache.insert(key("tokio", "spawn"), vec![vec![0.1, 0.2]]);
+
+    assert_eq!(
+        cache.get(&key("tokio", "Spawn")),
+        Some(vec![vec![0.1, 0.2]])
+    );
+}
+
+#[test]
+fn repeats_are_detected_per_connection_until_it_closes() {
+    let recent = RecentQuestions::new(Duration::from_secs(60), 16);
+    recent.record(
+        "conn-1",
+        key("tokio", "How do I spawn a task?"),
+        "spawn docs".to_string(),
+    );
+
+    let (response, age) = recent
+        .repeat_of("conn-1", &key("tokio", "how do i spawn a task?"))
+        .unwrap();
+    assert_eq!(response, "spawn docs");
+    assert!(age < Duration::from_secs(60));
+    assert!(repeat_note(Duration::from_secs(40)).contains("asked 40s ago"));
+
+    assert_eq!(
+        recent.repeat_of("conn-2", &key("tokio", "How do I spawn a task?")),
+        None
+    );
+    assert_eq!(
+        recent.repeat_of("conn-1", &key("axum", "How do I spawn a task?")),
+        None
+    );
+    let wider = QueryCacheKey::new(
+        "tokio",
+        "How do I spawn a task?",
+        "text-embedding-3-large",
+        "limit=10",
+    );
+    assert_eq!(recent.repeat_of("conn-1", &wider), None);
+
+    recent.forget_connection("conn-1");
+    assert_eq!(
+        recent.repeat_of("conn-1", &key("tokio", "How do I spawn a task?")),
+        None
+    );
+}
+
+#[test]
+fn old_questions_expire_and_each_connection_keeps_a_bounded_history() {
+    let expired = RecentQuestions::new(Duration::from_millis(1), 16);
+    expired.record("conn-1", key("tokio", "spawn"), "spawn docs".to_string());
+    std::thread::sleep(Duration::from_millis(5));
+    assert_eq!(expired.repeat_of("conn-1", &key("tokio", "spawn")), None);
+
+    let bounded = RecentQuestions::new(Duration::from_secs(60), 2);
+    for question in ["spawn", "select", "join"] {
+        bounded.record("conn-1", key("tokio", question), format!("{question} docs"));
+    }
+    assert_eq!(bounded.repeat_of("conn-1", &key("tokio", "spawn")), None);
+    assert!(bounded.repeat_of("conn-1", &key("tokio", "join")).is_some());
+
+    let disabled = RecentQuestions::new(Duration::ZERO, 16);
+    disabled.record("conn-1", key("tokio", "spawn"), "spawn docs".to_string());
+    assert_eq!(disabled.repeat_of("conn-1", &key("tokio", "spawn")), None);
+}
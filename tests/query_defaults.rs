@@ -0,0 +1,121 @@
+use rustdocs_mcp_server::{
+    database::ScoredDoc,
+    error::ServerError,
+    query_defaults::QueryDefaults,
+    search::{self, SearchParams},
+};
+use std::collections::HashMap;
+
+fn from_vars(vars: &[(&str, &str)]) -> Result<QueryDefaults, ServerError> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    QueryDefaults::from_lookup(|name| vars.get(name).cloned())
+}
+
+#[test]
+fn requests_beat_crate_defaults_which_beat_the_environment_which_beats_built_ins() {
+    let env = from_vars(&[
+        ("QUERY_DEFAULT_LIMIT", "7"),
+        ("QUERY_MIN_SIMILARITY", "0.25"),
+    ])
+    .unwrap();
+    let crate_defaults = SearchParams {
+        limit: Some(4),
+        ..Default::default()
+    };
+
+    // Request values win over everything
+    let params = SearchParams {
+        limit: Some(2),
+        min_similarity: Some(0.5),
+        path_prefix: None,
+    }
+    .or(crate_defaults.clone())
+    .or(env.search_params());
+    assert_eq!(params.result_limit(), 2);
+    assert_eq!(params.min_similarity, Some(0.5));
+
+    // The crate's limit wins over the environment; its unset threshold falls through
+    let params = SearchParams::default()
+        .or(crate_defaults)
+        .or(env.search_params());
+    assert_eq!(params.result_limit(), 4);
+    assert_eq!(params.min_similarity, Some(0.25));
+
+    // Only the environment
+    let params = SearchParams::default()
+        .or(SearchParams::default())
+        .or(env.search_params());
+    assert_eq!(params.result_limit(), 7);
+
+    // Nothing set anywhere: the built-in limit
+    let params = SearchParams::default().or(QueryDefaults::default().search_params());
+    assert_eq!(params.result_limit(), search::DEFAULT_RESULT_LIMIT);
+    assert_eq!(params.min_similarity, None);
+}
+
+#[test]
+fn summarize_and_the_response_budget_follow_the_same_precedence() {
+    let env = from_vars(&[
+        ("QUERY_SUMMARIZE", "off"),
+        ("QUERY_MAX_RESPONSE_TOKENS", "1500"),
+    ])
+    .unwrap();
+    assert!(!env.summarize(None));
+    assert!(env.summarize(Some(true)));
+    assert_eq!(env.max_response_tokens(None), Some(1500));
+    assert_eq!(env.max_response_tokens(Some(400)), Some(400));
+    assert_eq!(env.max_response_tokens(Some(0)), None);
+
+    let built_in = QueryDefaults::default();
+    assert!(built_in.summarize(None));
+    assert_eq!(built_in.max_response_tokens(None), None);
+
+    let effective = env.effective(3);
+    assert_eq!(effective.limit, 3);
+    assert!(!effective.summarize);
+    assert!(env
+        .describe(3)
+        .contains("summarize off, max response tokens 1500"));
+}
+
+#[test]
+fn blank_values_are_unset_and_invalid_ones_are_rejected() {
+    let blank = from_vars(&[("QUERY_DEFAULT_LIMIT", " "), ("QUERY_SUMMARIZE", "")]).unwrap();
+    assert_eq!(blank, QueryDefaults::default());
+
+    for (name, value) in [
+        ("QUERY_DEFAULT_LIMIT", "0"),
+        ("QUERY_DEFAULT_LIMIT", "50"),
+        ("QUERY_MIN_SIMILARITY", "1.5"),
+        ("QUERY_SUMMARIZE", "maybe"),
+        ("QUERY_MAX_RESPONSE_TOKENS", "lots"),
+    ] {
+        let error = from_vars(&[(name, value)]).unwrap_err().to_string();
+        assert!(error.contains(name), "{error}");
+    }
+}
+
+#[test]
+fn the_token_budget_keeps_leading_results_and_always_the_first() {
+    let docs = vec![hit("a", 300), hit("b", 300), hit("c", 300)];
+
+    assert_eq!(search::within_token_budget(&docs, 700).len(), 2);
+    assert_eq!(search::within_token_budget(&docs, 1000).len(), 3);
+    assert_eq!(search::within_token_budget(&docs, 100).len(), 1);
+    assert!(search::within_token_budget(&[], 100).is_empty());
+}
+
+fn hit(doc_path: &str, token_count: i32) -> ScoredDoc {
+    ScoredDoc {
+        doc_path: doc_path.to_string(),
+        content: String::new(),
+        distance: 0.1,
+        similarity: 0.9,
+        last_updated_at: None,
+        crate_version: None,
+        token_count,
+    }
+}
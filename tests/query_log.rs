@@ -0,0 +1,160 @@
+use chrono::{TimeZone, Utc};
+use rustdocs_mcp_server::{
+    error::ServerError,
+    query_log::{
+        question_hash, QueryLogPolicy, QueryLogSettings, QueryLogStatus, StoredQuestion,
+        CLEANUP_INTERVAL, DEFAULT_RETENTION_DAYS,
+    },
+};
+use std::collections::HashMap;
+
+const KEY: &str = "0123456789abcdef0123";
+
+fn from_vars(vars: &[(&str, &str)]) -> Result<QueryLogSettings, ServerError> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    QueryLogSettings::from_lookup(|name| vars.get(name).cloned())
+}
+
+#[test]
+fn the_log_is_off_unless_a_policy_is_set() {
+    for vars in [
+        &[][..],
+        &[("QUERY_LOG_POLICY", "off")],
+        &[("QUERY_LOG_POLICY", " ")],
+    ] {
+        let settings = from_vars(vars).unwrap();
+        assert_eq!(settings.policy, None);
+        assert_eq!(settings.store("how do I spawn?"), StoredQuestion::default());
+        assert_eq!(settings.status().policy, "off");
+    }
+}
+
+#[test]
+fn store_full_keeps_the_question_as_asked() {
+    let settings = from_vars(&[("QUERY_LOG_POLICY", "store_full")]).unwrap();
+    assert_eq!(settings.policy, Some(QueryLogPolicy::StoreFull));
+    assert_eq!(
+        settings.store("How do I spawn a task?"),
+        StoredQuestion {
+            question: Some("How do I spawn a task?".to_string()),
+            question_hash: None,
+        }
+    );
+}
+
+#[test]
+fn store_hashed_counts_repeats_without_keeping_the_text() {
+    let settings = from_vars(&[
+        ("QUERY_LOG_POLICY", "STORE_HASHED"),
+        ("QUERY_LOG_HMAC_KEY", KEY),
+    ])
+    .unwrap();
+    assert_eq!(settings.policy, Some(QueryLogPolicy::StoreHashed));
+
+    let stored = settings.store("How do I spawn a task?");
+    assert_eq!(stored.question, None);
+    let hash = stored.question_hash.unwrap();
+    assert_eq!(hash.len(), 64);
+    assert!(!hash.contains("spawn"));
+    // Repeats and rephrasings that only differ in case or spacing hash the same
+    assert_eq!(
+        settings.store("  how do i   spawn a task? ").question_hash,
+        Some(hash.clone())
+    );
+    assert_ne!(
+        settings.store("How do I join a task?").question_hash,
+        Some(hash.clone())
+    );
+    // Without the key the hash can't be reproduced
+    assert_ne!(
+        question_hash(b"another-key-of-enough-length", "How do I spawn a task?"),
+        hash
+    );
+    assert_eq!(
+        question_hash(KEY.as_bytes(), "How do I spawn a task?"),
+        hash
+    );
+}
+
+#[test]
+fn store_none_keeps_nothing_of_the_question() {
+    let settings = from_vars(&[
+        ("QUERY_LOG_POLICY", "store_none"),
+        ("QUERY_LOG_HMAC_KEY", KEY),
+    ])
+    .unwrap();
+    assert_eq!(settings.policy, Some(QueryLogPolicy::StoreNone));
+    assert_eq!(
+        settings.store("How do I spawn a task?"),
+        StoredQuestion::default()
+    );
+}
+
+#[test]
+fn misconfiguration_is_an_error_not_a_fallback() {
+    for vars in [
+        &[("QUERY_LOG_POLICY", "store_hashed")][..],
+        &[
+            ("QUERY_LOG_POLICY", "store_hashed"),
+            ("QUERY_LOG_HMAC_KEY", "short"),
+        ],
+        &[("QUERY_LOG_POLICY", "store_everything")],
+        &[
+            ("QUERY_LOG_POLICY", "store_full"),
+            ("QUERY_LOG_RETENTION_DAYS", "-1"),
+        ],
+        &[
+            ("QUERY_LOG_POLICY", "store_full"),
+            ("QUERY_LOG_RETENTION_DAYS", "a week"),
+        ],
+    ] {
+        assert!(
+            matches!(from_vars(vars), Err(ServerError::Config(_))),
+            "{vars:?}"
+        );
+    }
+}
+
+#[test]
+fn the_key_never_appears_in_debug_output_or_status() {
+    let settings = from_vars(&[
+        ("QUERY_LOG_POLICY", "store_hashed"),
+        ("QUERY_LOG_HMAC_KEY", KEY),
+    ])
+    .unwrap();
+    let debug = format!("{settings:?}");
+    assert!(!debug.contains(KEY), "{debug}");
+    assert!(debug.contains("<redacted>"));
+    let status = serde_json::to_string(&settings.status()).unwrap();
+    assert!(!status.contains(KEY), "{status}");
+}
+
+#[test]
+fn retention_defaults_to_thirty_days_and_zero_keeps_rows_forever() {
+    let now = Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap();
+    let settings = from_vars(&[("QUERY_LOG_POLICY", "store_none")]).unwrap();
+    assert_eq!(settings.retention_days, DEFAULT_RETENTION_DAYS);
+    assert_eq!(
+        settings.retention_cutoff(now),
+        Some(Utc.with_ymd_and_hms(2026, 9, 17, 12, 0, 0).unwrap())
+    );
+    assert_eq!(
+        settings.status(),
+        QueryLogStatus {
+            policy: "store_none",
+            retention_days: 30,
+            cleanup_interval_secs: Some(CLEANUP_INTERVAL.as_secs()),
+        }
+    );
+
+    let forever = from_vars(&[
+        ("QUERY_LOG_POLICY", "store_full"),
+        ("QUERY_LOG_RETENTION_DAYS", "0"),
+    ])
+    .unwrap();
+    assert_eq!(forever.retention_cutoff(now), None);
+    assert_eq!(forever.status().cleanup_interval_secs, None);
+}
@@ -0,0 +1,78 @@
+use rustdocs_mcp_server::rate_limit::{
+    RateLimiter, DEFAULT_ADD_CRATE_LIMIT_PER_MINUTE, DEFAULT_QUERY_LIMIT_PER_MINUTE,
+};
+use std::time::Duration;
+
+#[test]
+fn default_limits_allow_sixty_queries_and_five_add_crates_per_minute() {
+    assert_eq!(DEFAULT_QUERY_LIMIT_PER_MINUTE, 60);
+    assert_eq!(DEFAULT_ADD_CRATE_LIMIT_PER_MINUTE, 5);
+
+    let limiter = RateLimiter::default();
+    for _ in 0..DEFAULT_QUERY_LIMIT_PER_MINUTE {
+        limiter
+            .check("conn", "query_rust_docs", DEFAULT_QUERY_LIMIT_PER_MINUTE)
+            .unwrap();
+    }
+    let retry_after = limiter
+        .check("conn", "query_rust_docs", DEFAULT_QUERY_LIMIT_PER_MINUTE)
+        .unwrap_err();
+    assert!(retry_after > Duration::from_secs(59) && retry_after <= Duration::from_secs(60));
+
+    for _ in 0..DEFAULT_ADD_CRATE_LIMIT_PER_MINUTE {
+        limiter
+            .check("conn", "add_crate", DEFAULT_ADD_CRATE_LIMIT_PER_MINUTE)
+            .unwrap();
+    }
+    assert!(limiter
+        .check("conn", "add_crate", DEFAULT_ADD_CRATE_LIMIT_PER_MINUTE)
+        .is_err());
+}
+
+#[test]
+fn calls_leave_the_window_as_it_slides() {
+    let window = Duration::from_millis(400);
+    let limiter = RateLimiter::new(window);
+    limiter.check("conn", "query_rust_docs", 2).unwrap();
+    std::thread::sleep(window / 2);
+    limiter.check("conn", "query_rust_docs", 2).unwrap();
+    let retry_after = limiter.check("conn", "query_rust_docs", 2).unwrap_err();
+    assert!(retry_after <= window / 2, "{retry_after:?}");
+
+    // Only the first call has left the window, so exactly one more fits
+    std::thread::sleep(retry_after + Duration::from_millis(20));
+    limiter.check("conn", "query_rust_docs", 2).unwrap();
+    assert!(limiter.check("conn", "query_rust_docs", 2).is_err());
+}
+
+#[test]
+fn connections_and_buckets_are_limited_separately() {
+    let limiter = RateLimiter::default();
+    limiter.check("a", "add_crate", 1).unwrap();
+    assert!(limiter.check("a", "add_crate", 1).is_err());
+
+    // Another connection, or another bucket on the same one, has its own budget
+    limiter.check("b", "add_crate", 1).unwrap();
+    limiter.check("a", "query_rust_docs", 1).unwrap();
+    assert!(limiter.check("b", "add_crate", 1).is_err());
+    assert!(limiter.check("a", "query_rust_docs", 1).is_err());
+}
+
+#[test]
+fn a_zero_limit_disables_limiting() {
+    let limiter = RateLimiter::default();
+    for _ in 0..1000 {
+        limiter.check("conn", "query_rust_docs", 0).unwrap();
+    }
+}
+
+#[test]
+fn forgetting_a_connection_resets_only_its_counters() {
+    let limiter = RateLimiter::default();
+    limiter.check("closed", "add_crate", 1).unwrap();
+    limiter.check("open", "add_crate", 1).unwrap();
+
+    limiter.forget_connection("closed");
+    limiter.check("closed", "add_crate", 1).unwrap();
+    assert!(limiter.check("open", "add_crate", 1).is_err());
+}
@@ -0,0 +1,132 @@
+use rustdocs_mcp_server::{
+    database::CrateConfig,
+    refresh::{self, CrateDecision, Decision, RefreshStatus},
+};
+
+fn config(version_spec: &str, current_version: Option<&str>) -> CrateConfig {
+    CrateConfig {
+        current_version: current_version.map(str::to_string),
+        last_populated: Some(chrono::Utc::now()),
+        ..CrateConfig::new("demo", version_spec)
+    }
+}
+
+#[test]
+fn only_populated_latest_crates_on_docs_rs_are_checked() {
+    assert_eq!(refresh::skip_reason(&config("latest", Some("1.0.0"))), None);
+    assert_eq!(
+        refresh::skip_reason(&config("1.0.0", Some("1.0.0"))),
+        Some("pinned to a version")
+    );
+
+    let disabled = CrateConfig {
+        enabled: false,
+        ..config("latest", Some("1.0.0"))
+    };
+    let never_populated = CrateConfig {
+        last_populated: None,
+        ..config("latest", None)
+    };
+    let rustdoc_json = CrateConfig {
+        rustdoc_json_url: Some("https://ci.example.com/demo.json".to_string()),
+        ..config("latest", Some("1.0.0"))
+    };
+    assert_eq!(refresh::skip_reason(&disabled), Some("disabled"));
+    assert_eq!(
+        refresh::skip_reason(&never_populated),
+        Some("never populated")
+    );
+    assert_eq!(
+        refresh::skip_reason(&rustdoc_json),
+        Some("populated from rustdoc JSON, not docs.rs")
+    );
+}
+
+#[test]
+fn only_a_changed_version_is_re_populated() {
+    let populated = config("latest", Some("1.2.0"));
+
+    assert_eq!(
+        refresh::decide(&populated, Some("1.2.0")),
+        Decision::Unchanged {
+            version: "1.2.0".to_string()
+        }
+    );
+    assert_eq!(
+        refresh::decide(&populated, Some("1.3.0")),
+        Decision::Updated {
+            from: Some("1.2.0".to_string()),
+            to: "1.3.0".to_string()
+        }
+    );
+    // A crate stored before versions were recorded is re-populated to record one
+    assert_eq!(
+        refresh::decide(&config("latest", None), Some("1.3.0")),
+        Decision::Updated {
+            from: None,
+            to: "1.3.0".to_string()
+        }
+    );
+    assert!(matches!(
+        refresh::decide(&populated, None),
+        Decision::Skipped { .. }
+    ));
+}
+
+#[test]
+fn a_run_reports_checked_updated_and_skipped_crates() {
+    let decision = |name: &str, decision: Decision, job_id: Option<i32>| CrateDecision {
+        crate_name: name.to_string(),
+        target: None,
+        decision,
+        job_id,
+    };
+    let started_at = chrono::Utc::now();
+    let mut status = RefreshStatus::default();
+
+    status.record_run(
+        started_at,
+        vec![
+            decision(
+                "tokio",
+                Decision::Updated {
+                    from: Some("1.37.0".to_string()),
+                    to: "1.38.0".to_string(),
+                },
+                Some(7),
+            ),
+            decision(
+                "serde",
+                Decision::Unchanged {
+                    version: "1.0.200".to_string(),
+                },
+                None,
+            ),
+            decision(
+                "axum",
+                Decision::Skipped {
+                    reason: "pinned to a version".to_string(),
+                },
+                None,
+            ),
+        ],
+        None,
+    );
+
+    assert_eq!(
+        (status.runs, status.checked, status.updated, status.skipped),
+        (1, 2, 1, 1)
+    );
+    assert_eq!(status.last_started_at, Some(started_at));
+    assert_eq!(
+        serde_json::to_value(&status.decisions[0]).unwrap(),
+        serde_json::json!({
+            "crate_name": "tokio",
+            "target": null,
+            "decision": "updated",
+            "from": "1.37.0",
+            "to": "1.38.0",
+            "job_id": 7
+        })
+    );
+}
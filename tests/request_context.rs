@@ -0,0 +1,116 @@
+use rmcp::{model::CallToolResult, Error as McpError};
+use rustdocs_mcp_server::request_context;
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+use tokio_util::sync::CancellationToken;
+use tracing_subscriber::fmt::MakeWriter;
+
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for CapturedLogs {
+    type Writer = CapturedLogs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[tokio::test]
+async fn request_id_appears_in_error_and_logs() {
+    let logs = CapturedLogs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(logs.clone())
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let result: Result<CallToolResult, McpError> =
+        request_context::dispatch("conn-42", "query_rust_docs", None, async {
+            tracing::info!("inside tool");
+            Err(McpError::internal_error("embedding failed", None))
+        })
+        .await;
+
+    let error = result.unwrap_err();
+    let request_id = error.data.as_ref().unwrap()["request_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        error.message,
+        format!("[req {request_id}] embedding failed")
+    );
+
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    let tool_lines: Vec<&str> = logs.lines().collect();
+    assert!(
+        tool_lines.len() >= 3,
+        "expected start, inner and failure events: {logs}"
+    );
+    for line in tool_lines {
+        assert!(line.contains("conn-42"), "{line}");
+        assert!(line.contains(&request_id), "{line}");
+    }
+}
+
+#[test]
+fn tag_error_keeps_existing_data() {
+    let error = McpError::invalid_request(
+        "Rate limit exceeded",
+        Some(serde_json::json!({ "retry_after_secs": 5 })),
+    );
+    let tagged = request_context::tag_error(error, "7f3a");
+
+    assert_eq!(tagged.message, "[req 7f3a] Rate limit exceeded");
+    let data = tagged.data.unwrap();
+    assert_eq!(data["retry_after_secs"], 5);
+    assert_eq!(data["request_id"], "7f3a");
+}
+
+#[tokio::test]
+async fn a_disconnect_aborts_the_call_and_is_not_logged_as_a_failure() {
+    let logs = CapturedLogs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(logs.clone())
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let disconnected = CancellationToken::new();
+    let trigger = disconnected.clone();
+    let result = request_context::dispatch(
+        "conn-7",
+        "query_rust_docs",
+        Some(&disconnected),
+        async move {
+            trigger.cancel();
+            tokio::task::yield_now().await;
+            std::future::pending::<Result<CallToolResult, McpError>>().await
+        },
+    )
+    .await;
+
+    let error = result.unwrap_err();
+    assert!(
+        error.message.contains("client disconnected"),
+        "{}",
+        error.message
+    );
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(logs.contains("aborted"), "{logs}");
+    assert!(!logs.contains("failed"), "{logs}");
+}
@@ -0,0 +1,79 @@
+use rustdocs_mcp_server::response::split_response;
+
+/// Strip the `[part i/n]` marker and check it numbers the parts in order
+fn unmarked(parts: &[String]) -> Vec<&str> {
+    let total = parts.len();
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            let marker = format!("[part {}/{total}]\n", i + 1);
+            part.strip_prefix(&marker)
+                .unwrap_or_else(|| panic!("missing {marker:?} in {part:?}"))
+        })
+        .collect()
+}
+
+fn fences_balanced(text: &str) -> bool {
+    text.lines()
+        .filter(|line| line.trim_start().starts_with("```"))
+        .count()
+        % 2
+        == 0
+}
+
+#[test]
+fn small_responses_stay_a_single_unmarked_item() {
+    assert_eq!(split_response("short answer", 100), ["short answer"]);
+    let long = "word ".repeat(100);
+    assert_eq!(split_response(&long, 0), [long]);
+}
+
+#[test]
+fn large_responses_split_at_paragraphs_and_keep_everything() {
+    let text = (1..=6)
+        .map(|i| {
+            format!(
+                "{i}. Result number {i} with some explanation.\nSource: https://docs.rs/demo/{i}\n"
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let parts = split_response(&text, 120);
+
+    assert!(parts.len() > 1);
+    let pieces = unmarked(&parts);
+    assert_eq!(pieces.concat(), text);
+    for piece in &pieces {
+        assert!(piece.len() <= 120, "{piece:?}");
+        // Results are never separated from their source line
+        assert!(!piece.starts_with("Source:"), "{piece:?}");
+    }
+}
+
+#[test]
+fn split_points_never_fall_inside_code_fences() {
+    let example =
+        "```rust\nlet rt = Runtime::new()?;\nrt.block_on(async {\n    work().await;\n});\n```\n";
+    let text = format!(
+        "Intro line one.\nIntro line two.\n{example}Between the examples.\n{example}Outro.\n"
+    );
+    for max in [30, 60, 90, 120] {
+        let parts = split_response(&text, max);
+        let pieces = unmarked(&parts);
+        assert_eq!(pieces.concat(), text, "max {max}");
+        for piece in &pieces {
+            assert!(fences_balanced(piece), "max {max} split a fence: {piece:?}");
+        }
+    }
+}
+
+#[test]
+fn an_oversized_code_block_stays_whole() {
+    let block = format!("```\n{}```\n", "line of code\n".repeat(20));
+    let text = format!("Before.\n{block}After.\n");
+    let pieces_owned = split_response(&text, 50);
+    let pieces = unmarked(&pieces_owned);
+    assert!(pieces.contains(&block.as_str()));
+    assert_eq!(pieces.concat(), text);
+}
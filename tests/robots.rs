@@ -0,0 +1,162 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode, Uri},
+    response::{Html, IntoResponse, Response},
+    Router,
+};
+use rustdocs_mcp_server::{
+    doc_loader::{self, CrawlOptions, DocLoaderError},
+    robots::RobotsTxt,
+};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn longest_matching_rule_wins() {
+    let robots = RobotsTxt::parse(
+        "User-agent: *\nDisallow: /crate/\nAllow: /crate/latest/\nDisallow: /*.json$\n",
+        "rustdocs-mcp-server",
+    );
+
+    assert!(robots.is_allowed("/"));
+    assert!(!robots.is_allowed("/crate/1.0.0/index.html"));
+    assert!(robots.is_allowed("/crate/latest/index.html"));
+    assert!(!robots.is_allowed("/other/search-index.json"));
+    assert!(robots.is_allowed("/other/search-index.json.html"));
+}
+
+#[test]
+fn our_group_takes_precedence_over_the_wildcard_group() {
+    let body = "\
+User-agent: *
+Disallow: /
+
+User-agent: rustdocs-mcp-server
+Crawl-delay: 2
+Disallow: /private/
+";
+    let robots = RobotsTxt::parse(body, "rustdocs-mcp-server");
+
+    assert!(robots.is_allowed("/tokio/latest/tokio/"));
+    assert!(!robots.is_allowed("/private/page.html"));
+    assert_eq!(
+        robots.crawl_delay(),
+        Some(std::time::Duration::from_secs(2))
+    );
+}
+
+/// Requests seen by the mock docs host: (path, user agent)
+type RequestLog = Arc<Mutex<Vec<(String, String)>>>;
+
+async fn mock_docs_host(
+    State((robots, log)): State<(&'static str, RequestLog)>,
+    headers: HeaderMap,
+    uri: Uri,
+) -> Response {
+    let user_agent = headers
+        .get("user-agent")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    log.lock()
+        .unwrap()
+        .push((uri.path().to_string(), user_agent));
+
+    let page = |body: &str| {
+        Html(format!(
+            "<html><body><div class=\"docblock\"><p>{body}</p></div>\
+             <a href=\"public/index.html\">public</a>\
+             <a href=\"private/index.html\">private</a></body></html>"
+        ))
+        .into_response()
+    };
+    match uri.path() {
+        "/robots.txt" => robots.into_response(),
+        "/demo/latest/demo/" => page("Crate root docs."),
+        "/demo/latest/demo/public/index.html" => page("Public module docs."),
+        "/demo/latest/demo/private/index.html" => page("Private module docs."),
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn serve(robots: &'static str) -> (String, RequestLog) {
+    let log = RequestLog::default();
+    let app = Router::new()
+        .fallback(mock_docs_host)
+        .with_state((robots, log.clone()));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (origin, log)
+}
+
+fn options(origin: &str, ignore_robots: bool) -> CrawlOptions {
+    CrawlOptions {
+        docs_origin: Some(origin.to_string()),
+        ignore_robots,
+        ..CrawlOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn disallowed_pages_are_skipped_and_requests_identify_the_crawler() {
+    let (origin, log) = serve("User-agent: *\nDisallow: /demo/latest/demo/private/\n").await;
+
+    let result = doc_loader::load_documents_from_docs_rs(
+        "demo",
+        "*",
+        None,
+        Some(10),
+        &options(&origin, false),
+    )
+    .await
+    .unwrap();
+
+    let paths: Vec<&str> = result.documents.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        ["demo/latest/demo/", "demo/latest/demo/public/index.html"]
+    );
+
+    let diagnostics = &result.diagnostics;
+    assert_eq!(diagnostics.pages_fetched, 2);
+    assert_eq!(diagnostics.pages_skipped["robots"], 1);
+    assert!(diagnostics.pages_failed["not_found"] > 0);
+    assert!(diagnostics.bytes_downloaded > 0);
+    assert!(!diagnostics.hit_max_pages);
+
+    let log = log.lock().unwrap();
+    assert!(log
+        .iter()
+        .all(|(path, _)| !path.starts_with("/demo/latest/demo/private/")));
+    assert!(log
+        .iter()
+        .all(|(_, agent)| agent.starts_with("rustdocs-mcp-server/")
+            && agent.ends_with("(+https://github.com/5dlabs/rust-docs)")));
+}
+
+#[tokio::test]
+async fn disallowed_crate_root_fails_unless_robots_are_ignored() {
+    let (origin, _log) = serve("User-agent: *\nDisallow: /demo/\n").await;
+
+    let err = doc_loader::load_documents_from_docs_rs(
+        "demo",
+        "*",
+        None,
+        Some(10),
+        &options(&origin, false),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, DocLoaderError::RobotsDisallowed(_)));
+
+    let result = doc_loader::load_documents_from_docs_rs(
+        "demo",
+        "*",
+        None,
+        Some(10),
+        &options(&origin, true),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result.documents.len(), 3);
+}
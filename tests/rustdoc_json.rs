@@ -0,0 +1,49 @@
+use rustdocs_mcp_server::{doc_loader::DocLoaderError, rustdoc_json};
+use std::io::Write;
+
+const FIXTURE: &str = include_str!("fixtures/rustdoc.json");
+
+#[test]
+fn documented_items_map_to_docs_rs_paths() {
+    let result = rustdoc_json::parse_rustdoc_json(FIXTURE.as_bytes(), "widgets").unwrap();
+
+    let paths: Vec<&str> = result.documents.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "widgets/latest/widgets/enum.Shape.html",
+            "widgets/latest/widgets/enum.Shape.html#variant.Circle",
+            "widgets/latest/widgets/index.html",
+            "widgets/latest/widgets/render/index.html",
+            "widgets/latest/widgets/render/struct.Canvas.html",
+            "widgets/latest/widgets/render/struct.Canvas.html#method.draw",
+            "widgets/latest/widgets/render/struct.Canvas.html#structfield.width",
+        ]
+    );
+    assert_eq!(result.version.as_deref(), Some("0.3.1"));
+
+    let draw = &result.documents[5];
+    assert_eq!(
+        draw.content,
+        "method widgets::render::Canvas::draw\n\nDraw a shape onto the canvas."
+    );
+}
+
+#[test]
+fn gzipped_json_is_accepted() {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(FIXTURE.as_bytes()).unwrap();
+    let gzipped = encoder.finish().unwrap();
+
+    let result = rustdoc_json::parse_rustdoc_json(&gzipped, "widgets").unwrap();
+    assert_eq!(result.documents.len(), 7);
+}
+
+#[test]
+fn unsupported_format_version_is_rejected() {
+    let json = FIXTURE.replace("\"format_version\": 45", "\"format_version\": 12");
+    let err = rustdoc_json::parse_rustdoc_json(json.as_bytes(), "widgets").unwrap_err();
+
+    assert!(matches!(err, DocLoaderError::UnsupportedFormat(_)));
+    assert!(err.to_string().contains("format_version 12"));
+}
@@ -0,0 +1,172 @@
+use rustdocs_mcp_server::database::{
+    crate_partition_name, dimension_table, retarget_definition, schema_findings, schema_problems,
+    AuditSeverity, SchemaSnapshot, EMBEDDING_DIMENSION, REQUIRED_COLUMNS, REQUIRED_INDEXES,
+};
+
+fn complete_schema() -> SchemaSnapshot {
+    SchemaSnapshot {
+        pgvector_version: Some("0.8.0".to_string()),
+        pgvector_available: true,
+        columns: REQUIRED_COLUMNS
+            .iter()
+            .map(|(table, column, kind, _)| {
+                (
+                    (table.to_string(), column.to_string()),
+                    kind.unwrap_or("text").to_string(),
+                )
+            })
+            .collect(),
+        embedding_dimension: Some(3072),
+        indexes: REQUIRED_INDEXES
+            .iter()
+            .map(|(_, index, _)| index.to_string())
+            .collect(),
+    }
+}
+
+#[test]
+fn a_current_schema_has_no_problems() {
+    assert!(schema_problems(&complete_schema()).is_empty());
+}
+
+#[test]
+fn pgvector_without_hnsw_is_a_warning_not_a_startup_error() {
+    let mut schema = complete_schema();
+    schema.pgvector_version = Some("0.4.4".to_string());
+
+    let findings = schema_findings(&schema, None);
+    assert_eq!(findings.len(), 1, "{findings:?}");
+    assert_eq!(findings[0].check, "pgvector_hnsw");
+    assert_eq!(findings[0].severity, AuditSeverity::Warning);
+    assert!(schema_problems(&schema).is_empty());
+}
+
+#[test]
+fn a_provider_dimension_other_than_the_column_names_its_side_table() {
+    let schema = complete_schema();
+    assert!(schema_findings(&schema, Some(3072)).is_empty());
+
+    let findings = schema_findings(&schema, Some(1024));
+    assert_eq!(findings.len(), 1, "{findings:?}");
+    assert_eq!(findings[0].check, "provider_dimension");
+    assert_eq!(findings[0].severity, AuditSeverity::Warning);
+    assert!(findings[0].detail.contains("doc_embeddings_1024"));
+}
+
+#[test]
+fn missing_columns_are_grouped_by_the_migration_that_adds_them() {
+    let mut schema = complete_schema();
+    schema
+        .columns
+        .remove(&("crates".to_string(), "target".to_string()));
+    schema
+        .columns
+        .remove(&("crate_configs".to_string(), "target".to_string()));
+
+    let problems = schema_problems(&schema);
+    assert_eq!(problems.len(), 1, "{problems:?}");
+    assert!(problems[0].contains("column crates.target, column crate_configs.target"));
+    assert!(problems[0].ends_with("-f sql/migrations/add_crate_targets.sql"));
+}
+
+#[test]
+fn a_missing_index_names_the_migration_that_creates_it() {
+    let mut schema = complete_schema();
+    schema.indexes.remove("idx_crates_name_target");
+    schema
+        .columns
+        .remove(&("crates".to_string(), "target".to_string()));
+
+    let problems = schema_problems(&schema);
+    assert_eq!(problems.len(), 1, "{problems:?}");
+    assert!(problems[0].contains("column crates.target, index idx_crates_name_target"));
+    assert!(problems[0].ends_with("-f sql/migrations/add_crate_targets.sql"));
+}
+
+#[test]
+fn a_fresh_database_reports_every_problem_at_once() {
+    let problems = schema_problems(&SchemaSnapshot::default());
+
+    assert!(problems[0].starts_with("pgvector is not installed"));
+    assert!(
+        problems
+            .iter()
+            .any(|p| p.contains("table crates, table doc_embeddings")
+                && p.ends_with("sql/schema.sql"))
+    );
+    assert!(problems
+        .iter()
+        .any(|p| p.contains("table crate_configs, table population_jobs")
+            && p.ends_with("add_crate_configs.sql")));
+}
+
+#[test]
+fn wrong_column_shapes_and_dimensions_are_reported() {
+    let mut schema = complete_schema();
+    schema.pgvector_version = None;
+    schema.embedding_dimension = Some(1536);
+    schema.columns.insert(
+        ("population_jobs".to_string(), "diagnostics".to_string()),
+        "text".to_string(),
+    );
+
+    let problems = schema_problems(&schema);
+    assert_eq!(problems.len(), 3, "{problems:?}");
+    assert!(problems[0].contains("CREATE EXTENSION vector"));
+    assert!(problems[1].contains("population_jobs.diagnostics (is text, expected jsonb)"));
+    assert!(problems[2].contains("1536 dimensions, expected 3072"));
+}
+
+#[test]
+fn only_non_default_dimensions_get_their_own_table() {
+    assert_eq!(dimension_table(EMBEDDING_DIMENSION as usize), None);
+    assert_eq!(
+        dimension_table(1536).as_deref(),
+        Some("doc_embeddings_1536")
+    );
+}
+
+#[test]
+fn crate_partitions_have_stable_distinct_names() {
+    let name = crate_partition_name("windows-sys");
+    assert_eq!(name, crate_partition_name("windows-sys"));
+    assert_ne!(name, crate_partition_name("windows_sys"));
+    assert!(name.starts_with("doc_embeddings_part_"));
+    assert!(name.len() <= 63, "{name} is too long for an identifier");
+    // Must never be mistaken for a dimension table
+    assert!(!name
+        .trim_start_matches("doc_embeddings_")
+        .chars()
+        .all(|c| c.is_ascii_digit()));
+}
+
+#[test]
+fn index_and_trigger_definitions_move_to_the_partitioned_table() {
+    assert_eq!(
+        retarget_definition(
+            "CREATE INDEX idx_doc_embeddings_content_tsv ON public.doc_embeddings USING gin (content_tsv)",
+            "idx_doc_embeddings_content_tsv_new",
+            "doc_embeddings_partitioned",
+        )
+        .as_deref(),
+        Some("CREATE INDEX IF NOT EXISTS idx_doc_embeddings_content_tsv_new ON doc_embeddings_partitioned USING gin (content_tsv)")
+    );
+    assert_eq!(
+        retarget_definition(
+            "CREATE TRIGGER update_doc_embeddings_content_tsv BEFORE INSERT OR UPDATE OF content ON public.doc_embeddings FOR EACH ROW EXECUTE FUNCTION update_content_tsv_column()",
+            "update_doc_embeddings_content_tsv",
+            "doc_embeddings_partitioned",
+        )
+        .as_deref(),
+        Some("CREATE TRIGGER update_doc_embeddings_content_tsv BEFORE INSERT OR UPDATE OF content ON doc_embeddings_partitioned FOR EACH ROW EXECUTE FUNCTION update_content_tsv_column()")
+    );
+    // Unique indexes must include the partition key, so they aren't copied blindly
+    assert_eq!(
+        retarget_definition(
+            "CREATE UNIQUE INDEX doc_embeddings_pkey ON public.doc_embeddings USING btree (id)",
+            "doc_embeddings_pkey_new",
+            "doc_embeddings_partitioned",
+        ),
+        None
+    );
+}
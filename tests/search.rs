@@ -0,0 +1,593 @@
+use rustdocs_mcp_server::{database::ScoredDoc, search};
+use std::{collections::HashMap, sync::Arc};
+
+#[test]
+fn source_url_keeps_anchor_and_drops_chunk_suffix() {
+    assert_eq!(
+        search::source_url("tokio/latest/tokio/runtime/index.html#shutdown [chunk 2/3]"),
+        "https://docs.rs/tokio/latest/tokio/runtime/index.html#shutdown"
+    );
+    assert_eq!(
+        search::source_url("tokio/latest/tokio/runtime/index.html"),
+        "https://docs.rs/tokio/latest/tokio/runtime/index.html"
+    );
+}
+
+#[test]
+fn related_items_for_a_method_hit_are_other_anchors_on_the_page() {
+    let hit = "tokio/latest/tokio/runtime/struct.Runtime.html#method.shutdown_timeout [chunk 1/2]";
+    let prefix = search::related_prefix(hit).unwrap();
+    assert_eq!(prefix, "tokio/latest/tokio/runtime/struct.Runtime.html#");
+
+    let candidates = vec![
+        "tokio/latest/tokio/runtime/struct.Runtime.html#method.block_on".to_string(),
+        "tokio/latest/tokio/runtime/struct.Runtime.html#method.shutdown_timeout [chunk 1/2]"
+            .to_string(),
+        "tokio/latest/tokio/runtime/struct.Runtime.html#method.shutdown_timeout [chunk 2/2]"
+            .to_string(),
+        "tokio/latest/tokio/runtime/struct.Runtime.html#method.spawn [chunk 1/3]".to_string(),
+        "tokio/latest/tokio/runtime/struct.Runtime.html#method.spawn [chunk 2/3]".to_string(),
+    ];
+    let labels: Vec<String> = search::related_items(&prefix, hit, &candidates, 10)
+        .into_iter()
+        .map(|item| item.label)
+        .collect();
+
+    assert_eq!(labels, vec!["method block_on", "method spawn"]);
+}
+
+#[test]
+fn related_items_for_a_page_hit_are_other_items_in_the_module() {
+    let hit = "tokio/latest/tokio/runtime/struct.Runtime.html";
+    let prefix = search::related_prefix(hit).unwrap();
+    assert_eq!(prefix, "tokio/latest/tokio/runtime/");
+
+    let candidates = vec![
+        "tokio/latest/tokio/runtime/fn.spawn.html".to_string(),
+        "tokio/latest/tokio/runtime/index.html#shutdown".to_string(),
+        "tokio/latest/tokio/runtime/struct.Builder.html#method.new".to_string(),
+        "tokio/latest/tokio/runtime/struct.Builder.html#method.build".to_string(),
+        "tokio/latest/tokio/runtime/struct.Runtime.html#method.spawn".to_string(),
+    ];
+    let items = search::related_items(&prefix, hit, &candidates, 10);
+    let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+
+    assert_eq!(
+        labels,
+        vec!["fn spawn", "module overview", "struct Builder"]
+    );
+    assert_eq!(
+        items[2].doc_path,
+        "tokio/latest/tokio/runtime/struct.Builder.html"
+    );
+}
+
+#[test]
+fn compound_questions_split_at_conjunctions_and_sentence_ends() {
+    assert_eq!(
+        search::split_question(
+            "how do I create an mpsc channel and select over it with a timeout?"
+        ),
+        [
+            "how do I create an mpsc channel",
+            "select over it with a timeout?"
+        ]
+    );
+    assert_eq!(
+        search::split_question("How do I spawn a task? How do I cancel a spawned task?"),
+        ["How do I spawn a task?", "How do I cancel a spawned task?"]
+    );
+}
+
+#[test]
+fn parts_too_short_to_search_stay_with_their_neighbour() {
+    assert_eq!(
+        search::split_question("serialize and deserialize json structs"),
+        ["serialize and deserialize json structs"]
+    );
+    let parts = search::split_question(
+        "open a file and read lines and parse numbers and sum values and print totals",
+    );
+    assert_eq!(parts.len(), search::MAX_SUB_QUERIES);
+    assert_eq!(parts[3], "sum values and print totals");
+}
+
+fn hit(doc_path: &str, distance: f64) -> ScoredDoc {
+    ScoredDoc {
+        doc_path: doc_path.to_string(),
+        content: String::new(),
+        distance,
+        similarity: (1.0 - distance) as f32,
+        last_updated_at: None,
+        crate_version: None,
+        token_count: 0,
+    }
+}
+
+#[test]
+fn merged_results_interleave_sub_queries_and_drop_duplicates() {
+    let channels = vec![hit("mpsc", 0.1), hit("channel", 0.2), hit("select", 0.5)];
+    let timeouts = vec![hit("select", 0.15), hit("timeout", 0.3), hit("sleep", 0.4)];
+
+    let merged = search::merge_results(vec![channels, timeouts], 10);
+
+    let paths: Vec<&str> = merged.iter().map(|d| d.doc_path.as_str()).collect();
+    assert_eq!(paths, ["mpsc", "select", "channel", "timeout", "sleep"]);
+    // A document found twice keeps its best score
+    assert_eq!(merged[1].distance, 0.15);
+
+    let merged = search::merge_results(vec![vec![hit("a", 0.1), hit("b", 0.2)]], 1);
+    assert_eq!(merged.len(), 1);
+}
+
+#[test]
+fn content_dedup_keeps_the_best_ranked_copy() {
+    let with_content = |path: &str, distance: f64, content: &str| ScoredDoc {
+        content: content.to_string(),
+        ..hit(path, distance)
+    };
+    let results = vec![
+        with_content(
+            "tokio/sync/struct.Mutex.html",
+            0.1,
+            "An asynchronous Mutex.",
+        ),
+        with_content(
+            "tokio/sync/mpsc/index.html",
+            0.2,
+            "A multi-producer channel.",
+        ),
+        with_content("tokio/struct.Mutex.html", 0.3, "an  asynchronous\nmutex."),
+        with_content("tokio/sync/oneshot/index.html", 0.4, "A one-shot channel."),
+    ];
+
+    let (kept, dropped) = search::dedup_by_content(results);
+
+    let paths: Vec<&str> = kept.iter().map(|d| d.doc_path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "tokio/sync/struct.Mutex.html",
+            "tokio/sync/mpsc/index.html",
+            "tokio/sync/oneshot/index.html"
+        ]
+    );
+    assert_eq!(dropped, 1);
+}
+
+#[test]
+fn generic_results_are_demoted_behind_specific_ones() {
+    let results = vec![
+        hit("tokio/struct.Mutex.html#impl-Send", 0.1),
+        hit("tokio/sync/struct.Mutex.html", 0.15),
+        hit("tokio/sync/struct.RwLock.html", 0.4),
+    ];
+    let commonness = HashMap::from([
+        ("tokio/struct.Mutex.html#impl-Send".to_string(), 0.95),
+        ("tokio/sync/struct.Mutex.html".to_string(), 0.4),
+    ]);
+
+    let ranked = search::penalize_generic(results.clone(), &commonness, 0.5);
+
+    let paths: Vec<&str> = ranked.iter().map(|d| d.doc_path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "tokio/sync/struct.Mutex.html",
+            // No commonness score, so not penalized
+            "tokio/sync/struct.RwLock.html",
+            "tokio/struct.Mutex.html#impl-Send",
+        ]
+    );
+    // Displayed similarities are the search's, not the penalized scores
+    assert_eq!(ranked[2].similarity, 0.9);
+
+    let unchanged = search::penalize_generic(results, &commonness, 0.0);
+    let paths: Vec<&str> = unchanged.iter().map(|d| d.doc_path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "tokio/struct.Mutex.html#impl-Send",
+            "tokio/sync/struct.Mutex.html",
+            "tokio/sync/struct.RwLock.html",
+        ]
+    );
+}
+
+#[test]
+fn chunk_positions_come_from_the_path_suffix() {
+    assert_eq!(
+        search::chunk_position("tokio/latest/tokio/index.html [chunk 2/3]"),
+        Some((2, 3))
+    );
+    assert_eq!(
+        search::chunk_position("tokio/latest/tokio/index.html"),
+        None
+    );
+}
+
+#[test]
+fn chunks_are_reassembled_in_order_without_repeated_overlap() {
+    let chunks = vec![
+        (
+            "demo/index.html [chunk 2/2]".to_string(),
+            "The runtime drives spawned tasks. Shutdown waits for them.".to_string(),
+        ),
+        (
+            "demo/index.html [chunk 1/2]".to_string(),
+            "Create a runtime first. The runtime drives spawned tasks.".to_string(),
+        ),
+    ];
+    assert_eq!(
+        search::reassemble_chunks(chunks),
+        "Create a runtime first. The runtime drives spawned tasks. Shutdown waits for them."
+    );
+
+    let chunks = vec![
+        (
+            "demo/a.html [chunk 1/2]".to_string(),
+            "First sentence.".to_string(),
+        ),
+        (
+            "demo/a.html [chunk 2/2]".to_string(),
+            "Second sentence.".to_string(),
+        ),
+    ];
+    assert_eq!(
+        search::reassemble_chunks(chunks),
+        "First sentence. Second sentence."
+    );
+
+    let single = vec![("demo/b.html".to_string(), "Unsplit page.".to_string())];
+    assert_eq!(search::reassemble_chunks(single), "Unsplit page.");
+}
+
+#[test]
+fn explicit_search_args_take_precedence_over_crate_defaults() {
+    let defaults = search::SearchParams {
+        limit: Some(8),
+        min_similarity: Some(0.4),
+        path_prefix: Some("sqlx/latest/sqlx/postgres/".to_string()),
+    };
+
+    // Nothing explicit: the crate's defaults apply
+    let params = search::SearchParams::default().or(defaults.clone());
+    assert_eq!(params, defaults);
+    assert_eq!(params.result_limit(), 8);
+    assert_eq!(params.path_prefix(), Some("sqlx/latest/sqlx/postgres/"));
+
+    // Explicit values win, including ones that switch a default off
+    let params = search::SearchParams {
+        limit: Some(3),
+        min_similarity: Some(0.0),
+        path_prefix: Some(String::new()),
+    }
+    .or(defaults);
+    assert_eq!(params.result_limit(), 3);
+    assert_eq!(params.min_similarity, Some(0.0));
+    assert_eq!(params.path_prefix(), None);
+
+    // Without either, the built-in limit applies and oversized limits are capped
+    assert_eq!(
+        search::SearchParams::default().result_limit(),
+        search::DEFAULT_RESULT_LIMIT
+    );
+    let huge = search::SearchParams {
+        limit: Some(500),
+        ..Default::default()
+    };
+    assert_eq!(huge.result_limit(), search::MAX_RESULT_LIMIT);
+}
+
+#[test]
+fn more_candidates_are_fetched_than_shown_when_some_will_be_dropped_or_reordered() {
+    let params = search::SearchParams {
+        limit: Some(8),
+        ..Default::default()
+    };
+
+    assert_eq!(params.fetch_limit(false, false), 10);
+    assert_eq!(params.fetch_limit(true, false), 16);
+    assert_eq!(params.fetch_limit(true, true), 24);
+    assert_eq!(search::SearchParams::default().fetch_limit(true, false), 10);
+}
+
+#[test]
+fn short_chunks_are_dropped_below_min_tokens() {
+    let sized = |path: &str, token_count: i32| ScoredDoc {
+        token_count,
+        ..hit(path, 0.1)
+    };
+    let docs = vec![
+        sized("tokio/sync/type.Result.html", 12),
+        sized("tokio/sync/struct.Mutex.html", 480),
+        sized("tokio/sync/index.html", 50),
+    ];
+
+    let kept: Vec<String> = search::drop_short(docs.clone(), 50)
+        .into_iter()
+        .map(|d| d.doc_path)
+        .collect();
+    assert_eq!(
+        kept,
+        ["tokio/sync/struct.Mutex.html", "tokio/sync/index.html"]
+    );
+    assert_eq!(search::drop_short(docs, 0).len(), 3);
+}
+
+#[test]
+fn query_hints_are_prepended_to_the_embedded_question() {
+    assert_eq!(
+        search::apply_query_hint("how do I run a query?", Some("async Rust SQL toolkit")),
+        "async Rust SQL toolkit: how do I run a query?"
+    );
+    assert_eq!(
+        search::apply_query_hint("how do I run a query?", Some("  ")),
+        "how do I run a query?"
+    );
+    assert_eq!(
+        search::apply_query_hint("how do I run a query?", None),
+        "how do I run a query?"
+    );
+}
+
+#[test]
+fn symbol_context_matches_partial_identifiers_case_insensitively() {
+    let content = "Service trait.\nCalled before call.\nfn poll_ready(&mut self, cx: &mut Context)\nReturns Ready when able.\nMore text.\nEnd.";
+
+    assert_eq!(
+        search::symbol_context(content, "POLL_READ", false, 1).unwrap(),
+        "Called before call.\nfn poll_ready(&mut self, cx: &mut Context)\nReturns Ready when able."
+    );
+    assert_eq!(
+        search::symbol_context(content, "Service", false, 2).unwrap(),
+        "Service trait.\nCalled before call.\nfn poll_ready(&mut self, cx: &mut Context)"
+    );
+}
+
+#[test]
+fn case_sensitive_symbol_context_skips_other_casings() {
+    let content = "ready: bool\nfn Ready()";
+
+    assert_eq!(
+        search::symbol_context(content, "Ready", true, 0).unwrap(),
+        "fn Ready()"
+    );
+    assert_eq!(search::symbol_context(content, "READY", true, 0), None);
+}
+
+#[test]
+fn results_tagged_with_other_languages_are_left_out() {
+    let results = vec![
+        hit("demo/latest/demo/codec/index.html", 0.1),
+        hit("demo/latest/demo/codec/index.html#中文", 0.2),
+        hit("demo/latest/demo/codec/index.html#usage", 0.3),
+        hit("demo/latest/demo/codec/struct.Frame.html", 0.4),
+    ];
+    let languages = HashMap::from([
+        (
+            "demo/latest/demo/codec/index.html".to_string(),
+            "eng".to_string(),
+        ),
+        (
+            "demo/latest/demo/codec/index.html#中文".to_string(),
+            "cmn".to_string(),
+        ),
+        (
+            "demo/latest/demo/codec/index.html#usage".to_string(),
+            "eng".to_string(),
+        ),
+    ]);
+
+    let (kept, dropped) = search::filter_languages(results, &languages, &["eng"]);
+    let paths: Vec<&str> = kept.iter().map(|doc| doc.doc_path.as_str()).collect();
+
+    // Untagged results (language not detected) are kept
+    assert_eq!(
+        paths,
+        [
+            "demo/latest/demo/codec/index.html",
+            "demo/latest/demo/codec/index.html#usage",
+            "demo/latest/demo/codec/struct.Frame.html",
+        ]
+    );
+    assert_eq!(dropped, 1);
+}
+
+#[test]
+fn crates_are_ranked_by_centroid_similarity_to_the_question() {
+    let centroids: HashMap<String, Arc<Vec<f32>>> = [
+        ("tokio", vec![1.0, 0.1, 0.0]),
+        ("serde", vec![0.0, 1.0, 0.0]),
+        ("axum", vec![0.8, 0.3, 0.1]),
+        ("wide", vec![1.0, 0.0, 0.0, 0.0]),
+    ]
+    .into_iter()
+    .map(|(name, centroid)| (name.to_string(), Arc::new(centroid)))
+    .collect();
+
+    let ranked = search::rank_crates(&[1.0, 0.0, 0.0], &centroids, 2);
+
+    let names: Vec<&str> = ranked.iter().map(|c| c.crate_name.as_str()).collect();
+    assert_eq!(names, ["tokio", "axum"]);
+    assert!(ranked[0].similarity > ranked[1].similarity);
+    // A centroid of another dimension is never a candidate
+    assert_eq!(
+        search::rank_crates(&[1.0, 0.0, 0.0], &centroids, 10).len(),
+        3
+    );
+}
+
+#[test]
+fn crate_results_merge_by_similarity_and_keep_their_crate() {
+    let merged = search::merge_crate_results(
+        vec![
+            ("tokio".to_string(), vec![hit("a", 0.2), hit("b", 0.5)]),
+            ("axum".to_string(), vec![hit("c", 0.1), hit("d", 0.5)]),
+        ],
+        3,
+        None,
+    );
+
+    let order: Vec<(&str, &str)> = merged
+        .iter()
+        .map(|(krate, doc)| (krate.as_str(), doc.doc_path.as_str()))
+        .collect();
+    assert_eq!(order, [("axum", "c"), ("tokio", "a"), ("tokio", "b")]);
+}
+
+#[test]
+fn a_per_crate_quota_spreads_merged_results_across_crates() {
+    let result_sets = vec![
+        (
+            "tokio".to_string(),
+            vec![
+                hit("t1", 0.1),
+                hit("t2", 0.15),
+                hit("t3", 0.2),
+                hit("t4", 0.25),
+            ],
+        ),
+        ("axum".to_string(), vec![hit("a1", 0.4), hit("a2", 0.5)]),
+        ("hyper".to_string(), vec![hit("h1", 0.45)]),
+    ];
+    let order = |merged: Vec<(String, ScoredDoc)>| -> Vec<String> {
+        merged.into_iter().map(|(_, doc)| doc.doc_path).collect()
+    };
+
+    assert_eq!(
+        order(search::merge_crate_results(result_sets.clone(), 4, None)),
+        ["t1", "t2", "t3", "t4"]
+    );
+    assert_eq!(
+        order(search::merge_crate_results(result_sets.clone(), 4, Some(2))),
+        ["t1", "t2", "a1", "h1"]
+    );
+    // A quota that leaves fewer results than the limit returns what's left
+    assert_eq!(
+        order(search::merge_crate_results(result_sets, 5, Some(1))),
+        ["t1", "a1", "h1"]
+    );
+}
+
+#[test]
+fn comparisons_keep_each_crates_results_in_its_own_section() {
+    let response = search::format_comparison(
+        "async requests",
+        &[
+            ("reqwest".to_string(), vec![hit("reqwest/a", 0.1)]),
+            ("ureq".to_string(), vec![]),
+        ],
+    );
+
+    assert!(response.starts_with("Comparing reqwest vs ureq for: async requests"));
+    let reqwest = response.find("## reqwest").unwrap();
+    let ureq = response.find("## ureq").unwrap();
+    assert!(reqwest < ureq);
+    assert!(response[reqwest..ureq].contains("Source: https://docs.rs/reqwest/a"));
+    assert!(response[ureq..].contains("No relevant documentation found"));
+}
+
+#[test]
+fn the_best_result_is_returned_without_list_formatting() {
+    let best = ScoredDoc {
+        content: "  Spawns a new asynchronous task.\n".to_string(),
+        ..hit("tokio/latest/tokio/task/fn.spawn.html", 0.125)
+    };
+
+    assert_eq!(
+        search::format_best_result(&best),
+        "Spawns a new asynchronous task.\n\nSource: https://docs.rs/tokio/latest/tokio/task/fn.spawn.html (similarity: 0.875)"
+    );
+    assert_eq!(
+        search::no_best_result("tokio", Some(0.8)),
+        "No relevant result in crate 'tokio' above the similarity threshold of 0.8"
+    );
+    assert_eq!(
+        search::no_best_result("tokio", None),
+        "No relevant result in crate 'tokio'"
+    );
+}
+
+#[test]
+fn a_heavily_downvoted_document_drops_below_an_equally_similar_neighbor() {
+    let results = vec![hit("noise.html", 0.2), hit("useful.html", 0.2)];
+    let priors = HashMap::from([("noise.html".to_string(), search::feedback_prior(0, 40))]);
+
+    let reranked = search::apply_feedback(results.clone(), &priors, 0.05);
+    let order: Vec<&str> = reranked.iter().map(|d| d.doc_path.as_str()).collect();
+    assert_eq!(order, ["useful.html", "noise.html"]);
+    assert_eq!(reranked[1].similarity, 0.8, "shown similarity is unchanged");
+
+    // Disabled, the search order stands
+    let order: Vec<String> = search::apply_feedback(results, &priors, 0.0)
+        .into_iter()
+        .map(|d| d.doc_path)
+        .collect();
+    assert_eq!(order, ["noise.html", "useful.html"]);
+}
+
+#[test]
+fn feedback_moves_results_past_close_neighbours_only() {
+    // Chunks share their page's prior
+    let priors = HashMap::from([
+        ("loved.html".to_string(), search::feedback_prior(1000, 0)),
+        ("hated.html".to_string(), search::feedback_prior(0, 1000)),
+    ]);
+    let results = vec![
+        hit("hated.html [chunk 1/2]", 0.10),
+        hit("plain.html", 0.15),
+        hit("loved.html", 0.40),
+    ];
+
+    // Even an absurd weight is capped, so a 0.3 gap in similarity is never crossed
+    let order: Vec<String> = search::apply_feedback(results, &priors, 10.0)
+        .into_iter()
+        .map(|d| d.doc_path)
+        .collect();
+    assert_eq!(
+        order,
+        ["plain.html", "hated.html [chunk 1/2]", "loved.html"]
+    );
+}
+
+#[test]
+fn feedback_priors_grow_with_one_sided_votes() {
+    assert_eq!(search::feedback_prior(0, 0), 0.0);
+    assert_eq!(search::feedback_prior(3, 3), 0.0);
+    let one = search::feedback_prior(1, 0);
+    let many = search::feedback_prior(50, 0);
+    assert!(0.0 < one && one < many && many < 1.0);
+    assert_eq!(search::feedback_prior(0, 50), -many);
+}
+
+#[test]
+fn query_terms_drop_stop_words_short_words_and_repeats() {
+    assert_eq!(
+        search::query_terms("How do I use the Tokio mpsc channel with tokio::spawn?"),
+        ["tokio", "mpsc", "channel", "spawn"]
+    );
+}
+
+#[test]
+fn overlapping_terms_are_the_query_terms_found_in_the_content() {
+    let content = "Creates a bounded MPSC channel for communicating between tasks.";
+    assert_eq!(
+        search::overlapping_terms("How do I create an mpsc channel in tokio?", content),
+        ["mpsc", "channel"]
+    );
+}
+
+#[test]
+fn debug_diagnostics_report_scores_and_matched_terms() {
+    let mut doc = hit("tokio/sync/mpsc/fn.channel.html", 0.25);
+    doc.content = "Creates a bounded mpsc channel.".to_string();
+    assert_eq!(
+        search::format_debug_diagnostics("tokio mpsc channel", &doc),
+        "[debug] path: tokio/sync/mpsc/fn.channel.html, distance: 0.2500, similarity: 0.7500, \
+         matched terms: mpsc, channel"
+    );
+    assert!(
+        search::format_debug_diagnostics("oneshot sender", &doc).ends_with("matched terms: none")
+    );
+}
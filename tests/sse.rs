@@ -0,0 +1,232 @@
+//! The SSE transport over real connections: idle reaping, keep-alive comments, the
+//! connection limit and releasing sessions when clients go away.
+
+use futures::StreamExt;
+use rustdocs_mcp_server::sse::{CloseReason, SseServer, SseServerConfig, SseServerTransport};
+use std::time::Duration;
+use tokio::time::{timeout, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// How long a test waits for something that should happen well before it
+const PATIENCE: Duration = Duration::from_secs(5);
+
+async fn serve(
+    keep_alive: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    max_connections: Option<usize>,
+) -> SseServer {
+    SseServer::serve_with_config(SseServerConfig {
+        bind: "127.0.0.1:0".parse().unwrap(),
+        sse_path: "/sse".to_string(),
+        post_path: "/message".to_string(),
+        ct: CancellationToken::new(),
+        keep_alive,
+        idle_timeout,
+        max_connections,
+        profiles: Vec::new(),
+    })
+    .await
+    .unwrap()
+}
+
+/// An open event stream, read one event (or comment) at a time
+struct SseClient {
+    body: futures::stream::BoxStream<'static, reqwest::Result<Vec<u8>>>,
+    buffer: String,
+}
+
+impl SseClient {
+    async fn connect(server: &SseServer) -> reqwest::Response {
+        reqwest::get(format!("http://{}/sse", server.config.bind))
+            .await
+            .unwrap()
+    }
+
+    async fn open(server: &SseServer) -> Self {
+        let response = Self::connect(server).await;
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        Self {
+            body: response
+                .bytes_stream()
+                .map(|chunk| chunk.map(|bytes| bytes.to_vec()))
+                .boxed(),
+            buffer: String::new(),
+        }
+    }
+
+    async fn next_event(&mut self) -> String {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let event = self.buffer[..end].to_string();
+                self.buffer.drain(..end + 2);
+                return event;
+            }
+            let chunk = timeout(PATIENCE, self.body.next())
+                .await
+                .expect("no event in time")
+                .expect("stream ended")
+                .unwrap();
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+
+    /// The session ID from the `endpoint` event every stream starts with
+    async fn session_id(&mut self) -> String {
+        let event = self.next_event().await;
+        assert!(event.starts_with("event: endpoint"), "{event}");
+        event
+            .split_once("sessionId=")
+            .expect("endpoint event names the session")
+            .1
+            .trim()
+            .to_string()
+    }
+}
+
+async fn next_transport(server: &mut SseServer) -> SseServerTransport {
+    timeout(PATIENCE, server.next_transport())
+        .await
+        .expect("no session in time")
+        .unwrap()
+}
+
+/// Status of posting a notification to `session_id`
+async fn post(server: &SseServer, session_id: &str) -> reqwest::StatusCode {
+    reqwest::Client::new()
+        .post(format!(
+            "http://{}/message?sessionId={session_id}",
+            server.config.bind
+        ))
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/initialized"
+        }))
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn silent_clients_are_closed_after_the_idle_timeout() {
+    let idle_timeout = Duration::from_millis(300);
+    let mut server = serve(None, Some(idle_timeout), None).await;
+    let mut client = SseClient::open(&server).await;
+    let session_id = client.session_id().await;
+    let mut transport = next_transport(&mut server).await;
+    let handle = transport.handle();
+
+    // A message restarts the idle timer
+    tokio::time::sleep(idle_timeout / 2).await;
+    assert_eq!(
+        post(&server, &session_id).await,
+        reqwest::StatusCode::ACCEPTED
+    );
+    let started = Instant::now();
+    assert!(timeout(PATIENCE, transport.next()).await.unwrap().is_some());
+
+    assert!(timeout(PATIENCE, transport.next()).await.unwrap().is_none());
+    assert!(started.elapsed() >= idle_timeout);
+    assert_eq!(
+        handle.close_reason(),
+        Some(CloseReason::IdleTimeout(idle_timeout))
+    );
+    server.cancel();
+}
+
+#[tokio::test]
+async fn keep_alive_comments_arrive_at_the_configured_interval() {
+    let keep_alive = Duration::from_millis(200);
+    let mut server = serve(Some(keep_alive), None, None).await;
+    let mut client = SseClient::open(&server).await;
+    client.session_id().await;
+    let _transport = next_transport(&mut server).await;
+
+    let started = Instant::now();
+    let mut arrivals = Vec::new();
+    for _ in 0..3 {
+        assert_eq!(client.next_event().await, ": keep-alive");
+        arrivals.push(started.elapsed());
+    }
+    // Roughly one per interval: none early, none bunched up
+    let mut previous = Duration::ZERO;
+    for arrival in arrivals {
+        let gap = arrival - previous;
+        assert!(
+            gap >= keep_alive * 3 / 4 && gap < keep_alive * 3,
+            "keep-alive {gap:?} after the previous one, expected about {keep_alive:?}"
+        );
+        previous = arrival;
+    }
+    server.cancel();
+}
+
+#[tokio::test]
+async fn sessions_are_released_when_the_client_disconnects() {
+    // Keep-alives make the server write, which is how it notices the client is gone
+    let mut server = serve(Some(Duration::from_millis(50)), None, None).await;
+    let stats = server.stats();
+    let mut client = SseClient::open(&server).await;
+    let session_id = client.session_id().await;
+    let mut transport = next_transport(&mut server).await;
+    let handle = transport.handle();
+    assert_eq!(stats.active(), 1);
+
+    drop(client);
+    assert!(timeout(PATIENCE, handle.disconnected().cancelled())
+        .await
+        .is_ok());
+    assert!(timeout(PATIENCE, transport.next()).await.unwrap().is_none());
+    assert_eq!(handle.close_reason(), Some(CloseReason::ClientDisconnected));
+
+    drop(transport);
+    assert_eq!(stats.active(), 0);
+    // The session is removed from the routing table in the background
+    let deadline = Instant::now() + PATIENCE;
+    while post(&server, &session_id).await != reqwest::StatusCode::NOT_FOUND {
+        assert!(Instant::now() < deadline, "session still routable");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    server.cancel();
+}
+
+#[tokio::test]
+async fn clients_past_the_connection_limit_are_told_to_retry() {
+    let max_connections = 2;
+    let mut server = serve(Some(Duration::from_millis(50)), None, Some(max_connections)).await;
+    let stats = server.stats();
+    let mut clients = Vec::new();
+    let mut transports = Vec::new();
+    for _ in 0..max_connections {
+        let mut client = SseClient::open(&server).await;
+        client.session_id().await;
+        clients.push(client);
+        transports.push(next_transport(&mut server).await);
+    }
+
+    let rejected = SseClient::connect(&server).await;
+    assert_eq!(rejected.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+        rejected
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .unwrap(),
+        "10"
+    );
+    assert_eq!(stats.rejected(), 1);
+    assert_eq!(stats.accepted(), max_connections as u64);
+    assert_eq!(stats.active(), max_connections);
+
+    // Once a client disconnects and its session ends, its slot is free again
+    drop(clients.remove(0));
+    let mut transport = transports.remove(0);
+    assert!(timeout(PATIENCE, transport.next()).await.unwrap().is_none());
+    drop(transport);
+    assert_eq!(stats.active(), max_connections - 1);
+
+    let mut client = SseClient::open(&server).await;
+    client.session_id().await;
+    assert_eq!(stats.accepted(), max_connections as u64 + 1);
+    assert_eq!(stats.rejected(), 1);
+    server.cancel();
+}
@@ -0,0 +1,141 @@
+use async_openai::{config::OpenAIConfig, Client as OpenAIClient};
+use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+use rustdocs_mcp_server::{
+    database::ScoredDoc,
+    summarize::{Answer, Summarizer},
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+async fn mock_llm(router: Router, timeout: Duration) -> Summarizer {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let api_base = format!("http://{}/v1", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    let config = OpenAIConfig::new()
+        .with_api_base(api_base)
+        .with_api_key("test-key");
+    Summarizer::new(OpenAIClient::with_config(config), "mock-model").with_timeout(timeout)
+}
+
+fn results() -> Vec<ScoredDoc> {
+    vec![
+        ScoredDoc {
+            doc_path: "tokio/latest/tokio/time/fn.timeout.html".to_string(),
+            content: "Requires a Future to complete before the specified duration has elapsed."
+                .to_string(),
+            distance: 0.2,
+            similarity: 0.8,
+            last_updated_at: None,
+            crate_version: None,
+            token_count: 0,
+        },
+        ScoredDoc {
+            doc_path: "tokio/latest/tokio/time/fn.sleep.html".to_string(),
+            content: "Waits until duration has elapsed.".to_string(),
+            distance: 0.3,
+            similarity: 0.7,
+            last_updated_at: None,
+            crate_version: None,
+            token_count: 0,
+        },
+    ]
+}
+
+#[tokio::test]
+async fn a_successful_call_returns_the_llm_answer() {
+    let summarizer = mock_llm(
+        Router::new().route(
+            "/v1/chat/completions",
+            post(|Json(request): Json<Value>| async move {
+                let context = request["messages"][1]["content"].as_str().unwrap();
+                assert!(context.contains("Path: tokio/latest/tokio/time/fn.timeout.html"));
+                Json(json!({
+                    "id": "chatcmpl-1",
+                    "object": "chat.completion",
+                    "created": 0,
+                    "model": "mock-model",
+                    "choices": [{
+                        "index": 0,
+                        "message": {"role": "assistant", "content": "Wrap it in `timeout`."},
+                        "finish_reason": "stop"
+                    }]
+                }))
+            }),
+        ),
+        Duration::from_secs(5),
+    )
+    .await;
+
+    let answer = summarizer
+        .answer("tokio", "how do I give up on slow futures?", &results())
+        .await
+        .unwrap();
+    assert_eq!(answer, Answer::Summary("Wrap it in `timeout`.".to_string()));
+    assert_eq!(summarizer.fallback_count(), 0);
+}
+
+#[tokio::test]
+async fn a_failing_llm_returns_the_snippets_with_a_warning() {
+    let summarizer = mock_llm(
+        Router::new().route(
+            "/v1/chat/completions",
+            post(|| async {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error": {
+                            "message": "model not found",
+                            "type": "invalid_request_error",
+                            "param": null,
+                            "code": null
+                        }
+                    })),
+                )
+                    .into_response()
+            }),
+        ),
+        Duration::from_secs(5),
+    )
+    .await;
+
+    let answer = summarizer
+        .answer("tokio", "how do I give up on slow futures?", &results())
+        .await
+        .unwrap();
+    let Answer::Snippets { text, warning } = answer else {
+        panic!("expected raw snippets, got {answer:?}");
+    };
+    assert!(warning.contains("model not found"), "{warning}");
+    assert!(text.contains("--- Document 1 (similarity: 0.800) ---"));
+    assert!(text.contains("URL: https://docs.rs/tokio/latest/tokio/time/fn.sleep.html"));
+    assert!(text.contains("Waits until duration has elapsed."));
+    assert_eq!(summarizer.fallback_count(), 1);
+}
+
+#[tokio::test]
+async fn a_slow_llm_times_out_into_snippets() {
+    let summarizer = mock_llm(
+        Router::new().route(
+            "/v1/chat/completions",
+            post(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                StatusCode::OK
+            }),
+        ),
+        Duration::from_millis(100),
+    )
+    .await;
+
+    for attempt in 1..=2 {
+        let answer = summarizer
+            .answer("tokio", "how do I give up on slow futures?", &results())
+            .await
+            .unwrap();
+        assert!(
+            matches!(&answer, Answer::Snippets { warning, .. } if warning.contains("timed out")),
+            "{answer:?}"
+        );
+        assert_eq!(summarizer.fallback_count(), attempt);
+    }
+}
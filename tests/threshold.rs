@@ -0,0 +1,42 @@
+use rustdocs_mcp_server::threshold::{self, Method};
+
+#[test]
+fn percentiles_interpolate_between_neighbours() {
+    let sorted = [0.1, 0.2, 0.3, 0.4, 0.5];
+
+    assert_eq!(threshold::percentile(&sorted, 0.0), Some(0.1));
+    assert_eq!(threshold::percentile(&sorted, 50.0), Some(0.3));
+    assert_eq!(threshold::percentile(&sorted, 100.0), Some(0.5));
+    assert!((threshold::percentile(&sorted, 10.0).unwrap() - 0.14).abs() < 1e-6);
+    assert_eq!(threshold::percentile(&[], 50.0), None);
+}
+
+#[test]
+fn a_gap_below_the_sample_questions_puts_the_threshold_in_the_middle() {
+    let questions = [0.62, 0.71, 0.74, 0.78, 0.80, 0.83, 0.85, 0.86, 0.88, 0.90];
+    let off_topic = [0.21, 0.25, 0.30, 0.34, 0.40];
+
+    let suggestion = threshold::suggest(&questions, &off_topic, 10.0).unwrap();
+
+    assert_eq!(suggestion.method, Method::Gap);
+    // Midway between the best probe (0.40) and the questions' 10th percentile (0.701)
+    assert_eq!(suggestion.threshold, 0.551);
+    assert_eq!(suggestion.questions_kept, 1.0);
+    assert_eq!(suggestion.off_topic_kept, Some(0.0));
+    assert_eq!(suggestion.questions.count, 10);
+    assert_eq!(suggestion.off_topic.unwrap().max, 0.40);
+}
+
+#[test]
+fn overlapping_probes_fall_back_to_the_questions_percentile() {
+    let questions = [0.30, 0.52, 0.55, 0.58, 0.60];
+    let off_topic = [0.35, 0.45, 0.56];
+
+    let suggestion = threshold::suggest(&questions, &off_topic, 25.0).unwrap();
+
+    assert_eq!(suggestion.method, Method::Percentile);
+    assert_eq!(suggestion.threshold, 0.52);
+    assert_eq!(suggestion.questions_kept, 0.8);
+    assert_eq!(suggestion.off_topic_kept, Some(1.0 / 3.0));
+    assert_eq!(threshold::suggest(&[], &off_topic, 25.0), None);
+}
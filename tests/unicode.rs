@@ -0,0 +1,197 @@
+//! Emoji, CJK and combining-character documents through cleaning, tokenizing, chunking,
+//! embedding and response formatting: every stage must cut on character boundaries and
+//! never produce U+FFFD.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rustdocs_mcp_server::{
+    doc_loader::{self, Document},
+    embeddings::{
+        chunk_document, embed_chunk, truncate_for_embedding, EmbeddingProvider, TruncationStrategy,
+        MODEL_TOKEN_LIMIT, TOKEN_LIMIT,
+    },
+    error::ServerError,
+    response, search,
+};
+use std::sync::Mutex;
+use unicode_normalization::is_nfc;
+
+/// Pieces random documents are built from, each stressing the tokenizer differently
+const PIECES: &[&str] = &[
+    "🦀",
+    "👩‍👩‍👧‍👦",
+    "🇯🇵",
+    "✔️",
+    "非同期ランタイム",
+    "异步运行时",
+    "비동기 런타임",
+    "e\u{301}",
+    "a\u{308}\u{323}",
+    "ñ",
+    "Ελληνικά",
+    "العربية",
+    " ",
+    "\n",
+    "fn spawn<F>(future: F)",
+    "`Result<T, E>`",
+];
+
+fn random_text(rng: &mut StdRng, pieces: usize) -> String {
+    (0..pieces)
+        .map(|_| PIECES[rng.random_range(0..PIECES.len())])
+        .collect()
+}
+
+fn assert_clean(text: &str) {
+    assert!(
+        !text.contains('\u{fffd}'),
+        "replacement character in {text:?}"
+    );
+}
+
+#[test]
+fn cleaned_text_is_nfc_and_cleaning_is_idempotent() {
+    assert_eq!(doc_loader::clean_content("Cafe\u{301} 🦀"), "Café 🦀");
+
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..200 {
+        let text = random_text(&mut rng, 40);
+        let cleaned = doc_loader::clean_content(&text);
+        assert!(is_nfc(&cleaned), "{cleaned:?}");
+        assert_clean(&cleaned);
+        assert_eq!(doc_loader::clean_content(&cleaned), cleaned);
+    }
+}
+
+#[test]
+fn token_counts_and_truncation_keep_characters_whole() {
+    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let mut rng = StdRng::seed_from_u64(11);
+    for _ in 0..100 {
+        let text = random_text(&mut rng, 120);
+        let tokens = bpe.encode_with_special_tokens(&text);
+        assert_eq!(bpe.decode(tokens.clone()).unwrap(), text);
+
+        let limit = rng.random_range(5..tokens.len().max(6));
+        for strategy in [
+            TruncationStrategy::Tail,
+            TruncationStrategy::Head,
+            TruncationStrategy::Middle,
+        ] {
+            let Some(truncated) = truncate_for_embedding(&text, &bpe, limit, strategy) else {
+                continue;
+            };
+            assert_clean(&truncated);
+            assert!(bpe.encode_with_special_tokens(&truncated).len() <= limit);
+            match strategy {
+                TruncationStrategy::Tail => assert!(text.starts_with(&truncated)),
+                TruncationStrategy::Head => assert!(text.ends_with(&truncated)),
+                TruncationStrategy::Middle => {}
+            }
+        }
+    }
+}
+
+#[test]
+fn oversized_multibyte_documents_chunk_without_losing_text() {
+    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let mut rng = StdRng::seed_from_u64(13);
+    // No ". " to split sentences at, so the chunker has to cut between tokens
+    let content = random_text(&mut rng, 12_000);
+    let doc = Document {
+        path: "index.html".to_string(),
+        content: content.clone(),
+        section: None,
+    };
+
+    let chunks = chunk_document(&doc, &bpe);
+    assert!(chunks.len() > 1);
+    for (path, chunk) in &chunks {
+        assert!(path.starts_with("index.html [chunk "));
+        assert_clean(chunk);
+        assert!(bpe.encode_with_special_tokens(chunk).len() <= TOKEN_LIMIT);
+    }
+    let rejoined: String = chunks.iter().map(|(_, chunk)| chunk.as_str()).collect();
+    assert_eq!(
+        rejoined.trim_end_matches('.'),
+        content.trim_end_matches('.')
+    );
+}
+
+/// Records what it was asked to embed
+#[derive(Default)]
+struct RecordingProvider {
+    texts: Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for RecordingProvider {
+    async fn generate_embeddings(
+        &self,
+        texts: &[String],
+    ) -> Result<(Vec<Vec<f32>>, usize), ServerError> {
+        self.texts.lock().unwrap().extend_from_slice(texts);
+        Ok((vec![vec![0.5; 4]; texts.len()], texts.len()))
+    }
+
+    fn get_model_name(&self) -> &str {
+        "recording"
+    }
+}
+
+#[tokio::test]
+async fn embedded_text_is_valid_and_within_the_model_limit() {
+    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let provider = RecordingProvider::default();
+    let mut rng = StdRng::seed_from_u64(17);
+    for strategy in [
+        TruncationStrategy::Tail,
+        TruncationStrategy::Head,
+        TruncationStrategy::Middle,
+    ] {
+        let content = random_text(&mut rng, 9_000);
+        embed_chunk(&provider, &bpe, "index.html", content, strategy)
+            .await
+            .unwrap();
+    }
+
+    let texts = provider.texts.lock().unwrap();
+    assert_eq!(texts.len(), 3);
+    for text in texts.iter() {
+        assert_clean(text);
+        assert!(bpe.encode_with_special_tokens(text).len() <= MODEL_TOKEN_LIMIT);
+    }
+}
+
+#[test]
+fn responses_split_and_reassemble_on_character_boundaries() {
+    let mut rng = StdRng::seed_from_u64(19);
+    for _ in 0..100 {
+        let text = random_text(&mut rng, 300);
+        let max_bytes = rng.random_range(8..64);
+        let parts = response::split_response(&text, max_bytes);
+        let rejoined: String = parts
+            .iter()
+            .map(|part| match part.split_once("]\n") {
+                Some((marker, rest)) if marker.starts_with("[part ") => rest,
+                _ => part.as_str(),
+            })
+            .collect();
+        assert_eq!(rejoined, text);
+    }
+
+    for _ in 0..100 {
+        let text = random_text(&mut rng, 200);
+        let chars: Vec<char> = text.chars().collect();
+        // Two chunks repeating at least 20 characters, as the chunker's overlap does
+        let split = rng.random_range(chars.len() / 3..chars.len() / 2);
+        let overlap = rng.random_range(split / 2..split);
+        let first: String = chars[..split].iter().collect();
+        let second: String = chars[split - overlap.max(20).min(split)..].iter().collect();
+        let page = search::reassemble_chunks(vec![
+            ("a.html [chunk 2/2]".to_string(), second),
+            ("a.html [chunk 1/2]".to_string(), first),
+        ]);
+        assert_clean(&page);
+        assert!(page.starts_with(&chars[..split].iter().collect::<String>()));
+    }
+}
@@ -0,0 +1,484 @@
+use rmcp::Error as McpError;
+use rustdocs_mcp_server::{
+    server,
+    tool_args::{
+        parse_default_features, AddCrateArgs, AddCratesArgs, CheckCrateStatusArgs,
+        CompareCratesArgs, CrateInfoArgs, CrateSpec, CrawlTraceArgs, FindSymbolArgs, GetDocArgs,
+        IndexHealthArgs, JobStatusArgs, QueryRustDocsArgs, RawQuery, RawSearchArgs,
+        RemoveCrateArgs, RenameCrateArgs, SetCrateEnabledArgs, SmartQueryArgs, StorageReportArgs,
+        SubmitFeedbackArgs, SuggestThresholdArgs,
+    },
+    validation::{self, Validate},
+};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+fn failing_fields_of<T: DeserializeOwned + Validate>(args: Value) -> Vec<String> {
+    let args: T = serde_json::from_value(args).expect("arguments deserialize");
+    match args.validate() {
+        Ok(()) => vec![],
+        Err(e) => e.fields().into_iter().map(String::from).collect(),
+    }
+}
+
+/// Fields of `tool`'s arguments that fail validation
+fn failing_fields(tool: &str, args: Value) -> Vec<String> {
+    match tool {
+        "query_rust_docs" => failing_fields_of::<QueryRustDocsArgs>(args),
+        "stdio query_rust_docs" => failing_fields_of::<server::QueryRustDocsArgs>(args),
+        "get_doc" => failing_fields_of::<GetDocArgs>(args),
+        "find_symbol" => failing_fields_of::<FindSymbolArgs>(args),
+        "crate_info" => failing_fields_of::<CrateInfoArgs>(args),
+        "add_crate" => failing_fields_of::<AddCrateArgs>(args),
+        "add_crates" => failing_fields_of::<AddCratesArgs>(args),
+        "add_crates entry" => failing_fields_of::<CrateSpec>(args),
+        "rename_crate" => failing_fields_of::<RenameCrateArgs>(args),
+        "set_crate_enabled" => failing_fields_of::<SetCrateEnabledArgs>(args),
+        "check_crate_status" => failing_fields_of::<CheckCrateStatusArgs>(args),
+        "remove_crate" => failing_fields_of::<RemoveCrateArgs>(args),
+        "index_health" => failing_fields_of::<IndexHealthArgs>(args),
+        "job_status" => failing_fields_of::<JobStatusArgs>(args),
+        "crawl_trace" => failing_fields_of::<CrawlTraceArgs>(args),
+        "storage_report" => failing_fields_of::<StorageReportArgs>(args),
+        "suggest_threshold" => failing_fields_of::<SuggestThresholdArgs>(args),
+        "raw_search" => failing_fields_of::<RawSearchArgs>(args),
+        "smart_query" => failing_fields_of::<SmartQueryArgs>(args),
+        "compare_crates" => failing_fields_of::<CompareCratesArgs>(args),
+        "submit_feedback" => failing_fields_of::<SubmitFeedbackArgs>(args),
+        _ => panic!("no test dispatch for {tool}"),
+    }
+}
+
+#[test]
+fn every_tool_reports_each_offending_field() {
+    let long = "x".repeat(validation::MAX_TEXT_LEN + 1);
+    let cases: Vec<(&str, Value, &[&str])> = vec![
+        (
+            "query_rust_docs",
+            json!({"crate_name": "tokio", "question": "how do I spawn?", "limit": 5, "min_similarity": 0.3}),
+            &[],
+        ),
+        (
+            "query_rust_docs",
+            json!({"crate_name": "../etc", "question": "  ", "limit": 50, "min_similarity": 1.5, "path_prefix": long, "min_tokens": 100_000}),
+            &[
+                "crate_name",
+                "question",
+                "limit",
+                "min_similarity",
+                "path_prefix",
+                "min_tokens",
+            ],
+        ),
+        (
+            "query_rust_docs",
+            json!({"crate_name": "tokio", "question": "q".repeat(validation::MAX_QUESTION_LEN + 1), "limit": 0}),
+            &["question", "limit"],
+        ),
+        (
+            "stdio query_rust_docs",
+            json!({"crate_name": "tokio", "question": "how do I spawn?"}),
+            &[],
+        ),
+        (
+            "stdio query_rust_docs",
+            json!({"crate_name": "", "question": ""}),
+            &["crate_name", "question"],
+        ),
+        (
+            "get_doc",
+            json!({"crate_name": "tokio", "doc_path": "tokio/latest/tokio/fn.spawn.html"}),
+            &[],
+        ),
+        (
+            "get_doc",
+            json!({"crate_name": "tokio util", "doc_path": ""}),
+            &["crate_name", "doc_path"],
+        ),
+        (
+            "find_symbol",
+            json!({"identifier": "poll_ready", "crate_name": ""}),
+            &[],
+        ),
+        (
+            "find_symbol",
+            json!({"identifier": " x ", "crate_name": "9lives", "limit": 21}),
+            &["identifier", "crate_name", "limit"],
+        ),
+        ("crate_info", json!({"crate_name": "serde_json"}), &[]),
+        (
+            "crate_info",
+            json!({"crate_name": "a".repeat(65)}),
+            &["crate_name"],
+        ),
+        (
+            "add_crate",
+            json!({
+                "crate_name": "tokio",
+                "version_spec": "1.35.0",
+                "features": ["full", "dep:tracing"],
+                "include_url_patterns": ["/sync/"],
+                "target": "x86_64-pc-windows-msvc",
+                "default_limit": 10,
+                "default_min_similarity": 0.2,
+                "languages": ["eng"],
+                "language_action": "tag",
+                "max_embeddings": 5000,
+                "max_duration_secs": 7200
+            }),
+            &[],
+        ),
+        (
+            "add_crate",
+            json!({
+                "crate_name": "",
+                "version_spec": "newest",
+                "features": ["", "two words"],
+                "expected_docs": -1,
+                "content_selector": "div[",
+                "include_url_patterns": ["/sync/", "("],
+                "exclude_url_patterns": ["["],
+                "reexport_crates": ["bevy_ecs", "bad name"],
+                "reexport_page_budget": -5,
+                "reexport_pages_per_crate": -1,
+                "query_hint": long,
+                "default_limit": 0,
+                "default_min_similarity": -0.1,
+                "default_path_prefix": long,
+                "languages": ["eng", "english"],
+                "language_action": "translate"
+            }),
+            &[
+                "crate_name",
+                "version_spec",
+                "features[0]",
+                "features[1]",
+                "expected_docs",
+                "content_selector",
+                "include_url_patterns[1]",
+                "exclude_url_patterns[0]",
+                "reexport_crates[1]",
+                "reexport_page_budget",
+                "reexport_pages_per_crate",
+                "query_hint",
+                "default_limit",
+                "default_min_similarity",
+                "default_path_prefix",
+                "languages[1]",
+                "language_action",
+            ],
+        ),
+        (
+            "add_crate",
+            json!({
+                "crate_name": "private-api",
+                "version_spec": "latest",
+                "rustdoc_json_url": "file:///etc/passwd",
+                "target": "windows"
+            }),
+            &["rustdoc_json_url", "target", "target"],
+        ),
+        (
+            "add_crate",
+            json!({
+                "crate_name": "private-api",
+                "version_spec": "latest",
+                "rustdoc_json_url": "https://ci.example.com/private_api.json",
+                "target": "x86_64-pc-windows-msvc"
+            }),
+            &["target"],
+        ),
+        (
+            "add_crates",
+            json!({"crates": [{"crate_name": "serde"}]}),
+            &[],
+        ),
+        ("add_crates", json!({"crates": []}), &["crates"]),
+        ("add_crates entry", json!({"crate_name": "serde"}), &[]),
+        (
+            "add_crates entry",
+            json!({
+                "crate_name": "serde",
+                "version_spec": "1.x.y",
+                "default_limit": 21,
+                "max_embeddings": 0,
+                "max_duration_secs": -60
+            }),
+            &[
+                "version_spec",
+                "default_limit",
+                "max_embeddings",
+                "max_duration_secs",
+            ],
+        ),
+        (
+            "rename_crate",
+            json!({"old_name": "Legacy Name", "new_name": "legacy-name"}),
+            &[],
+        ),
+        (
+            "rename_crate",
+            json!({"old_name": "", "new_name": "has space"}),
+            &["old_name", "new_name"],
+        ),
+        (
+            "rename_crate",
+            json!({"old_name": "tokio", "new_name": " tokio "}),
+            &["new_name"],
+        ),
+        (
+            "set_crate_enabled",
+            json!({"crate_name": "tokio", "enabled": false}),
+            &[],
+        ),
+        (
+            "set_crate_enabled",
+            json!({"crate_name": " ", "enabled": true}),
+            &["crate_name"],
+        ),
+        (
+            "check_crate_status",
+            json!({"crate_name": "tokio", "target": ""}),
+            &[],
+        ),
+        (
+            "check_crate_status",
+            json!({"crate_name": "", "target": "../other"}),
+            &["crate_name", "target"],
+        ),
+        ("remove_crate", json!({"crate_name": "Legacy Name"}), &[]),
+        (
+            "remove_crate",
+            json!({"crate_name": "tokio", "version_spec": "", "target": "windows"}),
+            &["version_spec", "target"],
+        ),
+        ("job_status", json!({"job_id": 42}), &[]),
+        ("job_status", json!({"job_id": 0}), &["job_id"]),
+        (
+            "crawl_trace",
+            json!({"job_id": 42, "outcome": "not_found", "limit": 50}),
+            &[],
+        ),
+        (
+            "crawl_trace",
+            json!({"job_id": 0, "outcome": "404", "limit": 0}),
+            &["job_id", "outcome", "limit"],
+        ),
+        (
+            "suggest_threshold",
+            json!({"crate_name": "tokio", "questions": ["How do I spawn a task?"], "percentile": 5}),
+            &[],
+        ),
+        (
+            "suggest_threshold",
+            json!({"crate_name": "tokio", "questions": [], "percentile": 75}),
+            &["questions", "percentile"],
+        ),
+        (
+            "suggest_threshold",
+            json!({"crate_name": "", "questions": ["spawn", " "]}),
+            &["crate_name", "questions[1]"],
+        ),
+        (
+            "smart_query",
+            json!({"question": "how do I parse JSON?", "crates": 3, "limit": 5, "max_per_crate": 2}),
+            &[],
+        ),
+        (
+            "smart_query",
+            json!({"question": "", "crates": 11, "limit": 0, "min_similarity": -0.1, "max_per_crate": 0}),
+            &[
+                "question",
+                "crates",
+                "limit",
+                "min_similarity",
+                "max_per_crate",
+            ],
+        ),
+        (
+            "compare_crates",
+            json!({"crate_names": ["reqwest", "ureq"], "question": "async requests", "limit": 5}),
+            &[],
+        ),
+        (
+            "compare_crates",
+            json!({"crate_names": ["reqwest", "reqwest"], "question": "", "limit": 6, "min_similarity": 2.0}),
+            &["crate_names", "question", "limit", "min_similarity"],
+        ),
+        (
+            "compare_crates",
+            json!({"crate_names": ["reqwest"], "question": "async requests"}),
+            &["crate_names"],
+        ),
+        (
+            "compare_crates",
+            json!({"crate_names": ["reqwest", "../ureq"], "question": "async requests"}),
+            &["crate_names[1]"],
+        ),
+        (
+            "submit_feedback",
+            json!({"query_id": "q-1a2b3c4d", "crate_name": "tokio", "doc_path": "https://docs.rs/tokio/latest/tokio/fn.spawn.html", "useful": true}),
+            &[],
+        ),
+        (
+            "submit_feedback",
+            json!({"query_id": "", "crate_name": "../tokio", "doc_path": " ", "useful": false}),
+            &["query_id", "crate_name", "doc_path"],
+        ),
+        ("index_health", json!({}), &[]),
+        ("index_health", json!({"crate_name": long}), &["crate_name"]),
+        ("storage_report", json!({"crate_name": "tokio"}), &[]),
+        (
+            "storage_report",
+            json!({"crate_name": long}),
+            &["crate_name"],
+        ),
+        (
+            "raw_search",
+            json!({"crate_names": ["tokio", "axum"], "question": "spawn a task", "limit": 300}),
+            &[],
+        ),
+        (
+            "raw_search",
+            json!({"crate_names": ["tokio"], "embedding": [0.1, 0.2, 0.3]}),
+            &[],
+        ),
+        (
+            "raw_search",
+            json!({"crate_names": [], "limit": 301}),
+            &["crate_names", "question", "limit"],
+        ),
+        (
+            "raw_search",
+            json!({"crate_names": ["tokio", ""], "question": "spawn", "embedding": [0.1]}),
+            &["crate_names[1]", "embedding"],
+        ),
+        (
+            "raw_search",
+            json!({"crate_names": ["tokio"], "embedding": []}),
+            &["embedding"],
+        ),
+    ];
+
+    for (tool, args, expected) in cases {
+        assert_eq!(
+            failing_fields(tool, args.clone()),
+            expected,
+            "{tool} with {args}"
+        );
+    }
+}
+
+#[test]
+fn raw_search_takes_a_question_or_a_vector_of_the_stored_dimension() {
+    let by_question: RawSearchArgs =
+        serde_json::from_value(json!({"crate_names": ["tokio"], "question": "spawn a task"}))
+            .unwrap();
+    let by_vector: RawSearchArgs =
+        serde_json::from_value(json!({"crate_names": ["tokio"], "embedding": [0.5, 0.5, 0.0]}))
+            .unwrap();
+
+    assert_eq!(
+        by_question.query(),
+        Some(RawQuery::Question("spawn a task"))
+    );
+    assert_eq!(
+        by_vector.query(),
+        Some(RawQuery::Embedding(&[0.5, 0.5, 0.0][..]))
+    );
+    assert!(by_vector.check_dimension("tokio", 3, 3).is_ok());
+
+    let error = by_vector.check_dimension("tokio", 1536, 3).unwrap_err();
+    assert_eq!(error.fields(), ["embedding"]);
+    assert_eq!(
+        error.errors[0].constraint,
+        "must have 1536 dimensions like the vectors stored for tokio, not 3"
+    );
+    let error = by_question
+        .check_dimension("tokio", 1024, 1536)
+        .unwrap_err();
+    assert_eq!(error.fields(), ["question"]);
+}
+
+#[test]
+fn crate_names_follow_crates_io_rules() {
+    let cases = [
+        ("serde_json", None),
+        ("tokio-util", None),
+        ("", Some("must not be empty")),
+        ("../admin", Some("must start with an ASCII letter")),
+        ("1password", Some("must start with an ASCII letter")),
+        (
+            "serde json",
+            Some("may only contain ASCII letters, digits, '-' and '_'"),
+        ),
+    ];
+    for (name, problem) in cases {
+        assert_eq!(validation::crate_name_problem(name), problem, "{name:?}");
+    }
+    assert_eq!(
+        validation::crate_name_problem(&"a".repeat(65)),
+        Some("must be at most 64 characters")
+    );
+}
+
+#[test]
+fn version_specs_are_latest_or_semver() {
+    let cases = [
+        ("latest", true),
+        ("1.35.0", true),
+        ("0.12", true),
+        ("^1.2", true),
+        (">=1.0, <2.0", true),
+        ("1.0.0-beta.1", true),
+        ("", false),
+        ("newest", false),
+        ("1.x.y", false),
+        ("v1.0.0", false),
+    ];
+    for (spec, valid) in cases {
+        assert_eq!(validation::is_valid_version_spec(spec), valid, "{spec:?}");
+    }
+
+    let error = AddCrateArgs::validate(
+        &serde_json::from_value(json!({"crate_name": "tokio", "version_spec": "newest"})).unwrap(),
+    )
+    .unwrap_err();
+    let message = error.to_string();
+    assert!(message.contains("'latest', an exact version"), "{message}");
+    assert!(message.contains("'~1.2'"), "{message}");
+}
+
+#[test]
+fn errors_carry_field_value_and_constraint() {
+    let args: QueryRustDocsArgs = serde_json::from_value(json!({
+        "crate_name": "tokio",
+        "question": "how do I spawn?",
+        "limit": 50
+    }))
+    .unwrap();
+    let error: McpError = args.validate().unwrap_err().into();
+
+    assert_eq!(
+        error.message,
+        "Invalid arguments: limit: must be between 1 and 20"
+    );
+    assert_eq!(
+        error.data,
+        Some(json!({
+            "errors": [{"field": "limit", "value": 50, "constraint": "must be between 1 and 20"}]
+        }))
+    );
+}
+
+#[test]
+fn default_crate_features_are_a_comma_separated_list() {
+    assert_eq!(
+        parse_default_features(" full, serde ,,macros").unwrap(),
+        ["full", "serde", "macros"]
+    );
+    assert!(parse_default_features("").unwrap().is_empty());
+
+    let error = parse_default_features("full,rt multi-thread").unwrap_err();
+    assert_eq!(error.fields(), ["DEFAULT_CRATE_FEATURES[1]"]);
+}
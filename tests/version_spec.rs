@@ -0,0 +1,89 @@
+use rustdocs_mcp_server::version_spec::{PublishedVersion, VersionSpec};
+use semver::Version;
+
+fn published(versions: &[(&str, bool)]) -> Vec<PublishedVersion> {
+    versions
+        .iter()
+        .map(|&(version, yanked)| PublishedVersion {
+            version: Version::parse(version).unwrap(),
+            yanked,
+        })
+        .collect()
+}
+
+fn resolve(spec: &str, published: &[PublishedVersion], allow_prerelease: bool) -> Option<String> {
+    VersionSpec::parse(spec)
+        .unwrap()
+        .resolve(published, allow_prerelease)
+        .map(|version| version.to_string())
+}
+
+#[test]
+fn bare_versions_are_exact_pins_and_requirements_keep_their_operators() {
+    assert_eq!(VersionSpec::parse("latest"), Ok(VersionSpec::Latest));
+    assert_eq!(
+        VersionSpec::parse("1.2.3"),
+        Ok(VersionSpec::Exact(Version::parse("1.2.3").unwrap()))
+    );
+    assert!(matches!(
+        VersionSpec::parse("~1.2"),
+        Ok(VersionSpec::Requirement(_))
+    ));
+
+    let error = VersionSpec::parse("newest").unwrap_err();
+    assert_eq!(error.spec, "newest");
+    let message = error.to_string();
+    assert!(message.contains("'latest'"), "{message}");
+    assert!(message.contains("'^0.12'"), "{message}");
+}
+
+#[test]
+fn yanked_releases_are_skipped_unless_pinned() {
+    let versions = published(&[
+        ("1.2.0", false),
+        ("1.3.0", false),
+        ("1.4.0", true),
+        ("2.0.0", true),
+    ]);
+    assert_eq!(
+        resolve("latest", &versions, false).as_deref(),
+        Some("1.3.0")
+    );
+    assert_eq!(resolve("^1.2", &versions, false).as_deref(), Some("1.3.0"));
+    assert_eq!(resolve("~1.2", &versions, false).as_deref(), Some("1.2.0"));
+    assert_eq!(resolve("^2", &versions, false), None);
+    // Someone pinning a yanked release means it
+    assert_eq!(resolve("1.4.0", &versions, false).as_deref(), Some("1.4.0"));
+    assert_eq!(resolve("1.5.0", &versions, false), None);
+}
+
+#[test]
+fn pre_releases_need_allow_prerelease_or_an_exact_pin() {
+    let versions = published(&[
+        ("0.11.4", false),
+        ("0.12.0-rc.1", false),
+        ("0.12.0-rc.2", false),
+    ]);
+    assert_eq!(
+        resolve("latest", &versions, false).as_deref(),
+        Some("0.11.4")
+    );
+    assert_eq!(
+        resolve("latest", &versions, true).as_deref(),
+        Some("0.12.0-rc.2")
+    );
+    assert_eq!(resolve("^0.12", &versions, false), None);
+    assert_eq!(
+        resolve("^0.12", &versions, true).as_deref(),
+        Some("0.12.0-rc.2")
+    );
+    assert_eq!(
+        resolve("0.12.0-rc.1", &versions, false).as_deref(),
+        Some("0.12.0-rc.1")
+    );
+    // semver's own rule: a requirement naming a pre-release matches that version's others
+    assert_eq!(
+        resolve(">=0.12.0-rc.1", &versions, false).as_deref(),
+        Some("0.12.0-rc.2")
+    );
+}